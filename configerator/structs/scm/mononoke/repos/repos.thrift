@@ -277,6 +277,14 @@ struct RawBlobstoreDisabled {} (rust.exhaustive)
 struct RawBlobstoreFilePath {
   1: string path;
 } (rust.exhaustive)
+struct RawBlobstoreSegments {
+  1: string path;
+  // Size in bytes at which a segment file is sealed and a new one started
+  2: optional i64 max_segment_size;
+  // How many writes to batch between fsyncs. Defaults to 1 (fsync every
+  // write); 0 disables explicit fsyncs entirely.
+  3: optional i64 sync_every_n_writes;
+} (rust.exhaustive)
 struct RawBlobstoreManifold {
   1: string manifold_bucket;
   2: string manifold_prefix;
@@ -318,6 +326,7 @@ struct RawBlobstorePackZstdFormat {
 union RawBlobstorePackFormat {
   1: RawBlobstorePackRawFormat Raw;
   2: RawBlobstorePackZstdFormat ZstdIndividual;
+  3: RawBlobstorePackZstdFormat ZstdDictionary;
 }
 struct RawBlobstorePackConfig {
   1: RawBlobstorePackFormat put_format;
@@ -326,6 +335,17 @@ struct RawBlobstorePack {
   1: RawBlobstoreConfig blobstore (rust.box);
   2: optional RawBlobstorePackConfig pack_config;
 } (rust.exhaustive)
+struct RawBlobstoreTiered {
+  // Fast blobstore that receives writes; must support enumeration and unlink
+  1: RawBlobstoreConfig hot (rust.box);
+  // Cheap blobstore that idle blobs are moved to
+  2: RawBlobstoreConfig cold (rust.box);
+  // Copy blobs read from the cold tier into the hot tier. Defaults to true.
+  3: optional bool promote_on_read;
+  // Move blobs that have not been written or read for this long to the cold
+  // tier. Blobs are never demoted if unset.
+  4: optional i64 demote_after_secs;
+} (rust.exhaustive)
 struct RawBlobstoreS3 {
   1: string bucket;
   2: string keychain_group;
@@ -356,6 +376,8 @@ union RawBlobstoreConfig {
   9: RawBlobstoreLogging logging;
   10: RawBlobstorePack pack;
   11: RawBlobstoreS3 s3;
+  12: RawBlobstoreSegments blob_segments;
+  13: RawBlobstoreTiered tiered;
 }
 
 // A write-mostly blobstore is one that is not read from in normal operation.
@@ -481,6 +503,11 @@ struct RawPushrebaseParams {
   // Pushes to which bookmark should be logged to ODS for monitoring
   // This will usually be the "main bookmark" of the repo
   13: optional string monitoring_bookmark;
+  // Merge files changed both in the pushed commits and on the bookmark
+  // instead of rejecting the push, if the changes don't overlap.
+  14: optional bool merge_conflicting_files;
+  // Files larger than this (in bytes) are never merged during pushrebase.
+  15: optional i64 merge_max_file_size;
 } (rust.exhaustive)
 
 struct RawBookmarkConfig {
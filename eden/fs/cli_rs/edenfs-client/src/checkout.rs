@@ -209,6 +209,53 @@ pub struct CheckoutConfig {
 }
 
 impl CheckoutConfig {
+    /// Config for a new checkout of the repository at `backing_repo`.
+    pub fn new(
+        backing_repo: PathBuf,
+        repo_type: &str,
+        protocol: &str,
+        case_sensitive: bool,
+    ) -> Result<CheckoutConfig> {
+        if !SUPPORTED_REPOS.contains(&repo_type) {
+            return Err(EdenFsError::Other(anyhow!(
+                "Unsupported repository type: `{}`. Must be one of: {}",
+                repo_type,
+                SUPPORTED_REPOS.join(", ")
+            )));
+        }
+        if !SUPPORTED_MOUNT_PROTOCOLS.contains(&protocol) {
+            return Err(EdenFsError::Other(anyhow!(
+                "Unsupported mount protocol: `{}`. Must be one of: {}",
+                protocol,
+                SUPPORTED_MOUNT_PROTOCOLS.join(", ")
+            )));
+        }
+        Ok(CheckoutConfig {
+            repository: Repository {
+                path: backing_repo,
+                repo_type: repo_type.to_string(),
+                guid: default_guid(),
+                protocol: protocol.to_string(),
+                case_sensitive,
+                require_utf8_path: true,
+                // The tree overlay is only supported on Windows for now
+                enable_tree_overlay: cfg!(windows),
+                use_write_back_cache: false,
+            },
+            redirections: BTreeMap::new(),
+            profiles: Some(PrefetchProfiles { active: Vec::new() }),
+            predictive_prefetch: None,
+        })
+    }
+
+    pub fn backing_repo(&self) -> &Path {
+        &self.repository.path
+    }
+
+    pub fn repo_type(&self) -> &str {
+        &self.repository.repo_type
+    }
+
     /// Reads checkout config information from config.toml and
     /// returns an Err if it is not properly formatted or does not exist.
     pub fn parse_config(state_dir: PathBuf) -> Result<CheckoutConfig> {
@@ -438,6 +485,19 @@ impl SnapshotState {
     }
 }
 
+/// Records `commit_id` as the parent commit in the SNAPSHOT file of the client directory
+/// `data_dir`, which is what EdenFS checks out when it mounts the checkout.
+pub fn save_snapshot(data_dir: &Path, commit_id: &str) -> Result<()> {
+    let mut contents = SNAPSHOT_MAGIC_2.to_vec();
+    contents.extend_from_slice(&(commit_id.len() as u32).to_be_bytes());
+    contents.extend_from_slice(commit_id.as_bytes());
+    atomic_write(&data_dir.join(SNAPSHOT), 0o644, true, |f| {
+        f.write_all(&contents)
+    })
+    .from_err()?;
+    Ok(())
+}
+
 fn is_unknown_method_error(error: &PrefetchFilesError) -> bool {
     if let PrefetchFilesError::ApplicationException(ref e) = error {
         e.type_ == ApplicationExceptionErrorCode::UnknownMethod
@@ -474,6 +534,10 @@ impl EdenFsCheckout {
         self.data_dir.clone()
     }
 
+    pub fn state(&self) -> Option<MountState> {
+        self.state
+    }
+
     pub fn fsck_dir(&self) -> PathBuf {
         self.data_dir.join("fsck")
     }
@@ -581,13 +645,19 @@ impl EdenFsCheckout {
         self.configured = true;
     }
 
+    /// The file that holds the prefetch profile named `profile` in this
+    /// checkout.
+    pub fn profile_path(&self, profile: &str) -> PathBuf {
+        const RELATIVE_PROFILES_LOCATION: &str = "xplat/scm/prefetch_profiles/profiles";
+        self.path.join(RELATIVE_PROFILES_LOCATION).join(profile)
+    }
+
     pub fn get_contents_for_profile(
         &self,
         profile: &String,
         silent: bool,
     ) -> Result<HashSet<String>> {
-        const RELATIVE_PROFILES_LOCATION: &str = "xplat/scm/prefetch_profiles/profiles";
-        let profile_path = self.path.join(RELATIVE_PROFILES_LOCATION).join(profile);
+        let profile_path = self.profile_path(profile);
 
         if !profile_path.exists() {
             if !silent {
@@ -1073,7 +1143,9 @@ mod tests {
     use tempfile::tempdir;
     use tempfile::TempDir;
 
+    use crate::checkout::save_snapshot;
     use crate::checkout::CheckoutConfig;
+    use crate::checkout::EdenFsCheckout;
     use crate::checkout::RedirectionType;
     use crate::checkout::MOUNT_CONFIG;
     use crate::checkout::REPO_SOURCE;
@@ -1207,4 +1279,24 @@ mod tests {
         };
         assert!(update_and_test_redirection(redir4, config_dir, false).is_ok());
     }
+    #[test]
+    fn test_new_checkout() {
+        let data_dir = tempdir().expect("failed to create temp dir");
+        let mut config =
+            CheckoutConfig::new(PathBuf::from("/tmp/"), "hg", "nfs", false).expect("valid config");
+        config
+            .save_config(data_dir.path().into())
+            .expect("failed to save checkout config");
+        save_snapshot(data_dir.path(), "ab".repeat(20).as_str()).expect("failed to save snapshot");
+
+        let config = CheckoutConfig::parse_config(data_dir.path().into()).expect("valid config");
+        assert_eq!("hg", config.repo_type());
+        assert_eq!(Path::new("/tmp/"), config.backing_repo());
+        let checkout =
+            EdenFsCheckout::from_config(PathBuf::from("/checkout"), data_dir.path().into(), config);
+        let snapshot = checkout.get_snapshot().expect("valid snapshot");
+        assert_eq!("ab".repeat(20), snapshot.working_copy_parent);
+
+        assert!(CheckoutConfig::new(PathBuf::from("/tmp/"), "svn", "nfs", false).is_err());
+    }
 }
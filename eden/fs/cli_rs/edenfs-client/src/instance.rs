@@ -9,12 +9,15 @@
 //! [`EdenFsClient`]).
 
 use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Write;
 use std::path::Path;
 use std::path::PathBuf;
 use std::time::Duration;
 
 use anyhow::anyhow;
 use anyhow::Context;
+use atomicfile::atomic_write;
 use edenfs_config::EdenFsConfig;
 use edenfs_error::EdenFsError;
 use edenfs_error::Result;
@@ -25,6 +28,7 @@ use edenfs_utils::strip_unc_prefix;
 #[cfg(fbcode_build)]
 use fbinit::expect_init;
 use fbthrift_socket::SocketTransport;
+use fs2::FileExt;
 use once_cell::sync::OnceCell;
 #[cfg(fbcode_build)]
 use thrift_streaming_thriftclients::build_StreamingEdenService_client;
@@ -52,6 +56,7 @@ static INSTANCE: OnceCell<EdenFsInstance> = OnceCell::new();
 /// These paths are relative to the user's client directory.
 const CLIENTS_DIR: &str = "clients";
 const CONFIG_JSON: &str = "config.json";
+const CONFIG_JSON_LOCK: &str = "config.json.lock";
 
 #[derive(Debug)]
 pub struct EdenFsInstance {
@@ -60,6 +65,31 @@ pub struct EdenFsInstance {
     home_dir: Option<PathBuf>,
 }
 
+async fn _connect(socket_path: &Path) -> Result<EdenFsClient> {
+    let stream = UnixStream::connect(&socket_path)
+        .await
+        .map_err(EdenFsError::ThriftIoError)?;
+    let transport = SocketTransport::new(stream);
+    let client = <dyn EdenService>::new(BinaryProtocol, transport);
+
+    Ok(client)
+}
+
+/// Connects to the daemon listening on `socket_path`.
+pub(crate) async fn connect_socket(
+    socket_path: &Path,
+    timeout: Option<Duration>,
+) -> Result<EdenFsClient> {
+    let connect = _connect(socket_path);
+    if let Some(timeout) = timeout {
+        tokio::time::timeout(timeout, connect)
+            .await
+            .map_err(|_| EdenFsError::ThriftConnectionTimeout(socket_path.to_path_buf()))?
+    } else {
+        connect.await
+    }
+}
+
 impl EdenFsInstance {
     pub fn global() -> &'static EdenFsInstance {
         INSTANCE.get().expect("EdenFsInstance is not initialized")
@@ -89,6 +119,14 @@ impl EdenFsInstance {
         )
     }
 
+    pub fn config_dir(&self) -> &Path {
+        &self.config_dir
+    }
+
+    pub fn etc_eden_dir(&self) -> &Path {
+        &self.etc_eden_dir
+    }
+
     pub fn get_user_home_dir(&self) -> Option<&PathBuf> {
         self.home_dir.as_ref()
     }
@@ -107,29 +145,13 @@ impl EdenFsInstance {
             .map_or(false, |config| config.predictive_prefetching_enabled)
     }
 
-    async fn _connect(&self, socket_path: &PathBuf) -> Result<EdenFsClient> {
-        let stream = UnixStream::connect(&socket_path)
-            .await
-            .map_err(EdenFsError::ThriftIoError)?;
-        let transport = SocketTransport::new(stream);
-        let client = <dyn EdenService>::new(BinaryProtocol, transport);
-
-        Ok(client)
+    /// The socket the daemon serving this instance listens on.
+    pub fn socket_path(&self) -> PathBuf {
+        self.config_dir.join("socket")
     }
 
     pub async fn connect(&self, timeout: Option<Duration>) -> Result<EdenFsClient> {
-        let socket_path = self.config_dir.join("socket");
-
-        let connect = self._connect(&socket_path);
-        let res = if let Some(timeout) = timeout {
-            tokio::time::timeout(timeout, connect)
-                .await
-                .map_err(|_| EdenFsError::ThriftConnectionTimeout(socket_path))?
-        } else {
-            connect.await
-        };
-
-        res
+        connect_socket(&self.socket_path(), timeout).await
     }
 
     #[cfg(fbcode_build)]
@@ -147,7 +169,7 @@ impl EdenFsInstance {
         &self,
         timeout: Option<Duration>,
     ) -> Result<StreamingEdenFsClient> {
-        let socket_path = self.config_dir.join("socket");
+        let socket_path = self.socket_path();
         let client = self._connect_streaming(&socket_path);
 
         if let Some(timeout) = timeout {
@@ -283,6 +305,85 @@ impl EdenFsInstance {
     pub fn config_directory(&self, client_name: &str) -> PathBuf {
         self.clients_dir().join(client_name)
     }
+
+    /// Runs `update` on the directory map in config.json and writes it back, holding the same
+    /// lock as the Python CLI so concurrent clones and removals don't lose entries.
+    fn update_directory_map<F>(&self, update: F) -> Result<()>
+    where
+        F: FnOnce(&mut BTreeMap<PathBuf, String>) -> Result<bool>,
+    {
+        let lock_path = self.config_dir.join(CONFIG_JSON_LOCK);
+        let lock = File::create(&lock_path)
+            .with_context(|| format!("Failed to open lock file {}", lock_path.display()))?;
+        lock.lock_exclusive()
+            .with_context(|| format!("Failed to lock {}", lock_path.display()))?;
+
+        let mut directory_map = self.get_configured_mounts_map()?;
+        if !update(&mut directory_map)? {
+            return Ok(());
+        }
+        let string_map: BTreeMap<String, &String> = directory_map
+            .iter()
+            .map(|(path, name)| (path.to_string_lossy().into_owned(), name))
+            .collect();
+        let contents = serde_json::to_string_pretty(&string_map).from_err()? + "\n";
+        atomic_write(&self.config_dir.join(CONFIG_JSON), 0o644, true, |f| {
+            f.write_all(contents.as_bytes())
+        })
+        .from_err()?;
+        Ok(())
+    }
+
+    /// Records `client_name` as the client directory of the checkout at `path`.
+    pub fn add_path_to_directory_map(&self, path: &Path, client_name: &str) -> Result<()> {
+        self.update_directory_map(|directory_map| {
+            if directory_map.contains_key(path) {
+                return Err(EdenFsError::Other(anyhow!(
+                    "mount path {} already exists.",
+                    path.display()
+                )));
+            }
+            directory_map.insert(path.to_path_buf(), client_name.to_string());
+            Ok(true)
+        })
+    }
+
+    pub fn remove_path_from_directory_map(&self, path: &Path) -> Result<()> {
+        self.update_directory_map(|directory_map| Ok(directory_map.remove(path).is_some()))
+    }
+
+    /// Creates a new client directory named after the last component of `path`, adding a numeric
+    /// suffix if a directory with that name already exists.
+    pub fn create_client_dir(&self, path: &Path) -> Result<PathBuf> {
+        let basename = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .filter(|name| !name.is_empty())
+            .ok_or_else(|| anyhow!("Suspicious attempt to clone into: {}", path.display()))?;
+        let clients_dir = self.clients_dir();
+        std::fs::create_dir_all(&clients_dir)
+            .with_context(|| format!("Failed to create {}", clients_dir.display()))?;
+
+        for i in 0.. {
+            let client_dir = if i == 0 {
+                clients_dir.join(&basename)
+            } else {
+                clients_dir.join(format!("{}-{}", basename, i))
+            };
+            match std::fs::create_dir(&client_dir) {
+                Ok(()) => return Ok(client_dir),
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+                Err(e) => {
+                    return Err(EdenFsError::Other(anyhow!(
+                        "Failed to create {}: {}",
+                        client_dir.display(),
+                        e
+                    )));
+                }
+            }
+        }
+        unreachable!("there are always more client directory names to try")
+    }
 }
 
 pub trait DaemonHealthy {
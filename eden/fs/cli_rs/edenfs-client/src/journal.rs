@@ -0,0 +1,141 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::anyhow;
+use edenfs_error::EdenFsError;
+use edenfs_error::Result;
+use serde::Serialize;
+use serde::Serializer;
+use thrift_types::edenfs::types::JournalPosition as ThriftJournalPosition;
+
+/// A point in the journal of a checkout.
+///
+/// Positions are written as `<mount generation>:<sequence number>:<commit>`
+/// tokens, which callers can store and hand back later to ask what changed
+/// since.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct JournalPosition {
+    pub mount_generation: i64,
+    // Thrift generates i64 for the unsigned64 type
+    pub sequence_number: i64,
+    pub snapshot_hash: Vec<u8>,
+}
+
+impl fmt::Display for JournalPosition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}",
+            self.mount_generation,
+            self.sequence_number,
+            hex::encode(&self.snapshot_hash)
+        )
+    }
+}
+
+impl FromStr for JournalPosition {
+    type Err = EdenFsError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || {
+            EdenFsError::Other(anyhow!(
+                "invalid journal position '{}': expected \
+                <mount generation>:<sequence number>:<commit>",
+                s
+            ))
+        };
+        let mut parts = s.splitn(3, ':');
+        let mount_generation = parts
+            .next()
+            .and_then(|part| part.parse().ok())
+            .ok_or_else(invalid)?;
+        let sequence_number = parts
+            .next()
+            .and_then(|part| part.parse().ok())
+            .ok_or_else(invalid)?;
+        let snapshot_hash = parts
+            .next()
+            .and_then(|part| hex::decode(part).ok())
+            .ok_or_else(invalid)?;
+        Ok(JournalPosition {
+            mount_generation,
+            sequence_number,
+            snapshot_hash,
+        })
+    }
+}
+
+impl Serialize for JournalPosition {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+impl From<ThriftJournalPosition> for JournalPosition {
+    fn from(from: ThriftJournalPosition) -> Self {
+        JournalPosition {
+            mount_generation: from.mountGeneration,
+            sequence_number: from.sequenceNumber,
+            snapshot_hash: from.snapshotHash,
+        }
+    }
+}
+
+impl From<JournalPosition> for ThriftJournalPosition {
+    fn from(from: JournalPosition) -> Self {
+        ThriftJournalPosition {
+            mountGeneration: from.mount_generation,
+            sequenceNumber: from.sequence_number,
+            snapshotHash: from.snapshot_hash,
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let position = JournalPosition {
+            mount_generation: -42,
+            sequence_number: 1234,
+            snapshot_hash: vec![0xab, 0xcd, 0xef],
+        };
+        let token = position.to_string();
+        assert_eq!("-42:1234:abcdef", token);
+        assert_eq!(position, token.parse::<JournalPosition>().unwrap());
+        assert_eq!(
+            "\"-42:1234:abcdef\"",
+            serde_json::to_string(&position).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_empty_snapshot_hash() {
+        let position = "7:0:".parse::<JournalPosition>().unwrap();
+        assert_eq!(7, position.mount_generation);
+        assert_eq!(0, position.sequence_number);
+        assert!(position.snapshot_hash.is_empty());
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!("".parse::<JournalPosition>().is_err());
+        assert!("1:2".parse::<JournalPosition>().is_err());
+        assert!("a:2:ab".parse::<JournalPosition>().is_err());
+        assert!("1:b:ab".parse::<JournalPosition>().is_err());
+        assert!("1:2:xyz".parse::<JournalPosition>().is_err());
+    }
+}
@@ -13,11 +13,14 @@ use thrift_types::edenfs::client::EdenService;
 
 pub mod checkout;
 pub mod instance;
-mod mounttable;
+pub mod journal;
+pub mod mounttable;
 pub mod redirect;
+pub mod sdk;
 
 pub use instance::DaemonHealthy;
 pub use instance::EdenFsInstance;
+pub use journal::JournalPosition;
 
 pub type EdenFsClient = Arc<dyn EdenService + Sync>;
 
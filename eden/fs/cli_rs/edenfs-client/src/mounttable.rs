@@ -16,16 +16,24 @@ use subprocess::Exec;
 use subprocess::Redirection;
 
 #[derive(Debug, PartialEq)]
-pub(crate) struct MountTableInfo {
+pub struct MountTableInfo {
     device: String,
     mount_point: PathBuf,
     vfstype: String,
 }
 
 impl MountTableInfo {
-    pub(crate) fn mount_point(&self) -> PathBuf {
+    pub fn device(&self) -> &str {
+        &self.device
+    }
+
+    pub fn mount_point(&self) -> PathBuf {
         self.mount_point.clone()
     }
+
+    pub fn vfstype(&self) -> &str {
+        &self.vfstype
+    }
 }
 
 fn parse_linux_mtab(mtab_string: String) -> Vec<MountTableInfo> {
@@ -66,7 +74,7 @@ fn parse_macos_mtab(mtab_string: String) -> Vec<MountTableInfo> {
 }
 
 /// Returns the list of system mounts
-pub(crate) fn read_mount_table() -> Result<Vec<MountTableInfo>> {
+pub fn read_mount_table() -> Result<Vec<MountTableInfo>> {
     if cfg!(target_os = "linux") {
         Ok(parse_linux_mtab(
             std::fs::read_to_string(PathBuf::from("/proc/self/mounts")).from_err()?,
@@ -0,0 +1,592 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+//! A typed async API to query a running EdenFS daemon.
+//!
+//! [`EdenFsSdk`] is meant for tools built on top of EdenFS, such as IDE and
+//! build integrations, that would otherwise drive the raw Thrift client
+//! returned by [`EdenFsInstance::connect`]. Its types don't expose Thrift, so
+//! callers aren't affected by changes to the Thrift definitions.
+//!
+//! Everything goes through an [`EdenFsBackend`]. [`ThriftBackend`] talks to
+//! the daemon, and [`MockEdenFs`] is an in-memory daemon for tests.
+//!
+//! ```no_run
+//! # async fn example() -> edenfs_error::Result<()> {
+//! use std::path::Path;
+//! use std::time::Duration;
+//!
+//! use edenfs_client::sdk::EdenFsSdk;
+//! use edenfs_client::sdk::GlobOptions;
+//! use futures::StreamExt;
+//!
+//! let sdk = EdenFsSdk::from_socket("/home/user/local/.eden/socket");
+//! let mount = sdk
+//!     .find_mount(Path::new("/home/user/repo/src"))
+//!     .await?
+//!     .expect("not in a checkout");
+//! let sources = sdk
+//!     .glob(&mount.path, &GlobOptions::new(["**/*.rs"]))
+//!     .await?;
+//! let mut events = Box::pin(
+//!     sdk.subscribe(&mount.path, Duration::from_millis(200))
+//!         .await?,
+//! );
+//! while let Some(event) = events.next().await {
+//!     println!("{:?}", event?);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use edenfs_error::Result;
+use futures::Stream;
+
+use crate::EdenFsInstance;
+use crate::JournalPosition;
+
+mod mock;
+mod thrift;
+
+pub use mock::MockEdenFs;
+pub use thrift::ThriftBackend;
+
+/// The lifecycle state of a mounted checkout.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MountState {
+    /// The checkout is being set up and can't serve requests yet.
+    Starting,
+    /// The checkout serves requests.
+    Running,
+    /// The checkout is being unmounted.
+    Stopping,
+    /// The checkout failed to mount.
+    Failed,
+}
+
+/// A checkout the daemon knows about.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MountInfo {
+    pub path: PathBuf,
+    /// The directory EdenFS keeps the state of the checkout in.
+    pub data_dir: PathBuf,
+    pub state: MountState,
+    pub backing_repo: Option<PathBuf>,
+}
+
+/// How a file differs from the commit the working copy is based on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FileStatus {
+    Added,
+    Modified,
+    Removed,
+    Ignored,
+}
+
+/// The source control status of a working copy.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Status {
+    /// The files that differ from the working copy parent, relative to the
+    /// root of the checkout.
+    pub entries: BTreeMap<PathBuf, FileStatus>,
+    /// Paths whose status couldn't be computed, with the reason why. The
+    /// status of these paths may be missing from `entries`.
+    pub errors: BTreeMap<PathBuf, String>,
+}
+
+/// What to match with [`EdenFsSdk::glob`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GlobOptions {
+    pub patterns: Vec<String>,
+    /// Match files and directories whose name starts with a dot.
+    pub include_dotfiles: bool,
+    /// Only return files, not directories.
+    pub list_only_files: bool,
+    /// The directory, relative to the root of the checkout, that patterns and
+    /// results are relative to. The root of the checkout when empty.
+    pub search_root: PathBuf,
+    /// Match against these commits instead of the working copy.
+    pub revisions: Vec<Vec<u8>>,
+}
+
+impl GlobOptions {
+    pub fn new<I, S>(patterns: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        GlobOptions {
+            patterns: patterns.into_iter().map(Into::into).collect(),
+            ..Default::default()
+        }
+    }
+}
+
+/// A path matched by [`EdenFsSdk::glob`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GlobEntry {
+    pub path: PathBuf,
+    /// The commit the path was matched in, when globbing commits.
+    pub commit: Option<Vec<u8>>,
+}
+
+/// What to fetch with [`EdenFsSdk::prefetch`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PrefetchOptions {
+    pub patterns: Vec<String>,
+    /// Only fetch the directories leading to matching files.
+    pub directories_only: bool,
+    /// Fetch from these commits instead of the working copy.
+    pub revisions: Vec<Vec<u8>>,
+    /// Return once the daemon has accepted the request rather than when it
+    /// has completed.
+    pub background: bool,
+}
+
+/// The type of a file, as recorded in source control.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FileType {
+    Directory,
+    Regular,
+    Executable,
+    Symlink,
+}
+
+/// Attributes of a regular file, as returned by [`EdenFsSdk::file_attributes`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FileAttributes {
+    pub sha1: Vec<u8>,
+    pub size: u64,
+    pub file_type: FileType,
+}
+
+/// The files that changed in a checkout between two journal positions.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Changes {
+    pub from_position: JournalPosition,
+    pub to_position: JournalPosition,
+    /// Paths that existed at `from_position` and were modified, or removed,
+    /// since.
+    pub changed_paths: Vec<PathBuf>,
+    pub created_paths: Vec<PathBuf>,
+    /// Paths whose source control status may have changed across a checkout
+    /// operation.
+    pub unclean_paths: Vec<PathBuf>,
+    /// The commits the checkout went through, oldest first.
+    pub commit_transitions: Vec<Vec<u8>>,
+}
+
+impl Changes {
+    /// Whether nothing happened between the two positions.
+    pub fn is_empty(&self) -> bool {
+        self.from_position.mount_generation == self.to_position.mount_generation
+            && self.from_position.sequence_number == self.to_position.sequence_number
+    }
+}
+
+/// An event of a journal subscription.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum JournalEvent {
+    Changes(Changes),
+    /// The subscription lost track of the journal, because the daemon
+    /// restarted or forgot old changes, and resumed at this position.
+    /// Subscribers should assume anything may have changed.
+    Reset(JournalPosition),
+}
+
+/// The operations [`EdenFsSdk`] is built on.
+///
+/// Mount points are absolute paths, and other paths are relative to the root
+/// of their checkout.
+#[async_trait]
+pub trait EdenFsBackend: Send + Sync {
+    async fn list_mounts(&self) -> Result<Vec<MountInfo>>;
+
+    /// The status of the working copy of `mount` against `commit`, which has
+    /// to be the commit the working copy is based on.
+    async fn status(&self, mount: &Path, commit: &[u8], list_ignored: bool) -> Result<Status>;
+
+    async fn glob(&self, mount: &Path, options: &GlobOptions) -> Result<Vec<GlobEntry>>;
+
+    async fn prefetch(&self, mount: &Path, options: &PrefetchOptions) -> Result<()>;
+
+    /// The attributes of each of `paths`, in the same order. Directories and
+    /// symlinks have no attributes and get an error.
+    async fn file_attributes(
+        &self,
+        mount: &Path,
+        paths: &[PathBuf],
+    ) -> Result<Vec<Result<FileAttributes>>>;
+
+    async fn journal_position(&self, mount: &Path) -> Result<JournalPosition>;
+
+    /// Fails if `from` is no longer in the journal.
+    async fn changes_since(&self, mount: &Path, from: &JournalPosition) -> Result<Changes>;
+}
+
+/// A handle to an EdenFS daemon. Cloning it is cheap and shares the
+/// underlying connection.
+#[derive(Clone)]
+pub struct EdenFsSdk {
+    backend: Arc<dyn EdenFsBackend>,
+}
+
+impl EdenFsSdk {
+    pub fn new(backend: Arc<dyn EdenFsBackend>) -> Self {
+        EdenFsSdk { backend }
+    }
+
+    /// Talks to the daemon listening on `socket_path`. The connection is only
+    /// established by the first request.
+    pub fn from_socket(socket_path: impl Into<PathBuf>) -> Self {
+        Self::new(Arc::new(ThriftBackend::new(socket_path.into())))
+    }
+
+    /// Talks to the daemon of `instance`.
+    pub fn from_instance(instance: &EdenFsInstance) -> Self {
+        Self::from_socket(instance.socket_path())
+    }
+
+    pub async fn list_mounts(&self) -> Result<Vec<MountInfo>> {
+        self.backend.list_mounts().await
+    }
+
+    /// The mounted checkout containing `path`, which has to be absolute and
+    /// free of symlinks.
+    pub async fn find_mount(&self, path: &Path) -> Result<Option<MountInfo>> {
+        Ok(self
+            .list_mounts()
+            .await?
+            .into_iter()
+            .filter(|mount| path.starts_with(&mount.path))
+            // Checkouts can be nested.
+            .max_by_key(|mount| mount.path.components().count()))
+    }
+
+    /// The status of the working copy of `mount` against the commit it is
+    /// based on.
+    pub async fn status(&self, mount: &Path, list_ignored: bool) -> Result<Status> {
+        let position = self.backend.journal_position(mount).await?;
+        self.backend
+            .status(mount, &position.snapshot_hash, list_ignored)
+            .await
+    }
+
+    pub async fn glob(&self, mount: &Path, options: &GlobOptions) -> Result<Vec<GlobEntry>> {
+        self.backend.glob(mount, options).await
+    }
+
+    pub async fn prefetch(&self, mount: &Path, options: &PrefetchOptions) -> Result<()> {
+        self.backend.prefetch(mount, options).await
+    }
+
+    /// See [`EdenFsBackend::file_attributes`].
+    pub async fn file_attributes(
+        &self,
+        mount: &Path,
+        paths: &[PathBuf],
+    ) -> Result<Vec<Result<FileAttributes>>> {
+        self.backend.file_attributes(mount, paths).await
+    }
+
+    pub async fn journal_position(&self, mount: &Path) -> Result<JournalPosition> {
+        self.backend.journal_position(mount).await
+    }
+
+    pub async fn changes_since(&self, mount: &Path, from: &JournalPosition) -> Result<Changes> {
+        self.backend.changes_since(mount, from).await
+    }
+
+    /// Reports what changes in `mount` from now on, checking every
+    /// `interval`.
+    ///
+    /// The stream never ends. Failing to reach the daemon yields an error and
+    /// the next poll tries again, so the subscription outlives daemon
+    /// restarts, which are reported as [`JournalEvent::Reset`].
+    pub async fn subscribe(
+        &self,
+        mount: &Path,
+        interval: Duration,
+    ) -> Result<impl Stream<Item = Result<JournalEvent>> + Send + 'static> {
+        let subscription = Subscription {
+            backend: self.backend.clone(),
+            mount: mount.to_path_buf(),
+            interval,
+            position: self.backend.journal_position(mount).await?,
+        };
+        Ok(futures::stream::unfold(
+            subscription,
+            |mut subscription| async move {
+                let event = subscription.next_event().await;
+                Some((event, subscription))
+            },
+        ))
+    }
+}
+
+struct Subscription {
+    backend: Arc<dyn EdenFsBackend>,
+    mount: PathBuf,
+    interval: Duration,
+    position: JournalPosition,
+}
+
+impl Subscription {
+    async fn next_event(&mut self) -> Result<JournalEvent> {
+        loop {
+            tokio::time::sleep(self.interval).await;
+            match self
+                .backend
+                .changes_since(&self.mount, &self.position)
+                .await
+            {
+                Ok(changes) => {
+                    self.position = changes.to_position.clone();
+                    if !changes.is_empty() {
+                        return Ok(JournalEvent::Changes(changes));
+                    }
+                }
+                Err(e) => {
+                    // If the daemon is reachable, our position is what it
+                    // rejected.
+                    let current = self.backend.journal_position(&self.mount).await;
+                    return match current {
+                        Ok(current) => {
+                            self.position = current.clone();
+                            Ok(JournalEvent::Reset(current))
+                        }
+                        Err(_) => Err(e),
+                    };
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::StreamExt;
+
+    use super::*;
+
+    const COMMIT: &[u8] = &[0x11; 20];
+
+    fn mock_sdk() -> (Arc<MockEdenFs>, EdenFsSdk) {
+        let mock = Arc::new(MockEdenFs::new());
+        mock.add_mount("/repo", COMMIT);
+        mock.add_mount("/repo/nested", COMMIT);
+        mock.add_mount("/other", COMMIT);
+        (mock.clone(), EdenFsSdk::new(mock))
+    }
+
+    fn paths(paths: &[&str]) -> Vec<PathBuf> {
+        paths.iter().map(PathBuf::from).collect()
+    }
+
+    #[tokio::test]
+    async fn test_find_mount() {
+        let (_, sdk) = mock_sdk();
+        let find = |path: &'static str| {
+            let sdk = sdk.clone();
+            async move {
+                sdk.find_mount(Path::new(path))
+                    .await
+                    .unwrap()
+                    .map(|mount| mount.path)
+            }
+        };
+        assert_eq!(Some(PathBuf::from("/repo")), find("/repo/src/lib.rs").await);
+        assert_eq!(
+            Some(PathBuf::from("/repo/nested")),
+            find("/repo/nested/a").await
+        );
+        assert_eq!(Some(PathBuf::from("/other")), find("/other").await);
+        assert_eq!(None, find("/repository").await);
+    }
+
+    #[tokio::test]
+    async fn test_status() {
+        let (mock, sdk) = mock_sdk();
+        let repo = Path::new("/repo");
+        mock.write_file(repo, "a.txt", b"a");
+        mock.commit(repo, &[0x22; 20]);
+        mock.write_file(repo, "a.txt", b"b");
+        mock.write_file(repo, "b.txt", b"b");
+
+        let status = sdk.status(repo, false).await.unwrap();
+        assert_eq!(
+            BTreeMap::from([
+                (PathBuf::from("a.txt"), FileStatus::Modified),
+                (PathBuf::from("b.txt"), FileStatus::Added),
+            ]),
+            status.entries
+        );
+        assert!(mock.status(repo, COMMIT, false).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_glob() {
+        let (mock, sdk) = mock_sdk();
+        let repo = Path::new("/repo");
+        mock.write_file(repo, "src/lib.rs", b"");
+        mock.write_file(repo, "src/bin/main.rs", b"");
+        mock.write_file(repo, "src/.hidden.rs", b"");
+        mock.write_file(repo, "README", b"");
+
+        let glob = |options: GlobOptions| {
+            let sdk = sdk.clone();
+            async move {
+                let mut matches: Vec<_> = sdk
+                    .glob(repo, &options)
+                    .await
+                    .unwrap()
+                    .into_iter()
+                    .map(|entry| entry.path)
+                    .collect();
+                matches.sort();
+                matches
+            }
+        };
+        assert_eq!(
+            paths(&["src/bin/main.rs", "src/lib.rs"]),
+            glob(GlobOptions::new(["**/*.rs"])).await
+        );
+        assert_eq!(
+            paths(&["src/.hidden.rs", "src/bin/main.rs", "src/lib.rs"]),
+            glob(GlobOptions {
+                include_dotfiles: true,
+                ..GlobOptions::new(["**/*.rs"])
+            })
+            .await
+        );
+        assert_eq!(
+            paths(&["bin", "lib.rs"]),
+            glob(GlobOptions {
+                search_root: PathBuf::from("src"),
+                ..GlobOptions::new(["*"])
+            })
+            .await
+        );
+        assert_eq!(
+            paths(&["README", "src"]),
+            glob(GlobOptions::new(["*"])).await
+        );
+        assert_eq!(
+            paths(&["README"]),
+            glob(GlobOptions {
+                list_only_files: true,
+                ..GlobOptions::new(["*"])
+            })
+            .await
+        );
+    }
+
+    #[tokio::test]
+    async fn test_file_attributes() {
+        let (mock, sdk) = mock_sdk();
+        let repo = Path::new("/repo");
+        mock.write_file(repo, "dir/file", b"hello");
+
+        let attributes = sdk
+            .file_attributes(repo, &paths(&["dir/file", "dir", "missing"]))
+            .await
+            .unwrap();
+        assert_eq!(3, attributes.len());
+        let file = attributes[0].as_ref().unwrap();
+        assert_eq!(5, file.size);
+        assert_eq!(FileType::Regular, file.file_type);
+        assert_eq!(
+            "aaf4c61ddcc5e8a2dabede0f3b482cd9aea9434d",
+            hex::encode(&file.sha1)
+        );
+        assert!(attributes[1].is_err());
+        assert!(attributes[2].is_err());
+    }
+
+    #[tokio::test]
+    async fn test_prefetch() {
+        let (mock, sdk) = mock_sdk();
+        let options = PrefetchOptions {
+            patterns: vec!["src/**".to_string()],
+            background: true,
+            ..Default::default()
+        };
+        sdk.prefetch(Path::new("/repo"), &options).await.unwrap();
+        assert_eq!(vec![options], mock.prefetched(Path::new("/repo")));
+    }
+
+    #[tokio::test]
+    async fn test_changes_since() {
+        let (mock, sdk) = mock_sdk();
+        let repo = Path::new("/repo");
+        mock.write_file(repo, "old", b"");
+        let start = sdk.journal_position(repo).await.unwrap();
+
+        mock.write_file(repo, "old", b"changed");
+        mock.write_file(repo, "new", b"");
+        mock.commit(repo, &[0x22; 20]);
+        mock.remove_file(repo, "new");
+
+        let changes = sdk.changes_since(repo, &start).await.unwrap();
+        assert_eq!(paths(&["old"]), changes.changed_paths);
+        assert_eq!(paths(&["new"]), changes.created_paths);
+        assert_eq!(
+            vec![COMMIT.to_vec(), vec![0x22; 20]],
+            changes.commit_transitions
+        );
+        assert_eq!(start, changes.from_position);
+        assert_eq!(
+            sdk.journal_position(repo).await.unwrap(),
+            changes.to_position
+        );
+
+        mock.restart();
+        assert!(sdk.changes_since(repo, &start).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_subscribe() {
+        let (mock, sdk) = mock_sdk();
+        let repo = Path::new("/repo");
+        mock.write_file(repo, "before", b"");
+        let mut events = Box::pin(sdk.subscribe(repo, Duration::from_millis(1)).await.unwrap());
+
+        mock.write_file(repo, "a", b"");
+        assert!(is_created(&events.next().await.unwrap().unwrap(), "a"));
+
+        // Unreachable daemons yield errors until they come back.
+        mock.set_available(false);
+        assert!(events.next().await.unwrap().is_err());
+        mock.write_file(repo, "b", b"");
+        mock.set_available(true);
+        assert!(is_created(&events.next().await.unwrap().unwrap(), "b"));
+
+        // Changes made while the daemon restarts are lost.
+        mock.restart();
+        let position = sdk.journal_position(repo).await.unwrap();
+        assert_eq!(
+            JournalEvent::Reset(position),
+            events.next().await.unwrap().unwrap()
+        );
+        mock.write_file(repo, "c", b"");
+        assert!(is_created(&events.next().await.unwrap().unwrap(), "c"));
+    }
+
+    fn is_created(event: &JournalEvent, path: &str) -> bool {
+        match event {
+            JournalEvent::Changes(changes) => changes.created_paths == paths(&[path]),
+            JournalEvent::Reset(_) => false,
+        }
+    }
+}
@@ -0,0 +1,448 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::sync::MutexGuard;
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+use edenfs_error::EdenFsError;
+use edenfs_error::Result;
+use regex::Regex;
+use sha1::Digest;
+use sha1::Sha1;
+
+use super::Changes;
+use super::EdenFsBackend;
+use super::FileAttributes;
+use super::FileStatus;
+use super::FileType;
+use super::GlobEntry;
+use super::GlobOptions;
+use super::MountInfo;
+use super::MountState;
+use super::PrefetchOptions;
+use super::Status;
+use crate::JournalPosition;
+
+type Files = BTreeMap<PathBuf, Vec<u8>>;
+
+/// An in-memory EdenFS daemon, for testing code built on
+/// [`EdenFsSdk`](super::EdenFsSdk).
+///
+/// Checkouts only hold regular files, which tests add and modify with
+/// methods like [`MockEdenFs::write_file`]. Those are recorded in the journal
+/// like the daemon would.
+#[derive(Default)]
+pub struct MockEdenFs {
+    state: Mutex<MockState>,
+}
+
+#[derive(Default)]
+struct MockState {
+    unavailable: bool,
+    generation: i64,
+    mounts: BTreeMap<PathBuf, MockMount>,
+}
+
+struct MockMount {
+    commit: Vec<u8>,
+    /// The files of each commit.
+    commits: BTreeMap<Vec<u8>, Files>,
+    files: Files,
+    /// The commit the journal of this generation starts at.
+    journal_commit: Vec<u8>,
+    journal: Vec<JournalEntry>,
+    prefetched: Vec<PrefetchOptions>,
+}
+
+enum JournalEntry {
+    Created(PathBuf),
+    Changed(PathBuf),
+    Commit(Vec<u8>),
+}
+
+impl MockMount {
+    fn position(&self, generation: i64) -> JournalPosition {
+        JournalPosition {
+            mount_generation: generation,
+            sequence_number: self.journal.len() as i64,
+            snapshot_hash: self.commit.clone(),
+        }
+    }
+
+    fn directories(files: &Files) -> BTreeSet<PathBuf> {
+        files
+            .keys()
+            .flat_map(|path| path.ancestors().skip(1))
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .map(Path::to_path_buf)
+            .collect()
+    }
+}
+
+impl MockEdenFs {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    fn lock(&self) -> MutexGuard<MockState> {
+        self.state.lock().expect("mock EdenFS state is poisoned")
+    }
+
+    /// Runs `f` on the mount at `path` of a test. Panics if there is no such
+    /// mount.
+    fn with_mount<T>(&self, path: &Path, f: impl FnOnce(&mut MockMount) -> T) -> T {
+        let mut state = self.lock();
+        let mount = state
+            .mounts
+            .get_mut(path)
+            .unwrap_or_else(|| panic!("{} is not a mock mount", path.display()));
+        f(mount)
+    }
+
+    /// The state of the daemon, as long as it is reachable.
+    fn connect(&self) -> Result<MutexGuard<MockState>> {
+        let state = self.lock();
+        if state.unavailable {
+            return Err(EdenFsError::ThriftIoError(io::Error::new(
+                io::ErrorKind::ConnectionRefused,
+                "the mock EdenFS daemon is not running",
+            )));
+        }
+        Ok(state)
+    }
+
+    /// Runs `f` on a mount the backend was asked about, if the daemon is
+    /// reachable and has such a mount.
+    fn serve<T>(&self, path: &Path, f: impl FnOnce(&mut MockMount, i64) -> Result<T>) -> Result<T> {
+        let mut state = self.connect()?;
+        let generation = state.generation;
+        match state.mounts.get_mut(path) {
+            Some(mount) => f(mount, generation),
+            None => Err(EdenFsError::Other(anyhow!(
+                "{} is not an EdenFS mount",
+                path.display()
+            ))),
+        }
+    }
+
+    /// Adds an empty checkout of `commit` at `path`.
+    pub fn add_mount(&self, path: impl Into<PathBuf>, commit: &[u8]) {
+        self.lock().mounts.insert(
+            path.into(),
+            MockMount {
+                commit: commit.to_vec(),
+                commits: BTreeMap::from([(commit.to_vec(), Files::new())]),
+                files: Files::new(),
+                journal_commit: commit.to_vec(),
+                journal: Vec::new(),
+                prefetched: Vec::new(),
+            },
+        );
+    }
+
+    /// Creates or overwrites the file at `path` in `mount`.
+    pub fn write_file(&self, mount: &Path, path: impl AsRef<Path>, contents: &[u8]) {
+        let path = path.as_ref().to_path_buf();
+        self.with_mount(mount, |mount| {
+            let entry = match mount.files.insert(path.clone(), contents.to_vec()) {
+                Some(_) => JournalEntry::Changed(path),
+                None => JournalEntry::Created(path),
+            };
+            mount.journal.push(entry);
+        })
+    }
+
+    pub fn remove_file(&self, mount: &Path, path: impl AsRef<Path>) {
+        let path = path.as_ref().to_path_buf();
+        self.with_mount(mount, |mount| {
+            if mount.files.remove(&path).is_some() {
+                mount.journal.push(JournalEntry::Changed(path));
+            }
+        })
+    }
+
+    /// Commits the working copy of `mount` as `commit`, which becomes the
+    /// commit the working copy is based on.
+    pub fn commit(&self, mount: &Path, commit: &[u8]) {
+        self.with_mount(mount, |mount| {
+            mount.commits.insert(commit.to_vec(), mount.files.clone());
+            mount.commit = commit.to_vec();
+            mount.journal.push(JournalEntry::Commit(commit.to_vec()));
+        })
+    }
+
+    /// Restarts the daemon, which starts new journals.
+    pub fn restart(&self) {
+        let mut state = self.lock();
+        state.generation += 1;
+        for mount in state.mounts.values_mut() {
+            mount.journal.clear();
+            mount.journal_commit = mount.commit.clone();
+        }
+    }
+
+    /// Makes every request fail as if the daemon wasn't running, or not.
+    pub fn set_available(&self, available: bool) {
+        self.lock().unavailable = !available;
+    }
+
+    /// The prefetch requests `mount` received so far.
+    pub fn prefetched(&self, mount: &Path) -> Vec<PrefetchOptions> {
+        self.with_mount(mount, |mount| mount.prefetched.clone())
+    }
+}
+
+/// The paths of `path` joined with `/`, as in patterns.
+fn to_slash_string(path: &Path) -> String {
+    path.components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn glob_to_regex(pattern: &str) -> Result<Regex> {
+    let mut re = String::from("^");
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                if chars.peek() == Some(&'/') {
+                    chars.next();
+                    re.push_str("(?:.*/)?");
+                } else {
+                    re.push_str(".*");
+                }
+            }
+            '*' => re.push_str("[^/]*"),
+            '?' => re.push_str("[^/]"),
+            c => re.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    re.push('$');
+    Regex::new(&re).map_err(|e| EdenFsError::Other(anyhow!("invalid glob {}: {}", pattern, e)))
+}
+
+fn glob_files(files: &Files, options: &GlobOptions, patterns: &[Regex]) -> Vec<PathBuf> {
+    let mut candidates: BTreeSet<PathBuf> = files.keys().cloned().collect();
+    if !options.list_only_files {
+        candidates.extend(MockMount::directories(files));
+    }
+    candidates
+        .into_iter()
+        .filter_map(|path| {
+            path.strip_prefix(&options.search_root)
+                .ok()
+                .filter(|path| !path.as_os_str().is_empty())
+                .map(Path::to_path_buf)
+        })
+        .filter(|path| {
+            options.include_dotfiles
+                || !to_slash_string(path)
+                    .split('/')
+                    .any(|name| name.starts_with('.'))
+        })
+        .filter(|path| {
+            let path = to_slash_string(path);
+            patterns.iter().any(|pattern| pattern.is_match(&path))
+        })
+        .collect()
+}
+
+#[async_trait]
+impl EdenFsBackend for MockEdenFs {
+    async fn list_mounts(&self) -> Result<Vec<MountInfo>> {
+        Ok(self
+            .connect()?
+            .mounts
+            .keys()
+            .map(|path| MountInfo {
+                path: path.clone(),
+                data_dir: Path::new("/mock-eden/clients")
+                    .join(path.file_name().unwrap_or_default()),
+                state: MountState::Running,
+                backing_repo: None,
+            })
+            .collect())
+    }
+
+    // Mock checkouts have no ignored files.
+    async fn status(&self, mount: &Path, commit: &[u8], _list_ignored: bool) -> Result<Status> {
+        self.serve(mount, |mount, _| {
+            if mount.commit != commit {
+                return Err(EdenFsError::Other(anyhow!(
+                    "error computing status: requested parent commit is out-of-date: \
+                    requested {}, but current parent commit is {}",
+                    hex::encode(commit),
+                    hex::encode(&mount.commit)
+                )));
+            }
+            let base = &mount.commits[commit];
+            let mut entries = BTreeMap::new();
+            for (path, contents) in &mount.files {
+                match base.get(path) {
+                    None => {
+                        entries.insert(path.clone(), FileStatus::Added);
+                    }
+                    Some(base_contents) if base_contents != contents => {
+                        entries.insert(path.clone(), FileStatus::Modified);
+                    }
+                    Some(_) => {}
+                }
+            }
+            for path in base.keys() {
+                if !mount.files.contains_key(path) {
+                    entries.insert(path.clone(), FileStatus::Removed);
+                }
+            }
+            Ok(Status {
+                entries,
+                errors: BTreeMap::new(),
+            })
+        })
+    }
+
+    async fn glob(&self, mount: &Path, options: &GlobOptions) -> Result<Vec<GlobEntry>> {
+        let patterns = options
+            .patterns
+            .iter()
+            .map(|pattern| glob_to_regex(pattern))
+            .collect::<Result<Vec<_>>>()?;
+        self.serve(mount, |mount, _| {
+            if options.revisions.is_empty() {
+                return Ok(glob_files(&mount.files, options, &patterns)
+                    .into_iter()
+                    .map(|path| GlobEntry { path, commit: None })
+                    .collect());
+            }
+            let mut entries = Vec::new();
+            for revision in &options.revisions {
+                let files = mount.commits.get(revision).ok_or_else(|| {
+                    EdenFsError::Other(anyhow!("unknown commit {}", hex::encode(revision)))
+                })?;
+                entries.extend(
+                    glob_files(files, options, &patterns)
+                        .into_iter()
+                        .map(|path| GlobEntry {
+                            path,
+                            commit: Some(revision.clone()),
+                        }),
+                );
+            }
+            Ok(entries)
+        })
+    }
+
+    async fn prefetch(&self, mount: &Path, options: &PrefetchOptions) -> Result<()> {
+        self.serve(mount, |mount, _| {
+            mount.prefetched.push(options.clone());
+            Ok(())
+        })
+    }
+
+    async fn file_attributes(
+        &self,
+        mount: &Path,
+        paths: &[PathBuf],
+    ) -> Result<Vec<Result<FileAttributes>>> {
+        self.serve(mount, |mount, _| {
+            let directories = MockMount::directories(&mount.files);
+            Ok(paths
+                .iter()
+                .map(|path| match mount.files.get(path) {
+                    Some(contents) => Ok(FileAttributes {
+                        sha1: Sha1::digest(contents).to_vec(),
+                        size: contents.len() as u64,
+                        file_type: FileType::Regular,
+                    }),
+                    None if directories.contains(path) => Err(EdenFsError::Other(anyhow!(
+                        "{}: Is a directory",
+                        path.display()
+                    ))),
+                    None => Err(EdenFsError::Other(anyhow!(
+                        "{}: No such file or directory",
+                        path.display()
+                    ))),
+                })
+                .collect())
+        })
+    }
+
+    async fn journal_position(&self, mount: &Path) -> Result<JournalPosition> {
+        self.serve(mount, |mount, generation| Ok(mount.position(generation)))
+    }
+
+    async fn changes_since(&self, mount: &Path, from: &JournalPosition) -> Result<Changes> {
+        self.serve(mount, |mount, generation| {
+            let start = usize::try_from(from.sequence_number)
+                .ok()
+                .filter(|start| {
+                    from.mount_generation == generation && *start <= mount.journal.len()
+                })
+                .ok_or_else(|| {
+                    EdenFsError::Other(anyhow!("journal position {} is no longer valid", from))
+                })?;
+
+            let mut changed = BTreeSet::new();
+            let mut created = BTreeSet::new();
+            let mut commit_transitions = vec![mount.journal[..start]
+                .iter()
+                .rev()
+                .find_map(|entry| match entry {
+                    JournalEntry::Commit(commit) => Some(commit.clone()),
+                    _ => None,
+                })
+                .unwrap_or_else(|| mount.journal_commit.clone())];
+            for entry in &mount.journal[start..] {
+                match entry {
+                    JournalEntry::Created(path) if !changed.contains(path) => {
+                        created.insert(path.clone());
+                    }
+                    JournalEntry::Changed(path) if !created.contains(path) => {
+                        changed.insert(path.clone());
+                    }
+                    JournalEntry::Commit(commit) => commit_transitions.push(commit.clone()),
+                    _ => {}
+                }
+            }
+            Ok(Changes {
+                from_position: from.clone(),
+                to_position: mount.position(generation),
+                changed_paths: changed.into_iter().collect(),
+                created_paths: created.into_iter().collect(),
+                unclean_paths: Vec::new(),
+                commit_transitions,
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_to_regex() {
+        let matches = |pattern: &str, path: &str| glob_to_regex(pattern).unwrap().is_match(path);
+        assert!(matches("*.rs", "lib.rs"));
+        assert!(!matches("*.rs", "src/lib.rs"));
+        assert!(matches("**/*.rs", "lib.rs"));
+        assert!(matches("**/*.rs", "src/bin/main.rs"));
+        assert!(matches("src/**", "src/bin/main.rs"));
+        assert!(matches("?.txt", "a.txt"));
+        assert!(!matches("?.txt", "ab.txt"));
+        assert!(matches("a+b.txt", "a+b.txt"));
+        assert!(!matches("a+b.txt", "aab.txt"));
+    }
+}
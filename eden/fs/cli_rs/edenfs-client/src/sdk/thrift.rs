@@ -0,0 +1,283 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+use edenfs_error::EdenFsError;
+use edenfs_error::Result;
+use edenfs_error::ResultExt;
+use edenfs_utils::bytes_from_path;
+use edenfs_utils::path_from_bytes;
+use thrift_types::edenfs::types::FileAttributeDataOrError;
+use thrift_types::edenfs::types::FileAttributes as ThriftFileAttributes;
+use thrift_types::edenfs::types::GetAttributesFromFilesParams;
+use thrift_types::edenfs::types::GetScmStatusParams;
+use thrift_types::edenfs::types::GlobParams;
+use thrift_types::edenfs::types::MountState as ThriftMountState;
+use thrift_types::edenfs::types::PrefetchParams;
+use thrift_types::edenfs::types::ScmFileStatus;
+use thrift_types::edenfs::types::SourceControlType;
+
+use super::Changes;
+use super::EdenFsBackend;
+use super::FileAttributes;
+use super::FileStatus;
+use super::FileType;
+use super::GlobEntry;
+use super::GlobOptions;
+use super::MountInfo;
+use super::MountState;
+use super::PrefetchOptions;
+use super::Status;
+use crate::instance::connect_socket;
+use crate::EdenFsClient;
+use crate::JournalPosition;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Talks to an EdenFS daemon over Thrift.
+///
+/// The connection is established on first use. A failed request drops it, and
+/// the next request connects again, which lets users of a long-lived backend
+/// survive daemon restarts.
+pub struct ThriftBackend {
+    socket_path: PathBuf,
+    client: Mutex<Option<EdenFsClient>>,
+}
+
+impl ThriftBackend {
+    pub fn new(socket_path: PathBuf) -> Self {
+        ThriftBackend {
+            socket_path,
+            client: Mutex::new(None),
+        }
+    }
+
+    async fn client(&self) -> Result<EdenFsClient> {
+        if let Some(client) = self.client.lock().expect("poisoned lock").clone() {
+            return Ok(client);
+        }
+        let client = connect_socket(&self.socket_path, Some(CONNECT_TIMEOUT)).await?;
+        *self.client.lock().expect("poisoned lock") = Some(client.clone());
+        Ok(client)
+    }
+
+    /// Converts the result of a Thrift call, dropping the connection if it
+    /// failed.
+    fn check<T, E>(&self, result: std::result::Result<T, E>) -> Result<T>
+    where
+        E: std::error::Error + Send + Sync + 'static,
+    {
+        if result.is_err() {
+            self.client.lock().expect("poisoned lock").take();
+        }
+        result.from_err()
+    }
+}
+
+fn mount_state(state: ThriftMountState) -> MountState {
+    match state {
+        ThriftMountState::RUNNING => MountState::Running,
+        ThriftMountState::SHUTTING_DOWN
+        | ThriftMountState::SHUT_DOWN
+        | ThriftMountState::DESTROYING => MountState::Stopping,
+        ThriftMountState::FUSE_ERROR | ThriftMountState::INIT_ERROR => MountState::Failed,
+        _ => MountState::Starting,
+    }
+}
+
+fn file_status(status: ScmFileStatus) -> Result<FileStatus> {
+    match status {
+        ScmFileStatus::ADDED => Ok(FileStatus::Added),
+        ScmFileStatus::MODIFIED => Ok(FileStatus::Modified),
+        ScmFileStatus::REMOVED => Ok(FileStatus::Removed),
+        ScmFileStatus::IGNORED => Ok(FileStatus::Ignored),
+        status => Err(EdenFsError::Other(anyhow!(
+            "unknown file status {:?}",
+            status
+        ))),
+    }
+}
+
+fn file_type(source_control_type: SourceControlType) -> Result<FileType> {
+    match source_control_type {
+        SourceControlType::TREE => Ok(FileType::Directory),
+        SourceControlType::REGULAR_FILE => Ok(FileType::Regular),
+        SourceControlType::EXECUTABLE_FILE => Ok(FileType::Executable),
+        SourceControlType::SYMLINK => Ok(FileType::Symlink),
+        source_control_type => Err(EdenFsError::Other(anyhow!(
+            "unknown file type {:?}",
+            source_control_type
+        ))),
+    }
+}
+
+fn paths_from_bytes(paths: &[Vec<u8>]) -> Result<Vec<PathBuf>> {
+    paths.iter().map(|path| path_from_bytes(path)).collect()
+}
+
+#[async_trait]
+impl EdenFsBackend for ThriftBackend {
+    async fn list_mounts(&self) -> Result<Vec<MountInfo>> {
+        let client = self.client().await?;
+        let mounts = self.check(client.listMounts().await)?;
+        mounts
+            .into_iter()
+            .map(|mount| {
+                Ok(MountInfo {
+                    path: path_from_bytes(&mount.mountPoint)?,
+                    data_dir: path_from_bytes(&mount.edenClientPath)?,
+                    state: mount_state(mount.state),
+                    backing_repo: mount
+                        .backingRepoPath
+                        .map(|path| path_from_bytes(&path))
+                        .transpose()?,
+                })
+            })
+            .collect()
+    }
+
+    async fn status(&self, mount: &Path, commit: &[u8], list_ignored: bool) -> Result<Status> {
+        let params = GetScmStatusParams {
+            mountPoint: bytes_from_path(mount.to_path_buf())?,
+            commit: commit.to_vec(),
+            listIgnored: list_ignored,
+            ..Default::default()
+        };
+        let client = self.client().await?;
+        let status = self.check(client.getScmStatusV2(&params).await)?.status;
+        Ok(Status {
+            entries: status
+                .entries
+                .into_iter()
+                .map(|(path, status)| Ok((path_from_bytes(&path)?, file_status(status)?)))
+                .collect::<Result<_>>()?,
+            errors: status
+                .errors
+                .into_iter()
+                .map(|(path, error)| Ok((path_from_bytes(&path)?, error)))
+                .collect::<Result<_>>()?,
+        })
+    }
+
+    async fn glob(&self, mount: &Path, options: &GlobOptions) -> Result<Vec<GlobEntry>> {
+        let params = GlobParams {
+            mountPoint: bytes_from_path(mount.to_path_buf())?,
+            globs: options.patterns.clone(),
+            includeDotfiles: options.include_dotfiles,
+            prefetchFiles: false,
+            suppressFileList: false,
+            revisions: options.revisions.clone(),
+            searchRoot: bytes_from_path(options.search_root.clone())?,
+            listOnlyFiles: options.list_only_files,
+            ..Default::default()
+        };
+        let client = self.client().await?;
+        let glob = self.check(client.globFiles(&params).await)?;
+        // Origins are only filled in when globbing commits.
+        let mut origins = glob.originHashes.into_iter();
+        glob.matchingFiles
+            .iter()
+            .map(|path| {
+                Ok(GlobEntry {
+                    path: path_from_bytes(path)?,
+                    commit: origins.next(),
+                })
+            })
+            .collect()
+    }
+
+    async fn prefetch(&self, mount: &Path, options: &PrefetchOptions) -> Result<()> {
+        let params = PrefetchParams {
+            mountPoint: bytes_from_path(mount.to_path_buf())?,
+            globs: options.patterns.clone(),
+            directoriesOnly: options.directories_only,
+            revisions: options.revisions.clone(),
+            background: options.background,
+            ..Default::default()
+        };
+        let client = self.client().await?;
+        self.check(client.prefetchFiles(&params).await)
+    }
+
+    async fn file_attributes(
+        &self,
+        mount: &Path,
+        paths: &[PathBuf],
+    ) -> Result<Vec<Result<FileAttributes>>> {
+        let requested_attributes = ThriftFileAttributes::SHA1_HASH.0
+            | ThriftFileAttributes::FILE_SIZE.0
+            | ThriftFileAttributes::SOURCE_CONTROL_TYPE.0;
+        let params = GetAttributesFromFilesParams {
+            mountPoint: bytes_from_path(mount.to_path_buf())?,
+            paths: paths
+                .iter()
+                .map(|path| bytes_from_path(path.clone()))
+                .collect::<Result<_>>()?,
+            requestedAttributes: requested_attributes.into(),
+            ..Default::default()
+        };
+        let client = self.client().await?;
+        let result = self.check(client.getAttributesFromFiles(&params).await)?;
+        Ok(result
+            .res
+            .into_iter()
+            .zip(paths)
+            .map(|(attributes, path)| match attributes {
+                FileAttributeDataOrError::data(data) => {
+                    let missing =
+                        || EdenFsError::Other(anyhow!("missing attributes for {}", path.display()));
+                    Ok(FileAttributes {
+                        sha1: data.sha1.ok_or_else(missing)?,
+                        size: data.fileSize.ok_or_else(missing)? as u64,
+                        file_type: file_type(data.type_.ok_or_else(missing)?)?,
+                    })
+                }
+                FileAttributeDataOrError::error(e) => Err(EdenFsError::Other(anyhow!(
+                    "{}: {}",
+                    path.display(),
+                    e.message
+                ))),
+                FileAttributeDataOrError::UnknownField(field) => Err(EdenFsError::Other(anyhow!(
+                    "unknown attributes field {} for {}",
+                    field,
+                    path.display()
+                ))),
+            })
+            .collect())
+    }
+
+    async fn journal_position(&self, mount: &Path) -> Result<JournalPosition> {
+        let mount_point = bytes_from_path(mount.to_path_buf())?;
+        let client = self.client().await?;
+        let position = self.check(client.getCurrentJournalPosition(&mount_point).await)?;
+        Ok(position.into())
+    }
+
+    async fn changes_since(&self, mount: &Path, from: &JournalPosition) -> Result<Changes> {
+        let mount_point = bytes_from_path(mount.to_path_buf())?;
+        let client = self.client().await?;
+        let delta = self.check(
+            client
+                .getFilesChangedSince(&mount_point, &from.clone().into())
+                .await,
+        )?;
+        Ok(Changes {
+            changed_paths: paths_from_bytes(&delta.changedPaths)?,
+            created_paths: paths_from_bytes(&delta.createdPaths)?,
+            unclean_paths: paths_from_bytes(&delta.uncleanPaths)?,
+            commit_transitions: delta.snapshotTransitions,
+            from_position: delta.fromPosition.into(),
+            to_position: delta.toPosition.into(),
+        })
+    }
+}
@@ -0,0 +1,137 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+//! edenfsctl changes-since
+
+use std::path::PathBuf;
+
+use anyhow::anyhow;
+use anyhow::Result;
+use async_trait::async_trait;
+use clap::Parser;
+use edenfs_client::checkout::find_checkout;
+use edenfs_client::EdenFsInstance;
+use edenfs_client::JournalPosition;
+use edenfs_utils::bytes_from_path;
+use serde::Serialize;
+use thrift_types::edenfs::types::FileDelta;
+
+use crate::util::expand_path_or_cwd;
+use crate::ExitCode;
+
+#[derive(Parser, Debug)]
+#[clap(
+    about = "List the files that changed in a checkout since a journal position",
+    long_about = "List the files that changed in a checkout since a journal position. \
+    Without a position, print the current one, to be passed to a later invocation."
+)]
+pub struct ChangesSinceCmd {
+    #[clap(help = "Journal position, as printed by a previous invocation")]
+    position: Option<JournalPosition>,
+
+    #[clap(
+        long,
+        parse(try_from_str = expand_path_or_cwd),
+        default_value = "",
+        help = "Path to the checkout (default: the checkout containing cwd)"
+    )]
+    mount: PathBuf,
+
+    #[clap(long, help = "Print the output in JSON format")]
+    json: bool,
+}
+
+#[derive(Debug, Default, Serialize)]
+struct ChangesSince {
+    from_position: JournalPosition,
+    to_position: JournalPosition,
+    changed_paths: Vec<String>,
+    created_paths: Vec<String>,
+    /// Paths whose source control status may have changed across a checkout
+    /// operation.
+    unclean_paths: Vec<String>,
+    /// The commits the checkout went through, oldest first.
+    commit_transitions: Vec<String>,
+}
+
+fn paths_to_strings(paths: &[Vec<u8>]) -> Vec<String> {
+    paths
+        .iter()
+        .map(|path| String::from_utf8_lossy(path).into_owned())
+        .collect()
+}
+
+impl From<FileDelta> for ChangesSince {
+    fn from(from: FileDelta) -> Self {
+        ChangesSince {
+            changed_paths: paths_to_strings(&from.changedPaths),
+            created_paths: paths_to_strings(&from.createdPaths),
+            unclean_paths: paths_to_strings(&from.uncleanPaths),
+            commit_transitions: from.snapshotTransitions.iter().map(hex::encode).collect(),
+            from_position: from.fromPosition.into(),
+            to_position: from.toPosition.into(),
+        }
+    }
+}
+
+impl ChangesSinceCmd {
+    async fn changes_since(&self, instance: &EdenFsInstance) -> Result<ChangesSince> {
+        let checkout = find_checkout(instance, &self.mount)?;
+        let mount_point = bytes_from_path(checkout.path())?;
+        let client = instance.connect(None).await?;
+
+        let position = match &self.position {
+            Some(position) => position.clone(),
+            None => {
+                let current: JournalPosition = client
+                    .getCurrentJournalPosition(&mount_point)
+                    .await
+                    .map_err(|e| anyhow!("Failed to get the journal position: {:#}", e))?
+                    .into();
+                return Ok(ChangesSince {
+                    from_position: current.clone(),
+                    to_position: current,
+                    ..Default::default()
+                });
+            }
+        };
+        let delta = client
+            .getFilesChangedSince(&mount_point, &position.into())
+            .await
+            .map_err(|e| anyhow!("Failed to get the changed files: {:#}", e))?;
+        Ok(delta.into())
+    }
+}
+
+#[async_trait]
+impl crate::Subcommand for ChangesSinceCmd {
+    async fn run(&self) -> Result<ExitCode> {
+        let changes = match self.changes_since(EdenFsInstance::global()).await {
+            Ok(changes) => changes,
+            Err(e) => {
+                eprintln!("error: {:#}", e);
+                return Ok(1);
+            }
+        };
+
+        if self.json {
+            println!("{}", serde_json::to_string_pretty(&changes)?);
+        } else {
+            println!("{}", changes.to_position);
+            for path in &changes.created_paths {
+                println!("A {}", path);
+            }
+            for path in &changes.changed_paths {
+                println!("M {}", path);
+            }
+            for path in &changes.unclean_paths {
+                println!("U {}", path);
+            }
+        }
+        Ok(0)
+    }
+}
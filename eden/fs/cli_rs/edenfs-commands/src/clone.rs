@@ -0,0 +1,436 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+//! edenfsctl clone
+
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Command;
+use std::time::Duration;
+use std::time::Instant;
+
+use anyhow::anyhow;
+use anyhow::Context;
+use anyhow::Result;
+use async_trait::async_trait;
+use clap::Parser;
+use edenfs_client::checkout::find_checkout;
+use edenfs_client::checkout::save_snapshot;
+use edenfs_client::checkout::CheckoutConfig;
+use edenfs_client::DaemonHealthy;
+use edenfs_client::EdenFsInstance;
+use edenfs_utils::bytes_from_path;
+use hg_util::path::expand_path;
+use thrift_types::edenfs::types::MountArgument;
+use thrift_types::fb303_core::types::fb303_status;
+
+use crate::util::mount_point::cleanup_mount_point;
+use crate::util::mount_point::create_mount_point_dir;
+use crate::util::mount_point::resolve_mount_point;
+use crate::util::mount_point::unmount_checkout;
+use crate::ExitCode;
+
+mod hg;
+
+/// Marks a client directory whose checkout finished its first mount.
+const CLONE_SUCCEEDED: &str = "clone-succeeded";
+const NULL_REVISION: &str = "0000000000000000000000000000000000000000";
+const START_TIMEOUT: Duration = Duration::from_secs(600);
+
+#[derive(Parser, Debug)]
+#[clap(about = "Create a clone of a specific repo and check it out")]
+pub struct CloneCmd {
+    #[clap(parse(from_str = expand_path), help = "The path to an existing repository to clone")]
+    repo: PathBuf,
+
+    #[clap(parse(from_str = expand_path), help = "The path where the checkout should be mounted")]
+    path: PathBuf,
+
+    #[clap(short, long, help = "The initial revision to check out")]
+    rev: Option<String>,
+
+    #[clap(
+        short = 'e',
+        long,
+        help = "Allow repo with null revision (no revisions)"
+    )]
+    allow_empty_repo: bool,
+
+    #[clap(
+        short = 'n',
+        long,
+        help = "Allow creation of nested checkout (not recommended)"
+    )]
+    allow_nested_checkout: bool,
+
+    #[clap(
+        long,
+        help = "Mount the checkout with NFS instead of the default for this platform \
+        (see the clone.default-mount-protocol config)"
+    )]
+    nfs: bool,
+
+    #[clap(
+        long,
+        conflicts_with = "case-insensitive",
+        help = "Make the checkout case sensitive (the default on Linux)"
+    )]
+    case_sensitive: bool,
+
+    #[clap(
+        long,
+        help = "Make the checkout case insensitive (the default on macOS and Windows)"
+    )]
+    case_insensitive: bool,
+}
+
+/// The repository a new checkout is backed by.
+struct BackingRepo {
+    path: PathBuf,
+    repo_type: &'static str,
+}
+
+impl BackingRepo {
+    /// Finds the repository containing `path`, which may also be an existing
+    /// checkout whose backing repository should be shared.
+    fn find(instance: &EdenFsInstance, path: &Path) -> Result<Self> {
+        let path = match find_checkout(instance, path)
+            .ok()
+            .and_then(|checkout| checkout.backing_repo())
+        {
+            Some(backing_repo) => backing_repo,
+            None => path.to_path_buf(),
+        };
+        let path = path
+            .canonicalize()
+            .with_context(|| format!("{} does not exist", path.display()))?;
+
+        for ancestor in path.ancestors() {
+            if hg::find_dot_dir(ancestor).is_some() {
+                return Ok(Self {
+                    path: ancestor.to_path_buf(),
+                    repo_type: "hg",
+                });
+            }
+            if ancestor.join(".git").exists() {
+                return Ok(Self {
+                    path: ancestor.to_path_buf(),
+                    repo_type: "git",
+                });
+            }
+        }
+        Err(anyhow!(
+            "{} does not look like a valid repository",
+            path.display()
+        ))
+    }
+
+    fn default_revision(&self) -> &'static str {
+        match self.repo_type {
+            "hg" => "first(present(master) + .)",
+            _ => "refs/heads/master",
+        }
+    }
+
+    fn resolve_commit(&self, rev: &str) -> Result<String> {
+        if self.repo_type == "hg" {
+            return hg::resolve_commit(&self.path, rev);
+        }
+        let output = Command::new("git")
+            .args(["rev-parse", rev])
+            .current_dir(&self.path)
+            .output()
+            .context("Failed to run git")?;
+        if !output.status.success() {
+            return Err(anyhow!(
+                "{}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+}
+
+/// Starts EdenFS if it isn't running, and waits for it to be ready.
+async fn ensure_daemon_running(instance: &EdenFsInstance) -> Result<()> {
+    match instance.get_health(None).await {
+        Ok(health) if health.is_healthy() => return Ok(()),
+        Ok(health) if health.status == Some(fb303_status::STARTING) => {
+            println!("EdenFS daemon is still starting. Waiting for EdenFS to start ...");
+        }
+        _ => {
+            println!("edenfs daemon is not currently running. Starting...");
+            // `start` is handled by the Python CLI, which our binary falls
+            // back to for commands it doesn't implement.
+            let status = Command::new(std::env::current_exe()?)
+                .arg("--config-dir")
+                .arg(instance.config_dir())
+                .arg("--etc-eden-dir")
+                .arg(instance.etc_eden_dir())
+                .arg("start")
+                .status()
+                .context("Failed to run `edenfsctl start`")?;
+            if !status.success() {
+                return Err(anyhow!("`edenfsctl start` failed with {}", status));
+            }
+        }
+    }
+
+    let deadline = Instant::now() + START_TIMEOUT;
+    while Instant::now() < deadline {
+        if let Ok(health) = instance.get_health(None).await {
+            if health.is_healthy() {
+                return Ok(());
+            }
+        }
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+    Err(anyhow!(
+        "EdenFS did not start within {} seconds",
+        START_TIMEOUT.as_secs()
+    ))
+}
+
+impl CloneCmd {
+    fn mount_protocol(&self, instance: &EdenFsInstance) -> &'static str {
+        let default_nfs = instance
+            .get_config()
+            .ok()
+            .and_then(|config| {
+                Some(
+                    config
+                        .other
+                        .get("clone")?
+                        .get("default-mount-protocol")?
+                        .as_str()?
+                        .eq_ignore_ascii_case("nfs"),
+                )
+            })
+            .unwrap_or(false);
+        if self.nfs || default_nfs {
+            "nfs"
+        } else if cfg!(windows) {
+            "prjfs"
+        } else {
+            "fuse"
+        }
+    }
+
+    fn case_sensitive(&self) -> bool {
+        if self.case_sensitive || self.case_insensitive {
+            self.case_sensitive
+        } else {
+            cfg!(target_os = "linux")
+        }
+    }
+
+    /// Checks that the destination is an empty directory or doesn't exist,
+    /// and isn't inside another checkout.
+    fn check_destination(&self, instance: &EdenFsInstance, path: &Path) -> Result<()> {
+        if path.exists() {
+            if !path.is_dir() {
+                return Err(anyhow!(
+                    "destination path {} is not a directory",
+                    path.display()
+                ));
+            }
+            if fs::read_dir(path)?.next().is_some() {
+                return Err(anyhow!("destination path {} is not empty", path.display()));
+            }
+        }
+
+        let existing = path.ancestors().find(|ancestor| ancestor.exists());
+        if let Some(checkout) = existing.and_then(|dir| find_checkout(instance, dir).ok()) {
+            if !self.allow_nested_checkout {
+                return Err(anyhow!(
+                    "destination path {} is within an existing checkout {}.\n\n\
+                    Nested checkouts are usually not intended/recommended and may cause\n\
+                    `eden doctor` and `eden rm` to encounter spurious behavior. If you DO\n\
+                    want nested checkouts, re-run `eden clone` with --allow-nested-checkout or -n.",
+                    path.display(),
+                    checkout.path().display()
+                ));
+            }
+            println!(
+                "Warning: Creating a nested checkout. This is not recommended because it\n\
+                may cause `eden doctor` and `eden rm` to encounter spurious behavior."
+            );
+        }
+        Ok(())
+    }
+
+    fn resolve_commit(&self, repo: &BackingRepo) -> Result<String> {
+        let rev = self
+            .rev
+            .as_deref()
+            .unwrap_or_else(|| repo.default_revision());
+        let commit = repo
+            .resolve_commit(rev)
+            .with_context(|| format!("unable to find hash for commit {:?}", rev))?;
+        if self.rev.is_none() && commit == NULL_REVISION && !self.allow_empty_repo {
+            return Err(anyhow!(
+                "the initial revision that would be checked out is the empty commit\n\n\
+                The repository at {} may still be cloning.\n\
+                Please make sure cloning completes before running `eden clone`\n\
+                If you do want to check out the empty commit,\n\
+                re-run `eden clone` with --allow-empty-repo",
+                repo.path.display()
+            ));
+        }
+        Ok(commit)
+    }
+
+    /// Creates the checkout at `path`. Everything created along the way is
+    /// removed again if a step fails, so a failed clone can simply be retried.
+    async fn clone(
+        &self,
+        instance: &EdenFsInstance,
+        config: &mut CheckoutConfig,
+        path: &Path,
+        commit: &str,
+    ) -> Result<()> {
+        if instance.get_configured_mounts_map()?.contains_key(path) {
+            return Err(anyhow!(
+                "mount path {} is already configured (see `eden list`). \
+                Do you want to run `eden mount {}` instead?",
+                path.display(),
+                path.display()
+            ));
+        }
+
+        create_mount_point_dir(instance, path)?;
+        let client_dir = match instance.create_client_dir(path) {
+            Ok(client_dir) => client_dir,
+            Err(e) => {
+                cleanup_mount_point(path, false).ok();
+                return Err(e.into());
+            }
+        };
+
+        let mut mounted = false;
+        if let Err(e) = self
+            .setup_checkout(instance, config, path, &client_dir, commit, &mut mounted)
+            .await
+        {
+            eprintln!("Clone failed, cleaning up {}", path.display());
+            rollback(instance, path, &client_dir, mounted).await;
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    async fn setup_checkout(
+        &self,
+        instance: &EdenFsInstance,
+        config: &mut CheckoutConfig,
+        path: &Path,
+        client_dir: &Path,
+        commit: &str,
+        mounted: &mut bool,
+    ) -> Result<()> {
+        save_snapshot(client_dir, commit)?;
+        config.save_config(client_dir.to_path_buf())?;
+
+        let client = instance.connect(None).await?;
+        let mount_argument = MountArgument {
+            mountPoint: bytes_from_path(path.to_path_buf())?,
+            edenClientPath: bytes_from_path(client_dir.to_path_buf())?,
+            readOnly: false,
+            ..Default::default()
+        };
+        client
+            .mount(&mount_argument)
+            .await
+            .with_context(|| format!("Failed to mount {}", path.display()))?;
+        *mounted = true;
+
+        if config.repo_type() == "hg" {
+            hg::setup_hg_dir(instance, path, config.backing_repo(), commit)?;
+            fs::write(client_dir.join(CLONE_SUCCEEDED), "")?;
+            hg::run_post_update_hook(path)?;
+        } else {
+            fs::write(client_dir.join(CLONE_SUCCEEDED), "")?;
+        }
+
+        let client_name = client_dir
+            .file_name()
+            .ok_or_else(|| anyhow!("invalid client directory {}", client_dir.display()))?
+            .to_string_lossy();
+        instance.add_path_to_directory_map(path, &client_name)?;
+        Ok(())
+    }
+}
+
+/// Undoes a partially completed clone. Failures are reported but don't stop
+/// the rest of the cleanup.
+async fn rollback(instance: &EdenFsInstance, path: &Path, client_dir: &Path, mounted: bool) {
+    if mounted {
+        let unmount = async { unmount_checkout(&instance.connect(None).await?, path).await };
+        if let Err(e) = unmount.await {
+            eprintln!("warning: failed to unmount {}: {:#}", path.display(), e);
+            // The mount point can't be cleaned up while it is mounted.
+            return;
+        }
+    }
+    if let Err(e) = fs::remove_dir_all(client_dir) {
+        eprintln!("warning: failed to remove {}: {}", client_dir.display(), e);
+    }
+    if let Err(e) = cleanup_mount_point(path, false) {
+        eprintln!("warning: {:#}", e);
+    }
+}
+
+#[async_trait]
+impl crate::Subcommand for CloneCmd {
+    async fn run(&self) -> Result<ExitCode> {
+        let instance = EdenFsInstance::global();
+        let path = resolve_mount_point(&self.path)?;
+        if let Err(e) = self.check_destination(instance, &path) {
+            eprintln!("error: {:#}", e);
+            return Ok(1);
+        }
+
+        let repo = match BackingRepo::find(instance, &self.repo) {
+            Ok(repo) => repo,
+            Err(e) => {
+                eprintln!("error: {:#}", e);
+                return Ok(1);
+            }
+        };
+        let commit = match self.resolve_commit(&repo) {
+            Ok(commit) => commit,
+            Err(e) => {
+                eprintln!("error: {:#}", e);
+                return Ok(1);
+            }
+        };
+        let mut config = CheckoutConfig::new(
+            repo.path.clone(),
+            repo.repo_type,
+            self.mount_protocol(instance),
+            self.case_sensitive(),
+        )?;
+
+        if let Err(e) = ensure_daemon_running(instance).await {
+            eprintln!("error: {:#}", e);
+            return Ok(1);
+        }
+
+        println!("Cloning new repository at {}...", path.display());
+        match self.clone(instance, &mut config, &path, &commit).await {
+            Ok(()) => {
+                println!("Success.  Checked out commit {:.8}", commit);
+                Ok(0)
+            }
+            Err(e) => {
+                eprintln!("error: {:#}", e);
+                Ok(1)
+            }
+        }
+    }
+}
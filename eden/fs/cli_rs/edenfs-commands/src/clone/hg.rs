@@ -0,0 +1,196 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+//! The Mercurial side of a new checkout: a `.hg` directory that shares the
+//! store of the backing repository.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use anyhow::anyhow;
+use anyhow::Context;
+use anyhow::Result;
+use edenfs_client::EdenFsInstance;
+use edenfs_utils::get_environment_suitable_for_subprocess;
+use sha2::Digest;
+use sha2::Sha256;
+
+const DOT_DIRS: &[&str] = &[".hg", ".sl"];
+const DEFAULT_EXTRA_HGRC: &str = "\
+[extensions]
+eden =
+share =
+
+[ui]
+portablefilenames = ignore
+";
+const DIRSTATE_VERSION: u32 = 1;
+const CHECKSUM_MARKER: u8 = 0xFF;
+
+/// A Mercurial command with the environment EdenFS runs it with.
+pub fn hg_command() -> Command {
+    // EDEN_HG_BINARY is set by the integration tests.
+    let mut command =
+        Command::new(std::env::var_os("EDEN_HG_BINARY").unwrap_or_else(|| "hg".into()));
+    command
+        .env_clear()
+        .envs(get_environment_suitable_for_subprocess())
+        .env("HGPLAIN", "1");
+    command
+}
+
+/// The name of the metadata directory of the repository at `repo`, if it is a
+/// Mercurial repository.
+pub fn find_dot_dir(repo: &Path) -> Option<&'static str> {
+    DOT_DIRS
+        .iter()
+        .copied()
+        .find(|dot_dir| repo.join(dot_dir).is_dir())
+}
+
+pub fn resolve_commit(repo: &Path, rev: &str) -> Result<String> {
+    let output = hg_command()
+        .args(["log", "-r", rev, "-T{node}"])
+        .current_dir(repo)
+        .output()
+        .context("Failed to run hg")?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "{}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Creates the `.hg` directory of the checkout at `checkout_path`, with
+/// `commit_id` as its parent.
+pub fn setup_hg_dir(
+    instance: &EdenFsInstance,
+    checkout_path: &Path,
+    backing_repo: &Path,
+    commit_id: &str,
+) -> Result<()> {
+    let dot_dir = find_dot_dir(backing_repo).ok_or_else(|| {
+        anyhow!(
+            "backing repository does not exist: {}",
+            backing_repo.display()
+        )
+    })?;
+    let backing_hg_dir = backing_repo.join(dot_dir);
+    let hg_dir = checkout_path.join(dot_dir);
+    fs::create_dir(&hg_dir).with_context(|| format!("Failed to create {}", hg_dir.display()))?;
+
+    fs::write(hg_dir.join("hgrc"), hgrc(instance, &backing_hg_dir))?;
+    fs::write(hg_dir.join("requires"), requires(&backing_hg_dir))?;
+    // Most of the Mercurial state lives in the backing repository. The
+    // sharedpath intentionally has no trailing newline, like Mercurial's own.
+    fs::write(
+        hg_dir.join("sharedpath"),
+        backing_hg_dir.to_string_lossy().as_bytes(),
+    )?;
+    fs::write(hg_dir.join("shared"), "bookmarks\n")?;
+    fs::write(hg_dir.join("bookmarks"), "")?;
+    // Some shell prompts read the branch even though we don't use branches.
+    fs::write(hg_dir.join("branch"), "default\n")?;
+
+    let parent =
+        hex::decode(commit_id).with_context(|| format!("Invalid commit id {}", commit_id))?;
+    fs::write(hg_dir.join("dirstate"), dirstate(&parent)?)?;
+    Ok(())
+}
+
+fn hgrc(instance: &EdenFsInstance, backing_hg_dir: &Path) -> String {
+    let mut extra_hgrc = instance
+        .get_config()
+        .ok()
+        .and_then(|config| {
+            config
+                .other
+                .get("hg")?
+                .get("extra_hgrc")?
+                .as_str()
+                .map(|hgrc| hgrc.to_string())
+        })
+        .unwrap_or_else(|| DEFAULT_EXTRA_HGRC.to_string());
+    if !extra_hgrc.is_empty() && !extra_hgrc.ends_with('\n') {
+        extra_hgrc.push('\n');
+    }
+    // Repositories aren't required to have an hgrc.
+    let orig_hgrc = fs::read_to_string(backing_hg_dir.join("hgrc")).unwrap_or_default();
+    format!("{}\n{}", orig_hgrc, extra_hgrc)
+}
+
+fn requires(backing_hg_dir: &Path) -> String {
+    let orig_requires = fs::read_to_string(backing_hg_dir.join("requires")).unwrap_or_default();
+    let mut requires: BTreeSet<&str> = orig_requires.lines().collect();
+    requires.insert("eden");
+    // These are specific to the dirstate of the backing repository.
+    requires.remove("sqldirstate");
+    requires.remove("treedirstate");
+    requires.into_iter().collect::<Vec<_>>().join("\n") + "\n"
+}
+
+/// An EdenFS dirstate with `parent` as its only parent and no file entries.
+fn dirstate(parent: &[u8]) -> Result<Vec<u8>> {
+    if parent.len() != 20 {
+        return Err(anyhow!("{} is not a valid commit", hex::encode(parent)));
+    }
+    let mut content = parent.to_vec();
+    content.extend_from_slice(&[0u8; 20]);
+    content.extend_from_slice(&DIRSTATE_VERSION.to_be_bytes());
+    content.push(CHECKSUM_MARKER);
+    let checksum = Sha256::digest(&content);
+    content.extend_from_slice(&checksum);
+    Ok(content)
+}
+
+/// Lets Mercurial run its post-checkout setup in the new checkout.
+pub fn run_post_update_hook(checkout_path: &Path) -> Result<()> {
+    let status = hg_command()
+        .arg("debugedenrunpostupdatehook")
+        .arg("-R")
+        .arg(checkout_path)
+        .status()
+        .context("Failed to run hg")?;
+    if !status.success() {
+        return Err(anyhow!(
+            "`hg debugedenrunpostupdatehook` failed with {}",
+            status
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dirstate() {
+        let data = dirstate(&[0xab; 20]).unwrap();
+        assert_eq!(20 + 20 + 4 + 1 + 32, data.len());
+        assert_eq!(&[0xab; 20], &data[..20]);
+        assert_eq!(&[0, 0, 0, 1, CHECKSUM_MARKER], &data[40..45]);
+        assert_eq!(Sha256::digest(&data[..45])[..], data[45..]);
+
+        assert!(dirstate(&[0xab; 4]).is_err());
+    }
+
+    #[test]
+    fn test_requires() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("requires"),
+            "treedirstate\nstore\nremotefilelog\n",
+        )
+        .unwrap();
+        assert_eq!("eden\nremotefilelog\nstore\n", requires(dir.path()));
+    }
+}
@@ -7,6 +7,7 @@
 
 //! edenfsctl debug subscribe
 
+use std::collections::BTreeSet;
 #[cfg(unix)]
 use std::ffi::OsStr;
 #[cfg(unix)]
@@ -16,14 +17,15 @@ use std::path::Path;
 use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Duration;
-use std::time::Instant;
 
 use anyhow::anyhow;
 use anyhow::Context;
 use anyhow::Result;
 use async_trait::async_trait;
 use clap::Parser;
+use edenfs_client::EdenFsClient;
 use edenfs_client::EdenFsInstance;
+#[cfg(fbcode_build)]
 use futures::StreamExt;
 use serde::Serialize;
 use thrift_types::edenfs as edenfs_thrift;
@@ -35,20 +37,35 @@ use crate::util::jsonrpc::ResponseBuilder;
 use crate::util::locate_repo_root;
 use crate::ExitCode;
 
+/// Lower bound on how often the journal is polled when it can't be streamed.
+#[cfg(not(fbcode_build))]
+const MIN_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
 #[derive(Debug, Serialize)]
 struct SubscribeResponse {
     mount_generation: i64,
     // Thrift somehow generates i64 for unsigned64 type
     sequence_number: i64,
     snapshot_hash: String,
+    /// Paths, relative to the mount point, that changed since the previous
+    /// response.
+    changed_files: BTreeSet<String>,
 }
 
-impl From<edenfs_thrift::JournalPosition> for SubscribeResponse {
-    fn from(from: edenfs_thrift::JournalPosition) -> Self {
+impl From<edenfs_thrift::FileDelta> for SubscribeResponse {
+    fn from(from: edenfs_thrift::FileDelta) -> Self {
+        let changed_files = from
+            .changedPaths
+            .iter()
+            .chain(from.createdPaths.iter())
+            .chain(from.uncleanPaths.iter())
+            .map(|path| String::from_utf8_lossy(path).into_owned())
+            .collect();
         Self {
-            mount_generation: from.mountGeneration,
-            sequence_number: from.sequenceNumber,
-            snapshot_hash: hex::encode(from.snapshotHash),
+            mount_generation: from.toPosition.mountGeneration,
+            sequence_number: from.toPosition.sequenceNumber,
+            snapshot_hash: hex::encode(from.toPosition.snapshotHash),
+            changed_files,
         }
     }
 }
@@ -63,47 +80,130 @@ pub struct SubscribeCmd {
     #[clap(short, long, default_value = "500")]
     /// [Unit: ms] number of milliseconds to wait between events
     throttle: u64,
+
+    #[clap(long)]
+    /// Print each event as a bare JSON object instead of a JSON-RPC response
+    no_json_rpc: bool,
 }
 
 impl SubscribeCmd {
-    async fn _make_notify_event(mount_point: &Vec<u8>) -> ResponseBuilder {
-        let instance = EdenFsInstance::global();
-        let client = match instance.connect(None).await {
-            Ok(client) => client,
+    /// Collects the changes since `position`, and moves `position` past them.
+    async fn changes_since(
+        client: &EdenFsClient,
+        mount_point: &Vec<u8>,
+        position: &mut edenfs_thrift::JournalPosition,
+    ) -> Result<SubscribeResponse> {
+        match client.getFilesChangedSince(mount_point, position).await {
+            Ok(delta) => {
+                *position = delta.toPosition.clone();
+                Ok(SubscribeResponse::from(delta))
+            }
             Err(e) => {
-                return ResponseBuilder::error(&format!(
-                    "error while establishing connection to EdenFS server {e:?}"
-                ));
+                // The journal may have been truncated past `position`: start
+                // over from the current position so the next event succeeds.
+                if let Ok(current) = client.getCurrentJournalPosition(mount_point).await {
+                    *position = current;
+                }
+                Err(anyhow!("error while getting changed files: {e:?}"))
             }
+        }
+    }
+
+    fn format_event(&self, event: Result<SubscribeResponse>) -> Result<Vec<u8>> {
+        let value = match event {
+            Ok(event) => serde_json::to_value(event)
+                .map_err(|e| anyhow!("error while serializing subscription response: {e:?}")),
+            Err(e) => Err(e),
+        };
+        let mut bytes = if self.no_json_rpc {
+            let value = value.unwrap_or_else(|e| serde_json::json!({ "error": e.to_string() }));
+            serde_json::to_vec(&value)?
+        } else {
+            let response = match value {
+                Ok(value) => ResponseBuilder::result(value),
+                Err(e) => ResponseBuilder::error(&e.to_string()),
+            };
+            serde_json::to_vec(&response.build())?
         };
+        bytes.push(b'\n');
+        Ok(bytes)
+    }
 
-        let journal = match client.getCurrentJournalPosition(mount_point).await {
-            Ok(journal) => journal,
-            Err(e) => {
-                return ResponseBuilder::error(&format!(
-                    "error while getting current journal position: {e:?}",
-                ));
+    /// Writes an event each time `notify` fires, at most once per throttle
+    /// period. Changes that arrive while throttled are reported together in
+    /// the next event.
+    async fn emit_events(
+        &self,
+        client: EdenFsClient,
+        mount_point: Vec<u8>,
+        mut position: edenfs_thrift::JournalPosition,
+        notify: Arc<Notify>,
+    ) {
+        let mut stdout = tokio::io::stdout();
+        let throttle = Duration::from_millis(self.throttle);
+        loop {
+            notify.notified().await;
+            let event = Self::changes_since(&client, &mount_point, &mut position).await;
+            match self.format_event(event) {
+                Ok(bytes) => {
+                    if stdout.write_all(&bytes).await.is_err() {
+                        // Nobody is listening anymore.
+                        return;
+                    }
+                    stdout.flush().await.ok();
+                }
+                Err(e) => tracing::error!(?e, "unable to serialize response to JSON"),
             }
-        };
+            tokio::time::sleep(throttle).await;
+        }
+    }
+
+    #[cfg(fbcode_build)]
+    async fn watch_journal(&self, mount_point: &Vec<u8>, notify: &Notify) -> Result<()> {
+        let stream_client = EdenFsInstance::global()
+            .connect_streaming(None)
+            .await
+            .with_context(|| anyhow!("unable to establish Thrift connection to EdenFS server"))?;
+        // TODO: feels weird that this method accepts a `&Vec<u8>` instead of a `&[u8]`.
+        let mut subscription = stream_client.subscribeStreamTemporary(mount_point).await?;
+        tracing::info!("subscription created");
 
-        match serde_json::to_value(SubscribeResponse::from(journal)) {
-            Err(e) => ResponseBuilder::error(&format!(
-                "error while serializing subscription response: {e:?}",
-            )),
-            Ok(serialized) => ResponseBuilder::result(serialized),
+        while let Some(journal) = subscription.next().await {
+            match journal {
+                Ok(_) => notify.notify_one(),
+                Err(e) => tracing::error!(?e, "error while processing subscription"),
+            }
         }
+        Ok(())
     }
-}
 
-#[async_trait]
-impl crate::Subcommand for SubscribeCmd {
+    /// The generated streaming client needs the Rocket transport, which is
+    /// only available in fbcode builds. The socket transport used elsewhere
+    /// can't carry Thrift streams, so poll the journal position instead.
     #[cfg(not(fbcode_build))]
-    async fn run(&self) -> Result<ExitCode> {
-        eprintln!("not supported in non-fbcode build");
-        Ok(1)
+    async fn watch_journal(&self, mount_point: &Vec<u8>, notify: &Notify) -> Result<()> {
+        let client = EdenFsInstance::global()
+            .connect(None)
+            .await
+            .with_context(|| anyhow!("unable to establish Thrift connection to EdenFS server"))?;
+        let mut last = client.getCurrentJournalPosition(mount_point).await?;
+        let mut interval =
+            tokio::time::interval(Duration::from_millis(self.throttle).max(MIN_POLL_INTERVAL));
+        loop {
+            interval.tick().await;
+            let current = client.getCurrentJournalPosition(mount_point).await?;
+            if current.mountGeneration != last.mountGeneration
+                || current.sequenceNumber != last.sequenceNumber
+            {
+                notify.notify_one();
+                last = current;
+            }
+        }
     }
+}
 
-    #[cfg(fbcode_build)]
+#[async_trait]
+impl crate::Subcommand for SubscribeCmd {
     async fn run(&self) -> Result<ExitCode> {
         let mount_point_path = locate_repo_root(&self.mount_point)
             .with_context(|| anyhow!("unable to locate repository root"))?;
@@ -114,54 +214,19 @@ impl crate::Subcommand for SubscribeCmd {
         // SAFETY: paths on Windows are Unicode
         #[cfg(windows)]
         let mount_point = mount_point_path.to_string_lossy().into_owned().into_bytes();
-        let stream_client = EdenFsInstance::global()
-            .connect_streaming(None)
+
+        let client = EdenFsInstance::global()
+            .connect(None)
             .await
             .with_context(|| anyhow!("unable to establish Thrift connection to EdenFS server"))?;
+        let position = client.getCurrentJournalPosition(&mount_point).await?;
+        tracing::info!(?mount_point_path, "watching journal");
 
         let notify = Arc::new(Notify::new());
-
-        tokio::task::spawn({
-            let notify = notify.clone();
-            let mount_point = mount_point.clone();
-
-            async move {
-                let mut stdout = tokio::io::stdout();
-                loop {
-                    notify.notified().await;
-                    let response = Self::_make_notify_event(&mount_point).await.build();
-
-                    match serde_json::to_vec(&response) {
-                        Ok(mut bytes) => {
-                            bytes.push(b'\n');
-                            stdout.write_all(&bytes).await.ok();
-                        }
-                        Err(e) => {
-                            tracing::error!(?e, ?response, "unable to seralize response to JSON");
-                        }
-                    }
-                }
-            }
-        });
-
-        // TODO: feels weird that this method accepts a `&Vec<u8>` instead of a `&[u8]`.
-        let mut subscription = stream_client.subscribeStreamTemporary(&mount_point).await?;
-        tracing::info!(?mount_point_path, "subscription created");
-
-        let mut last = Instant::now();
-        let throttle = Duration::from_millis(self.throttle);
-        while let Some(journal) = subscription.next().await {
-            match journal {
-                Ok(_) => {
-                    if last.elapsed() >= throttle {
-                        notify.notify_one();
-                        last = Instant::now();
-                    }
-                }
-                Err(e) => tracing::error!(?e, "error while processing subscription"),
-            }
+        let emitter = self.emit_events(client, mount_point.clone(), position, notify.clone());
+        tokio::select! {
+            _ = emitter => Ok(0),
+            res = self.watch_journal(&mount_point, &notify) => res.map(|_| 0),
         }
-
-        Ok(0)
     }
 }
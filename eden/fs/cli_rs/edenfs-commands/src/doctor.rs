@@ -0,0 +1,187 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+//! edenfsctl doctor
+
+use std::collections::HashSet;
+use std::path::Path;
+use std::path::PathBuf;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use clap::Parser;
+use colored::Colorize;
+use edenfs_client::checkout::find_checkout;
+use edenfs_client::checkout::get_mounts;
+use edenfs_client::EdenFsInstance;
+use thrift_types::edenfs::types::MountState;
+
+use crate::ExitCode;
+
+mod disk_space;
+mod health;
+mod hg;
+mod problem;
+mod redirections;
+mod stale_mounts;
+mod watchman;
+
+use problem::problem_count;
+use problem::ProblemFixer;
+use problem::UnexpectedCheckError;
+
+#[derive(Parser, Debug)]
+#[clap(about = "Debug and fix issues with EdenFS")]
+pub struct DoctorCmd {
+    #[clap(
+        long,
+        short = 'n',
+        help = "Do not try to fix any issues: only report them."
+    )]
+    dry_run: bool,
+}
+
+impl DoctorCmd {
+    async fn run_checks(&self, fixer: &mut ProblemFixer) -> Result<()> {
+        let instance = EdenFsInstance::global();
+
+        let daemon_problem = health::check_daemon_health(instance).await;
+        let daemon_healthy = daemon_problem.is_none();
+        if let Some(problem) = daemon_problem {
+            if instance.get_configured_mounts_map()?.is_empty() {
+                fixer.using_edenfs = false;
+                return Ok(());
+            }
+            fixer.add_problem(problem).await;
+        }
+
+        match stale_mounts::check_for_stale_mounts() {
+            Ok(Some(problem)) => fixer.add_problem(problem).await,
+            Ok(None) => {}
+            Err(e) => fixer.add_problem(Box::new(UnexpectedCheckError(e))).await,
+        }
+
+        let checkouts = get_mounts(instance).await?;
+        let backing_repos: Vec<PathBuf> = checkouts
+            .values()
+            .filter_map(|checkout| checkout.backing_repo())
+            .collect();
+        for problem in disk_space::check_disk_usage(instance, &backing_repos, daemon_healthy) {
+            fixer.add_problem(problem).await;
+        }
+
+        // Everything below inspects mounted checkouts.
+        if !daemon_healthy {
+            return Ok(());
+        }
+
+        let watch_roots = watchman::get_watch_roots();
+        for (path, checkout) in checkouts {
+            if checkout.state() != Some(MountState::RUNNING) {
+                continue;
+            }
+            println!("Checking {}", path.display());
+            if let Err(e) = self
+                .check_checkout(fixer, &path, watch_roots.as_ref())
+                .await
+            {
+                fixer.add_problem(Box::new(UnexpectedCheckError(e))).await;
+            }
+        }
+        Ok(())
+    }
+
+    async fn check_checkout(
+        &self,
+        fixer: &mut ProblemFixer,
+        path: &Path,
+        watch_roots: Option<&HashSet<PathBuf>>,
+    ) -> Result<()> {
+        // Redirections are only known for checkouts looked up by path.
+        let checkout = find_checkout(EdenFsInstance::global(), path)?;
+
+        for problem in redirections::check_redirections(&checkout)? {
+            fixer.add_problem(problem).await;
+        }
+        if let Some(problem) = hg::check_parents(&checkout)? {
+            fixer.add_problem(problem).await;
+        }
+        if let Some(roots) = watch_roots {
+            if let Some(problem) = watchman::check_watchman_watch(path, roots)? {
+                fixer.add_problem(problem).await;
+            }
+        }
+        Ok(())
+    }
+
+    fn report(&self, fixer: &ProblemFixer) -> ExitCode {
+        if fixer.num_problems == 0 {
+            if fixer.using_edenfs {
+                println!("{}", "No issues detected.".green());
+            } else {
+                println!("EdenFS is not in use.");
+            }
+            return 0;
+        }
+
+        if self.dry_run {
+            println!(
+                "{}",
+                format!(
+                    "Discovered {} during --dry-run",
+                    problem_count(fixer.num_problems)
+                )
+                .yellow()
+            );
+            return 1;
+        }
+
+        if fixer.num_fixed_problems > 0 {
+            println!(
+                "{}",
+                format!(
+                    "Successfully fixed {}.",
+                    problem_count(fixer.num_fixed_problems)
+                )
+                .yellow()
+            );
+        }
+        if fixer.num_failed_fixes > 0 {
+            println!(
+                "{}",
+                format!("Failed to fix {}.", problem_count(fixer.num_failed_fixes)).red()
+            );
+        }
+        if fixer.num_manual_fixes == 1 {
+            println!("{}", "1 issue requires manual attention.".yellow());
+        } else if fixer.num_manual_fixes > 1 {
+            println!(
+                "{}",
+                format!(
+                    "{} issues require manual attention.",
+                    fixer.num_manual_fixes
+                )
+                .yellow()
+            );
+        }
+
+        if fixer.num_fixed_problems == fixer.num_problems {
+            0
+        } else {
+            1
+        }
+    }
+}
+
+#[async_trait]
+impl crate::Subcommand for DoctorCmd {
+    async fn run(&self) -> Result<ExitCode> {
+        let mut fixer = ProblemFixer::new(self.dry_run);
+        self.run_checks(&mut fixer).await?;
+        Ok(self.report(&fixer))
+    }
+}
@@ -0,0 +1,138 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+use std::collections::HashSet;
+use std::path::Path;
+use std::path::PathBuf;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use edenfs_client::EdenFsInstance;
+
+use super::problem::FixableProblem;
+use super::problem::Problem;
+use super::problem::ProblemSeverity;
+use crate::du::format_size;
+
+const ADVICE_USED_RATIO: f64 = 0.90;
+const ERROR_AVAILABLE_BYTES: u64 = 1024 * 1024 * 1024;
+
+/// Checks the free space of the filesystems holding the EdenFS state
+/// directory and of the given backing repositories.
+///
+/// Low disk space can be mitigated by compacting the local store, which needs
+/// a healthy daemon.
+pub fn check_disk_usage(
+    instance: &EdenFsInstance,
+    backing_repos: &[PathBuf],
+    daemon_healthy: bool,
+) -> Vec<Box<dyn Problem>> {
+    let mut problems: Vec<Box<dyn Problem>> = Vec::new();
+    let mut seen_devices = HashSet::new();
+    let paths =
+        std::iter::once(instance.config_dir()).chain(backing_repos.iter().map(|p| p.as_path()));
+    for path in paths {
+        match device_id(path) {
+            Some(device) if seen_devices.insert(device) => {}
+            _ => continue,
+        }
+        let (total, available) = match (fs2::total_space(path), fs2::available_space(path)) {
+            (Ok(total), Ok(available)) if total > 0 => (total, available),
+            _ => continue,
+        };
+
+        let used_ratio = total.saturating_sub(available) as f64 / total as f64;
+        let message = "EdenFS lazily loads your files and needs enough disk space to \
+            store these files when loaded.";
+        let (description, severity) = if available <= ERROR_AVAILABLE_BYTES {
+            (
+                format!(
+                    "{} has only {} available. {}",
+                    path.display(),
+                    format_size(available),
+                    message
+                ),
+                ProblemSeverity::Error,
+            )
+        } else if used_ratio >= ADVICE_USED_RATIO {
+            (
+                format!(
+                    "{} is {:.2}% full. {}",
+                    path.display(),
+                    used_ratio * 100.0,
+                    message
+                ),
+                ProblemSeverity::Advice,
+            )
+        } else {
+            continue;
+        };
+        problems.push(Box::new(LowDiskSpace {
+            description,
+            severity,
+            fixable: daemon_healthy,
+        }));
+    }
+    problems
+}
+
+/// Identifies the filesystem `path` is on, so each one is only checked once.
+#[cfg(unix)]
+fn device_id(path: &Path) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    std::fs::metadata(path).ok().map(|metadata| metadata.dev())
+}
+
+#[cfg(windows)]
+fn device_id(path: &Path) -> Option<PathBuf> {
+    path.exists().then(|| path.components().take(1).collect())
+}
+
+struct LowDiskSpace {
+    description: String,
+    severity: ProblemSeverity,
+    fixable: bool,
+}
+
+impl Problem for LowDiskSpace {
+    fn description(&self) -> String {
+        self.description.clone()
+    }
+
+    fn severity(&self) -> ProblemSeverity {
+        self.severity
+    }
+
+    fn manual_remediation(&self) -> Option<String> {
+        Some("Free up some disk space, or run `edenfsctl gc` once EdenFS is running.".to_string())
+    }
+
+    fn as_fixable(&self) -> Option<&dyn FixableProblem> {
+        if self.fixable {
+            Some(self)
+        } else {
+            None
+        }
+    }
+}
+
+#[async_trait]
+impl FixableProblem for LowDiskSpace {
+    fn dry_run_msg(&self) -> String {
+        "Would clear and compact the EdenFS local store".to_string()
+    }
+
+    fn start_msg(&self) -> String {
+        "Clearing and compacting the EdenFS local store".to_string()
+    }
+
+    async fn perform_fix(&self) -> Result<()> {
+        let client = EdenFsInstance::global().connect(None).await?;
+        client.clearAndCompactLocalStore().await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,124 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+use std::process::Command;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use anyhow::Context;
+use anyhow::Result;
+use async_trait::async_trait;
+use edenfs_client::EdenFsInstance;
+use thrift_types::fb303_core::types::fb303_status;
+
+use super::problem::FixableProblem;
+use super::problem::Problem;
+
+const HEALTH_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Returns the problem with the EdenFS daemon, or `None` if it is healthy.
+pub async fn check_daemon_health(instance: &EdenFsInstance) -> Option<Box<dyn Problem>> {
+    let status = match instance.get_health(Some(HEALTH_TIMEOUT)).await {
+        Ok(info) => info.status,
+        Err(_) => return Some(Box::new(EdenfsNotRunning)),
+    };
+    match status {
+        Some(fb303_status::ALIVE) => None,
+        Some(fb303_status::STARTING) => Some(Box::new(EdenfsStarting)),
+        Some(fb303_status::STOPPING) => Some(Box::new(EdenfsStopping)),
+        Some(fb303_status::DEAD) | Some(fb303_status::STOPPED) => Some(Box::new(EdenfsNotRunning)),
+        status => Some(Box::new(EdenfsUnexpectedStatus(
+            status.map_or_else(|| "unknown".to_string(), |status| status.to_string()),
+        ))),
+    }
+}
+
+struct EdenfsNotRunning;
+
+impl Problem for EdenfsNotRunning {
+    fn description(&self) -> String {
+        "EdenFS is not running.".to_string()
+    }
+
+    fn as_fixable(&self) -> Option<&dyn FixableProblem> {
+        Some(self)
+    }
+}
+
+#[async_trait]
+impl FixableProblem for EdenfsNotRunning {
+    fn dry_run_msg(&self) -> String {
+        "Would start EdenFS".to_string()
+    }
+
+    fn start_msg(&self) -> String {
+        "Starting EdenFS".to_string()
+    }
+
+    async fn perform_fix(&self) -> Result<()> {
+        // `start` is handled by the Python CLI, which our binary falls back
+        // to for commands it doesn't implement.
+        let instance = EdenFsInstance::global();
+        let status = Command::new(std::env::current_exe()?)
+            .arg("--config-dir")
+            .arg(instance.config_dir())
+            .arg("--etc-eden-dir")
+            .arg(instance.etc_eden_dir())
+            .arg("start")
+            .status()
+            .context("Failed to run `edenfsctl start`")?;
+        if !status.success() {
+            return Err(anyhow!("`edenfsctl start` failed with {}", status));
+        }
+        Ok(())
+    }
+}
+
+struct EdenfsStarting;
+
+impl Problem for EdenfsStarting {
+    fn description(&self) -> String {
+        "EdenFS is currently still starting.".to_string()
+    }
+
+    fn manual_remediation(&self) -> Option<String> {
+        Some(
+            "Please wait for edenfs to finish starting.\n\
+            If EdenFS seems to be taking too long to start you can try restarting it\n\
+            with \"eden restart --force\""
+                .to_string(),
+        )
+    }
+}
+
+struct EdenfsStopping;
+
+impl Problem for EdenfsStopping {
+    fn description(&self) -> String {
+        "EdenFS is currently shutting down.".to_string()
+    }
+
+    fn manual_remediation(&self) -> Option<String> {
+        Some(
+            "Either wait for edenfs to exit, or to forcibly kill EdenFS, run:\n\n    \
+            eden stop --kill"
+                .to_string(),
+        )
+    }
+}
+
+struct EdenfsUnexpectedStatus(String);
+
+impl Problem for EdenfsUnexpectedStatus {
+    fn description(&self) -> String {
+        format!("Unexpected health status reported by edenfs: {}", self.0)
+    }
+
+    fn manual_remediation(&self) -> Option<String> {
+        Some("You can try restarting EdenFS by running \"eden restart\"".to_string())
+    }
+}
@@ -0,0 +1,248 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+
+use anyhow::anyhow;
+use anyhow::Context;
+use anyhow::Result;
+use async_trait::async_trait;
+use edenfs_client::checkout::EdenFsCheckout;
+use sha2::Digest;
+use sha2::Sha256;
+
+use super::problem::FixableProblem;
+use super::problem::Problem;
+
+const HASH_LEN: usize = 20;
+const PARENTS_LEN: usize = 2 * HASH_LEN;
+/// A `\xFF` marker followed by the SHA-256 of everything before it.
+const CHECKSUM_LEN: usize = 1 + 32;
+const CHECKSUM_MARKER: u8 = 0xFF;
+
+/// Compares the parent commit EdenFS has for the checkout with the one
+/// recorded in the dirstate.
+pub fn check_parents(checkout: &EdenFsCheckout) -> Result<Option<Box<dyn Problem>>> {
+    let checkout_path = checkout.path();
+    let dirstate_path = match find_dot_dir(&checkout_path) {
+        Some(dot_dir) => dot_dir.join("dirstate"),
+        None => {
+            return Ok(Some(Box::new(MissingDotDir {
+                checkout: checkout_path,
+            })));
+        }
+    };
+
+    let snapshot = match checkout.get_snapshot() {
+        Ok(snapshot) => snapshot.working_copy_parent,
+        Err(e) => {
+            return Ok(Some(Box::new(UnreadableParents(format!(
+                "Failed to read the EdenFS parent commit of {}: {}",
+                checkout_path.display(),
+                e
+            )))));
+        }
+    };
+
+    let data = fs::read(&dirstate_path)
+        .with_context(|| format!("Failed to read {}", dirstate_path.display()))?;
+    let dirstate_parent = match parse_dirstate_parent(&data) {
+        Ok(parent) => parent,
+        Err(e) => {
+            return Ok(Some(Box::new(UnreadableParents(format!(
+                "Failed to parse {}: {}",
+                dirstate_path.display(),
+                e
+            )))));
+        }
+    };
+
+    if dirstate_parent.eq_ignore_ascii_case(&snapshot) {
+        return Ok(None);
+    }
+    Ok(Some(Box::new(MismatchedParents {
+        dirstate_path,
+        dirstate_parent,
+        snapshot,
+    })))
+}
+
+fn find_dot_dir(checkout_path: &Path) -> Option<PathBuf> {
+    [".hg", ".sl"]
+        .iter()
+        .map(|name| checkout_path.join(name))
+        .find(|path| path.is_dir())
+}
+
+fn validate_dirstate(data: &[u8]) -> Result<()> {
+    if data.len() < PARENTS_LEN + CHECKSUM_LEN {
+        return Err(anyhow!("dirstate is truncated"));
+    }
+    let (content, checksum) = data.split_at(data.len() - CHECKSUM_LEN + 1);
+    if content.last() != Some(&CHECKSUM_MARKER) || Sha256::digest(content)[..] != checksum[..] {
+        return Err(anyhow!("dirstate checksum mismatch"));
+    }
+    Ok(())
+}
+
+/// Returns the first parent recorded in the dirstate, as hex.
+fn parse_dirstate_parent(data: &[u8]) -> Result<String> {
+    validate_dirstate(data)?;
+    Ok(hex::encode(&data[..HASH_LEN]))
+}
+
+/// Rewrites the dirstate to have `parent` as its only parent, keeping the
+/// file entries and recomputing the checksum.
+fn set_dirstate_parent(data: &[u8], parent: &[u8]) -> Result<Vec<u8>> {
+    validate_dirstate(data)?;
+    if parent.len() != HASH_LEN {
+        return Err(anyhow!("{} is not a valid commit", hex::encode(parent)));
+    }
+    let mut content = Vec::with_capacity(data.len());
+    content.extend_from_slice(parent);
+    content.extend_from_slice(&[0u8; HASH_LEN]);
+    content.extend_from_slice(&data[PARENTS_LEN..data.len() - CHECKSUM_LEN + 1]);
+    let checksum = Sha256::digest(&content);
+    content.extend_from_slice(&checksum);
+    Ok(content)
+}
+
+struct MissingDotDir {
+    checkout: PathBuf,
+}
+
+impl Problem for MissingDotDir {
+    fn description(&self) -> String {
+        format!(
+            "Missing .hg directory in checkout {}",
+            self.checkout.display()
+        )
+    }
+
+    fn manual_remediation(&self) -> Option<String> {
+        Some("Run `edenfsctl doctor` with the Python CLI to recreate it.".to_string())
+    }
+}
+
+struct UnreadableParents(String);
+
+impl Problem for UnreadableParents {
+    fn description(&self) -> String {
+        self.0.clone()
+    }
+}
+
+struct MismatchedParents {
+    dirstate_path: PathBuf,
+    dirstate_parent: String,
+    snapshot: String,
+}
+
+impl Problem for MismatchedParents {
+    fn description(&self) -> String {
+        format!(
+            "mercurial's parent commit is {}, but Eden's internal parent commit is {}",
+            self.dirstate_parent, self.snapshot
+        )
+    }
+
+    fn as_fixable(&self) -> Option<&dyn FixableProblem> {
+        Some(self)
+    }
+}
+
+#[async_trait]
+impl FixableProblem for MismatchedParents {
+    fn dry_run_msg(&self) -> String {
+        format!(
+            "Would set the parent in {} to {}",
+            self.dirstate_path.display(),
+            self.snapshot
+        )
+    }
+
+    fn start_msg(&self) -> String {
+        format!(
+            "Setting the parent in {} to {}",
+            self.dirstate_path.display(),
+            self.snapshot
+        )
+    }
+
+    async fn perform_fix(&self) -> Result<()> {
+        // Hold the working copy lock, like hg does while writing the
+        // dirstate, so a running hg command can't race the rewrite. Without a
+        // ui.timeout the lock is only tried once.
+        let dot_dir = self
+            .dirstate_path
+            .parent()
+            .ok_or_else(|| anyhow!("{} has no parent", self.dirstate_path.display()))?;
+        let _wlock = repolock::lock_working_copy(&BTreeMap::<&str, &str>::new(), dot_dir)
+            .with_context(|| {
+                format!(
+                    "Failed to lock {}, is an hg command running?",
+                    dot_dir.display()
+                )
+            })?;
+
+        // EdenFS is the source of truth for what is checked out.
+        let parent = hex::decode(&self.snapshot)
+            .with_context(|| format!("Invalid EdenFS parent commit {}", self.snapshot))?;
+        let data = fs::read(&self.dirstate_path)?;
+        let data = set_dirstate_parent(&data, &parent)?;
+
+        let tmp_path = self.dirstate_path.with_extension("doctor.tmp");
+        fs::write(&tmp_path, data)
+            .with_context(|| format!("Failed to write {}", tmp_path.display()))?;
+        fs::rename(&tmp_path, &self.dirstate_path)
+            .with_context(|| format!("Failed to replace {}", self.dirstate_path.display()))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_dirstate(p1: u8, p2: u8) -> Vec<u8> {
+        let mut content = vec![p1; HASH_LEN];
+        content.extend_from_slice(&[p2; HASH_LEN]);
+        // Version 1, followed by one file entry.
+        content.extend_from_slice(&[0, 0, 0, 1]);
+        content.extend_from_slice(&[1, b'n', 0, 0, 0x81, 0xa4, 0, 0, 3]);
+        content.extend_from_slice(b"foo");
+        content.push(CHECKSUM_MARKER);
+        let checksum = Sha256::digest(&content);
+        content.extend_from_slice(&checksum);
+        content
+    }
+
+    #[test]
+    fn test_set_dirstate_parent() {
+        let data = make_dirstate(0xab, 0xcd);
+        assert_eq!("ab".repeat(HASH_LEN), parse_dirstate_parent(&data).unwrap());
+
+        let updated = set_dirstate_parent(&data, &[0x12; HASH_LEN]).unwrap();
+        assert_eq!(make_dirstate(0x12, 0), updated);
+        assert_eq!(
+            "12".repeat(HASH_LEN),
+            parse_dirstate_parent(&updated).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_corrupt_dirstate() {
+        let mut data = make_dirstate(0xab, 0);
+        data[PARENTS_LEN + 5] ^= 1;
+        assert!(parse_dirstate_parent(&data).is_err());
+        assert!(parse_dirstate_parent(&data[..10]).is_err());
+        assert!(set_dirstate_parent(&make_dirstate(0xab, 0), &[1; 4]).is_err());
+    }
+}
@@ -0,0 +1,132 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+use std::io::stdout;
+use std::io::Write;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use colored::Colorize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ProblemSeverity {
+    Advice,
+    Error,
+}
+
+/// An issue found by one of the doctor checks.
+pub trait Problem: Send + Sync {
+    fn description(&self) -> String;
+
+    fn severity(&self) -> ProblemSeverity {
+        ProblemSeverity::Error
+    }
+
+    /// Explains how to fix the problem by hand, for problems that can't be
+    /// fixed automatically.
+    fn manual_remediation(&self) -> Option<String> {
+        None
+    }
+
+    fn as_fixable(&self) -> Option<&dyn FixableProblem> {
+        None
+    }
+}
+
+/// A problem that `edenfsctl doctor` knows how to fix.
+#[async_trait]
+pub trait FixableProblem: Problem {
+    /// What would be done to fix the problem, printed with `--dry-run`.
+    fn dry_run_msg(&self) -> String;
+
+    /// Printed right before attempting the fix.
+    fn start_msg(&self) -> String;
+
+    async fn perform_fix(&self) -> Result<()>;
+}
+
+/// Reports a check that failed to run.
+pub struct UnexpectedCheckError(pub anyhow::Error);
+
+impl Problem for UnexpectedCheckError {
+    fn description(&self) -> String {
+        format!("unexpected error while checking for problems: {:#}", self.0)
+    }
+}
+
+/// Reports the problems found by the checks, and fixes them unless this is a
+/// dry run.
+pub struct ProblemFixer {
+    dry_run: bool,
+    /// Cleared when EdenFS isn't running and no checkouts are configured.
+    pub using_edenfs: bool,
+    pub num_problems: usize,
+    pub num_fixed_problems: usize,
+    pub num_failed_fixes: usize,
+    pub num_manual_fixes: usize,
+}
+
+impl ProblemFixer {
+    pub fn new(dry_run: bool) -> Self {
+        Self {
+            dry_run,
+            using_edenfs: true,
+            num_problems: 0,
+            num_fixed_problems: 0,
+            num_failed_fixes: 0,
+            num_manual_fixes: 0,
+        }
+    }
+
+    pub async fn add_problem(&mut self, problem: Box<dyn Problem>) {
+        self.num_problems += 1;
+        match problem.severity() {
+            ProblemSeverity::Advice => println!("{}", "- Found advice:".yellow()),
+            ProblemSeverity::Error => println!("{}", "- Found problem:".yellow()),
+        }
+        println!("{}", problem.description());
+
+        match problem.as_fixable() {
+            Some(fixable) => self.fix_problem(fixable).await,
+            None => {
+                self.num_manual_fixes += 1;
+                if let Some(remediation) = problem.manual_remediation() {
+                    println!("{}\n", remediation);
+                }
+            }
+        }
+    }
+
+    async fn fix_problem(&mut self, problem: &dyn FixableProblem) {
+        if self.dry_run {
+            println!("{}\n", problem.dry_run_msg());
+            return;
+        }
+
+        print!("{}...", problem.start_msg());
+        stdout().flush().ok();
+        match problem.perform_fix().await {
+            Ok(()) => {
+                println!("{}\n", "fixed".green());
+                self.num_fixed_problems += 1;
+            }
+            Err(e) => {
+                println!("{}", "error".red());
+                println!("Failed to fix problem: {:#}\n", e);
+                self.num_failed_fixes += 1;
+            }
+        }
+    }
+}
+
+pub fn problem_count(num: usize) -> String {
+    if num == 1 {
+        "1 problem".to_string()
+    } else {
+        format!("{} problems", num)
+    }
+}
@@ -0,0 +1,89 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+use std::path::PathBuf;
+
+use anyhow::Context;
+use anyhow::Result;
+use async_trait::async_trait;
+use edenfs_client::checkout::find_checkout;
+use edenfs_client::checkout::EdenFsCheckout;
+use edenfs_client::redirect::get_effective_redirections;
+use edenfs_client::redirect::Redirection;
+use edenfs_client::redirect::RedirectionState;
+use edenfs_client::redirect::RedirectionType;
+use edenfs_client::EdenFsInstance;
+
+use super::problem::FixableProblem;
+use super::problem::Problem;
+
+pub fn check_redirections(checkout: &EdenFsCheckout) -> Result<Vec<Box<dyn Problem>>> {
+    let redirs = get_effective_redirections(checkout).with_context(|| {
+        format!(
+            "Failed to get redirections of {}",
+            checkout.path().display()
+        )
+    })?;
+    Ok(redirs
+        .into_values()
+        .filter(|redir| redir.state != Some(RedirectionState::MatchesConfiguration))
+        .map(|redir| {
+            Box::new(MisconfiguredRedirection {
+                checkout: checkout.path(),
+                redir,
+            }) as Box<dyn Problem>
+        })
+        .collect())
+}
+
+struct MisconfiguredRedirection {
+    checkout: PathBuf,
+    redir: Redirection,
+}
+
+impl Problem for MisconfiguredRedirection {
+    fn description(&self) -> String {
+        format!(
+            "Misconfigured redirection at {} ({})",
+            self.redir.repo_path.display(),
+            self.redir
+                .state
+                .as_ref()
+                .map_or_else(|| "unknown".to_string(), |state| state.to_string())
+        )
+    }
+
+    fn as_fixable(&self) -> Option<&dyn FixableProblem> {
+        Some(self)
+    }
+}
+
+#[async_trait]
+impl FixableProblem for MisconfiguredRedirection {
+    fn dry_run_msg(&self) -> String {
+        format!(
+            "Would fix redirection at {}",
+            self.redir.repo_path.display()
+        )
+    }
+
+    fn start_msg(&self) -> String {
+        format!("Fixing redirection at {}", self.redir.repo_path.display())
+    }
+
+    async fn perform_fix(&self) -> Result<()> {
+        let checkout = find_checkout(EdenFsInstance::global(), &self.checkout)?;
+        if self.redir.redir_type == RedirectionType::Unknown {
+            // There's no configuration to restore, so just get rid of
+            // whatever is mounted there.
+            self.redir.remove_existing(&checkout, false).await?;
+        } else {
+            self.redir.apply(&checkout).await?;
+        }
+        Ok(())
+    }
+}
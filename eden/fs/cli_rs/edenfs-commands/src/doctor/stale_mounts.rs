@@ -0,0 +1,148 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+use std::collections::BTreeSet;
+use std::io::ErrorKind;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Command;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use anyhow::anyhow;
+use anyhow::Result;
+use async_trait::async_trait;
+use edenfs_client::mounttable::read_mount_table;
+use edenfs_client::mounttable::MountTableInfo;
+
+use super::problem::FixableProblem;
+use super::problem::Problem;
+
+// Returned when accessing an NFS mount whose server has gone away.
+const ENXIO: i32 = 6;
+
+pub fn check_for_stale_mounts() -> Result<Option<Box<dyn Problem>>> {
+    let stale_mounts = get_stale_eden_mount_points()?;
+    if stale_mounts.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(Box::new(StaleMountsFound {
+        mounts: stale_mounts,
+    })))
+}
+
+fn is_edenfs_mount_device(device: &str) -> bool {
+    device == "eden" || device == "edenfs" || device.starts_with("edenfs:")
+}
+
+fn is_eden_mount(mount: &MountTableInfo) -> bool {
+    is_edenfs_mount_device(mount.device())
+        && matches!(
+            mount.vfstype(),
+            "fuse" | "macfuse_eden" | "nfs" | "edenfs:" | "osxfuse_eden"
+        )
+}
+
+/// Whether the daemon serving `mount_point` has gone away.
+fn is_stale(mount_point: &Path) -> bool {
+    // Even if the daemon is gone, stat() can succeed on a cached entry, so look
+    // up a name that can't be in the kernel's caches.
+    let nonce = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.subsec_nanos());
+    let probe = mount_point
+        .join(".eden")
+        .join(format!("doctor-probe-{:x}", nonce));
+    match std::fs::symlink_metadata(probe) {
+        Ok(_) => false,
+        Err(e) => e.kind() == ErrorKind::NotConnected || e.raw_os_error() == Some(ENXIO),
+    }
+}
+
+fn get_stale_eden_mount_points() -> Result<Vec<PathBuf>> {
+    let stale: BTreeSet<PathBuf> = read_mount_table()?
+        .iter()
+        .filter(|mount| is_eden_mount(mount))
+        .map(|mount| mount.mount_point())
+        .filter(|mount_point| is_stale(mount_point))
+        .collect();
+    Ok(stale.into_iter().collect())
+}
+
+fn unmount(mount_point: &Path, flag: &str) -> bool {
+    Command::new("sudo")
+        .arg("umount")
+        .arg(flag)
+        .arg(mount_point)
+        .status()
+        .map_or(false, |status| status.success())
+}
+
+struct StaleMountsFound {
+    mounts: Vec<PathBuf>,
+}
+
+impl StaleMountsFound {
+    fn mounts_str(&self) -> String {
+        if self.mounts.len() == 1 {
+            "1 stale edenfs mount".to_string()
+        } else {
+            format!("{} stale edenfs mounts", self.mounts.len())
+        }
+    }
+}
+
+impl Problem for StaleMountsFound {
+    fn description(&self) -> String {
+        let mounts: Vec<String> = self
+            .mounts
+            .iter()
+            .map(|mount| mount.display().to_string())
+            .collect();
+        format!("Found {}:\n  {}", self.mounts_str(), mounts.join("\n  "))
+    }
+
+    fn as_fixable(&self) -> Option<&dyn FixableProblem> {
+        Some(self)
+    }
+}
+
+#[async_trait]
+impl FixableProblem for StaleMountsFound {
+    fn dry_run_msg(&self) -> String {
+        format!("Would unmount {}", self.mounts_str())
+    }
+
+    fn start_msg(&self) -> String {
+        format!("Unmounting {}", self.mounts_str())
+    }
+
+    async fn perform_fix(&self) -> Result<()> {
+        // A lazy unmount can also release the bind mounts inside a checkout,
+        // so try that first. macOS has no lazy unmount.
+        if cfg!(target_os = "linux") {
+            for mount in &self.mounts {
+                unmount(mount, "-l");
+            }
+        }
+
+        let failed: Vec<String> = get_stale_eden_mount_points()?
+            .into_iter()
+            .filter(|mount| !unmount(mount, "-f"))
+            .map(|mount| mount.display().to_string())
+            .collect();
+        if !failed.is_empty() {
+            return Err(anyhow!(
+                "Failed to unmount {} mount point{}:\n  {}",
+                failed.len(),
+                if failed.len() == 1 { "" } else { "s" },
+                failed.join("\n  ")
+            ));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,119 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+use std::collections::HashSet;
+use std::path::Path;
+use std::path::PathBuf;
+
+use anyhow::anyhow;
+use anyhow::Result;
+use async_trait::async_trait;
+use serde_json::Value;
+use subprocess::Exec;
+use subprocess::Redirection;
+
+use super::problem::FixableProblem;
+use super::problem::Problem;
+
+fn call_watchman(args: &[&str]) -> Result<Value> {
+    let output = Exec::cmd("watchman")
+        .args(args)
+        .stdout(Redirection::Pipe)
+        .stderr(Redirection::Pipe)
+        .capture()?;
+    if !output.success() {
+        return Err(anyhow!(
+            "`watchman {}` failed: {}",
+            args.join(" "),
+            output.stderr_str()
+        ));
+    }
+    Ok(serde_json::from_slice(&output.stdout)?)
+}
+
+/// The roots watched by watchman, or `None` if watchman isn't available.
+pub fn get_watch_roots() -> Option<HashSet<PathBuf>> {
+    let watch_list = call_watchman(&["watch-list"]).ok()?;
+    Some(
+        watch_list
+            .get("roots")?
+            .as_array()?
+            .iter()
+            .filter_map(|root| root.as_str().map(PathBuf::from))
+            .collect(),
+    )
+}
+
+fn get_watcher(path: &Path) -> Result<Option<String>> {
+    let path = path.to_string_lossy();
+    let details = call_watchman(&["watch-project", &path])?;
+    Ok(details
+        .get("watcher")
+        .and_then(|watcher| watcher.as_str())
+        .map(|watcher| watcher.to_string()))
+}
+
+/// Checks that watchman uses the EdenFS watcher for the checkout, if it is
+/// watching it at all.
+pub fn check_watchman_watch(
+    checkout: &Path,
+    roots: &HashSet<PathBuf>,
+) -> Result<Option<Box<dyn Problem>>> {
+    if !roots.contains(checkout) {
+        return Ok(None);
+    }
+    let watcher = get_watcher(checkout)?;
+    if watcher.as_deref() == Some("eden") {
+        return Ok(None);
+    }
+    Ok(Some(Box::new(IncorrectWatchmanWatch {
+        path: checkout.to_path_buf(),
+        watcher: watcher.unwrap_or_else(|| "unknown".to_string()),
+    })))
+}
+
+struct IncorrectWatchmanWatch {
+    path: PathBuf,
+    watcher: String,
+}
+
+impl Problem for IncorrectWatchmanWatch {
+    fn description(&self) -> String {
+        format!(
+            "Watchman is watching {} with the wrong watcher type: \"{}\" instead of \"eden\"",
+            self.path.display(),
+            self.watcher
+        )
+    }
+
+    fn as_fixable(&self) -> Option<&dyn FixableProblem> {
+        Some(self)
+    }
+}
+
+#[async_trait]
+impl FixableProblem for IncorrectWatchmanWatch {
+    fn dry_run_msg(&self) -> String {
+        format!("Would fix watchman watch for {}", self.path.display())
+    }
+
+    fn start_msg(&self) -> String {
+        format!("Fixing watchman watch for {}", self.path.display())
+    }
+
+    async fn perform_fix(&self) -> Result<()> {
+        // Re-establish the watch, which should now pick the EdenFS watcher.
+        call_watchman(&["watch-del", &self.path.to_string_lossy()])?;
+        if get_watcher(&self.path)?.as_deref() != Some("eden") {
+            return Err(anyhow!(
+                "Failed to replace watchman watch for {} with an \"eden\" watcher",
+                self.path.display()
+            ));
+        }
+        Ok(())
+    }
+}
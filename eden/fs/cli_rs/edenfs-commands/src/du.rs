@@ -108,7 +108,7 @@ impl AggregatedUsageCounts {
     }
 }
 
-fn format_size(size: u64) -> String {
+pub fn format_size(size: u64) -> String {
     if size > 1000000000 {
         format!("{:.1} GB", size as f64 / 1000000000.0)
     } else if size > 1000000 {
@@ -230,7 +230,7 @@ fn ignored_io_error(error: &std::io::Error) -> bool {
         || error.kind() == std::io::ErrorKind::PermissionDenied
 }
 
-fn usage_for_dir(path: &Path, device_id: Option<u64>) -> std::io::Result<(u64, Vec<PathBuf>)> {
+pub fn usage_for_dir(path: &Path, device_id: Option<u64>) -> std::io::Result<(u64, Vec<PathBuf>)> {
     let device_id = match device_id {
         Some(device_id) => device_id,
         None => match fs::metadata(&path) {
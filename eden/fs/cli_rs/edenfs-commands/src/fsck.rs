@@ -0,0 +1,180 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+//! edenfsctl fsck
+
+use std::path::Path;
+use std::path::PathBuf;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use clap::Parser;
+use edenfs_client::checkout::find_checkout;
+use edenfs_client::EdenFsInstance;
+
+use crate::ExitCode;
+
+mod checker;
+mod overlay;
+
+use checker::ErrorLevel;
+use checker::FilesystemChecker;
+use checker::FsckError;
+
+const EXIT_OK: ExitCode = 0;
+const EXIT_SKIPPED: ExitCode = 1;
+const EXIT_WARNINGS: ExitCode = 2;
+const EXIT_ERRORS: ExitCode = 3;
+
+#[derive(Parser, Debug)]
+#[clap(about = "Perform a filesystem check for EdenFS")]
+pub struct FsckCmd {
+    #[clap(
+        long,
+        help = "Force fsck to scan for errors even on checkouts that appear to \
+        currently be mounted.  It will not attempt to fix any problems, but will \
+        only scan and report possible issues."
+    )]
+    force: bool,
+
+    #[clap(
+        short = 'n',
+        long,
+        help = "Only report errors, and do not attempt to fix any problems found."
+    )]
+    check_only: bool,
+
+    #[clap(
+        short,
+        long,
+        help = "Print more verbose information about issues found."
+    )]
+    verbose: bool,
+
+    #[clap(
+        name = "CHECKOUT_PATH",
+        help = "The path to an EdenFS checkout to verify."
+    )]
+    paths: Vec<PathBuf>,
+}
+
+impl FsckCmd {
+    fn check_explicit_paths(&self, instance: &EdenFsInstance) -> Result<Vec<ExitCode>> {
+        let mut return_codes = Vec::new();
+        for path in self.paths.iter() {
+            // The path may be the state directory of a checkout rather than
+            // the checkout itself.
+            let result =
+                if path.join("local").join("info").exists() && path.join("config.toml").exists() {
+                    self.check_one(path, path)?
+                } else {
+                    let checkout = find_checkout(instance, path)?;
+                    self.check_one(&checkout.path(), &checkout.data_dir())?
+                };
+            return_codes.push(result);
+        }
+        Ok(return_codes)
+    }
+
+    fn check_all(&self, instance: &EdenFsInstance) -> Result<Vec<ExitCode>> {
+        let mut return_codes = Vec::new();
+        for (path, name) in instance.get_configured_mounts_map()? {
+            return_codes.push(self.check_one(&path, &instance.config_directory(&name))?);
+        }
+        Ok(return_codes)
+    }
+
+    fn check_one(&self, checkout_path: &Path, state_dir: &Path) -> Result<ExitCode> {
+        let mut checker = FilesystemChecker::new(state_dir);
+        if !checker.is_locked() {
+            if self.force {
+                println!(
+                    "warning: could not obtain lock on {}, but scanning anyway due to --force",
+                    checkout_path.display()
+                );
+            } else {
+                println!(
+                    "Not checking {}: mount is currently in use",
+                    checkout_path.display()
+                );
+                return Ok(EXIT_SKIPPED);
+            }
+        }
+
+        println!("Checking {}...", checkout_path.display());
+        checker.scan_for_errors()?;
+        if checker.errors.is_empty() {
+            println!("  No issues found");
+            return Ok(EXIT_OK);
+        }
+
+        let mut num_warnings = 0;
+        let mut num_errors = 0;
+        for error in checker.errors.iter() {
+            self.report_error(error);
+            match error.level() {
+                ErrorLevel::Warning => num_warnings += 1,
+                ErrorLevel::Error => num_errors += 1,
+            }
+        }
+
+        if num_warnings > 0 {
+            println!("  {} warnings", num_warnings);
+        }
+        println!("  {} errors", num_errors);
+
+        if self.check_only {
+            println!("Not fixing errors: --check-only was specified");
+        } else if !checker.is_locked() {
+            println!("Not fixing errors: checkout is currently in use");
+        } else {
+            checker.fix_errors()?;
+        }
+
+        Ok(if num_errors == 0 {
+            EXIT_WARNINGS
+        } else {
+            EXIT_ERRORS
+        })
+    }
+
+    fn report_error(&self, error: &FsckError) {
+        println!("{}: {}", error.level(), error);
+        if self.verbose {
+            if let Some(details) = error.detailed_description() {
+                for line in details.lines() {
+                    println!("  {}", line);
+                }
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl crate::Subcommand for FsckCmd {
+    async fn run(&self) -> Result<ExitCode> {
+        if cfg!(windows) {
+            eprintln!("`edenfsctl fsck` is not supported on Windows.");
+            eprintln!("If you are looking to fix your EdenFS mount, try `edenfsctl doctor`.");
+            return Ok(1);
+        }
+
+        let instance = EdenFsInstance::global();
+        let return_codes = if self.paths.is_empty() {
+            let return_codes = self.check_all(instance)?;
+            if return_codes.is_empty() {
+                eprintln!("No EdenFS checkouts are configured.  Nothing to check.");
+                return Ok(EXIT_OK);
+            }
+            return_codes
+        } else {
+            self.check_explicit_paths(instance)?
+        };
+
+        Ok(return_codes.into_iter().max().unwrap_or(EXIT_OK))
+    }
+}
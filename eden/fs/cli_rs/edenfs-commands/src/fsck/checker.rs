@@ -0,0 +1,884 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+//! Consistency checks and repairs of an overlay.
+
+use std::collections::BTreeMap;
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use anyhow::anyhow;
+use anyhow::bail;
+use anyhow::Result;
+use chrono::DateTime;
+use chrono::Local;
+
+use super::overlay::parse_dir;
+use super::overlay::InodeType;
+use super::overlay::Overlay;
+use super::overlay::ROOT_INODE_NUMBER;
+use super::overlay::S_IFDIR;
+use super::overlay::S_IFLNK;
+use super::overlay::S_IFMT;
+use super::overlay::S_IFREG;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ErrorLevel {
+    /// Issues that don't affect reading file or directory contents.
+    Warning,
+    /// Issues that prevent reading file or directory contents.
+    Error,
+}
+
+impl fmt::Display for ErrorLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorLevel::Warning => write!(f, "warning"),
+            ErrorLevel::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// An entry in the overlay data of a directory.
+struct ChildInfo {
+    inode_number: u64,
+    name: String,
+    mode: u32,
+    hash: Option<Vec<u8>>,
+}
+
+impl ChildInfo {
+    /// Materialized entries have no source control hash.
+    fn is_materialized(&self) -> bool {
+        self.hash.as_ref().map_or(true, |hash| hash.is_empty())
+    }
+}
+
+struct InodeInfo {
+    /// `Err` if the overlay file could not be read.
+    inode_type: Result<InodeType, String>,
+    children: Vec<ChildInfo>,
+    /// Modification time of the overlay file, to help find out when a
+    /// problem was introduced.
+    mtime: Option<SystemTime>,
+    /// Directories listing this inode, with the name it has there.
+    parents: Vec<(u64, String)>,
+}
+
+pub enum FsckError {
+    UnexpectedOverlayFile {
+        path: PathBuf,
+        mtime: Option<SystemTime>,
+    },
+    /// A materialized child of a directory has no overlay data.
+    MissingMaterializedInode {
+        inode_number: u64,
+        path: String,
+        mode: u32,
+    },
+    InvalidMaterializedInode {
+        inode_number: u64,
+        path: String,
+        expected_type: Option<InodeType>,
+        mtime: Option<SystemTime>,
+        error: String,
+    },
+    /// A file has overlay data, but its parent still refers to it by hash.
+    MaterializedWithHash {
+        inode_number: u64,
+        path: String,
+    },
+    BadMode {
+        inode_number: u64,
+        path: String,
+        mode: u32,
+        inode_type: Option<InodeType>,
+    },
+    HardLinkedInode {
+        inode_number: u64,
+        paths: Vec<String>,
+    },
+    OrphanInodes {
+        directories: Vec<(u64, Option<SystemTime>)>,
+        files: Vec<(u64, Option<SystemTime>)>,
+    },
+    MissingNextInodeNumber {
+        next_inode_number: u64,
+    },
+    BadNextInodeNumber {
+        read: u64,
+        next_inode_number: u64,
+    },
+    CorruptNextInodeNumber {
+        error: String,
+        next_inode_number: u64,
+    },
+}
+
+impl FsckError {
+    pub fn level(&self) -> ErrorLevel {
+        match self {
+            FsckError::UnexpectedOverlayFile { .. }
+            | FsckError::MaterializedWithHash { .. }
+            | FsckError::HardLinkedInode { .. }
+            | FsckError::OrphanInodes { .. }
+            | FsckError::MissingNextInodeNumber { .. }
+            | FsckError::CorruptNextInodeNumber { .. } => ErrorLevel::Warning,
+            FsckError::MissingMaterializedInode { .. }
+            | FsckError::InvalidMaterializedInode { .. }
+            | FsckError::BadMode { .. }
+            | FsckError::BadNextInodeNumber { .. } => ErrorLevel::Error,
+        }
+    }
+
+    pub fn detailed_description(&self) -> Option<String> {
+        match self {
+            FsckError::OrphanInodes { directories, files } => {
+                let mut lines = Vec::new();
+                for (kind, inodes) in [("directory", directories), ("file", files)] {
+                    if inodes.is_empty() {
+                        continue;
+                    }
+                    lines.push(format!("Orphan {} inodes", kind));
+                    for (inode_number, mtime) in inodes {
+                        lines.push(format!("  {}{}", inode_number, mtime_str(*mtime)));
+                    }
+                }
+                Some(lines.join("\n"))
+            }
+            _ => None,
+        }
+    }
+
+    /// Try to fix the problem. Returns false if there is no automatic fix.
+    fn repair(&self, log: &mut RepairLog, overlay: &Overlay, fsck_dir: &Path) -> Result<bool> {
+        match self {
+            FsckError::MissingMaterializedInode {
+                inode_number,
+                path,
+                mode,
+            } => {
+                if mode & S_IFMT == S_IFDIR {
+                    log.info(format!(
+                        "replacing missing directory '{}' with an empty directory",
+                        path
+                    ))?;
+                    overlay.write_empty_dir(*inode_number)?;
+                } else {
+                    log.info(format!(
+                        "replacing missing file '{}' with an empty file",
+                        path
+                    ))?;
+                    overlay.write_empty_file(*inode_number)?;
+                }
+            }
+            FsckError::InvalidMaterializedInode {
+                inode_number,
+                path,
+                expected_type,
+                ..
+            } => {
+                let backup_dir = fsck_dir.join("broken_inodes");
+                fs::create_dir_all(&backup_dir)?;
+                fs::rename(
+                    overlay.inode_path(*inode_number),
+                    backup_dir.join(inode_number.to_string()),
+                )?;
+                if *expected_type == Some(InodeType::Dir) {
+                    log.info(format!(
+                        "replacing corrupt directory inode '{}' with an empty directory",
+                        path
+                    ))?;
+                    overlay.write_empty_dir(*inode_number)?;
+                } else {
+                    log.info(format!(
+                        "replacing corrupt file inode '{}' with an empty file",
+                        path
+                    ))?;
+                    overlay.write_empty_file(*inode_number)?;
+                }
+            }
+            FsckError::OrphanInodes { directories, files } => {
+                let lost_and_found = fsck_dir.join("lost+found");
+                fs::create_dir_all(&lost_and_found)?;
+                log.info(format!(
+                    "moving orphan inodes to {}",
+                    lost_and_found.display()
+                ))?;
+                for (inode_number, _) in directories {
+                    log.info(format!(
+                        "moving contents of orphan directory {} to lost+found",
+                        inode_number
+                    ))?;
+                    overlay.extract_dir(
+                        *inode_number,
+                        &lost_and_found.join(inode_number.to_string()),
+                    )?;
+                }
+                for (inode_number, _) in files {
+                    log.info(format!("moving orphan file {} to lost+found", inode_number))?;
+                    overlay.extract_file(
+                        *inode_number,
+                        &lost_and_found.join(inode_number.to_string()),
+                        S_IFREG | 0o644,
+                    )?;
+                }
+            }
+            FsckError::MissingNextInodeNumber { next_inode_number }
+            | FsckError::BadNextInodeNumber {
+                next_inode_number, ..
+            }
+            | FsckError::CorruptNextInodeNumber {
+                next_inode_number, ..
+            } => {
+                log.info(format!(
+                    "setting max inode number data to {}",
+                    next_inode_number
+                ))?;
+                overlay.write_next_inode_number(*next_inode_number)?;
+            }
+            FsckError::UnexpectedOverlayFile { .. }
+            | FsckError::MaterializedWithHash { .. }
+            | FsckError::BadMode { .. }
+            | FsckError::HardLinkedInode { .. } => {
+                log.info("no automatic remediation available for this error")?;
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+}
+
+impl fmt::Display for FsckError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FsckError::UnexpectedOverlayFile { path, mtime } => write!(
+                f,
+                "unexpected file present in overlay: {}{}",
+                path.display(),
+                mtime_str(*mtime)
+            ),
+            FsckError::MissingMaterializedInode {
+                inode_number,
+                path,
+                mode,
+            } => write!(
+                f,
+                "missing overlay file for materialized {} inode {} ({}) with file mode {:#o}",
+                file_type_name(*mode),
+                inode_number,
+                path,
+                mode
+            ),
+            FsckError::InvalidMaterializedInode {
+                inode_number,
+                path,
+                expected_type,
+                mtime,
+                error,
+            } => {
+                let type_str = match expected_type {
+                    None => "inode",
+                    Some(InodeType::Dir) => "directory inode",
+                    Some(InodeType::File) => "file inode",
+                };
+                write!(
+                    f,
+                    "invalid overlay file for materialized {} {} ({}){}: {}",
+                    type_str,
+                    inode_number,
+                    path,
+                    mtime_str(*mtime),
+                    error
+                )
+            }
+            FsckError::MaterializedWithHash { inode_number, path } => write!(
+                f,
+                "file inode {} ({}) is materialized in the overlay but its parent \
+                refers to it by source control hash",
+                inode_number, path
+            ),
+            FsckError::BadMode {
+                inode_number,
+                path,
+                mode,
+                inode_type,
+            } => match inode_type {
+                Some(inode_type) => write!(
+                    f,
+                    "inode {} ({}) has file mode {:#o} but is a {} in the overlay",
+                    inode_number,
+                    path,
+                    mode,
+                    match inode_type {
+                        InodeType::Dir => "directory",
+                        InodeType::File => "file",
+                    }
+                ),
+                None => write!(
+                    f,
+                    "inode {} ({}) has invalid file mode {:#o}",
+                    inode_number, path, mode
+                ),
+            },
+            FsckError::HardLinkedInode {
+                inode_number,
+                paths,
+            } => write!(
+                f,
+                "inode {} exists in multiple locations: {:?}",
+                inode_number, paths
+            ),
+            FsckError::OrphanInodes { directories, files } => {
+                if !directories.is_empty() && !files.is_empty() {
+                    write!(
+                        f,
+                        "found {} orphan directory inodes and {} orphan file inodes",
+                        directories.len(),
+                        files.len()
+                    )
+                } else if !directories.is_empty() {
+                    write!(f, "found {} orphan directory inodes", directories.len())
+                } else {
+                    write!(f, "found {} orphan file inodes", files.len())
+                }
+            }
+            // EdenFS removes the file while the checkout is mounted, so this
+            // just means that it was not unmounted cleanly.
+            FsckError::MissingNextInodeNumber { .. } => {
+                write!(f, "edenfs appears to have been shut down uncleanly")
+            }
+            FsckError::BadNextInodeNumber {
+                read,
+                next_inode_number,
+            } => write!(
+                f,
+                "bad stored next inode number: read {} but should be at least {}",
+                read, next_inode_number
+            ),
+            FsckError::CorruptNextInodeNumber { error, .. } => {
+                write!(f, "stored next-inode-number file is corrupt: {}", error)
+            }
+        }
+    }
+}
+
+fn file_type_name(mode: u32) -> &'static str {
+    match mode & S_IFMT {
+        S_IFDIR => "directory",
+        S_IFLNK => "symlink",
+        _ => "file",
+    }
+}
+
+fn mtime_str(mtime: Option<SystemTime>) -> String {
+    match mtime {
+        Some(mtime) => format!(
+            ", with mtime {}",
+            DateTime::<Local>::from(mtime).format("%a %b %e %H:%M:%S %Y")
+        ),
+        None => String::new(),
+    }
+}
+
+/// Writes repair progress to `fsck.log` and stdout.
+struct RepairLog {
+    file: File,
+}
+
+impl RepairLog {
+    fn info(&mut self, message: impl AsRef<str>) -> Result<()> {
+        println!("{}", message.as_ref());
+        self.debug(message)
+    }
+
+    fn debug(&mut self, message: impl AsRef<str>) -> Result<()> {
+        writeln!(
+            self.file,
+            "{} {}",
+            Local::now().format("%Y-%m-%d %H:%M:%S"),
+            message.as_ref()
+        )?;
+        Ok(())
+    }
+}
+
+pub struct FilesystemChecker {
+    state_dir: PathBuf,
+    overlay: Overlay,
+    /// Held while EdenFS is not using the checkout.
+    lock: Option<File>,
+    inodes: BTreeMap<u64, InodeInfo>,
+    max_inode_number: u64,
+    pub errors: Vec<FsckError>,
+}
+
+impl FilesystemChecker {
+    pub fn new(state_dir: &Path) -> Self {
+        let overlay = Overlay::new(state_dir.join("local"));
+        let lock = overlay.try_lock();
+        Self {
+            state_dir: state_dir.to_path_buf(),
+            overlay,
+            lock,
+            inodes: BTreeMap::new(),
+            max_inode_number: 0,
+            errors: Vec::new(),
+        }
+    }
+
+    /// Whether EdenFS is not using this checkout, so it is safe to repair.
+    pub fn is_locked(&self) -> bool {
+        self.lock.is_some()
+    }
+
+    pub fn scan_for_errors(&mut self) -> Result<()> {
+        println!("Reading materialized inodes...");
+        self.read_inodes()?;
+
+        println!("Found {} materialized inodes", self.inodes.len());
+        println!("Computing directory relationships...");
+        let missing = self.link_inode_children();
+
+        println!("Scanning for inconsistencies...");
+        for (parent, index) in missing {
+            let child = &self.inodes[&parent].children[index];
+            self.errors.push(FsckError::MissingMaterializedInode {
+                inode_number: child.inode_number,
+                path: self.child_path(parent, &child.name),
+                mode: child.mode,
+            });
+        }
+        self.scan_inodes_for_errors();
+
+        let next_inode_number = self.max_inode_number + 1;
+        match self.overlay.read_next_inode_number() {
+            // If EdenFS is still running, it is normal that the file does not
+            // exist.
+            Ok(None) if self.is_locked() => {
+                self.errors
+                    .push(FsckError::MissingNextInodeNumber { next_inode_number });
+            }
+            Ok(Some(read)) if read < next_inode_number => {
+                self.errors.push(FsckError::BadNextInodeNumber {
+                    read,
+                    next_inode_number,
+                });
+            }
+            Ok(_) => {}
+            Err(e) => {
+                self.errors.push(FsckError::CorruptNextInodeNumber {
+                    error: e.to_string(),
+                    next_inode_number,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    fn read_inodes(&mut self) -> Result<()> {
+        for subdir in 0..256 {
+            let dir_path = self.overlay.path().join(format!("{:02x}", subdir));
+            for entry in fs::read_dir(&dir_path)? {
+                let entry = entry?;
+                let inode_number = match entry.file_name().to_str().map(str::parse::<u64>) {
+                    Some(Ok(inode_number)) => inode_number,
+                    _ => {
+                        self.errors.push(FsckError::UnexpectedOverlayFile {
+                            path: entry.path(),
+                            mtime: entry.metadata().and_then(|m| m.modified()).ok(),
+                        });
+                        continue;
+                    }
+                };
+                let inode = self.load_inode_info(inode_number, &entry.path());
+                self.inodes.insert(inode_number, inode);
+            }
+        }
+        Ok(())
+    }
+
+    fn load_inode_info(&mut self, inode_number: u64, path: &Path) -> InodeInfo {
+        self.max_inode_number = self.max_inode_number.max(inode_number);
+        let mtime = fs::metadata(path).and_then(|m| m.modified()).ok();
+
+        let mut children = Vec::new();
+        let inode_type = match self.overlay.read_inode(inode_number) {
+            Ok((InodeType::Dir, data)) => match parse_dir(&data) {
+                Ok(dir) => {
+                    for (name, entry) in dir.entries {
+                        let child_number = entry.inodeNumber.max(0) as u64;
+                        self.max_inode_number = self.max_inode_number.max(child_number);
+                        children.push(ChildInfo {
+                            inode_number: child_number,
+                            name,
+                            mode: entry.mode as u32,
+                            hash: entry.hash,
+                        });
+                    }
+                    Ok(InodeType::Dir)
+                }
+                Err(e) => Err(format!("{:#}", e)),
+            },
+            Ok((inode_type, _)) => Ok(inode_type),
+            Err(e) => Err(format!("{:#}", e)),
+        };
+
+        InodeInfo {
+            inode_type,
+            children,
+            mtime,
+            parents: Vec::new(),
+        }
+    }
+
+    /// Record the parents of every inode. Returns materialized children
+    /// without overlay data, as (parent inode, child index) pairs.
+    fn link_inode_children(&mut self) -> Vec<(u64, usize)> {
+        let mut links = Vec::new();
+        let mut missing = Vec::new();
+        for (parent, inode) in self.inodes.iter() {
+            for (index, child) in inode.children.iter().enumerate() {
+                // Older versions of EdenFS left the inode number unset for
+                // children that were never loaded, which can't be in the
+                // overlay.
+                if child.inode_number == 0 {
+                    continue;
+                }
+                if self.inodes.contains_key(&child.inode_number) {
+                    links.push((child.inode_number, *parent, child.name.clone()));
+                } else if child.is_materialized() {
+                    missing.push((*parent, index));
+                }
+            }
+        }
+        for (child, parent, name) in links {
+            if let Some(inode) = self.inodes.get_mut(&child) {
+                inode.parents.push((parent, name));
+            }
+        }
+        missing
+    }
+
+    fn scan_inodes_for_errors(&mut self) {
+        let mut errors = Vec::new();
+        let mut orphan_directories = Vec::new();
+        let mut orphan_files = Vec::new();
+
+        for (inode_number, inode) in self.inodes.iter() {
+            let inode_number = *inode_number;
+            match &inode.inode_type {
+                Ok(inode_type) => {
+                    for (parent, name) in inode.parents.iter() {
+                        let child = self.inodes[parent].children.iter().find(|child| {
+                            child.inode_number == inode_number && child.name == *name
+                        });
+                        let child = match child {
+                            Some(child) => child,
+                            None => continue,
+                        };
+                        let expected_type = match child.mode & S_IFMT {
+                            S_IFDIR => Some(InodeType::Dir),
+                            S_IFREG | S_IFLNK => Some(InodeType::File),
+                            _ => None,
+                        };
+                        if expected_type != Some(*inode_type) {
+                            errors.push(FsckError::BadMode {
+                                inode_number,
+                                path: self.child_path(*parent, name),
+                                mode: child.mode,
+                                inode_type: expected_type.map(|_| *inode_type),
+                            });
+                        } else if *inode_type == InodeType::File && !child.is_materialized() {
+                            errors.push(FsckError::MaterializedWithHash {
+                                inode_number,
+                                path: self.child_path(*parent, name),
+                            });
+                        }
+                    }
+                }
+                Err(error) => {
+                    let expected_type = match inode.parents.first() {
+                        Some((parent, name)) => self.inodes[parent]
+                            .children
+                            .iter()
+                            .find(|child| child.name == *name)
+                            .map(|child| {
+                                if child.mode & S_IFMT == S_IFDIR {
+                                    InodeType::Dir
+                                } else {
+                                    InodeType::File
+                                }
+                            }),
+                        None => None,
+                    };
+                    errors.push(FsckError::InvalidMaterializedInode {
+                        inode_number,
+                        path: self.inode_path(inode_number),
+                        expected_type,
+                        mtime: inode.mtime,
+                        error: error.clone(),
+                    });
+                }
+            }
+
+            if inode.parents.is_empty() {
+                if inode_number != ROOT_INODE_NUMBER {
+                    if inode.inode_type == Ok(InodeType::Dir) {
+                        orphan_directories.push((inode_number, inode.mtime));
+                    } else {
+                        orphan_files.push((inode_number, inode.mtime));
+                    }
+                }
+            } else if inode.parents.len() > 1 {
+                errors.push(FsckError::HardLinkedInode {
+                    inode_number,
+                    paths: inode
+                        .parents
+                        .iter()
+                        .map(|(parent, name)| self.child_path(*parent, name))
+                        .collect(),
+                });
+            }
+        }
+
+        self.errors.extend(errors);
+        if !orphan_directories.is_empty() || !orphan_files.is_empty() {
+            self.errors.push(FsckError::OrphanInodes {
+                directories: orphan_directories,
+                files: orphan_files,
+            });
+        }
+    }
+
+    /// Path of an inode in the checkout, following its first parent.
+    fn inode_path(&self, inode_number: u64) -> String {
+        let mut components = Vec::new();
+        let mut seen = HashSet::new();
+        let mut current = inode_number;
+        while current != ROOT_INODE_NUMBER {
+            let parent = self
+                .inodes
+                .get(&current)
+                .and_then(|inode| inode.parents.first());
+            match parent {
+                Some((parent, name)) if seen.insert(current) => {
+                    components.push(name.clone());
+                    current = *parent;
+                }
+                _ => {
+                    components.push(format!("[unlinked({})]", current));
+                    break;
+                }
+            }
+        }
+        if components.is_empty() {
+            return "/".to_string();
+        }
+        components.reverse();
+        components.join("/")
+    }
+
+    fn child_path(&self, parent: u64, name: &str) -> String {
+        if parent == ROOT_INODE_NUMBER {
+            name.to_string()
+        } else {
+            format!("{}/{}", self.inode_path(parent), name)
+        }
+    }
+
+    /// Fix the errors found by `scan_for_errors`. Returns the directory with
+    /// the repair log and backups of the changed inodes, or `None` if there
+    /// was nothing to fix.
+    pub fn fix_errors(&self) -> Result<Option<PathBuf>> {
+        if !self.is_locked() {
+            bail!("cannot repair errors without holding the overlay lock");
+        }
+        if self.errors.is_empty() {
+            return Ok(None);
+        }
+
+        let fsck_dir = create_fsck_dir(&self.state_dir)?;
+        println!(
+            "Beginning repairs.  Putting logs and backup data in {}",
+            fsck_dir.display()
+        );
+
+        let mut log = RepairLog {
+            file: OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(fsck_dir.join("fsck.log"))?,
+        };
+        log.debug("Beginning fsck repair run")?;
+        log.debug(format!("{} issues were detected", self.errors.len()))?;
+
+        let mut num_fixed = 0;
+        for error in self.errors.iter() {
+            log.info(format!("Processing error: {}", error))?;
+            if let Some(detail) = error.detailed_description() {
+                log.debug(detail)?;
+            }
+            match error.repair(&mut log, &self.overlay, &fsck_dir) {
+                Ok(true) => num_fixed += 1,
+                Ok(false) => {}
+                Err(e) => {
+                    log.debug(format!("unhandled error: {:#}", e))?;
+                    return Err(e);
+                }
+            }
+        }
+        log.info(format!(
+            "Fixed {} of {} issues",
+            num_fixed,
+            self.errors.len()
+        ))?;
+
+        Ok(Some(fsck_dir))
+    }
+}
+
+fn create_fsck_dir(state_dir: &Path) -> Result<PathBuf> {
+    let fsck_base_dir = state_dir.join("fsck");
+    fs::create_dir_all(&fsck_base_dir)?;
+    let timestamp = Local::now().format("%Y%m%d_%H%M%S").to_string();
+
+    // Add a numeric suffix in the unlikely case of several runs within the
+    // same second.
+    for n in 0..20 {
+        let fsck_run_dir = if n == 0 {
+            fsck_base_dir.join(&timestamp)
+        } else {
+            fsck_base_dir.join(format!("{}.{}", timestamp, n))
+        };
+        match fs::create_dir(&fsck_run_dir) {
+            Ok(()) => return Ok(fsck_run_dir),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Err(anyhow!(
+        "too many fsck run directories for the current time"
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+    use thrift_types::edenfs_overlay::types::OverlayDir;
+    use thrift_types::edenfs_overlay::types::OverlayEntry;
+    use thrift_types::fbthrift::compact_protocol;
+
+    use super::*;
+
+    fn write_dir(overlay: &Overlay, inode_number: u64, entries: &[(&str, u32, i64, bool)]) {
+        let dir = OverlayDir {
+            entries: entries
+                .iter()
+                .map(|(name, mode, child, materialized)| {
+                    let entry = OverlayEntry {
+                        mode: *mode as i32,
+                        inodeNumber: *child,
+                        hash: (!materialized).then(|| vec![1; 20]),
+                        ..Default::default()
+                    };
+                    (name.to_string(), entry)
+                })
+                .collect(),
+            ..Default::default()
+        };
+        overlay.write_empty_dir(inode_number).unwrap();
+        let mut data = fs::read(overlay.inode_path(inode_number)).unwrap();
+        data.truncate(64);
+        data.extend_from_slice(&compact_protocol::serialize(&dir));
+        fs::write(overlay.inode_path(inode_number), data).unwrap();
+    }
+
+    fn create_state_dir() -> TempDir {
+        let state_dir = TempDir::new().unwrap();
+        let local = state_dir.path().join("local");
+        for subdir in 0..256 {
+            fs::create_dir_all(local.join(format!("{:02x}", subdir))).unwrap();
+        }
+        fs::write(local.join("info"), b"").unwrap();
+        state_dir
+    }
+
+    #[test]
+    fn test_clean_overlay() -> Result<()> {
+        let state_dir = create_state_dir();
+        let overlay = Overlay::new(state_dir.path().join("local"));
+        write_dir(&overlay, 1, &[("dir", S_IFDIR | 0o755, 2, true)]);
+        write_dir(&overlay, 2, &[("file", S_IFREG | 0o644, 3, true)]);
+        overlay.write_empty_file(3)?;
+        overlay.write_next_inode_number(4)?;
+
+        let mut checker = FilesystemChecker::new(state_dir.path());
+        assert!(checker.is_locked());
+        checker.scan_for_errors()?;
+        assert!(checker.errors.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_detect_and_repair() -> Result<()> {
+        let state_dir = create_state_dir();
+        let overlay = Overlay::new(state_dir.path().join("local"));
+        write_dir(
+            &overlay,
+            1,
+            &[
+                ("dir", S_IFDIR | 0o755, 2, true),
+                ("missing", S_IFREG | 0o644, 3, true),
+                ("hashed", S_IFREG | 0o644, 4, false),
+                ("wrongtype", S_IFDIR | 0o755, 5, true),
+            ],
+        );
+        write_dir(&overlay, 2, &[]);
+        overlay.write_empty_file(4)?;
+        overlay.write_empty_file(5)?;
+        // An orphan directory with a file.
+        write_dir(&overlay, 10, &[("lost", S_IFREG | 0o644, 11, true)]);
+        overlay.write_empty_file(11)?;
+
+        let mut checker = FilesystemChecker::new(state_dir.path());
+        checker.scan_for_errors()?;
+        let messages: Vec<_> = checker.errors.iter().map(|e| e.to_string()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "missing overlay file for materialized file inode 3 (missing) \
+                with file mode 0o100644",
+                "file inode 4 (hashed) is materialized in the overlay but its parent \
+                refers to it by source control hash",
+                "inode 5 (wrongtype) has file mode 0o40755 but is a file in the overlay",
+                "found 1 orphan directory inodes",
+                "edenfs appears to have been shut down uncleanly",
+            ]
+        );
+
+        let fsck_dir = checker.fix_errors()?.unwrap();
+        assert!(overlay.inode_path(3).exists());
+        assert!(!overlay.inode_path(10).exists());
+        assert!(fsck_dir.join("lost+found/10/lost").exists());
+        assert_eq!(overlay.read_next_inode_number()?, Some(12));
+        drop(checker);
+
+        let mut checker = FilesystemChecker::new(state_dir.path());
+        checker.scan_for_errors()?;
+        assert_eq!(checker.errors.len(), 2);
+        Ok(())
+    }
+}
@@ -0,0 +1,226 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+//! Access to the on-disk overlay of a checkout that is not mounted.
+//!
+//! Every materialized inode is stored in `local/<xx>/<inode>`, where `xx` is
+//! the inode number modulo 256 in hex. The file starts with a 64 byte header,
+//! followed by the file contents or, for directories, a compact-serialized
+//! `OverlayDir`.
+
+use std::fs;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use anyhow::bail;
+use anyhow::Context;
+use anyhow::Result;
+use fs2::FileExt;
+use thrift_types::edenfs_overlay::types::OverlayDir;
+use thrift_types::fbthrift::compact_protocol;
+
+pub const ROOT_INODE_NUMBER: u64 = 1;
+
+pub const S_IFMT: u32 = 0o170000;
+pub const S_IFDIR: u32 = 0o040000;
+pub const S_IFREG: u32 = 0o100000;
+pub const S_IFLNK: u32 = 0o120000;
+
+const HEADER_LENGTH: usize = 64;
+const HEADER_VERSION: u32 = 1;
+const TYPE_DIR: &[u8] = b"OVDR";
+const TYPE_FILE: &[u8] = b"OVFL";
+
+const INFO_FILE: &str = "info";
+const NEXT_INODE_NUMBER_FILE: &str = "next-inode-number";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InodeType {
+    File,
+    Dir,
+}
+
+pub struct Overlay {
+    path: PathBuf,
+}
+
+impl Overlay {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Take the lock EdenFS holds on the overlay while the checkout is
+    /// mounted. Returns `None` if the lock is held by someone else.
+    pub fn try_lock(&self) -> Option<File> {
+        let file = File::open(self.path.join(INFO_FILE)).ok()?;
+        file.try_lock_exclusive().ok()?;
+        Some(file)
+    }
+
+    pub fn inode_path(&self, inode_number: u64) -> PathBuf {
+        self.path
+            .join(format!("{:02x}", inode_number % 256))
+            .join(inode_number.to_string())
+    }
+
+    /// Read the overlay file of an inode, returning its type and the data
+    /// following the header.
+    pub fn read_inode(&self, inode_number: u64) -> Result<(InodeType, Vec<u8>)> {
+        let mut data = fs::read(self.inode_path(inode_number))?;
+        let inode_type = parse_header(&data)?;
+        Ok((inode_type, data.split_off(HEADER_LENGTH)))
+    }
+
+    pub fn read_dir(&self, inode_number: u64) -> Result<OverlayDir> {
+        match self.read_inode(inode_number)? {
+            (InodeType::Dir, data) => parse_dir(&data),
+            (InodeType::File, _) => bail!("inode {} is not a directory", inode_number),
+        }
+    }
+
+    pub fn write_empty_file(&self, inode_number: u64) -> Result<()> {
+        self.write_inode(inode_number, TYPE_FILE, &[])
+    }
+
+    pub fn write_empty_dir(&self, inode_number: u64) -> Result<()> {
+        let body = compact_protocol::serialize(&OverlayDir::default());
+        self.write_inode(inode_number, TYPE_DIR, &body)
+    }
+
+    fn write_inode(&self, inode_number: u64, header_type: &[u8], body: &[u8]) -> Result<()> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let mut data = Vec::with_capacity(HEADER_LENGTH + body.len());
+        data.extend_from_slice(header_type);
+        data.extend_from_slice(&HEADER_VERSION.to_be_bytes());
+        // atime, ctime and mtime, each as seconds and nanoseconds.
+        for value in [now, 0, now, 0, now, 0] {
+            data.extend_from_slice(&value.to_be_bytes());
+        }
+        data.resize(HEADER_LENGTH, 0);
+        data.extend_from_slice(body);
+
+        let path = self.inode_path(inode_number);
+        fs::write(&path, data).with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// The next inode number EdenFS will allocate. The file only exists
+    /// while the checkout is not mounted.
+    pub fn read_next_inode_number(&self) -> Result<Option<u64>> {
+        let data = match fs::read(self.path.join(NEXT_INODE_NUMBER_FILE)) {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        match <[u8; 8]>::try_from(data.as_slice()) {
+            Ok(bytes) => Ok(Some(u64::from_ne_bytes(bytes))),
+            Err(_) => bail!(
+                "invalid data in {} file: expected file to contain 8 bytes, but is {} bytes",
+                NEXT_INODE_NUMBER_FILE,
+                data.len()
+            ),
+        }
+    }
+
+    pub fn write_next_inode_number(&self, next_inode_number: u64) -> Result<()> {
+        hg_util::file::atomic_write(&self.path.join(NEXT_INODE_NUMBER_FILE), |f| {
+            f.write_all(&next_inode_number.to_ne_bytes())
+        })?;
+        Ok(())
+    }
+
+    /// Move the contents of a file inode out of the overlay to `output`.
+    pub fn extract_file(&self, inode_number: u64, output: &Path, mode: u32) -> Result<()> {
+        let contents = match self.read_inode(inode_number)? {
+            (InodeType::File, contents) => contents,
+            (InodeType::Dir, _) => bail!("expected inode {} to be a file", inode_number),
+        };
+        if let Some(parent) = output.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        match mode & S_IFMT {
+            #[cfg(unix)]
+            S_IFLNK => {
+                use std::os::unix::ffi::OsStrExt;
+                std::os::unix::fs::symlink(std::ffi::OsStr::from_bytes(&contents), output)?;
+            }
+            S_IFREG => {
+                fs::write(output, contents)?;
+                // Permissions are not stored in the overlay, so only give
+                // access to the owner.
+                #[cfg(unix)]
+                {
+                    use std::os::unix::fs::PermissionsExt;
+                    fs::set_permissions(output, fs::Permissions::from_mode(0o600))?;
+                }
+            }
+            // Other file types have no contents worth keeping.
+            _ => {}
+        }
+        fs::remove_file(self.inode_path(inode_number))?;
+        Ok(())
+    }
+
+    /// Recursively move the materialized contents of a directory inode out
+    /// of the overlay to `output`.
+    pub fn extract_dir(&self, inode_number: u64, output: &Path) -> Result<()> {
+        let dir = self.read_dir(inode_number)?;
+        fs::create_dir_all(output)?;
+        for (name, entry) in dir.entries {
+            let child = entry.inodeNumber as u64;
+            // Children without overlay data are identical to source control,
+            // but unmaterialized directories may still contain materialized
+            // files if EdenFS crashed while materializing them.
+            if child == 0 || !self.inode_path(child).exists() {
+                continue;
+            }
+            let child_output = output.join(name);
+            let mode = entry.mode as u32;
+            if mode & S_IFMT == S_IFDIR {
+                self.extract_dir(child, &child_output)?;
+            } else {
+                self.extract_file(child, &child_output, mode)?;
+            }
+        }
+        fs::remove_file(self.inode_path(inode_number))?;
+        Ok(())
+    }
+}
+
+fn parse_header(data: &[u8]) -> Result<InodeType> {
+    // A zero-sized file is somewhat common after an unclean reboot.
+    if data.is_empty() {
+        bail!("zero-sized overlay file");
+    }
+    if data.len() < HEADER_LENGTH {
+        bail!(
+            "overlay file is too short to contain a header: length={}",
+            data.len()
+        );
+    }
+    let inode_type = match &data[..4] {
+        TYPE_DIR => InodeType::Dir,
+        TYPE_FILE => InodeType::File,
+        header_id => bail!("unknown overlay file type {:?}", header_id),
+    };
+    let version = u32::from_be_bytes([data[4], data[5], data[6], data[7]]);
+    if version != HEADER_VERSION {
+        bail!("unsupported overlay file version {}", version);
+    }
+    Ok(inode_type)
+}
+
+pub fn parse_dir(data: &[u8]) -> Result<OverlayDir> {
+    compact_protocol::deserialize(data).context("failed to parse directory data")
+}
@@ -9,32 +9,99 @@
 
 use std::io::stderr;
 use std::io::Write;
+use std::path::Path;
 
 use anyhow::Result;
 use async_trait::async_trait;
 use clap::Parser;
 use edenfs_client::EdenFsInstance;
+use edenfs_utils::path_from_bytes;
+use thrift_types::edenfs::types::MountState;
+use thrift_types::edenfs::types::TimeSpec;
 
+use crate::du::format_size;
+use crate::du::usage_for_dir;
 use crate::ExitCode;
 
 #[derive(Parser, Debug)]
 #[clap(about = "Minimize disk and memory usage by freeing caches")]
 pub struct GcCmd {}
 
+/// Size of the local store on disk, or `None` if it could not be read.
+fn local_store_size(instance: &EdenFsInstance) -> Option<u64> {
+    match usage_for_dir(&instance.storage_dir(), None) {
+        Ok((size, _)) => Some(size),
+        Err(_) => None,
+    }
+}
+
 #[async_trait]
 impl crate::Subcommand for GcCmd {
     async fn run(&self) -> Result<ExitCode> {
-        let client = EdenFsInstance::global().connect(None).await?;
+        let instance = EdenFsInstance::global();
+        let client = instance.connect(None).await?;
+        let size_before = local_store_size(instance);
+
+        let mut total_unloaded = 0;
+        for mount in client.listMounts().await? {
+            if mount.state != MountState::RUNNING {
+                continue;
+            }
+            let mount_path = path_from_bytes(&mount.mountPoint)?;
+            let root = Vec::new();
 
-        // TODO: unload inodes
+            // Inodes referenced by the kernel can't be unloaded, so drop the
+            // kernel caches first.
+            eprint!("Invalidating kernel caches of {}...", mount_path.display());
+            stderr().flush()?;
+            client
+                .invalidateKernelInodeCache(&mount.mountPoint, &root)
+                .await?;
+            eprintln!();
+
+            eprint!("Unloading inodes of {}...", mount_path.display());
+            stderr().flush()?;
+            let unloaded = client
+                .unloadInodeForPath(&mount.mountPoint, &root, &TimeSpec::default())
+                .await?;
+            eprintln!(" {} inodes unloaded", unloaded);
+            total_unloaded += unloaded;
+        }
 
         eprint!("Clearing and compacting local caches...");
         stderr().flush()?;
         client.clearAndCompactLocalStore().await?;
         eprintln!();
 
-        // TODO: clear kernel caches here
+        print_summary(
+            &instance.storage_dir(),
+            total_unloaded,
+            size_before,
+            local_store_size(instance),
+        );
 
         Ok(0)
     }
 }
+
+fn print_summary(
+    storage_dir: &Path,
+    unloaded: i64,
+    size_before: Option<u64>,
+    size_after: Option<u64>,
+) {
+    println!("Unloaded {} inodes", unloaded);
+    match (size_before, size_after) {
+        (Some(before), Some(after)) => println!(
+            "Local store in {}: {} -> {} ({} reclaimed)",
+            storage_dir.display(),
+            format_size(before),
+            format_size(after),
+            format_size(before.saturating_sub(after))
+        ),
+        _ => println!(
+            "Could not determine the size of the local store in {}",
+            storage_dir.display()
+        ),
+    }
+}
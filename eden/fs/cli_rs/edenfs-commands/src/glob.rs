@@ -0,0 +1,208 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+//! edenfsctl glob
+
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+
+use anyhow::anyhow;
+use anyhow::Context;
+use anyhow::Result;
+use async_trait::async_trait;
+use clap::Parser;
+use edenfs_client::checkout::find_checkout;
+use edenfs_client::EdenFsInstance;
+use edenfs_utils::bytes_from_path;
+use edenfs_utils::path_from_bytes;
+use hg_util::path::expand_path;
+use serde::Serialize;
+use thrift_types::edenfs::types::GlobParams;
+
+use crate::ExitCode;
+
+#[derive(Parser, Debug)]
+#[clap(
+    about = "Print matching filenames",
+    long_about = "Print matching filenames. This command does not do any filtering based on \
+    source control state or gitignore files."
+)]
+pub struct GlobCmd {
+    #[clap(
+        help = "Filename patterns (relative to the current directory) to match via glob, see: \
+        https://man7.org/linux/man-pages/man7/glob.7.html"
+    )]
+    patterns: Vec<String>,
+
+    #[clap(
+        long,
+        parse(from_str = expand_path),
+        help = "Specify path to repo root (default: root of cwd)"
+    )]
+    repo: Option<PathBuf>,
+
+    #[clap(
+        long,
+        parse(from_str = expand_path),
+        help = "Specify path to a file that lists patterns to match, one per line"
+    )]
+    pattern_file: Option<PathBuf>,
+
+    #[clap(
+        long,
+        help = "Include hidden files in the list of returned matching files"
+    )]
+    include_dot_files: bool,
+
+    #[clap(
+        long,
+        help = "When printing the list of matching files, exclude directories"
+    )]
+    list_only_files: bool,
+
+    #[clap(
+        short,
+        long = "revision",
+        help = "Commit to evaluate the patterns against instead of the working copy. \
+        Can be repeated to glob several commits at once"
+    )]
+    revisions: Vec<String>,
+
+    #[clap(long, help = "Print the output in JSON format")]
+    json: bool,
+}
+
+#[derive(Serialize)]
+struct GlobEntry {
+    path: String,
+    /// The commit the path was matched in, when globbing commits.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    commit: Option<String>,
+}
+
+// \ is a path separator on Windows, and it is much more common for Windows
+// tools to hand out paths with \ than to use it as an escape character in a
+// pattern. EdenFS only understands /.
+fn clean_pattern(pattern: &str) -> String {
+    if cfg!(windows) {
+        pattern.replace('\\', "/")
+    } else {
+        pattern.to_string()
+    }
+}
+
+impl GlobCmd {
+    fn patterns(&self) -> Result<Vec<String>> {
+        let mut patterns = self.patterns.clone();
+        if let Some(pattern_file) = &self.pattern_file {
+            let content = fs::read_to_string(pattern_file)
+                .with_context(|| format!("Failed to read {}", pattern_file.display()))?;
+            patterns.extend(
+                content
+                    .lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty())
+                    .map(String::from),
+            );
+        }
+        Ok(patterns
+            .iter()
+            .map(|pattern| clean_pattern(pattern))
+            .collect())
+    }
+
+    /// The checkout to glob, and the directory inside it the patterns are
+    /// relative to.
+    fn checkout_and_search_root(&self, instance: &EdenFsInstance) -> Result<(PathBuf, PathBuf)> {
+        let path = match &self.repo {
+            Some(repo) => repo.clone(),
+            None => {
+                std::env::current_dir().context("Unable to retrieve current working directory")?
+            }
+        };
+        let checkout = find_checkout(instance, &path)?;
+        let checkout_path = checkout.path();
+        let path = path.canonicalize()?;
+        #[cfg(windows)]
+        let path = edenfs_utils::strip_unc_prefix(path);
+        let search_root = path
+            .strip_prefix(&checkout_path)
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+        if self.repo.is_some() && !search_root.as_os_str().is_empty() {
+            return Err(anyhow!(
+                "{} is not the root of an EdenFS repo",
+                path.display()
+            ));
+        }
+        Ok((checkout_path, search_root))
+    }
+}
+
+#[async_trait]
+impl crate::Subcommand for GlobCmd {
+    async fn run(&self) -> Result<ExitCode> {
+        let instance = EdenFsInstance::global();
+        let patterns = self.patterns()?;
+        let (checkout_path, search_root) = match self.checkout_and_search_root(instance) {
+            Ok(paths) => paths,
+            Err(e) => {
+                eprintln!("error: {:#}", e);
+                return Ok(1);
+            }
+        };
+        let revisions = self
+            .revisions
+            .iter()
+            .map(|revision| {
+                hex::decode(revision).with_context(|| format!("Invalid commit {}", revision))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let params = GlobParams {
+            mountPoint: bytes_from_path(checkout_path)?,
+            globs: patterns,
+            includeDotfiles: self.include_dot_files,
+            prefetchFiles: false,
+            suppressFileList: false,
+            revisions,
+            searchRoot: bytes_from_path(search_root)?,
+            listOnlyFiles: self.list_only_files,
+            ..Default::default()
+        };
+        let client = instance.connect(None).await?;
+        let glob = client
+            .globFiles(&params)
+            .await
+            .context("Failed globFiles() thrift call")?;
+
+        // Origins are only filled in when globbing commits.
+        let mut origins = glob.originHashes.iter().map(hex::encode);
+        let mut entries = Vec::with_capacity(glob.matchingFiles.len());
+        for path in &glob.matchingFiles {
+            entries.push(GlobEntry {
+                path: path_from_bytes(path)?.display().to_string(),
+                commit: origins.next(),
+            });
+        }
+
+        if self.json {
+            println!("{}", serde_json::to_string_pretty(&entries)?);
+        } else {
+            for entry in entries {
+                match entry.commit {
+                    Some(commit) if self.revisions.len() > 1 => {
+                        println!("{} {}", commit, entry.path)
+                    }
+                    _ => println!("{}", entry.path),
+                }
+            }
+        }
+        Ok(0)
+    }
+}
@@ -20,17 +20,27 @@ use hg_util::path::expand_path;
 use tracing::event;
 use tracing::Level;
 
+mod changes_since;
+mod clone;
 mod config;
 mod debug;
+mod doctor;
 mod du;
+mod fsck;
 mod gc;
+mod glob;
 mod list;
 mod minitop;
+mod mount;
 mod pid;
 mod prefetch_profile;
+mod rage;
 mod redirect;
+mod remove;
 mod status;
 mod top;
+mod trace;
+mod unmount;
 mod uptime;
 mod util;
 
@@ -46,7 +56,21 @@ const DEFAULT_ETC_EDEN_DIR: &str = "C:\\ProgramData\\facebook\\eden";
 
 // Used to determine whether we should gate off certain oxidized edenfsctl commands
 const ROLLOUT_JSON: &str = "edenfsctl_rollout.json";
-const EXPERIMENTAL_COMMANDS: &[&str] = &["redirect"];
+const EXPERIMENTAL_COMMANDS: &[&str] = &[
+    "redirect",
+    "trace",
+    "doctor",
+    "rage",
+    "clone",
+    "mount",
+    "unmount",
+    "remove",
+    "glob",
+    "changes-since",
+    "fsck",
+    "top",
+    "gc",
+];
 
 type ExitCode = i32;
 
@@ -95,17 +119,28 @@ pub enum TopLevelSubcommand {
     Status(crate::status::StatusCmd),
     Pid(crate::pid::PidCmd),
     Uptime(crate::uptime::UptimeCmd),
-    // Gc(crate::gc::GcCmd),
+    Gc(crate::gc::GcCmd),
     Config(crate::config::ConfigCmd),
     Debug(crate::debug::DebugCmd),
-    // Top(crate::top::TopCmd),
+    Doctor(crate::doctor::DoctorCmd),
+    Top(crate::top::TopCmd),
     Minitop(crate::minitop::MinitopCmd),
     Du(crate::du::DiskUsageCmd),
+    Fsck(crate::fsck::FsckCmd),
     List(crate::list::ListCmd),
     #[clap(subcommand, alias = "pp")]
     PrefetchProfile(crate::prefetch_profile::PrefetchCmd),
     #[clap(subcommand, alias = "redir")]
     Redirect(crate::redirect::RedirectCmd),
+    Trace(crate::trace::TraceCmd),
+    Rage(crate::rage::RageCmd),
+    Clone(crate::clone::CloneCmd),
+    Mount(crate::mount::MountCmd),
+    Unmount(crate::unmount::UnmountCmd),
+    #[clap(alias = "rm")]
+    Remove(crate::remove::RemoveCmd),
+    Glob(crate::glob::GlobCmd),
+    ChangesSince(crate::changes_since::ChangesSinceCmd),
 }
 
 #[async_trait]
@@ -116,15 +151,25 @@ impl Subcommand for TopLevelSubcommand {
             Status(cmd) => cmd,
             Pid(cmd) => cmd,
             Uptime(cmd) => cmd,
-            // Gc(cmd) => cmd,
+            Gc(cmd) => cmd,
             Config(cmd) => cmd,
             Debug(cmd) => cmd,
-            // Top(cmd) => cmd,
+            Doctor(cmd) => cmd,
+            Top(cmd) => cmd,
             Minitop(cmd) => cmd,
             Du(cmd) => cmd,
+            Fsck(cmd) => cmd,
             List(cmd) => cmd,
             PrefetchProfile(cmd) => cmd,
             Redirect(cmd) => cmd,
+            Trace(cmd) => cmd,
+            Rage(cmd) => cmd,
+            Clone(cmd) => cmd,
+            Mount(cmd) => cmd,
+            Unmount(cmd) => cmd,
+            Remove(cmd) => cmd,
+            Glob(cmd) => cmd,
+            ChangesSince(cmd) => cmd,
         };
         sc.run().await
     }
@@ -139,15 +184,25 @@ impl fmt::Display for TopLevelSubcommand {
                 TopLevelSubcommand::Status(_) => "status",
                 TopLevelSubcommand::Pid(_) => "pid",
                 TopLevelSubcommand::Uptime(_) => "uptime",
-                //TopLevelSubcommand::Gc(_) => "gc",
+                TopLevelSubcommand::Gc(_) => "gc",
                 TopLevelSubcommand::Config(_) => "config",
                 TopLevelSubcommand::Debug(_) => "debug",
-                //TopLevelSubcommand::Top(_) => "top",
+                TopLevelSubcommand::Doctor(_) => "doctor",
+                TopLevelSubcommand::Top(_) => "top",
                 TopLevelSubcommand::Minitop(_) => "minitop",
                 TopLevelSubcommand::Du(_) => "du",
+                TopLevelSubcommand::Fsck(_) => "fsck",
                 TopLevelSubcommand::List(_) => "list",
                 TopLevelSubcommand::PrefetchProfile(_) => "prefetch-profile",
                 TopLevelSubcommand::Redirect(_) => "redirect",
+                TopLevelSubcommand::Trace(_) => "trace",
+                TopLevelSubcommand::Rage(_) => "rage",
+                TopLevelSubcommand::Clone(_) => "clone",
+                TopLevelSubcommand::Mount(_) => "mount",
+                TopLevelSubcommand::Unmount(_) => "unmount",
+                TopLevelSubcommand::Remove(_) => "remove",
+                TopLevelSubcommand::Glob(_) => "glob",
+                TopLevelSubcommand::ChangesSince(_) => "changes-since",
             }
         )
     }
@@ -66,7 +66,7 @@ pub struct MinitopCmd {
     interactive: bool,
 }
 
-fn parse_refresh_rate(arg: &str) -> Duration {
+pub fn parse_refresh_rate(arg: &str) -> Duration {
     let seconds = arg
         .parse::<u64>()
         .expect("Please enter a valid whole positive number for refresh_rate.");
@@ -76,11 +76,11 @@ fn parse_refresh_rate(arg: &str) -> Duration {
 
 const PENDING_COUNTER_REGEX: &str = r"store\.hg\.pending_import\..*";
 const LIVE_COUNTER_REGEX: &str = r"store\.hg\.live_import\..*";
-const IMPORT_OBJECT_TYPES: &[&str] = &["blob", "tree"];
+pub const IMPORT_OBJECT_TYPES: &[&str] = &["blob", "tree"];
 const STATS_NOT_AVAILABLE: i64 = 0;
 
 const UNKNOWN_COMMAND: &str = "<unknown>";
-const COLUMN_TITLES: &[&str] = &[
+pub const COLUMN_TITLES: &[&str] = &[
     "PID",
     "MOUNT",
     "READS",
@@ -155,13 +155,13 @@ impl AccessCountsExt for AccessCounts {
 }
 
 #[derive(Clone)]
-struct Process {
-    pid: pid_t,
-    mount_name: String,
-    cmd: String,
-    access_counts: AccessCounts,
-    fetch_counts: i64,
-    last_access_time: Instant,
+pub struct Process {
+    pub pid: pid_t,
+    pub mount_name: String,
+    pub cmd: String,
+    pub access_counts: AccessCounts,
+    pub fetch_counts: i64,
+    pub last_access_time: Instant,
 }
 
 impl Process {
@@ -223,12 +223,40 @@ fn get_mount_name(mount_path: &[u8]) -> anyhow::Result<String> {
         .map_err(|_| anyhow!("mount name is not UTF-8"))
 }
 
-type TrackedProcesses = BTreeMap<pid_t, Process>;
+pub type TrackedProcesses = BTreeMap<pid_t, Process>;
+
+/// Add the access and fetch counts returned by `getAccessCounts` to the
+/// tracked processes, starting to track processes seen for the first time.
+pub fn update_tracked_processes(
+    tracked_processes: &mut TrackedProcesses,
+    counts: &GetAccessCountsResult,
+) -> Result<()> {
+    for (mount, accesses) in &counts.accessesByMount {
+        let mount_name = get_mount_name(mount)?;
+
+        for (pid, access_counts) in &accesses.accessCountsByPid {
+            tracked_processes
+                .entry(*pid)
+                .or_insert_with(|| Process::new(*pid, mount_name.clone()))
+                .set_cmd(counts.get_cmd_for_pid(*pid)?)
+                .increment_access_counts(access_counts);
+        }
+
+        for (pid, fetch_counts) in &accesses.fetchCountsByPid {
+            tracked_processes
+                .entry(*pid)
+                .or_insert_with(|| Process::new(*pid, mount_name.clone()))
+                .set_cmd(counts.get_cmd_for_pid(*pid)?)
+                .set_fetch_counts(*fetch_counts);
+        }
+    }
+    Ok(())
+}
 
 /// We aggregate all tracked processes in a separate step right before rendering
 /// (as opposed to aggregating eagerly as we receive process logs in `update_process`)
 /// because tracked processes could stop running which may change the top_pid.
-fn aggregate_processes(processes: &TrackedProcesses, system: &System) -> Vec<Process> {
+pub fn aggregate_processes(processes: &TrackedProcesses, system: &System) -> Vec<Process> {
     // Technically, it's more correct to aggregate this by TGID
     // Because that's hard to get, we instead aggregate by mount & cmd
     // (mount, cmd) => Process
@@ -260,12 +288,14 @@ fn aggregate_processes(processes: &TrackedProcesses, system: &System) -> Vec<Pro
     sorted_processes
 }
 
-struct ImportStat {
-    count: i64,
-    max_duration_us: i64,
+pub struct ImportStat {
+    pub count: i64,
+    pub max_duration_us: i64,
 }
 
-async fn get_pending_import_counts(client: &EdenFsClient) -> Result<BTreeMap<String, ImportStat>> {
+pub async fn get_pending_import_counts(
+    client: &EdenFsClient,
+) -> Result<BTreeMap<String, ImportStat>> {
     let mut imports = BTreeMap::<String, ImportStat>::new();
 
     let counters = client.getRegexCounters(PENDING_COUNTER_REGEX).await?;
@@ -290,7 +320,7 @@ async fn get_pending_import_counts(client: &EdenFsClient) -> Result<BTreeMap<Str
     Ok(imports)
 }
 
-async fn get_live_import_counts(client: &EdenFsClient) -> Result<BTreeMap<String, ImportStat>> {
+pub async fn get_live_import_counts(client: &EdenFsClient) -> Result<BTreeMap<String, ImportStat>> {
     let mut imports = BTreeMap::<String, ImportStat>::new();
     let counters = client.getRegexCounters(LIVE_COUNTER_REGEX).await?;
     for import_type in IMPORT_OBJECT_TYPES {
@@ -324,7 +354,7 @@ async fn get_live_import_counts(client: &EdenFsClient) -> Result<BTreeMap<String
     Ok(imports)
 }
 
-struct TerminalAttributes {
+pub struct TerminalAttributes {
     line_wrap_disabled: bool,
     alt_screen_entered: bool,
     raw_mode_entered: bool,
@@ -332,7 +362,7 @@ struct TerminalAttributes {
 }
 
 impl TerminalAttributes {
-    fn new() -> TerminalAttributes {
+    pub fn new() -> TerminalAttributes {
         let stdout = stdout();
         Self {
             line_wrap_disabled: false,
@@ -342,19 +372,19 @@ impl TerminalAttributes {
         }
     }
 
-    fn disable_line_wrap(mut self) -> Result<TerminalAttributes> {
+    pub fn disable_line_wrap(mut self) -> Result<TerminalAttributes> {
         queue!(self.stdout, terminal::DisableLineWrap)?;
         self.line_wrap_disabled = true;
         Ok(self)
     }
 
-    fn enter_alt_screen(mut self) -> Result<TerminalAttributes> {
+    pub fn enter_alt_screen(mut self) -> Result<TerminalAttributes> {
         queue!(self.stdout, terminal::EnterAlternateScreen)?;
         self.alt_screen_entered = true;
         Ok(self)
     }
 
-    fn enter_raw_mode(mut self) -> Result<TerminalAttributes> {
+    pub fn enter_raw_mode(mut self) -> Result<TerminalAttributes> {
         terminal::enable_raw_mode()?;
         self.raw_mode_entered = true;
         Ok(self)
@@ -455,25 +485,7 @@ impl crate::Subcommand for MinitopCmd {
                 .getAccessCounts(self.refresh_rate.as_secs().try_into()?)
                 .await?;
 
-            for (mount, accesses) in &counts.accessesByMount {
-                let mount_name = get_mount_name(mount)?;
-
-                for (pid, access_counts) in &accesses.accessCountsByPid {
-                    tracked_processes
-                        .entry(*pid)
-                        .or_insert_with(|| Process::new(*pid, mount_name.clone()))
-                        .set_cmd(counts.get_cmd_for_pid(*pid)?)
-                        .increment_access_counts(access_counts);
-                }
-
-                for (pid, fetch_counts) in &accesses.fetchCountsByPid {
-                    tracked_processes
-                        .entry(*pid)
-                        .or_insert_with(|| Process::new(*pid, mount_name.clone()))
-                        .set_cmd(counts.get_cmd_for_pid(*pid)?)
-                        .set_fetch_counts(*fetch_counts);
-                }
-            }
+            update_tracked_processes(&mut tracked_processes, &counts)?;
 
             // Render pending trees/blobs
             for import_type in IMPORT_OBJECT_TYPES {
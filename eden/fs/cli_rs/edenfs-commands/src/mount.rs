@@ -0,0 +1,111 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+//! edenfsctl mount
+
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+
+use anyhow::anyhow;
+use anyhow::Context;
+use anyhow::Result;
+use async_trait::async_trait;
+use clap::Parser;
+use edenfs_client::checkout::CheckoutConfig;
+use edenfs_client::EdenFsInstance;
+use edenfs_utils::bytes_from_path;
+use hg_util::path::expand_path;
+use thrift_types::edenfs::types::MountArgument;
+
+use crate::util::mount_point::resolve_mount_point;
+use crate::ExitCode;
+
+#[derive(Parser, Debug)]
+#[clap(about = "Remount an existing checkout (for instance, after it was manually unmounted)")]
+pub struct MountCmd {
+    #[clap(
+        parse(from_str = expand_path),
+        required = true,
+        help = "The checkout mount path"
+    )]
+    paths: Vec<PathBuf>,
+
+    #[clap(long, help = "Read only mount")]
+    read_only: bool,
+}
+
+impl MountCmd {
+    async fn mount(&self, instance: &EdenFsInstance, path: &Path) -> Result<ExitCode> {
+        let path = resolve_mount_point(path)?;
+        let client_name = instance
+            .get_configured_mounts_map()?
+            .remove(&path)
+            .ok_or_else(|| anyhow!("could not find mount path {}", path.display()))?;
+        let client_dir = instance.config_directory(&client_name);
+        // Refuse to mount a checkout whose config is broken.
+        CheckoutConfig::parse_config(client_dir.clone())?;
+
+        fs::create_dir_all(&path)
+            .with_context(|| format!("Failed to create {}", path.display()))?;
+        // A stale mount, or a directory that was never mounted, has no
+        // readable .eden/root.
+        if let Ok(root) = fs::read_link(path.join(".eden").join("root")) {
+            if root == path {
+                eprintln!(
+                    "ERROR: Mount point in use! {} is already mounted by EdenFS.",
+                    path.display()
+                );
+            } else {
+                eprintln!(
+                    "ERROR: Mount point in use! {} is already mounted by EdenFS as part of {}.",
+                    path.display(),
+                    root.display()
+                );
+            }
+            return Ok(1);
+        }
+
+        let mount_argument = MountArgument {
+            mountPoint: bytes_from_path(path.clone())?,
+            edenClientPath: bytes_from_path(client_dir)?,
+            readOnly: self.read_only,
+            ..Default::default()
+        };
+        let client = instance.connect(None).await?;
+        if let Err(e) = client.mount(&mount_argument).await {
+            let message = e.to_string();
+            if message.contains("already mounted") {
+                eprintln!(
+                    "ERROR: Mount point in use! {} is already mounted by EdenFS.",
+                    path.display()
+                );
+                return Ok(1);
+            }
+            return Err(anyhow!(message));
+        }
+        Ok(0)
+    }
+}
+
+#[async_trait]
+impl crate::Subcommand for MountCmd {
+    async fn run(&self) -> Result<ExitCode> {
+        let instance = EdenFsInstance::global();
+        for path in &self.paths {
+            match self.mount(instance, path).await {
+                Ok(0) => {}
+                Ok(code) => return Ok(code),
+                Err(e) => {
+                    eprintln!("error: {:#}", e);
+                    return Ok(1);
+                }
+            }
+        }
+        Ok(0)
+    }
+}
@@ -12,6 +12,7 @@ use std::io::Write;
 use std::path::Path;
 use std::path::PathBuf;
 use std::str;
+use std::time::Duration;
 
 use anyhow::anyhow;
 use anyhow::Context;
@@ -33,6 +34,8 @@ use crate::util::expand_path_or_cwd;
 use crate::ExitCode;
 use crate::Subcommand;
 
+mod record;
+
 #[derive(Parser, Debug)]
 pub struct ActivationOptions {
     #[clap(
@@ -116,8 +119,42 @@ pub enum PrefetchCmd {
         )]
         output_path: PathBuf,
     },
-    #[clap(about = "Start recording fetched file paths.")]
-    Record,
+    #[clap(
+        about = "Start recording fetched file paths, or record a profile.",
+        long_about = "Start recording fetched file paths, to be saved with `finish`. \
+        When given a profile name, record the files accessed in the checkout instead, \
+        until interrupted or for --duration seconds, and write them as globs to the \
+        profile of that name in the checkout for review."
+    )]
+    Record {
+        #[clap(help = "The name of the profile to write")]
+        profile_name: Option<String>,
+        #[clap(
+            long,
+            parse(try_from_str = expand_path_or_cwd),
+            default_value = "",
+            help = "The checkout in which to record accessed files"
+        )]
+        checkout: PathBuf,
+        #[clap(
+            long,
+            requires = "profile-name",
+            help = "Stop recording after this many seconds instead of on Ctrl-C"
+        )]
+        duration: Option<u64>,
+        #[clap(
+            long,
+            default_value = "5",
+            help = "Match a whole directory once this many of its files were accessed"
+        )]
+        min_files_per_glob: usize,
+        #[clap(
+            long,
+            requires = "profile-name",
+            help = "Overwrite the profile if it already exists"
+        )]
+        force: bool,
+    },
     #[clap(about = "List all of the currenly activated prefetch profiles for a checkout.")]
     List {
         #[clap(
@@ -234,6 +271,68 @@ impl PrefetchCmd {
         Ok(0)
     }
 
+    async fn record_profile(
+        &self,
+        profile_name: &str,
+        checkout: &Path,
+        duration: Option<u64>,
+        min_files_per_glob: usize,
+        force: bool,
+    ) -> Result<ExitCode> {
+        if let Err(e) = record::check_profile_name(profile_name) {
+            eprintln!("error: {:#}", e);
+            return Ok(1);
+        }
+        let instance = EdenFsInstance::global();
+        let checkout = find_checkout(instance, checkout)
+            .with_context(|| anyhow!("Failed to find checkout with path {}", checkout.display()))?;
+        let profile_path = checkout.profile_path(profile_name);
+        if profile_path.exists() && !force {
+            eprintln!(
+                "error: {} already exists, use --force to overwrite it",
+                profile_path.display()
+            );
+            return Ok(1);
+        }
+
+        let client = instance.connect(None).await?;
+        let mount = checkout.path();
+        let recording =
+            match record::record_activity(&client, &mount, duration.map(Duration::from_secs)).await
+            {
+                Ok(recording) => recording,
+                Err(e) => {
+                    eprintln!("error: {:#}", e);
+                    return Ok(1);
+                }
+            };
+
+        let paths = match record::recorded_paths(&recording, &mount) {
+            Ok(paths) => paths,
+            Err(e) => {
+                eprintln!("error: {:#}", e);
+                return Ok(1);
+            }
+        };
+        if paths.is_empty() {
+            eprintln!("error: no files were accessed in {}", mount.display());
+            return Ok(1);
+        }
+        let globs = record::generalize(&paths, min_files_per_glob);
+        record::write_profile(&profile_path, &globs)?;
+        println!(
+            "Wrote {} globs matching {} accessed files to {}",
+            globs.len(),
+            paths.len(),
+            profile_path.display()
+        );
+        println!(
+            "Review it, then run `edenfsctl prefetch-profile activate {}` to use it.",
+            profile_name
+        );
+        Ok(0)
+    }
+
     async fn list(&self, checkout: &Path) -> Result<ExitCode> {
         let instance = EdenFsInstance::global();
         let client_name = instance.client_name(checkout).with_context(|| {
@@ -640,7 +739,25 @@ impl Subcommand for PrefetchCmd {
     async fn run(&self) -> Result<ExitCode> {
         match self {
             Self::Finish { output_path } => self.finish(output_path).await,
-            Self::Record {} => self.record().await,
+            Self::Record {
+                profile_name: None, ..
+            } => self.record().await,
+            Self::Record {
+                profile_name: Some(profile_name),
+                checkout,
+                duration,
+                min_files_per_glob,
+                force,
+            } => {
+                self.record_profile(
+                    profile_name,
+                    checkout,
+                    *duration,
+                    *min_files_per_glob,
+                    *force,
+                )
+                .await
+            }
             Self::List { checkout } => self.list(checkout).await,
             Self::Activate {
                 options,
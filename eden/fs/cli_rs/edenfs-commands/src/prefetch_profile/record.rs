@@ -0,0 +1,327 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+//! Authoring prefetch profiles from the files accessed in a checkout.
+
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Component;
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use anyhow::Context;
+use anyhow::Result;
+use edenfs_client::EdenFsClient;
+use edenfs_utils::bytes_from_path;
+use edenfs_utils::path_from_bytes;
+use serde_json::Value;
+
+/// Metadata directories whose accesses don't belong in a profile.
+const IGNORED_DIRS: &[&str] = &[".eden", ".hg", ".sl", ".git"];
+
+/// Profiles are files directly in the profiles directory of the checkout, so
+/// their names can't be paths.
+pub fn check_profile_name(name: &str) -> Result<()> {
+    if name.is_empty() || name == "." || name == ".." || name.contains(|c| c == '/' || c == '\\') {
+        return Err(anyhow!("invalid profile name '{}'", name));
+    }
+    Ok(())
+}
+
+/// Records the files accessed in the checkout at `mount` until `duration`
+/// elapses, or until interrupted. Returns the contents of the recording.
+pub async fn record_activity(
+    client: &EdenFsClient,
+    mount: &Path,
+    duration: Option<Duration>,
+) -> Result<String> {
+    let output_dir =
+        std::env::temp_dir().join(format!("edenfs-prefetch-record-{}", std::process::id()));
+    fs::create_dir_all(&output_dir)
+        .with_context(|| format!("Failed to create {}", output_dir.display()))?;
+    let result = record_activity_in(client, mount, &output_dir, duration).await;
+    // The recording was only needed to build the profile.
+    let _ = fs::remove_dir_all(&output_dir);
+    result
+}
+
+async fn record_activity_in(
+    client: &EdenFsClient,
+    mount: &Path,
+    output_dir: &Path,
+    duration: Option<Duration>,
+) -> Result<String> {
+    let mount_point = bytes_from_path(mount.to_path_buf())?;
+    let started = client
+        .debugStartRecordingActivity(&mount_point, &bytes_from_path(output_dir.to_path_buf())?)
+        .await
+        .with_context(|| anyhow!("debugStartRecordingActivity thrift call failed"))?;
+    // The daemon reports failures with a zero id, including when it was built
+    // without an activity recorder.
+    if started.unique == 0 {
+        return Err(anyhow!(
+            "EdenFS failed to start recording activity in {}",
+            mount.display()
+        ));
+    }
+
+    // Stop the recording even if waiting failed, so it doesn't keep running
+    // in the daemon.
+    let waited = wait_for_recording(mount, duration).await;
+    let stopped = client
+        .debugStopRecordingActivity(&mount_point, started.unique)
+        .await
+        .with_context(|| anyhow!("debugStopRecordingActivity thrift call failed"));
+    waited?;
+    let recording_path = match stopped?.path {
+        Some(path) => path_from_bytes(&path)?,
+        None => {
+            return Err(anyhow!(
+                "EdenFS failed to stop recording {}",
+                started.unique
+            ))
+        }
+    };
+    fs::read_to_string(&recording_path)
+        .with_context(|| format!("Failed to read recording {}", recording_path.display()))
+}
+
+async fn wait_for_recording(mount: &Path, duration: Option<Duration>) -> Result<()> {
+    match duration {
+        Some(duration) => {
+            eprintln!(
+                "Recording file accesses in {} for {} seconds. Press Ctrl-C to stop early.",
+                mount.display(),
+                duration.as_secs()
+            );
+            tokio::select! {
+                _ = tokio::time::sleep(duration) => {},
+                _ = tokio::signal::ctrl_c() => {},
+            }
+        }
+        None => {
+            eprintln!(
+                "Recording file accesses in {}. Press Ctrl-C to stop.",
+                mount.display()
+            );
+            tokio::signal::ctrl_c().await?;
+        }
+    }
+    Ok(())
+}
+
+/// Converts a recorded path to a `/`-separated path relative to `mount`, if
+/// it is a path inside the checkout that belongs in a profile.
+fn relative_path(path: &str, mount: &Path) -> Option<String> {
+    let path = Path::new(path);
+    let path = if path.is_absolute() {
+        path.strip_prefix(mount).ok()?
+    } else {
+        path
+    };
+    let mut components = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(name) => components.push(name.to_str()?),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+    match components.first() {
+        None => None,
+        Some(first) if IGNORED_DIRS.contains(first) => None,
+        Some(_) => Some(components.join("/")),
+    }
+}
+
+/// The files of the checkout at `mount` accessed in `recording`.
+///
+/// The recording must be JSON lines, one object per event, with the accessed
+/// path in a top-level `path` string. Events without one are skipped. Anything
+/// else is an error rather than being guessed at, as the format of a recording
+/// is up to the activity recorder that the daemon was built with.
+pub fn recorded_paths(recording: &str, mount: &Path) -> Result<BTreeSet<String>> {
+    let mut paths = BTreeSet::new();
+    for (i, line) in recording.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let event = match serde_json::from_str::<Value>(line) {
+            Ok(Value::Object(event)) => event,
+            _ => {
+                return Err(anyhow!(
+                    "line {} of the activity recording is not a JSON object",
+                    i + 1
+                ));
+            }
+        };
+        if let Some(Value::String(path)) = event.get("path") {
+            paths.extend(relative_path(path, mount));
+        }
+    }
+    Ok(paths)
+}
+
+#[derive(Default)]
+struct PathTree {
+    files: BTreeSet<String>,
+    dirs: BTreeMap<String, PathTree>,
+}
+
+impl PathTree {
+    fn insert(&mut self, path: &str) {
+        let mut node = self;
+        let mut components = path.split('/').peekable();
+        while let Some(name) = components.next() {
+            if components.peek().is_none() {
+                // A path can be recorded both as a file and as a directory
+                // when its type changed during the recording.
+                if !node.dirs.contains_key(name) {
+                    node.files.insert(name.to_string());
+                }
+            } else {
+                node.files.remove(name);
+                node = node.dirs.entry(name.to_string()).or_default();
+            }
+        }
+    }
+
+    /// Appends the globs matching the files under this directory to `globs`.
+    /// Returns whether all of them were generalized to globs matching any
+    /// file in their directory.
+    fn globs(&self, prefix: &str, min_files: usize, globs: &mut Vec<String>) -> bool {
+        let mut dir_globs = Vec::new();
+        let mut dirs_generalized = true;
+        for (name, dir) in &self.dirs {
+            let dir_prefix = format!("{}{}/", prefix, name);
+            dirs_generalized &= dir.globs(&dir_prefix, min_files, &mut dir_globs);
+        }
+        let files_generalized = self.files.len() >= min_files;
+        let generalized = dirs_generalized && (self.files.is_empty() || files_generalized);
+
+        // A directory whose subdirectories were broadly accessed is likely to
+        // be accessed as a whole. The root is never, as it is the whole
+        // checkout.
+        if generalized && self.dirs.len() > 1 && !prefix.is_empty() {
+            globs.push(format!("{}**", prefix));
+            return true;
+        }
+        globs.append(&mut dir_globs);
+        if files_generalized {
+            globs.push(format!("{}*", prefix));
+        } else {
+            globs.extend(self.files.iter().map(|name| format!("{}{}", prefix, name)));
+        }
+        generalized
+    }
+}
+
+/// Generalizes accessed paths to globs: directories in which at least
+/// `min_files` files were accessed are matched as a whole, as are directories
+/// whose subdirectories all were.
+pub fn generalize(paths: &BTreeSet<String>, min_files: usize) -> Vec<String> {
+    let mut tree = PathTree::default();
+    for path in paths {
+        tree.insert(path);
+    }
+    let mut globs = Vec::new();
+    tree.globs("", min_files, &mut globs);
+    globs.sort();
+    globs
+}
+
+/// Writes the profile to `path`, one glob per line.
+pub fn write_profile(path: &Path, globs: &[String]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    let mut contents = globs.join("\n");
+    contents.push('\n');
+    fs::write(path, contents).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(paths: &[&str]) -> BTreeSet<String> {
+        paths.iter().map(|path| path.to_string()).collect()
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_recorded_paths() {
+        let mount = Path::new("/repo");
+        let recording = r#"{"type": "fetch", "path": "a/b.txt"}
+{"type": "fetch", "path": "/repo/c/d.txt"}
+{"type": "fetch", "path": "./e.txt"}
+
+{"type": "fetch", "path": "/elsewhere/f.txt"}
+{"type": "fetch", "path": ".hg/store/00changelog.i"}
+{"type": "fetch", "path": "../g.txt"}
+{"type": "start"}
+"#;
+        assert_eq!(
+            set(&["a/b.txt", "c/d.txt", "e.txt"]),
+            recorded_paths(recording, mount).unwrap()
+        );
+
+        assert!(recorded_paths("a/b.txt\n", mount).is_err());
+        assert!(recorded_paths("{\"events\": []}\n[]\n", mount).is_err());
+    }
+
+    #[test]
+    fn test_check_profile_name() {
+        assert!(check_profile_name("www").is_ok());
+        assert!(check_profile_name("fbandroid.v2").is_ok());
+        for name in ["", ".", "..", "../../x", "a/b", "a\\b"] {
+            assert!(check_profile_name(name).is_err(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_generalize_keeps_sparse_accesses() {
+        let paths = set(&["a/one", "a/two", "b/c/three", "four"]);
+        assert_eq!(
+            vec!["a/one", "a/two", "b/c/three", "four"],
+            generalize(&paths, 3)
+        );
+    }
+
+    #[test]
+    fn test_generalize_directories() {
+        let paths = set(&[
+            "lib/x/1",
+            "lib/x/2",
+            "lib/x/3",
+            "lib/y/1",
+            "lib/y/2",
+            "lib/y/3",
+            "src/1",
+            "src/2",
+            "src/3",
+            "src/4",
+            "src/other/1",
+            "root",
+        ]);
+        // lib only has broadly accessed subdirectories, src also has a
+        // sparsely accessed one.
+        assert_eq!(
+            vec!["lib/**", "root", "src/*", "src/other/1"],
+            generalize(&paths, 3)
+        );
+    }
+
+    #[test]
+    fn test_generalize_never_covers_the_checkout() {
+        let paths = set(&["a/1", "a/2", "b/1", "b/2"]);
+        assert_eq!(vec!["a/*", "b/*"], generalize(&paths, 2));
+    }
+}
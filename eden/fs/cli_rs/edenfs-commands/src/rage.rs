@@ -0,0 +1,193 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+//! edenfsctl rage
+
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Command;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use anyhow::Context;
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::Local;
+use clap::Parser;
+use edenfs_client::DaemonHealthy;
+use edenfs_client::EdenFsInstance;
+
+use crate::ExitCode;
+
+mod archive;
+mod redact;
+mod sections;
+
+use archive::RageArchive;
+use redact::Redactor;
+
+#[derive(Parser, Debug)]
+#[clap(about = "Gather diagnostic information about EdenFS to report an issue")]
+pub struct RageCmd {
+    #[clap(long, help = "Only write the report: do not upload it")]
+    dry_run: bool,
+
+    #[clap(
+        short,
+        long,
+        help = "Where to write the report (defaults to a file in the temporary directory)"
+    )]
+    output: Option<PathBuf>,
+
+    #[clap(
+        long,
+        help = "Command to upload the report with, called with the report path as its last \
+        argument (defaults to the rage.upload-command config)"
+    )]
+    upload_command: Option<String>,
+
+    #[clap(long, help = "Keep the home directory and user name in the report")]
+    no_redact: bool,
+
+    #[clap(
+        long,
+        default_value = "50000000",
+        help = "Maximum uncompressed size of the report in bytes"
+    )]
+    max_size: usize,
+
+    #[clap(
+        long,
+        default_value = "1000000",
+        help = "Maximum number of bytes to include from the end of each log file"
+    )]
+    max_log_size: u64,
+}
+
+impl RageCmd {
+    fn output_path(&self) -> PathBuf {
+        self.output.clone().unwrap_or_else(|| {
+            std::env::temp_dir().join(format!(
+                "edenfs-rage-{}.tar.gz",
+                Local::now().format("%Y%m%d-%H%M%S")
+            ))
+        })
+    }
+
+    fn redactor(&self, instance: &EdenFsInstance) -> Option<Redactor> {
+        if self.no_redact {
+            return None;
+        }
+        let user = std::env::var("USER")
+            .or_else(|_| std::env::var("USERNAME"))
+            .ok();
+        Some(Redactor::new(
+            instance.get_user_home_dir().map(|home| home.as_path()),
+            user.as_deref(),
+        ))
+    }
+
+    fn upload_command(&self, instance: &EdenFsInstance) -> Option<String> {
+        self.upload_command.clone().or_else(|| {
+            let config = instance.get_config().ok()?;
+            config
+                .other
+                .get("rage")?
+                .get("upload-command")?
+                .as_str()
+                .map(|command| command.to_string())
+                .filter(|command| !command.is_empty())
+        })
+    }
+
+    async fn collect(&self, instance: &EdenFsInstance, archive: &mut RageArchive) -> Result<()> {
+        let health = instance
+            .get_health(None)
+            .await
+            .ok()
+            .filter(|health| health.is_healthy());
+        let client = match health {
+            Some(_) => instance.connect(Some(Duration::from_secs(3))).await.ok(),
+            None => None,
+        };
+
+        let system = sections::system_info(client.as_ref(), health.as_ref(), !self.no_redact).await;
+        archive.add("system.txt", system.as_bytes())?;
+        archive.add("config.toml", sections::config(instance).as_bytes())?;
+        archive.add("mounts.txt", sections::mounts(instance).await.as_bytes())?;
+        archive.add(
+            "redirections.txt",
+            sections::redirections(instance).as_bytes(),
+        )?;
+        let processes = sections::processes(health.as_ref().map(|health| health.pid));
+        archive.add("processes.txt", processes.as_bytes())?;
+        archive.add("du.txt", sections::disk_usage(instance).as_bytes())?;
+        if let Some(client) = &client {
+            archive.add("counters.txt", sections::counters(client).await.as_bytes())?;
+        }
+        archive.add("environment.txt", sections::environment().as_bytes())?;
+        if cfg!(unix) {
+            archive.add("mount_table.txt", sections::mount_table().as_bytes())?;
+        }
+
+        // Logs go last: they are the largest, so they are what gets truncated
+        // when the report reaches its size limit.
+        for (name, data) in sections::logs(instance, self.max_log_size) {
+            archive.add(&format!("logs/{}", name), &data)?;
+        }
+        Ok(())
+    }
+
+    fn upload(&self, command: &str, path: &Path) -> Result<()> {
+        let args = shlex::split(command)
+            .filter(|args| !args.is_empty())
+            .ok_or_else(|| anyhow!("Invalid upload command: {}", command))?;
+        let output = Command::new(&args[0])
+            .args(&args[1..])
+            .arg(path)
+            .output()
+            .with_context(|| format!("Failed to run upload command: {}", command))?;
+        print!("{}", String::from_utf8_lossy(&output.stdout));
+        if !output.status.success() {
+            return Err(anyhow!(
+                "Upload command failed with {}: {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr)
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl crate::Subcommand for RageCmd {
+    async fn run(&self) -> Result<ExitCode> {
+        let instance = EdenFsInstance::global();
+        let path = self.output_path();
+
+        eprintln!("Collecting EdenFS diagnostics...");
+        let mut archive = RageArchive::create(&path, self.redactor(instance), self.max_size)?;
+        self.collect(instance, &mut archive).await?;
+        archive.finish()?;
+        println!("Wrote rage report to {}", path.display());
+
+        if self.dry_run {
+            return Ok(0);
+        }
+        match self.upload_command(instance) {
+            Some(command) => {
+                eprintln!("Uploading rage report...");
+                if let Err(e) = self.upload(&command, &path) {
+                    eprintln!("{:#}", e);
+                    return Ok(1);
+                }
+            }
+            None => eprintln!("No upload command is configured: please share the report by hand."),
+        }
+        Ok(0)
+    }
+}
@@ -0,0 +1,90 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+use std::fs::File;
+use std::path::Path;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use anyhow::Context;
+use anyhow::Result;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+use super::redact::Redactor;
+
+const MANIFEST: &str = "MANIFEST.txt";
+
+/// A gzipped tarball of rage sections. The uncompressed size of all sections
+/// is capped; sections that don't fit are truncated to their most recent
+/// data, and the manifest records what was cut.
+pub struct RageArchive {
+    builder: tar::Builder<GzEncoder<File>>,
+    redactor: Option<Redactor>,
+    remaining: usize,
+    manifest: Vec<String>,
+}
+
+impl RageArchive {
+    pub fn create(path: &Path, redactor: Option<Redactor>, max_size: usize) -> Result<Self> {
+        let file = File::create(path)
+            .with_context(|| format!("Failed to create rage archive {}", path.display()))?;
+        Ok(Self {
+            builder: tar::Builder::new(GzEncoder::new(file, Compression::default())),
+            redactor,
+            remaining: max_size,
+            manifest: Vec::new(),
+        })
+    }
+
+    pub fn add(&mut self, name: &str, contents: &[u8]) -> Result<()> {
+        let contents = match &self.redactor {
+            Some(redactor) => redactor
+                .redact(&String::from_utf8_lossy(contents))
+                .into_bytes(),
+            None => contents.to_vec(),
+        };
+
+        let mut line = format!("{:>10}  {}", contents.len(), name);
+        let contents = if contents.len() > self.remaining {
+            line.push_str(&format!(
+                " (truncated to the last {} bytes: size limit reached)",
+                self.remaining
+            ));
+            &contents[contents.len() - self.remaining..]
+        } else {
+            &contents[..]
+        };
+        self.remaining -= contents.len();
+        self.manifest.push(line);
+        self.append(name, contents)
+    }
+
+    fn append(&mut self, name: &str, contents: &[u8]) -> Result<()> {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs()),
+        );
+        header.set_cksum();
+        self.builder
+            .append_data(&mut header, name, contents)
+            .with_context(|| format!("Failed to add {} to the rage archive", name))
+    }
+
+    /// Writes the manifest, which isn't subject to the size limit, and
+    /// flushes the archive to disk.
+    pub fn finish(mut self) -> Result<()> {
+        let manifest = self.manifest.join("\n") + "\n";
+        self.append(MANIFEST, manifest.as_bytes())?;
+        self.builder.into_inner()?.finish()?;
+        Ok(())
+    }
+}
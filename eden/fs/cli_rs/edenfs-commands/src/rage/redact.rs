@@ -0,0 +1,78 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+use std::path::Path;
+
+use regex::Regex;
+
+/// Replaces the user's home directory and user name in paths, so reports can
+/// be shared without revealing who produced them.
+pub struct Redactor {
+    home_dir: Option<String>,
+    user: Option<Regex>,
+}
+
+impl Redactor {
+    pub fn new(home_dir: Option<&Path>, user: Option<&str>) -> Self {
+        let home_dir = home_dir
+            .map(|home| {
+                home.to_string_lossy()
+                    .trim_end_matches(&['/', '\\'][..])
+                    .to_string()
+            })
+            .filter(|home| !home.is_empty());
+        // Only match the user name as a whole path component, so that it
+        // doesn't get replaced inside unrelated words.
+        let user = user.filter(|user| !user.is_empty()).map(|user| {
+            Regex::new(&format!(
+                r#"(?P<start>[/\\]){}(?P<end>[/\\\s"':]|$)"#,
+                regex::escape(user)
+            ))
+            .expect("escaped user name should be a valid regex")
+        });
+        Self { home_dir, user }
+    }
+
+    pub fn redact(&self, text: &str) -> String {
+        let text = match &self.home_dir {
+            Some(home_dir) => text.replace(home_dir.as_str(), "$HOME"),
+            None => text.to_string(),
+        };
+        match &self.user {
+            Some(user) => user.replace_all(&text, "${start}$$USER${end}").into_owned(),
+            None => text,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact() {
+        let redactor = Redactor::new(Some(Path::new("/home/alice/")), Some("alice"));
+        assert_eq!(
+            "$HOME/fbsource (running)",
+            redactor.redact("/home/alice/fbsource (running)")
+        );
+        assert_eq!(
+            "mount /data/users/$USER/www\nowner /Users/$USER",
+            redactor.redact("mount /data/users/alice/www\nowner /Users/alice")
+        );
+        assert_eq!(
+            "user alice at /srv/alicebob",
+            redactor.redact("user alice at /srv/alicebob")
+        );
+    }
+
+    #[test]
+    fn test_redact_nothing() {
+        let redactor = Redactor::new(None, Some(""));
+        assert_eq!("/home/alice", redactor.redact("/home/alice"));
+    }
+}
@@ -0,0 +1,350 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+//! The sections of a rage report. Each one renders to text, with failures
+//! reported inline so that one broken section doesn't lose the others.
+
+use std::fmt::Write;
+use std::fs;
+use std::io::Read;
+use std::io::Seek;
+use std::io::SeekFrom;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use anyhow::Result;
+use edenfs_client::checkout::find_checkout;
+use edenfs_client::checkout::get_mounts;
+use edenfs_client::mounttable::read_mount_table;
+use edenfs_client::redirect::get_effective_redirections;
+use edenfs_client::EdenFsClient;
+use edenfs_client::EdenFsInstance;
+use sysinfo::ProcessExt;
+use sysinfo::System;
+use sysinfo::SystemExt;
+use thrift_types::edenfs::types::DaemonInfo;
+
+use crate::du::format_size;
+use crate::du::usage_for_dir;
+
+/// The counters `edenfsctl top` and the Python rage report on.
+const COUNTER_REGEX: &str = r"((store\.hg.*)|(fuse\.([^\.]*)\..*requests.*)|(object_store\..*))";
+const MAX_LOG_FILES: usize = 5;
+
+/// Environment variables that may be included in the rage. Anything else can
+/// hold credentials, so it is left out.
+const ENVIRONMENT_PREFIXES: &[&str] = &["EDEN", "LC_"];
+const ENVIRONMENT_NAMES: &[&str] = &["LANG", "LANGUAGE", "TZ"];
+
+fn or_error(name: &str, result: Result<String>) -> String {
+    result.unwrap_or_else(|e| format!("Error getting {}: {:#}\n", name, e))
+}
+
+/// With `redact_user`, the user name is replaced like the redactor does in
+/// paths.
+pub async fn system_info(
+    client: Option<&EdenFsClient>,
+    health: Option<&DaemonInfo>,
+    redact_user: bool,
+) -> String {
+    let mut out = String::new();
+    let system = System::new();
+    let user = match redact_user {
+        true => "$USER".to_string(),
+        false => std::env::var("USER")
+            .or_else(|_| std::env::var("USERNAME"))
+            .unwrap_or_default(),
+    };
+    let unknown = || "unknown".to_string();
+    writeln!(out, "User                    : {}", user).ok();
+    writeln!(
+        out,
+        "Hostname                : {}",
+        system.host_name().unwrap_or_else(unknown)
+    )
+    .ok();
+    writeln!(
+        out,
+        "OS                      : {}",
+        system.long_os_version().unwrap_or_else(unknown)
+    )
+    .ok();
+    writeln!(
+        out,
+        "Kernel                  : {}",
+        system.kernel_version().unwrap_or_else(unknown)
+    )
+    .ok();
+    writeln!(out, "Architecture            : {}", std::env::consts::ARCH).ok();
+
+    let (client, health) = match (client, health) {
+        (Some(client), Some(health)) => (client, health),
+        _ => {
+            out.push_str("\nEdenFS is not running\n");
+            return out;
+        }
+    };
+    writeln!(out, "\nEdenFS pid              : {}", health.pid).ok();
+    writeln!(
+        out,
+        "EdenFS command line     : {}",
+        health.commandLine.join(" ")
+    )
+    .ok();
+    if let Some(uptime) = health.uptime {
+        writeln!(out, "EdenFS uptime           : {:.0}s", uptime).ok();
+    }
+    match client.getRegexExportedValues("^build_.*").await {
+        Ok(values) => {
+            for (key, value) in values {
+                writeln!(out, "{:<24}: {}", key, value).ok();
+            }
+        }
+        Err(e) => {
+            writeln!(out, "Error getting build info: {}", e).ok();
+        }
+    }
+    out
+}
+
+pub fn config(instance: &EdenFsInstance) -> String {
+    or_error(
+        "EdenFS config",
+        (|| Ok(toml::to_string(&instance.get_config()?)?))(),
+    )
+}
+
+pub async fn mounts(instance: &EdenFsInstance) -> String {
+    let result: Result<String> = async {
+        let mut out = String::new();
+        for (path, checkout) in get_mounts(instance).await? {
+            writeln!(out, "{}", checkout)?;
+            writeln!(out, "  {}", serde_json::to_string(&checkout)?)?;
+            if let Some(name) = instance.get_configured_mounts_map()?.get(&path) {
+                writeln!(out, "  name: {}", name)?;
+            }
+        }
+        Ok::<_, anyhow::Error>(out)
+    }
+    .await;
+    or_error("the EdenFS mounts", result)
+}
+
+pub fn redirections(instance: &EdenFsInstance) -> String {
+    let result: Result<String> = (|| {
+        let mut out = String::new();
+        for path in instance.get_configured_mounts_map()?.keys() {
+            writeln!(out, "{}", path.display())?;
+            let checkout = match find_checkout(instance, path) {
+                Ok(checkout) => checkout,
+                Err(e) => {
+                    writeln!(out, "\tError finding checkout: {}", e)?;
+                    continue;
+                }
+            };
+            match get_effective_redirections(&checkout) {
+                Ok(redirs) => {
+                    for redir in redirs.values() {
+                        writeln!(
+                            out,
+                            "\t{} {} {} {}",
+                            redir.repo_path.display(),
+                            redir.redir_type,
+                            redir.source,
+                            redir
+                                .state
+                                .as_ref()
+                                .map_or_else(|| "unknown".to_string(), |s| s.to_string())
+                        )?;
+                    }
+                }
+                Err(e) => writeln!(out, "\tError getting redirections: {}", e)?,
+            }
+        }
+        Ok(out)
+    })();
+    or_error("EdenFS redirections", result)
+}
+
+/// Reads up to `max_size` bytes from the end of the file.
+fn read_tail(path: &Path, max_size: u64) -> Result<Vec<u8>> {
+    let mut file = fs::File::open(path)?;
+    let size = file.metadata()?.len();
+    file.seek(SeekFrom::Start(size.saturating_sub(max_size)))?;
+    let mut data = Vec::new();
+    file.read_to_end(&mut data)?;
+    Ok(data)
+}
+
+/// The tails of the most recently modified logs, by file name.
+pub fn logs(instance: &EdenFsInstance, max_size: u64) -> Vec<(String, Vec<u8>)> {
+    let logs_dir = instance.logs_dir();
+    let mut files: Vec<(SystemTime, PathBuf)> = match fs::read_dir(&logs_dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let metadata = entry.metadata().ok()?;
+                if !metadata.is_file() {
+                    return None;
+                }
+                Some((metadata.modified().ok()?, entry.path()))
+            })
+            .collect(),
+        Err(e) => {
+            return vec![(
+                "error.txt".to_string(),
+                format!("Error listing {}: {}\n", logs_dir.display(), e).into_bytes(),
+            )];
+        }
+    };
+    files.sort_by(|a, b| b.0.cmp(&a.0));
+
+    files
+        .into_iter()
+        .take(MAX_LOG_FILES)
+        .map(|(_, path)| {
+            let name = path
+                .file_name()
+                .map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+            let data = read_tail(&path, max_size).unwrap_or_else(|e| {
+                format!("Error reading {}: {}\n", path.display(), e).into_bytes()
+            });
+            (name, data)
+        })
+        .collect()
+}
+
+/// Running EdenFS processes, and the chain of processes that started the
+/// daemon.
+pub fn processes(daemon_pid: Option<i32>) -> String {
+    let mut out = String::new();
+    let mut system = System::new();
+    system.refresh_processes();
+    writeln!(out, "{:>10} {:>10} {:>10} Command", "Pid", "PPid", "Memory").ok();
+    let mut processes: Vec<_> = system
+        .processes()
+        .values()
+        .filter(|process| process.name().contains("eden"))
+        .collect();
+    processes.sort_by_key(|process| process.pid());
+    for process in processes {
+        writeln!(
+            out,
+            "{:>10} {:>10} {:>10} {}",
+            process.pid(),
+            process
+                .parent()
+                .map_or_else(|| "-".to_string(), |ppid| ppid.to_string()),
+            format_size(process.memory() * 1024),
+            process.cmd().join(" ")
+        )
+        .ok();
+    }
+
+    if let Some(pid) = daemon_pid {
+        out.push_str("\nEdenFS process ancestry:\n");
+        let mut pid = pid as u32;
+        let mut depth = 0;
+        // Stop at init, or on a cycle when the parent can't be determined.
+        while pid > 1 && depth < 64 {
+            writeln!(
+                out,
+                "{}{} {}",
+                "  ".repeat(depth),
+                pid,
+                procinfo::exe_name(pid)
+            )
+            .ok();
+            pid = procinfo::parent_pid(pid);
+            depth += 1;
+        }
+    }
+    out
+}
+
+/// Disk usage of the EdenFS state directory.
+pub fn disk_usage(instance: &EdenFsInstance) -> String {
+    let mut out = String::new();
+    for (name, dir) in [
+        ("Local store", instance.storage_dir()),
+        ("Checkouts state", instance.clients_dir()),
+        ("Logs", instance.logs_dir()),
+    ] {
+        match usage_for_dir(&dir, None) {
+            Ok((size, failed)) => {
+                writeln!(
+                    out,
+                    "{:<16}: {} ({})",
+                    name,
+                    format_size(size),
+                    dir.display()
+                )
+                .ok();
+                if !failed.is_empty() {
+                    writeln!(out, "  {} files could not be measured", failed.len()).ok();
+                }
+            }
+            Err(e) => {
+                writeln!(out, "{:<16}: error reading {}: {}", name, dir.display(), e).ok();
+            }
+        }
+    }
+    out
+}
+
+pub async fn counters(client: &EdenFsClient) -> String {
+    let result: Result<String> = async {
+        let mut out = String::new();
+        for (key, value) in client.getRegexCounters(COUNTER_REGEX).await? {
+            writeln!(out, "{}: {}", key, value)?;
+        }
+        Ok::<_, anyhow::Error>(out)
+    }
+    .await;
+    or_error("EdenFS Thrift counters", result)
+}
+
+pub fn environment() -> String {
+    let mut vars: Vec<(String, String)> = std::env::vars_os()
+        .map(|(key, value)| {
+            (
+                key.to_string_lossy().into_owned(),
+                value.to_string_lossy().into_owned(),
+            )
+        })
+        .filter(|(key, _)| {
+            ENVIRONMENT_NAMES.contains(&key.as_str())
+                || ENVIRONMENT_PREFIXES
+                    .iter()
+                    .any(|prefix| key.starts_with(prefix))
+        })
+        .collect();
+    vars.sort();
+    let mut out = String::new();
+    for (key, value) in vars {
+        writeln!(out, "{}={}", key, value).ok();
+    }
+    out
+}
+
+pub fn mount_table() -> String {
+    let result: Result<String> = (|| {
+        let mut out = String::new();
+        for mount in read_mount_table()? {
+            writeln!(
+                out,
+                "{} on {} type {}",
+                mount.device(),
+                mount.mount_point().display(),
+                mount.vfstype()
+            )?;
+        }
+        Ok(out)
+    })();
+    or_error("the mount table", result)
+}
@@ -0,0 +1,272 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+//! edenfsctl remove
+
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use anyhow::Context;
+use anyhow::Result;
+use async_trait::async_trait;
+use clap::Parser;
+use dialoguer::Confirm;
+use edenfs_client::checkout::find_checkout;
+use edenfs_client::checkout::get_mounts;
+use edenfs_client::redirect::get_effective_redirections;
+use edenfs_client::EdenFsInstance;
+use hg_util::path::expand_path;
+
+use crate::util::mount_point::cleanup_mount_point;
+use crate::util::mount_point::resolve_mount_point;
+use crate::util::mount_point::unmount_checkout;
+use crate::ExitCode;
+
+#[derive(Parser, Debug)]
+#[clap(about = "Remove an EdenFS checkout")]
+pub struct RemoveCmd {
+    #[clap(
+        short = 'y',
+        long = "yes",
+        alias = "no-prompt",
+        help = "Do not prompt for confirmation before removing the checkouts."
+    )]
+    no_prompt: bool,
+
+    #[clap(long, hide = true)]
+    preserve_mount_point: bool,
+
+    #[clap(
+        parse(from_str = expand_path),
+        required = true,
+        help = "The EdenFS checkout(s) to remove"
+    )]
+    paths: Vec<PathBuf>,
+}
+
+enum RemoveType {
+    /// A mounted checkout, which the daemon has to unmount first.
+    ActiveMount,
+    /// A configured checkout that isn't mounted: only its configuration and
+    /// mount point are left.
+    InactiveMount,
+    /// A directory EdenFS no longer knows about, most likely left behind by a
+    /// removal that failed half way.
+    Leftover,
+}
+
+fn unmount_timeout_suggestions(path: &Path) -> String {
+    let mut suggestions = String::new();
+    if cfg!(unix) {
+        suggestions.push_str(&format!(
+            "    * `sudo umount -f {}` -> retry `eden rm`\n",
+            path.display()
+        ));
+    }
+    suggestions.push_str(
+        "    * `eden stop` -> retry `eden rm`\n\
+        \x20   * `eden doctor` -> retry `eden rm`\n\
+        \x20   * Reboot your machine -> retry `eden rm`\n",
+    );
+    suggestions
+}
+
+impl RemoveCmd {
+    fn should_prompt(&self) -> bool {
+        !self.no_prompt && atty::is(atty::Stream::Stdin)
+    }
+
+    /// Works out what needs to be removed for `path`, or why it can't be.
+    async fn classify(
+        &self,
+        instance: &EdenFsInstance,
+        path: &Path,
+    ) -> Result<Option<(PathBuf, RemoveType)>> {
+        // A checkout whose mount point is gone can still be removed.
+        let mount_path = resolve_mount_point(path)?;
+        if let Some(checkout) = get_mounts(instance).await?.get(&mount_path) {
+            return Ok(Some(if checkout.state().is_some() {
+                (mount_path, RemoveType::ActiveMount)
+            } else {
+                (mount_path, RemoveType::InactiveMount)
+            }));
+        }
+
+        if !mount_path.exists() {
+            return Err(anyhow!(
+                "{} is neither an EdenFS mount nor an existing directory",
+                path.display()
+            ));
+        }
+        if let Ok(checkout) = find_checkout(instance, &mount_path) {
+            return Err(anyhow!(
+                "{} is not the root of checkout {}, not deleting",
+                path.display(),
+                checkout.path().display()
+            ));
+        }
+        // We can't know what the user wants for a directory that isn't a
+        // checkout, so only remove it when they confirm.
+        if !self.should_prompt() {
+            return Err(anyhow!("{} is not an EdenFS mount", path.display()));
+        }
+        let confirmed = Confirm::new()
+            .with_prompt(format!(
+                "Warning: The following is not an EdenFS Mount: {}. Any files in this \
+                directory will be lost forever. Do you still want to delete {}?",
+                path.display(),
+                path.display()
+            ))
+            .interact()?;
+        Ok(confirmed.then(|| (mount_path, RemoveType::Leftover)))
+    }
+
+    /// Unmounts a checkout and its redirections. The removal can only go on
+    /// if this succeeds or the daemon isn't running.
+    async fn unmount(&self, instance: &EdenFsInstance, mount: &Path) -> Result<()> {
+        // Redirections on Windows are symlinks, removed with the checkout.
+        if cfg!(unix) {
+            if let Err(e) = unmount_redirections(instance, mount).await {
+                eprintln!("ignoring error while unmounting bind mounts: {:#}", e);
+            }
+        }
+
+        let client = match instance.connect(Some(Duration::from_secs(3))).await {
+            Ok(client) => client,
+            // Without a daemon there is nobody to unmount the checkout.
+            Err(_) => return Ok(()),
+        };
+        println!(
+            "Unmounting `{}`. Please be patient: this can take up to 1 minute!",
+            mount.display()
+        );
+        unmount_checkout(&client, mount).await
+    }
+
+    /// Removes the configuration of a checkout and then its mount point.
+    fn destroy(&self, instance: &EdenFsInstance, mount: &Path) -> Result<()> {
+        println!("Deleting mount {}", mount.display());
+        if let Some(client_name) = instance.get_configured_mounts_map()?.get(mount) {
+            let client_dir = instance.config_directory(client_name);
+            match fs::remove_dir_all(&client_dir) {
+                Err(e) if e.kind() != ErrorKind::NotFound => {
+                    return Err(e)
+                        .with_context(|| format!("Failed to remove {}", client_dir.display()));
+                }
+                _ => {}
+            }
+        }
+        instance.remove_path_from_directory_map(mount)?;
+
+        println!("Cleaning up mount {}", mount.display());
+        if mount.exists() {
+            cleanup_mount_point(mount, self.preserve_mount_point)?;
+        }
+        Ok(())
+    }
+
+    fn remove_leftover(&self, path: &Path) -> Result<()> {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(path, fs::Permissions::from_mode(0o755))?;
+        }
+        for entry in fs::read_dir(path)? {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                fs::remove_dir_all(entry.path())?;
+            } else {
+                fs::remove_file(entry.path())?;
+            }
+        }
+        if !self.preserve_mount_point {
+            fs::remove_dir(path)?;
+        }
+        Ok(())
+    }
+}
+
+async fn unmount_redirections(instance: &EdenFsInstance, mount: &Path) -> Result<()> {
+    let checkout = find_checkout(instance, mount)?;
+    for redir in get_effective_redirections(&checkout)?.values() {
+        redir.remove_existing(&checkout, false).await?;
+    }
+    Ok(())
+}
+
+#[async_trait]
+impl crate::Subcommand for RemoveCmd {
+    async fn run(&self) -> Result<ExitCode> {
+        let instance = EdenFsInstance::global();
+
+        let mut mounts = Vec::new();
+        for path in &self.paths {
+            match self.classify(instance, path).await {
+                Ok(Some(mount)) => mounts.push(mount),
+                Ok(None) => return Ok(2),
+                Err(e) => {
+                    eprintln!("error: {:#}", e);
+                    return Ok(1);
+                }
+            }
+        }
+
+        // This permanently destroys data.
+        let checkouts: Vec<String> = mounts
+            .iter()
+            .filter(|(_, remove_type)| !matches!(remove_type, RemoveType::Leftover))
+            .map(|(path, _)| path.display().to_string())
+            .collect();
+        if !checkouts.is_empty() && self.should_prompt() {
+            println!(
+                "Warning: this operation will permanently delete the following checkouts:\n  \
+                {}\n\n\
+                Any uncommitted changes and shelves in this checkout will be lost forever.",
+                checkouts.join("\n  ")
+            );
+            if !Confirm::new().with_prompt("Proceed?").interact()? {
+                println!("Not confirmed");
+                return Ok(2);
+            }
+        }
+
+        let mut exit_code = 0;
+        for (mount, remove_type) in mounts {
+            println!("Removing {}...", mount.display());
+            let result = match remove_type {
+                RemoveType::ActiveMount => {
+                    if let Err(e) = self.unmount(instance, &mount).await {
+                        // Modifying the configuration underneath a mounted
+                        // checkout would leave it in a worse state.
+                        eprintln!("\nerror unmounting {}: {:#}\n\n", mount.display(), e);
+                        eprintln!(
+                            "For unmount timeouts, you can try:\n{}",
+                            unmount_timeout_suggestions(&mount)
+                        );
+                        return Ok(1);
+                    }
+                    self.destroy(instance, &mount)
+                }
+                RemoveType::InactiveMount => self.destroy(instance, &mount),
+                RemoveType::Leftover => self.remove_leftover(&mount),
+            };
+            if let Err(e) = result {
+                eprintln!("error removing {}: {:#}", mount.display(), e);
+                exit_code = 1;
+            }
+        }
+
+        if exit_code == 0 {
+            println!("Success");
+        }
+        Ok(exit_code)
+    }
+}
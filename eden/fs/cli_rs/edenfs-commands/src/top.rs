@@ -7,35 +7,64 @@
 
 //! edenfsctl top
 
-use std::sync::atomic::AtomicBool;
-use std::sync::atomic::Ordering;
-use std::sync::RwLock;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::io::stdout;
+use std::io::Stdout;
+use std::io::Write;
 use std::time::Duration;
+use std::time::Instant;
 
 use anyhow::Result;
 use async_trait::async_trait;
 use clap::Parser;
-use once_cell::sync::Lazy;
-use termwiz::caps::Capabilities;
-use termwiz::color::ColorAttribute;
-use termwiz::input::InputEvent;
-use termwiz::input::KeyCode;
-use termwiz::input::KeyEvent;
-use termwiz::surface::Change;
-use termwiz::terminal::buffered::BufferedTerminal;
-use termwiz::terminal::new_terminal;
-use termwiz::terminal::Terminal;
-use termwiz::widgets::layout::ChildOrientation;
-use termwiz::widgets::layout::Constraints;
-use termwiz::widgets::RenderArgs;
-use termwiz::widgets::Ui;
-use termwiz::widgets::UpdateArgs;
-use termwiz::widgets::Widget;
-use termwiz::widgets::WidgetEvent;
-use termwiz::Error;
-
+use comfy_table::presets::UTF8_BORDERS_ONLY;
+use comfy_table::Table;
+use crossterm::cursor;
+use crossterm::event::Event;
+use crossterm::event::EventStream;
+use crossterm::event::KeyCode;
+use crossterm::event::KeyEvent;
+use crossterm::event::KeyModifiers;
+use crossterm::queue;
+use crossterm::style;
+use crossterm::terminal;
+use edenfs_client::EdenFsInstance;
+use edenfs_utils::humantime::HumanTime;
+use edenfs_utils::humantime::TimeUnit;
+use futures::FutureExt;
+use futures::StreamExt;
+use sysinfo::System;
+use sysinfo::SystemExt;
+
+use crate::minitop::aggregate_processes;
+use crate::minitop::get_live_import_counts;
+use crate::minitop::get_pending_import_counts;
+use crate::minitop::parse_refresh_rate;
+use crate::minitop::update_tracked_processes;
+use crate::minitop::ImportStat;
+use crate::minitop::Process;
+use crate::minitop::TerminalAttributes;
+use crate::minitop::TrackedProcesses;
+use crate::minitop::COLUMN_TITLES;
+use crate::minitop::IMPORT_OBJECT_TYPES;
 use crate::ExitCode;
 
+/// Lines above the process table: the title, import stats and a blank line.
+const HEADER_LINES: usize = 2 + IMPORT_OBJECT_TYPES.len();
+/// Borders and column titles of the process table.
+const TABLE_DECORATION_LINES: usize = 4;
+
+const HELP: &[(&str, &str)] = &[
+    ("q, Ctrl-C", "quit"),
+    ("h", "show this help"),
+    ("Esc", "leave this help"),
+    ("Left, Right", "select the column to sort processes by"),
+    ("r", "reverse the sort order"),
+    ("Up, Down", "scroll the process table"),
+    ("+, -", "increase or decrease the refresh rate by a second"),
+];
+
 #[derive(Parser, Debug)]
 #[clap(about = "Monitor EdenFS accesses by process.")]
 pub struct TopCmd {
@@ -45,367 +74,338 @@ pub struct TopCmd {
     ephemeral: bool,
 
     /// Specify the rate (in seconds) at which eden top updates.
-    #[clap(short, long, default_value = "1")]
-    refresh_rate: u64,
-}
-
-#[derive(PartialEq, Copy, Clone)]
-enum Pages {
-    HelpPage,
-    MainPage,
-}
-
-// top has a few different "views" or "pages". This controls which page will be
-// rendered in the next rendering cycle. Wigets that belong in each view should
-// only declare non zero width and height if their parent page is the active page.
-// widgets should only render themselves if they and their parent page declared
-// a non zero width and height in the _last_ rendering cycle. This is because
-// the size of a widget is determined by its declared size in the last rendering
-// cycle, and it will cause errors to render anything to a widget of zero size.
-// Disclaimer: this is a hack. Ideally termwiz would manage visable and hidden
-// widgets.
-static OBSERVED_ACTIVE_PAGE: Lazy<RwLock<Pages>> = Lazy::new(|| RwLock::new(Pages::MainPage));
-
-struct EdenTopHeader {}
+    #[clap(short, long, default_value = "1", parse(from_str = parse_refresh_rate))]
+    refresh_rate: Duration,
 
-impl EdenTopHeader {
-    pub fn new() -> Self {
-        Self {}
-    }
-}
-
-impl Widget for EdenTopHeader {
-    fn render(&mut self, args: &mut RenderArgs) {
-        args.surface
-            .add_change(Change::ClearScreen(ColorAttribute::Default));
-        args.surface.add_change("eden top");
-    }
-
-    fn get_size_constraints(&self) -> Constraints {
-        let mut c = Constraints::default();
-        c.set_fixed_height(1);
-        c.set_pct_width(100);
-        c.child_orientation = ChildOrientation::Horizontal;
-        c
-    }
+    /// Only show processes accessing the mount with this name (can be
+    /// repeated).
+    #[clap(long)]
+    mount: Vec<String>,
 }
 
-// This indicates whether the help page delcared itself to have non zero width
-// and height in the last cycle. The Help page is allowed to render itself if it
-// declared itself to have non zero height.
-// Disclaimer: this is a super hack. This could be a member variable of
-// HelpPage, but we keep the state global to match the main page state,
-// this will also allow any widgets nested in the help page to share state,
-// should we add any of these.
-static HELP_SHOULD_RENDER: AtomicBool = AtomicBool::new(false);
-
-struct HelpPage;
-
-impl HelpPage {
-    pub fn new() -> Self {
-        Self {}
-    }
-
-    fn set_should_render(&self, new_should_render: bool) {
-        HELP_SHOULD_RENDER.store(new_should_render, Ordering::SeqCst)
-    }
-
-    fn get_should_render(&self) -> bool {
-        HELP_SHOULD_RENDER.load(Ordering::SeqCst)
-    }
-
-    pub fn is_active(&self) -> bool {
-        *OBSERVED_ACTIVE_PAGE.read().unwrap() == Pages::HelpPage
-    }
+/// The columns of the process table, in the order of `COLUMN_TITLES`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Column {
+    Pid,
+    Mount,
+    Reads,
+    Writes,
+    Total,
+    Fetches,
+    Memory,
+    Disk,
+    Imports,
+    TimeSpent,
+    LastAccess,
+    Cmd,
 }
 
-impl Widget for HelpPage {
-    fn render(&mut self, args: &mut RenderArgs) {
-        if self.get_should_render() {
-            args.surface
-                .add_change(Change::ClearScreen(ColorAttribute::Default));
-            args.surface.add_change("eden help");
-        }
-    }
-
-    fn get_size_constraints(&self) -> Constraints {
-        if self.is_active() {
-            self.set_should_render(true);
-            Constraints::default()
-        } else {
-            self.set_should_render(false);
-            Constraints::with_fixed_width_height(0, 0)
+const COLUMNS: &[Column] = &[
+    Column::Pid,
+    Column::Mount,
+    Column::Reads,
+    Column::Writes,
+    Column::Total,
+    Column::Fetches,
+    Column::Memory,
+    Column::Disk,
+    Column::Imports,
+    Column::TimeSpent,
+    Column::LastAccess,
+    Column::Cmd,
+];
+
+impl Column {
+    /// The natural order of the column: text in alphabetical order and
+    /// everything else with the largest or most recent value first.
+    fn compare(&self, a: &Process, b: &Process) -> Ordering {
+        let (a_counts, b_counts) = (&a.access_counts, &b.access_counts);
+        match self {
+            Column::Pid => a.pid.cmp(&b.pid),
+            Column::Mount => a.mount_name.cmp(&b.mount_name),
+            Column::Reads => b_counts.fsChannelReads.cmp(&a_counts.fsChannelReads),
+            Column::Writes => b_counts.fsChannelWrites.cmp(&a_counts.fsChannelWrites),
+            Column::Total => b_counts.fsChannelTotal.cmp(&a_counts.fsChannelTotal),
+            Column::Fetches => b.fetch_counts.cmp(&a.fetch_counts),
+            Column::Memory => b_counts
+                .fsChannelMemoryCacheImports
+                .cmp(&a_counts.fsChannelMemoryCacheImports),
+            Column::Disk => b_counts
+                .fsChannelDiskCacheImports
+                .cmp(&a_counts.fsChannelDiskCacheImports),
+            Column::Imports => b_counts
+                .fsChannelBackingStoreImports
+                .cmp(&a_counts.fsChannelBackingStoreImports),
+            Column::TimeSpent => b_counts
+                .fsChannelDurationNs
+                .cmp(&a_counts.fsChannelDurationNs),
+            Column::LastAccess => b.last_access_time.cmp(&a.last_access_time),
+            Column::Cmd => a.cmd.cmp(&b.cmd),
         }
     }
 }
 
-// This indicates whether the main page delcared itself to have non zero width
-// and height in the last cycle. The Main page and its nested widgets are
-// allowed to render themselves if the main page widgets declared themselves to
-// have non zero height. They all should either declare 0 size or non zero size.
-// There should not be a mix.
-// Disclaimer: this is a super hack. We keep this global state to allow
-// cordinating between child widgets of the main page.
-static MAIN_SHOULD_RENDER: AtomicBool = AtomicBool::new(false);
-struct MainPage;
-
-impl MainPage {
-    pub fn new() -> Self {
-        Self {}
-    }
-
-    fn set_should_render(&self, new_should_render: bool) {
-        MAIN_SHOULD_RENDER.store(new_should_render, Ordering::SeqCst)
-    }
-
-    pub fn is_active(&self) -> bool {
-        *OBSERVED_ACTIVE_PAGE.read().unwrap() == Pages::MainPage
-    }
+fn process_row(process: &Process) -> Result<Vec<String>> {
+    let counts = &process.access_counts;
+    Ok(vec![
+        process.pid.to_string(),
+        process.mount_name.clone(),
+        counts.fsChannelReads.to_string(),
+        counts.fsChannelWrites.to_string(),
+        counts.fsChannelTotal.to_string(),
+        process.fetch_counts.to_string(),
+        counts.fsChannelMemoryCacheImports.to_string(),
+        counts.fsChannelDiskCacheImports.to_string(),
+        counts.fsChannelBackingStoreImports.to_string(),
+        HumanTime::from(Duration::from_nanos(counts.fsChannelDurationNs.try_into()?))
+            .simple_human_time(TimeUnit::Nanoseconds),
+        HumanTime::from(process.last_access_time.elapsed()).simple_human_time(TimeUnit::Seconds),
+        process.cmd.clone(),
+    ])
 }
 
-impl Widget for MainPage {
-    fn render(&mut self, _args: &mut RenderArgs) {}
-
-    fn get_size_constraints(&self) -> Constraints {
-        if self.is_active() {
-            self.set_should_render(true);
-            let mut c = Constraints {
-                child_orientation: ChildOrientation::Vertical,
-                ..Default::default()
-            };
-            c.set_pct_width(100);
-            c.set_pct_height(100);
-            c
-        } else {
-            self.set_should_render(false);
-            Constraints::with_fixed_width_height(0, 0)
-        }
-    }
+#[derive(PartialEq)]
+enum Page {
+    Main,
+    Help,
 }
 
-struct TopLevelStatsSection {}
-
-impl TopLevelStatsSection {
-    pub fn new() -> Self {
-        Self {}
-    }
-
-    // the main wiget sets should render for this widget
-
-    fn get_should_render(&self) -> bool {
-        MAIN_SHOULD_RENDER.load(Ordering::SeqCst)
-    }
-
-    pub fn is_active(&self) -> bool {
-        *OBSERVED_ACTIVE_PAGE.read().unwrap() == Pages::MainPage
-    }
+/// What the user selected with the keyboard.
+struct TopState {
+    page: Page,
+    sort_column: usize,
+    reverse: bool,
+    scroll: usize,
+    refresh_rate: Duration,
 }
 
-impl Widget for TopLevelStatsSection {
-    fn render(&mut self, args: &mut RenderArgs) {
-        if self.get_should_render() {
-            args.surface
-                .add_change(Change::ClearScreen(ColorAttribute::Default));
-            args.surface.add_change("top level stats");
+impl TopState {
+    /// Returns false if top should exit.
+    fn handle_event(&mut self, event: Event) -> bool {
+        let key = match event {
+            Event::Key(key) => key,
+            _ => return true,
+        };
+        if key == KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL) {
+            return false;
         }
-    }
-
-    fn get_size_constraints(&self) -> Constraints {
-        if self.is_active() {
-            let mut c = Constraints {
-                child_orientation: ChildOrientation::Vertical,
-                ..Default::default()
-            };
-            c.set_fixed_height(4);
-            c.set_pct_width(100);
-            c
-        } else {
-            Constraints::with_fixed_width_height(0, 0)
+        match key.code {
+            KeyCode::Char('q') if self.page == Page::Main => return false,
+            KeyCode::Char('h') => self.page = Page::Help,
+            KeyCode::Esc => self.page = Page::Main,
+            KeyCode::Left => {
+                self.sort_column = (self.sort_column + COLUMNS.len() - 1) % COLUMNS.len();
+            }
+            KeyCode::Right => self.sort_column = (self.sort_column + 1) % COLUMNS.len(),
+            KeyCode::Char('r') => self.reverse = !self.reverse,
+            KeyCode::Up => self.scroll = self.scroll.saturating_sub(1),
+            KeyCode::Down => self.scroll += 1,
+            KeyCode::Char('+') => self.refresh_rate += Duration::from_secs(1),
+            KeyCode::Char('-') if self.refresh_rate > Duration::from_secs(1) => {
+                self.refresh_rate -= Duration::from_secs(1);
+            }
+            _ => {}
         }
-    }
-}
-
-struct ProcessTableSection {}
-
-impl ProcessTableSection {
-    pub fn new() -> Self {
-        Self {}
-    }
-
-    // the main wiget sets should render for this widget
-    fn get_should_render(&self) -> bool {
-        MAIN_SHOULD_RENDER.load(Ordering::SeqCst)
+        true
     }
 
-    pub fn is_active(&self) -> bool {
-        *OBSERVED_ACTIVE_PAGE.read().unwrap() == Pages::MainPage
+    fn sort(&self, processes: &mut [Process]) {
+        let column = COLUMNS[self.sort_column];
+        processes.sort_by(|a, b| {
+            let ordering = column.compare(a, b);
+            if self.reverse {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        });
     }
 }
 
-impl Widget for ProcessTableSection {
-    fn render(&mut self, args: &mut RenderArgs) {
-        if self.get_should_render() {
-            args.surface
-                .add_change(Change::ClearScreen(ColorAttribute::Default));
-            args.surface.add_change("process table stats");
-        }
-    }
+fn print_line(stdout: &mut Stdout, line: &str) -> Result<()> {
+    queue!(stdout, style::Print(line), cursor::MoveToNextLine(1))?;
+    Ok(())
+}
 
-    fn get_size_constraints(&self) -> Constraints {
-        if self.is_active() {
-            let mut c = Constraints {
-                child_orientation: ChildOrientation::Vertical,
-                ..Default::default()
+impl TopCmd {
+    fn render_main(
+        &self,
+        stdout: &mut Stdout,
+        state: &mut TopState,
+        mut processes: Vec<Process>,
+        pending_imports: &BTreeMap<String, ImportStat>,
+        live_imports: &BTreeMap<String, ImportStat>,
+    ) -> Result<()> {
+        let (_, rows) = terminal::size()?;
+
+        print_line(
+            stdout,
+            &format!(
+                "eden top    refresh every {}s    sorted by {}{}    press h for help",
+                state.refresh_rate.as_secs(),
+                COLUMN_TITLES[state.sort_column],
+                if state.reverse { " (reversed)" } else { "" }
+            ),
+        )?;
+        for import_type in IMPORT_OBJECT_TYPES {
+            let format_stat = |kind: &str, stat: Option<&ImportStat>| match stat {
+                Some(stat) => format!(
+                    "total {} {}: {} ({:.3}s)",
+                    kind,
+                    import_type,
+                    stat.count,
+                    stat.max_duration_us as f64 / 1000000.0
+                ),
+                None => format!("total {} {}: -", kind, import_type),
             };
-            c.set_pct_width(100);
-            c
-        } else {
-            Constraints::with_fixed_width_height(0, 0)
+            print_line(
+                stdout,
+                &format!(
+                    "{:<40} {}",
+                    format_stat("pending", pending_imports.get(*import_type)),
+                    format_stat("live", live_imports.get(*import_type))
+                ),
+            )?;
         }
-    }
-}
-
-struct BasePage {
-    height: usize,
-    width: usize,
-}
+        print_line(stdout, "")?;
 
-impl BasePage {
-    pub fn new(inital_height: usize, initial_width: usize) -> Self {
-        Self {
-            height: inital_height,
-            width: initial_width,
+        if !self.mount.is_empty() {
+            processes.retain(|process| self.mount.contains(&process.mount_name));
         }
-    }
-}
-
-impl Widget for BasePage {
-    fn process_event(&mut self, event: &WidgetEvent, _args: &mut UpdateArgs) -> bool {
-        match event {
-            WidgetEvent::Input(InputEvent::Resized { rows, cols }) => {
-                self.height = rows.clone();
-                self.width = cols.clone();
+        state.sort(&mut processes);
+
+        let visible_rows = (rows as usize)
+            .saturating_sub(HEADER_LINES + TABLE_DECORATION_LINES)
+            .max(1);
+        state.scroll = state
+            .scroll
+            .min(processes.len().saturating_sub(visible_rows));
+
+        let mut table = Table::new();
+        table.load_preset(UTF8_BORDERS_ONLY);
+        table.set_header(COLUMN_TITLES.iter().enumerate().map(|(i, title)| {
+            if i == state.sort_column {
+                format!(
+                    "{} {}",
+                    title,
+                    if state.reverse {
+                        '\u{25B2}'
+                    } else {
+                        '\u{25BC}'
+                    }
+                )
+            } else {
+                title.to_string()
             }
-            _ => {}
-        };
-        true
+        }));
+        for process in processes.iter().skip(state.scroll).take(visible_rows) {
+            table.add_row(process_row(process)?);
+        }
+        for line in table.lines() {
+            print_line(stdout, &line)?;
+        }
+        Ok(())
     }
 
-    fn render(&mut self, _args: &mut RenderArgs) {}
-
-    fn get_size_constraints(&self) -> Constraints {
-        let mut c = Constraints {
-            child_orientation: ChildOrientation::Vertical,
-            ..Default::default()
-        };
-        // we should be doing this, but there is a bug that the width turns
-        // this into 1 column so we use a fized width to avoid this for now
-        // c.set_pct_width(100);
-        // c.set_pct_height(100);
-        c.set_fixed_width(self.width as u16);
-        c.set_fixed_height(self.height as u16);
-        c
+    fn render_help(&self, stdout: &mut Stdout) -> Result<()> {
+        print_line(stdout, "eden top help")?;
+        print_line(stdout, "")?;
+        for (keys, description) in HELP {
+            print_line(stdout, &format!("  {:<14} {}", keys, description))?;
+        }
+        print_line(stdout, "")?;
+        print_line(
+            stdout,
+            "READS, WRITES and TOTAL COUNT are filesystem requests made by the process.",
+        )?;
+        print_line(
+            stdout,
+            "FETCHES counts objects fetched for the process since EdenFS started, and \
+            MEMORY, DISK and IMPORTS where its requests found them.",
+        )?;
+        Ok(())
     }
 }
 
-impl TopCmd {
-    fn main_loop(&self) -> Result<(), Error> {
-        let caps = Capabilities::new_from_env()?;
-        let mut terminal = new_terminal(caps)?;
-        // getting the screen size here is a bit of a hack, we should not actually read
-        // this value, but do so to get around a bug with percentage based widget size
-        let screen_size = terminal.get_screen_size()?;
-        let mut buf = BufferedTerminal::new(terminal)?;
-        buf.terminal().set_raw_mode()?;
-
-        let mut ui = Ui::new();
-        let base_id = ui.set_root(BasePage::new(screen_size.rows, screen_size.cols));
-        ui.add_child(base_id, EdenTopHeader::new());
-        let main_id = ui.add_child(base_id, MainPage::new());
-        let _help_id = ui.add_child(base_id, HelpPage::new());
-        ui.add_child(main_id, TopLevelStatsSection::new());
-        ui.add_child(main_id, ProcessTableSection::new());
+#[async_trait]
+impl crate::Subcommand for TopCmd {
+    async fn run(&self) -> Result<ExitCode> {
+        let client = EdenFsInstance::global().connect(None).await?;
+        let mut tracked_processes = TrackedProcesses::new();
+        let mut system = System::new();
+        let mut state = TopState {
+            page: Page::Main,
+            sort_column: COLUMNS
+                .iter()
+                .position(|column| *column == Column::LastAccess)
+                .unwrap_or_default(),
+            reverse: false,
+            scroll: 0,
+            refresh_rate: self.refresh_rate,
+        };
 
-        // allow resize events to go to the base, so that the high level size gets updated
-        // with resizing
-        ui.set_focus(base_id);
+        let _attributes = TerminalAttributes::new()
+            .disable_line_wrap()?
+            .enter_alt_screen()?
+            .enter_raw_mode()?;
+        let mut stdout = stdout();
+        let mut events = EventStream::new();
 
+        let mut pending_imports = BTreeMap::new();
+        let mut live_imports = BTreeMap::new();
+        let mut next_update = Instant::now();
         loop {
-            // let the widgets process events
-            ui.process_event_queue()?;
-
-            // After updating and processing all of the widgets, compose them
-            // and render them to the screen.
-            if ui.render_to_screen(&mut buf)? {
-                // We have more events to process immediately; don't block waiting
-                // for input below, but jump to the top of the loop to re-run the
-                // updates.
-                continue;
-            }
-            // Compute an optimized delta to apply to the terminal and display it
-            buf.flush()?;
-
-            match buf
-                .terminal()
-                .poll_input(Some(Duration::from_secs(self.refresh_rate)))
-            {
-                Ok(Some(InputEvent::Resized { rows, cols })) => {
-                    // TODO(kmancini): this is working around a bug where we
-                    // don't realize that we should redraw everything on resize
-                    // in BufferedTerminal.
-                    buf.add_change(Change::ClearScreen(Default::default()));
-                    buf.resize(cols, rows);
-                    ui.queue_event(WidgetEvent::Input(InputEvent::Resized { rows, cols }));
-                }
-                Ok(Some(input)) => match input {
-                    InputEvent::Key(KeyEvent {
-                        key: KeyCode::Char('q'),
-                        ..
-                    }) => {
-                        break;
-                    }
-                    InputEvent::Key(KeyEvent {
-                        key: KeyCode::Char('h'),
-                        ..
-                    }) => {
-                        *OBSERVED_ACTIVE_PAGE.write().unwrap() = Pages::HelpPage;
-                    }
-                    InputEvent::Key(KeyEvent {
-                        key: KeyCode::Escape,
-                        ..
-                    }) => {
-                        *OBSERVED_ACTIVE_PAGE.write().unwrap() = Pages::MainPage;
-                    }
-                    input => {
-                        ui.queue_event(WidgetEvent::Input(input));
-                    }
-                },
-                Ok(None) => {}
-                Err(e) => {
-                    print!("{:?}\r\n", e);
-                    break;
+            if Instant::now() >= next_update {
+                if self.ephemeral {
+                    tracked_processes.clear();
                 }
+                client.flushStatsNow().await?;
+                system.refresh_processes();
+                (pending_imports, live_imports) = tokio::try_join!(
+                    get_pending_import_counts(&client),
+                    get_live_import_counts(&client)
+                )?;
+                let counts = client
+                    .getAccessCounts(state.refresh_rate.as_secs().try_into()?)
+                    .await?;
+                update_tracked_processes(&mut tracked_processes, &counts)?;
+                next_update = Instant::now() + state.refresh_rate;
             }
-        }
-        // clear the sceen before we exit because the terminal
-        // prompt will write over the eden top stuff and then
-        // the next command output and eden top output gets
-        // jumbled together.
-        buf.add_change(Change::ClearScreen(Default::default()));
-        buf.flush()?;
-        Ok(())
-    }
-}
 
-#[async_trait]
-impl crate::Subcommand for TopCmd {
-    async fn run(&self) -> Result<ExitCode> {
-        match self.main_loop() {
-            Ok(_) => Ok(0),
-            Err(cause) => {
-                println!("Error: {}", cause);
-                Ok(1)
+            queue!(
+                stdout,
+                terminal::Clear(terminal::ClearType::All),
+                cursor::MoveTo(0, 0)
+            )?;
+            match state.page {
+                Page::Main => self.render_main(
+                    &mut stdout,
+                    &mut state,
+                    aggregate_processes(&tracked_processes, &system),
+                    &pending_imports,
+                    &live_imports,
+                )?,
+                Page::Help => self.render_help(&mut stdout)?,
             }
+            stdout.flush()?;
+
+            // Redraw right away on key presses, but only poll EdenFS once
+            // per refresh period since access counts are incremental.
+            let delay = tokio::time::sleep_until(next_update.into());
+            let event = events.next().fuse();
+            tokio::select! {
+                _ = delay => {}
+                maybe_event = event => {
+                    match maybe_event {
+                        Some(event) => {
+                            if !state.handle_event(event?) {
+                                return Ok(0);
+                            }
+                        }
+                        None => return Ok(0),
+                    }
+                }
+            };
         }
     }
 }
@@ -0,0 +1,390 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+//! edenfsctl trace
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::BufWriter;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+
+use anyhow::Context;
+use anyhow::Result;
+use async_trait::async_trait;
+use clap::Parser;
+#[cfg(fbcode_build)]
+use edenfs_client::checkout::find_checkout;
+#[cfg(fbcode_build)]
+use edenfs_client::EdenFsInstance;
+use sysinfo::Pid;
+use sysinfo::ProcessExt;
+use sysinfo::System;
+use sysinfo::SystemExt;
+
+use crate::util::expand_path_or_cwd;
+use crate::ExitCode;
+use crate::Subcommand;
+
+mod event;
+
+use event::TraceEvent;
+use event::TraceFilter;
+use event::TraceFormatter;
+
+// Event categories of `traceFsEvents`, from streamingeden.thrift.
+#[cfg(fbcode_build)]
+const FS_EVENT_READ: i64 = 1;
+#[cfg(fbcode_build)]
+const FS_EVENT_WRITE: i64 = 2;
+
+#[derive(Parser, Debug)]
+#[clap(about = "Trace EdenFS requests as they happen")]
+pub struct TraceCmd {
+    #[clap(subcommand)]
+    subcommand: TraceSubcommand,
+}
+
+#[derive(Parser, Debug)]
+enum TraceSubcommand {
+    #[clap(about = "Trace filesystem requests of a checkout")]
+    Fs {
+        #[clap(
+            parse(try_from_str = expand_path_or_cwd),
+            default_value = "",
+            help = "The EdenFS mount point path."
+        )]
+        mount: PathBuf,
+        #[clap(long, help = "Limit trace to read operations")]
+        reads: bool,
+        #[clap(long, help = "Limit trace to write operations")]
+        writes: bool,
+        #[clap(flatten)]
+        options: TraceOptions,
+    },
+    #[clap(about = "Trace Thrift requests to EdenFS")]
+    Thrift {
+        #[clap(flatten)]
+        options: TraceOptions,
+    },
+    #[clap(about = "Trace source control imports of a checkout")]
+    Hg {
+        #[clap(
+            parse(try_from_str = expand_path_or_cwd),
+            default_value = "",
+            help = "The EdenFS mount point path."
+        )]
+        mount: PathBuf,
+        #[clap(long, help = "Only show imports of paths starting with PREFIX")]
+        path: Option<String>,
+        #[clap(flatten)]
+        options: TraceOptions,
+    },
+    #[clap(about = "Print a trace recorded with --record")]
+    Replay {
+        #[clap(help = "The recorded trace")]
+        file: PathBuf,
+        #[clap(long, help = "Only show events for paths starting with PREFIX")]
+        path: Option<String>,
+        #[clap(flatten)]
+        options: TraceOptions,
+    },
+}
+
+#[derive(Parser, Debug)]
+struct TraceOptions {
+    #[clap(long, help = "Only show requests from this process (can be repeated)")]
+    pid: Vec<u32>,
+    #[clap(
+        long,
+        help = "Only show this type of event, e.g. a FUSE opcode, a Thrift method or \
+        'blob' and 'tree' for imports (can be repeated)"
+    )]
+    event: Vec<String>,
+    #[clap(
+        short,
+        long,
+        help = "Show import priority and cause, and the requesting process"
+    )]
+    verbose: bool,
+    #[clap(
+        long,
+        help = "Also write all traced events to FILE, to be printed later with \
+        `edenfsctl trace replay`"
+    )]
+    record: Option<PathBuf>,
+}
+
+impl TraceOptions {
+    fn filter(&self, path_prefix: Option<&String>) -> TraceFilter {
+        TraceFilter {
+            pids: self.pid.clone(),
+            path_prefix: path_prefix.cloned(),
+            operations: self.event.clone(),
+        }
+    }
+}
+
+/// Prints the events that pass the filter, and records all of them if
+/// requested, so a recording can be replayed with different filters.
+#[cfg_attr(not(fbcode_build), allow(dead_code))]
+struct TracePrinter {
+    filter: TraceFilter,
+    formatter: TraceFormatter,
+    recorder: Option<BufWriter<File>>,
+    processes: ProcessNames,
+}
+
+#[cfg_attr(not(fbcode_build), allow(dead_code))]
+impl TracePrinter {
+    fn new(options: &TraceOptions, path_prefix: Option<&String>) -> Result<Self> {
+        let recorder = match &options.record {
+            Some(path) => Some(BufWriter::new(File::create(path).with_context(|| {
+                format!("Failed to create trace recording {}", path.display())
+            })?)),
+            None => None,
+        };
+        Ok(Self {
+            filter: options.filter(path_prefix),
+            formatter: TraceFormatter::new(options.verbose),
+            recorder,
+            processes: ProcessNames::default(),
+        })
+    }
+
+    fn handle(&mut self, mut event: TraceEvent) -> Result<()> {
+        if event.process_name.is_none() {
+            event.process_name = event.pid.and_then(|pid| self.processes.get(pid));
+        }
+        if let Some(recorder) = self.recorder.as_mut() {
+            serde_json::to_writer(&mut *recorder, &event)?;
+            recorder.write_all(b"\n")?;
+            // Keep the recording usable if the trace is interrupted.
+            recorder.flush()?;
+        }
+        if self.filter.matches(&event) {
+            if let Some(line) = self.formatter.format(&event) {
+                println!("{}", line);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Names of client processes, for events that only come with a pid.
+#[derive(Default)]
+#[cfg_attr(not(fbcode_build), allow(dead_code))]
+struct ProcessNames {
+    system: System,
+    names: HashMap<u32, Option<String>>,
+}
+
+#[cfg_attr(not(fbcode_build), allow(dead_code))]
+impl ProcessNames {
+    fn get(&mut self, pid: u32) -> Option<String> {
+        let system = &mut self.system;
+        self.names
+            .entry(pid)
+            .or_insert_with(|| {
+                system.refresh_process(pid as Pid);
+                system
+                    .process(pid as Pid)
+                    .map(|process| process.name().to_string())
+            })
+            .clone()
+    }
+}
+
+impl TraceCmd {
+    fn replay(&self, file: &Path, path: Option<&String>, options: &TraceOptions) -> Result<()> {
+        let reader = BufReader::new(
+            File::open(file)
+                .with_context(|| format!("Failed to open trace recording {}", file.display()))?,
+        );
+        let filter = options.filter(path);
+        let mut formatter = TraceFormatter::new(options.verbose);
+        for (index, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.is_empty() {
+                continue;
+            }
+            let event: TraceEvent = serde_json::from_str(&line).with_context(|| {
+                format!("Invalid event on line {} of {}", index + 1, file.display())
+            })?;
+            if filter.matches(&event) {
+                if let Some(line) = formatter.format(&event) {
+                    println!("{}", line);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    #[cfg(fbcode_build)]
+    async fn trace_fs(
+        &self,
+        mount: &Path,
+        reads: bool,
+        writes: bool,
+        options: &TraceOptions,
+    ) -> Result<()> {
+        use futures::StreamExt;
+
+        let (mount_path, mount_point) = get_mount_point(mount)?;
+        let mut mask = 0;
+        if reads {
+            mask |= FS_EVENT_READ;
+        }
+        if writes {
+            mask |= FS_EVENT_WRITE;
+        }
+
+        let mut printer = TracePrinter::new(options, None)?;
+        let client = EdenFsInstance::global()
+            .connect_streaming(None)
+            .await
+            .context("Unable to establish Thrift connection to EdenFS server")?;
+        let mut stream = client.traceFsEvents(&mount_point, mask).await?;
+        while let Some(event) = stream.next().await {
+            match event {
+                Ok(event) => {
+                    if let Some(event) = TraceEvent::from_fs(event) {
+                        printer.handle(event)?;
+                    }
+                }
+                Err(e) => println!("Error: {:?}", e),
+            }
+        }
+        println!("{} was unmounted", mount_path.display());
+        Ok(())
+    }
+
+    #[cfg(fbcode_build)]
+    async fn trace_thrift(&self, options: &TraceOptions) -> Result<()> {
+        use futures::StreamExt;
+
+        let mut printer = TracePrinter::new(options, None)?;
+        let instance = EdenFsInstance::global();
+        let client = instance
+            .connect(None)
+            .await
+            .context("Unable to establish Thrift connection to EdenFS server")?;
+        let stream_client = instance
+            .connect_streaming(None)
+            .await
+            .context("Unable to establish Thrift connection to EdenFS server")?;
+
+        let mut stream = stream_client.traceThriftRequestEvents().await?;
+        let outstanding = client.debugOutstandingThriftRequests().await?;
+        if !outstanding.is_empty() {
+            let header = "Outstanding Thrift requests";
+            println!("{}\n{}", header, "-".repeat(header.len()));
+            for request in outstanding {
+                println!("  {}: {}", request.requestId, request.method);
+            }
+            println!();
+        }
+        let header = "Ongoing Thrift requests";
+        println!("{}\n{}", header, "-".repeat(header.len()));
+
+        while let Some(event) = stream.next().await {
+            match event {
+                Ok(event) => {
+                    if let Some(event) = TraceEvent::from_thrift(event) {
+                        printer.handle(event)?;
+                    }
+                }
+                Err(e) => println!("Error: {:?}", e),
+            }
+        }
+        Ok(())
+    }
+
+    #[cfg(fbcode_build)]
+    async fn trace_hg(
+        &self,
+        mount: &Path,
+        path: Option<&String>,
+        options: &TraceOptions,
+    ) -> Result<()> {
+        use futures::StreamExt;
+
+        let (mount_path, mount_point) = get_mount_point(mount)?;
+        let mut printer = TracePrinter::new(options, path)?;
+        let client = EdenFsInstance::global()
+            .connect_streaming(None)
+            .await
+            .context("Unable to establish Thrift connection to EdenFS server")?;
+        let mut stream = client.traceHgEvents(&mount_point).await?;
+        while let Some(event) = stream.next().await {
+            match event {
+                Ok(event) => {
+                    if let Some(event) = TraceEvent::from_hg(event) {
+                        printer.handle(event)?;
+                    }
+                }
+                Err(e) => println!("Error: {:?}", e),
+            }
+        }
+        println!("{} was unmounted", mount_path.display());
+        Ok(())
+    }
+}
+
+/// The root of the checkout containing `path`, and its path as sent over
+/// Thrift.
+#[cfg(fbcode_build)]
+fn get_mount_point(path: &Path) -> Result<(PathBuf, Vec<u8>)> {
+    let checkout = find_checkout(EdenFsInstance::global(), path)?;
+    let mount_path = checkout.path();
+    #[cfg(unix)]
+    let mount_point = {
+        use std::os::unix::ffi::OsStrExt;
+        mount_path.as_os_str().as_bytes().to_vec()
+    };
+    // SAFETY: paths on Windows are Unicode
+    #[cfg(windows)]
+    let mount_point = mount_path.to_string_lossy().into_owned().into_bytes();
+    Ok((mount_path, mount_point))
+}
+
+#[async_trait]
+impl Subcommand for TraceCmd {
+    async fn run(&self) -> Result<ExitCode> {
+        match &self.subcommand {
+            TraceSubcommand::Replay {
+                file,
+                path,
+                options,
+            } => self.replay(file, path.as_ref(), options)?,
+            #[cfg(fbcode_build)]
+            TraceSubcommand::Fs {
+                mount,
+                reads,
+                writes,
+                options,
+            } => self.trace_fs(mount, *reads, *writes, options).await?,
+            #[cfg(fbcode_build)]
+            TraceSubcommand::Thrift { options } => self.trace_thrift(options).await?,
+            #[cfg(fbcode_build)]
+            TraceSubcommand::Hg {
+                mount,
+                path,
+                options,
+            } => self.trace_hg(mount, path.as_ref(), options).await?,
+            #[cfg(not(fbcode_build))]
+            _ => {
+                eprintln!("not supported in non-fbcode build");
+                return Ok(1);
+            }
+        }
+        Ok(0)
+    }
+}
@@ -0,0 +1,438 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+//! Trace events in a form that can be filtered, printed and recorded.
+//!
+//! Events from the different trace streams are converted to a single
+//! `TraceEvent` type, so a recording is the same JSON lines whatever was
+//! traced, and replaying it goes through the same filters and output as a
+//! live trace.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use serde::Deserialize;
+use serde::Serialize;
+use thrift_streaming::types::FsEvent;
+use thrift_streaming::types::FsEventType;
+use thrift_types::edenfs::types::HgEvent;
+use thrift_types::edenfs::types::HgEventType;
+use thrift_types::edenfs::types::ThriftRequestEvent;
+use thrift_types::edenfs::types::ThriftRequestEventType;
+
+/// Async C++ method name prefixes to omit from Thrift method names.
+///
+/// If one prefix is a prefix of another, it must come after it.
+const ASYNC_THRIFT_METHOD_PREFIXES: &[&str] =
+    &["semifuture_", "future_", "async_tm_", "async_", "co_"];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TraceKind {
+    Fs,
+    Thrift,
+    Hg,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Phase {
+    Queue,
+    Start,
+    Finish,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TraceEvent {
+    pub kind: TraceKind,
+    pub phase: Phase,
+    /// Identifies the request the event belongs to, unique per kind.
+    pub id: i64,
+    /// Nanoseconds since the epoch.
+    pub timestamp_ns: i64,
+    /// Nanoseconds since an arbitrary clock base, used to compute latencies.
+    pub monotonic_time_ns: i64,
+    /// The FUSE opcode, NFS procedure, ProjectedFS callback, Thrift method
+    /// or the type of imported object.
+    pub operation: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pid: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub process_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub arguments: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// Import priority and cause of hg events.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+fn non_empty(s: &str) -> Option<String> {
+    if s.is_empty() {
+        None
+    } else {
+        Some(s.to_string())
+    }
+}
+
+fn to_pid(pid: i32) -> Option<u32> {
+    if pid > 0 {
+        Some(pid as u32)
+    } else {
+        None
+    }
+}
+
+impl TraceEvent {
+    /// Convert an event of `traceFsEvents`. Returns `None` for unknown event
+    /// types and events without a request.
+    pub fn from_fs(event: FsEvent) -> Option<Self> {
+        let phase = match event.r#type {
+            FsEventType::START => Phase::Start,
+            FsEventType::FINISH => Phase::Finish,
+            _ => return None,
+        };
+
+        let arguments = non_empty(&event.arguments);
+        let (id, operation, pid, process_name, arguments) = if let Some(call) = &event.fuseRequest {
+            let operation = call
+                .opcodeName
+                .strip_prefix("FUSE_")
+                .unwrap_or(&call.opcodeName)
+                .to_lowercase();
+            // FUSE requests are shown with the node they apply to. Finish
+            // events repeat the arguments of the start event, so leave them
+            // out.
+            let arguments = match arguments {
+                Some(arguments) if phase == Phase::Start => {
+                    format!("{}, {}", call.nodeid, arguments)
+                }
+                _ => call.nodeid.to_string(),
+            };
+            (
+                call.unique,
+                operation,
+                to_pid(call.pid),
+                call.processName
+                    .clone()
+                    .or_else(|| event.requestInfo.processName.clone()),
+                Some(arguments),
+            )
+        } else if let Some(call) = &event.nfsRequest {
+            (
+                call.xid as u32 as i64,
+                format!("{}({})", call.procName, call.procNumber),
+                event.requestInfo.pid.and_then(to_pid),
+                event.requestInfo.processName.clone(),
+                arguments,
+            )
+        } else if let Some(call) = &event.prjfsRequest {
+            (
+                call.commandId as i64,
+                call.callType.to_string().to_lowercase(),
+                to_pid(call.pid),
+                event.requestInfo.processName.clone(),
+                arguments,
+            )
+        } else {
+            return None;
+        };
+
+        Some(Self {
+            kind: TraceKind::Fs,
+            phase,
+            id,
+            timestamp_ns: event.times.timestamp,
+            monotonic_time_ns: event.times.monotonic_time_ns,
+            operation,
+            pid,
+            process_name,
+            arguments,
+            result: event.result,
+            path: None,
+            detail: None,
+        })
+    }
+
+    /// Convert an event of `traceThriftRequestEvents`.
+    pub fn from_thrift(event: ThriftRequestEvent) -> Option<Self> {
+        let phase = match event.eventType {
+            ThriftRequestEventType::START => Phase::Start,
+            ThriftRequestEventType::FINISH => Phase::Finish,
+            _ => return None,
+        };
+        let method = &event.requestMetadata.method;
+        let operation = ASYNC_THRIFT_METHOD_PREFIXES
+            .iter()
+            .find_map(|prefix| method.strip_prefix(prefix))
+            .unwrap_or(method)
+            .to_string();
+
+        Some(Self {
+            kind: TraceKind::Thrift,
+            phase,
+            id: event.requestMetadata.requestId,
+            timestamp_ns: event.times.timestamp,
+            monotonic_time_ns: event.times.monotonic_time_ns,
+            operation,
+            pid: to_pid(event.requestMetadata.clientPid),
+            process_name: None,
+            arguments: None,
+            result: None,
+            path: None,
+            detail: None,
+        })
+    }
+
+    /// Convert an event of `traceHgEvents`.
+    pub fn from_hg(event: HgEvent) -> Option<Self> {
+        let phase = match event.eventType {
+            HgEventType::QUEUE => Phase::Queue,
+            HgEventType::START => Phase::Start,
+            HgEventType::FINISH => Phase::Finish,
+            _ => return None,
+        };
+        let (pid, process_name) = match event.requestInfo {
+            Some(info) => (info.pid.and_then(to_pid), info.processName),
+            None => (None, None),
+        };
+
+        Some(Self {
+            kind: TraceKind::Hg,
+            phase,
+            id: event.unique,
+            timestamp_ns: event.times.timestamp,
+            monotonic_time_ns: event.times.monotonic_time_ns,
+            operation: event.resourceType.to_string(),
+            pid,
+            process_name,
+            arguments: None,
+            result: None,
+            path: Some(String::from_utf8_lossy(&event.path).into_owned()),
+            detail: Some(format!("{} {}", event.importPriority, event.importCause)),
+        })
+    }
+}
+
+/// Which events to show.
+#[derive(Debug, Default)]
+pub struct TraceFilter {
+    pub pids: Vec<u32>,
+    pub path_prefix: Option<String>,
+    pub operations: Vec<String>,
+}
+
+impl TraceFilter {
+    pub fn matches(&self, event: &TraceEvent) -> bool {
+        if !self.pids.is_empty() && !event.pid.map_or(false, |pid| self.pids.contains(&pid)) {
+            return false;
+        }
+        if let Some(prefix) = &self.path_prefix {
+            if !event
+                .path
+                .as_ref()
+                .map_or(false, |path| path.starts_with(prefix.as_str()))
+            {
+                return false;
+            }
+        }
+        if !self.operations.is_empty()
+            && !self
+                .operations
+                .iter()
+                .any(|op| op.eq_ignore_ascii_case(&event.operation))
+        {
+            return false;
+        }
+        true
+    }
+}
+
+/// Formats events one line at a time, matching finish events with the
+/// events that started the request to show latencies.
+#[derive(Default)]
+pub struct TraceFormatter {
+    verbose: bool,
+    queued: HashMap<(TraceKind, i64), i64>,
+    started: HashMap<(TraceKind, i64), i64>,
+}
+
+impl TraceFormatter {
+    pub fn new(verbose: bool) -> Self {
+        Self {
+            verbose,
+            ..Default::default()
+        }
+    }
+
+    /// Returns `None` for events that are not printed on their own.
+    pub fn format(&mut self, event: &TraceEvent) -> Option<String> {
+        let key = (event.kind, event.id);
+        let mut line = String::new();
+        match event.kind {
+            TraceKind::Fs | TraceKind::Thrift => {
+                let latency = match event.phase {
+                    Phase::Queue => return None,
+                    Phase::Start => {
+                        self.started.insert(key, event.monotonic_time_ns);
+                        None
+                    }
+                    Phase::Finish => self
+                        .started
+                        .remove(&key)
+                        .map(|start| event.monotonic_time_ns - start),
+                };
+                let symbol = if event.phase == Phase::Start {
+                    '+'
+                } else {
+                    '-'
+                };
+                write!(line, "{} {}", symbol, event.id).ok()?;
+                if let Some(process) = format_process(event) {
+                    write!(line, " from {}", process).ok()?;
+                }
+                write!(line, ": {}", event.operation).ok()?;
+                if event.kind == TraceKind::Fs {
+                    write!(line, "({})", event.arguments.as_deref().unwrap_or("")).ok()?;
+                }
+                if let Some(result) = event.result {
+                    write!(line, " = {}", result).ok()?;
+                }
+                if let Some(latency) = latency {
+                    write!(line, " in {:.3} \u{03BC}s", latency as f64 / 1000.0).ok()?;
+                }
+            }
+            TraceKind::Hg => {
+                let annotation = match event.phase {
+                    Phase::Queue => {
+                        self.queued.insert(key, event.monotonic_time_ns);
+                        return None;
+                    }
+                    Phase::Start => {
+                        self.started.insert(key, event.monotonic_time_ns);
+                        // Don't bother showing queue times under 1ms.
+                        self.queued
+                            .remove(&key)
+                            .map(|queued| event.monotonic_time_ns - queued)
+                            .filter(|queue_time| *queue_time >= 1_000_000)
+                            .map(|queue_time| format!(" queued for {}", format_ms(queue_time)))
+                    }
+                    Phase::Finish => self.started.remove(&key).map(|start| {
+                        format!(" fetched in {}", format_ms(event.monotonic_time_ns - start))
+                    }),
+                };
+                let phase = if event.phase == Phase::Start {
+                    "START"
+                } else {
+                    "FINISH"
+                };
+                write!(line, "{} {}", phase, event.operation).ok()?;
+                if self.verbose {
+                    if let Some(detail) = &event.detail {
+                        write!(line, " {}", detail).ok()?;
+                    }
+                    if let Some(process) = format_process(event) {
+                        write!(line, " from {}", process).ok()?;
+                    }
+                }
+                write!(
+                    line,
+                    " {}{}",
+                    event.path.as_deref().unwrap_or(""),
+                    annotation.unwrap_or_default()
+                )
+                .ok()?;
+            }
+        }
+        Some(line)
+    }
+}
+
+fn format_process(event: &TraceEvent) -> Option<String> {
+    match (&event.process_name, event.pid) {
+        (Some(name), Some(pid)) => Some(format!("{}({})", name, pid)),
+        (Some(name), None) => Some(name.clone()),
+        (None, Some(pid)) => Some(pid.to_string()),
+        (None, None) => None,
+    }
+}
+
+fn format_ms(ns: i64) -> String {
+    format!("{:.3} ms", ns as f64 / 1_000_000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(kind: TraceKind, phase: Phase, monotonic_time_ns: i64) -> TraceEvent {
+        TraceEvent {
+            kind,
+            phase,
+            id: 7,
+            timestamp_ns: 0,
+            monotonic_time_ns,
+            operation: "lookup".to_string(),
+            pid: Some(42),
+            process_name: Some("ls".to_string()),
+            arguments: Some("1, foo".to_string()),
+            result: None,
+            path: None,
+            detail: None,
+        }
+    }
+
+    #[test]
+    fn test_format_latency() {
+        let mut formatter = TraceFormatter::new(false);
+        assert_eq!(
+            formatter
+                .format(&event(TraceKind::Fs, Phase::Start, 1_000))
+                .as_deref(),
+            Some("+ 7 from ls(42): lookup(1, foo)")
+        );
+        let mut finish = event(TraceKind::Fs, Phase::Finish, 3_500);
+        finish.result = Some(0);
+        assert_eq!(
+            formatter.format(&finish).as_deref(),
+            Some("- 7 from ls(42): lookup(1, foo) = 0 in 2.500 \u{03BC}s")
+        );
+        // The request is done, so a second finish has no latency.
+        assert_eq!(
+            formatter.format(&finish).as_deref(),
+            Some("- 7 from ls(42): lookup(1, foo) = 0")
+        );
+    }
+
+    #[test]
+    fn test_filter_and_record_roundtrip() {
+        let mut hg = event(TraceKind::Hg, Phase::Start, 0);
+        hg.operation = "BLOB".to_string();
+        hg.path = Some("fbcode/eden/fs".to_string());
+
+        let line = serde_json::to_string(&hg).unwrap();
+        let replayed: TraceEvent = serde_json::from_str(&line).unwrap();
+        assert_eq!(replayed, hg);
+
+        let filter = TraceFilter {
+            pids: vec![42],
+            path_prefix: Some("fbcode/".to_string()),
+            operations: vec!["blob".to_string()],
+        };
+        assert!(filter.matches(&replayed));
+        assert!(!filter.matches(&event(TraceKind::Fs, Phase::Start, 0)));
+
+        let filter = TraceFilter {
+            pids: vec![1],
+            ..Default::default()
+        };
+        assert!(!filter.matches(&replayed));
+    }
+}
@@ -0,0 +1,56 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+//! edenfsctl unmount
+
+use std::path::PathBuf;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use clap::Parser;
+use edenfs_client::EdenFsInstance;
+use hg_util::path::expand_path;
+
+use crate::util::mount_point::resolve_mount_point;
+use crate::util::mount_point::unmount_checkout;
+use crate::ExitCode;
+
+#[derive(Parser, Debug)]
+#[clap(about = "Temporarily unmount a specific checkout")]
+pub struct UnmountCmd {
+    #[clap(
+        parse(from_str = expand_path),
+        required = true,
+        help = "Path where checkout should be unmounted from"
+    )]
+    paths: Vec<PathBuf>,
+}
+
+#[async_trait]
+impl crate::Subcommand for UnmountCmd {
+    async fn run(&self) -> Result<ExitCode> {
+        let instance = EdenFsInstance::global();
+        let client = match instance.connect(None).await {
+            Ok(client) => client,
+            Err(e) => {
+                eprintln!("error: {}", e);
+                return Ok(1);
+            }
+        };
+        for path in &self.paths {
+            let result = match resolve_mount_point(path) {
+                Ok(path) => unmount_checkout(&client, &path).await,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = result {
+                eprintln!("error: {:#}", e);
+                return Ok(1);
+            }
+        }
+        Ok(0)
+    }
+}
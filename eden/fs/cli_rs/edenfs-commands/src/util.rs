@@ -13,6 +13,7 @@ use anyhow::Result;
 use hg_util::path::expand_path;
 
 pub mod jsonrpc;
+pub mod mount_point;
 
 /// Expand the path if the user has supplied anything. Otherwise, use the current working directory instead.
 ///
@@ -0,0 +1,148 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+//! Creation and cleanup of the directories checkouts are mounted on.
+
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use anyhow::Context;
+use anyhow::Result;
+use edenfs_client::EdenFsClient;
+use edenfs_client::EdenFsInstance;
+use edenfs_utils::bytes_from_path;
+
+/// Visible in the mount point when the checkout is not mounted.
+#[cfg(unix)]
+const NOT_MOUNTED_README_PATH: &str = "README_EDEN.txt";
+/// The site-specific contents for the README, under /etc/eden.
+#[cfg(unix)]
+const NOT_MOUNTED_SITE_SPECIFIC_README_PATH: &str = "NOT_MOUNTED_README.txt";
+#[cfg(unix)]
+const NOT_MOUNTED_DEFAULT_TEXT: &str = "\
+This directory is the mount point for a virtual checkout managed by EdenFS.
+
+If you are seeing this file that means that your repository checkout is not
+currently mounted.  This could either be because the edenfs daemon is not
+currently running, or it simply does not have this checkout mounted yet.
+
+You can run \"eden doctor\" to check for problems with EdenFS and try to have it
+automatically remount your checkouts.
+";
+
+/// EdenFS can take a long time to unmount while it waits for inodes to become unreferenced, but
+/// that shouldn't hang the CLI.
+const UNMOUNT_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Makes `path` absolute and resolves the symlinks in the part of it that exists, which is how
+/// checkouts are recorded in config.json. Unlike `canonicalize`, this works for mount points that
+/// don't exist (yet) or whose mount went stale.
+pub fn resolve_mount_point(path: &Path) -> Result<PathBuf> {
+    let path = std::env::current_dir()?.join(path);
+    let existing = path
+        .ancestors()
+        .find(|ancestor| ancestor.exists())
+        .ok_or_else(|| anyhow!("unable to access {}", path.display()))?;
+    let rest = path.strip_prefix(existing)?;
+    Ok(existing.canonicalize()?.join(rest))
+}
+
+pub async fn unmount_checkout(client: &EdenFsClient, path: &Path) -> Result<()> {
+    let mount_point = bytes_from_path(path.to_path_buf())?;
+    tokio::time::timeout(UNMOUNT_TIMEOUT, client.unmount(&mount_point))
+        .await
+        .map_err(|_| {
+            anyhow!(
+                "Timed out after {} seconds unmounting {}",
+                UNMOUNT_TIMEOUT.as_secs(),
+                path.display()
+            )
+        })?
+        .with_context(|| format!("Failed to unmount {}", path.display()))
+}
+
+/// Creates the directory a new checkout is mounted on, which must be empty if it already exists.
+#[cfg_attr(windows, allow(unused_variables))]
+pub fn create_mount_point_dir(instance: &EdenFsInstance, path: &Path) -> Result<()> {
+    fs::create_dir_all(path).with_context(|| format!("Failed to create {}", path.display()))?;
+    if fs::read_dir(path)?.next().is_some() {
+        return Err(anyhow!(
+            "The directory \"{}\" already exists on disk. Use `edenfsctl remove` if this is \
+            an old EdenFS clone to remove it.",
+            path.display()
+        ));
+    }
+
+    // On Windows anything in this directory would be visible in the checkout itself.
+    #[cfg(unix)]
+    create_readme(instance, path)?;
+    Ok(())
+}
+
+/// Tells users how to get the checkout mounted again when they find the bare mount point.
+#[cfg(unix)]
+fn create_readme(instance: &EdenFsInstance, path: &Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let readme_path = path.join(NOT_MOUNTED_README_PATH);
+    let site_readme_path = instance
+        .etc_eden_dir()
+        .join(NOT_MOUNTED_SITE_SPECIFIC_README_PATH);
+    // A symlink keeps the README up to date if the site-specific one changes.
+    if site_readme_path.is_file()
+        && std::os::unix::fs::symlink(&site_readme_path, &readme_path).is_ok()
+    {
+        return Ok(());
+    }
+    let contents = fs::read_to_string(&site_readme_path)
+        .unwrap_or_else(|_| NOT_MOUNTED_DEFAULT_TEXT.to_string());
+    fs::write(&readme_path, contents)
+        .with_context(|| format!("Failed to write {}", readme_path.display()))?;
+    fs::set_permissions(&readme_path, fs::Permissions::from_mode(0o444))?;
+    Ok(())
+}
+
+/// Removes what EdenFS left in the mount point of an unmounted checkout. Only the README is
+/// deleted, so that files EdenFS did not create are never lost.
+#[cfg(unix)]
+pub fn cleanup_mount_point(path: &Path, preserve_mount_point: bool) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    // Older versions of EdenFS made the mount point read-only.
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755))
+        .with_context(|| format!("Failed to make {} writable", path.display()))?;
+    match fs::remove_file(path.join(NOT_MOUNTED_README_PATH)) {
+        Err(e) if e.kind() != ErrorKind::NotFound => {
+            return Err(e).context("Failed to remove the mount point README");
+        }
+        _ => {}
+    }
+    if !preserve_mount_point {
+        fs::remove_dir(path).with_context(|| format!("Failed to remove {}", path.display()))?;
+    }
+    Ok(())
+}
+
+/// On Windows the mount point holds the ProjectedFS placeholders of the checkout.
+#[cfg(windows)]
+pub fn cleanup_mount_point(path: &Path, preserve_mount_point: bool) -> Result<()> {
+    match fs::remove_dir_all(path) {
+        Err(e) if e.kind() != ErrorKind::NotFound => Err(e).with_context(|| {
+            format!(
+                "Failed to remove {}: a process may still be using it",
+                path.display()
+            )
+        }),
+        _ if preserve_mount_point => fs::create_dir_all(path)
+            .with_context(|| format!("Failed to recreate {}", path.display())),
+        _ => Ok(()),
+    }
+}
@@ -23,6 +23,11 @@ use blobstore::PutBehaviour;
 use blobstore::DEFAULT_PUT_BEHAVIOUR;
 use blobstore_sync_queue::SqlBlobstoreSyncQueue;
 use cacheblob::CachelibBlobstoreOptions;
+#[cfg(not(fbcode_build))]
+use cacheblob::InProcessLease;
+use cacheblob::LeaseOps;
+#[cfg(fbcode_build)]
+use cacheblob::MemcacheOps;
 use cached_config::ConfigStore;
 use chaosblob::ChaosBlobstore;
 use chaosblob::ChaosOptions;
@@ -54,6 +59,8 @@ use readonlyblob::ReadOnlyBlobstore;
 use samplingblob::ComponentSamplingHandler;
 use samplingblob::SamplingBlobstorePutOps;
 use scuba_ext::MononokeScubaSampleBuilder;
+use segmentblob::Segmentblob;
+use segmentblob::DEFAULT_MAX_SEGMENT_SIZE;
 use slog::Logger;
 use sql_construct::SqlConstructFromDatabaseConfig;
 use sql_ext::facebook::MysqlOptions;
@@ -61,6 +68,8 @@ use sqlblob::CountedSqlblob;
 use sqlblob::Sqlblob;
 use throttledblob::ThrottleOptions;
 use throttledblob::ThrottledBlob;
+use tieredblob::TieredBlobstore;
+use tieredblob::TieredOptions;
 
 use crate::ReadOnlyStorage;
 
@@ -372,6 +381,28 @@ async fn make_files_blobstore(
     }
 }
 
+async fn make_segments_blobstore(
+    blobconfig: BlobConfig,
+    blobstore_options: &BlobstoreOptions,
+) -> Result<Segmentblob, Error> {
+    if let BlobConfig::Segments {
+        path,
+        max_segment_size,
+        sync_policy,
+    } = blobconfig
+    {
+        Segmentblob::open(
+            path.join("blobs"),
+            max_segment_size.map_or(DEFAULT_MAX_SEGMENT_SIZE, NonZeroU64::get),
+            sync_policy,
+            blobstore_options.put_behaviour,
+        )
+        .context(ErrorKind::StateOpen)
+    } else {
+        bail!("Not a segments blobstore")
+    }
+}
+
 async fn make_blobstore_with_link<'a>(
     fb: FacebookInit,
     blobconfig: BlobConfig,
@@ -401,6 +432,9 @@ async fn make_blobstore_with_link<'a>(
         Files { .. } => make_files_blobstore(blobconfig, blobstore_options)
             .await
             .map(|store| Arc::new(store) as Arc<dyn BlobstoreUnlinkOps>),
+        Segments { .. } => make_segments_blobstore(blobconfig, blobstore_options)
+            .await
+            .map(|store| Arc::new(store) as Arc<dyn BlobstoreUnlinkOps>),
         _ => bail!("Not a physical blobstore"),
     }
 }
@@ -450,10 +484,87 @@ pub async fn raw_blobstore_enumerable_with_unlink<'a>(
         Files { .. } => make_files_blobstore(blobconfig, blobstore_options)
             .await
             .map(|store| Arc::new(store) as Arc<dyn BlobstoreEnumerableWithUnlink>),
+        Segments { .. } => make_segments_blobstore(blobconfig, blobstore_options)
+            .await
+            .map(|store| Arc::new(store) as Arc<dyn BlobstoreEnumerableWithUnlink>),
         _ => bail!("Not a physical blobstore that supports unlink + keysource + putops"),
     }
 }
 
+/// Construct the tiered blobstore described by a `BlobConfig::Tiered`. This does not
+/// demote anything by itself; the job that runs the mover uses this to get a store it
+/// can call `demote_pass` on.
+pub async fn make_tiered_blobstore<'a>(
+    fb: FacebookInit,
+    blobconfig: BlobConfig,
+    mysql_options: &'a MysqlOptions,
+    readonly_storage: ReadOnlyStorage,
+    blobstore_options: &'a BlobstoreOptions,
+    logger: &'a Logger,
+    config_store: &'a ConfigStore,
+    scrub_handler: &'a Arc<dyn ScrubHandler>,
+    component_sampler: Option<&'a Arc<dyn ComponentSamplingHandler>>,
+) -> Result<TieredBlobstore<Arc<dyn BlobstoreEnumerableWithUnlink>, Arc<dyn BlobstorePutOps>>, Error>
+{
+    let (hot, cold, promote_on_read, demote_after) = match blobconfig {
+        BlobConfig::Tiered {
+            hot,
+            cold,
+            promote_on_read,
+            demote_after,
+        } => (hot, cold, promote_on_read, demote_after),
+        _ => bail!("Not a tiered blobstore"),
+    };
+
+    // The mover needs to enumerate and unlink the hot tier, so it has to be a
+    // physical blobstore.
+    let hot = make_blobstore_enumerable_with_unlink(fb, *hot, blobstore_options, logger)
+        .watched(logger)
+        .await?;
+    let cold = make_blobstore_put_ops(
+        fb,
+        *cold,
+        mysql_options,
+        readonly_storage,
+        blobstore_options,
+        logger,
+        config_store,
+        scrub_handler,
+        component_sampler,
+        None,
+    )
+    .watched(logger)
+    .await?;
+
+    // Every process writing to the hot tier must share the lease that excludes
+    // writes during demotion, and the mover runs in a process of its own.
+    // Memcache is the only such lease, and it is only available in fbcode
+    // builds. Without demotion the lease is never taken.
+    #[cfg(fbcode_build)]
+    let lease = Arc::new(MemcacheOps::new(fb, "tiered-blobstore", "")?) as Arc<dyn LeaseOps>;
+    #[cfg(not(fbcode_build))]
+    let lease = {
+        if demote_after.is_some() {
+            bail!(
+                "Demotion from a tiered blobstore needs a memcache lease, which this build lacks"
+            );
+        }
+        Arc::new(InProcessLease::new()) as Arc<dyn LeaseOps>
+    };
+
+    Ok(TieredBlobstore::new(
+        hot,
+        cold,
+        lease,
+        TieredOptions {
+            promote_on_read,
+            demote_after,
+            put_behaviour: blobstore_options.put_behaviour,
+            ..Default::default()
+        },
+    ))
+}
+
 // Constructs the BlobstorePutOps store implementations for low level blobstore access
 fn make_blobstore_put_ops<'a>(
     fb: FacebookInit,
@@ -493,6 +604,9 @@ fn make_blobstore_put_ops<'a>(
             Files { .. } => make_files_blobstore(blobconfig, blobstore_options)
                 .await
                 .map(|store| Arc::new(store) as Arc<dyn BlobstorePutOps>)?,
+            Segments { .. } => make_segments_blobstore(blobconfig, blobstore_options)
+                .await
+                .map(|store| Arc::new(store) as Arc<dyn BlobstorePutOps>)?,
             S3 {
                 bucket,
                 keychain_group,
@@ -598,6 +712,19 @@ fn make_blobstore_put_ops<'a>(
                     })?;
                 Arc::new(LogBlob::new(store, scuba, scuba_sample_rate)) as Arc<dyn BlobstorePutOps>
             }
+            Tiered { .. } => make_tiered_blobstore(
+                fb,
+                blobconfig,
+                mysql_options,
+                readonly_storage,
+                blobstore_options,
+                logger,
+                config_store,
+                scrub_handler,
+                component_sampler,
+            )
+            .await
+            .map(|store| Arc::new(store) as Arc<dyn BlobstorePutOps>)?,
             Pack { .. } => {
                 // NB packblob does not apply the wrappers internally
                 make_packblob(
@@ -31,6 +31,17 @@ struct ZstdFromDictValue {
   2: bytes zstd;
 } (rust.exhaustive)
 
+// Represents a Zstandard blob compressed with a dictionary trained on
+// samples of blobs of the same type.
+//
+// dict_key is the blobstore key of the trained dictionary, which is
+// stored as an ordinary single value. Dictionary keys are versioned and
+// never overwritten, so a dictionary can be cached once loaded.
+struct ZstdFromTrainedDictValue {
+  1: string dict_key;
+  2: bytes zstd;
+} (rust.exhaustive)
+
 // Packed values might not take any advantage of delta compression, but its
 // there if the packer decides its most efficient for the blob
 union PackedValue {
@@ -64,12 +75,14 @@ struct PackedFormat {
 } (rust.exhaustive)
 
 // Discriminated union with the variant forms, for now we handle single
-// independent values or a list of packed entries.
+// independent values, a list of packed entries, or single values that
+// need a trained dictionary to decode.
 // The blobstore would theoretically still work (super slowly/with OOMs)
 // if all blobs were stored in one list<PackedEntry>
 union StorageFormat {
   1: SingleValue Single;
   2: PackedFormat Packed;
+  3: ZstdFromTrainedDictValue ZstdFromTrainedDict;
 }
 
 // At-rest form for mononoke blobs, top level struct for persistance.
@@ -0,0 +1,245 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+//! Zstd dictionaries trained per blob type.
+//!
+//! Small blobs of one type (e.g. `hgmanifest.`) share a lot of structure that
+//! compressing each blob on its own cannot exploit. The packer trains a
+//! dictionary per type from samples and stores it as a versioned blob, and
+//! puts in the `ZstdDictionary` format compress small blobs with the current
+//! dictionary for their type.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+
+use anyhow::Context;
+use anyhow::Result;
+use bytes::Buf;
+use bytes::BufMut;
+use bytes::Bytes;
+use bytes::BytesMut;
+use zstd::bulk::Compressor;
+use zstd::dict::DecoderDictionary;
+use zstd::dict::EncoderDictionary;
+use zstd::stream::read::Decoder as ZstdDecoder;
+
+use crate::pack::split_key_prefix;
+
+/// Type prefix under which dictionaries are stored.  Dictionaries are never
+/// compressed with a dictionary themselves.
+pub const DICTIONARY_TYPE_PREFIX: &str = "packblob_dict.";
+
+/// Suffix of the key that names the current dictionary for a blob type.
+const CURRENT_SUFFIX: &str = "current";
+
+/// Blobs larger than this compress well enough on their own, so are not
+/// compressed with a dictionary.
+pub const MAX_DICTIONARY_BLOB_SIZE: usize = 64 * 1024;
+
+/// Default size of trained dictionaries, the zstd CLI's default.
+pub const DEFAULT_DICTIONARY_SIZE: usize = 110 * 1024;
+
+/// How long to trust a cached lookup of the current dictionary for a type
+/// before checking whether the packer has trained a new one.
+const CURRENT_REFRESH_INTERVAL: Duration = Duration::from_secs(600);
+
+/// Split a blobstore key into its repo prefix and blob type prefix, e.g.
+/// `repo0001.hgmanifest.sha1.xxx` into `repo0001.` and `hgmanifest.`.
+pub fn blob_type_prefix(key: &str) -> Option<(&str, &str)> {
+    let (repo_prefix, key) = split_key_prefix(key);
+    let type_end = key.find('.')? + 1;
+    let type_prefix = &key[..type_end];
+    if type_prefix == DICTIONARY_TYPE_PREFIX {
+        None
+    } else {
+        Some((repo_prefix, type_prefix))
+    }
+}
+
+/// The key version `version` of the dictionary for a blob type is stored at.
+pub fn dictionary_key(repo_prefix: &str, type_prefix: &str, version: u64) -> String {
+    format!(
+        "{}{}{}v{}",
+        repo_prefix, DICTIONARY_TYPE_PREFIX, type_prefix, version
+    )
+}
+
+/// The key that holds the key of the current dictionary for a blob type.
+pub(crate) fn current_dictionary_key(repo_prefix: &str, type_prefix: &str) -> String {
+    format!(
+        "{}{}{}{}",
+        repo_prefix, DICTIONARY_TYPE_PREFIX, type_prefix, CURRENT_SUFFIX
+    )
+}
+
+/// Parse the version back out of a key made by `dictionary_key`.
+pub(crate) fn dictionary_version(
+    repo_prefix: &str,
+    type_prefix: &str,
+    dict_key: &str,
+) -> Option<u64> {
+    let version_prefix = dictionary_key(repo_prefix, type_prefix, 0);
+    let version_prefix = version_prefix.strip_suffix('0')?;
+    dict_key.strip_prefix(version_prefix)?.parse().ok()
+}
+
+/// Train a dictionary of at most `max_size` bytes from sample blobs of one type.
+pub fn train_dictionary(samples: &[Bytes], max_size: usize) -> Result<Bytes> {
+    let dictionary = zstd::dict::from_samples(samples, max_size).with_context(|| {
+        format!(
+            "While training zstd dictionary from {} samples",
+            samples.len()
+        )
+    })?;
+    Ok(Bytes::from(dictionary))
+}
+
+/// A trained dictionary, prepared for compression at one zstd level and for
+/// decompression.
+pub struct TrainedDictionary {
+    key: String,
+    encoder: EncoderDictionary<'static>,
+    decoder: DecoderDictionary<'static>,
+}
+
+impl fmt::Debug for TrainedDictionary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TrainedDictionary")
+            .field("key", &self.key)
+            .finish()
+    }
+}
+
+impl TrainedDictionary {
+    pub fn new(key: String, dictionary: &[u8], zstd_level: i32) -> Self {
+        Self {
+            key,
+            encoder: EncoderDictionary::copy(dictionary, zstd_level),
+            decoder: DecoderDictionary::copy(dictionary),
+        }
+    }
+
+    /// The key the dictionary is stored under
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub fn compress(&self, data: &[u8]) -> Result<Bytes> {
+        let mut compressor = Compressor::with_prepared_dictionary(&self.encoder)?;
+        Ok(Bytes::from(compressor.compress(data)?))
+    }
+
+    pub fn decompress(&self, data: Bytes) -> Result<Bytes> {
+        let mut decoder = ZstdDecoder::with_prepared_dictionary(data.reader(), &self.decoder)?;
+        let mut output_bytes = BytesMut::new();
+        let mut writer = (&mut output_bytes).writer();
+        io::copy(&mut decoder, &mut writer)?;
+        Ok(output_bytes.freeze())
+    }
+}
+
+/// Dictionaries loaded by a PackBlob.
+#[derive(Debug, Default)]
+pub(crate) struct DictionaryCache {
+    /// Loaded dictionaries by key.  Dictionary keys are never overwritten, so
+    /// these never go stale.
+    loaded: Mutex<HashMap<String, Arc<TrainedDictionary>>>,
+    /// Key of the current dictionary for each type, by the key naming it,
+    /// along with when it was looked up.
+    current: Mutex<HashMap<String, (Instant, Option<String>)>>,
+}
+
+impl DictionaryCache {
+    pub fn get_loaded(&self, dict_key: &str) -> Option<Arc<TrainedDictionary>> {
+        self.loaded
+            .lock()
+            .expect("lock poisoned")
+            .get(dict_key)
+            .cloned()
+    }
+
+    pub fn insert_loaded(&self, dictionary: Arc<TrainedDictionary>) {
+        self.loaded
+            .lock()
+            .expect("lock poisoned")
+            .insert(dictionary.key().to_owned(), dictionary);
+    }
+
+    /// The cached current dictionary key for a type, if it was looked up recently.
+    pub fn get_current(&self, current_key: &str) -> Option<Option<String>> {
+        let current = self.current.lock().expect("lock poisoned");
+        let (looked_up, dict_key) = current.get(current_key)?;
+        if looked_up.elapsed() < CURRENT_REFRESH_INTERVAL {
+            Some(dict_key.clone())
+        } else {
+            None
+        }
+    }
+
+    pub fn insert_current(&self, current_key: String, dict_key: Option<String>) {
+        self.current
+            .lock()
+            .expect("lock poisoned")
+            .insert(current_key, (Instant::now(), dict_key));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blob_type_prefix_test() {
+        assert_eq!(
+            blob_type_prefix("repo0001.hgmanifest.sha1.abcd"),
+            Some(("repo0001.", "hgmanifest."))
+        );
+        assert_eq!(
+            blob_type_prefix("fsnode.blake2.abcd"),
+            Some(("", "fsnode."))
+        );
+        assert_eq!(blob_type_prefix("repo0001.nodots"), None);
+        assert_eq!(
+            blob_type_prefix("repo0001.packblob_dict.hgmanifest.v1"),
+            None
+        );
+    }
+
+    #[test]
+    fn dictionary_version_test() {
+        let key = dictionary_key("repo0001.", "fsnode.", 12);
+        assert_eq!(key, "repo0001.packblob_dict.fsnode.v12");
+        assert_eq!(dictionary_version("repo0001.", "fsnode.", &key), Some(12));
+        assert_eq!(dictionary_version("repo0001.", "hgmanifest.", &key), None);
+    }
+
+    #[test]
+    fn trained_roundtrip_test() -> Result<()> {
+        let samples: Vec<Bytes> = (0..1000)
+            .map(|i| {
+                Bytes::from(format!(
+                    "manifest entry {} points at file{}.txt\n",
+                    i,
+                    i * 7
+                ))
+            })
+            .collect();
+        let dictionary = train_dictionary(&samples, 4096)?;
+        let dictionary = TrainedDictionary::new("dict".to_string(), &dictionary, 3);
+
+        let data = Bytes::from("manifest entry 12345 points at file86415.txt\n");
+        let compressed = dictionary.compress(&data)?;
+        assert!(compressed.len() < data.len());
+        assert_eq!(dictionary.decompress(compressed)?, data);
+        Ok(())
+    }
+}
@@ -23,6 +23,7 @@ use mononoke_types::BlobstoreBytes;
 use packblob_thrift::StorageEnvelope;
 use packblob_thrift::StorageFormat;
 
+use crate::dictionary::TrainedDictionary;
 use crate::pack;
 
 enum HeaderType {
@@ -75,7 +76,19 @@ where
 pub(crate) struct PackEnvelope(pub packblob_thrift::StorageEnvelope);
 
 impl PackEnvelope {
-    pub fn decode(self, key: &str) -> Result<(BlobstoreBytes, SizeMetadata), Error> {
+    /// The trained dictionary needed to decode this envelope, if any
+    pub fn trained_dict_key(&self) -> Option<&str> {
+        match &self.0.storage {
+            StorageFormat::ZstdFromTrainedDict(v) => Some(&v.dict_key),
+            _ => None,
+        }
+    }
+
+    pub fn decode(
+        self,
+        key: &str,
+        dictionary: Option<&TrainedDictionary>,
+    ) -> Result<(BlobstoreBytes, SizeMetadata), Error> {
         Ok(match self.0.storage {
             StorageFormat::Single(single) => {
                 let (decoded, unique_compressed_size) = pack::decode_independent(single)
@@ -88,6 +101,20 @@ impl PackEnvelope {
             }
             StorageFormat::Packed(packed) => pack::decode_pack(packed, key)
                 .with_context(|| format!("While decoding pack for {:?}", key))?,
+            StorageFormat::ZstdFromTrainedDict(v) => {
+                let dictionary = dictionary.ok_or_else(|| {
+                    format_err!("Dictionary {} not loaded for key {:?}", v.dict_key, key)
+                })?;
+                let (decoded, unique_compressed_size) =
+                    pack::decode_zstd_from_trained_dict(v, dictionary).with_context(|| {
+                        format!("While decoding with trained dictionary {:?}", key)
+                    })?;
+                let sizing = SizeMetadata {
+                    unique_compressed_size,
+                    pack_meta: None,
+                };
+                (decoded, sizing)
+            }
             StorageFormat::UnknownField(e) => {
                 return Err(format_err!("StorageFormat::UnknownField {:?}", e));
             }
@@ -5,10 +5,17 @@
  * GNU General Public License version 2.
  */
 
+mod dictionary;
 mod envelope;
 mod pack;
 mod store;
 
+pub use dictionary::blob_type_prefix;
+pub use dictionary::dictionary_key;
+pub use dictionary::train_dictionary;
+pub use dictionary::TrainedDictionary;
+pub use dictionary::DEFAULT_DICTIONARY_SIZE;
+pub use dictionary::MAX_DICTIONARY_BLOB_SIZE;
 pub use pack::get_entry_compressed_size;
 pub use pack::EmptyPack;
 pub use pack::Pack;
@@ -32,11 +32,13 @@ use packblob_thrift::SingleValue;
 use packblob_thrift::StorageEnvelope;
 use packblob_thrift::StorageFormat;
 use packblob_thrift::ZstdFromDictValue;
+use packblob_thrift::ZstdFromTrainedDictValue;
 use zstd::bulk::Compressor;
 use zstd::dict::EncoderDictionary;
 use zstd::stream::read::Decoder as ZstdDecoder;
 use zstd::stream::write::Encoder as ZstdEncoder;
 
+use crate::dictionary::TrainedDictionary;
 use crate::envelope::PackEnvelope;
 use crate::store;
 
@@ -77,6 +79,28 @@ impl SingleCompressed {
     }
 }
 
+/// Compresses the given blob with a trained dictionary, falling back to
+/// `SingleCompressed` if the dictionary does not make it any smaller
+pub(crate) fn compress_with_trained_dict(
+    zstd_level: i32,
+    blob: BlobstoreBytes,
+    dictionary: &TrainedDictionary,
+) -> Result<BlobstoreBytes> {
+    let zstd = dictionary.compress(blob.as_bytes())?;
+    let single = SingleCompressed::new(zstd_level, blob)?;
+    if zstd.len() < single.get_compressed_size()? {
+        Ok(PackEnvelope(StorageEnvelope {
+            storage: StorageFormat::ZstdFromTrainedDict(ZstdFromTrainedDictValue {
+                dict_key: dictionary.key().to_owned(),
+                zstd,
+            }),
+        })
+        .into())
+    } else {
+        Ok(single.into_blobstore_bytes())
+    }
+}
+
 /// An empty pack with no data. Cannot be uploaded, takes a dictionary blob
 #[derive(Debug)]
 pub struct EmptyPack(i32);
@@ -264,6 +288,23 @@ fn decode_zstd_from_dict(
     }
 }
 
+// returns (decoded, unique_compressed_size)
+pub(crate) fn decode_zstd_from_trained_dict(
+    v: ZstdFromTrainedDictValue,
+    dictionary: &TrainedDictionary,
+) -> Result<(BlobstoreBytes, u64)> {
+    if v.dict_key != dictionary.key() {
+        bail!(
+            "Value needs dictionary {} but was given {}",
+            v.dict_key,
+            dictionary.key()
+        );
+    }
+    let compressed_size = v.zstd.len() as u64;
+    let decoded = dictionary.decompress(v.zstd)?;
+    Ok((BlobstoreBytes::from_bytes(decoded), compressed_size))
+}
+
 // Unpack `key` from `packed`
 pub(crate) fn decode_pack(
     packed: PackedFormat,
@@ -367,7 +408,7 @@ fn compute_pack_hash(keys: &[String]) -> AsciiString {
 /// Find the key prefix for a given key.  Key prefixes are removed when
 /// keys are stored in packs.  Returns the key prefix and the remainder
 /// of the key.
-fn split_key_prefix(key: &str) -> (&str, &str) {
+pub(crate) fn split_key_prefix(key: &str) -> (&str, &str) {
     if let Some(m) = REPO_PREFIX_REGEX.find(key) {
         key.split_at(m.end())
     } else if let Some(m) = EPH_REPO_PREFIX_REGEX.find(key) {
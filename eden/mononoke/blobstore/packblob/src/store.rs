@@ -5,6 +5,9 @@
  * GNU General Public License version 2.
  */
 
+use std::sync::Arc;
+
+use anyhow::bail;
 use anyhow::Context;
 use anyhow::Result;
 use async_trait::async_trait;
@@ -19,12 +22,16 @@ use blobstore::BlobstorePutOps;
 use blobstore::BlobstoreUnlinkOps;
 use blobstore::OverwriteStatus;
 use blobstore::PutBehaviour;
+use bytes::Bytes;
 use context::CoreContext;
 use futures::stream::FuturesUnordered;
 use futures::stream::TryStreamExt;
 use metaconfig_types::PackFormat;
 use mononoke_types::BlobstoreBytes;
 
+use crate::dictionary;
+use crate::dictionary::DictionaryCache;
+use crate::dictionary::TrainedDictionary;
 use crate::envelope::PackEnvelope;
 use crate::pack;
 
@@ -48,6 +55,7 @@ impl PackOptions {
 pub struct PackBlob<T> {
     inner: T,
     put_format: PackFormat,
+    dictionaries: DictionaryCache,
 }
 
 impl<T: std::fmt::Display> std::fmt::Display for PackBlob<T> {
@@ -58,7 +66,11 @@ impl<T: std::fmt::Display> std::fmt::Display for PackBlob<T> {
 
 impl<T> PackBlob<T> {
     pub fn new(inner: T, put_format: PackFormat) -> Self {
-        Self { inner, put_format }
+        Self {
+            inner,
+            put_format,
+            dictionaries: DictionaryCache::default(),
+        }
     }
 }
 
@@ -86,7 +98,15 @@ impl<T: Blobstore + BlobstorePutOps> Blobstore for PackBlob<T> {
 
         let ctime = inner_get_data.as_meta().ctime();
         let envelope: PackEnvelope = inner_get_data.into_bytes().try_into()?;
-        let (decoded, sizing) = envelope.decode(key)?;
+        let dictionary = match envelope.trained_dict_key() {
+            Some(dict_key) => Some(
+                self.load_dictionary(ctx, dict_key)
+                    .await
+                    .with_context(|| format!("While loading dictionary for {:?}", key))?,
+            ),
+            None => None,
+        };
+        let (decoded, sizing) = envelope.decode(key, dictionary.as_deref())?;
         let meta = BlobstoreMetadata::new(ctime, Some(sizing));
         Ok(Some(BlobstoreGetData::new(meta, decoded)))
     }
@@ -112,6 +132,87 @@ impl<T: Blobstore + BlobstorePutOps> Blobstore for PackBlob<T> {
     }
 }
 
+impl<T: Blobstore> PackBlob<T> {
+    // Gets a value stored as a single value envelope, such as a dictionary
+    async fn get_single<'a>(
+        &'a self,
+        ctx: &'a CoreContext,
+        key: &'a str,
+    ) -> Result<Option<BlobstoreBytes>> {
+        let inner_key = &[key, ENVELOPE_SUFFIX].concat();
+        match self.inner.get(ctx, inner_key).await? {
+            Some(inner_get_data) => {
+                let envelope: PackEnvelope = inner_get_data.into_bytes().try_into()?;
+                let (decoded, _) = envelope.decode(key, None)?;
+                Ok(Some(decoded))
+            }
+            None => Ok(None),
+        }
+    }
+
+    async fn load_dictionary<'a>(
+        &'a self,
+        ctx: &'a CoreContext,
+        dict_key: &'a str,
+    ) -> Result<Arc<TrainedDictionary>> {
+        if let Some(dictionary) = self.dictionaries.get_loaded(dict_key) {
+            return Ok(dictionary);
+        }
+        let data = self
+            .get_single(ctx, dict_key)
+            .await?
+            .with_context(|| format!("Dictionary {} not found", dict_key))?;
+        let zstd_level = match self.put_format {
+            PackFormat::ZstdDictionary(zstd_level) => zstd_level,
+            PackFormat::Raw | PackFormat::ZstdIndividual(_) => 0,
+        };
+        let dictionary = Arc::new(TrainedDictionary::new(
+            dict_key.to_owned(),
+            data.as_bytes(),
+            zstd_level,
+        ));
+        self.dictionaries.insert_loaded(dictionary.clone());
+        Ok(dictionary)
+    }
+
+    async fn current_dictionary_key<'a>(
+        &'a self,
+        ctx: &'a CoreContext,
+        repo_prefix: &'a str,
+        type_prefix: &'a str,
+    ) -> Result<Option<String>> {
+        let current_key = dictionary::current_dictionary_key(repo_prefix, type_prefix);
+        if let Some(dict_key) = self.dictionaries.get_current(&current_key) {
+            return Ok(dict_key);
+        }
+        let dict_key = self
+            .get_single(ctx, &current_key)
+            .await?
+            .map(|data| String::from_utf8(data.into_bytes().to_vec()))
+            .transpose()
+            .with_context(|| format!("Invalid dictionary key in {}", current_key))?;
+        self.dictionaries
+            .insert_current(current_key, dict_key.clone());
+        Ok(dict_key)
+    }
+
+    /// The dictionary that puts of this type of blob are currently compressed with, if any
+    pub async fn current_dictionary<'a>(
+        &'a self,
+        ctx: &'a CoreContext,
+        repo_prefix: &'a str,
+        type_prefix: &'a str,
+    ) -> Result<Option<Arc<TrainedDictionary>>> {
+        match self
+            .current_dictionary_key(ctx, repo_prefix, type_prefix)
+            .await?
+        {
+            Some(dict_key) => Ok(Some(self.load_dictionary(ctx, &dict_key).await?)),
+            None => Ok(None),
+        }
+    }
+}
+
 impl<T: BlobstorePutOps> PackBlob<T> {
     async fn put_impl<'a>(
         &'a self,
@@ -120,15 +221,32 @@ impl<T: BlobstorePutOps> PackBlob<T> {
         value: BlobstoreBytes,
         put_behaviour: Option<PutBehaviour>,
     ) -> Result<OverwriteStatus> {
-        key.push_str(ENVELOPE_SUFFIX);
-
         let bytes = match self.put_format {
+            PackFormat::ZstdDictionary(zstd_level) => {
+                let dictionary = match dictionary::blob_type_prefix(&key) {
+                    Some((repo_prefix, type_prefix))
+                        if value.len() <= dictionary::MAX_DICTIONARY_BLOB_SIZE =>
+                    {
+                        self.current_dictionary(ctx, repo_prefix, type_prefix)
+                            .await?
+                    }
+                    _ => None,
+                };
+                match dictionary {
+                    Some(dictionary) => {
+                        pack::compress_with_trained_dict(zstd_level, value, &dictionary)?
+                    }
+                    None => pack::SingleCompressed::new(zstd_level, value)?.into_blobstore_bytes(),
+                }
+            }
             PackFormat::ZstdIndividual(zstd_level) => {
-                pack::SingleCompressed::new(zstd_level, value)?
+                pack::SingleCompressed::new(zstd_level, value)?.into_blobstore_bytes()
             }
-            PackFormat::Raw => pack::SingleCompressed::new_uncompressed(value),
-        }
-        .into_blobstore_bytes();
+            PackFormat::Raw => {
+                pack::SingleCompressed::new_uncompressed(value).into_blobstore_bytes()
+            }
+        };
+        key.push_str(ENVELOPE_SUFFIX);
 
         // pass through the put after wrapping
         if let Some(put_behaviour) = put_behaviour {
@@ -252,6 +370,69 @@ impl<T: Blobstore + BlobstoreUnlinkOps> PackBlob<T> {
     }
 }
 
+impl<T: BlobstorePutOps> PackBlob<T> {
+    /// Store a newly trained dictionary for a type of blob, and make it the
+    /// one that later puts of that type are compressed with.  Returns the
+    /// dictionary's key.
+    ///
+    /// Each dictionary is stored under a new versioned key, so that blobs
+    /// compressed with earlier versions can still be decoded.
+    pub async fn put_dictionary<'a>(
+        &'a self,
+        ctx: &'a CoreContext,
+        repo_prefix: &'a str,
+        type_prefix: &'a str,
+        dictionary: Bytes,
+    ) -> Result<String> {
+        let current_key = dictionary::current_dictionary_key(repo_prefix, type_prefix);
+        let version = match self.get_single(ctx, &current_key).await? {
+            Some(data) => {
+                let dict_key = String::from_utf8(data.into_bytes().to_vec())
+                    .with_context(|| format!("Invalid dictionary key in {}", current_key))?;
+                dictionary::dictionary_version(repo_prefix, type_prefix, &dict_key)
+                    .with_context(|| format!("Invalid dictionary key {}", dict_key))?
+                    + 1
+            }
+            None => 1,
+        };
+        let dict_key = dictionary::dictionary_key(repo_prefix, type_prefix, version);
+
+        // Dictionaries are never overwritten, as blobs may already be
+        // compressed with them.
+        let status = self
+            .inner
+            .put_explicit(
+                ctx,
+                [&dict_key, ENVELOPE_SUFFIX].concat(),
+                pack::SingleCompressed::new_uncompressed(BlobstoreBytes::from_bytes(dictionary))
+                    .into_blobstore_bytes(),
+                PutBehaviour::IfAbsent,
+            )
+            .await?;
+        if status == OverwriteStatus::Prevented {
+            bail!(
+                "Dictionary {} already exists, was another one trained concurrently?",
+                dict_key
+            );
+        }
+
+        self.inner
+            .put_explicit(
+                ctx,
+                [&current_key, ENVELOPE_SUFFIX].concat(),
+                pack::SingleCompressed::new_uncompressed(BlobstoreBytes::from_bytes(
+                    dict_key.clone(),
+                ))
+                .into_blobstore_bytes(),
+                PutBehaviour::Overwrite,
+            )
+            .await?;
+        self.dictionaries
+            .insert_current(current_key, Some(dict_key.clone()));
+        Ok(dict_key)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::sync::Arc;
@@ -265,6 +446,7 @@ mod tests {
     use rand_xorshift::XorShiftRng;
 
     use super::*;
+    use crate::dictionary::train_dictionary;
 
     #[fbinit::test]
     async fn simple_roundtrip_test(fb: FacebookInit) -> Result<()> {
@@ -448,4 +630,104 @@ mod tests {
         );
         Ok(())
     }
+
+    #[fbinit::test]
+    async fn trained_dictionary_roundtrip_test(fb: FacebookInit) -> Result<()> {
+        let ctx = CoreContext::test_mock(fb);
+        borrowed!(ctx);
+        let innerblob = Arc::new(Memblob::default());
+        let packblob = PackBlob::new(innerblob.clone(), PackFormat::ZstdDictionary(3));
+
+        let sample = |i: usize| {
+            Bytes::from(format!(
+                "{{\"path\": \"dir{}/file{}.rs\", \"kind\": \"regular\", \"size\": {}}}",
+                i % 13,
+                i,
+                i * 31
+            ))
+        };
+        let value = BlobstoreBytes::from_bytes(sample(123456));
+
+        // Without a dictionary, puts are compressed individually
+        let inner_key = roundtrip(
+            ctx,
+            innerblob.clone(),
+            &packblob,
+            "repo0000.hgmanifest.before",
+            value.clone(),
+        )
+        .await?;
+        let envelope: PackEnvelope = innerblob
+            .get(ctx, &inner_key)
+            .await?
+            .unwrap()
+            .into_bytes()
+            .try_into()?;
+        assert_eq!(envelope.trained_dict_key(), None);
+
+        let samples: Vec<Bytes> = (0..1000).map(sample).collect();
+        let dict_key = packblob
+            .put_dictionary(
+                ctx,
+                "repo0000.",
+                "hgmanifest.",
+                train_dictionary(&samples, 4096)?,
+            )
+            .await?;
+        assert_eq!(dict_key, "repo0000.packblob_dict.hgmanifest.v1");
+
+        let inner_key = roundtrip(
+            ctx,
+            innerblob.clone(),
+            &packblob,
+            "repo0000.hgmanifest.after",
+            value.clone(),
+        )
+        .await?;
+        let envelope: PackEnvelope = innerblob
+            .get(ctx, &inner_key)
+            .await?
+            .unwrap()
+            .into_bytes()
+            .try_into()?;
+        assert_eq!(envelope.trained_dict_key(), Some(dict_key.as_str()));
+
+        // Other types don't use the dictionary
+        let inner_key = roundtrip(
+            ctx,
+            innerblob.clone(),
+            &packblob,
+            "repo0000.fsnode.after",
+            value.clone(),
+        )
+        .await?;
+        let envelope: PackEnvelope = innerblob
+            .get(ctx, &inner_key)
+            .await?
+            .unwrap()
+            .into_bytes()
+            .try_into()?;
+        assert_eq!(envelope.trained_dict_key(), None);
+
+        // Training a new version keeps old blobs readable, even by a
+        // packblob that has not loaded any dictionaries yet
+        let dict_key = packblob
+            .put_dictionary(
+                ctx,
+                "repo0000.",
+                "hgmanifest.",
+                train_dictionary(&samples[..500], 4096)?,
+            )
+            .await?;
+        assert_eq!(dict_key, "repo0000.packblob_dict.hgmanifest.v2");
+        let fresh = PackBlob::new(innerblob, PackFormat::Raw);
+        assert_eq!(
+            fresh
+                .get(ctx, "repo0000.hgmanifest.after")
+                .await?
+                .map(|v| v.into_bytes()),
+            Some(value)
+        );
+        Ok(())
+    }
 }
@@ -0,0 +1,309 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+//! A local blobstore that appends blobs into large segment files.
+//!
+//! Unlike Fileblob, which creates a file per blob, this keeps the number of
+//! files small and the index compact, so it copes with multi-GB repos on a
+//! single host. See `store.rs` for the on-disk format.
+
+mod store;
+#[cfg(test)]
+mod tests;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::ops::Bound;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::Weak;
+use std::time::SystemTime;
+
+use anyhow::format_err;
+use anyhow::Context;
+use anyhow::Result;
+use async_trait::async_trait;
+use blobstore::Blobstore;
+use blobstore::BlobstoreEnumerationData;
+use blobstore::BlobstoreGetData;
+use blobstore::BlobstoreIsPresent;
+use blobstore::BlobstoreKeyParam;
+use blobstore::BlobstoreKeySource;
+use blobstore::BlobstoreMetadata;
+use blobstore::BlobstorePutOps;
+use blobstore::BlobstoreUnlinkOps;
+use blobstore::OverwriteStatus;
+use blobstore::PutBehaviour;
+use context::CoreContext;
+use metaconfig_types::SegmentSyncPolicy;
+use mononoke_types::BlobstoreBytes;
+use once_cell::sync::Lazy;
+use tokio::task::spawn_blocking;
+
+pub use crate::store::CompactionStats;
+use crate::store::SegmentStore;
+
+/// Segments are sealed once they reach this size, unless configured otherwise.
+pub const DEFAULT_MAX_SEGMENT_SIZE: u64 = 1 << 30;
+
+/// Stores opened by this process, by canonical path. The store's lock file
+/// keeps other processes out, so opening the same store again in this process
+/// shares the existing one.
+static OPEN_STORES: Lazy<Mutex<HashMap<PathBuf, Weak<Mutex<SegmentStore>>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Clone)]
+pub struct Segmentblob {
+    path: PathBuf,
+    store: Arc<Mutex<SegmentStore>>,
+    put_behaviour: PutBehaviour,
+}
+
+impl Segmentblob {
+    pub fn open<P: AsRef<Path>>(
+        path: P,
+        max_segment_size: u64,
+        sync_policy: SegmentSyncPolicy,
+        put_behaviour: PutBehaviour,
+    ) -> Result<Self> {
+        let path = path.as_ref();
+        fs::create_dir_all(path).with_context(|| format!("Failed to create {}", path.display()))?;
+        let path = path
+            .canonicalize()
+            .with_context(|| format!("Failed to resolve {}", path.display()))?;
+
+        // Hold the registry lock while opening, so two opens racing in this
+        // process do not both try to take the store's lock file.
+        let mut open_stores = OPEN_STORES.lock().expect("lock poisoned");
+        let store = match open_stores.get(&path).and_then(Weak::upgrade) {
+            // The segment size and sync policy of the first open apply.
+            Some(store) => store,
+            None => {
+                let store = Arc::new(Mutex::new(SegmentStore::open(
+                    &path,
+                    max_segment_size,
+                    sync_policy,
+                )?));
+                open_stores.retain(|_, store| store.strong_count() > 0);
+                open_stores.insert(path.clone(), Arc::downgrade(&store));
+                store
+            }
+        };
+
+        Ok(Self {
+            path,
+            store,
+            put_behaviour,
+        })
+    }
+
+    async fn with_store<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut SegmentStore) -> Result<T> + Send + 'static,
+    {
+        let store = self.store.clone();
+        spawn_blocking(move || f(&mut store.lock().expect("lock poisoned"))).await?
+    }
+
+    /// Make all writes so far durable, regardless of the sync policy.
+    pub async fn sync(&self) -> Result<()> {
+        self.with_store(|store| store.sync()).await
+    }
+
+    /// Reclaim space used by overwritten and unlinked blobs. Sealed segments
+    /// where at least `min_garbage_ratio` of the bytes are garbage have their
+    /// live blobs copied forward and are then deleted.
+    pub async fn compact(&self, min_garbage_ratio: f64) -> Result<CompactionStats> {
+        self.with_store(move |store| store.compact(min_garbage_ratio))
+            .await
+    }
+}
+
+impl fmt::Display for Segmentblob {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Segmentblob")
+    }
+}
+
+impl fmt::Debug for Segmentblob {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Segmentblob")
+            .field("path", &self.path)
+            .field("put_behaviour", &self.put_behaviour)
+            .finish()
+    }
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs() as i64)
+}
+
+#[async_trait]
+impl BlobstorePutOps for Segmentblob {
+    async fn put_explicit<'a>(
+        &'a self,
+        _ctx: &'a CoreContext,
+        key: String,
+        value: BlobstoreBytes,
+        put_behaviour: PutBehaviour,
+    ) -> Result<OverwriteStatus> {
+        self.with_store(move |store| {
+            let value = value.as_bytes().as_ref();
+            let status = match put_behaviour {
+                PutBehaviour::Overwrite => OverwriteStatus::NotChecked,
+                PutBehaviour::IfAbsent | PutBehaviour::OverwriteAndLog => {
+                    if !store.contains(&key)? {
+                        OverwriteStatus::New
+                    } else if put_behaviour.should_overwrite() {
+                        OverwriteStatus::Overwrote
+                    } else {
+                        return Ok(OverwriteStatus::Prevented);
+                    }
+                }
+            };
+            store.put(&key, value, now())?;
+            Ok(status)
+        })
+        .await
+    }
+
+    async fn put_with_status<'a>(
+        &'a self,
+        ctx: &'a CoreContext,
+        key: String,
+        value: BlobstoreBytes,
+    ) -> Result<OverwriteStatus> {
+        self.put_explicit(ctx, key, value, self.put_behaviour).await
+    }
+}
+
+#[async_trait]
+impl Blobstore for Segmentblob {
+    async fn get<'a>(
+        &'a self,
+        _ctx: &'a CoreContext,
+        key: &'a str,
+    ) -> Result<Option<BlobstoreGetData>> {
+        let key = key.to_owned();
+        let store = self.store.clone();
+        spawn_blocking(move || {
+            // Only look up the location under the lock; the segment file
+            // handle stays valid even if compaction deletes the file.
+            let located = store.lock().expect("lock poisoned").locate(&key)?;
+            let (loc, file) = match located {
+                Some(located) => located,
+                None => return Ok(None),
+            };
+            let value = store::read_value(&file, &loc)?;
+            Ok(Some(BlobstoreGetData::new(
+                BlobstoreMetadata::new(Some(loc.ctime), None),
+                BlobstoreBytes::from_bytes(value),
+            )))
+        })
+        .await?
+    }
+
+    async fn is_present<'a>(
+        &'a self,
+        _ctx: &'a CoreContext,
+        key: &'a str,
+    ) -> Result<BlobstoreIsPresent> {
+        let key = key.to_owned();
+        let present = self.with_store(move |store| store.contains(&key)).await?;
+        Ok(if present {
+            BlobstoreIsPresent::Present
+        } else {
+            BlobstoreIsPresent::Absent
+        })
+    }
+
+    async fn put<'a>(
+        &'a self,
+        ctx: &'a CoreContext,
+        key: String,
+        value: BlobstoreBytes,
+    ) -> Result<()> {
+        BlobstorePutOps::put_with_status(self, ctx, key, value).await?;
+        Ok(())
+    }
+
+    // Like Fileblob, this has hardlink semantics: both keys share one record.
+    async fn copy<'a>(
+        &'a self,
+        _ctx: &'a CoreContext,
+        old_key: &'a str,
+        new_key: String,
+    ) -> Result<()> {
+        let old_key = old_key.to_owned();
+        self.with_store(move |store| {
+            if store.link(&old_key, &new_key)? {
+                Ok(())
+            } else {
+                Err(format_err!(
+                    "Unknown key {} to Segmentblob::copy()",
+                    old_key
+                ))
+            }
+        })
+        .await
+    }
+}
+
+#[async_trait]
+impl BlobstoreUnlinkOps for Segmentblob {
+    async fn unlink<'a>(&'a self, _ctx: &'a CoreContext, key: &'a str) -> Result<()> {
+        let key = key.to_owned();
+        self.with_store(move |store| {
+            if store.unlink(&key)? {
+                Ok(())
+            } else {
+                Err(format_err!("Unknown key {} to Segmentblob::unlink()", key))
+            }
+        })
+        .await
+    }
+}
+
+#[async_trait]
+impl BlobstoreKeySource for Segmentblob {
+    async fn enumerate<'a>(
+        &'a self,
+        _ctx: &'a CoreContext,
+        range: &'a BlobstoreKeyParam,
+    ) -> Result<BlobstoreEnumerationData> {
+        match range {
+            BlobstoreKeyParam::Start(range) => {
+                let range = range.clone();
+                let keys = self
+                    .with_store(move |store| {
+                        let bound = |key: &String| {
+                            if key.is_empty() {
+                                Bound::Unbounded
+                            } else {
+                                Bound::Included(key.as_bytes())
+                            }
+                        };
+                        store.keys(bound(&range.begin_key), bound(&range.end_key))
+                    })
+                    .await?;
+                Ok(BlobstoreEnumerationData {
+                    keys: keys.into_iter().collect(),
+                    next_token: None,
+                })
+            }
+            BlobstoreKeyParam::Continuation(_) => {
+                Err(format_err!("Continuation not supported for segmentblob"))
+            }
+        }
+    }
+}
@@ -0,0 +1,480 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+//! On-disk layout of the segment file blobstore.
+//!
+//! A store directory contains:
+//!  - `segment-NNNNNNNNNN` files, which blob records are appended to. Only the
+//!    segment with the highest number is ever written to; once it grows past
+//!    the configured size a new one is started.
+//!  - `index`, an indexedlog mapping each key to the location of its record.
+//!    Unlinking appends a tombstone, which drops the key from the index.
+//!  - `lock`, held exclusively by the process that has the store open.
+//!    `Segmentblob::open` shares one `SegmentStore` between all opens of a
+//!    store within a process.
+//!
+//! Records are written to the segment before the index entry pointing at them,
+//! so a crash can only leave unreferenced bytes at the end of a segment. Those
+//! are reclaimed by compaction along with overwritten and unlinked blobs.
+
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::fs;
+use std::fs::File;
+use std::hash::Hasher;
+use std::ops::Bound;
+use std::os::unix::fs::FileExt;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::bail;
+use anyhow::format_err;
+use anyhow::Context;
+use anyhow::Result;
+use fs2::FileExt as LockExt;
+use indexedlog::log::IndexOutput;
+use indexedlog::log::Log;
+use indexedlog::log::OpenOptions;
+use metaconfig_types::SegmentSyncPolicy;
+use twox_hash::XxHash64;
+
+const LOCK_FILE: &str = "lock";
+const INDEX_DIR: &str = "index";
+const SEGMENT_PREFIX: &str = "segment-";
+
+const KEY_INDEX: usize = 0;
+
+/// With `SegmentSyncPolicy::Never`, index entries are buffered in memory and
+/// only written out once they reach this size, on `sync` and on drop.
+const INDEX_AUTO_SYNC_BYTES: u64 = 1 << 20;
+
+const RECORD_MAGIC: &[u8; 4] = b"SEG1";
+/// magic + key length + value length + ctime + checksum
+const RECORD_HEADER_LEN: u64 = 4 + 4 + 8 + 8 + 8;
+
+const ENTRY_PUT: u8 = 1;
+const ENTRY_TOMBSTONE: u8 = 2;
+/// kind + segment + offset + key length + value length + ctime
+const PUT_ENTRY_HEADER_LEN: usize = 1 + 4 + 8 + 4 + 8 + 8;
+
+/// Where a record lives in the segment files.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Location {
+    pub segment: u32,
+    pub offset: u64,
+    pub key_len: u32,
+    pub value_len: u64,
+    pub ctime: i64,
+}
+
+impl Location {
+    fn record_len(&self) -> u64 {
+        RECORD_HEADER_LEN + self.key_len as u64 + self.value_len
+    }
+}
+
+/// Outcome of a compaction pass.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CompactionStats {
+    /// Number of segment files deleted.
+    pub segments_removed: u64,
+    /// Number of live blobs copied out of the deleted segments.
+    pub blobs_moved: u64,
+    /// Bytes of disk space freed, not counting the copied blobs.
+    pub bytes_reclaimed: u64,
+}
+
+pub struct SegmentStore {
+    dir: PathBuf,
+    index: Log,
+    segments: BTreeMap<u32, Arc<File>>,
+    active_id: u32,
+    active_len: u64,
+    max_segment_size: u64,
+    sync_policy: SegmentSyncPolicy,
+    unsynced_writes: usize,
+    _lock: File,
+}
+
+fn index_key(entry: &[u8]) -> Vec<IndexOutput> {
+    match entry.first() {
+        Some(&ENTRY_PUT) if entry.len() > PUT_ENTRY_HEADER_LEN => {
+            vec![IndexOutput::Reference(
+                PUT_ENTRY_HEADER_LEN as u64..entry.len() as u64,
+            )]
+        }
+        Some(&ENTRY_TOMBSTONE) if entry.len() > 1 => {
+            vec![IndexOutput::Remove(entry[1..].to_vec().into_boxed_slice())]
+        }
+        _ => vec![],
+    }
+}
+
+fn encode_put_entry(key: &[u8], loc: &Location) -> Vec<u8> {
+    let mut entry = Vec::with_capacity(PUT_ENTRY_HEADER_LEN + key.len());
+    entry.push(ENTRY_PUT);
+    entry.extend_from_slice(&loc.segment.to_le_bytes());
+    entry.extend_from_slice(&loc.offset.to_le_bytes());
+    entry.extend_from_slice(&loc.key_len.to_le_bytes());
+    entry.extend_from_slice(&loc.value_len.to_le_bytes());
+    entry.extend_from_slice(&loc.ctime.to_le_bytes());
+    entry.extend_from_slice(key);
+    entry
+}
+
+fn encode_tombstone_entry(key: &[u8]) -> Vec<u8> {
+    let mut entry = Vec::with_capacity(1 + key.len());
+    entry.push(ENTRY_TOMBSTONE);
+    entry.extend_from_slice(key);
+    entry
+}
+
+fn decode_put_entry(entry: &[u8]) -> Result<Location> {
+    if entry.len() <= PUT_ENTRY_HEADER_LEN || entry[0] != ENTRY_PUT {
+        bail!("Malformed segmentblob index entry");
+    }
+    let u32_at = |at: usize| u32::from_le_bytes(entry[at..at + 4].try_into().unwrap());
+    let u64_at = |at: usize| u64::from_le_bytes(entry[at..at + 8].try_into().unwrap());
+    Ok(Location {
+        segment: u32_at(1),
+        offset: u64_at(5),
+        key_len: u32_at(13),
+        value_len: u64_at(17),
+        ctime: u64_at(25) as i64,
+    })
+}
+
+fn checksum(key: &[u8], value: &[u8]) -> u64 {
+    let mut hasher = XxHash64::with_seed(0);
+    hasher.write(key);
+    hasher.write(value);
+    hasher.finish()
+}
+
+fn parse_segment_id(name: &str) -> Option<u32> {
+    name.strip_prefix(SEGMENT_PREFIX)?.parse().ok()
+}
+
+fn open_segment(path: &Path, create: bool) -> Result<File> {
+    fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create_new(create)
+        .open(path)
+        .with_context(|| format!("Failed to open segment {}", path.display()))
+}
+
+/// Read the value of the record at `loc`, verifying it against its checksum.
+pub fn read_value(file: &File, loc: &Location) -> Result<Vec<u8>> {
+    let mut record = vec![0; loc.record_len() as usize];
+    file.read_exact_at(&mut record, loc.offset)
+        .with_context(|| format!("Failed to read record at {:?}", loc))?;
+
+    let header_len = RECORD_HEADER_LEN as usize;
+    let key_len = u32::from_le_bytes(record[4..8].try_into().unwrap());
+    let value_len = u64::from_le_bytes(record[8..16].try_into().unwrap());
+    let expected = u64::from_le_bytes(record[24..32].try_into().unwrap());
+    if &record[0..4] != RECORD_MAGIC || key_len != loc.key_len || value_len != loc.value_len {
+        bail!("Corrupt segmentblob record header at {:?}", loc);
+    }
+    let (key, value) = record[header_len..].split_at(key_len as usize);
+    if checksum(key, value) != expected {
+        bail!("Checksum mismatch for segmentblob record at {:?}", loc);
+    }
+
+    Ok(record.split_off(header_len + key_len as usize))
+}
+
+impl SegmentStore {
+    pub fn open(dir: &Path, max_segment_size: u64, sync_policy: SegmentSyncPolicy) -> Result<Self> {
+        fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+
+        let lock = fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .open(dir.join(LOCK_FILE))?;
+        lock.try_lock_exclusive()
+            .with_context(|| format!("Segmentblob {} is in use", dir.display()))?;
+
+        let never_sync = sync_policy == SegmentSyncPolicy::Never;
+        let index = OpenOptions::new()
+            .create(true)
+            .fsync(!never_sync)
+            .auto_sync_threshold(never_sync.then_some(INDEX_AUTO_SYNC_BYTES))
+            .index("key", index_key)
+            .open(dir.join(INDEX_DIR))?;
+
+        let mut segments = BTreeMap::new();
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            if let Some(id) = entry.file_name().to_str().and_then(parse_segment_id) {
+                segments.insert(id, Arc::new(open_segment(&entry.path(), false)?));
+            }
+        }
+
+        let mut store = Self {
+            dir: dir.to_owned(),
+            index,
+            segments,
+            active_id: 0,
+            active_len: 0,
+            max_segment_size,
+            sync_policy,
+            unsynced_writes: 0,
+            _lock: lock,
+        };
+
+        match store.segments.iter().next_back() {
+            // Any torn record at the end of the last segment is left in place:
+            // nothing in the index refers to it, and new records go after it.
+            Some((id, file)) => {
+                store.active_id = *id;
+                store.active_len = file.metadata()?.len();
+            }
+            None => store.start_segment(0)?,
+        }
+
+        Ok(store)
+    }
+
+    fn segment_path(&self, id: u32) -> PathBuf {
+        self.dir.join(format!("{}{:010}", SEGMENT_PREFIX, id))
+    }
+
+    fn start_segment(&mut self, id: u32) -> Result<()> {
+        let file = open_segment(&self.segment_path(id), true)?;
+        self.segments.insert(id, Arc::new(file));
+        self.active_id = id;
+        self.active_len = 0;
+        Ok(())
+    }
+
+    fn active_segment(&self) -> &File {
+        &self.segments[&self.active_id]
+    }
+
+    /// Seal the active segment and start writing to a new one.
+    fn roll_segment(&mut self) -> Result<()> {
+        if self.sync_policy != SegmentSyncPolicy::Never {
+            self.active_segment().sync_data()?;
+        }
+        let next_id = self
+            .active_id
+            .checked_add(1)
+            .ok_or_else(|| format_err!("Segmentblob ran out of segment ids"))?;
+        self.start_segment(next_id)
+    }
+
+    fn append_record(&mut self, key: &[u8], value: &[u8], ctime: i64) -> Result<Location> {
+        let loc = Location {
+            segment: self.active_id,
+            offset: self.active_len,
+            key_len: key
+                .len()
+                .try_into()
+                .context("Segmentblob key is too long")?,
+            value_len: value.len() as u64,
+            ctime,
+        };
+        let loc =
+            if self.active_len > 0 && self.active_len + loc.record_len() > self.max_segment_size {
+                self.roll_segment()?;
+                Location {
+                    segment: self.active_id,
+                    offset: 0,
+                    ..loc
+                }
+            } else {
+                loc
+            };
+
+        let mut record = Vec::with_capacity(loc.record_len() as usize);
+        record.extend_from_slice(RECORD_MAGIC);
+        record.extend_from_slice(&loc.key_len.to_le_bytes());
+        record.extend_from_slice(&loc.value_len.to_le_bytes());
+        record.extend_from_slice(&ctime.to_le_bytes());
+        record.extend_from_slice(&checksum(key, value).to_le_bytes());
+        record.extend_from_slice(key);
+        record.extend_from_slice(value);
+
+        self.active_segment().write_all_at(&record, loc.offset)?;
+        self.active_len += loc.record_len();
+        Ok(loc)
+    }
+
+    /// Persist pending writes according to the sync policy.
+    fn write_done(&mut self) -> Result<()> {
+        self.unsynced_writes += 1;
+        match self.sync_policy {
+            SegmentSyncPolicy::EveryWrite => self.sync(),
+            SegmentSyncPolicy::Batched(n) if self.unsynced_writes >= n.get() => self.sync(),
+            SegmentSyncPolicy::Batched(_) | SegmentSyncPolicy::Never => Ok(()),
+        }
+    }
+
+    /// Make all writes so far durable. Segment data is flushed before the
+    /// index, so the index never refers to data that is not on disk.
+    pub fn sync(&mut self) -> Result<()> {
+        if self.sync_policy != SegmentSyncPolicy::Never {
+            self.active_segment().sync_data()?;
+        }
+        self.index.sync()?;
+        self.unsynced_writes = 0;
+        Ok(())
+    }
+
+    pub fn locate(&self, key: &str) -> Result<Option<(Location, Arc<File>)>> {
+        if key.is_empty() {
+            bail!("Segmentblob does not support empty keys");
+        }
+        let entry = match self.index.lookup(KEY_INDEX, key)?.next() {
+            Some(entry) => entry?,
+            None => return Ok(None),
+        };
+        let loc = decode_put_entry(entry)?;
+        let file = self
+            .segments
+            .get(&loc.segment)
+            .ok_or_else(|| format_err!("Segmentblob key {} refers to missing segment", key))?;
+        Ok(Some((loc, file.clone())))
+    }
+
+    pub fn contains(&self, key: &str) -> Result<bool> {
+        Ok(self.locate(key)?.is_some())
+    }
+
+    pub fn put(&mut self, key: &str, value: &[u8], ctime: i64) -> Result<()> {
+        if key.is_empty() {
+            bail!("Segmentblob does not support empty keys");
+        }
+        let loc = self.append_record(key.as_bytes(), value, ctime)?;
+        self.index.append(encode_put_entry(key.as_bytes(), &loc))?;
+        self.write_done()
+    }
+
+    /// Point `new_key` at the record of `old_key`. Returns false if `old_key`
+    /// does not exist.
+    pub fn link(&mut self, old_key: &str, new_key: &str) -> Result<bool> {
+        if new_key.is_empty() {
+            bail!("Segmentblob does not support empty keys");
+        }
+        let loc = match self.locate(old_key)? {
+            Some((loc, _)) => loc,
+            None => return Ok(false),
+        };
+        self.index
+            .append(encode_put_entry(new_key.as_bytes(), &loc))?;
+        self.write_done()?;
+        Ok(true)
+    }
+
+    /// Remove `key` from the index. Returns false if it did not exist.
+    pub fn unlink(&mut self, key: &str) -> Result<bool> {
+        if !self.contains(key)? {
+            return Ok(false);
+        }
+        self.index.append(encode_tombstone_entry(key.as_bytes()))?;
+        self.write_done()?;
+        Ok(true)
+    }
+
+    pub fn keys(&self, start: Bound<&[u8]>, end: Bound<&[u8]>) -> Result<Vec<String>> {
+        let mut keys = Vec::new();
+        for item in self.index.lookup_range(KEY_INDEX, (start, end))? {
+            let (key, _) = item?;
+            keys.push(String::from_utf8(key.into_owned()).context("Non-UTF8 segmentblob key")?);
+        }
+        Ok(keys)
+    }
+
+    /// Rewrite sealed segments in which at least `min_garbage_ratio` of the
+    /// bytes are no longer referenced, then delete them.
+    ///
+    /// Old segments are only deleted once the copies and the index entries
+    /// pointing at them are on disk, so an interrupted compaction leaves the
+    /// store readable and at worst holding duplicate records.
+    ///
+    /// The active segment is sealed first only if it qualifies itself, so a
+    /// pass that finds nothing to reclaim leaves the segment files as they
+    /// were.
+    pub fn compact(&mut self, min_garbage_ratio: f64) -> Result<CompactionStats> {
+        // Segment -> offset -> (record, keys referring to it). Several keys
+        // can share a record after a `link`.
+        let mut live: HashMap<u32, BTreeMap<u64, (Location, Vec<Vec<u8>>)>> = HashMap::new();
+        for item in self.index.lookup_range(KEY_INDEX, ..)? {
+            let (key, mut entries) = item?;
+            if let Some(entry) = entries.next() {
+                let loc = decode_put_entry(entry?)?;
+                live.entry(loc.segment)
+                    .or_default()
+                    .entry(loc.offset)
+                    .or_insert_with(|| (loc, Vec::new()))
+                    .1
+                    .push(key.into_owned());
+            }
+        }
+
+        // Segment -> (live records, garbage bytes) for each segment to compact.
+        let mut candidates = Vec::new();
+        for (id, file) in &self.segments {
+            if *id == self.active_id && self.active_len == 0 {
+                continue;
+            }
+            let total = file.metadata()?.len();
+            let records = live.remove(id).unwrap_or_default();
+            let live_bytes: u64 = records.values().map(|(loc, _)| loc.record_len()).sum();
+            let garbage = total.saturating_sub(live_bytes);
+            if !records.is_empty()
+                && (garbage == 0 || (garbage as f64) < min_garbage_ratio * total as f64)
+            {
+                continue;
+            }
+            candidates.push((*id, records, garbage));
+        }
+
+        // Copies must not go into a segment that is about to be deleted.
+        if candidates.iter().any(|(id, _, _)| *id == self.active_id) {
+            self.roll_segment()?;
+        }
+
+        let mut stats = CompactionStats::default();
+        let mut compacted = Vec::new();
+        for (id, records, garbage) in candidates {
+            let file = self.segments[&id].clone();
+            for (loc, keys) in records.into_values() {
+                let value = read_value(&file, &loc)?;
+                let new_loc = self.append_record(&keys[0], &value, loc.ctime)?;
+                for key in keys {
+                    self.index.append(encode_put_entry(&key, &new_loc))?;
+                }
+                stats.blobs_moved += 1;
+            }
+            stats.bytes_reclaimed += garbage;
+            compacted.push(id);
+        }
+
+        if !compacted.is_empty() {
+            self.active_segment().sync_data()?;
+            self.index.sync()?;
+            self.unsynced_writes = 0;
+            for id in compacted {
+                self.segments.remove(&id);
+                fs::remove_file(self.segment_path(id))?;
+                stats.segments_removed += 1;
+            }
+        }
+
+        Ok(stats)
+    }
+}
+
+impl Drop for SegmentStore {
+    fn drop(&mut self) {
+        let _ = self.sync();
+    }
+}
@@ -0,0 +1,232 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+use std::fs;
+use std::io::Write;
+use std::ops::Bound;
+
+use fbinit::FacebookInit;
+use tempfile::TempDir;
+
+use super::*;
+
+fn open_store(dir: &TempDir, max_segment_size: u64) -> Result<SegmentStore> {
+    SegmentStore::open(dir.path(), max_segment_size, SegmentSyncPolicy::EveryWrite)
+}
+
+fn get(store: &SegmentStore, key: &str) -> Result<Option<Vec<u8>>> {
+    match store.locate(key)? {
+        Some((loc, file)) => Ok(Some(store::read_value(&file, &loc)?)),
+        None => Ok(None),
+    }
+}
+
+fn segment_count(dir: &TempDir) -> Result<usize> {
+    Ok(fs::read_dir(dir.path())?
+        .filter(|entry| {
+            entry.as_ref().map_or(false, |entry| {
+                entry.file_name().to_string_lossy().starts_with("segment-")
+            })
+        })
+        .count())
+}
+
+#[test]
+fn test_reopen() -> Result<()> {
+    let dir = TempDir::new()?;
+    {
+        let mut store = open_store(&dir, DEFAULT_MAX_SEGMENT_SIZE)?;
+        store.put("foo", b"foo value", 1)?;
+        store.put("bar", b"bar value", 2)?;
+        store.put("foo", b"new foo value", 3)?;
+        assert!(store.unlink("bar")?);
+        assert!(!store.unlink("bar")?);
+    }
+
+    let store = open_store(&dir, DEFAULT_MAX_SEGMENT_SIZE)?;
+    assert_eq!(get(&store, "foo")?, Some(b"new foo value".to_vec()));
+    assert_eq!(get(&store, "bar")?, None);
+    assert_eq!(store.locate("foo")?.unwrap().0.ctime, 3);
+    Ok(())
+}
+
+#[test]
+fn test_exclusive_lock() -> Result<()> {
+    let dir = TempDir::new()?;
+    let _store = open_store(&dir, DEFAULT_MAX_SEGMENT_SIZE)?;
+    assert!(open_store(&dir, DEFAULT_MAX_SEGMENT_SIZE).is_err());
+    Ok(())
+}
+
+#[fbinit::test]
+async fn test_reopen_in_process(fb: FacebookInit) -> Result<()> {
+    let ctx = CoreContext::test_mock(fb);
+    let dir = TempDir::new()?;
+    let open = || {
+        Segmentblob::open(
+            dir.path(),
+            DEFAULT_MAX_SEGMENT_SIZE,
+            SegmentSyncPolicy::EveryWrite,
+            PutBehaviour::Overwrite,
+        )
+    };
+
+    let first = open()?;
+    let second = open()?;
+    first
+        .put(
+            &ctx,
+            "foo".to_string(),
+            BlobstoreBytes::from_bytes("foo value"),
+        )
+        .await?;
+    let value = second.get(&ctx, "foo").await?.unwrap();
+    assert_eq!(value.into_bytes(), BlobstoreBytes::from_bytes("foo value"));
+
+    // Once every handle is dropped, the store can be opened again.
+    drop(first);
+    drop(second);
+    assert!(open()?.get(&ctx, "foo").await?.is_some());
+    Ok(())
+}
+
+#[test]
+fn test_empty_keys() -> Result<()> {
+    let dir = TempDir::new()?;
+    let mut store = open_store(&dir, DEFAULT_MAX_SEGMENT_SIZE)?;
+    store.put("foo", b"foo value", 1)?;
+    assert!(store.put("", b"value", 1).is_err());
+    assert!(store.link("foo", "").is_err());
+    assert_eq!(
+        store.keys(Bound::Unbounded, Bound::Unbounded)?,
+        vec!["foo".to_string()]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_never_sync_buffers_index() -> Result<()> {
+    let dir = TempDir::new()?;
+    let index_log = dir.path().join("index").join("log");
+    let mut store = SegmentStore::open(
+        dir.path(),
+        DEFAULT_MAX_SEGMENT_SIZE,
+        SegmentSyncPolicy::Never,
+    )?;
+    let len_before = fs::metadata(&index_log)?.len();
+    store.put("foo", b"foo value", 1)?;
+    assert_eq!(fs::metadata(&index_log)?.len(), len_before);
+
+    store.sync()?;
+    assert!(fs::metadata(&index_log)?.len() > len_before);
+    Ok(())
+}
+
+#[test]
+fn test_torn_write() -> Result<()> {
+    let dir = TempDir::new()?;
+    {
+        let mut store = open_store(&dir, DEFAULT_MAX_SEGMENT_SIZE)?;
+        store.put("foo", b"foo value", 1)?;
+    }
+
+    // Simulate a crash halfway through appending a record.
+    let segment = dir.path().join("segment-0000000000");
+    fs::OpenOptions::new()
+        .append(true)
+        .open(&segment)?
+        .write_all(b"SEG1\x03\x00")?;
+
+    let mut store = open_store(&dir, DEFAULT_MAX_SEGMENT_SIZE)?;
+    assert_eq!(get(&store, "foo")?, Some(b"foo value".to_vec()));
+    store.put("bar", b"bar value", 2)?;
+    assert_eq!(get(&store, "bar")?, Some(b"bar value".to_vec()));
+    Ok(())
+}
+
+#[test]
+fn test_corruption_detected() -> Result<()> {
+    let dir = TempDir::new()?;
+    let mut store = open_store(&dir, DEFAULT_MAX_SEGMENT_SIZE)?;
+    store.put("foo", b"foo value", 1)?;
+
+    let segment = dir.path().join("segment-0000000000");
+    let mut data = fs::read(&segment)?;
+    *data.last_mut().unwrap() ^= 0xff;
+    fs::write(&segment, data)?;
+
+    assert!(get(&store, "foo").is_err());
+    Ok(())
+}
+
+#[test]
+fn test_compaction() -> Result<()> {
+    let dir = TempDir::new()?;
+    let mut store = open_store(&dir, 256)?;
+    for i in 0..20 {
+        store.put(&format!("key{}", i), &[i as u8; 64], i)?;
+    }
+    assert!(store.link("key0", "copy0")?);
+    for i in 1..15 {
+        assert!(store.unlink(&format!("key{}", i))?);
+    }
+    let segments_before = segment_count(&dir)?;
+
+    let stats = store.compact(0.5)?;
+    assert!(stats.segments_removed > 0);
+    assert!(stats.bytes_reclaimed > 0);
+    assert!(segment_count(&dir)? < segments_before);
+
+    assert_eq!(get(&store, "key0")?, Some(vec![0; 64]));
+    assert_eq!(get(&store, "copy0")?, Some(vec![0; 64]));
+    for i in 15..20 {
+        let key = format!("key{}", i);
+        assert_eq!(get(&store, &key)?, Some(vec![i as u8; 64]));
+        assert_eq!(store.locate(&key)?.unwrap().0.ctime, i);
+    }
+    assert_eq!(
+        store.keys(Bound::Unbounded, Bound::Unbounded)?.len(),
+        7,
+        "copy0, key0 and key15..key19 should survive"
+    );
+
+    // Everything is live now, so a second pass has nothing to do and does
+    // not start a new segment.
+    let segments_after = segment_count(&dir)?;
+    assert_eq!(store.compact(0.5)?, CompactionStats::default());
+    assert_eq!(segment_count(&dir)?, segments_after);
+    Ok(())
+}
+
+#[fbinit::test]
+async fn test_enumerate(fb: FacebookInit) -> Result<()> {
+    let ctx = CoreContext::test_mock(fb);
+    let dir = TempDir::new()?;
+    let blobstore = Segmentblob::open(
+        dir.path(),
+        DEFAULT_MAX_SEGMENT_SIZE,
+        SegmentSyncPolicy::Batched(std::num::NonZeroUsize::new(10).unwrap()),
+        PutBehaviour::Overwrite,
+    )?;
+    for key in ["a", "b", "c", "d"] {
+        blobstore
+            .put(&ctx, key.to_string(), BlobstoreBytes::from_bytes(key))
+            .await?;
+    }
+    blobstore.unlink(&ctx, "c").await?;
+
+    let range = BlobstoreKeyParam::from("b".to_string()..="d".to_string());
+    let mut keys: Vec<_> = blobstore
+        .enumerate(&ctx, &range)
+        .await?
+        .keys
+        .into_iter()
+        .collect();
+    keys.sort();
+    assert_eq!(keys, vec!["b".to_string(), "d".to_string()]);
+    Ok(())
+}
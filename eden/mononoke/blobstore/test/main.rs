@@ -24,7 +24,10 @@ use context::CoreContext;
 use fbinit::FacebookInit;
 use fileblob::Fileblob;
 use memblob::Memblob;
+use metaconfig_types::SegmentSyncPolicy;
 use mononoke_types::BlobstoreBytes;
+use segmentblob::Segmentblob;
+use segmentblob::DEFAULT_MAX_SEGMENT_SIZE;
 use sqlblob::get_test_config_store;
 use sqlblob::Sqlblob;
 use strum::IntoEnumIterator;
@@ -246,6 +249,15 @@ blobstore_test_impl! {
     }
 }
 
+blobstore_test_impl! {
+    segmentblob_test => {
+        state: Arc::new(TempDir::new("segmentblob_test").unwrap()),
+        new: move |dir: Arc<TempDir>, put_behaviour,| Segmentblob::open(&*dir, DEFAULT_MAX_SEGMENT_SIZE, SegmentSyncPolicy::EveryWrite, put_behaviour),
+        persistent: true,
+        has_ctime: true,
+    }
+}
+
 blobstore_test_impl! {
     sqlblob_test_no_inline => {
         state: (),
@@ -0,0 +1,413 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+//! A blobstore that keeps recently used blobs on fast storage and the rest on
+//! cheap storage.
+//!
+//! Writes go to the hot tier. Reads try the hot tier and fall back to the cold
+//! tier, optionally promoting what they find. A mover, run from a single job
+//! with `demote_pass`, demotes blobs that have not been written or read for a
+//! while from hot to cold.
+//!
+//! Recency is taken from the hot tier's ctime. Reads rewrite blobs whose ctime
+//! is getting old, so the hot tier itself records when each blob was last
+//! used and every process sees the same access times. Writes to the hot tier
+//! and the final step of a demotion hold a per-key lease, so a blob written
+//! while it is being demoted is never unlinked.
+
+#[cfg(test)]
+mod test;
+
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::SystemTime;
+
+use anyhow::bail;
+use anyhow::Result;
+use async_trait::async_trait;
+use blobstore::Blobstore;
+use blobstore::BlobstoreGetData;
+use blobstore::BlobstoreIsPresent;
+use blobstore::BlobstoreKeyParam;
+use blobstore::BlobstoreKeySource;
+use blobstore::BlobstorePutOps;
+use blobstore::BlobstoreUnlinkOps;
+use blobstore::OverwriteStatus;
+use blobstore::PutBehaviour;
+use blobstore::DEFAULT_PUT_BEHAVIOUR;
+use cacheblob::LeaseOps;
+use context::CoreContext;
+use futures::channel::oneshot;
+use futures::future::FutureExt;
+use futures::stream;
+use futures::stream::StreamExt;
+use mononoke_types::BlobstoreBytes;
+use slog::warn;
+
+/// Longest wait between attempts to take a key's lease.
+const MAX_LEASE_BACKOFF: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Debug)]
+pub struct TieredOptions {
+    /// Copy blobs that are only in the cold tier into the hot tier when read.
+    pub promote_on_read: bool,
+    /// Blobs that have not been written or read for this long are moved to
+    /// the cold tier by the mover. If None, blobs are never demoted and hot
+    /// writes do not take leases.
+    pub demote_after: Option<Duration>,
+    /// How many blobs the mover demotes at once.
+    pub mover_concurrency: usize,
+    /// Put behaviour for puts that do not specify one.
+    pub put_behaviour: PutBehaviour,
+}
+
+impl Default for TieredOptions {
+    fn default() -> Self {
+        Self {
+            promote_on_read: true,
+            demote_after: None,
+            mover_concurrency: 100,
+            put_behaviour: DEFAULT_PUT_BEHAVIOUR,
+        }
+    }
+}
+
+/// Outcome of a demotion pass over the hot tier.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DemotionStats {
+    pub demoted: u64,
+    pub kept: u64,
+    pub failed: u64,
+}
+
+#[derive(Debug)]
+pub struct TieredBlobstore<H, C> {
+    hot: H,
+    cold: C,
+    /// Excludes hot writes of a key while a demotion decides whether to
+    /// unlink it. Shared by every process that writes to the hot tier.
+    lease: Arc<dyn LeaseOps>,
+    options: TieredOptions,
+}
+
+/// A held lease on a key. The lease is renewed until the guard is dropped.
+struct KeyLeaseGuard {
+    sender: Option<oneshot::Sender<()>>,
+}
+
+impl Drop for KeyLeaseGuard {
+    fn drop(&mut self) {
+        if let Some(sender) = self.sender.take() {
+            let _ = sender.send(());
+        }
+    }
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs() as i64)
+}
+
+impl<H, C> TieredBlobstore<H, C> {
+    pub fn new(hot: H, cold: C, lease: Arc<dyn LeaseOps>, options: TieredOptions) -> Self {
+        Self {
+            hot,
+            cold,
+            lease,
+            options,
+        }
+    }
+
+    /// Take the lease on `key`, waiting for any other holder to release it.
+    /// Returns None if blobs are never demoted, as there is nothing to
+    /// exclude.
+    async fn lock_key(&self, ctx: &CoreContext, key: &str) -> Result<Option<KeyLeaseGuard>> {
+        if self.options.demote_after.is_none() {
+            return Ok(None);
+        }
+        let mut backoff = Duration::from_millis(10);
+        while !self.lease.try_add_put_lease(key).await? {
+            tokio::time::sleep(backoff).await;
+            backoff = std::cmp::min(backoff * 2, MAX_LEASE_BACKOFF);
+        }
+        let (sender, receiver) = oneshot::channel();
+        self.lease
+            .renew_lease_until(ctx.clone(), key, receiver.map(|_| ()).boxed());
+        Ok(Some(KeyLeaseGuard {
+            sender: Some(sender),
+        }))
+    }
+
+    /// The time before which a blob must have been last used to be demoted.
+    fn demotion_cutoff(&self, now: i64) -> Option<i64> {
+        let demote_after = self.options.demote_after?;
+        Some(now.saturating_sub(i64::try_from(demote_after.as_secs()).unwrap_or(i64::MAX)))
+    }
+
+    /// Whether a blob with this ctime may be demoted. Without a ctime there
+    /// is no telling when the blob was last used, so it stays hot.
+    fn is_idle(&self, ctime: Option<i64>, now: i64) -> bool {
+        match (self.demotion_cutoff(now), ctime) {
+            (Some(cutoff), Some(ctime)) => ctime <= cutoff,
+            _ => false,
+        }
+    }
+
+    /// Whether a read of a blob with this ctime should rewrite it to record
+    /// the access. Only blobs halfway to demotion are rewritten, so a blob is
+    /// rewritten at most once per half of `demote_after` however often it is
+    /// read.
+    fn needs_refresh(&self, ctime: Option<i64>, now: i64) -> bool {
+        match (self.options.demote_after, ctime) {
+            (Some(demote_after), Some(ctime)) => {
+                let half = i64::try_from(demote_after.as_secs() / 2).unwrap_or(i64::MAX);
+                ctime <= now.saturating_sub(half)
+            }
+            _ => false,
+        }
+    }
+}
+
+impl<H: BlobstorePutOps, C> TieredBlobstore<H, C> {
+    /// Write to the hot tier while holding the key's lease.
+    async fn put_hot(
+        &self,
+        ctx: &CoreContext,
+        key: String,
+        value: BlobstoreBytes,
+        put_behaviour: PutBehaviour,
+    ) -> Result<OverwriteStatus> {
+        let _guard = self.lock_key(ctx, &key).await?;
+        self.hot.put_explicit(ctx, key, value, put_behaviour).await
+    }
+
+    /// Rewrite a hot blob so that its ctime records a read.
+    async fn refresh(&self, ctx: &CoreContext, key: &str) -> Result<()> {
+        let _guard = self.lock_key(ctx, key).await?;
+        // Re-read under the lease so a concurrent write is not replaced by the
+        // value read before it.
+        if let Some(data) = self.hot.get(ctx, key).await? {
+            self.hot
+                .put_explicit(
+                    ctx,
+                    key.to_owned(),
+                    data.into_bytes(),
+                    PutBehaviour::Overwrite,
+                )
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+impl<H, C> TieredBlobstore<H, C>
+where
+    H: BlobstoreUnlinkOps,
+    C: BlobstorePutOps,
+{
+    /// Move `key` from the hot to the cold tier if it has been idle for long
+    /// enough. Returns whether the blob was moved.
+    ///
+    /// The blob is copied and confirmed present in the cold tier before the
+    /// hot copy is removed, so it is always in at least one tier. If the cold
+    /// tier is a multiplex, its put only succeeds once enough components have
+    /// the blob and the healer takes care of the rest, so demotion never
+    /// leaves a blob less durable than the cold tier's configuration promises.
+    pub async fn demote(&self, ctx: &CoreContext, key: &str, now: i64) -> Result<bool> {
+        let data = match self.hot.get(ctx, key).await? {
+            Some(data) => data,
+            None => return Ok(false),
+        };
+        if !self.is_idle(data.as_meta().ctime(), now) {
+            return Ok(false);
+        }
+
+        self.cold
+            .put_explicit(
+                ctx,
+                key.to_owned(),
+                data.as_bytes().clone(),
+                PutBehaviour::Overwrite,
+            )
+            .await?;
+        if !self.cold.is_present(ctx, key).await?.fail_if_unsure()? {
+            bail!(
+                "Blob {} is missing from the cold tier after being copied",
+                key
+            );
+        }
+
+        // Keep the hot copy if it was rewritten or read during the copy. Hot
+        // writes wait for the lease, so none can land between this check and
+        // the unlink.
+        let _guard = self.lock_key(ctx, key).await?;
+        match self.hot.get(ctx, key).await? {
+            Some(current)
+                if current.as_bytes() == data.as_bytes()
+                    && self.is_idle(current.as_meta().ctime(), now) => {}
+            _ => return Ok(false),
+        }
+        self.hot.unlink(ctx, key).await?;
+        Ok(true)
+    }
+}
+
+impl<H, C> TieredBlobstore<H, C>
+where
+    H: BlobstoreKeySource + BlobstoreUnlinkOps,
+    C: BlobstorePutOps,
+{
+    /// Demote every idle blob in the hot tier. Run this from a single job or
+    /// tool rather than from every process using the blobstore.
+    pub async fn demote_pass(&self, ctx: &CoreContext) -> Result<DemotionStats> {
+        let now = now();
+        let mut stats = DemotionStats::default();
+        let mut param = BlobstoreKeyParam::from(..);
+        loop {
+            let data = self.hot.enumerate(ctx, &param).await?;
+            let results: Vec<_> = stream::iter(data.keys)
+                .map(|key| async move { (self.demote(ctx, &key, now).await, key) })
+                .buffer_unordered(self.options.mover_concurrency)
+                .collect()
+                .await;
+            for (result, key) in results {
+                match result {
+                    Ok(true) => stats.demoted += 1,
+                    Ok(false) => stats.kept += 1,
+                    Err(e) => {
+                        stats.failed += 1;
+                        warn!(ctx.logger(), "Failed to demote {}: {:?}", key, e);
+                    }
+                }
+            }
+            match data.next_token {
+                Some(next) => param = next,
+                None => break,
+            }
+        }
+
+        Ok(stats)
+    }
+}
+
+impl<H: fmt::Display, C: fmt::Display> fmt::Display for TieredBlobstore<H, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "TieredBlobstore<{}, {}>", self.hot, self.cold)
+    }
+}
+
+#[async_trait]
+impl<H: BlobstorePutOps, C: Blobstore> Blobstore for TieredBlobstore<H, C> {
+    async fn get<'a>(
+        &'a self,
+        ctx: &'a CoreContext,
+        key: &'a str,
+    ) -> Result<Option<BlobstoreGetData>> {
+        if let Some(data) = self.hot.get(ctx, key).await? {
+            if self.needs_refresh(data.as_meta().ctime(), now()) {
+                // Failing to record the read only risks an early demotion.
+                if let Err(e) = self.refresh(ctx, key).await {
+                    warn!(ctx.logger(), "Failed to record read of {}: {:?}", key, e);
+                }
+            }
+            return Ok(Some(data));
+        }
+
+        let data = self.cold.get(ctx, key).await?;
+        if let Some(data) = &data {
+            if self.options.promote_on_read {
+                // Promotion is only an optimisation; the blob is still in the
+                // cold tier if it fails. Never replace a newer hot write.
+                if let Err(e) = self
+                    .put_hot(
+                        ctx,
+                        key.to_owned(),
+                        data.as_bytes().clone(),
+                        PutBehaviour::IfAbsent,
+                    )
+                    .await
+                {
+                    warn!(
+                        ctx.logger(),
+                        "Failed to promote {} to hot tier: {:?}", key, e
+                    );
+                }
+            }
+        }
+        Ok(data)
+    }
+
+    async fn is_present<'a>(
+        &'a self,
+        ctx: &'a CoreContext,
+        key: &'a str,
+    ) -> Result<BlobstoreIsPresent> {
+        match self.hot.is_present(ctx, key).await? {
+            BlobstoreIsPresent::Present => Ok(BlobstoreIsPresent::Present),
+            hot => match self.cold.is_present(ctx, key).await? {
+                BlobstoreIsPresent::Absent => Ok(hot),
+                cold => Ok(cold),
+            },
+        }
+    }
+
+    async fn put<'a>(
+        &'a self,
+        ctx: &'a CoreContext,
+        key: String,
+        value: BlobstoreBytes,
+    ) -> Result<()> {
+        BlobstorePutOps::put_with_status(self, ctx, key, value).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<H: BlobstorePutOps, C: Blobstore> BlobstorePutOps for TieredBlobstore<H, C> {
+    async fn put_explicit<'a>(
+        &'a self,
+        ctx: &'a CoreContext,
+        key: String,
+        value: BlobstoreBytes,
+        put_behaviour: PutBehaviour,
+    ) -> Result<OverwriteStatus> {
+        let _guard = self.lock_key(ctx, &key).await?;
+        // A demoted blob is only in the cold tier, so it counts as present.
+        // This is checked under the lease so a demotion cannot move the blob
+        // out of the hot tier after the cold tier has been checked.
+        if put_behaviour == PutBehaviour::IfAbsent
+            && self.cold.is_present(ctx, &key).await?.fail_if_unsure()?
+        {
+            return Ok(OverwriteStatus::Prevented);
+        }
+        self.hot.put_explicit(ctx, key, value, put_behaviour).await
+    }
+
+    async fn put_with_status<'a>(
+        &'a self,
+        ctx: &'a CoreContext,
+        key: String,
+        value: BlobstoreBytes,
+    ) -> Result<OverwriteStatus> {
+        self.put_explicit(ctx, key, value, self.options.put_behaviour)
+            .await
+    }
+}
+
+#[async_trait]
+impl<H: BlobstoreUnlinkOps, C: BlobstoreUnlinkOps> BlobstoreUnlinkOps for TieredBlobstore<H, C> {
+    async fn unlink<'a>(&'a self, ctx: &'a CoreContext, key: &'a str) -> Result<()> {
+        // The key may be in either tier, or in both after a promotion.
+        let (hot, cold) = futures::join!(self.hot.unlink(ctx, key), self.cold.unlink(ctx, key));
+        match (hot, cold) {
+            (Err(e), Err(_)) => Err(e),
+            _ => Ok(()),
+        }
+    }
+}
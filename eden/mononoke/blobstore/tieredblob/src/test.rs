@@ -0,0 +1,334 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+use std::collections::HashMap;
+use std::sync::atomic::AtomicI64;
+use std::sync::atomic::Ordering;
+use std::sync::Mutex;
+
+use blobstore::BlobstoreEnumerationData;
+use blobstore::BlobstoreMetadata;
+use borrowed::borrowed;
+use cacheblob::InProcessLease;
+use fbinit::FacebookInit;
+use memblob::Memblob;
+
+use super::*;
+
+/// A Memblob that records the ctime of each put, taken from a settable clock.
+#[derive(Clone, Debug, Default)]
+struct CtimeBlob {
+    inner: Memblob,
+    ctimes: Arc<Mutex<HashMap<String, i64>>>,
+    clock: Arc<AtomicI64>,
+}
+
+impl CtimeBlob {
+    fn set_clock(&self, now: i64) {
+        self.clock.store(now, Ordering::SeqCst);
+    }
+}
+
+impl fmt::Display for CtimeBlob {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "CtimeBlob")
+    }
+}
+
+#[async_trait]
+impl Blobstore for CtimeBlob {
+    async fn get<'a>(
+        &'a self,
+        ctx: &'a CoreContext,
+        key: &'a str,
+    ) -> Result<Option<BlobstoreGetData>> {
+        let ctime = self.ctimes.lock().unwrap().get(key).copied();
+        Ok(self.inner.get(ctx, key).await?.map(|data| {
+            BlobstoreGetData::new(BlobstoreMetadata::new(ctime, None), data.into_bytes())
+        }))
+    }
+
+    async fn put<'a>(
+        &'a self,
+        ctx: &'a CoreContext,
+        key: String,
+        value: BlobstoreBytes,
+    ) -> Result<()> {
+        self.put_with_status(ctx, key, value).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl BlobstorePutOps for CtimeBlob {
+    async fn put_explicit<'a>(
+        &'a self,
+        ctx: &'a CoreContext,
+        key: String,
+        value: BlobstoreBytes,
+        put_behaviour: PutBehaviour,
+    ) -> Result<OverwriteStatus> {
+        let status = self
+            .inner
+            .put_explicit(ctx, key.clone(), value, put_behaviour)
+            .await?;
+        if status != OverwriteStatus::Prevented {
+            let now = self.clock.load(Ordering::SeqCst);
+            self.ctimes.lock().unwrap().insert(key, now);
+        }
+        Ok(status)
+    }
+
+    async fn put_with_status<'a>(
+        &'a self,
+        ctx: &'a CoreContext,
+        key: String,
+        value: BlobstoreBytes,
+    ) -> Result<OverwriteStatus> {
+        self.put_explicit(ctx, key, value, PutBehaviour::Overwrite)
+            .await
+    }
+}
+
+#[async_trait]
+impl BlobstoreUnlinkOps for CtimeBlob {
+    async fn unlink<'a>(&'a self, ctx: &'a CoreContext, key: &'a str) -> Result<()> {
+        BlobstoreUnlinkOps::unlink(&self.inner, ctx, key).await?;
+        self.ctimes.lock().unwrap().remove(key);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl BlobstoreKeySource for CtimeBlob {
+    async fn enumerate<'a>(
+        &'a self,
+        ctx: &'a CoreContext,
+        range: &'a BlobstoreKeyParam,
+    ) -> Result<BlobstoreEnumerationData> {
+        self.inner.enumerate(ctx, range).await
+    }
+}
+
+fn make_tiered<H: Clone + Default>(
+    demote_after: Option<Duration>,
+    promote_on_read: bool,
+) -> (H, Memblob, TieredBlobstore<H, Memblob>) {
+    let hot = H::default();
+    let cold = Memblob::default();
+    let tiered = TieredBlobstore::new(
+        hot.clone(),
+        cold.clone(),
+        Arc::new(InProcessLease::new()),
+        TieredOptions {
+            promote_on_read,
+            demote_after,
+            ..Default::default()
+        },
+    );
+    (hot, cold, tiered)
+}
+
+async fn present<B: Blobstore>(ctx: &CoreContext, blobstore: &B, key: &str) -> Result<bool> {
+    Ok(blobstore
+        .is_present(ctx, key)
+        .await?
+        .assume_not_found_if_unsure())
+}
+
+#[fbinit::test]
+async fn test_put_goes_to_hot(fb: FacebookInit) -> Result<()> {
+    let ctx = CoreContext::test_mock(fb);
+    borrowed!(ctx);
+    let (hot, cold, tiered) = make_tiered::<Memblob>(None, true);
+
+    tiered
+        .put(ctx, "key".to_owned(), BlobstoreBytes::from_bytes("value"))
+        .await?;
+    assert!(present(ctx, &hot, "key").await?);
+    assert!(!present(ctx, &cold, "key").await?);
+    Ok(())
+}
+
+#[fbinit::test]
+async fn test_promote_on_read(fb: FacebookInit) -> Result<()> {
+    let ctx = CoreContext::test_mock(fb);
+    borrowed!(ctx);
+
+    let (hot, cold, tiered) = make_tiered::<Memblob>(None, false);
+    cold.put(ctx, "key".to_owned(), BlobstoreBytes::from_bytes("value"))
+        .await?;
+    assert!(tiered.get(ctx, "key").await?.is_some());
+    assert!(present(ctx, &tiered, "key").await?);
+    assert!(!present(ctx, &hot, "key").await?);
+
+    let (hot, cold, tiered) = make_tiered::<Memblob>(None, true);
+    cold.put(ctx, "key".to_owned(), BlobstoreBytes::from_bytes("value"))
+        .await?;
+    assert!(tiered.get(ctx, "key").await?.is_some());
+    assert!(present(ctx, &hot, "key").await?);
+    assert!(present(ctx, &cold, "key").await?);
+    Ok(())
+}
+
+#[fbinit::test]
+async fn test_demote(fb: FacebookInit) -> Result<()> {
+    let ctx = CoreContext::test_mock(fb);
+    borrowed!(ctx);
+    let (hot, cold, tiered) = make_tiered::<CtimeBlob>(Some(Duration::from_secs(0)), true);
+
+    tiered
+        .put(ctx, "key".to_owned(), BlobstoreBytes::from_bytes("value"))
+        .await?;
+    let stats = tiered.demote_pass(ctx).await?;
+    assert_eq!(stats.demoted, 1);
+    assert!(!present(ctx, &hot, "key").await?);
+    assert!(present(ctx, &cold, "key").await?);
+
+    let value = tiered.get(ctx, "key").await?.unwrap();
+    assert_eq!(value.into_bytes(), BlobstoreBytes::from_bytes("value"));
+    Ok(())
+}
+
+#[fbinit::test]
+async fn test_no_ctime_stays_hot(fb: FacebookInit) -> Result<()> {
+    let ctx = CoreContext::test_mock(fb);
+    borrowed!(ctx);
+    let (hot, cold, tiered) = make_tiered::<Memblob>(Some(Duration::from_secs(0)), true);
+
+    // Memblob has no ctime, so there is no telling whether the blob is idle.
+    tiered
+        .put(ctx, "key".to_owned(), BlobstoreBytes::from_bytes("value"))
+        .await?;
+    let stats = tiered.demote_pass(ctx).await?;
+    assert_eq!(stats.kept, 1);
+    assert!(present(ctx, &hot, "key").await?);
+    assert!(!present(ctx, &cold, "key").await?);
+    Ok(())
+}
+
+#[fbinit::test]
+async fn test_recently_read_stays_hot(fb: FacebookInit) -> Result<()> {
+    let ctx = CoreContext::test_mock(fb);
+    borrowed!(ctx);
+    let (hot, cold, tiered) = make_tiered::<CtimeBlob>(Some(Duration::from_secs(3600)), true);
+
+    hot.set_clock(now() - 7200);
+    tiered
+        .put(ctx, "read".to_owned(), BlobstoreBytes::from_bytes("value"))
+        .await?;
+    tiered
+        .put(
+            ctx,
+            "unread".to_owned(),
+            BlobstoreBytes::from_bytes("value"),
+        )
+        .await?;
+    hot.set_clock(now());
+    tiered.get(ctx, "read").await?;
+
+    // The read is recorded in the hot tier, so a mover in another process
+    // sees it.
+    let mover = TieredBlobstore::new(
+        hot.clone(),
+        cold.clone(),
+        Arc::new(InProcessLease::new()),
+        TieredOptions {
+            demote_after: Some(Duration::from_secs(3600)),
+            ..Default::default()
+        },
+    );
+    let stats = mover.demote_pass(ctx).await?;
+    assert_eq!(stats.demoted, 1);
+    assert_eq!(stats.kept, 1);
+    assert!(present(ctx, &hot, "read").await?);
+    assert!(!present(ctx, &cold, "read").await?);
+    assert!(!present(ctx, &hot, "unread").await?);
+    assert!(present(ctx, &cold, "unread").await?);
+    Ok(())
+}
+
+#[fbinit::test]
+async fn test_no_demotion_without_policy(fb: FacebookInit) -> Result<()> {
+    let ctx = CoreContext::test_mock(fb);
+    borrowed!(ctx);
+    let (hot, _cold, tiered) = make_tiered::<Memblob>(None, true);
+
+    tiered
+        .put(ctx, "key".to_owned(), BlobstoreBytes::from_bytes("value"))
+        .await?;
+    assert!(!tiered.demote(ctx, "key", now()).await?);
+    assert!(present(ctx, &hot, "key").await?);
+    Ok(())
+}
+
+#[fbinit::test]
+async fn test_unlink(fb: FacebookInit) -> Result<()> {
+    let ctx = CoreContext::test_mock(fb);
+    borrowed!(ctx);
+    let (hot, cold, tiered) = make_tiered::<Memblob>(None, true);
+
+    cold.put(ctx, "key".to_owned(), BlobstoreBytes::from_bytes("value"))
+        .await?;
+    tiered.get(ctx, "key").await?;
+    tiered.unlink(ctx, "key").await?;
+    assert!(!present(ctx, &hot, "key").await?);
+    assert!(!present(ctx, &cold, "key").await?);
+    assert!(tiered.unlink(ctx, "key").await.is_err());
+    Ok(())
+}
+
+#[fbinit::test]
+async fn test_put_if_absent_checks_cold(fb: FacebookInit) -> Result<()> {
+    let ctx = CoreContext::test_mock(fb);
+    borrowed!(ctx);
+    let (hot, cold, tiered) = make_tiered::<Memblob>(None, true);
+
+    cold.put(ctx, "key".to_owned(), BlobstoreBytes::from_bytes("value"))
+        .await?;
+    let status = tiered
+        .put_explicit(
+            ctx,
+            "key".to_owned(),
+            BlobstoreBytes::from_bytes("other"),
+            PutBehaviour::IfAbsent,
+        )
+        .await?;
+    assert_eq!(status, OverwriteStatus::Prevented);
+    assert!(!present(ctx, &hot, "key").await?);
+
+    // Puts without an explicit behaviour use the configured one.
+    let status = tiered
+        .put_with_status(ctx, "key".to_owned(), BlobstoreBytes::from_bytes("other"))
+        .await?;
+    assert_eq!(status, OverwriteStatus::Prevented);
+    tiered
+        .put(ctx, "key".to_owned(), BlobstoreBytes::from_bytes("other"))
+        .await?;
+    assert!(!present(ctx, &hot, "key").await?);
+    Ok(())
+}
+
+#[fbinit::test]
+async fn test_put_waits_for_demotion(fb: FacebookInit) -> Result<()> {
+    let ctx = CoreContext::test_mock(fb);
+    borrowed!(ctx);
+    let (hot, _cold, tiered) = make_tiered::<Memblob>(Some(Duration::from_secs(0)), true);
+
+    let guard = tiered.lock_key(ctx, "key").await?;
+    let put = tiered.put(ctx, "key".to_owned(), BlobstoreBytes::from_bytes("value"));
+    futures::pin_mut!(put);
+    assert!(tokio::time::timeout(Duration::from_millis(100), &mut put)
+        .await
+        .is_err());
+    assert!(!present(ctx, &hot, "key").await?);
+
+    drop(guard);
+    put.await?;
+    assert!(present(ctx, &hot, "key").await?);
+    Ok(())
+}
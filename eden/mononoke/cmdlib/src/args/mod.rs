@@ -670,7 +670,9 @@ where
 
     info!(logger, "using repo \"{}\" repoid {:?}", reponame, repo_id);
     match &config.storage_config.blobstore {
-        BlobConfig::Files { path } | BlobConfig::Sqlite { path } => {
+        BlobConfig::Files { path }
+        | BlobConfig::Sqlite { path }
+        | BlobConfig::Segments { path, .. } => {
             let create = if create {
                 // Many path repos can share one blobstore, so allow store to exist or create it.
                 CreateStorage::ExistingOrCreate
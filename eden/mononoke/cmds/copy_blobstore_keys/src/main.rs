@@ -246,7 +246,9 @@ async fn open_repo<'a>(
     }
     info!(logger, "using repo \"{}\" repoid {:?}", reponame, repo_id);
     match &config.storage_config.blobstore {
-        BlobConfig::Files { path } | BlobConfig::Sqlite { path } => {
+        BlobConfig::Files { path }
+        | BlobConfig::Sqlite { path }
+        | BlobConfig::Segments { path, .. } => {
             setup_repo_dir(path, CreateStorage::ExistingOnly)?;
         }
         _ => {}
@@ -22,6 +22,7 @@ use futures::stream;
 use futures::stream::TryStreamExt;
 use metaconfig_types::BlobConfig;
 use metaconfig_types::BlobstoreId;
+use packblob::DEFAULT_DICTIONARY_SIZE;
 
 mod pack_utils;
 
@@ -29,6 +30,8 @@ const ARG_ZSTD_LEVEL: &str = "zstd-level";
 const ARG_INNER_ID: &str = "inner-blobstore-id";
 const ARG_DRY_RUN: &str = "dry-run";
 const ARG_SCHEDULED_MAX: &str = "scheduled-max";
+const ARG_TRAIN_DICTIONARIES: &str = "train-dictionaries";
+const ARG_DICTIONARY_SIZE: &str = "dictionary-size";
 
 const PACK_PREFIX: &str = "multiblob-";
 
@@ -67,6 +70,21 @@ fn setup_app<'a, 'b>() -> MononokeClapApp<'a, 'b> {
                 .required(false)
                 .help("Maximum number of parallel packs to work on. Default 10"),
         )
+        .arg(
+            Arg::with_name(ARG_TRAIN_DICTIONARIES)
+                .long(ARG_TRAIN_DICTIONARIES)
+                .takes_value(false)
+                .required(false)
+                .help("Instead of packing, use the blobs on stdin as samples to train a zstd dictionary per blob type, for use by the ZstdDictionary put format"),
+        )
+        .arg(
+            Arg::with_name(ARG_DICTIONARY_SIZE)
+                .long(ARG_DICTIONARY_SIZE)
+                .takes_value(true)
+                .required(false)
+                .requires(ARG_TRAIN_DICTIONARIES)
+                .help("Maximum size in bytes of each trained dictionary. Default 112640"),
+        )
 }
 
 fn get_blobconfig(
@@ -137,6 +155,11 @@ fn main(fb: FacebookInit) -> Result<()> {
         .value_of(ARG_SCHEDULED_MAX)
         .map_or(Ok(10), str::parse::<usize>)?;
 
+    let train_dictionaries = matches.is_present(ARG_TRAIN_DICTIONARIES);
+    let dictionary_size = matches
+        .value_of(ARG_DICTIONARY_SIZE)
+        .map_or(Ok(DEFAULT_DICTIONARY_SIZE), str::parse::<usize>)?;
+
     let input_lines: Vec<String> = io::stdin()
         .lock()
         .lines()
@@ -155,6 +178,23 @@ fn main(fb: FacebookInit) -> Result<()> {
             config_store,
         )
         .await?;
+        if train_dictionaries {
+            let keys: Vec<&str> = input_lines
+                .iter()
+                .filter(|key| !key.is_empty())
+                .map(|key| key.as_ref())
+                .collect();
+            return pack_utils::train_dictionaries(
+                &ctx,
+                &blobstore,
+                &repo_prefix,
+                &keys,
+                dictionary_size,
+                dry_run,
+                &scuba,
+            )
+            .await;
+        }
         stream::iter(input_lines.split(String::is_empty).map(Result::Ok))
             .try_for_each_concurrent(max_parallelism, |pack_keys| {
                 borrowed!(ctx, repo_prefix, blobstore, scuba);
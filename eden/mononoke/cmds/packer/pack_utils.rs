@@ -8,6 +8,7 @@
 use std::collections::HashMap;
 
 use anyhow::anyhow;
+use anyhow::bail;
 use anyhow::Error;
 use anyhow::Result;
 use blobstore::Blobstore;
@@ -17,21 +18,30 @@ use context::CoreContext;
 use futures::future::FutureExt;
 use futures::stream::FuturesUnordered;
 use futures::stream::TryStreamExt;
+use packblob::blob_type_prefix;
 use packblob::get_entry_compressed_size;
+use packblob::train_dictionary;
 use packblob::EmptyPack;
 use packblob::Pack;
 use packblob::PackBlob;
 use packblob::SingleCompressed;
+use packblob::MAX_DICTIONARY_BLOB_SIZE;
 use scuba_ext::MononokeScubaSampleBuilder;
+use slog::warn;
 use tokio::task::spawn_blocking;
 
 type BlobsWithKeys = Vec<(String, BlobstoreBytes)>;
 
 const BLOBSTORE_KEY: &str = "blobstore_key";
 const COMPRESSED_SIZE: &str = "compressed_size";
+const DICTIONARY_KEY: &str = "dictionary_key";
 const PACK_KEY: &str = "pack_key";
+const SAMPLE_COUNT: &str = "sample_count";
 const UNCOMPRESSED_SIZE: &str = "uncompressed_size";
 
+// zstd needs a reasonable number of samples to train a useful dictionary
+const MIN_DICTIONARY_SAMPLES: usize = 100;
+
 // Tries to pack with the first blob from `blobs` as the dictionary for the other blobs
 fn try_pack(zstd_level: i32, blobs: Vec<(String, BlobstoreBytes)>) -> Result<Pack> {
     let empty_pack = EmptyPack::new(zstd_level);
@@ -191,3 +201,59 @@ pub async fn repack_keys<T: BlobstoreUnlinkOps>(
     }
     Ok(())
 }
+
+/// Given a list of sample keys, train and store a dictionary for each blob
+/// type with enough small samples
+pub async fn train_dictionaries<T: BlobstoreUnlinkOps>(
+    ctx: &CoreContext,
+    blobstore: &PackBlob<T>,
+    repo_prefix: &str,
+    keys: &[&str],
+    dictionary_size: usize,
+    dry_run: bool,
+    scuba: &MononokeScubaSampleBuilder,
+) -> Result<()> {
+    let mut keys_by_type: HashMap<&str, Vec<&str>> = HashMap::new();
+    for key in keys {
+        match blob_type_prefix(key) {
+            Some((key_repo_prefix, type_prefix)) if key_repo_prefix == repo_prefix => {
+                keys_by_type.entry(type_prefix).or_default().push(*key);
+            }
+            _ => bail!("Cannot find the blob type of {} in {}", key, repo_prefix),
+        }
+    }
+
+    for (type_prefix, keys) in keys_by_type {
+        let samples: Vec<_> = fetch_blobs(ctx, blobstore, repo_prefix, &keys)
+            .await?
+            .into_iter()
+            .map(|(_key, blob)| blob.into_bytes())
+            .filter(|blob| blob.len() <= MAX_DICTIONARY_BLOB_SIZE)
+            .collect();
+        if samples.len() < MIN_DICTIONARY_SAMPLES {
+            warn!(
+                ctx.logger(),
+                "Only {} small samples of {}, need {} to train a dictionary",
+                samples.len(),
+                type_prefix,
+                MIN_DICTIONARY_SAMPLES,
+            );
+            continue;
+        }
+
+        let sample_count = samples.len();
+        let dictionary =
+            spawn_blocking(move || train_dictionary(&samples, dictionary_size)).await??;
+        let mut scuba = scuba.clone();
+        scuba.add(SAMPLE_COUNT, sample_count);
+        scuba.add(COMPRESSED_SIZE, dictionary.len());
+        if !dry_run {
+            let dict_key = blobstore
+                .put_dictionary(ctx, repo_prefix, type_prefix, dictionary)
+                .await?;
+            scuba.add(DICTIONARY_KEY, dict_key);
+            scuba.log();
+        }
+    }
+    Ok(())
+}
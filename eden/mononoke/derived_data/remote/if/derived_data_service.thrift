@@ -53,6 +53,7 @@ union DerivedData {
   10: DerivedDataTreeHandle tree_handle;
   11: DerivedDataDeletedManifestV2 deleted_manifest_v2;
   12: DerivedDataBasenameSuffixSkeletonManifest basename_suffix_skeleton_manifest;
+  13: DerivedDataTrigramIndex trigram_index;
 }
 
 union DerivedDataFsnode {
@@ -110,6 +111,10 @@ union DerivedDataBasenameSuffixSkeletonManifest {
   1: mononoke_types_thrift.BssmDirectory root_basename_suffix_skeleton_manifest;
 }
 
+union DerivedDataTrigramIndex {
+  1: mononoke_types_thrift.TrigramIndexId root_trigram_index_id;
+}
+
 union DerivedDataSkeletonManifest {
   1: mononoke_types_thrift.SkeletonManifestId root_skeleton_manifest_id;
 }
@@ -0,0 +1,225 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+//! The trigram index of a commit is derived from the index of its first
+//! parent by applying the commit's file changes: the trigrams of the old
+//! contents of each changed path are removed from their posting lists and
+//! the trigrams of the new contents are added. Deriving a commit therefore
+//! only reads the contents of the files it changes.
+//!
+//! Merges can take files from any parent, so their changes come from
+//! diffing the fsnodes of the first parent against the merge's fsnodes.
+
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use anyhow::Result;
+use blobstore::Blobstore;
+use blobstore::Loadable;
+use blobstore::Storable;
+use context::CoreContext;
+use derived_data_manager::DerivationContext;
+use filestore::FetchKey;
+use fsnodes::RootFsnodeId;
+use futures::future;
+use futures::stream;
+use futures::StreamExt;
+use futures::TryStreamExt;
+use manifest::Diff;
+use manifest::Entry;
+use manifest::ManifestOps;
+use mononoke_types::trigram_index::content_trigrams;
+use mononoke_types::trigram_index::Trigram;
+use mononoke_types::trigram_index::TrigramIndex;
+use mononoke_types::trigram_index::TrigramIndexChanges;
+use mononoke_types::trigram_index::TrigramIndexFile;
+use mononoke_types::trigram_index::MAX_INDEXED_FILE_SIZE;
+use mononoke_types::BlobstoreValue;
+use mononoke_types::BonsaiChangeset;
+use mononoke_types::ContentId;
+use mononoke_types::MPath;
+use skeleton_manifest::mapping::get_file_changes;
+
+use crate::mapping::RootTrigramIndexId;
+
+const CONCURRENCY: usize = 100;
+
+/// The trigrams of a file's contents, or None if the file is not indexed.
+async fn indexed_trigrams(
+    ctx: &CoreContext,
+    blobstore: &Arc<dyn Blobstore>,
+    content_id: ContentId,
+) -> Result<Option<BTreeSet<Trigram>>> {
+    let metadata = filestore::get_metadata(blobstore, ctx, &FetchKey::Canonical(content_id))
+        .await?
+        .ok_or_else(|| anyhow!("Missing metadata for content {}", content_id))?;
+    if metadata.total_size > MAX_INDEXED_FILE_SIZE {
+        return Ok(None);
+    }
+    let content = filestore::fetch_concat(blobstore, ctx, content_id).await?;
+    Ok(content_trigrams(&content))
+}
+
+/// Work out the new contents of every path whose indexed contents may
+/// differ from the first parent's, with None for paths that no longer exist.
+async fn path_changes(
+    ctx: &CoreContext,
+    derivation_ctx: &DerivationContext,
+    parent: &TrigramIndex,
+    bonsai: &BonsaiChangeset,
+) -> Result<BTreeMap<MPath, Option<ContentId>>> {
+    if bonsai.is_merge() {
+        return merge_path_changes(ctx, derivation_ctx, bonsai).await;
+    }
+
+    let blobstore = derivation_ctx.blobstore();
+    let mut changes: BTreeMap<MPath, Option<ContentId>> = get_file_changes(bonsai)
+        .into_iter()
+        .map(|(path, content)| (path, content.map(|(content_id, _)| content_id)))
+        .collect();
+
+    // Adding a file implicitly deletes any file at one of its parent
+    // directories, and any files in a directory at its path.
+    let added = changes
+        .iter()
+        .filter(|(_, content)| content.is_some())
+        .map(|(path, _)| path.clone())
+        .collect::<Vec<_>>();
+    let implicit_deletes = stream::iter(added)
+        .map(|path| async move {
+            let mut deleted = Vec::new();
+            for dir in path.clone().into_parent_dir_iter().skip(1) {
+                if parent.lookup_file(ctx, blobstore, &dir).await?.is_some() {
+                    deleted.push(dir);
+                }
+            }
+            let files_in_dir = parent
+                .clone()
+                .into_files_in_dir(ctx, blobstore, &path)
+                .map_ok(|(path, _)| path)
+                .try_collect::<Vec<_>>()
+                .await?;
+            deleted.extend(files_in_dir);
+            anyhow::Ok(deleted)
+        })
+        .buffer_unordered(CONCURRENCY)
+        .try_collect::<Vec<_>>()
+        .await?;
+    for path in implicit_deletes.into_iter().flatten() {
+        changes.entry(path).or_insert(None);
+    }
+
+    Ok(changes)
+}
+
+/// The paths whose contents differ between the first parent and the merged
+/// manifest, which also covers files that are in the first parent but were
+/// not indexed there.
+async fn merge_path_changes(
+    ctx: &CoreContext,
+    derivation_ctx: &DerivationContext,
+    bonsai: &BonsaiChangeset,
+) -> Result<BTreeMap<MPath, Option<ContentId>>> {
+    let p1 = bonsai
+        .parents()
+        .next()
+        .ok_or_else(|| anyhow!("Merge {} has no parents", bonsai.get_changeset_id()))?;
+    let (parent, merged) = future::try_join(
+        derivation_ctx.fetch_dependency::<RootFsnodeId>(ctx, p1),
+        derivation_ctx.fetch_dependency::<RootFsnodeId>(ctx, bonsai.get_changeset_id()),
+    )
+    .await?;
+    parent
+        .into_fsnode_id()
+        .diff(
+            ctx.clone(),
+            derivation_ctx.blobstore().clone(),
+            merged.into_fsnode_id(),
+        )
+        .try_filter_map(|diff| async move {
+            Ok(match diff {
+                Diff::Added(Some(path), Entry::Leaf(file))
+                | Diff::Changed(Some(path), _, Entry::Leaf(file)) => {
+                    Some((path, Some(*file.content_id())))
+                }
+                Diff::Removed(Some(path), Entry::Leaf(_)) => Some((path, None)),
+                _ => None,
+            })
+        })
+        .try_collect()
+        .await
+}
+
+pub(crate) async fn derive_single(
+    ctx: &CoreContext,
+    derivation_ctx: &DerivationContext,
+    bonsai: BonsaiChangeset,
+    parents: Vec<RootTrigramIndexId>,
+) -> Result<RootTrigramIndexId> {
+    let blobstore = derivation_ctx.blobstore();
+    let parent = match parents.into_iter().next() {
+        Some(root) => root.into_trigram_index_id().load(ctx, blobstore).await?,
+        None => TrigramIndex::empty(),
+    };
+
+    let changes = path_changes(ctx, derivation_ctx, &parent, &bonsai).await?;
+    let parent = &parent;
+    let updates = stream::iter(changes)
+        .map(|(path, new_content)| async move {
+            let old_file = parent.lookup_file(ctx, blobstore, &path).await?;
+            if old_file.map(|file| file.content_id) == new_content {
+                return Ok(None);
+            }
+            let old_trigrams = match old_file {
+                Some(file) => indexed_trigrams(ctx, blobstore, file.content_id)
+                    .await?
+                    .unwrap_or_default(),
+                None => BTreeSet::new(),
+            };
+            let new = match new_content {
+                Some(content_id) => indexed_trigrams(ctx, blobstore, content_id)
+                    .await?
+                    .map(|trigrams| (TrigramIndexFile { content_id }, trigrams)),
+                None => None,
+            };
+            anyhow::Ok(Some((path, old_trigrams, new)))
+        })
+        .buffer_unordered(CONCURRENCY)
+        .try_filter_map(|update| async move { Ok(update) })
+        .try_collect::<Vec<_>>()
+        .await?;
+
+    let mut index_changes = TrigramIndexChanges::default();
+    for (path, old_trigrams, new) in updates {
+        let (new_file, new_trigrams) = match new {
+            Some((file, trigrams)) => (Some(file), trigrams),
+            None => (None, BTreeSet::new()),
+        };
+        for trigram in old_trigrams.difference(&new_trigrams) {
+            index_changes
+                .trigrams
+                .entry(*trigram)
+                .or_default()
+                .insert(path.clone(), None);
+        }
+        for trigram in new_trigrams {
+            index_changes
+                .trigrams
+                .entry(trigram)
+                .or_default()
+                .insert(path.clone(), new_file);
+        }
+        index_changes.files.insert(path, new_file);
+    }
+
+    let index = parent.clone().update(ctx, blobstore, index_changes).await?;
+    Ok(RootTrigramIndexId(
+        index.into_blob().store(ctx, blobstore).await?,
+    ))
+}
@@ -0,0 +1,132 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+use anyhow::anyhow;
+use anyhow::Error;
+use anyhow::Result;
+use async_trait::async_trait;
+use blobstore::BlobstoreGetData;
+use bytes::Bytes;
+use context::CoreContext;
+use derived_data::impl_bonsai_derived_via_manager;
+use derived_data_manager::dependencies;
+use derived_data_manager::BonsaiDerivable;
+use derived_data_manager::DerivationContext;
+use derived_data_service_if::types as thrift;
+use fsnodes::RootFsnodeId;
+use mononoke_types::BlobstoreBytes;
+use mononoke_types::BonsaiChangeset;
+use mononoke_types::ChangesetId;
+use mononoke_types::TrigramIndexId;
+
+use crate::derive::derive_single;
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct RootTrigramIndexId(pub(crate) TrigramIndexId);
+
+impl RootTrigramIndexId {
+    pub fn trigram_index_id(&self) -> &TrigramIndexId {
+        &self.0
+    }
+    pub fn into_trigram_index_id(self) -> TrigramIndexId {
+        self.0
+    }
+}
+
+impl TryFrom<BlobstoreBytes> for RootTrigramIndexId {
+    type Error = Error;
+
+    fn try_from(blob_bytes: BlobstoreBytes) -> Result<Self> {
+        TrigramIndexId::from_bytes(&blob_bytes.into_bytes()).map(RootTrigramIndexId)
+    }
+}
+
+impl TryFrom<BlobstoreGetData> for RootTrigramIndexId {
+    type Error = Error;
+
+    fn try_from(blob_get_data: BlobstoreGetData) -> Result<Self> {
+        blob_get_data.into_bytes().try_into()
+    }
+}
+
+impl From<RootTrigramIndexId> for BlobstoreBytes {
+    fn from(root_trigram_index_id: RootTrigramIndexId) -> Self {
+        BlobstoreBytes::from_bytes(Bytes::copy_from_slice(
+            root_trigram_index_id.0.blake2().as_ref(),
+        ))
+    }
+}
+
+fn format_key(derivation_ctx: &DerivationContext, changeset_id: ChangesetId) -> String {
+    let root_prefix = "derived_root_trigramindex.";
+    let key_prefix = derivation_ctx.mapping_key_prefix::<RootTrigramIndexId>();
+    format!("{}{}{}", root_prefix, key_prefix, changeset_id)
+}
+
+#[async_trait]
+impl BonsaiDerivable for RootTrigramIndexId {
+    const NAME: &'static str = "trigram_index";
+
+    type Dependencies = dependencies![RootFsnodeId];
+
+    async fn derive_single(
+        ctx: &CoreContext,
+        derivation_ctx: &DerivationContext,
+        bonsai: BonsaiChangeset,
+        parents: Vec<Self>,
+    ) -> Result<Self> {
+        derive_single(ctx, derivation_ctx, bonsai, parents).await
+    }
+
+    async fn store_mapping(
+        self,
+        ctx: &CoreContext,
+        derivation_ctx: &DerivationContext,
+        changeset_id: ChangesetId,
+    ) -> Result<()> {
+        let key = format_key(derivation_ctx, changeset_id);
+        derivation_ctx.blobstore().put(ctx, key, self.into()).await
+    }
+
+    async fn fetch(
+        ctx: &CoreContext,
+        derivation_ctx: &DerivationContext,
+        changeset_id: ChangesetId,
+    ) -> Result<Option<Self>> {
+        let key = format_key(derivation_ctx, changeset_id);
+        derivation_ctx
+            .blobstore()
+            .get(ctx, &key)
+            .await?
+            .map(TryInto::try_into)
+            .transpose()
+    }
+
+    fn from_thrift(data: thrift::DerivedData) -> Result<Self> {
+        if let thrift::DerivedData::trigram_index(
+            thrift::DerivedDataTrigramIndex::root_trigram_index_id(id),
+        ) = data
+        {
+            TrigramIndexId::from_thrift(id).map(Self)
+        } else {
+            Err(anyhow!(
+                "Can't convert {} from provided thrift::DerivedData",
+                Self::NAME.to_string(),
+            ))
+        }
+    }
+
+    fn into_thrift(data: Self) -> Result<thrift::DerivedData> {
+        Ok(thrift::DerivedData::trigram_index(
+            thrift::DerivedDataTrigramIndex::root_trigram_index_id(
+                data.trigram_index_id().into_thrift(),
+            ),
+        ))
+    }
+}
+
+impl_bonsai_derived_via_manager!(RootTrigramIndexId);
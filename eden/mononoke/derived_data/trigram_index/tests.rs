@@ -0,0 +1,126 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use blobstore::Loadable;
+use changesets::ChangesetsRef;
+use context::CoreContext;
+use fbinit::FacebookInit;
+use fixtures::TestRepoFixture;
+use fsnodes::RootFsnodeId;
+use futures::stream;
+use futures::TryStreamExt;
+use manifest::ManifestOps;
+use mononoke_types::trigram_index::content_trigrams;
+use mononoke_types::trigram_index::TrigramIndexFile;
+use mononoke_types::ChangesetId;
+use mononoke_types::ChangesetIdPrefix;
+use mononoke_types::ChangesetIdsResolvedFromPrefix;
+use repo_blobstore::RepoBlobstore;
+use repo_derived_data::RepoDerivedData;
+use repo_derived_data::RepoDerivedDataRef;
+
+use crate::RootTrigramIndexId;
+
+/// Check that the index of a commit contains exactly its indexable files,
+/// and that every trigram of every file points back at it.
+async fn validate(
+    ctx: &CoreContext,
+    blobstore: &RepoBlobstore,
+    derived_data: &RepoDerivedData,
+    cs_id: ChangesetId,
+) -> Result<()> {
+    let root: RootTrigramIndexId = derived_data.derive(ctx, cs_id).await?;
+    let index = root.into_trigram_index_id().load(ctx, blobstore).await?;
+    let fsnode: RootFsnodeId = derived_data.derive(ctx, cs_id).await?;
+
+    let mut expected = BTreeMap::new();
+    let leaves = fsnode
+        .into_fsnode_id()
+        .list_leaf_entries(ctx.clone(), blobstore.clone())
+        .try_collect::<Vec<_>>()
+        .await?;
+    for (path, file) in leaves {
+        let content = filestore::fetch_concat(blobstore, ctx, *file.content_id()).await?;
+        if let Some(trigrams) = content_trigrams(&content) {
+            expected.insert(path, (*file.content_id(), trigrams));
+        }
+    }
+
+    let indexed = index
+        .clone()
+        .into_files(ctx, blobstore)
+        .map_ok(|(path, file)| (path, file.content_id))
+        .try_collect::<BTreeMap<_, _>>()
+        .await?;
+    assert_eq!(
+        indexed,
+        expected
+            .iter()
+            .map(|(path, (content_id, _))| (path.clone(), *content_id))
+            .collect::<BTreeMap<_, _>>()
+    );
+
+    for (path, (content_id, trigrams)) in expected {
+        for trigram in trigrams {
+            let postings = index
+                .lookup_trigram(ctx, blobstore, &trigram)
+                .await?
+                .expect("trigram should be indexed");
+            let files = postings
+                .into_files(ctx, blobstore)
+                .try_collect::<BTreeMap<_, _>>()
+                .await?;
+            assert_eq!(files.len() as u64, postings.file_count);
+            assert_eq!(files.get(&path), Some(&TrigramIndexFile { content_id }));
+        }
+    }
+    Ok(())
+}
+
+async fn test_for_fixture<F: TestRepoFixture + Send>(fb: FacebookInit) -> Result<()> {
+    let ctx = CoreContext::test_mock(fb);
+    let ctx = &ctx;
+    let repo = F::getrepo(fb).await;
+    let derived_data = repo.repo_derived_data();
+    let blobstore = repo.blobstore();
+    let all_commits = repo
+        .changesets()
+        .get_many_by_prefix(
+            ctx.clone(),
+            ChangesetIdPrefix::from_bytes("").unwrap(),
+            1000,
+        )
+        .await?;
+    let all_commits = match all_commits {
+        ChangesetIdsResolvedFromPrefix::Multiple(all_commits) => all_commits,
+        other => anyhow::bail!("Weird number of commits: {:?}", other),
+    };
+    stream::iter(all_commits.into_iter().map(anyhow::Ok))
+        .try_for_each_concurrent(None, |cs_id| validate(ctx, blobstore, derived_data, cs_id))
+        .await?;
+    Ok(())
+}
+
+#[fbinit::test]
+async fn basic_test(fb: FacebookInit) {
+    futures::try_join!(
+        test_for_fixture::<fixtures::Linear>(fb),
+        test_for_fixture::<fixtures::BranchEven>(fb),
+        test_for_fixture::<fixtures::BranchUneven>(fb),
+        test_for_fixture::<fixtures::BranchWide>(fb),
+        test_for_fixture::<fixtures::MergeEven>(fb),
+        test_for_fixture::<fixtures::ManyFilesDirs>(fb),
+        test_for_fixture::<fixtures::MergeUneven>(fb),
+        test_for_fixture::<fixtures::UnsharedMergeEven>(fb),
+        test_for_fixture::<fixtures::UnsharedMergeUneven>(fb),
+        test_for_fixture::<fixtures::ManyDiamonds>(fb),
+    )
+    .unwrap();
+}
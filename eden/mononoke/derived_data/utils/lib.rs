@@ -65,6 +65,7 @@ use scuba_ext::MononokeScubaSampleBuilder;
 use skeleton_manifest::RootSkeletonManifestId;
 use topo_sort::sort_topological;
 use trait_alias::trait_alias;
+use trigram_index::RootTrigramIndexId;
 use unodes::RootUnodeManifestId;
 
 pub const POSSIBLE_DERIVED_TYPES: &[&str] = &[
@@ -79,6 +80,7 @@ pub const POSSIBLE_DERIVED_TYPES: &[&str] = &[
     TreeHandle::NAME,
     RootDeletedManifestV2Id::NAME,
     RootBasenameSuffixSkeletonManifest::NAME,
+    RootTrigramIndexId::NAME,
 ];
 
 pub const DEFAULT_BACKFILLING_CONFIG_NAME: &str = "backfilling";
@@ -96,6 +98,7 @@ lazy_static! {
         let filenodes = FilenodesOnlyPublic::NAME;
         let skeleton_mf = RootSkeletonManifestId::NAME;
         let bssm = RootBasenameSuffixSkeletonManifest::NAME;
+        let trigram_index = RootTrigramIndexId::NAME;
 
         let mut dag = HashMap::new();
 
@@ -109,6 +112,7 @@ lazy_static! {
         dag.insert(deleted_mf_v2, vec![unodes]);
         dag.insert(skeleton_mf, vec![]);
         dag.insert(bssm, vec![]);
+        dag.insert(trigram_index, vec![]);
 
         dag
     };
@@ -505,6 +509,9 @@ fn derived_data_utils_impl(
                 repo, config, enabled_config_name
             )))
         }
+        RootTrigramIndexId::NAME => Ok(Arc::new(
+            DerivedUtilsFromManager::<RootTrigramIndexId>::new(repo, config, enabled_config_name),
+        )),
         name => Err(format_err!("Unsupported derived data type: {}", name)),
     }
 }
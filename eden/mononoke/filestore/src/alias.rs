@@ -16,12 +16,18 @@ use mononoke_types::hash;
 
 use crate::expected_size::ExpectedSize;
 use crate::incremental_hash::GitSha1IncrementalHasher;
+use crate::incremental_hash::GitSha256IncrementalHasher;
 use crate::incremental_hash::Sha1IncrementalHasher;
 use crate::incremental_hash::Sha256IncrementalHasher;
 use crate::multiplexer::Multiplexer;
 use crate::streamhash::hash_stream;
 
-type Aliases = (hash::Sha1, hash::Sha256, hash::RichGitSha1);
+type Aliases = (
+    hash::Sha1,
+    hash::Sha256,
+    hash::RichGitSha1,
+    hash::RichGitSha256,
+);
 
 /// Hashes are dependent on the ExpectedSize we received, so we want to make sure callers verify
 /// that the size they gave us is actually the one they observed before giving them access to the
@@ -59,8 +65,10 @@ pub fn add_aliases_to_multiplexer<T: AsRef<[u8]> + Send + Sync + Clone + 'static
     let sha256 = multiplexer.add(|stream| hash_stream(Sha256IncrementalHasher::new(), stream));
     let git_sha1 = multiplexer
         .add(move |stream| hash_stream(GitSha1IncrementalHasher::new(expected_size), stream));
+    let git_sha256 = multiplexer
+        .add(move |stream| hash_stream(GitSha256IncrementalHasher::new(expected_size), stream));
 
-    future::try_join3(sha1, sha256, git_sha1)
+    future::try_join4(sha1, sha256, git_sha1, git_sha256)
         .map_ok(move |aliases| RedeemableAliases::new(expected_size, aliases))
         .map_err(Error::from)
 }
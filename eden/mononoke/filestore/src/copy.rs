@@ -39,7 +39,19 @@ pub async fn copy(
     let copy_sha256 = copier.copy(ctx, Alias::Sha256(data.sha256).blobstore_key());
     let copy_git_sha1 = copier.copy(ctx, Alias::GitSha1(data.git_sha1.sha1()).blobstore_key());
 
-    future::try_join3(copy_sha1, copy_sha256, copy_git_sha1).await?;
+    // Metadata computed before Git SHA-256 support has no such alias to copy.
+    let copy_git_sha256 = async {
+        match data.git_sha256 {
+            Some(git_sha256) => {
+                copier
+                    .copy(ctx, Alias::GitSha256(git_sha256.sha256()).blobstore_key())
+                    .await
+            }
+            None => Ok(()),
+        }
+    };
+
+    future::try_join4(copy_sha1, copy_sha256, copy_git_sha1, copy_git_sha256).await?;
 
     // Files are stored inline or in chunks, depending on their size. If they're chunked,
     // we need to copy all chunks. Unfortunately, the only way to know how they're stored is
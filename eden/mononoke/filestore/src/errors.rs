@@ -8,6 +8,7 @@
 use std::fmt::Debug;
 
 use mononoke_types::hash::RichGitSha1;
+use mononoke_types::hash::RichGitSha256;
 use mononoke_types::hash::Sha1;
 use mononoke_types::hash::Sha256;
 use mononoke_types::ContentId;
@@ -39,6 +40,9 @@ pub enum ErrorKind {
     #[error("Invalid RichGitSha1: {0:?}")]
     InvalidGitSha1(InvalidHash<RichGitSha1>),
 
+    #[error("Invalid RichGitSha256: {0:?}")]
+    InvalidGitSha256(InvalidHash<RichGitSha256>),
+
     #[error("Missing content: {0:?}")]
     MissingContent(FetchKey),
 }
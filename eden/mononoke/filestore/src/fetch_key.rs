@@ -45,6 +45,12 @@ impl From<hash::Sha256> for FetchKey {
     }
 }
 
+impl From<hash::GitSha256> for FetchKey {
+    fn from(hash: hash::GitSha256) -> Self {
+        FetchKey::Aliased(Alias::GitSha256(hash))
+    }
+}
+
 impl From<hash::Sha1> for FetchKey {
     fn from(hash: hash::Sha1) -> Self {
         FetchKey::Aliased(Alias::Sha1(hash))
@@ -75,6 +81,7 @@ pub enum Alias {
     Sha1(hash::Sha1),
     Sha256(hash::Sha256),
     GitSha1(hash::GitSha1),
+    GitSha256(hash::GitSha256),
 }
 
 #[async_trait]
@@ -102,6 +109,7 @@ impl Alias {
     pub fn blobstore_key(&self) -> String {
         match self {
             Alias::GitSha1(git_sha1) => format!("alias.gitsha1.{}", git_sha1.to_hex()),
+            Alias::GitSha256(git_sha256) => format!("alias.gitsha256.{}", git_sha256.to_hex()),
             Alias::Sha1(sha1) => format!("alias.sha1.{}", sha1.to_hex()),
             Alias::Sha256(sha256) => format!("alias.sha256.{}", sha256.to_hex()),
         }
@@ -111,6 +119,7 @@ impl Alias {
     pub fn sampling_fingerprint(&self) -> u64 {
         match self {
             Alias::GitSha1(git_sha1) => git_sha1.sampling_fingerprint(),
+            Alias::GitSha256(git_sha256) => git_sha256.sampling_fingerprint(),
             Alias::Sha1(sha1) => sha1.sampling_fingerprint(),
             Alias::Sha256(sha256) => sha256.sampling_fingerprint(),
         }
@@ -47,6 +47,7 @@ pub async fn finalize<B: Blobstore>(
         sha1,
         sha256,
         git_sha1,
+        git_sha256,
         contents,
     } = outcome;
 
@@ -63,6 +64,7 @@ pub async fn finalize<B: Blobstore>(
             sha1: req_sha1,
             sha256: req_sha256,
             git_sha1: req_git_sha1,
+            git_sha256: req_git_sha256,
         } = req;
 
         expected_size.check_equals(total_size)?;
@@ -73,13 +75,17 @@ pub async fn finalize<B: Blobstore>(
             check_hash(*req_sha1, sha1).map_err(InvalidSha1)?;
             check_hash(*req_sha256, sha256).map_err(InvalidSha256)?;
             check_hash(*req_git_sha1, git_sha1).map_err(InvalidGitSha1)?;
+            check_hash(*req_git_sha256, git_sha256).map_err(InvalidGitSha256)?;
         }
     }
 
     let alias = ContentAlias::from_content_id(content_id);
     let put_sha1 = AliasBlob(Alias::Sha1(sha1), alias.clone()).store(ctx, blobstore);
     let put_sha256 = AliasBlob(Alias::Sha256(sha256), alias.clone()).store(ctx, blobstore);
-    let put_git_sha1 = AliasBlob(Alias::GitSha1(git_sha1.sha1()), alias).store(ctx, blobstore);
+    let put_git_sha1 =
+        AliasBlob(Alias::GitSha1(git_sha1.sha1()), alias.clone()).store(ctx, blobstore);
+    let put_git_sha256 =
+        AliasBlob(Alias::GitSha256(git_sha256.sha256()), alias).store(ctx, blobstore);
 
     // Since we don't have atomicity for multiple puts, we need to make sure they're ordered
     // correctly:
@@ -98,7 +104,7 @@ pub async fn finalize<B: Blobstore>(
     // cache, as everything in it can be computed from the content id. Therefore, in principle,
     // if it doesn't get written we can fix it up later.
 
-    future::try_join3(put_sha1, put_sha256, put_git_sha1).await?;
+    future::try_join4(put_sha1, put_sha256, put_git_sha1, put_git_sha256).await?;
 
     blob.store(ctx, blobstore).await?;
 
@@ -108,6 +114,7 @@ pub async fn finalize<B: Blobstore>(
         sha1,
         git_sha1,
         sha256,
+        git_sha256: Some(git_sha256),
     };
 
     metadata.clone().into_blob().store(ctx, blobstore).await?;
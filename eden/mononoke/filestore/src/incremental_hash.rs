@@ -115,3 +115,26 @@ impl Hasher<hash::RichGitSha1> for GitSha1IncrementalHasher {
         hash::RichGitSha1::from_byte_array(hash, "blob", self.1)
     }
 }
+
+pub struct GitSha256IncrementalHasher(Sha256, u64);
+
+impl GitSha256IncrementalHasher {
+    pub fn new<A: AdvisorySize>(size: A) -> Self {
+        let size = size.advise();
+        let mut sha256 = Sha256::new();
+        let prototype = hash::RichGitSha256::from_byte_array([0; 32], "blob", size);
+        sha256.update(&prototype.prefix());
+        Self(sha256, size)
+    }
+}
+
+impl Hasher<hash::RichGitSha256> for GitSha256IncrementalHasher {
+    fn update<T: AsRef<[u8]>>(&mut self, bytes: T) {
+        self.0.update(bytes.as_ref())
+    }
+
+    fn finish(self) -> hash::RichGitSha256 {
+        let hash = self.0.finalize().into();
+        hash::RichGitSha256::from_byte_array(hash, "blob", self.1)
+    }
+}
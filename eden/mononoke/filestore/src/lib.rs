@@ -98,6 +98,7 @@ pub struct StoreRequest {
     pub sha1: Option<hash::Sha1>,
     pub sha256: Option<hash::Sha256>,
     pub git_sha1: Option<hash::RichGitSha1>,
+    pub git_sha256: Option<hash::RichGitSha256>,
 }
 
 impl StoreRequest {
@@ -110,6 +111,7 @@ impl StoreRequest {
             sha1: None,
             sha256: None,
             git_sha1: None,
+            git_sha256: None,
         }
     }
 
@@ -122,6 +124,7 @@ impl StoreRequest {
             sha1: None,
             sha256: None,
             git_sha1: None,
+            git_sha256: None,
         }
     }
 
@@ -134,6 +137,7 @@ impl StoreRequest {
             sha1: Some(sha1),
             sha256: None,
             git_sha1: None,
+            git_sha256: None,
         }
     }
 
@@ -146,6 +150,7 @@ impl StoreRequest {
             sha1: None,
             sha256: Some(sha256),
             git_sha1: None,
+            git_sha256: None,
         }
     }
 
@@ -158,6 +163,20 @@ impl StoreRequest {
             sha1: None,
             sha256: None,
             git_sha1: Some(git_sha1),
+            git_sha256: None,
+        }
+    }
+
+    pub fn with_git_sha256(size: u64, git_sha256: hash::RichGitSha256) -> Self {
+        use expected_size::ExpectedSize;
+
+        Self {
+            expected_size: ExpectedSize::new(size),
+            canonical: None,
+            sha1: None,
+            sha256: None,
+            git_sha1: None,
+            git_sha256: Some(git_sha256),
         }
     }
 
@@ -170,6 +189,9 @@ impl StoreRequest {
                 Self::with_git_sha1(size, hash::RichGitSha1::from_sha1(id, "blob", size))
             }
             Aliased(Alias::Sha256(id)) => Self::with_sha256(size, id),
+            Aliased(Alias::GitSha256(id)) => {
+                Self::with_git_sha256(size, hash::RichGitSha256::from_sha256(id, "blob", size))
+            }
         }
     }
 }
@@ -197,6 +219,29 @@ pub async fn get_metadata<B: Blobstore>(
     }
 }
 
+/// Fetch the metadata for the underlying content, making sure it includes the Git SHA-256
+/// alias. This will return None if the content does not exist. Metadata for content stored
+/// before the Git SHA-256 alias was computed is recomputed, and the alias is backfilled.
+pub async fn get_metadata_with_git_sha256<B: Blobstore>(
+    blobstore: &B,
+    ctx: &CoreContext,
+    key: &FetchKey,
+) -> Result<Option<ContentMetadata>, Error> {
+    let maybe_id = key
+        .load(ctx, blobstore)
+        .await
+        .map(Some)
+        .or_else(|err| match err {
+            LoadableError::Error(err) => Err(err),
+            LoadableError::Missing(_) => Ok(None),
+        })?;
+
+    match maybe_id {
+        Some(id) => metadata::get_metadata_with_git_sha256(blobstore, ctx, id).await,
+        None => Ok(None),
+    }
+}
+
 /// Fetch the metadata for the underlying content. This will return None if the content does
 /// not exist, Some(None) if the metadata does not exist, and Some(Some(ContentMetadata))
 /// when metadata found. It will not recompute metadata on the fly
@@ -12,6 +12,7 @@ use blobstore::LoadableError;
 use blobstore::Storable;
 use context::CoreContext;
 use mononoke_types::BlobstoreValue;
+use mononoke_types::ContentAlias;
 use mononoke_types::ContentId;
 use mononoke_types::ContentMetadata;
 use mononoke_types::ContentMetadataId;
@@ -20,6 +21,8 @@ use thiserror::Error;
 use crate::alias::alias_stream;
 use crate::expected_size::ExpectedSize;
 use crate::fetch;
+use crate::fetch_key::Alias;
+use crate::fetch_key::AliasBlob;
 
 #[derive(Debug, Error)]
 pub enum RebuildBackmappingError {
@@ -81,15 +84,58 @@ pub async fn get_metadata_readonly<B: Blobstore>(
         })
 }
 
+/// Finds the metadata for a ContentId, making sure it includes the Git SHA-256 alias. Metadata
+/// stored before the filestore computed that alias is recomputed, and the missing alias is
+/// written before the updated metadata. Returns None if the content does not exist.
+pub async fn get_metadata_with_git_sha256<B: Blobstore>(
+    blobstore: &B,
+    ctx: &CoreContext,
+    content_id: ContentId,
+) -> Result<Option<ContentMetadata>, Error> {
+    match get_metadata(blobstore, ctx, content_id).await? {
+        Some(metadata) if metadata.git_sha256.is_some() => Ok(Some(metadata)),
+        Some(_) => {
+            let metadata = compute_metadata(blobstore, ctx, content_id).await?;
+            if let Some(git_sha256) = metadata.git_sha256 {
+                AliasBlob(
+                    Alias::GitSha256(git_sha256.sha256()),
+                    ContentAlias::from_content_id(content_id),
+                )
+                .store(ctx, blobstore)
+                .await?;
+            }
+            metadata.clone().into_blob().store(ctx, blobstore).await?;
+            Ok(Some(metadata))
+        }
+        None => Ok(None),
+    }
+}
+
 /// If the metadata is missing, we can rebuild it on the fly, since all that's needed to do so
 /// is the file contents. This can happen if we successfully stored a file, but failed to store
-/// its metadata. To rebuild the metadata, we peek at the content in the blobstore to get
-/// its size, then produce a stream of its contents and compute aliases over it. Finally, store
-/// the metadata, and return it.
+/// its metadata. Store the recomputed metadata, and return it.
 async fn rebuild_metadata<B: Blobstore>(
     blobstore: &B,
     ctx: &CoreContext,
     content_id: ContentId,
+) -> Result<ContentMetadata, RebuildBackmappingError> {
+    let metadata = compute_metadata(blobstore, ctx, content_id).await?;
+
+    let blob = metadata.clone().into_blob();
+
+    blob.store(ctx, blobstore)
+        .await
+        .map_err(|e| RebuildBackmappingError::InternalError(content_id, e))?;
+
+    Ok(metadata)
+}
+
+/// To compute the metadata, we peek at the content in the blobstore to get its size, then
+/// produce a stream of its contents and compute aliases over it.
+async fn compute_metadata<B: Blobstore>(
+    blobstore: &B,
+    ctx: &CoreContext,
+    content_id: ContentId,
 ) -> Result<ContentMetadata, RebuildBackmappingError> {
     use RebuildBackmappingError::*;
 
@@ -112,7 +158,7 @@ async fn rebuild_metadata<B: Blobstore>(
         .await
         .map_err(|e| InternalError(content_id, e))?;
 
-    let (sha1, sha256, git_sha1) = redeemable
+    let (sha1, sha256, git_sha1, git_sha256) = redeemable
         .redeem(total_size)
         .map_err(|e| InternalError(content_id, e))?;
 
@@ -122,13 +168,8 @@ async fn rebuild_metadata<B: Blobstore>(
         sha1,
         sha256,
         git_sha1,
+        git_sha256: Some(git_sha256),
     };
 
-    let blob = metadata.clone().into_blob();
-
-    blob.store(ctx, blobstore)
-        .await
-        .map_err(|e| InternalError(content_id, e))?;
-
     Ok(metadata)
 }
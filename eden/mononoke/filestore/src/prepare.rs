@@ -29,6 +29,7 @@ use crate::expected_size::ExpectedSize;
 use crate::incremental_hash::hash_bytes;
 use crate::incremental_hash::ContentIdIncrementalHasher;
 use crate::incremental_hash::GitSha1IncrementalHasher;
+use crate::incremental_hash::GitSha256IncrementalHasher;
 use crate::incremental_hash::Sha1IncrementalHasher;
 use crate::incremental_hash::Sha256IncrementalHasher;
 use crate::multiplexer::Multiplexer;
@@ -40,6 +41,7 @@ pub struct Prepared {
     pub sha1: hash::Sha1,
     pub sha256: hash::Sha256,
     pub git_sha1: hash::RichGitSha1,
+    pub git_sha256: hash::RichGitSha256,
     pub contents: FileContents,
 }
 
@@ -47,6 +49,7 @@ pub fn prepare_bytes(bytes: Bytes) -> Prepared {
     let sha1 = hash_bytes(Sha1IncrementalHasher::new(), &bytes);
     let sha256 = hash_bytes(Sha256IncrementalHasher::new(), &bytes);
     let git_sha1 = hash_bytes(GitSha1IncrementalHasher::new(&bytes), &bytes);
+    let git_sha256 = hash_bytes(GitSha256IncrementalHasher::new(&bytes), &bytes);
 
     let contents = FileContents::Bytes(bytes);
 
@@ -54,6 +57,7 @@ pub fn prepare_bytes(bytes: Bytes) -> Prepared {
         sha1,
         sha256,
         git_sha1,
+        git_sha256,
         contents,
     }
 }
@@ -136,12 +140,13 @@ where
 
             let contents = FileContents::Chunked(ChunkedFileContents::new(content_id, chunks));
 
-            let (sha1, sha256, git_sha1) = aliases.redeem(contents.size())?;
+            let (sha1, sha256, git_sha1, git_sha256) = aliases.redeem(contents.size())?;
 
             let prepared = Prepared {
                 sha1,
                 sha256,
                 git_sha1,
+                git_sha256,
                 contents,
             };
 
@@ -31,6 +31,7 @@ mod test {
     use bytes::Bytes;
     use futures::stream;
     use mononoke_types::hash::RichGitSha1;
+    use mononoke_types::hash::RichGitSha256;
     use mononoke_types::hash::Sha1;
     use mononoke_types::hash::Sha256;
     use mononoke_types::ContentId;
@@ -39,6 +40,7 @@ mod test {
     use crate::expected_size::ExpectedSize;
     use crate::incremental_hash::ContentIdIncrementalHasher;
     use crate::incremental_hash::GitSha1IncrementalHasher;
+    use crate::incremental_hash::GitSha256IncrementalHasher;
     use crate::incremental_hash::Sha1IncrementalHasher;
     use crate::incremental_hash::Sha256IncrementalHasher;
 
@@ -124,6 +126,31 @@ mod test {
         );
     }
 
+    #[tokio::test]
+    async fn git_sha256_chunks() {
+        let data = vec![&b"hello"[..], &b", "[..], &b"world"[..]] // 7d0be525d6521168c74051e5ab1b99e3b6d1c962fba763818f1954ab9e1c821a
+            .into_iter()
+            .map(Bytes::from);
+        let s = stream::iter(data);
+
+        let res: RichGitSha256 =
+            hash_stream(GitSha256IncrementalHasher::new(ExpectedSize::new(12)), s).await;
+
+        assert_eq!(
+            res,
+            RichGitSha256::from_bytes(
+                [
+                    0x7d, 0x0b, 0xe5, 0x25, 0xd6, 0x52, 0x11, 0x68, 0xc7, 0x40, 0x51, 0xe5, 0xab,
+                    0x1b, 0x99, 0xe3, 0xb6, 0xd1, 0xc9, 0x62, 0xfb, 0xa7, 0x63, 0x81, 0x8f, 0x19,
+                    0x54, 0xab, 0x9e, 0x1c, 0x82, 0x1a
+                ],
+                "blob",
+                12
+            )
+            .unwrap()
+        );
+    }
+
     #[tokio::test]
     async fn sha256_simple() {
         let data = Bytes::from(&b"hello, world"[..]); // 09ca7e4eaa6e8ae9c7d261167129184883644d07dfba7cbfbc4c8a2e08360d5b
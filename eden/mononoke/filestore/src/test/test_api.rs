@@ -10,6 +10,7 @@ use anyhow::Result;
 use assert_matches::assert_matches;
 use blobstore::Blobstore;
 use blobstore::PutBehaviour;
+use blobstore::Storable;
 use borrowed::borrowed;
 use bytes::Bytes;
 use bytes::BytesMut;
@@ -22,6 +23,7 @@ use futures::stream::TryStreamExt;
 use lazy_static::lazy_static;
 use mononoke_types::hash;
 use mononoke_types::typed_hash::BlobstoreKey;
+use mononoke_types::BlobstoreValue;
 use mononoke_types::ContentId;
 use mononoke_types::ContentMetadata;
 use mononoke_types::ContentMetadataId;
@@ -61,6 +63,16 @@ lazy_static! {
         HELLO_WORLD_LENGTH
     )
     .unwrap();
+    static ref HELLO_WORLD_GIT_SHA256: hash::RichGitSha256 = hash::RichGitSha256::from_bytes(
+        [
+            0x7d, 0x0b, 0xe5, 0x25, 0xd6, 0x52, 0x11, 0x68, 0xc7, 0x40, 0x51, 0xe5, 0xab, 0x1b,
+            0x99, 0xe3, 0xb6, 0xd1, 0xc9, 0x62, 0xfb, 0xa7, 0x63, 0x81, 0x8f, 0x19, 0x54, 0xab,
+            0x9e, 0x1c, 0x82, 0x1a
+        ],
+        "blob",
+        HELLO_WORLD_LENGTH
+    )
+    .unwrap();
     static ref HELLO_WORLD_SHA256: hash::Sha256 = hash::Sha256::from_bytes([
         0x09, 0xca, 0x7e, 0x4e, 0xaa, 0x6e, 0x8a, 0xe9, 0xc7, 0xd2, 0x61, 0x16, 0x71, 0x29, 0x18,
         0x48, 0x83, 0x64, 0x4d, 0x07, 0xdf, 0xba, 0x7c, 0xbf, 0xbc, 0x4c, 0x8a, 0x2e, 0x08, 0x36,
@@ -97,7 +109,8 @@ async fn filestore_put_alias(fb: FacebookInit) -> Result<()> {
             content_id,
             sha1: *HELLO_WORLD_SHA1,
             git_sha1: *HELLO_WORLD_GIT_SHA1,
-            sha256: *HELLO_WORLD_SHA256
+            sha256: *HELLO_WORLD_SHA256,
+            git_sha256: Some(*HELLO_WORLD_GIT_SHA256),
         })
     );
 
@@ -191,6 +204,36 @@ async fn filestore_put_get_git_sha1(fb: FacebookInit) -> Result<()> {
     Ok(())
 }
 
+#[fbinit::test]
+async fn filestore_put_get_git_sha256(fb: FacebookInit) -> Result<()> {
+    let req = request(HELLO_WORLD);
+
+    let blob = memblob::Memblob::default();
+    let ctx = CoreContext::test_mock(fb);
+    borrowed!(ctx, blob, req);
+
+    filestore::store(
+        blob,
+        DEFAULT_CONFIG,
+        ctx,
+        req,
+        stream::once(future::ready(Ok(Bytes::from(HELLO_WORLD)))),
+    )
+    .await?;
+
+    let res = filestore::fetch_concat_opt(
+        blob,
+        ctx,
+        &FetchKey::Aliased(Alias::GitSha256(HELLO_WORLD_GIT_SHA256.sha256())),
+    )
+    .await;
+
+    println!("res = {:#?}", res);
+
+    assert_eq!(res?, Some(Bytes::from(HELLO_WORLD)));
+    Ok(())
+}
+
 #[fbinit::test]
 async fn filestore_put_get_sha256(fb: FacebookInit) -> Result<()> {
     let req = request(HELLO_WORLD);
@@ -518,6 +561,52 @@ async fn filestore_put_git_sha1(fb: FacebookInit) -> Result<()> {
     Ok(())
 }
 
+#[fbinit::test]
+async fn filestore_put_git_sha256(fb: FacebookInit) -> Result<()> {
+    let blob = memblob::Memblob::default();
+    let config = FilestoreConfig {
+        chunk_size: Some(3),
+        concurrency: 5,
+    };
+    let ctx = CoreContext::test_mock(fb);
+
+    // Bad Content Id should fail
+    let req = StoreRequest::with_git_sha256(
+        HELLO_WORLD_LENGTH,
+        hash::RichGitSha256::from_byte_array([0x00; 32], "blob", HELLO_WORLD_LENGTH),
+    );
+    borrowed!(ctx, blob, req);
+
+    let res = filestore::store(
+        blob,
+        config,
+        ctx,
+        req,
+        stream::once(future::ready(Ok(Bytes::from(HELLO_WORLD)))),
+    )
+    .await;
+    println!("res = {:#?}", res);
+    assert_matches!(
+        res.unwrap_err().downcast::<errors::ErrorKind>(),
+        Ok(errors::ErrorKind::InvalidGitSha256(..))
+    );
+
+    // Correct content Id should succeed
+    let req = StoreRequest::with_git_sha256(HELLO_WORLD_LENGTH, *HELLO_WORLD_GIT_SHA256);
+    let res = filestore::store(
+        blob,
+        config,
+        ctx,
+        &req,
+        stream::once(future::ready(Ok(Bytes::from(HELLO_WORLD)))),
+    )
+    .await;
+    println!("res = {:#?}", res);
+    assert!(res.is_ok());
+
+    Ok(())
+}
+
 #[fbinit::test]
 async fn filestore_put_sha256(fb: FacebookInit) -> Result<()> {
     let blob = memblob::Memblob::default();
@@ -776,6 +865,7 @@ async fn filestore_rebuild_metadata(fb: FacebookInit) -> Result<()> {
         sha1: *HELLO_WORLD_SHA1,
         git_sha1: *HELLO_WORLD_GIT_SHA1,
         sha256: *HELLO_WORLD_SHA256,
+        git_sha256: Some(*HELLO_WORLD_GIT_SHA256),
     });
 
     let blob = memblob::Memblob::default();
@@ -834,6 +924,54 @@ async fn filestore_rebuild_metadata(fb: FacebookInit) -> Result<()> {
     Ok(())
 }
 
+#[fbinit::test]
+async fn filestore_backfill_git_sha256(fb: FacebookInit) -> Result<()> {
+    let req = request(HELLO_WORLD);
+    let content_id = canonical(HELLO_WORLD);
+    let git_sha256_key = FetchKey::Aliased(Alias::GitSha256(HELLO_WORLD_GIT_SHA256.sha256()));
+
+    let blob = memblob::Memblob::default();
+    let ctx = CoreContext::test_mock(fb);
+    borrowed!(ctx, blob, req);
+
+    filestore::store(
+        blob,
+        DEFAULT_CONFIG,
+        ctx,
+        req,
+        stream::once(future::ready(Ok(Bytes::from(HELLO_WORLD)))),
+    )
+    .await?;
+
+    // Make it look like the content was stored before Git SHA-256 aliases were computed.
+    let old_metadata = ContentMetadata {
+        git_sha256: None,
+        ..filestore::get_metadata(blob, ctx, &FetchKey::Canonical(content_id))
+            .await?
+            .unwrap()
+    };
+    old_metadata.into_blob().store(ctx, blob).await?;
+    assert!(
+        blob.unlink(git_sha256_key.blobstore_key())
+            .await
+            .unwrap()
+            .is_some()
+    );
+
+    let res = filestore::get_metadata_with_git_sha256(blob, ctx, &FetchKey::Canonical(content_id))
+        .await?;
+    assert_eq!(
+        res.and_then(|metadata| metadata.git_sha256),
+        Some(*HELLO_WORLD_GIT_SHA256)
+    );
+
+    // The alias was backfilled too.
+    let res = filestore::fetch_concat_opt(blob, ctx, &git_sha256_key).await?;
+    assert_eq!(res, Some(Bytes::from(HELLO_WORLD)));
+
+    Ok(())
+}
+
 #[fbinit::test]
 async fn filestore_test_missing_metadata(fb: FacebookInit) -> Result<()> {
     let content_id = canonical(HELLO_WORLD);
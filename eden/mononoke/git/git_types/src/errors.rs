@@ -16,4 +16,6 @@ pub enum ErrorKind {
     TreeDerivationFailed,
     #[error("Invalid Thrift")]
     InvalidThrift,
+    #[error("Unknown Git object format: {0}")]
+    UnknownObjectFormat(String),
 }
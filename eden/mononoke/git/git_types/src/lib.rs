@@ -19,9 +19,12 @@ mod object;
 mod store;
 mod tree;
 
+pub use object::ObjectFormat;
 pub use object::ObjectKind;
 
 pub use crate::blob::BlobHandle;
+pub use crate::tree::sha256_tree_oid;
+pub use crate::tree::Sha256TreeMember;
 pub use crate::tree::Tree;
 pub use crate::tree::TreeBuilder;
 pub use crate::tree::TreeHandle;
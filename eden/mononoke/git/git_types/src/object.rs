@@ -5,9 +5,53 @@
  * GNU General Public License version 2.
  */
 
+use std::str::FromStr;
+
+use anyhow::Error;
 use digest::Digest;
 use mononoke_types::hash::RichGitSha1;
+use mononoke_types::hash::RichGitSha256;
 use sha1::Sha1;
+use sha2::Sha256;
+
+use crate::errors::ErrorKind;
+
+/// The hash function a Git repository uses to name its objects, as set by
+/// `extensions.objectFormat`.
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
+pub enum ObjectFormat {
+    Sha1,
+    Sha256,
+}
+
+impl ObjectFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Sha1 => "sha1",
+            Self::Sha256 => "sha256",
+        }
+    }
+
+    /// Length of an object id in this format, in hex digits.
+    pub fn hex_len(&self) -> usize {
+        match self {
+            Self::Sha1 => 40,
+            Self::Sha256 => 64,
+        }
+    }
+}
+
+impl FromStr for ObjectFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        match s.trim() {
+            "sha1" => Ok(Self::Sha1),
+            "sha256" => Ok(Self::Sha256),
+            other => Err(ErrorKind::UnknownObjectFormat(other.to_string()).into()),
+        }
+    }
+}
 
 #[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
 pub enum ObjectKind {
@@ -49,4 +93,37 @@ impl ObjectKind {
 
         RichGitSha1::from_byte_array(hash, self.as_str(), size)
     }
+
+    /// Like `create_oid`, but for repositories using the SHA-256 object format.
+    pub fn create_oid_sha256(&self, object_buff: impl AsRef<[u8]>) -> RichGitSha256 {
+        let object_buff = object_buff.as_ref();
+        let size = object_buff
+            .len()
+            .try_into()
+            .expect("Object size must fit in a u64");
+
+        let mut sha256 = Sha256::new();
+        sha256.update(&format!("{} {}", self.as_str(), size));
+        sha256.update(&[0]);
+        sha256.update(object_buff.as_ref());
+
+        let hash: [u8; 32] = sha256.finalize().into();
+
+        RichGitSha256::from_byte_array(hash, self.as_str(), size)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_object_format() -> Result<(), Error> {
+        // As printed by `git config extensions.objectFormat`.
+        assert_eq!("sha256\n".parse::<ObjectFormat>()?, ObjectFormat::Sha256);
+        assert_eq!("sha1".parse::<ObjectFormat>()?, ObjectFormat::Sha1);
+        assert!("md5".parse::<ObjectFormat>().is_err());
+        assert_eq!(ObjectFormat::Sha256.hex_len(), 64);
+        Ok(())
+    }
 }
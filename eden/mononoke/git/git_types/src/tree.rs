@@ -16,6 +16,8 @@ use std::io::Write;
 use ::manifest::Entry;
 use anyhow::Error;
 use mononoke_types::hash::RichGitSha1;
+use mononoke_types::hash::RichGitSha256;
+use mononoke_types::FileType;
 use mononoke_types::MPathElement;
 
 use crate::errors::ErrorKind;
@@ -261,22 +263,84 @@ impl Display for TreeBuilder {
     }
 }
 
+/// A member of a tree in a repository using the SHA-256 object format. Trees in this format
+/// are not derived, so members only carry what is needed to compute the tree's oid.
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
+pub struct Sha256TreeMember {
+    kind: ObjectKind,
+    filemode: i32,
+    oid: RichGitSha256,
+}
+
+impl Sha256TreeMember {
+    /// The oid of file content is its `git_sha256` in the filestore metadata. Content stored
+    /// before that alias was computed needs `filestore::get_metadata_with_git_sha256`.
+    pub fn blob(oid: RichGitSha256, file_type: FileType) -> Self {
+        let filemode = match file_type {
+            FileType::Regular => mode::GIT_FILEMODE_BLOB,
+            FileType::Executable => mode::GIT_FILEMODE_BLOB_EXECUTABLE,
+            FileType::Symlink => mode::GIT_FILEMODE_LINK,
+        };
+        Self {
+            kind: ObjectKind::Blob,
+            filemode,
+            oid,
+        }
+    }
+
+    pub fn tree(oid: RichGitSha256) -> Self {
+        Self {
+            kind: ObjectKind::Tree,
+            filemode: mode::GIT_FILEMODE_TREE,
+            oid,
+        }
+    }
+
+    pub fn filemode(&self) -> i32 {
+        self.filemode
+    }
+
+    pub fn oid(&self) -> &RichGitSha256 {
+        &self.oid
+    }
+
+    pub fn kind(&self) -> ObjectKind {
+        self.kind
+    }
+}
+
+/// Compute the oid of a tree with these members in a repository using the SHA-256 object
+/// format. The serialization is the same as for SHA-1 trees, but with 32 byte oids.
+pub fn sha256_tree_oid(members: &HashMap<MPathElement, Sha256TreeMember>) -> RichGitSha256 {
+    let mut members: Vec<_> = members.iter().collect();
+    members.sort_by(|(p1, e1), (p2, e2)| {
+        git_path_cmp(p1, e1.kind().is_tree(), p2, e2.kind().is_tree())
+    });
+
+    let mut object_buff = Vec::new();
+    for (path, member) in members {
+        write!(object_buff, "{:o} ", member.filemode()).expect("Writes to Vec cannot fail");
+        object_buff.extend_from_slice(path.as_ref());
+        object_buff.push(0);
+        object_buff.extend_from_slice(member.oid().as_ref());
+    }
+
+    ObjectKind::Tree.create_oid_sha256(&object_buff)
+}
+
 fn iter_members_git_path_order(
     members: &HashMap<MPathElement, TreeMember>,
 ) -> impl Iterator<Item = (&MPathElement, &TreeMember)> {
     let mut members: Vec<_> = members.iter().collect();
-    members.sort_by(|(p1, e1), (p2, e2)| git_path_cmp(p1, e1, p2, e2));
+    members.sort_by(|(p1, e1), (p2, e2)| {
+        git_path_cmp(p1, e1.kind().is_tree(), p2, e2.kind().is_tree())
+    });
     members.into_iter()
 }
 
 // TODO: Expose git_path_cmp from libgit2 and use it here
 // https://github.com/libgit2/libgit2/blob/fb439c975a2de33f5b0c317f3fdea49dc94b27dc/src/path.c#L850
-fn git_path_cmp(
-    p1: &MPathElement,
-    e1: &TreeMember,
-    p2: &MPathElement,
-    e2: &TreeMember,
-) -> Ordering {
+fn git_path_cmp(p1: &MPathElement, is_tree1: bool, p2: &MPathElement, is_tree2: bool) -> Ordering {
     const NULL: u8 = 0;
     const SLASH: u8 = b'/';
 
@@ -289,13 +353,58 @@ fn git_path_cmp(
         return ordering;
     }
 
-    let c1 = p1
-        .get(len)
-        .unwrap_or(if e1.kind().is_tree() { &SLASH } else { &NULL });
+    let c1 = p1.get(len).unwrap_or(if is_tree1 { &SLASH } else { &NULL });
 
-    let c2 = p2
-        .get(len)
-        .unwrap_or(if e2.kind().is_tree() { &SLASH } else { &NULL });
+    let c2 = p2.get(len).unwrap_or(if is_tree2 { &SLASH } else { &NULL });
 
     c1.cmp(c2)
 }
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use mononoke_types::hash::GitSha256;
+
+    use super::*;
+
+    fn element(name: &str) -> MPathElement {
+        MPathElement::new(name.as_bytes().to_vec()).unwrap()
+    }
+
+    #[test]
+    fn test_sha256_tree_oid() -> Result<(), Error> {
+        // "hello, world" as a blob in a SHA-256 repository.
+        let blob = RichGitSha256::from_sha256(
+            GitSha256::from_str(
+                "7d0be525d6521168c74051e5ab1b99e3b6d1c962fba763818f1954ab9e1c821a",
+            )?,
+            "blob",
+            12,
+        );
+        assert_eq!(ObjectKind::Blob.create_oid_sha256("hello, world"), blob);
+
+        let dir = sha256_tree_oid(&HashMap::from([(
+            element("b"),
+            Sha256TreeMember::blob(blob, FileType::Regular),
+        )]));
+        assert_eq!(
+            dir.to_hex().as_str(),
+            "7cea22c43c2d2c8d121919343dc8d35655a295d8bf9abc50fa10201bc011b158"
+        );
+
+        let root = sha256_tree_oid(&HashMap::from([
+            (
+                element("a"),
+                Sha256TreeMember::blob(blob, FileType::Regular),
+            ),
+            (element("d"), Sha256TreeMember::tree(dir)),
+        ]));
+        assert_eq!(
+            root.to_hex().as_str(),
+            "6e61007621c162d605408d390f20ea8d87655d1d12d7b32ecfbb8142e6440408"
+        );
+
+        Ok(())
+    }
+}
@@ -20,6 +20,7 @@ use git_hash::ObjectId;
 use git_object::Kind;
 use git_object::Object;
 use git_object::ObjectRef;
+use git_types::ObjectFormat;
 use tokio::io::AsyncBufReadExt;
 use tokio::io::AsyncReadExt;
 use tokio::io::AsyncWriteExt;
@@ -38,10 +39,43 @@ pub struct GitRepoReader {
     outstanding_requests: Arc<Mutex<HashMap<ObjectId, Vec<ObjectSender>>>>,
 }
 
+/// Find out which object format the repo at `repo_path` uses. Repos that don't set
+/// `extensions.objectFormat` use SHA-1.
+pub async fn git_object_format(git_command_path: &Path, repo_path: &Path) -> Result<ObjectFormat> {
+    let output = Command::new(git_command_path)
+        .current_dir(repo_path)
+        .env_clear()
+        .arg("config")
+        .arg("--get")
+        .arg("extensions.objectFormat")
+        .output()
+        .await?;
+    // git config exits with 1 if the key is not set
+    match output.status.code() {
+        Some(0) => String::from_utf8(output.stdout)?.parse(),
+        Some(1) => Ok(ObjectFormat::Sha1),
+        _ => bail!(
+            "Failed to read object format: {}",
+            String::from_utf8_lossy(&output.stderr)
+        ),
+    }
+}
+
 impl GitRepoReader {
     /// Create a new repo reader for the repo at `repo_path`, using `git_command_path`
     /// as `git`
     pub async fn new(git_command_path: &Path, repo_path: &Path) -> Result<Self> {
+        // Object ids and trees are parsed with gitoxide, which only understands SHA-1 ids,
+        // so fail up front rather than on the first object.
+        let object_format = git_object_format(git_command_path, repo_path).await?;
+        if object_format != ObjectFormat::Sha1 {
+            bail!(
+                "Repo at {} uses the {} object format, which the git reader can't parse yet",
+                repo_path.display(),
+                object_format.as_str(),
+            );
+        }
+
         let mut batch_cat_file = Command::new(git_command_path)
             .current_dir(repo_path)
             .env_clear()
@@ -43,6 +43,7 @@ use tokio::io::BufReader;
 use tokio::process::Command;
 use tokio::task;
 
+pub use crate::git_reader::git_object_format;
 pub use crate::git_reader::GitRepoReader;
 pub use crate::gitimport_objects::convert_time_to_datetime;
 pub use crate::gitimport_objects::oid_to_sha1;
@@ -810,6 +810,8 @@ mod test {
             casefolding_check = false
             emit_obsmarkers = false
             allow_change_xrepo_mapping_extra = true
+            merge_conflicting_files = true
+            merge_max_file_size = 4096
 
             [pushrebase.remote_mode]
             remote_scs = { tier = "my-tier" }
@@ -1115,6 +1117,8 @@ mod test {
                         casefolding_check: false,
                         not_generated_filenodes_limit: 500,
                         monitoring_bookmark: None,
+                        merge_conflicting_files: true,
+                        merge_max_file_size: 4096,
                     },
                     block_merges: false,
                     emit_obsmarkers: false,
@@ -294,6 +294,14 @@ impl Convert for RawPushrebaseParams {
                     .unwrap_or(default.flags.casefolding_check),
                 not_generated_filenodes_limit: 500,
                 monitoring_bookmark: self.monitoring_bookmark,
+                merge_conflicting_files: self
+                    .merge_conflicting_files
+                    .unwrap_or(default.flags.merge_conflicting_files),
+                merge_max_file_size: self
+                    .merge_max_file_size
+                    .map(|v| v.try_into())
+                    .transpose()?
+                    .unwrap_or(default.flags.merge_max_file_size),
             },
             commit_scribe_category: self.commit_scribe_category,
             block_merges: self.block_merges.unwrap_or(default.block_merges),
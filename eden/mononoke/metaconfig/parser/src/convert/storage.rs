@@ -28,6 +28,7 @@ use metaconfig_types::PackConfig;
 use metaconfig_types::PackFormat;
 use metaconfig_types::RemoteDatabaseConfig;
 use metaconfig_types::RemoteMetadataDatabaseConfig;
+use metaconfig_types::SegmentSyncPolicy;
 use metaconfig_types::ShardableRemoteDatabaseConfig;
 use metaconfig_types::ShardedRemoteDatabaseConfig;
 use metaconfig_types::StorageConfig;
@@ -112,6 +113,21 @@ impl Convert for RawBlobstoreConfig {
             RawBlobstoreConfig::blob_sqlite(raw) => BlobConfig::Sqlite {
                 path: PathBuf::from(raw.path),
             },
+            RawBlobstoreConfig::blob_segments(raw) => BlobConfig::Segments {
+                path: PathBuf::from(raw.path),
+                max_segment_size: raw
+                    .max_segment_size
+                    .map(|size| {
+                        NonZeroU64::new(size.try_into()?)
+                            .ok_or_else(|| anyhow!("max_segment_size must be larger than zero"))
+                    })
+                    .transpose()?,
+                sync_policy: match raw.sync_every_n_writes.map(usize::try_from).transpose()? {
+                    None | Some(1) => SegmentSyncPolicy::EveryWrite,
+                    Some(n) => NonZeroUsize::new(n)
+                        .map_or(SegmentSyncPolicy::Never, SegmentSyncPolicy::Batched),
+                },
+            },
             RawBlobstoreConfig::manifold(raw) => BlobConfig::Manifold {
                 bucket: raw.manifold_bucket,
                 prefix: raw.manifold_prefix,
@@ -190,6 +206,15 @@ impl Convert for RawBlobstoreConfig {
                 blobconfig: Box::new(raw.blobstore.convert()?),
                 pack_config: raw.pack_config.map(|c| c.convert()).transpose()?,
             },
+            RawBlobstoreConfig::tiered(raw) => BlobConfig::Tiered {
+                hot: Box::new(raw.hot.convert()?),
+                cold: Box::new(raw.cold.convert()?),
+                promote_on_read: raw.promote_on_read.unwrap_or(true),
+                demote_after: raw
+                    .demote_after_secs
+                    .map(|secs| secs.try_into().map(Duration::from_secs))
+                    .transpose()?,
+            },
             RawBlobstoreConfig::s3(raw) => BlobConfig::S3 {
                 bucket: raw.bucket,
                 keychain_group: raw.keychain_group,
@@ -230,6 +255,9 @@ impl Convert for RawBlobstorePackFormat {
             RawBlobstorePackFormat::ZstdIndividual(zstd) => {
                 PackFormat::ZstdIndividual(zstd.compression_level)
             }
+            RawBlobstorePackFormat::ZstdDictionary(zstd) => {
+                PackFormat::ZstdDictionary(zstd.compression_level)
+            }
             RawBlobstorePackFormat::UnknownField(f) => bail!("Unsupported PackFormat {}", f),
         };
         Ok(pack_format)
@@ -621,6 +621,11 @@ pub struct PushrebaseFlags {
     pub not_generated_filenodes_limit: u64,
     /// Which bookmark to track in ODS
     pub monitoring_bookmark: Option<String>,
+    /// Merge files changed both in the pushed commits and on the bookmark
+    /// instead of rejecting the push, if the changes don't overlap.
+    pub merge_conflicting_files: bool,
+    /// Files larger than this (in bytes) are never merged during pushrebase.
+    pub merge_max_file_size: u64,
 }
 
 impl Default for PushrebaseFlags {
@@ -632,6 +637,8 @@ impl Default for PushrebaseFlags {
             casefolding_check: true,
             not_generated_filenodes_limit: 500,
             monitoring_bookmark: None,
+            merge_conflicting_files: false,
+            merge_max_file_size: 1024 * 1024,
         }
     }
 }
@@ -795,6 +802,9 @@ pub enum PackFormat {
     Raw,
     /// Data will be compressed and written in compressed form if its smaller than Raw
     ZstdIndividual(i32),
+    /// Like ZstdIndividual, but small blobs are compressed with the dictionary
+    /// trained by the packer for their type if that is smaller still
+    ZstdDictionary(i32),
 }
 
 impl Default for PackFormat {
@@ -810,6 +820,23 @@ pub struct PackConfig {
     pub put_format: PackFormat,
 }
 
+/// How often the segment file blobstore makes writes durable
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Deserialize, Hash)]
+pub enum SegmentSyncPolicy {
+    /// fsync after every write
+    EveryWrite,
+    /// fsync after this many writes; a crash loses at most the writes since the last fsync
+    Batched(NonZeroUsize),
+    /// Never fsync explicitly and leave flushing to the OS
+    Never,
+}
+
+impl Default for SegmentSyncPolicy {
+    fn default() -> Self {
+        SegmentSyncPolicy::EveryWrite
+    }
+}
+
 /// Configuration for a blobstore
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub enum BlobConfig {
@@ -828,6 +855,16 @@ pub enum BlobConfig {
         /// Path to SQLite DB
         path: PathBuf,
     },
+    /// Blob repository with path pointing to a directory of append-only segment files with an
+    /// on-disk index. Suitable for single-host production use.
+    Segments {
+        /// Path to directory containing the segment files
+        path: PathBuf,
+        /// Size at which a segment is sealed and a new one started
+        max_segment_size: Option<NonZeroU64>,
+        /// When to fsync writes
+        sync_policy: SegmentSyncPolicy,
+    },
     /// Store in a manifold bucket
     Manifold {
         /// Bucket of the backing Manifold blobstore to connect to
@@ -885,6 +922,19 @@ pub enum BlobConfig {
         /// Optional configuration for setting things like default compression levels
         pack_config: Option<PackConfig>,
     },
+    /// A blobstore that keeps recently used blobs in a fast hot tier and moves idle ones to a
+    /// cheaper cold tier
+    Tiered {
+        /// The blobstore that receives writes. Must support enumeration and unlink.
+        hot: Box<BlobConfig>,
+        /// The blobstore that idle blobs are moved to
+        cold: Box<BlobConfig>,
+        /// Copy blobs read from the cold tier into the hot tier
+        promote_on_read: bool,
+        /// Move blobs that have not been written or read for this long to the cold tier. If
+        /// None, blobs are never demoted.
+        demote_after: Option<Duration>,
+    },
     /// Store in a S3 compatible storage
     S3 {
         /// Bucket to connect to
@@ -909,7 +959,7 @@ impl BlobConfig {
         use BlobConfig::*;
 
         match self {
-            Disabled | Files { .. } | Sqlite { .. } => true,
+            Disabled | Files { .. } | Sqlite { .. } | Segments { .. } => true,
             Manifold { .. } | Mysql { .. } | ManifoldWithTtl { .. } | S3 { .. } => false,
             Multiplexed { blobstores, .. } => blobstores
                 .iter()
@@ -917,6 +967,7 @@ impl BlobConfig {
                 .all(BlobConfig::is_local),
             Logging { blobconfig, .. } => blobconfig.is_local(),
             Pack { blobconfig, .. } => blobconfig.is_local(),
+            Tiered { hot, cold, .. } => hot.is_local() && cold.is_local(),
         }
     }
 
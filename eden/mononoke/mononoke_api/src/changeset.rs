@@ -5,6 +5,7 @@
  * GNU General Public License version 2.
  */
 
+use std::collections::BTreeMap;
 use std::collections::BTreeSet;
 use std::collections::HashMap;
 use std::collections::HashSet;
@@ -49,7 +50,9 @@ use manifest::ManifestOrderedOps;
 use manifest::PathOrPrefix;
 use maplit::hashset;
 use mercurial_types::Globalrev;
+use mononoke_types::trigram_index::query_trigrams;
 use mononoke_types::BonsaiChangeset;
+use mononoke_types::ContentId;
 use mononoke_types::FileChange;
 pub use mononoke_types::Generation;
 use mononoke_types::MPath;
@@ -61,6 +64,7 @@ use repo_blobstore::RepoBlobstoreArc;
 use repo_derived_data::RepoDerivedDataArc;
 use skeleton_manifest::RootSkeletonManifestId;
 use sorted_vector_map::SortedVectorMap;
+use trigram_index::RootTrigramIndexId;
 use tunables::tunables;
 use unodes::RootUnodeManifestId;
 use vec1::Vec1;
@@ -123,6 +127,7 @@ pub struct ChangesetContext {
     root_deleted_manifest_v2_id: LazyShared<Result<RootDeletedManifestV2Id, MononokeError>>,
     root_basename_suffix_skeleton_manifest:
         LazyShared<Result<RootBasenameSuffixSkeletonManifest, MononokeError>>,
+    root_trigram_index_id: LazyShared<Result<RootTrigramIndexId, MononokeError>>,
     /// None if no mutable history, else map from supplied paths to data fetched
     mutable_history: Option<HashMap<MononokePath, PathMutableHistory>>,
 }
@@ -140,6 +145,16 @@ pub enum ChangesetFileOrdering {
     Ordered { after: Option<MononokePath> },
 }
 
+/// A line of a file that matched a content search.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ContentSearchMatch {
+    pub path: MononokePath,
+    /// Line number of the matching line, starting from 1.
+    pub line_number: usize,
+    /// The matching line, without its newline.
+    pub line: Bytes,
+}
+
 #[derive(PartialEq, Eq, PartialOrd, Ord)]
 pub enum ChangesetDiffItem {
     TREES,
@@ -161,6 +176,28 @@ fn to_vec1<X>(maybe_vec: Option<Vec<X>>) -> Option<Vec1<X>> {
     maybe_vec.and_then(|v| Vec1::try_from_vec(v).ok())
 }
 
+/// Find the lines of `content` that contain `query`.  For case-insensitive
+/// searches, `query` must already be lowercase.
+fn matching_lines(content: &Bytes, query: &[u8], case_sensitive: bool) -> Vec<(usize, Bytes)> {
+    let mut matches = Vec::new();
+    let mut start = 0;
+    for (index, line) in content.split(|byte| *byte == b'\n').enumerate() {
+        let end = start + line.len();
+        let matched = if case_sensitive {
+            line.windows(query.len()).any(|window| window == query)
+        } else {
+            line.to_ascii_lowercase()
+                .windows(query.len())
+                .any(|window| window == query)
+        };
+        if matched {
+            matches.push((index + 1, content.slice(start..end)));
+        }
+        start = end + 1;
+    }
+    matches
+}
+
 /// A context object representing a query to a particular commit in a repo.
 impl ChangesetContext {
     /// Construct a new `MononokeChangeset`.  The changeset must exist
@@ -173,6 +210,7 @@ impl ChangesetContext {
         let root_skeleton_manifest_id = LazyShared::new_empty();
         let root_deleted_manifest_v2_id = LazyShared::new_empty();
         let root_basename_suffix_skeleton_manifest = LazyShared::new_empty();
+        let root_trigram_index_id = LazyShared::new_empty();
         Self {
             repo,
             id,
@@ -183,6 +221,7 @@ impl ChangesetContext {
             root_skeleton_manifest_id,
             root_deleted_manifest_v2_id,
             root_basename_suffix_skeleton_manifest,
+            root_trigram_index_id,
             mutable_history: None,
         }
     }
@@ -320,6 +359,12 @@ impl ChangesetContext {
             .await
     }
 
+    pub(crate) async fn root_trigram_index_id(&self) -> Result<RootTrigramIndexId, MononokeError> {
+        self.root_trigram_index_id
+            .get_or_init(|| self.derive::<RootTrigramIndexId>())
+            .await
+    }
+
     pub(crate) async fn root_skeleton_manifest_id(
         &self,
     ) -> Result<RootSkeletonManifestId, MononokeError> {
@@ -1308,6 +1353,120 @@ impl ChangesetContext {
             .map_err(MononokeError::from))
     }
 
+    /// Search the contents of the files in this changeset for lines that
+    /// contain `query`, like `grep -F`.  If `prefixes` is not None, only
+    /// files under one of the prefixes are searched.  Matches are returned
+    /// in path order.
+    ///
+    /// Candidate files are found with the trigram index, so `query` must be
+    /// at least three bytes long.  Binary and very large files are not
+    /// indexed, so never match.
+    pub async fn search_content(
+        &self,
+        query: &str,
+        prefixes: Option<Vec<MononokePath>>,
+        case_sensitive: bool,
+    ) -> Result<impl Stream<Item = Result<ContentSearchMatch, MononokeError>>, MononokeError> {
+        const CONCURRENCY: usize = 100;
+        let trigrams = query_trigrams(query.as_bytes());
+        if trigrams.is_empty() {
+            return Err(MononokeError::InvalidRequest(format!(
+                "content search query must be at least 3 bytes long: {:?}",
+                query
+            )));
+        }
+        let ctx = self.ctx().clone();
+        let blobstore = self.repo().blob_repo().get_blobstore();
+        let index = self
+            .root_trigram_index_id()
+            .await?
+            .into_trigram_index_id()
+            .load(&ctx, &blobstore)
+            .await?;
+
+        // Every trigram of the query must be in a matching file, so start
+        // with the files containing the rarest trigram and check the others
+        // only for those.
+        let postings = stream::iter(trigrams)
+            .map(|trigram| {
+                let (ctx, blobstore, index) = (&ctx, &blobstore, &index);
+                async move { index.lookup_trigram(ctx, blobstore, &trigram).await }
+            })
+            .buffered(CONCURRENCY)
+            .try_collect::<Vec<_>>()
+            .await?;
+        let mut postings = match postings.into_iter().collect::<Option<Vec<_>>>() {
+            Some(postings) => postings,
+            None => return Ok(stream::empty().left_stream()),
+        };
+        postings.sort_by_key(|postings| postings.file_count);
+        let mut postings = postings.into_iter();
+        let rarest = postings.next().expect("query has at least one trigram");
+        let prefixes = prefixes.map(|prefixes| {
+            prefixes
+                .into_iter()
+                .map(MononokePath::into_mpath)
+                .collect::<Vec<_>>()
+        });
+        let mut candidates = rarest
+            .into_files(&ctx, &blobstore)
+            .try_filter(|(path, _)| {
+                future::ready(match &prefixes {
+                    Some(prefixes) => prefixes
+                        .iter()
+                        .any(|prefix| MPath::is_prefix_of_opt(prefix.as_ref(), path)),
+                    None => true,
+                })
+            })
+            .map_ok(|(path, file)| (path, file.content_id))
+            .try_collect::<BTreeMap<MPath, ContentId>>()
+            .await?;
+        for postings in postings {
+            if candidates.is_empty() {
+                break;
+            }
+            let files = postings.files.load(&ctx, &blobstore).await?;
+            let (ctx, blobstore, files) = (&ctx, &blobstore, &files);
+            candidates = stream::iter(candidates)
+                .map(|(path, content_id)| async move {
+                    let found = files.lookup(ctx, blobstore, &path.to_vec()).await?;
+                    anyhow::Ok(found.map(|_| (path, content_id)))
+                })
+                .buffered(CONCURRENCY)
+                .try_filter_map(|candidate| async move { Ok(candidate) })
+                .try_collect()
+                .await?;
+        }
+
+        let query = if case_sensitive {
+            query.as_bytes().to_vec()
+        } else {
+            query.as_bytes().to_ascii_lowercase()
+        };
+        Ok(stream::iter(candidates)
+            .map(move |(path, content_id)| {
+                cloned!(ctx, blobstore, query);
+                async move {
+                    let content = filestore::fetch_concat(&blobstore, &ctx, content_id).await?;
+                    let path = MononokePath::new(Some(path));
+                    let matches = matching_lines(&content, &query, case_sensitive)
+                        .into_iter()
+                        .map(move |(line_number, line)| {
+                            Ok::<_, MononokeError>(ContentSearchMatch {
+                                path: path.clone(),
+                                line_number,
+                                line,
+                            })
+                        });
+                    anyhow::Ok(stream::iter(matches))
+                }
+            })
+            .buffered(CONCURRENCY)
+            .map_err(MononokeError::from)
+            .try_flatten()
+            .right_stream())
+    }
+
     /// Returns a stream of `ChangesetContext` for the history of the repository from this commit.
     pub async fn history(
         &self,
@@ -47,6 +47,7 @@ pub use crate::changeset::ChangesetContext;
 pub use crate::changeset::ChangesetDiffItem;
 pub use crate::changeset::ChangesetFileOrdering;
 pub use crate::changeset::ChangesetHistoryOptions;
+pub use crate::changeset::ContentSearchMatch;
 pub use crate::changeset::Generation;
 pub use crate::changeset_path::unified_diff;
 pub use crate::changeset_path::ChangesetPathContentContext;
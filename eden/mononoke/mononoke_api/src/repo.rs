@@ -85,6 +85,7 @@ use metaconfig_types::RepoConfig;
 use metaconfig_types::SourceControlServiceParams;
 use mononoke_api_types::InnerRepo;
 use mononoke_types::hash::GitSha1;
+use mononoke_types::hash::GitSha256;
 use mononoke_types::hash::Sha1;
 use mononoke_types::hash::Sha256;
 use mononoke_types::Generation;
@@ -1415,6 +1416,14 @@ impl RepoContext {
         FileContext::new_check_exists(self.clone(), FetchKey::Aliased(Alias::GitSha1(hash))).await
     }
 
+    /// Get a File by content git-sha-256.  Returns `None` if the file doesn't exist.
+    pub async fn file_by_content_gitsha256(
+        &self,
+        hash: GitSha256,
+    ) -> Result<Option<FileContext>, MononokeError> {
+        FileContext::new_check_exists(self.clone(), FetchKey::Aliased(Alias::GitSha256(hash))).await
+    }
+
     fn get_target_repo_and_lca_hint(
         &self,
     ) -> (Target<BlobRepo>, Target<Arc<dyn LeastCommonAncestorsHint>>) {
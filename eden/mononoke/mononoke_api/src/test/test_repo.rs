@@ -33,7 +33,9 @@ use maplit::hashmap;
 use metaconfig_types::CommitSyncConfigVersion;
 use metaconfig_types::DefaultSmallToLargeCommitSyncPathAction;
 use mononoke_types::hash::GitSha1;
+use mononoke_types::hash::GitSha256;
 use mononoke_types::hash::RichGitSha1;
+use mononoke_types::hash::RichGitSha256;
 use mononoke_types::hash::Sha1;
 use mononoke_types::hash::Sha256;
 use mononoke_types::MPath;
@@ -52,6 +54,7 @@ use crate::ChangesetIdPrefix;
 use crate::ChangesetPrefixSpecifier;
 use crate::ChangesetSpecifier;
 use crate::ChangesetSpecifierPrefixResolution;
+use crate::ContentSearchMatch;
 use crate::CoreContext;
 use crate::FileId;
 use crate::FileMetadata;
@@ -760,6 +763,85 @@ async fn commit_find_files_with_bssm(fb: FacebookInit) {
         .unwrap();
 }
 
+#[fbinit::test]
+async fn commit_search_content(fb: FacebookInit) -> Result<(), Error> {
+    let ctx = CoreContext::test_mock(fb);
+    let mononoke = Mononoke::new_test(
+        ctx.clone(),
+        vec![("test".to_string(), ManyFilesDirs::getrepo(fb).await)],
+    )
+    .await?;
+    let repo = mononoke
+        .repo(ctx, "test")
+        .await?
+        .expect("repo exists")
+        .build()
+        .await?;
+    let hash = "b0d1bf77898839595ee0f0cba673dd6e3be9dadaaa78bc6dd2dea97ca6bee77e";
+    let cs_id = ChangesetId::from_str(hash)?;
+    let cs = repo.changeset(cs_id).await?.expect("changeset exists");
+
+    let paths = |matches: Vec<ContentSearchMatch>| {
+        matches
+            .into_iter()
+            .map(|m| {
+                assert_eq!(m.line_number, 1);
+                m.path.to_string()
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let matches: Vec<_> = cs
+        .search_content("content", None, true)
+        .await?
+        .try_collect()
+        .await?;
+    assert_eq!(
+        paths(matches),
+        vec![
+            "dir1/file_1_in_dir1",
+            "dir1/file_2_in_dir1",
+            "dir1/subdir1/file_1",
+            "dir1/subdir1/subsubdir1/file_1",
+            "dir1/subdir1/subsubdir2/file_1",
+            "dir1/subdir1/subsubdir2/file_2",
+            "dir2/file_1_in_dir2",
+        ]
+    );
+
+    let matches: Vec<_> = cs
+        .search_content("content", Some(vec![MononokePath::try_from("dir2")?]), true)
+        .await?
+        .try_collect()
+        .await?;
+    assert_eq!(paths(matches), vec!["dir2/file_1_in_dir2"]);
+
+    let matches: Vec<_> = cs
+        .search_content("CONTENT1", None, false)
+        .await?
+        .try_collect()
+        .await?;
+    assert_eq!(
+        matches,
+        vec![ContentSearchMatch {
+            path: MononokePath::try_from("dir1/file_1_in_dir1")?,
+            line_number: 1,
+            line: Bytes::from("content1"),
+        }]
+    );
+
+    let matches: Vec<_> = cs
+        .search_content("CONTENT1", None, true)
+        .await?
+        .try_collect()
+        .await?;
+    assert!(matches.is_empty());
+
+    assert!(cs.search_content("co", None, true).await.is_err());
+
+    Ok(())
+}
+
 #[fbinit::test]
 async fn commit_path_exists_and_type(fb: FacebookInit) -> Result<(), Error> {
     let ctx = CoreContext::test_mock(fb);
@@ -957,6 +1039,13 @@ async fn file_metadata(fb: FacebookInit) -> Result<(), Error> {
             "blob",
             9,
         ),
+        git_sha256: Some(RichGitSha256::from_sha256(
+            GitSha256::from_str(
+                "a1877c76445e320ef3762ddeb5845a4e9d799f7aa0c1312da032ba2d3e54ce12",
+            )?,
+            "blob",
+            9,
+        )),
     };
 
     // Get file by changeset path.
@@ -997,6 +1086,16 @@ async fn file_metadata(fb: FacebookInit) -> Result<(), Error> {
     let metadata = file.metadata().await?;
     assert_eq!(metadata, expected_metadata);
 
+    // Get file by content git sha256.
+    let file = repo
+        .file_by_content_gitsha256(GitSha256::from_str(
+            "a1877c76445e320ef3762ddeb5845a4e9d799f7aa0c1312da032ba2d3e54ce12",
+        )?)
+        .await?
+        .expect("file exists");
+    let metadata = file.metadata().await?;
+    assert_eq!(metadata, expected_metadata);
+
     Ok(())
 }
 
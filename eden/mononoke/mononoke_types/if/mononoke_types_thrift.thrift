@@ -44,6 +44,7 @@ typedef IdType FsnodeId (rust.newtype)
 typedef IdType SkeletonManifestId (rust.newtype)
 typedef IdType MPathHash (rust.newtype)
 typedef IdType BasenameSuffixSkeletonManifestId (rust.newtype)
+typedef IdType TrigramIndexId (rust.newtype)
 
 typedef IdType ContentMetadataId (rust.newtype)
 typedef IdType ContentMetadataV2Id (rust.newtype)
@@ -62,6 +63,10 @@ typedef binary GitSha1 (
   rust.newtype,
   rust.type = "smallvec::SmallVec<[u8; 20]>",
 )
+typedef binary GitSha256 (
+  rust.newtype,
+  rust.type = "smallvec::SmallVec<[u8; 32]>",
+)
 
 // A path in a repo is stored as a list of elements. This is so that the sort
 // order of paths is the same as that of a tree traversal, so that deltas on
@@ -191,6 +196,9 @@ struct ContentMetadata {
   4: optional Sha256 sha256;
   // always object type "blob"
   5: optional GitSha1 git_sha1;
+  // Hash of the content in a SHA-256 object format Git repository. Always
+  // object type "blob". Absent for content stored before this was computed.
+  6: optional GitSha256 git_sha256;
 } (rust.exhaustive)
 
 // Metadata and properties associated with a file.
@@ -417,6 +425,30 @@ struct BasenameSuffixSkeletonManifest {
   1: ShardedMapNode subentries;
 } (rust.exhaustive)
 
+// A file whose contents are covered by the trigram index.
+struct TrigramIndexFile {
+  1: ContentId content_id;
+} (rust.exhaustive)
+
+// The files that contain one trigram.
+struct TrigramPostings {
+  // Id of the root of a map of path -> TrigramIndexFile
+  1: ShardedMapNodeId files;
+  // Number of entries in files, so searches can start from the rarest trigram
+  2: i64 file_count;
+} (rust.exhaustive)
+
+// Trigram index of the file contents of a commit, used for code search.
+// Trigrams are three consecutive bytes of a file with ASCII letters
+// lowercased, so the index can serve both case-sensitive and insensitive
+// searches. Binary and very large files are not indexed.
+struct TrigramIndex {
+  // Map of path -> TrigramIndexFile for every indexed file
+  1: ShardedMapNode files;
+  // Map of trigram -> TrigramPostings
+  2: ShardedMapNode trigrams;
+} (rust.exhaustive)
+
 struct FsnodeFile {
   1: ContentId content_id;
   2: FileType file_type;
@@ -30,6 +30,7 @@ use crate::typed_hash::ManifestUnodeId;
 use crate::typed_hash::RawBundle2Id;
 use crate::typed_hash::RedactionKeyListId;
 use crate::typed_hash::SkeletonManifestId;
+use crate::typed_hash::TrigramIndexId;
 
 /// A serialized blob in memory.
 #[derive(Clone)]
@@ -92,6 +93,7 @@ pub type ContentMetadataV2Blob = Blob<ContentMetadataV2Id>;
 pub type FastlogBatchBlob = Blob<FastlogBatchId>;
 pub type RedactionKeyListBlob = Blob<RedactionKeyListId>;
 pub type BasenameSuffixSkeletonManifestBlob = Blob<BasenameSuffixSkeletonManifestId>;
+pub type TrigramIndexBlob = Blob<TrigramIndexId>;
 
 impl<Id: BlobstoreKey> From<Blob<Id>> for BlobstoreBytes {
     #[inline]
@@ -68,6 +68,9 @@ pub struct ContentMetadata {
     pub sha1: hash::Sha1,
     pub sha256: hash::Sha256,
     pub git_sha1: hash::RichGitSha1,
+    /// Only present for content whose metadata was computed after Git
+    /// SHA-256 support was added to the filestore.
+    pub git_sha256: Option<hash::RichGitSha256>,
 }
 
 impl ContentMetadata {
@@ -85,6 +88,12 @@ impl ContentMetadata {
                 "blob",
                 total_size,
             )?,
+            git_sha256: cab
+                .git_sha256
+                .map(|git_sha256| {
+                    hash::RichGitSha256::from_bytes(&git_sha256.0, "blob", total_size)
+                })
+                .transpose()?,
         };
 
         Ok(res)
@@ -97,6 +106,7 @@ impl ContentMetadata {
             sha1: Some(self.sha1.into_thrift()),
             git_sha1: Some(self.git_sha1.into_thrift()),
             sha256: Some(self.sha256.into_thrift()),
+            git_sha256: self.git_sha256.map(|git_sha256| git_sha256.into_thrift()),
         }
     }
 }
@@ -112,6 +122,8 @@ impl Arbitrary for ContentMetadata {
             sha1: hash::Sha1::arbitrary(g),
             sha256: hash::Sha256::arbitrary(g),
             git_sha1: hash::RichGitSha1::from_sha1(hash::GitSha1::arbitrary(g), "blob", total_size),
+            git_sha256: Option::<hash::GitSha256>::arbitrary(g)
+                .map(|git_sha256| hash::RichGitSha256::from_sha256(git_sha256, "blob", total_size)),
         }
     }
 }
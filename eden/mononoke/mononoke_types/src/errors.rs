@@ -19,6 +19,8 @@ pub enum ErrorKind {
     InvalidSha256Input(String),
     #[error("invalid git sha1 input: {0}")]
     InvalidGitSha1Input(String),
+    #[error("invalid git sha256 input: {0}")]
+    InvalidGitSha256Input(String),
     #[error("invalid path '{0}': {1}")]
     InvalidPath(String, String),
     #[error("invalid Mononoke path '{0}': {1}")]
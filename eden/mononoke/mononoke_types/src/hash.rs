@@ -455,6 +455,7 @@ macro_rules! impl_hash {
 impl_hash!(Sha256, 32, InvalidSha256Input);
 impl_hash!(Sha1, 20, InvalidSha1Input);
 impl_hash!(GitSha1, 20, InvalidGitSha1Input);
+impl_hash!(GitSha256, 32, InvalidGitSha256Input);
 
 /// Git-style content blob hashes. Same as SHA-1 but with "<type> NNNN\0" appended to the front,
 /// where <type> is the object type (blob, tree, etc), and NNNN is the blob size as a decimal
@@ -529,6 +530,77 @@ impl Display for RichGitSha1 {
     }
 }
 
+/// Git-style content hashes for repositories using the SHA-256 object format. These are
+/// computed the same way as `RichGitSha1`, with the "<type> NNNN\0" prefix, but using SHA-256.
+#[derive(Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[derive(Serialize, Deserialize)]
+pub struct RichGitSha256 {
+    sha256: GitSha256,
+    ty: &'static str,
+    size: u64,
+}
+
+impl RichGitSha256 {
+    pub fn from_bytes(bytes: impl AsRef<[u8]>, ty: &'static str, size: u64) -> Result<Self> {
+        Ok(Self::from_sha256(GitSha256::from_bytes(bytes)?, ty, size))
+    }
+
+    pub const fn from_byte_array(bytes: [u8; 32], ty: &'static str, size: u64) -> Self {
+        Self::from_sha256(GitSha256::from_byte_array(bytes), ty, size)
+    }
+
+    pub const fn from_sha256(sha256: GitSha256, ty: &'static str, size: u64) -> Self {
+        RichGitSha256 { sha256, ty, size }
+    }
+
+    pub fn sha256(&self) -> GitSha256 {
+        self.sha256
+    }
+
+    pub fn ty(&self) -> &'static str {
+        self.ty
+    }
+
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    pub fn to_hex(&self) -> AsciiString {
+        self.sha256.to_hex()
+    }
+
+    /// Return the Git prefix bytes
+    pub fn prefix(&self) -> Vec<u8> {
+        format!("{} {}\0", self.ty, self.size).into_bytes()
+    }
+
+    pub fn into_thrift(self) -> thrift::GitSha256 {
+        thrift::GitSha256(self.sha256.0.into())
+    }
+}
+
+impl AsRef<[u8]> for RichGitSha256 {
+    fn as_ref(&self) -> &[u8] {
+        self.sha256.as_ref()
+    }
+}
+
+impl Debug for RichGitSha256 {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("GitSha256")
+            .field("sha256", &self.sha256.to_hex())
+            .field("ty", &self.ty)
+            .field("size", &self.size)
+            .finish()
+    }
+}
+
+impl Display for RichGitSha256 {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        Display::fmt(&self.to_hex(), fmt)
+    }
+}
+
 impl From<GitSha1> for EdenapiGitSha1 {
     fn from(v: GitSha1) -> Self {
         EdenapiGitSha1::from(v.0)
@@ -551,6 +623,16 @@ impl Arbitrary for GitSha1 {
     }
 }
 
+impl Arbitrary for GitSha256 {
+    fn arbitrary(g: &mut Gen) -> Self {
+        let mut bytes = [0; 32];
+        for b in bytes.iter_mut() {
+            *b = u8::arbitrary(g);
+        }
+        GitSha256(bytes)
+    }
+}
+
 impl From<Sha1> for EdenapiSha1 {
     fn from(v: Sha1) -> Self {
         EdenapiSha1::from(v.0)
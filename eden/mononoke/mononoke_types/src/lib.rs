@@ -35,6 +35,7 @@ pub mod skeleton_manifest;
 pub mod sql_types;
 pub mod svnrev;
 pub mod thrift_convert;
+pub mod trigram_index;
 pub mod typed_hash;
 pub mod unode;
 
@@ -94,6 +95,7 @@ pub use typed_hash::ManifestUnodeId;
 pub use typed_hash::MononokeId;
 pub use typed_hash::RawBundle2Id;
 pub use typed_hash::SkeletonManifestId;
+pub use typed_hash::TrigramIndexId;
 
 mod macros;
 
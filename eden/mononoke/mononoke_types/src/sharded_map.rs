@@ -25,9 +25,11 @@ use bytes::Bytes;
 use context::CoreContext;
 use derivative::Derivative;
 use futures::stream;
+use futures::stream::BoxStream;
 use futures::FutureExt;
 use futures::Stream;
 use futures::StreamExt;
+use futures::TryFutureExt;
 use futures::TryStreamExt;
 use itertools::Itertools;
 use nonzero_ext::nonzero;
@@ -532,6 +534,83 @@ impl<Value: MapValue> ShardedMapNode<Value> {
         )
     }
 
+    /// Iterates through all values whose keys start with `prefix`, only
+    /// loading the blobs for that part of the map.
+    pub fn into_prefix_entries<'a>(
+        self,
+        ctx: &'a CoreContext,
+        blobstore: &'a impl Blobstore,
+        prefix: &'a [u8],
+    ) -> BoxStream<'a, Result<(SmallBinary, Value)>> {
+        async move {
+            let mut node = self;
+            // Key bytes leading to `node`, and the part of `prefix` they don't cover
+            let mut consumed = SmallBinary::new();
+            let mut remaining = prefix;
+            loop {
+                match node {
+                    // Case 1. Filter values by the rest of the prefix
+                    Self::Terminal { values } => {
+                        let entries = values
+                            .into_iter()
+                            .filter(|(key, _)| key.starts_with(remaining))
+                            .map(|(key, value)| {
+                                let mut full_key = consumed.clone();
+                                full_key.extend(key);
+                                Ok((full_key, value))
+                            })
+                            .collect::<Vec<_>>();
+                        return Ok(stream::iter(entries).boxed());
+                    }
+                    Self::Intermediate {
+                        prefix: node_prefix,
+                        value,
+                        edges,
+                        size,
+                    } => {
+                        let rest = remaining.strip_prefix(node_prefix.as_slice());
+                        match rest.and_then(|rest| rest.split_first()) {
+                            // Case 2. Follow the edge for the next byte of the prefix
+                            Some((first, rest)) => {
+                                match edges.into_iter().find(|(byte, _)| byte == first) {
+                                    Some((_, edge)) => {
+                                        consumed.extend(node_prefix);
+                                        consumed.push(*first);
+                                        remaining = rest;
+                                        node = edge.load_child(ctx, blobstore).await?;
+                                    }
+                                    None => return Ok(stream::empty().boxed()),
+                                }
+                            }
+                            // Case 3. The prefix ends within this node, so
+                            // every key below it matches
+                            None if rest.is_some() || node_prefix.starts_with(remaining) => {
+                                let node = Self::Intermediate {
+                                    prefix: node_prefix,
+                                    value,
+                                    edges,
+                                    size,
+                                };
+                                return Ok(node
+                                    .into_entries(ctx, blobstore)
+                                    .map_ok(move |(key, value)| {
+                                        let mut full_key = consumed.clone();
+                                        full_key.extend(key);
+                                        (full_key, value)
+                                    })
+                                    .boxed());
+                            }
+                            // Case 4. The prefix diverges from this node's prefix
+                            None => return Ok(stream::empty().boxed()),
+                        }
+                    }
+                }
+            }
+        }
+        .try_flatten_stream()
+        .boxed()
+    }
+
     pub fn is_empty(&self) -> bool {
         match self {
             Self::Terminal { values } => values.is_empty(),
@@ -968,6 +1047,39 @@ mod test {
         Ok(())
     }
 
+    #[fbinit::test]
+    async fn into_prefix_entries_test(fb: FacebookInit) -> Result<()> {
+        let ctx = CoreContext::test_mock(fb);
+        let blobstore = Memblob::default();
+
+        let map = MapHelper(example_map(), ctx, blobstore);
+        let prefix_entries = |prefix: &'static str| {
+            map.0
+                .clone()
+                .into_prefix_entries(&map.1, &map.2, prefix.as_bytes())
+                .and_then(|(k, v)| async move { Ok((String::from_utf8(k.to_vec())?, v.0)) })
+                .try_collect::<Vec<_>>()
+        };
+        let expected = |prefix: &str| {
+            EXAMPLE_ENTRIES
+                .iter()
+                .filter(|(k, _)| k.starts_with(prefix))
+                .map(|(k, v)| (String::from(*k), *v))
+                .collect::<Vec<_>>()
+        };
+        for prefix in [
+            "", "a", "ab", "aba", "abac", "abacak", "abal", "abx", "o", "omi", "omun", "x",
+        ] {
+            assert_eq!(
+                prefix_entries(prefix).await?,
+                expected(prefix),
+                "{}",
+                prefix
+            );
+        }
+        Ok(())
+    }
+
     async fn get_all_keys(
         ctx: &CoreContext,
         blobstore: &impl BlobstoreKeySource,
@@ -0,0 +1,360 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::sync::atomic::AtomicI64;
+use std::sync::atomic::Ordering;
+
+use anyhow::Result;
+use blobstore::Blobstore;
+use blobstore::Loadable;
+use blobstore::Storable;
+use bytes::Bytes;
+use context::CoreContext;
+use futures::stream;
+use futures::stream::BoxStream;
+use futures::stream::StreamExt;
+use futures::stream::TryStreamExt;
+
+use crate::blob::Blob;
+use crate::blob::BlobstoreValue;
+use crate::blob::TrigramIndexBlob;
+use crate::sharded_map::MapValue;
+use crate::sharded_map::ShardedMapNode;
+use crate::thrift;
+use crate::typed_hash::IdContext;
+use crate::typed_hash::ShardedMapNodeTrigramContext;
+use crate::typed_hash::ShardedMapNodeTrigramFileContext;
+use crate::typed_hash::ShardedMapNodeTrigramFileId;
+use crate::typed_hash::ShardedMapNodeTrigramId;
+use crate::typed_hash::TrigramIndexContext;
+use crate::typed_hash::TrigramIndexId;
+use crate::ContentId;
+use crate::MPath;
+use crate::ThriftConvert;
+
+/// Files larger than this are not indexed.
+pub const MAX_INDEXED_FILE_SIZE: u64 = 1024 * 1024;
+
+/// Number of posting lists updated at once.
+const TRIGRAM_CONCURRENCY: usize = 100;
+
+pub type Trigram = [u8; 3];
+
+fn fold(byte: u8) -> u8 {
+    byte.to_ascii_lowercase()
+}
+
+/// The trigrams in `content`, or None if it should not be indexed because it
+/// is binary or too large.
+pub fn content_trigrams(content: &[u8]) -> Option<BTreeSet<Trigram>> {
+    if content.len() as u64 > MAX_INDEXED_FILE_SIZE || content.contains(&0) {
+        return None;
+    }
+    Some(query_trigrams(content))
+}
+
+/// The trigrams any content matching `query` must contain.  Empty if the
+/// query is shorter than a trigram, in which case the index can't help.
+pub fn query_trigrams(query: &[u8]) -> BTreeSet<Trigram> {
+    query
+        .windows(3)
+        .map(|w| [fold(w[0]), fold(w[1]), fold(w[2])])
+        .collect()
+}
+
+/// A file whose contents are covered by the trigram index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TrigramIndexFile {
+    pub content_id: ContentId,
+}
+
+/// The files that contain one trigram.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TrigramPostings {
+    pub files: ShardedMapNodeTrigramFileId,
+    pub file_count: u64,
+}
+
+impl ThriftConvert for TrigramIndexFile {
+    const NAME: &'static str = "TrigramIndexFile";
+    type Thrift = thrift::TrigramIndexFile;
+
+    fn from_thrift(t: Self::Thrift) -> Result<Self> {
+        Ok(Self {
+            content_id: ContentId::from_thrift(t.content_id)?,
+        })
+    }
+
+    fn into_thrift(self) -> Self::Thrift {
+        thrift::TrigramIndexFile {
+            content_id: self.content_id.into_thrift(),
+        }
+    }
+}
+
+impl MapValue for TrigramIndexFile {
+    type Id = ShardedMapNodeTrigramFileId;
+    type Context = ShardedMapNodeTrigramFileContext;
+}
+
+impl ThriftConvert for TrigramPostings {
+    const NAME: &'static str = "TrigramPostings";
+    type Thrift = thrift::TrigramPostings;
+
+    fn from_thrift(t: Self::Thrift) -> Result<Self> {
+        Ok(Self {
+            files: ShardedMapNodeTrigramFileId::from_thrift(t.files)?,
+            file_count: t.file_count.try_into()?,
+        })
+    }
+
+    fn into_thrift(self) -> Self::Thrift {
+        thrift::TrigramPostings {
+            files: self.files.into_thrift(),
+            file_count: self.file_count.try_into().unwrap_or(i64::MAX),
+        }
+    }
+}
+
+impl MapValue for TrigramPostings {
+    type Id = ShardedMapNodeTrigramId;
+    type Context = ShardedMapNodeTrigramContext;
+}
+
+impl TrigramPostings {
+    /// Iterate over the files containing this trigram.
+    pub fn into_files<'a>(
+        self,
+        ctx: &'a CoreContext,
+        blobstore: &'a impl Blobstore,
+    ) -> BoxStream<'a, Result<(MPath, TrigramIndexFile)>> {
+        async move {
+            let files = self.files.load(ctx, blobstore).await?;
+            anyhow::Ok(
+                files
+                    .into_entries(ctx, blobstore)
+                    .and_then(|(path, file)| async move { Ok((MPath::new(path)?, file)) }),
+            )
+        }
+        .try_flatten_stream()
+        .boxed()
+    }
+}
+
+/// Changes to the index for one commit.
+#[derive(Debug, Default)]
+pub struct TrigramIndexChanges {
+    /// Files to add, replace or remove from the index.
+    pub files: BTreeMap<MPath, Option<TrigramIndexFile>>,
+    /// For each trigram, files to add to, replace in or remove from its posting list.
+    pub trigrams: BTreeMap<Trigram, BTreeMap<MPath, Option<TrigramIndexFile>>>,
+}
+
+/// Index of which files in a commit contain which trigrams.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct TrigramIndex {
+    files: ShardedMapNode<TrigramIndexFile>,
+    trigrams: ShardedMapNode<TrigramPostings>,
+}
+
+impl ThriftConvert for TrigramIndex {
+    const NAME: &'static str = "TrigramIndex";
+    type Thrift = thrift::TrigramIndex;
+
+    fn from_thrift(t: Self::Thrift) -> Result<Self> {
+        Ok(Self {
+            files: ShardedMapNode::from_thrift(t.files)?,
+            trigrams: ShardedMapNode::from_thrift(t.trigrams)?,
+        })
+    }
+
+    fn into_thrift(self) -> Self::Thrift {
+        thrift::TrigramIndex {
+            files: self.files.into_thrift(),
+            trigrams: self.trigrams.into_thrift(),
+        }
+    }
+}
+
+fn path_key(path: &MPath) -> Bytes {
+    Bytes::from(path.to_vec())
+}
+
+impl TrigramIndex {
+    pub fn empty() -> Self {
+        Self {
+            files: ShardedMapNode::default(),
+            trigrams: ShardedMapNode::default(),
+        }
+    }
+
+    /// Look up an indexed file by path.
+    pub async fn lookup_file(
+        &self,
+        ctx: &CoreContext,
+        blobstore: &impl Blobstore,
+        path: &MPath,
+    ) -> Result<Option<TrigramIndexFile>> {
+        self.files.lookup(ctx, blobstore, &path.to_vec()).await
+    }
+
+    /// Iterate over the indexed files in the directory `path`, recursively.
+    pub fn into_files_in_dir<'a>(
+        self,
+        ctx: &'a CoreContext,
+        blobstore: &'a impl Blobstore,
+        path: &MPath,
+    ) -> BoxStream<'a, Result<(MPath, TrigramIndexFile)>> {
+        let mut prefix = path.to_vec();
+        prefix.push(b'/');
+        async move {
+            let files = self
+                .files
+                .into_prefix_entries(ctx, blobstore, &prefix)
+                .and_then(|(path, file)| async move { Ok((MPath::new(path)?, file)) })
+                .try_collect::<Vec<_>>()
+                .await?;
+            anyhow::Ok(stream::iter(files.into_iter().map(Ok)))
+        }
+        .try_flatten_stream()
+        .boxed()
+    }
+
+    /// Iterate over all indexed files.
+    pub fn into_files<'a>(
+        self,
+        ctx: &'a CoreContext,
+        blobstore: &'a impl Blobstore,
+    ) -> BoxStream<'a, Result<(MPath, TrigramIndexFile)>> {
+        self.files
+            .into_entries(ctx, blobstore)
+            .and_then(|(path, file)| async move { Ok((MPath::new(path)?, file)) })
+            .boxed()
+    }
+
+    /// Look up the files containing a trigram.
+    pub async fn lookup_trigram(
+        &self,
+        ctx: &CoreContext,
+        blobstore: &impl Blobstore,
+        trigram: &Trigram,
+    ) -> Result<Option<TrigramPostings>> {
+        self.trigrams.lookup(ctx, blobstore, trigram).await
+    }
+
+    async fn update_postings(
+        &self,
+        ctx: &CoreContext,
+        blobstore: &impl Blobstore,
+        trigram: Trigram,
+        files: BTreeMap<MPath, Option<TrigramIndexFile>>,
+    ) -> Result<(Bytes, Option<TrigramPostings>)> {
+        let (root, file_count) = match self.lookup_trigram(ctx, blobstore, &trigram).await? {
+            Some(postings) => (
+                postings.files.load(ctx, blobstore).await?,
+                postings.file_count as i64,
+            ),
+            None => (ShardedMapNode::default(), 0),
+        };
+        let count_diff = AtomicI64::new(0);
+        let root = root
+            .update(
+                ctx,
+                blobstore,
+                files
+                    .into_iter()
+                    .inspect(|(_, file)| {
+                        if file.is_some() {
+                            count_diff.fetch_add(1, Ordering::Relaxed);
+                        }
+                    })
+                    .map(|(path, file)| (path_key(&path), file))
+                    .collect(),
+                |_deleted| {
+                    count_diff.fetch_sub(1, Ordering::Relaxed);
+                },
+            )
+            .await?;
+        let file_count = file_count + count_diff.load(Ordering::Relaxed);
+        let postings = if root.is_empty() {
+            None
+        } else {
+            Some(TrigramPostings {
+                files: root.into_blob().store(ctx, blobstore).await?,
+                file_count: file_count as u64,
+            })
+        };
+        Ok((Bytes::copy_from_slice(&trigram), postings))
+    }
+
+    /// Apply the changes for one commit to the index.
+    pub async fn update(
+        self,
+        ctx: &CoreContext,
+        blobstore: &impl Blobstore,
+        changes: TrigramIndexChanges,
+    ) -> Result<Self> {
+        let trigram_updates = stream::iter(changes.trigrams)
+            .map(|(trigram, files)| self.update_postings(ctx, blobstore, trigram, files))
+            .buffer_unordered(TRIGRAM_CONCURRENCY)
+            .try_collect::<BTreeMap<_, _>>()
+            .await?;
+        let trigrams = self
+            .trigrams
+            .update(ctx, blobstore, trigram_updates, |_| ())
+            .await?;
+        let files = self
+            .files
+            .update(
+                ctx,
+                blobstore,
+                changes
+                    .files
+                    .into_iter()
+                    .map(|(path, file)| (path_key(&path), file))
+                    .collect(),
+                |_| (),
+            )
+            .await?;
+        Ok(Self { files, trigrams })
+    }
+}
+
+impl BlobstoreValue for TrigramIndex {
+    type Key = TrigramIndexId;
+
+    fn into_blob(self) -> TrigramIndexBlob {
+        let data = self.into_bytes();
+        let id = TrigramIndexContext::id_from_data(&data);
+        Blob::new(id, data)
+    }
+
+    fn from_blob(blob: Blob<Self::Key>) -> Result<Self> {
+        Self::from_bytes(blob.data())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn content_trigrams_test() {
+        assert_eq!(
+            content_trigrams(b"Abcd"),
+            Some([*b"abc", *b"bcd"].into_iter().collect())
+        );
+        assert_eq!(content_trigrams(b"ab"), Some(BTreeSet::new()));
+        assert_eq!(content_trigrams(b"abc\0def"), None);
+        assert_eq!(
+            content_trigrams(&vec![b'a'; MAX_INDEXED_FILE_SIZE as usize + 1]),
+            None
+        );
+    }
+}
@@ -43,6 +43,9 @@ use crate::redaction_key_list::RedactionKeyList;
 use crate::sharded_map::ShardedMapNode;
 use crate::skeleton_manifest::SkeletonManifest;
 use crate::thrift;
+use crate::trigram_index::TrigramIndex;
+use crate::trigram_index::TrigramIndexFile;
+use crate::trigram_index::TrigramPostings;
 use crate::unode::FileUnode;
 use crate::unode::ManifestUnode;
 use crate::ThriftConvert;
@@ -142,6 +145,18 @@ pub struct ShardedMapNodeBSSMId(Blake2);
 #[derive(Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Debug, Hash)]
 pub struct BasenameSuffixSkeletonManifestId(Blake2);
 
+/// An identifier for a sharded map node used in the trigram index file map
+#[derive(Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Debug, Hash)]
+pub struct ShardedMapNodeTrigramFileId(Blake2);
+
+/// An identifier for a sharded map node used in the trigram index trigram map
+#[derive(Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Debug, Hash)]
+pub struct ShardedMapNodeTrigramId(Blake2);
+
+/// An identifier for a trigram index
+#[derive(Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Debug, Hash)]
+pub struct TrigramIndexId(Blake2);
+
 /// An identifier for an fsnode
 #[derive(Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Debug, Hash)]
 pub struct FsnodeId(Blake2);
@@ -569,6 +584,30 @@ impl_typed_hash! {
     context_key => "bssm.mapnode",
 }
 
+impl_typed_hash! {
+    hash_type => TrigramIndexId,
+    thrift_hash_type => thrift::TrigramIndexId,
+    value_type => TrigramIndex,
+    context_type => TrigramIndexContext,
+    context_key => "trigramindex",
+}
+
+impl_typed_hash! {
+    hash_type => ShardedMapNodeTrigramFileId,
+    thrift_hash_type => thrift::ShardedMapNodeId,
+    value_type => ShardedMapNode<TrigramIndexFile>,
+    context_type => ShardedMapNodeTrigramFileContext,
+    context_key => "trigramindex.filemapnode",
+}
+
+impl_typed_hash! {
+    hash_type => ShardedMapNodeTrigramId,
+    thrift_hash_type => thrift::ShardedMapNodeId,
+    value_type => ShardedMapNode<TrigramPostings>,
+    context_type => ShardedMapNodeTrigramContext,
+    context_key => "trigramindex.mapnode",
+}
+
 impl_typed_hash! {
     hash_type => FsnodeId,
     thrift_hash_type => thrift::FsnodeId,
@@ -756,6 +795,18 @@ mod test {
         let id = ShardedMapNodeBSSMId::from_byte_array([1; 32]);
         assert_eq!(id.blobstore_key(), format!("bssm.mapnode.blake2.{}", id));
 
+        let id = ShardedMapNodeTrigramFileId::from_byte_array([1; 32]);
+        assert_eq!(
+            id.blobstore_key(),
+            format!("trigramindex.filemapnode.blake2.{}", id)
+        );
+
+        let id = ShardedMapNodeTrigramId::from_byte_array([1; 32]);
+        assert_eq!(
+            id.blobstore_key(),
+            format!("trigramindex.mapnode.blake2.{}", id)
+        );
+
         let id = ContentChunkId::from_byte_array([1; 32]);
         assert_eq!(id.blobstore_key(), format!("chunk.blake2.{}", id));
 
@@ -777,6 +828,9 @@ mod test {
         let id = BasenameSuffixSkeletonManifestId::from_byte_array([1; 32]);
         assert_eq!(id.blobstore_key(), format!("bssm.blake2.{}", id),);
 
+        let id = TrigramIndexId::from_byte_array([1; 32]);
+        assert_eq!(id.blobstore_key(), format!("trigramindex.blake2.{}", id));
+
         let id = FsnodeId::from_byte_array([1; 32]);
         assert_eq!(id.blobstore_key(), format!("fsnode.blake2.{}", id));
 
@@ -830,6 +884,16 @@ mod test {
         let deserialized = serde_json::from_str(&serialized).unwrap();
         assert_eq!(id, deserialized);
 
+        let id = ShardedMapNodeTrigramFileId::from_byte_array([1; 32]);
+        let serialized = serde_json::to_string(&id).unwrap();
+        let deserialized = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(id, deserialized);
+
+        let id = ShardedMapNodeTrigramId::from_byte_array([1; 32]);
+        let serialized = serde_json::to_string(&id).unwrap();
+        let deserialized = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(id, deserialized);
+
         let id = ContentChunkId::from_byte_array([1; 32]);
         let serialized = serde_json::to_string(&id).unwrap();
         let deserialized = serde_json::from_str(&serialized).unwrap();
@@ -860,6 +924,11 @@ mod test {
         let deserialized = serde_json::from_str(&serialized).unwrap();
         assert_eq!(id, deserialized);
 
+        let id = TrigramIndexId::from_byte_array([1; 32]);
+        let serialized = serde_json::to_string(&id).unwrap();
+        let deserialized = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(id, deserialized);
+
         let id = FsnodeId::from_byte_array([1; 32]);
         let serialized = serde_json::to_string(&id).unwrap();
         let deserialized = serde_json::from_str(&serialized).unwrap();
@@ -68,6 +68,7 @@ use changeset_fetcher::ChangesetFetcherArc;
 use changesets::ChangesetsRef;
 use context::CoreContext;
 use derived_data_filenodes::FilenodesOnlyPublic;
+use filestore::FilestoreConfigRef;
 use futures::compat::Stream01CompatExt;
 use futures::future;
 use futures::future::try_join;
@@ -109,6 +110,11 @@ use thiserror::Error;
 use trait_alias::trait_alias;
 use tunables::tunables;
 
+use crate::merge::merge_conflicting_files;
+use crate::merge::MergedFiles;
+
+mod merge;
+
 define_stats! {
     prefix = "mononoke.pushrebase";
     // Clowntown: This is actually nanoseconds (ns), not microseconds (us)
@@ -241,6 +247,7 @@ pub trait Repo = BonsaiHgMappingRef
     + BookmarksRef
     + ChangesetsRef
     + ChangesetFetcherArc
+    + FilestoreConfigRef
     + RepoBlobstoreArc
     + RepoDerivedDataRef
     + RepoIdentityRef
@@ -326,6 +333,10 @@ async fn rebase_in_loop(
     let should_log = config.monitoring_bookmark.as_deref() == Some(onto_bookmark.as_str());
     let mut latest_rebase_attempt = root;
     let mut pushrebase_distance = PushrebaseDistance(0);
+    // Files changed on both sides that are merged on the server. Each attempt
+    // only checks new server commits for conflicts, but the files have to be
+    // merged again with every new bookmark value.
+    let mut merge_paths = HashSet::new();
 
     let repo_args = (repo.repo_identity().name().to_string(),);
     for retry_num in 0..MAX_REBASE_ATTEMPTS {
@@ -373,7 +384,46 @@ async fn rebase_in_loop(
         .await?;
 
         // TODO: Avoid this clone
-        intersect_changed_files(server_cf, client_cf.clone())?;
+        match intersect_changed_files(server_cf, client_cf.clone()) {
+            Ok(()) => {}
+            Err(PushrebaseError::Conflicts(conflicts))
+                if config.merge_conflicting_files
+                    && conflicts
+                        .iter()
+                        .all(|conflict| conflict.left == conflict.right) =>
+            {
+                merge_paths.extend(conflicts.into_iter().map(|conflict| conflict.left));
+            }
+            Err(err) => return Err(err),
+        }
+
+        let merged_files = if merge_paths.is_empty() {
+            MergedFiles::new()
+        } else {
+            let merged_files = merge_conflicting_files(
+                ctx,
+                repo,
+                config,
+                &merge_paths,
+                root,
+                old_bookmark_value.unwrap_or(root),
+                client_bcs,
+            )
+            .await?;
+            match merged_files {
+                Some(merged_files) => merged_files,
+                None => {
+                    let mut paths: Vec<_> = merge_paths.into_iter().collect();
+                    paths.sort_unstable();
+                    return Err(PushrebaseError::Conflicts(
+                        paths
+                            .into_iter()
+                            .map(|path| PushrebaseConflict::new(path.clone(), path))
+                            .collect(),
+                    ));
+                }
+            }
+        };
 
         let rebase_outcome = do_rebase(
             ctx,
@@ -383,6 +433,7 @@ async fn rebase_in_loop(
             head,
             old_bookmark_value,
             onto_bookmark,
+            &merged_files,
             hooks,
             retry_num,
         )
@@ -436,6 +487,7 @@ async fn do_rebase(
     head: ChangesetId,
     old_bookmark_value: Option<ChangesetId>,
     onto_bookmark: &BookmarkName,
+    merged_files: &MergedFiles,
     mut hooks: Vec<Box<dyn PushrebaseCommitHook>>,
     retry_num: PushrebaseRetryNum,
 ) -> Result<Option<(ChangesetId, Vec<PushrebaseChangesetPair>)>, PushrebaseError> {
@@ -446,6 +498,7 @@ async fn do_rebase(
         root,
         head,
         old_bookmark_value.unwrap_or(root),
+        merged_files,
         &mut hooks,
     )
     .await?;
@@ -889,6 +942,7 @@ async fn create_rebased_changesets(
     root: ChangesetId,
     head: ChangesetId,
     onto: ChangesetId,
+    merged_files: &MergedFiles,
     hooks: &mut [Box<dyn PushrebaseCommitHook>],
 ) -> Result<(ChangesetId, RebasedChangesets), PushrebaseError> {
     let rebased_set = find_rebased_set(ctx, repo, root, head).await?;
@@ -923,6 +977,7 @@ async fn create_rebased_changesets(
             &onto,
             repo,
             &rebased_set_ids,
+            merged_files,
             hooks,
         )
         .await?;
@@ -955,6 +1010,7 @@ async fn rebase_changeset(
     onto: &ChangesetId,
     repo: &impl Repo,
     rebased_set: &HashSet<ChangesetId>,
+    merged_files: &MergedFiles,
     hooks: &mut [Box<dyn PushrebaseCommitHook>],
 ) -> Result<BonsaiChangeset> {
     let orig_cs_id = bcs.get_changeset_id();
@@ -1006,6 +1062,13 @@ async fn rebase_changeset(
         }
     }
 
+    // Files that were also changed on the server get the merged contents.
+    if let Some(merged) = merged_files.get(&orig_cs_id) {
+        for (path, file_change) in merged {
+            file_changes.insert(path.clone(), file_change.clone());
+        }
+    }
+
     let new_file_paths: HashSet<_> =
         HashSet::from_iter(new_file_changes.iter().map(|(path, _)| path));
     for path in file_changes.keys() {
@@ -1842,6 +1905,125 @@ mod tests {
         })
     }
 
+    #[fbinit::test]
+    async fn pushrebase_merge_conflicting_files(fb: FacebookInit) -> Result<(), Error> {
+        let ctx = CoreContext::test_mock(fb);
+        let repo = Linear::getrepo(fb).await;
+        let root = CreateCommitContext::new(
+            &ctx,
+            &repo,
+            vec!["79a13814c5ce7330173ec04d279bf95ab3f652fb"],
+        )
+        .add_file("TARGETS", "a\nb\nc\nd\ne\n")
+        .commit()
+        .await?;
+        let server = CreateCommitContext::new(&ctx, &repo, vec![root])
+            .add_file("TARGETS", "A\nb\nc\nd\ne\n")
+            .commit()
+            .await?;
+        let book = master_bookmark();
+        bookmark(&ctx, &repo, book.clone()).set_to(server).await?;
+
+        let client_1 = CreateCommitContext::new(&ctx, &repo, vec![root])
+            .add_file("TARGETS", "a\nb\nc\nd\nE\n")
+            .commit()
+            .await?;
+        let client_2 = CreateCommitContext::new(&ctx, &repo, vec![client_1])
+            .add_file("TARGETS", "a\nb\nc\nd\nE\nf\n")
+            .add_file("other", "other")
+            .commit()
+            .await?;
+        let hgcss = hashset![
+            repo.derive_hg_changeset(&ctx, client_1).await?,
+            repo.derive_hg_changeset(&ctx, client_2).await?,
+        ];
+
+        // Without the flag the push conflicts.
+        let res = do_pushrebase(&ctx, &repo, &Default::default(), &book, &hgcss).await;
+        should_have_conflicts(res);
+
+        let config = PushrebaseFlags {
+            merge_conflicting_files: true,
+            ..Default::default()
+        };
+        let outcome = do_pushrebase(&ctx, &repo, &config, &book, &hgcss)
+            .map_err(|err| format_err!("{:?}", err))
+            .await?;
+
+        let head = repo.derive_hg_changeset(&ctx, outcome.head).await?;
+        let content = ensure_file_content(&ctx, &repo, head, "TARGETS").await?;
+        assert_eq!(content, "A\nb\nc\nd\nE\nf\n");
+
+        // Overlapping changes are still rejected.
+        let client_3 = CreateCommitContext::new(&ctx, &repo, vec![root])
+            .add_file("TARGETS", "a2\nb\nc\nd\ne\n")
+            .commit()
+            .await?;
+        let hgcss = hashset![repo.derive_hg_changeset(&ctx, client_3).await?];
+        let res = do_pushrebase(&ctx, &repo, &config, &book, &hgcss).await;
+        should_have_conflicts(res);
+
+        Ok(())
+    }
+
+    #[fbinit::test]
+    async fn pushrebase_merge_conflicting_files_keeps_file_type(
+        fb: FacebookInit,
+    ) -> Result<(), Error> {
+        let ctx = CoreContext::test_mock(fb);
+        let repo = Linear::getrepo(fb).await;
+        let root = CreateCommitContext::new(
+            &ctx,
+            &repo,
+            vec!["79a13814c5ce7330173ec04d279bf95ab3f652fb"],
+        )
+        .add_file("script", "a\nb\nc\nd\ne\n")
+        .commit()
+        .await?;
+        let server = CreateCommitContext::new(&ctx, &repo, vec![root])
+            .add_file_with_type("script", "A\nb\nc\nd\ne\n", FileType::Executable)
+            .commit()
+            .await?;
+        let book = master_bookmark();
+        bookmark(&ctx, &repo, book.clone()).set_to(server).await?;
+
+        let client = CreateCommitContext::new(&ctx, &repo, vec![root])
+            .add_file("script", "a\nb\nc\nd\nE\n")
+            .commit()
+            .await?;
+        let hgcss = hashset![repo.derive_hg_changeset(&ctx, client).await?];
+
+        let config = PushrebaseFlags {
+            merge_conflicting_files: true,
+            ..Default::default()
+        };
+        let outcome = do_pushrebase(&ctx, &repo, &config, &book, &hgcss)
+            .map_err(|err| format_err!("{:?}", err))
+            .await?;
+
+        // The executable bit set on the server is not reverted by the client,
+        // which never changed it.
+        let head = repo.derive_hg_changeset(&ctx, outcome.head).await?;
+        let content = ensure_file_content(&ctx, &repo, head, "script").await?;
+        assert_eq!(content, "A\nb\nc\nd\nE\n");
+        let entry = head
+            .load(&ctx, repo.repo_blobstore())
+            .await?
+            .manifestid()
+            .find_entry(
+                ctx.clone(),
+                repo.repo_blobstore().clone(),
+                Some(MPath::new("script")?),
+            )
+            .await?;
+        assert!(matches!(
+            entry,
+            Some(Entry::Leaf((FileType::Executable, _)))
+        ));
+
+        Ok(())
+    }
+
     #[fbinit::test]
     fn pushrebase_caseconflicting_rename(fb: FacebookInit) -> Result<(), Error> {
         let runtime = tokio::runtime::Runtime::new().unwrap();
@@ -2636,11 +2818,9 @@ mod tests {
                 ..Default::default()
             };
 
-            assert!(
-                do_pushrebase(&ctx, &repo, &config_forbid_p2, &book, &hgcss)
-                    .await
-                    .is_err()
-            );
+            assert!(do_pushrebase(&ctx, &repo, &config_forbid_p2, &book, &hgcss)
+                .await
+                .is_err());
 
             let config_allow_p2 = PushrebaseFlags {
                 forbid_p2_root_rebases: false,
@@ -3280,6 +3460,33 @@ mod tests {
         Ok(())
     }
 
+    async fn ensure_file_content(
+        ctx: &CoreContext,
+        repo: &impl Repo,
+        hg_cs_id: HgChangesetId,
+        path: &str,
+    ) -> Result<String, Error> {
+        let cs = hg_cs_id.load(ctx, repo.repo_blobstore()).await?;
+        let entry = cs
+            .manifestid()
+            .find_entry(
+                ctx.clone(),
+                repo.repo_blobstore().clone(),
+                Some(MPath::new(path)?),
+            )
+            .await?
+            .ok_or_else(|| format_err!("{} is missing", path))?;
+        match entry {
+            Entry::Leaf((_, filenode_id)) => {
+                let store = repo.repo_blobstore();
+                let content_id = filenode_id.load(ctx, store).await?.content_id();
+                let content = filestore::fetch_concat(store, ctx, content_id).await?;
+                Ok(String::from_utf8_lossy(content.as_ref()).into_owned())
+            }
+            Entry::Tree(_) => Err(format_err!("{} is a directory", path)),
+        }
+    }
+
     fn should_have_conflicts(res: Result<PushrebaseOutcome, PushrebaseError>) {
         match res {
             Err(err) => match err {
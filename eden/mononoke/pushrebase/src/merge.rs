@@ -0,0 +1,307 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+//! Server-side merge of files that were changed both in the pushed commits and
+//! on the onto bookmark since the pushrebase root.
+//!
+//! Every pushed commit that changes such a file gets new contents for it: the
+//! commit's version of the file merged with the onto version, using the root
+//! version as the base. Only text files not larger than `merge_max_file_size`
+//! are merged, and only if the two sides change different lines. The file type
+//! (e.g. the executable bit) is merged the same way.
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::ops::Range;
+
+use anyhow::Error;
+use blobstore::Loadable;
+use bytes::Bytes;
+use context::CoreContext;
+use futures::future::try_join;
+use futures::future::try_join_all;
+use futures::TryStreamExt;
+use manifest::Entry;
+use manifest::ManifestOps;
+use mercurial_types::MPath;
+use metaconfig_types::PushrebaseFlags;
+use mononoke_types::BonsaiChangeset;
+use mononoke_types::ChangesetId;
+use mononoke_types::FileChange;
+use mononoke_types::FileType;
+
+use crate::id_to_manifestid;
+use crate::Repo;
+
+/// Merged file changes of the rebased commits, keyed by the original commit.
+pub(crate) type MergedFiles = HashMap<ChangesetId, Vec<(MPath, FileChange)>>;
+
+/// Merge `paths` in every commit of `client_bcs` that changes them.
+///
+/// Returns `None` if any of the files can't be merged, in which case the
+/// conflicts must be reported to the client as usual.
+pub(crate) async fn merge_conflicting_files(
+    ctx: &CoreContext,
+    repo: &impl Repo,
+    config: &PushrebaseFlags,
+    paths: &HashSet<MPath>,
+    root: ChangesetId,
+    onto: ChangesetId,
+    client_bcs: &[BonsaiChangeset],
+) -> Result<Option<MergedFiles>, Error> {
+    // Files in merge commits come from both parents, so there is no single
+    // version to merge with onto.
+    if client_bcs.iter().any(|bcs| bcs.parents().count() > 1) {
+        return Ok(None);
+    }
+
+    let (base_files, onto_files) = try_join(
+        fetch_mergeable_files(ctx, repo, config, root, paths),
+        fetch_mergeable_files(ctx, repo, config, onto, paths),
+    )
+    .await?;
+
+    let mut merged_files = MergedFiles::new();
+    let mut merged_paths = HashSet::new();
+    for bcs in client_bcs {
+        for (path, file_change) in bcs.file_changes() {
+            if !paths.contains(path) {
+                continue;
+            }
+            let tc = match file_change {
+                FileChange::Change(tc)
+                    if tc.copy_from().is_none()
+                        && is_mergeable_type(tc.file_type())
+                        && tc.size() <= config.merge_max_file_size =>
+                {
+                    tc
+                }
+                _ => return Ok(None),
+            };
+            let ((base_type, base), (other_type, other)) =
+                match (base_files.get(path), onto_files.get(path)) {
+                    (Some(base), Some(other)) => (base, other),
+                    _ => return Ok(None),
+                };
+            let file_type = match merge_file_type(*base_type, tc.file_type(), *other_type) {
+                Some(file_type) => file_type,
+                None => return Ok(None),
+            };
+
+            let local =
+                filestore::fetch_concat(repo.repo_blobstore(), ctx, tc.content_id()).await?;
+            let merged = match merge_text(base, &local, other) {
+                Some(merged) => merged,
+                None => return Ok(None),
+            };
+
+            let ((content_id, size), upload) = filestore::store_bytes(
+                repo.repo_blobstore(),
+                *repo.filestore_config(),
+                ctx,
+                Bytes::from(merged),
+            );
+            upload.await?;
+
+            merged_files
+                .entry(bcs.get_changeset_id())
+                .or_default()
+                .push((
+                    path.clone(),
+                    FileChange::tracked(content_id, file_type, size, None),
+                ));
+            merged_paths.insert(path);
+        }
+    }
+
+    // Paths that conflict only as copy sources can't be merged.
+    if merged_paths.len() != paths.len() {
+        return Ok(None);
+    }
+
+    Ok(Some(merged_files))
+}
+
+/// Types and contents of the `paths` in `cs_id` that can take part in a merge.
+/// Missing files, directories, symlinks and files that are too large are left
+/// out.
+async fn fetch_mergeable_files(
+    ctx: &CoreContext,
+    repo: &impl Repo,
+    config: &PushrebaseFlags,
+    cs_id: ChangesetId,
+    paths: &HashSet<MPath>,
+) -> Result<HashMap<MPath, (FileType, Bytes)>, Error> {
+    let mf_id = id_to_manifestid(ctx, repo, cs_id).await?;
+    let entries = mf_id
+        .find_entries(
+            ctx.clone(),
+            repo.repo_blobstore().clone(),
+            paths.iter().cloned().collect::<Vec<_>>(),
+        )
+        .try_collect::<Vec<_>>()
+        .await?;
+
+    let files = entries
+        .into_iter()
+        .filter_map(|(path, entry)| match (path, entry) {
+            (Some(path), Entry::Leaf((file_type, filenode_id))) if is_mergeable_type(file_type) => {
+                Some((path, file_type, filenode_id))
+            }
+            _ => None,
+        })
+        .map(|(path, file_type, filenode_id)| async move {
+            let envelope = filenode_id.load(ctx, repo.repo_blobstore()).await?;
+            if envelope.content_size() > config.merge_max_file_size {
+                return Ok(None);
+            }
+            let content =
+                filestore::fetch_concat(repo.repo_blobstore(), ctx, envelope.content_id()).await?;
+            Result::<_, Error>::Ok(Some((path, (file_type, content))))
+        });
+
+    Ok(try_join_all(files).await?.into_iter().flatten().collect())
+}
+
+fn is_mergeable_type(file_type: FileType) -> bool {
+    matches!(file_type, FileType::Regular | FileType::Executable)
+}
+
+/// Three-way merge of the file type, keeping whichever side changed it.
+/// Returns `None` if both sides changed it differently.
+fn merge_file_type(base: FileType, local: FileType, other: FileType) -> Option<FileType> {
+    if local == base || local == other {
+        Some(other)
+    } else if other == base {
+        Some(local)
+    } else {
+        None
+    }
+}
+
+/// Line-based three-way merge of `local` and `other` with the common ancestor
+/// `base`. Returns `None` if the two sides change overlapping or adjacent
+/// lines, or if any of the texts looks binary.
+fn merge_text(base: &[u8], local: &[u8], other: &[u8]) -> Option<Vec<u8>> {
+    if [base, local, other].iter().any(|text| text.contains(&0)) {
+        return None;
+    }
+
+    let base_lines = split_lines(base);
+    let mut changes = line_changes(base, local);
+    changes.extend(line_changes(base, other));
+    changes.sort_by_key(|(remove, _)| (remove.start, remove.end));
+
+    let mut merged = Vec::with_capacity(local.len().max(other.len()));
+    let mut pos = 0;
+    let mut last: Option<&(Range<usize>, Vec<&[u8]>)> = None;
+    for change in changes.iter() {
+        if let Some(last) = last {
+            if change == last {
+                // Both sides made the same change.
+                continue;
+            }
+            if change.0.start <= last.0.end {
+                return None;
+            }
+        }
+        let (remove, add) = change;
+        for line in base_lines[pos..remove.start].iter().chain(add.iter()) {
+            merged.extend_from_slice(line);
+        }
+        pos = remove.end;
+        last = Some(change);
+    }
+    for line in &base_lines[pos..] {
+        merged.extend_from_slice(line);
+    }
+
+    Some(merged)
+}
+
+/// Ranges of `base` lines replaced in `new`, with the lines replacing them.
+fn line_changes<'a>(base: &[u8], new: &'a [u8]) -> Vec<(Range<usize>, Vec<&'a [u8]>)> {
+    let new_lines = split_lines(new);
+    xdiff::diff_hunks(base, new)
+        .into_iter()
+        .map(|hunk| (hunk.remove, new_lines[hunk.add].to_vec()))
+        .collect()
+}
+
+fn split_lines(text: &[u8]) -> Vec<&[u8]> {
+    text.split_inclusive(|c| *c == b'\n').collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn merge(base: &str, local: &str, other: &str) -> Option<String> {
+        merge_text(base.as_bytes(), local.as_bytes(), other.as_bytes())
+            .map(|merged| String::from_utf8(merged).unwrap())
+    }
+
+    #[test]
+    fn test_merge_text() {
+        let base = "a\nb\nc\nd\ne\n";
+
+        // Changes in different parts of the file.
+        assert_eq!(
+            merge(base, "A\nb\nc\nd\ne\n", "a\nb\nc\nd\nE\n").as_deref(),
+            Some("A\nb\nc\nd\nE\n"),
+        );
+        assert_eq!(
+            merge(base, "a\nb\nc\nd\ne\nf\n", "z\na\nb\nd\ne\n").as_deref(),
+            Some("z\na\nb\nd\ne\nf\n"),
+        );
+
+        // The same change on both sides.
+        assert_eq!(
+            merge(base, "a\nB\nc\nd\nE\n", "a\nB\nc\nd\ne\n").as_deref(),
+            Some("a\nB\nc\nd\nE\n"),
+        );
+
+        // Only one side changed.
+        assert_eq!(merge(base, base, "a\nc\n").as_deref(), Some("a\nc\n"));
+
+        // Overlapping and adjacent changes conflict.
+        assert_eq!(merge(base, "a\nB\nc\nd\ne\n", "a\nb2\nc\nd\ne\n"), None);
+        assert_eq!(merge(base, "a\nB\nc\nd\ne\n", "a\nb\nC\nd\ne\n"), None);
+        assert_eq!(
+            merge(base, "a\nb\nx\nc\nd\ne\n", "a\nb\ny\nc\nd\ne\n"),
+            None
+        );
+
+        // Binary files are never merged.
+        assert_eq!(merge("a\0\nb\n", "A\0\nb\n", "a\0\nB\n"), None);
+    }
+
+    #[test]
+    fn test_merge_file_type() {
+        use FileType::*;
+
+        assert_eq!(merge_file_type(Regular, Regular, Regular), Some(Regular));
+        assert_eq!(
+            merge_file_type(Regular, Regular, Executable),
+            Some(Executable)
+        );
+        assert_eq!(
+            merge_file_type(Regular, Executable, Regular),
+            Some(Executable)
+        );
+        assert_eq!(merge_file_type(Executable, Regular, Regular), Some(Regular));
+        assert_eq!(merge_file_type(Regular, Executable, Symlink), None);
+    }
+
+    #[test]
+    fn test_merge_text_no_trailing_newline() {
+        assert_eq!(
+            merge("a\nb\nc", "A\nb\nc", "a\nb\nC").as_deref(),
+            Some("A\nb\nC"),
+        );
+    }
+}
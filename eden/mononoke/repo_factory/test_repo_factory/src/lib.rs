@@ -116,6 +116,7 @@ use sqlphases::SqlPhasesBuilder;
 use streaming_clone::ArcStreamingClone;
 use streaming_clone::StreamingCloneBuilder;
 use synced_commit_mapping::SqlSyncedCommitMapping;
+use trigram_index::RootTrigramIndexId;
 use unodes::RootUnodeManifestId;
 use wireproto_handler::ArcRepoHandlerBase;
 use wireproto_handler::PushRedirectorBase;
@@ -161,6 +162,7 @@ pub fn default_test_repo_config() -> RepoConfig {
             MappedHgChangesetId::NAME.to_string(),
             RootSkeletonManifestId::NAME.to_string(),
             RootBasenameSuffixSkeletonManifest::NAME.to_string(),
+            RootTrigramIndexId::NAME.to_string(),
         },
         unode_version: UnodeVersion::V2,
         blame_version: BlameVersion::V2,
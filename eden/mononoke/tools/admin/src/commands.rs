@@ -7,6 +7,7 @@
 
 mononoke_app::subcommands! {
     mod blobstore;
+    mod blobstore_demote;
     mod blobstore_unlink;
     mod bookmarks;
     mod changelog;
@@ -0,0 +1,119 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+use std::io::Write;
+use std::time::Duration;
+
+use anyhow::format_err;
+use anyhow::Context;
+use anyhow::Result;
+use blobstore_factory::default_scrub_handler;
+use blobstore_factory::make_tiered_blobstore;
+use clap::Parser;
+use metaconfig_types::BlobConfig;
+use metaconfig_types::BlobstoreId;
+use mononoke_app::args::RepoArgs;
+use mononoke_app::MononokeApp;
+use slog::warn;
+
+/// Demote idle blobs of a tiered blobstore to its cold tier
+///
+/// Only one instance of this should run against each tiered blobstore.
+#[derive(Parser)]
+pub struct CommandArgs {
+    #[clap(flatten)]
+    repo_args: RepoArgs,
+
+    /// If the repo's blobstore is multiplexed, use this inner blobstore
+    #[clap(long)]
+    inner_blobstore_id: Option<u64>,
+
+    /// Keep running, starting a new demotion pass this many seconds after the
+    /// previous one finishes
+    #[clap(long)]
+    interval: Option<u64>,
+}
+
+fn remove_wrapper_blobconfigs(mut blob_config: BlobConfig) -> BlobConfig {
+    // Pack is a wrapper store - remove it
+    while let BlobConfig::Pack { ref blobconfig, .. } = blob_config {
+        blob_config = BlobConfig::clone(blobconfig);
+    }
+    blob_config
+}
+
+fn get_blobconfig(blob_config: BlobConfig, inner_blobstore_id: Option<u64>) -> Result<BlobConfig> {
+    match inner_blobstore_id {
+        None => Ok(remove_wrapper_blobconfigs(blob_config)),
+        Some(inner_blobstore_id) => match blob_config {
+            BlobConfig::Multiplexed { blobstores, .. } => {
+                let seeked_id = BlobstoreId::new(inner_blobstore_id);
+                blobstores
+                    .into_iter()
+                    .find_map(|(blobstore_id, _, blobstore)| {
+                        if blobstore_id == seeked_id {
+                            Some(remove_wrapper_blobconfigs(blobstore))
+                        } else {
+                            None
+                        }
+                    })
+                    .ok_or_else(|| {
+                        format_err!("could not find a blobstore with id {}", inner_blobstore_id)
+                    })
+            }
+            _ => Err(format_err!(
+                "inner-blobstore-id supplied but blobstore is not multiplexed"
+            )),
+        },
+    }
+}
+
+pub async fn run(app: MononokeApp, args: CommandArgs) -> Result<()> {
+    let ctx = app.new_basic_context();
+
+    let repo_arg = args.repo_args.id_or_name()?;
+    let (_repo_name, repo_config) = app.repo_config(repo_arg)?;
+    let blobconfig = get_blobconfig(
+        repo_config.storage_config.blobstore,
+        args.inner_blobstore_id,
+    )?;
+    let env = app.environment();
+    let blobstore = make_tiered_blobstore(
+        app.fb,
+        blobconfig,
+        &env.mysql_options,
+        env.readonly_storage,
+        &env.blobstore_options,
+        app.logger(),
+        app.config_store(),
+        &default_scrub_handler(),
+        None,
+    )
+    .await
+    .context("Failed to open tiered blobstore")?;
+
+    loop {
+        match blobstore.demote_pass(&ctx).await {
+            Ok(stats) => writeln!(
+                std::io::stdout(),
+                "Demoted {} blobs, kept {}, failed {}",
+                stats.demoted,
+                stats.kept,
+                stats.failed
+            )?,
+            // A failed pass is retried on the next interval if there is one.
+            Err(e) if args.interval.is_some() => {
+                warn!(ctx.logger(), "Demotion pass failed: {:?}", e)
+            }
+            Err(e) => return Err(e.context("Demotion pass failed")),
+        }
+        match args.interval {
+            Some(interval) => tokio::time::sleep(Duration::from_secs(interval)).await,
+            None => return Ok(()),
+        }
+    }
+}
@@ -24,6 +24,7 @@ use changesets::Changesets;
 use clap::Parser;
 use clap::Subcommand;
 use context::CoreContext;
+use filestore::FilestoreConfig;
 use futures::compat::Stream01CompatExt;
 use futures::TryStreamExt;
 use metaconfig_types::RepoConfig;
@@ -88,6 +89,9 @@ pub struct Repo {
     #[facet]
     repo_derived_data: RepoDerivedData,
 
+    #[facet]
+    filestore_config: FilestoreConfig,
+
     #[facet]
     pushrebase_mutation_mapping: dyn PushrebaseMutationMapping,
 }
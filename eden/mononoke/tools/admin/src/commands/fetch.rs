@@ -185,6 +185,9 @@ async fn display_hg_entry(
             writeln!(std::io::stdout(), "Sha1: {}", metadata.sha1)?;
             writeln!(std::io::stdout(), "Sha256: {}", metadata.sha256)?;
             writeln!(std::io::stdout(), "Git-Sha1: {}", metadata.git_sha1)?;
+            if let Some(git_sha256) = metadata.git_sha256 {
+                writeln!(std::io::stdout(), "Git-Sha256: {}", git_sha256)?;
+            }
 
             let content = filestore::fetch_concat(blobstore, ctx, envelope.content_id())
                 .await
@@ -79,6 +79,15 @@ pub async fn verify(
     println!("sha1: {}", sha1.is_ok());
     println!("sha256: {}", sha256.is_ok());
     println!("git_sha1: {}", git_sha1.is_ok());
+    if let Some(git_sha256) = metadata.git_sha256 {
+        let git_sha256 = filestore::fetch(
+            &blobstore,
+            ctx.clone(),
+            &FetchKey::Aliased(Alias::GitSha256(git_sha256.sha256())),
+        )
+        .await;
+        println!("git_sha256: {}", git_sha256.is_ok());
+    }
 
     Ok(())
 }
@@ -45,6 +45,11 @@ pub struct CommandArgs {
     #[clap(long, default_value = "3")]
     pub compression_level: i32,
 
+    /// Also estimate packblob's ZstdDictionary format, training a dictionary
+    /// for each blob type from this many samples of small blobs.
+    #[clap(long)]
+    pub dictionary_samples: Option<usize>,
+
     #[clap(flatten, next_help_heading = "SAMPLING OPTIONS")]
     pub sampling: SamplingArgs,
 
@@ -164,6 +169,7 @@ async fn setup_sizing(
 ) -> Result<(JobParams, SizingCommand), Error> {
     let CommandArgs {
         compression_level,
+        dictionary_samples,
         sampling,
         common_args,
     } = args;
@@ -187,6 +193,7 @@ async fn setup_sizing(
 
     let command = SizingCommand {
         compression_level: *compression_level,
+        dictionary_samples: *dictionary_samples,
         progress_options: common_args.progress.parse_args(),
         sampling_options: sampling.parse_args(100 /* default_sample_rate */)?,
         sampler,
@@ -798,6 +798,7 @@ impl From<Option<MPath>> for WrappedPath {
 define_type_enum! {
     enum AliasType {
         GitSha1,
+        GitSha256,
         Sha1,
         Sha256,
     }
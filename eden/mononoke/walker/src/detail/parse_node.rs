@@ -13,6 +13,7 @@ use filestore::Alias;
 use mercurial_types::HgFileNodeId;
 use mercurial_types::HgManifestId;
 use mononoke_types::hash::GitSha1;
+use mononoke_types::hash::GitSha256;
 use mononoke_types::hash::Sha1;
 use mononoke_types::hash::Sha256;
 use mononoke_types::FileUnodeId;
@@ -151,6 +152,7 @@ impl FromStr for AliasKey {
         let id = &parts[1..].join(NODE_SEP);
         let alias = match alias_type {
             AliasType::GitSha1 => Alias::GitSha1(GitSha1::from_str(id)?),
+            AliasType::GitSha256 => Alias::GitSha256(GitSha256::from_str(id)?),
             AliasType::Sha1 => Alias::Sha1(Sha1::from_str(id)?),
             AliasType::Sha256 => Alias::Sha256(Sha256::from_str(id)?),
         };
@@ -13,6 +13,7 @@ use std::io::Cursor;
 use std::io::Write;
 use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
+use std::sync::Mutex;
 use std::time::Duration;
 
 use anyhow::Error;
@@ -36,8 +37,14 @@ use futures::stream::Stream;
 use futures::stream::TryStreamExt;
 use maplit::hashset;
 use mononoke_types::BlobstoreBytes;
+use packblob::blob_type_prefix;
+use packblob::train_dictionary;
+use packblob::TrainedDictionary;
+use packblob::DEFAULT_DICTIONARY_SIZE;
+use packblob::MAX_DICTIONARY_BLOB_SIZE;
 use samplingblob::SamplingHandler;
 use slog::info;
+use slog::warn;
 
 use crate::commands::JobParams;
 use crate::commands::JobWalkParams;
@@ -93,22 +100,119 @@ impl fmt::Display for SizingStats {
     }
 }
 
-fn try_compress(raw_data: &Bytes, compressor_type: CompressorType) -> Result<SizingStats, Error> {
+enum DictionaryState {
+    Collecting(Vec<Bytes>),
+    Training,
+    Trained(Arc<TrainedDictionary>),
+    Failed,
+}
+
+/// Zstd dictionaries trained per blob type from the first small samples of
+/// each type, to estimate the benefit of packblob's ZstdDictionary format.
+/// Samples seen before their type's dictionary is trained are sized without it.
+///
+/// Training is slow, so it runs on a blocking thread without holding the lock,
+/// and sizing carries on without the dictionary until it is ready.
+pub struct SampleDictionaries {
+    compression_level: i32,
+    samples_per_type: usize,
+    by_type: Arc<Mutex<HashMap<String, DictionaryState>>>,
+}
+
+impl SampleDictionaries {
+    pub fn new(compression_level: i32, samples_per_type: usize) -> Self {
+        Self {
+            compression_level,
+            samples_per_type,
+            by_type: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    fn dictionary_for(
+        &self,
+        ctx: &CoreContext,
+        key: &str,
+        raw_data: &Bytes,
+    ) -> Option<Arc<TrainedDictionary>> {
+        if raw_data.len() > MAX_DICTIONARY_BLOB_SIZE {
+            return None;
+        }
+        let (_repo_prefix, type_prefix) = blob_type_prefix(key)?;
+        let mut by_type = self.by_type.lock().expect("lock poisoned");
+        let state = by_type
+            .entry(type_prefix.to_owned())
+            .or_insert_with(|| DictionaryState::Collecting(Vec::new()));
+        match state {
+            DictionaryState::Trained(dictionary) => Some(dictionary.clone()),
+            DictionaryState::Training | DictionaryState::Failed => None,
+            DictionaryState::Collecting(samples) => {
+                samples.push(raw_data.clone());
+                if samples.len() >= self.samples_per_type {
+                    let samples = std::mem::take(samples);
+                    *state = DictionaryState::Training;
+                    self.spawn_training(ctx, type_prefix.to_owned(), samples);
+                }
+                None
+            }
+        }
+    }
+
+    /// Train the dictionary for `type_prefix` on a blocking thread, and record
+    /// the result once it is done.
+    fn spawn_training(&self, ctx: &CoreContext, type_prefix: String, samples: Vec<Bytes>) {
+        let compression_level = self.compression_level;
+        cloned!(ctx, self.by_type);
+        tokio::task::spawn_blocking(move || {
+            let state = match train_dictionary(&samples, DEFAULT_DICTIONARY_SIZE) {
+                Ok(dictionary) => DictionaryState::Trained(Arc::new(TrainedDictionary::new(
+                    type_prefix.clone(),
+                    &dictionary,
+                    compression_level,
+                ))),
+                Err(e) => {
+                    warn!(
+                        ctx.logger(),
+                        "Could not train dictionary for {}: {:?}", type_prefix, e
+                    );
+                    DictionaryState::Failed
+                }
+            };
+            by_type
+                .lock()
+                .expect("lock poisoned")
+                .insert(type_prefix, state);
+        });
+    }
+}
+
+fn try_compress(
+    ctx: &CoreContext,
+    key: &str,
+    raw_data: &Bytes,
+    compressor_type: CompressorType,
+    dictionaries: Option<&SampleDictionaries>,
+) -> Result<SizingStats, Error> {
     let raw = raw_data.len() as u64;
     let compressed_buf = MeteredWrite::new(Cursor::new(Vec::with_capacity(4 * 1024)));
     let mut compressor = Compressor::new(compressed_buf, compressor_type);
     compressor.write_all(raw_data)?;
     let compressed_buf = compressor.try_finish().map_err(|(_encoder, e)| e)?;
     // Assume we wouldn't compress if its bigger
-    let compressed = min(raw, compressed_buf.total_thru());
+    let mut compressed = min(raw, compressed_buf.total_thru());
+    // Like packblob, only use the dictionary if it helps
+    if let Some(dictionary) = dictionaries.and_then(|d| d.dictionary_for(ctx, key, raw_data)) {
+        compressed = min(compressed, dictionary.compress(raw_data)?.len() as u64);
+    }
     Ok(SizingStats { raw, compressed })
 }
 
 // Force load of leaf data and check compression ratio
 fn size_sampling_stream<InStream, InStats>(
+    ctx: CoreContext,
     scheduled_max: usize,
     s: InStream,
     compressor_type: CompressorType,
+    dictionaries: Option<Arc<SampleDictionaries>>,
     sampler: Arc<WalkSampleMapping<Node, SizingSample>>,
 ) -> impl Stream<Item = Result<(Node, Option<NodeData>, Option<SizingStats>), Error>>
 where
@@ -141,17 +245,23 @@ where
                         .right_future(),
                 }
                 .and_then({
-                    cloned!(sampler);
+                    cloned!(ctx, dictionaries, sampler);
                     move |fs_stream_size| {
                         // Report the blobstore sizes in sizing stats, more accurate than stream sizes, as headers included
                         let sizes = sampler
                             .complete_step(&walk_key.node)
                             .map(|sizing_sample| {
-                                sizing_sample.data.values().try_fold(
+                                sizing_sample.data.iter().try_fold(
                                     SizingStats::default(),
-                                    |acc, v| {
-                                        try_compress(v.as_bytes(), compressor_type)
-                                            .map(|sizes| acc + sizes)
+                                    |acc, (k, v)| {
+                                        try_compress(
+                                            &ctx,
+                                            k,
+                                            v.as_bytes(),
+                                            compressor_type,
+                                            dictionaries.as_deref(),
+                                        )
+                                        .map(|sizes| acc + sizes)
                                     },
                                 )
                             })
@@ -178,9 +288,16 @@ where
                     .map(|sizing_sample| {
                         sizing_sample
                             .data
-                            .values()
-                            .try_fold(SizingStats::default(), |acc, v| {
-                                try_compress(v.as_bytes(), compressor_type).map(|sizes| acc + sizes)
+                            .iter()
+                            .try_fold(SizingStats::default(), |acc, (k, v)| {
+                                try_compress(
+                                    &ctx,
+                                    k,
+                                    v.as_bytes(),
+                                    compressor_type,
+                                    dictionaries.as_deref(),
+                                )
+                                .map(|sizes| acc + sizes)
                             })
                     })
                     .transpose();
@@ -299,6 +416,7 @@ impl SamplingHandler for WalkSampleMapping<Node, SizingSample> {
 #[derive(Clone)]
 pub struct SizingCommand {
     pub compression_level: i32,
+    pub dictionary_samples: Option<usize>,
     pub progress_options: ProgressOptions,
     pub sampling_options: SamplingOptions,
     pub sampler: Arc<WalkSampleMapping<Node, SizingSample>>,
@@ -360,23 +478,33 @@ async fn run_one(
             command.progress_options,
         ));
 
+    // Shared by all chunks of the walk, so each dictionary is only trained once
+    let dictionaries = command.dictionary_samples.map(|samples_per_type| {
+        Arc::new(SampleDictionaries::new(
+            command.compression_level,
+            samples_per_type,
+        ))
+    });
+
     let make_sink = {
         cloned!(command, job_params.quiet, sub_params.progress_state,);
         move |ctx: &CoreContext, repo_params: &RepoWalkParams| {
             cloned!(ctx, repo_params.scheduled_max);
             async move |walk_output, _run_start, _chunk_num, _checkpoint_name| {
-                cloned!(ctx, sizing_progress_state);
+                cloned!(ctx, dictionaries, sizing_progress_state);
                 // Sizing doesn't use mtime, so remove it from payload
                 let walk_progress = progress_stream(quiet, &progress_state, walk_output).map_ok(
                     |(key, payload, stats): (_, WalkPayloadMtime, _)| (key, payload.data, stats),
                 );
 
                 let compressor = size_sampling_stream(
+                    ctx.clone(),
                     scheduled_max,
                     walk_progress,
                     CompressorType::Zstd {
                         level: command.compression_level,
                     },
+                    dictionaries.clone(),
                     command.sampler,
                 );
                 let report_sizing = progress_stream(quiet, &sizing_progress_state, compressor);
@@ -62,4 +62,17 @@ impl ReadFileContents for PythonFileScmStore {
 
         futures::stream::iter(contents.into_iter()).boxed()
     }
+
+    /// The Python callback only returns file contents, without copy headers.
+    async fn read_rename_metadata(
+        &self,
+        _keys: Vec<Key>,
+    ) -> BoxStream<Result<(Option<Key>, Key), Self::Error>> {
+        futures::stream::once(async {
+            Err(anyhow::anyhow!(
+                "rename metadata is not available from a Python file store"
+            ))
+        })
+        .boxed()
+    }
 }
@@ -1035,6 +1035,13 @@ mod test {
                 .map(|key| Ok((hgid_file(&key.hgid).into(), key)))
                 .boxed()
         }
+
+        async fn read_rename_metadata(
+            &self,
+            keys: Vec<Key>,
+        ) -> BoxStream<Result<(Option<Key>, Key)>> {
+            stream::iter(keys).map(|key| Ok((None, key))).boxed()
+        }
     }
 
     fn hgid_file(hgid: &HgId) -> Vec<u8> {
@@ -5,6 +5,8 @@
  * GNU General Public License version 2.
  */
 
+mod resume;
+
 use std::env;
 use std::ffi::OsStr;
 use std::io::Write;
@@ -32,6 +34,11 @@ use util::file::atomic_write;
 use util::path::absolute;
 use util::path::expand_path;
 
+pub use crate::resume::CloneCheckout;
+pub use crate::resume::ClonePhase;
+pub use crate::resume::CloneState;
+pub use crate::resume::CLONE_STATE_FILE;
+
 pub fn get_default_destination_directory(config: &dyn Config) -> Result<PathBuf> {
     Ok(absolute(
         if let Some(default_dir) = config.get("clone", "default-destination-dir") {
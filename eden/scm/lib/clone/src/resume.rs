@@ -0,0 +1,255 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+//! Checkpoints of a clone in progress, so an interrupted clone can continue
+//! from the last completed phase instead of starting over.
+//!
+//! The state is kept in `.hg/clonestate` and removed once the clone is done:
+//!
+//! ```text
+//! phase commits
+//! bookmark 9bc730a19041f9ec7cb33c626e811aa233efb18c master
+//! profile tools/sparse/base
+//! updaterev 9bc730a19041f9ec7cb33c626e811aa233efb18c
+//! ```
+//!
+//! A `noupdate` line takes the place of `updaterev` for `-U` clones.
+
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use anyhow::bail;
+use anyhow::Result;
+use types::HgId;
+use util::file::atomic_write;
+
+/// Name of the clone state file in the `.hg` directory.
+pub const CLONE_STATE_FILE: &str = "clonestate";
+
+/// The last completed phase of a clone.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ClonePhase {
+    /// The repo was created, but nothing was fetched yet.
+    Init,
+    /// Bookmarks were fetched from the server.
+    Bookmarks,
+    /// The segmented changelog was imported and remote bookmarks were
+    /// written to the metalog.
+    Commits,
+    /// The working copy checkout was started. Files already written are
+    /// tracked by the checkout itself in `.hg/updateprogress`.
+    Checkout,
+}
+
+impl ClonePhase {
+    fn as_str(&self) -> &'static str {
+        match self {
+            ClonePhase::Init => "init",
+            ClonePhase::Bookmarks => "bookmarks",
+            ClonePhase::Commits => "commits",
+            ClonePhase::Checkout => "checkout",
+        }
+    }
+}
+
+impl FromStr for ClonePhase {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            "init" => ClonePhase::Init,
+            "bookmarks" => ClonePhase::Bookmarks,
+            "commits" => ClonePhase::Commits,
+            "checkout" => ClonePhase::Checkout,
+            _ => bail!("unknown clone phase '{}'", s),
+        })
+    }
+}
+
+/// What a clone checks out once it has the commits.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CloneCheckout {
+    /// Leave the working copy empty (`-U`).
+    pub noupdate: bool,
+    /// Check out this commit instead of the main bookmark (`-u`).
+    pub updaterev: Option<HgId>,
+    /// Sparse profiles to enable.
+    pub sparse_profiles: Vec<String>,
+}
+
+/// Progress of a clone, written to disk after every phase.
+#[derive(Debug)]
+pub struct CloneState {
+    path: PathBuf,
+    phase: ClonePhase,
+    bookmarks: BTreeMap<String, HgId>,
+    checkout: CloneCheckout,
+}
+
+impl CloneState {
+    /// Start tracking a new clone in `dot_hg_path` that ends with `checkout`.
+    pub fn init(dot_hg_path: &Path, checkout: CloneCheckout) -> Result<Self> {
+        let state = Self {
+            path: dot_hg_path.join(CLONE_STATE_FILE),
+            phase: ClonePhase::Init,
+            bookmarks: BTreeMap::new(),
+            checkout,
+        };
+        state.save()?;
+        Ok(state)
+    }
+
+    /// Load the state of an unfinished clone, if there is one.
+    pub fn load(dot_hg_path: &Path) -> Result<Option<Self>> {
+        let path = dot_hg_path.join(CLONE_STATE_FILE);
+        if util::file::exists(&path)?.is_none() {
+            return Ok(None);
+        }
+        let content = util::file::read_to_string(&path)?;
+
+        let mut phase = None;
+        let mut bookmarks = BTreeMap::new();
+        let mut checkout = CloneCheckout::default();
+        for line in content.lines() {
+            if line == "noupdate" {
+                checkout.noupdate = true;
+                continue;
+            }
+            match line.split_once(' ') {
+                Some(("phase", value)) => phase = Some(value.parse()?),
+                Some(("bookmark", value)) => match value.split_once(' ') {
+                    Some((hex, name)) => {
+                        bookmarks.insert(name.to_string(), HgId::from_str(hex)?);
+                    }
+                    None => bail!("invalid bookmark in {}: {}", path.display(), line),
+                },
+                Some(("profile", value)) => checkout.sparse_profiles.push(value.to_string()),
+                Some(("updaterev", value)) => checkout.updaterev = Some(HgId::from_str(value)?),
+                _ => bail!("invalid line in {}: {}", path.display(), line),
+            }
+        }
+        let phase = match phase {
+            Some(phase) => phase,
+            None => bail!("{} has no clone phase", path.display()),
+        };
+
+        Ok(Some(Self {
+            path,
+            phase,
+            bookmarks,
+            checkout,
+        }))
+    }
+
+    pub fn phase(&self) -> ClonePhase {
+        self.phase
+    }
+
+    /// Bookmarks fetched from the server, once the `Bookmarks` phase is done.
+    pub fn bookmarks(&self) -> &BTreeMap<String, HgId> {
+        &self.bookmarks
+    }
+
+    /// What to check out, as requested when the clone started.
+    pub fn checkout(&self) -> &CloneCheckout {
+        &self.checkout
+    }
+
+    /// Record the fetched bookmarks and complete the `Bookmarks` phase.
+    pub fn set_bookmarks(&mut self, bookmarks: BTreeMap<String, HgId>) -> Result<()> {
+        self.bookmarks = bookmarks;
+        self.advance(ClonePhase::Bookmarks)
+    }
+
+    /// Mark `phase` as completed. Going back to an earlier phase is a no-op.
+    pub fn advance(&mut self, phase: ClonePhase) -> Result<()> {
+        if phase > self.phase {
+            self.phase = phase;
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    /// The clone is complete. Remove the state file.
+    pub fn finish(self) -> Result<()> {
+        util::path::remove_file(&self.path)?;
+        Ok(())
+    }
+
+    fn save(&self) -> Result<()> {
+        let mut content = format!("phase {}\n", self.phase.as_str());
+        for (name, id) in self.bookmarks.iter() {
+            content.push_str(&format!("bookmark {} {}\n", id.to_hex(), name));
+        }
+        for profile in self.checkout.sparse_profiles.iter() {
+            content.push_str(&format!("profile {}\n", profile));
+        }
+        if self.checkout.noupdate {
+            content.push_str("noupdate\n");
+        }
+        if let Some(updaterev) = &self.checkout.updaterev {
+            content.push_str(&format!("updaterev {}\n", updaterev.to_hex()));
+        }
+        atomic_write(&self.path, |f| f.write_all(content.as_bytes()))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn test_clone_state() -> Result<()> {
+        let tmpdir = TempDir::new()?;
+        let dot_hg = tmpdir.path();
+        assert!(CloneState::load(dot_hg)?.is_none());
+
+        let id = HgId::from_str("9bc730a19041f9ec7cb33c626e811aa233efb18c")?;
+        let checkout = CloneCheckout {
+            noupdate: false,
+            updaterev: Some(id),
+            sparse_profiles: vec!["tools/sparse/base".to_string()],
+        };
+        let mut state = CloneState::init(dot_hg, checkout.clone())?;
+        assert_eq!(CloneState::load(dot_hg)?.unwrap().phase(), ClonePhase::Init);
+
+        state.set_bookmarks(BTreeMap::from([("master".to_string(), id)]))?;
+        state.advance(ClonePhase::Commits)?;
+        state.advance(ClonePhase::Bookmarks)?;
+
+        let state = CloneState::load(dot_hg)?.unwrap();
+        assert_eq!(state.phase(), ClonePhase::Commits);
+        assert_eq!(state.bookmarks().get("master"), Some(&id));
+        assert_eq!(state.checkout(), &checkout);
+
+        state.finish()?;
+        assert!(CloneState::load(dot_hg)?.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_clone_state_noupdate() -> Result<()> {
+        let tmpdir = TempDir::new()?;
+        let dot_hg = tmpdir.path();
+
+        let checkout = CloneCheckout {
+            noupdate: true,
+            ..Default::default()
+        };
+        CloneState::init(dot_hg, checkout.clone())?;
+        assert_eq!(CloneState::load(dot_hg)?.unwrap().checkout(), &checkout);
+
+        Ok(())
+    }
+}
@@ -15,6 +15,8 @@ use std::sync::Arc;
 use minibytes::Text;
 
 use crate::convert::FromConfigValue;
+use crate::schema::Schema;
+use crate::Error;
 use crate::Result;
 
 /// Readable config. This can be used as a trait object.
@@ -107,6 +109,36 @@ pub trait ConfigExt: Config {
     fn get_or_default<T: Default + FromConfigValue>(&self, section: &str, name: &str) -> Result<T> {
         self.get_or(section, name, Default::default)
     }
+
+    /// Get a config item registered in the global `Schema`. Convert to type `T`.
+    ///
+    /// If the config item is not set, deprecated aliases of the item are
+    /// tried, then the schema default. Errors out if the item is not
+    /// registered, to catch typos in config names.
+    fn get_schema_opt<T: FromConfigValue>(&self, section: &str, name: &str) -> Result<Option<T>> {
+        let item = match Schema::global().item(section, name) {
+            Some(item) => item,
+            None => {
+                return Err(Error::General(format!(
+                    "config {}.{} is not registered",
+                    section, name
+                )));
+            }
+        };
+        let value = std::iter::once((item.section, item.name))
+            .chain(item.alias_names())
+            .find_map(|(section, name)| self.get(section, name))
+            .or_else(|| item.default.map(Text::from_static));
+        value.map(|value| T::try_from_str(&value)).transpose()
+    }
+
+    /// Get a config item registered in the global `Schema`. Convert to type `T`.
+    ///
+    /// If neither the config item nor the schema default is set, return
+    /// `T::default()`.
+    fn get_schema<T: Default + FromConfigValue>(&self, section: &str, name: &str) -> Result<T> {
+        Ok(self.get_schema_opt(section, name)?.unwrap_or_default())
+    }
 }
 
 impl<T: Config> ConfigExt for T {}
@@ -256,4 +288,28 @@ mod tests {
         // Make sure we can pass BTreeMap config to generic func.
         wants_impl(&map);
     }
+
+    #[test]
+    fn test_get_schema() {
+        use crate::schema::ConfigItem;
+        use crate::schema::ConfigType;
+
+        static ITEMS: &[ConfigItem] = &[
+            ConfigItem::new("schematest", "size", ConfigType::Int).default("10"),
+            ConfigItem::new("schematest", "name", ConfigType::String)
+                .aliases(&["schematest.oldname"]),
+        ];
+        Schema::global().register(ITEMS);
+
+        let map: BTreeMap<&str, &str> = BTreeMap::new();
+        assert_eq!(map.get_schema::<u32>("schematest", "size").unwrap(), 10);
+        assert_eq!(map.get_schema::<String>("schematest", "name").unwrap(), "");
+        assert!(map.get_schema::<u32>("schematest", "typo").is_err());
+
+        let map: BTreeMap<&str, &str> = vec![("schematest.size", "3"), ("schematest.oldname", "a")]
+            .into_iter()
+            .collect();
+        assert_eq!(map.get_schema::<u32>("schematest", "size").unwrap(), 3);
+        assert_eq!(map.get_schema::<String>("schematest", "name").unwrap(), "a");
+    }
 }
@@ -12,6 +12,7 @@
 pub mod config;
 pub mod convert;
 pub mod error;
+pub mod schema;
 
 pub use config::Config;
 pub use config::ConfigExt;
@@ -0,0 +1,246 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+//! Declarative config schema.
+//!
+//! Crates describe the config items they read with [`ConfigItem`] and
+//! register them with [`Schema::global`]. The schema is used to provide
+//! defaults (see `ConfigExt::get_schema_opt`) and to validate config files.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::RwLock;
+use std::time::Duration;
+
+use once_cell::sync::Lazy;
+
+use crate::convert::ByteCount;
+use crate::convert::FromConfigValue;
+use crate::Result;
+
+/// Type of a config value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConfigType {
+    Bool,
+    Int,
+    Float,
+    String,
+    /// A list parsed by `convert::parse_list`.
+    List,
+    /// A byte count with an optional unit, like "1.5 MB".
+    ByteCount,
+    Path,
+    /// Seconds, as a float.
+    Duration,
+}
+
+impl ConfigType {
+    /// Check that `value` can be converted to this type.
+    pub fn validate(self, value: &str) -> Result<()> {
+        match self {
+            ConfigType::Bool => bool::try_from_str(value).map(|_| ()),
+            ConfigType::Int => i64::try_from_str(value).map(|_| ()),
+            ConfigType::Float => f64::try_from_str(value).map(|_| ()),
+            ConfigType::String | ConfigType::List => Ok(()),
+            ConfigType::ByteCount => ByteCount::try_from_str(value).map(|_| ()),
+            ConfigType::Path => PathBuf::try_from_str(value).map(|_| ()),
+            ConfigType::Duration => Duration::try_from_str(value).map(|_| ()),
+        }
+    }
+}
+
+impl fmt::Display for ConfigType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            ConfigType::Bool => "bool",
+            ConfigType::Int => "int",
+            ConfigType::Float => "float",
+            ConfigType::String => "string",
+            ConfigType::List => "list",
+            ConfigType::ByteCount => "bytes",
+            ConfigType::Path => "path",
+            ConfigType::Duration => "duration",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Description of a config item.
+///
+/// Intended to be declared as constants:
+///
+/// ```
+/// use configmodel::schema::ConfigItem;
+/// use configmodel::schema::ConfigType;
+///
+/// const ITEMS: &[ConfigItem] = &[
+///     ConfigItem::new("progress", "delay", ConfigType::Float)
+///         .default("3")
+///         .doc("seconds before showing progress bars"),
+/// ];
+/// ```
+#[derive(Clone, Debug)]
+pub struct ConfigItem {
+    pub section: &'static str,
+    pub name: &'static str,
+    pub ty: ConfigType,
+    /// Default value, in config file syntax.
+    pub default: Option<&'static str>,
+    pub doc: &'static str,
+    /// Deprecated names, in "section.name" form. They are read if the item
+    /// itself is not set.
+    pub aliases: &'static [&'static str],
+}
+
+impl ConfigItem {
+    pub const fn new(section: &'static str, name: &'static str, ty: ConfigType) -> Self {
+        Self {
+            section,
+            name,
+            ty,
+            default: None,
+            doc: "",
+            aliases: &[],
+        }
+    }
+
+    pub const fn default(mut self, default: &'static str) -> Self {
+        self.default = Some(default);
+        self
+    }
+
+    pub const fn doc(mut self, doc: &'static str) -> Self {
+        self.doc = doc;
+        self
+    }
+
+    pub const fn aliases(mut self, aliases: &'static [&'static str]) -> Self {
+        self.aliases = aliases;
+        self
+    }
+
+    /// Deprecated names, split into (section, name).
+    pub fn alias_names(&self) -> impl Iterator<Item = (&'static str, &'static str)> {
+        self.aliases
+            .iter()
+            .filter_map(|alias| alias.split_once('.'))
+    }
+}
+
+type ItemMap = HashMap<&'static str, HashMap<&'static str, &'static ConfigItem>>;
+
+/// Registered config items.
+#[derive(Default)]
+pub struct Schema {
+    /// section -> name -> item.
+    items: RwLock<ItemMap>,
+    /// Deprecated section -> name -> item.
+    aliases: RwLock<ItemMap>,
+}
+
+impl Schema {
+    /// The schema that crates register their config items with.
+    pub fn global() -> &'static Self {
+        static SCHEMA: Lazy<Schema> = Lazy::new(Schema::default);
+        &*SCHEMA
+    }
+
+    /// Register config items. Panics if an item or alias is already registered.
+    pub fn register(&self, items: &'static [ConfigItem]) {
+        let mut registered = self.items.write().unwrap();
+        let mut aliases = self.aliases.write().unwrap();
+        for item in items {
+            let section = registered.entry(item.section).or_default();
+            if section.insert(item.name, item).is_some() {
+                panic!("Config {}.{} is duplicated", item.section, item.name)
+            }
+            for (alias_section, alias_name) in item.alias_names() {
+                let section = aliases.entry(alias_section).or_default();
+                if section.insert(alias_name, item).is_some() {
+                    panic!(
+                        "Config alias {}.{} is duplicated",
+                        alias_section, alias_name
+                    )
+                }
+            }
+        }
+    }
+
+    /// Look up an item by its name.
+    pub fn item(&self, section: &str, name: &str) -> Option<&'static ConfigItem> {
+        lookup(&self.items.read().unwrap(), section, name)
+    }
+
+    /// Look up the item that has `section.name` as a deprecated alias.
+    pub fn renamed_item(&self, section: &str, name: &str) -> Option<&'static ConfigItem> {
+        lookup(&self.aliases.read().unwrap(), section, name)
+    }
+
+    /// Whether any item is registered in `section`.
+    pub fn has_section(&self, section: &str) -> bool {
+        self.items.read().unwrap().contains_key(section)
+    }
+
+    /// All registered items, sorted by name.
+    pub fn items(&self) -> Vec<&'static ConfigItem> {
+        let mut items: Vec<_> = self
+            .items
+            .read()
+            .unwrap()
+            .values()
+            .flat_map(|section| section.values().copied())
+            .collect();
+        items.sort_by_key(|item| (item.section, item.name));
+        items
+    }
+}
+
+fn lookup(map: &ItemMap, section: &str, name: &str) -> Option<&'static ConfigItem> {
+    map.get(section)?.get(name).copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static ITEMS: &[ConfigItem] = &[
+        ConfigItem::new("foo", "bar", ConfigType::Int).default("1"),
+        ConfigItem::new("foo", "baz", ConfigType::Bool).aliases(&["old.baz"]),
+    ];
+
+    #[test]
+    fn test_register() {
+        let schema = Schema::default();
+        schema.register(ITEMS);
+
+        assert_eq!(schema.item("foo", "bar").unwrap().default, Some("1"));
+        assert!(schema.item("foo", "qux").is_none());
+        assert_eq!(schema.renamed_item("old", "baz").unwrap().name, "baz");
+        assert!(schema.has_section("foo"));
+        assert!(!schema.has_section("old"));
+        assert_eq!(schema.items().len(), 2);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_register_duplicated() {
+        let schema = Schema::default();
+        schema.register(ITEMS);
+        schema.register(ITEMS);
+    }
+
+    #[test]
+    fn test_validate_type() {
+        assert!(ConfigType::Bool.validate("yes").is_ok());
+        assert!(ConfigType::Bool.validate("maybe").is_err());
+        assert!(ConfigType::Int.validate("-3").is_ok());
+        assert!(ConfigType::Int.validate("3.5").is_err());
+        assert!(ConfigType::ByteCount.validate("1.5 MB").is_ok());
+        assert!(ConfigType::Duration.validate("x").is_err());
+    }
+}
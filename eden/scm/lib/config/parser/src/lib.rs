@@ -69,6 +69,7 @@
 pub(crate) mod builtin;
 pub mod config;
 pub mod hg;
+pub mod validate;
 
 pub use configmodel;
 pub use configmodel::convert;
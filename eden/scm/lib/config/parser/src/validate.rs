@@ -0,0 +1,159 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+//! Check config values against a `Schema`.
+
+use std::fmt;
+
+use configmodel::schema::ConfigItem;
+use configmodel::schema::Schema;
+use configmodel::Config;
+use configmodel::ValueSource;
+use minibytes::Text;
+
+/// A problem found in a config value.
+#[derive(Debug)]
+pub struct Issue {
+    pub section: Text,
+    pub name: Text,
+    /// Where the value is set, like "path/to/hgrc:12" or "--config".
+    pub location: String,
+    pub kind: IssueKind,
+}
+
+#[derive(Debug)]
+pub enum IssueKind {
+    /// The config is not registered, although its section is.
+    Unknown,
+    /// The config is a deprecated alias of another config.
+    Renamed(&'static ConfigItem),
+    /// The value cannot be converted to the registered type.
+    InvalidValue { value: Text, error: String },
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}.{}: ", self.location, self.section, self.name)?;
+        match &self.kind {
+            IssueKind::Unknown => write!(f, "unknown config"),
+            IssueKind::Renamed(item) => {
+                write!(f, "deprecated, use {}.{} instead", item.section, item.name)
+            }
+            IssueKind::InvalidValue { value, error } => {
+                write!(f, "invalid value '{}': {}", value, error)
+            }
+        }
+    }
+}
+
+/// Check all values set in `config` against `schema`.
+///
+/// Only sections with registered items are checked for unknown names, so
+/// configs of unregistered extensions are not reported. Every source of a
+/// value is checked, including overridden ones.
+pub fn validate(config: &dyn Config, schema: &Schema) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    for section in config.sections().iter() {
+        let check_unknown = schema.has_section(section);
+        for name in config.keys(section) {
+            let (item, renamed) = match schema.item(section, &name) {
+                Some(item) => (Some(item), false),
+                None => (schema.renamed_item(section, &name), true),
+            };
+            for source in config.get_sources(section, &name).iter() {
+                let value = match source.value() {
+                    Some(value) => value,
+                    None => continue,
+                };
+                let mut report = |kind| {
+                    issues.push(Issue {
+                        section: section.clone(),
+                        name: name.clone(),
+                        location: location(source),
+                        kind,
+                    })
+                };
+                match item {
+                    None if check_unknown => report(IssueKind::Unknown),
+                    None => {}
+                    Some(item) => {
+                        if renamed {
+                            report(IssueKind::Renamed(item));
+                        }
+                        if let Err(err) = item.ty.validate(value) {
+                            report(IssueKind::InvalidValue {
+                                value: value.clone(),
+                                error: err.to_string(),
+                            });
+                        }
+                    }
+                }
+            }
+        }
+    }
+    issues
+}
+
+/// "path:line" of a value, or its source name if it is not from a file.
+fn location(source: &ValueSource) -> String {
+    let (path, range) = match source.location() {
+        Some(location) => location,
+        None => return source.source().to_string(),
+    };
+    let content = source.file_content().unwrap_or_default();
+    let line = 1 + content
+        .get(..range.start)
+        .unwrap_or_default()
+        .matches('\n')
+        .count();
+    if path.as_os_str().is_empty() {
+        format!("{}:{}", source.source(), line)
+    } else {
+        format!("{}:{}", path.display(), line)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use configmodel::schema::ConfigType;
+
+    use super::*;
+    use crate::config::ConfigSet;
+    use crate::config::Options;
+
+    static ITEMS: &[ConfigItem] = &[
+        ConfigItem::new("a", "int", ConfigType::Int),
+        ConfigItem::new("a", "bool", ConfigType::Bool).aliases(&["a.oldbool"]),
+    ];
+
+    #[test]
+    fn test_validate() {
+        let schema = Schema::default();
+        schema.register(ITEMS);
+
+        let mut config = ConfigSet::new();
+        config.parse(
+            "[a]\nint = 1\nint = x\nbool = yes\noldbool = maybe\ntypo = 1\n[b]\nx = 1\n",
+            &Options::new().source("test"),
+        );
+        config.set("a", "int", Some("2"), &"--config".into());
+
+        let issues: Vec<String> = validate(&config, &schema)
+            .iter()
+            .map(|issue| issue.to_string())
+            .collect();
+        assert_eq!(
+            issues,
+            vec![
+                "test:3: a.int: invalid value 'x': invalid digit found in string",
+                "test:5: a.oldbool: deprecated, use a.bool instead",
+                "test:5: a.oldbool: invalid value 'maybe': invalid bool: maybe",
+                "test:6: a.typo: unknown config",
+            ]
+        );
+    }
+}
@@ -535,7 +535,7 @@ fn extract_p1_p2(data: &[u8]) -> (HgId, HgId) {
 /// data is not prefixed by hashes.
 ///
 /// See `filelog.py:parsemeta`.
-fn extract_rename(data: &[u8]) -> Option<Key> {
+pub(crate) fn extract_rename(data: &[u8]) -> Option<Key> {
     if data.starts_with(b"\x01\n") {
         let data = &data[2..];
         if let Some(pos) = data.windows(2).position(|needle| needle == b"\x01\n") {
@@ -18,6 +18,7 @@ use types::HgId;
 use types::Key;
 use types::RepoPath;
 
+use crate::api::extract_rename;
 use crate::EagerRepoStore;
 
 // storemodel traits
@@ -40,6 +41,20 @@ impl ReadFileContents for EagerRepoStore {
         });
         futures::stream::iter(iter).boxed()
     }
+
+    async fn read_rename_metadata(
+        &self,
+        keys: Vec<Key>,
+    ) -> BoxStream<Result<(Option<Key>, Key), Self::Error>> {
+        let iter = keys.into_iter().map(|k| {
+            let data = match self.get_content(k.hgid)? {
+                Some(data) => data,
+                None => anyhow::bail!("no such file: {:?}", &k),
+            };
+            Ok((extract_rename(&data), k))
+        });
+        futures::stream::iter(iter).boxed()
+    }
 }
 
 impl TreeStore for EagerRepoStore {
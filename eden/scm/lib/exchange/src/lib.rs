@@ -31,14 +31,32 @@ pub fn clone(
     metalog: &mut MetaLog,
     commits: &mut Box<dyn DagCommits + Send + 'static>,
     bookmarks: Vec<String>,
+) -> Result<BTreeMap<String, HgId>> {
+    let bookmarks = fetch_bookmarks(&edenapi, bookmarks)?;
+    import_clone_data(&edenapi, commits, bookmarks.values().cloned().collect())?;
+    record_bookmarks(metalog, commits, &bookmarks)?;
+    Ok(bookmarks)
+}
+
+/// Resolve `bookmarks` on the server. Bookmarks that do not exist are skipped.
+pub fn fetch_bookmarks(
+    edenapi: &Arc<dyn EdenApi>,
+    bookmarks: Vec<String>,
 ) -> Result<BTreeMap<String, HgId>> {
     let bookmarks = block_on(edenapi.bookmarks(bookmarks))?.map_err(|e| e.tag_network())?;
-    let bookmarks = bookmarks
+    Ok(bookmarks
         .into_iter()
         .filter_map(|bm| bm.hgid.map(|id| (bm.bookmark, id)))
-        .collect::<BTreeMap<String, HgId>>();
+        .collect())
+}
 
-    let heads = bookmarks.values().cloned().collect();
+/// Download the segmented changelog for `heads` into an empty commit graph.
+/// The imported graph is flushed to disk.
+pub fn import_clone_data(
+    edenapi: &Arc<dyn EdenApi>,
+    commits: &mut Box<dyn DagCommits + Send + 'static>,
+    heads: Vec<HgId>,
+) -> Result<()> {
     let clone_data = block_on(edenapi.pull_lazy(vec![], heads))?.map_err(|e| e.tag_network())?;
     let idmap: BTreeMap<_, _> = clone_data
         .idmap
@@ -50,7 +68,15 @@ pub fn clone(
         idmap,
     };
     block_on(commits.import_clone_data(vertex_clone_data))??;
+    Ok(())
+}
 
+/// Write the tip and the remote `bookmarks` to the metalog.
+pub fn record_bookmarks(
+    metalog: &mut MetaLog,
+    commits: &mut Box<dyn DagCommits + Send + 'static>,
+    bookmarks: &BTreeMap<String, HgId>,
+) -> Result<()> {
     let all = block_on(commits.all())??;
     let tip = block_on(all.first())??;
     if let Some(tip) = tip {
@@ -66,6 +92,5 @@ pub fn clone(
         ),
     )?;
     metalog.commit(CommitOptions::default())?;
-
-    Ok(bookmarks)
+    Ok(())
 }
@@ -11,6 +11,10 @@ use thiserror::Error;
 #[error("unable to find formatter for template {0}")]
 pub struct FormatterNotFound(pub String);
 
+#[derive(Debug, Error)]
+#[error("unknown template keyword: {0}")]
+pub struct UnknownKeyword(pub String);
+
 #[derive(Debug, Error)]
 pub enum FormattingError {
     /// IO error likely caused by failure to write
@@ -21,6 +25,10 @@ pub enum FormattingError {
     #[error("Serializing Error")]
     JsonFormatterError(#[from] serde_json::Error),
 
+    /// Template referring to a keyword the item does not have
+    #[error(transparent)]
+    UnknownKeyword(#[from] UnknownKeyword),
+
     /// Non-IO error caused by `format_plain` application code
     #[error(transparent)]
     PlainFormattingError(#[from] anyhow::Error),
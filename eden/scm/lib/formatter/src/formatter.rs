@@ -13,6 +13,8 @@ use serde_json::to_writer_pretty;
 
 use crate::errors::FormatterNotFound;
 use crate::errors::FormattingError;
+use crate::errors::UnknownKeyword;
+use crate::template::Template;
 
 pub type FormatResult<T> = std::result::Result<T, FormattingError>;
 
@@ -28,6 +30,12 @@ pub struct FormatOptions {
 
 pub trait JsonFormattable {
     fn format_json(&self, writer: &mut dyn Write) -> Result<(), serde_json::Error>;
+
+    fn to_json_value(&self) -> Result<serde_json::Value, serde_json::Error> {
+        let mut buf = Vec::new();
+        self.format_json(&mut buf)?;
+        serde_json::from_slice(&buf)
+    }
 }
 
 impl<S: Serialize> JsonFormattable for S {
@@ -35,6 +43,10 @@ impl<S: Serialize> JsonFormattable for S {
         to_writer_pretty(writer, self)?;
         Ok(())
     }
+
+    fn to_json_value(&self) -> Result<serde_json::Value, serde_json::Error> {
+        serde_json::to_value(self)
+    }
 }
 
 pub trait StyleWrite: Write {
@@ -92,6 +104,12 @@ pub trait Formattable: JsonFormattable {
         options: &FormatOptions,
         writer: &mut dyn StyleWrite,
     ) -> Result<(), anyhow::Error>;
+
+    /// Keywords that are left out of the JSON value when they have no value.
+    /// Templates render them as empty instead of failing.
+    fn optional_keywords(&self) -> &'static [&'static str] {
+        &[]
+    }
 }
 
 pub trait ListFormatter {
@@ -112,6 +130,27 @@ pub struct JsonFormatter {
     first_item_formatted: bool,
 }
 
+pub struct TemplateFormatter {
+    writer: Box<dyn Write>,
+    options: FormatOptions,
+    styles: HashMap<String, String>,
+    styler: termstyle::Styler,
+    template: Template,
+    /// Output held back while a keyword of the template might still turn out
+    /// to be unknown. Failing with `UnknownKeyword` makes the caller rerun the
+    /// command in Python, so nothing may have been written by then.
+    pending: Option<Vec<u8>>,
+}
+
+impl TemplateFormatter {
+    fn flush_pending(&mut self) -> FormatResult<()> {
+        if let Some(pending) = self.pending.take() {
+            self.writer.write_all(&pending)?;
+        }
+        Ok(())
+    }
+}
+
 impl ListFormatter for PlainFormatter {
     fn format_item(&mut self, item: &dyn Formattable) -> FormatResult<()> {
         item.format_plain(
@@ -139,6 +178,49 @@ impl ListFormatter for PlainFormatter {
     }
 }
 
+impl ListFormatter for TemplateFormatter {
+    fn format_item(&mut self, item: &dyn Formattable) -> FormatResult<()> {
+        let value = item.to_json_value()?;
+        let buffering = self.pending.is_some();
+        let w: &mut dyn Write = match self.pending.as_mut() {
+            Some(pending) => pending,
+            None => self.writer.as_mut(),
+        };
+        self.template
+            .render(
+                &value,
+                item.optional_keywords(),
+                &mut PlainWriter {
+                    w,
+                    styler: &mut self.styler,
+                    styles: &self.styles,
+                    should_color: self.options.color,
+                    debug: self.options.debug_color,
+                },
+            )
+            .map_err(|err| match err.downcast::<std::io::Error>() {
+                Ok(io_err) => FormattingError::WriterError(io_err),
+                Err(err) if buffering => match err.downcast::<UnknownKeyword>() {
+                    Ok(unknown) => FormattingError::UnknownKeyword(unknown),
+                    Err(err) => FormattingError::PlainFormattingError(err),
+                },
+                Err(err) => FormattingError::PlainFormattingError(err),
+            })?;
+        if buffering && self.template.all_symbols_resolved() {
+            self.flush_pending()?;
+        }
+        Ok(())
+    }
+
+    fn begin_list(&mut self) -> FormatResult<()> {
+        Ok(())
+    }
+
+    fn end_list(&mut self) -> FormatResult<()> {
+        self.flush_pending()
+    }
+}
+
 impl ListFormatter for JsonFormatter {
     fn format_item(&mut self, item: &dyn Formattable) -> FormatResult<()> {
         let prev_separator = if self.first_item_formatted {
@@ -163,6 +245,40 @@ impl ListFormatter for JsonFormatter {
     }
 }
 
+fn get_styles(config: &dyn configmodel::Config) -> HashMap<String, String> {
+    config
+        .keys("color")
+        .into_iter()
+        .filter_map(|k| {
+            if !k.contains('.') || k.starts_with("color.") {
+                None
+            } else {
+                Some((
+                    k.to_string(),
+                    config.get("color", &k).unwrap_or_default().to_string(),
+                ))
+            }
+        })
+        .collect()
+}
+
+/// Find the literal template for `template`, which is either a template
+/// itself or the name of one in the `[templates]` config section.
+fn lookup_template(config: &dyn configmodel::Config, template: &str) -> Option<String> {
+    if template.contains('{') {
+        return Some(template.to_string());
+    }
+    let value = config.get("templates", template)?;
+    let value = value.as_ref();
+    let unquoted = match value.chars().next() {
+        Some(q @ ('"' | '\'')) if value.len() >= 2 && value.ends_with(q) => {
+            &value[1..value.len() - 1]
+        }
+        _ => value,
+    };
+    Some(unquoted.to_string())
+}
+
 pub fn get_formatter(
     config: &dyn configmodel::Config,
     _topic: &str,
@@ -172,21 +288,7 @@ pub fn get_formatter(
 ) -> anyhow::Result<Box<dyn ListFormatter>> {
     match template {
         "" => {
-            let styles: HashMap<String, String> = config
-                .keys("color")
-                .into_iter()
-                .filter_map(|k| {
-                    if !k.contains('.') || k.starts_with("color.") {
-                        None
-                    } else {
-                        Some((
-                            k.to_string(),
-                            config.get("color", &k).unwrap_or_default().to_string(),
-                        ))
-                    }
-                })
-                .collect();
-
+            let styles = get_styles(config);
             let styler = termstyle::Styler::new(options.pager_active)?;
             Ok(Box::new(PlainFormatter {
                 writer,
@@ -199,7 +301,21 @@ pub fn get_formatter(
             writer,
             first_item_formatted: false,
         })),
-        _ => Err(FormatterNotFound(template.into()).into()),
+        _ => {
+            let template = lookup_template(config, template)
+                .and_then(|t| Template::parse(&t).ok())
+                .ok_or_else(|| FormatterNotFound(template.into()))?;
+            let styles = get_styles(config);
+            let styler = termstyle::Styler::new(options.pager_active)?;
+            Ok(Box::new(TemplateFormatter {
+                writer,
+                options,
+                styles,
+                styler,
+                template,
+                pending: Some(Vec::new()),
+            }))
+        }
     }
 }
 
@@ -307,12 +423,12 @@ mod tests {
     #[test]
     fn test_formatter() {
         let buf = Rc::new(RefCell::new(Vec::<u8>::new()));
-        let err = get_trivial_formatter("{node|short}", buf.clone())
+        let err = get_trivial_formatter("{node|nonexistent}", buf.clone())
             .err()
             .unwrap();
         assert_eq!(
             err.to_string(),
-            "unable to find formatter for template {node|short}"
+            "unable to find formatter for template {node|nonexistent}"
         );
 
         let item = RequestTest {
@@ -327,6 +443,59 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_template_formatter() {
+        let item = RequestTest {
+            url: "foo://bar",
+            result: 200,
+        };
+        let buf = Rc::new(RefCell::new(Vec::<u8>::new()));
+        let mut fm =
+            get_trivial_formatter("{url} {label('foo.bar', result)}\\n", buf.clone()).unwrap();
+        fm.format_item(&item).unwrap();
+        fm.format_item(&item).unwrap();
+        fm.end_list().unwrap();
+        assert_eq!(
+            String::from_utf8(buf.as_ref().borrow().clone()).unwrap(),
+            "foo://bar \x1b[32m200\x1b[39m\nfoo://bar \x1b[32m200\x1b[39m\n",
+        );
+
+        // Templates that can't be handled in Rust, such as style names.
+        let buf = Rc::new(RefCell::new(Vec::<u8>::new()));
+        assert!(get_trivial_formatter("{node|nonexistent}", buf.clone()).is_err());
+        assert!(get_trivial_formatter("compact", buf).is_err());
+    }
+
+    #[test]
+    fn test_template_unknown_keyword() {
+        let item = RequestTest {
+            url: "foo://bar",
+            result: 200,
+        };
+        let other = RequestTest {
+            url: "foo://baz",
+            result: 404,
+        };
+
+        // Nothing is written before a keyword in an untaken branch fails.
+        let buf = Rc::new(RefCell::new(Vec::<u8>::new()));
+        let mut fm =
+            get_trivial_formatter("{url}{ifeq(result, 200, '', nonexistent)}\\n", buf.clone())
+                .unwrap();
+        fm.format_item(&item).unwrap();
+        assert!(matches!(
+            fm.format_item(&other).err().unwrap(),
+            FormattingError::UnknownKeyword(_)
+        ));
+        assert!(buf.borrow().is_empty());
+
+        // Output streams once every keyword has resolved.
+        let buf = Rc::new(RefCell::new(Vec::<u8>::new()));
+        let mut fm = get_trivial_formatter("{url}\\n", buf.clone()).unwrap();
+        fm.format_item(&item).unwrap();
+        assert_eq!(buf.borrow().as_slice(), b"foo://bar\n");
+    }
+
     #[derive(Serialize, Deserialize)]
     struct ColorfulItem {}
 
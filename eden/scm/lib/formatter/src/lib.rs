@@ -7,6 +7,7 @@
 
 pub mod errors;
 pub mod formatter;
+pub mod template;
 
 pub use formatter::FormatOptions;
 pub use formatter::Formattable;
@@ -0,0 +1,821 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+//! A subset of the Mercurial template language (`-T`), evaluated against the
+//! JSON serialization of the formatted item. Top-level fields of the item are
+//! the keywords.
+//!
+//! Templates using filters or functions that are not implemented here fail to
+//! parse, and keywords the item does not have fail to render with
+//! [`UnknownKeyword`], so callers can fall back to the Python implementation.
+//! A keyword in a branch that isn't taken for the first item may only fail
+//! for a later one, so [`Template::all_symbols_resolved`] tells callers when
+//! that can no longer happen.
+
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::fmt::Write as _;
+
+use anyhow::anyhow;
+use anyhow::bail;
+use anyhow::Result;
+use chrono::FixedOffset;
+use chrono::TimeZone;
+use serde_json::Map;
+use serde_json::Value;
+
+use crate::errors::UnknownKeyword;
+use crate::formatter::StyleWrite;
+
+const FILTERS: &[&str] = &[
+    "basename",
+    "count",
+    "date",
+    "firstline",
+    "hgdate",
+    "isodate",
+    "json",
+    "lower",
+    "short",
+    "shortdate",
+    "stringify",
+    "strip",
+    "upper",
+];
+
+/// Functions and their minimum and maximum number of arguments.
+const FUNCTIONS: &[(&str, usize, usize)] = &[
+    ("date", 1, 2),
+    ("get", 2, 2),
+    ("if", 2, 3),
+    ("ifcontains", 3, 4),
+    ("ifeq", 3, 4),
+    ("join", 2, 2),
+    ("label", 2, 2),
+    ("pad", 2, 4),
+    ("separate", 1, usize::MAX),
+    ("startswith", 2, 2),
+];
+
+#[derive(Debug)]
+pub struct Template {
+    nodes: Vec<Node>,
+    /// Every keyword the template refers to, in any branch.
+    symbols: HashSet<String>,
+    /// Keywords that have been looked up successfully while rendering.
+    resolved: RefCell<HashSet<String>>,
+}
+
+#[derive(Debug)]
+enum Node {
+    Text(String),
+    Expr(Expr),
+}
+
+#[derive(Debug)]
+enum Expr {
+    Integer(i64),
+    /// A raw string (`r'...'`), which isn't expanded.
+    String(String),
+    /// A quoted string, which is itself a template.
+    Template(Vec<Node>),
+    Symbol(String),
+    Filter(Box<Expr>, String),
+    Call(String, Vec<Expr>),
+    /// `list % template`: render `template` once per item of `list`.
+    Map(Box<Expr>, Box<Expr>),
+}
+
+impl Template {
+    pub fn parse(text: &str) -> Result<Self> {
+        let chars: Vec<char> = text.chars().collect();
+        let mut parser = Parser { chars, pos: 0 };
+        let nodes = parser.template()?;
+        let mut symbols = HashSet::new();
+        collect_symbols(&nodes, &mut symbols);
+        Ok(Template {
+            nodes,
+            symbols,
+            resolved: RefCell::new(HashSet::new()),
+        })
+    }
+
+    /// Whether every keyword in the template has been resolved by a previous
+    /// render, so that later renders can't fail with [`UnknownKeyword`].
+    pub fn all_symbols_resolved(&self) -> bool {
+        self.resolved.borrow().len() == self.symbols.len()
+    }
+
+    /// Render the template for `item`, writing labeled text to `writer`.
+    /// `optional` lists keywords that render as empty when `item` lacks them.
+    pub fn render(
+        &self,
+        item: &Value,
+        optional: &[&str],
+        writer: &mut dyn StyleWrite,
+    ) -> Result<()> {
+        let empty = Map::new();
+        let scope = Scope {
+            mapping: item.as_object().unwrap_or(&empty),
+            optional,
+            resolved: &self.resolved,
+            parent: None,
+        };
+        for segment in render_nodes(&self.nodes, &scope)? {
+            if segment.label.is_empty() {
+                writer.write_all(segment.text.as_bytes())?;
+            } else {
+                writer.write_styled(&segment.label, &segment.text)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek();
+        self.pos += 1;
+        c
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(c) if c.is_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<()> {
+        self.skip_whitespace();
+        match self.next() {
+            Some(c) if c == expected => Ok(()),
+            Some(c) => bail!("unexpected '{}' in template, expected '{}'", c, expected),
+            None => bail!("unterminated template expansion, expected '{}'", expected),
+        }
+    }
+
+    fn template(&mut self) -> Result<Vec<Node>> {
+        let mut nodes = Vec::new();
+        let mut text = String::new();
+        while let Some(c) = self.next() {
+            match c {
+                '\\' => match self.next() {
+                    Some('n') => text.push('\n'),
+                    Some('t') => text.push('\t'),
+                    Some('r') => text.push('\r'),
+                    Some('0') => text.push('\0'),
+                    Some(c @ ('\\' | '{' | '"' | '\'')) => text.push(c),
+                    Some(c) => {
+                        text.push('\\');
+                        text.push(c);
+                    }
+                    None => text.push('\\'),
+                },
+                '{' => {
+                    if !text.is_empty() {
+                        nodes.push(Node::Text(std::mem::take(&mut text)));
+                    }
+                    let expr = self.expr()?;
+                    self.expect('}')?;
+                    nodes.push(Node::Expr(expr));
+                }
+                c => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            nodes.push(Node::Text(text));
+        }
+        Ok(nodes)
+    }
+
+    fn expr(&mut self) -> Result<Expr> {
+        let mut expr = self.primary()?;
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                Some('|') => {
+                    self.pos += 1;
+                    self.skip_whitespace();
+                    let name = self.symbol()?;
+                    if !FILTERS.contains(&name.as_str()) {
+                        bail!("unknown template filter: {}", name);
+                    }
+                    expr = Expr::Filter(Box::new(expr), name);
+                }
+                Some('%') => {
+                    self.pos += 1;
+                    let template = self.primary()?;
+                    expr = Expr::Map(Box::new(expr), Box::new(template));
+                }
+                _ => return Ok(expr),
+            }
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expr> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('(') => {
+                self.pos += 1;
+                let expr = self.expr()?;
+                self.expect(')')?;
+                Ok(expr)
+            }
+            Some(q @ ('"' | '\'')) => {
+                self.pos += 1;
+                let raw = self.quoted(q)?;
+                Ok(Expr::Template(Template::parse(&raw)?.nodes))
+            }
+            Some('r') if matches!(self.chars.get(self.pos + 1), Some('"' | '\'')) => {
+                let q = self.chars[self.pos + 1];
+                self.pos += 2;
+                Ok(Expr::String(self.quoted(q)?))
+            }
+            Some(c) if c.is_ascii_digit() || c == '-' => {
+                let start = self.pos;
+                self.pos += 1;
+                while matches!(self.peek(), Some(c) if c.is_ascii_digit()) {
+                    self.pos += 1;
+                }
+                let digits: String = self.chars[start..self.pos].iter().collect();
+                Ok(Expr::Integer(digits.parse().map_err(|_| {
+                    anyhow!("invalid integer in template: {}", digits)
+                })?))
+            }
+            _ => {
+                let name = self.symbol()?;
+                self.skip_whitespace();
+                if self.peek() != Some('(') {
+                    return Ok(Expr::Symbol(name));
+                }
+                self.pos += 1;
+                let (min, max) = FUNCTIONS
+                    .iter()
+                    .find(|(n, _, _)| *n == name)
+                    .map(|(_, min, max)| (*min, *max))
+                    .ok_or_else(|| anyhow!("unknown template function: {}", name))?;
+                let mut args = Vec::new();
+                self.skip_whitespace();
+                if self.peek() == Some(')') {
+                    self.pos += 1;
+                } else {
+                    loop {
+                        args.push(self.expr()?);
+                        self.skip_whitespace();
+                        match self.next() {
+                            Some(',') => continue,
+                            Some(')') => break,
+                            _ => bail!("expected ',' or ')' in arguments to {}", name),
+                        }
+                    }
+                }
+                if args.len() < min || args.len() > max {
+                    bail!("{} got an invalid number of arguments", name);
+                }
+                Ok(Expr::Call(name, args))
+            }
+        }
+    }
+
+    fn symbol(&mut self) -> Result<String> {
+        let start = self.pos;
+        while matches!(self.peek(), Some(c) if c.is_alphanumeric() || c == '_' || c == '.') {
+            self.pos += 1;
+        }
+        if start == self.pos {
+            match self.peek() {
+                Some(c) => bail!("unexpected '{}' in template", c),
+                None => bail!("unterminated template expansion"),
+            }
+        }
+        Ok(self.chars[start..self.pos].iter().collect())
+    }
+
+    /// Read up to the closing `quote`. Escapes are kept so the content can be
+    /// parsed as a template.
+    fn quoted(&mut self, quote: char) -> Result<String> {
+        let mut s = String::new();
+        loop {
+            match self.next() {
+                Some('\\') => {
+                    s.push('\\');
+                    if let Some(c) = self.next() {
+                        s.push(c);
+                    }
+                }
+                Some(c) if c == quote => return Ok(s),
+                Some(c) => s.push(c),
+                None => bail!("unterminated string in template"),
+            }
+        }
+    }
+}
+
+/// A piece of rendered output, with the color label it should be written
+/// with (empty for no label).
+#[derive(Clone, Debug, PartialEq)]
+struct Segment {
+    label: String,
+    text: String,
+}
+
+/// The result of evaluating an expression: either a value from the item, or
+/// rendered (possibly labeled) text.
+enum Evaluated {
+    Value(Value),
+    Text(Vec<Segment>),
+}
+
+impl Evaluated {
+    fn text(s: String) -> Self {
+        Evaluated::Text(vec![Segment {
+            label: String::new(),
+            text: s,
+        }])
+    }
+
+    fn into_segments(self) -> Vec<Segment> {
+        match self {
+            Evaluated::Value(value) => vec![Segment {
+                label: String::new(),
+                text: stringify(&value),
+            }],
+            Evaluated::Text(segments) => segments,
+        }
+    }
+
+    fn to_text(&self) -> String {
+        match self {
+            Evaluated::Value(value) => stringify(value),
+            Evaluated::Text(segments) => segments.iter().map(|s| s.text.as_str()).collect(),
+        }
+    }
+
+    fn into_value(self) -> Value {
+        match self {
+            Evaluated::Value(value) => value,
+            text => Value::String(text.to_text()),
+        }
+    }
+
+    fn is_true(&self) -> bool {
+        match self {
+            Evaluated::Value(Value::Null) | Evaluated::Value(Value::Bool(false)) => false,
+            Evaluated::Value(Value::Array(a)) => !a.is_empty(),
+            Evaluated::Value(Value::Object(o)) => !o.is_empty(),
+            Evaluated::Value(Value::Number(n)) => n.as_f64() != Some(0.0),
+            e => !e.to_text().is_empty(),
+        }
+    }
+}
+
+struct Scope<'a> {
+    mapping: &'a Map<String, Value>,
+    optional: &'a [&'a str],
+    resolved: &'a RefCell<HashSet<String>>,
+    parent: Option<&'a Scope<'a>>,
+}
+
+impl Scope<'_> {
+    fn lookup(&self, name: &str) -> Option<&Value> {
+        self.mapping
+            .get(name)
+            .or_else(|| self.parent.and_then(|p| p.lookup(name)))
+    }
+}
+
+fn stringify(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::Bool(true) => "True".to_string(),
+        Value::Bool(false) => "False".to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => s.clone(),
+        Value::Array(items) => items.iter().map(stringify).collect::<Vec<_>>().join(" "),
+        Value::Object(map) => map
+            .iter()
+            .map(|(k, v)| format!("{}={}", k, stringify(v)))
+            .collect::<Vec<_>>()
+            .join(" "),
+    }
+}
+
+fn collect_symbols(nodes: &[Node], symbols: &mut HashSet<String>) {
+    for node in nodes {
+        if let Node::Expr(expr) = node {
+            collect_expr_symbols(expr, symbols);
+        }
+    }
+}
+
+fn collect_expr_symbols(expr: &Expr, symbols: &mut HashSet<String>) {
+    match expr {
+        Expr::Integer(_) | Expr::String(_) => {}
+        Expr::Template(nodes) => collect_symbols(nodes, symbols),
+        Expr::Symbol(name) => {
+            symbols.insert(name.clone());
+        }
+        Expr::Filter(expr, _) => collect_expr_symbols(expr, symbols),
+        Expr::Call(_, args) => {
+            for arg in args {
+                collect_expr_symbols(arg, symbols);
+            }
+        }
+        Expr::Map(list, template) => {
+            collect_expr_symbols(list, symbols);
+            collect_expr_symbols(template, symbols);
+        }
+    }
+}
+
+fn render_nodes(nodes: &[Node], scope: &Scope) -> Result<Vec<Segment>> {
+    let mut segments: Vec<Segment> = Vec::new();
+    for node in nodes {
+        let rendered = match node {
+            Node::Text(text) => Evaluated::text(text.clone()),
+            Node::Expr(expr) => eval(expr, scope)?,
+        };
+        for segment in rendered.into_segments() {
+            match segments.last_mut() {
+                Some(last) if last.label == segment.label => last.text.push_str(&segment.text),
+                _ if segment.text.is_empty() => {}
+                _ => segments.push(segment),
+            }
+        }
+    }
+    Ok(segments)
+}
+
+fn eval(expr: &Expr, scope: &Scope) -> Result<Evaluated> {
+    Ok(match expr {
+        Expr::Integer(i) => Evaluated::Value(Value::from(*i)),
+        Expr::String(s) => Evaluated::text(s.clone()),
+        Expr::Template(nodes) => Evaluated::Text(render_nodes(nodes, scope)?),
+        Expr::Symbol(name) => {
+            let value = match scope.lookup(name) {
+                Some(value) => value.clone(),
+                None if scope.optional.contains(&name.as_str()) => Value::Null,
+                None => bail!(UnknownKeyword(name.clone())),
+            };
+            if !scope.resolved.borrow().contains(name) {
+                scope.resolved.borrow_mut().insert(name.clone());
+            }
+            Evaluated::Value(value)
+        }
+        Expr::Filter(expr, name) => filter(name, eval(expr, scope)?)?,
+        Expr::Call(name, args) => call(name, args, scope)?,
+        Expr::Map(list, template) => {
+            let items = match eval(list, scope)?.into_value() {
+                Value::Array(items) => items,
+                Value::Null => Vec::new(),
+                v => bail!("{} is not iterable", stringify(&v)),
+            };
+            // Items that aren't objects are available as {value}, and as the
+            // singular of the list's name (e.g. {file} in "files % ...").
+            let singular = match &**list {
+                Expr::Symbol(name) => name.strip_suffix('s').map(|s| s.to_string()),
+                _ => None,
+            };
+            let mut segments = Vec::new();
+            for item in items {
+                let mapping = match item {
+                    Value::Object(map) => map,
+                    value => {
+                        let mut map = Map::new();
+                        if let Some(singular) = &singular {
+                            map.insert(singular.clone(), value.clone());
+                        }
+                        map.insert("value".to_string(), value);
+                        map
+                    }
+                };
+                let inner = Scope {
+                    mapping: &mapping,
+                    optional: scope.optional,
+                    resolved: scope.resolved,
+                    parent: Some(scope),
+                };
+                segments.extend(eval(template, &inner)?.into_segments());
+            }
+            Evaluated::Text(segments)
+        }
+    })
+}
+
+fn filter(name: &str, value: Evaluated) -> Result<Evaluated> {
+    Ok(match name {
+        "stringify" => Evaluated::text(value.to_text()),
+        "short" => Evaluated::text(value.to_text().chars().take(12).collect()),
+        "lower" => Evaluated::text(value.to_text().to_lowercase()),
+        "upper" => Evaluated::text(value.to_text().to_uppercase()),
+        "strip" => Evaluated::text(value.to_text().trim().to_string()),
+        "firstline" => Evaluated::text(value.to_text().lines().next().unwrap_or("").to_string()),
+        "basename" => {
+            let text = value.to_text();
+            let trimmed = text.trim_end_matches('/');
+            Evaluated::text(trimmed.rsplit('/').next().unwrap_or("").to_string())
+        }
+        "count" => Evaluated::Value(Value::from(match value.into_value() {
+            Value::Array(items) => items.len(),
+            Value::Object(map) => map.len(),
+            v => stringify(&v).chars().count(),
+        })),
+        "json" => Evaluated::text(serde_json::to_string(&value.into_value())?),
+        "date" => Evaluated::text(format_date(&value.into_value(), "%a %b %d %H:%M:%S %Y %z")?),
+        "isodate" => Evaluated::text(format_date(&value.into_value(), "%Y-%m-%d %H:%M %z")?),
+        "shortdate" => Evaluated::text(format_date(&value.into_value(), "%Y-%m-%d")?),
+        "hgdate" => {
+            let (unixtime, offset) = parse_date(&value.into_value())?;
+            Evaluated::text(format!("{} {}", unixtime, offset))
+        }
+        _ => bail!("unknown template filter: {}", name),
+    })
+}
+
+fn call(name: &str, args: &[Expr], scope: &Scope) -> Result<Evaluated> {
+    let arg =
+        |i: usize| -> Result<Option<Evaluated>> { args.get(i).map(|a| eval(a, scope)).transpose() };
+    let branch = |cond: bool, then: usize| -> Result<Evaluated> {
+        let index = if cond { then } else { then + 1 };
+        Ok(arg(index)?.unwrap_or_else(|| Evaluated::text(String::new())))
+    };
+
+    match name {
+        "if" => {
+            let cond = eval(&args[0], scope)?.is_true();
+            branch(cond, 1)
+        }
+        "ifeq" => {
+            let a = eval(&args[0], scope)?.to_text();
+            let b = eval(&args[1], scope)?.to_text();
+            branch(a == b, 2)
+        }
+        "ifcontains" => {
+            let needle = eval(&args[0], scope)?.to_text();
+            let found = match eval(&args[1], scope)?.into_value() {
+                Value::Array(items) => items.iter().any(|i| stringify(i) == needle),
+                Value::Object(map) => map.contains_key(&needle),
+                v => stringify(&v).contains(&needle),
+            };
+            branch(found, 2)
+        }
+        "join" => {
+            let sep = eval(&args[1], scope)?.to_text();
+            let text = match eval(&args[0], scope)?.into_value() {
+                Value::Array(items) => items.iter().map(stringify).collect::<Vec<_>>().join(&sep),
+                v => stringify(&v),
+            };
+            Ok(Evaluated::text(text))
+        }
+        "label" => {
+            let label = eval(&args[0], scope)?.to_text();
+            let segments = eval(&args[1], scope)?
+                .into_segments()
+                .into_iter()
+                .map(|s| Segment {
+                    label: if s.label.is_empty() {
+                        label.clone()
+                    } else {
+                        format!("{} {}", label, s.label)
+                    },
+                    text: s.text,
+                })
+                .collect();
+            Ok(Evaluated::Text(segments))
+        }
+        "date" => {
+            let date = eval(&args[0], scope)?.into_value();
+            let format = match arg(1)? {
+                Some(format) => format.to_text(),
+                None => "%a %b %d %H:%M:%S %Y %z".to_string(),
+            };
+            Ok(Evaluated::text(format_date(&date, &format)?))
+        }
+        "pad" => {
+            let text = eval(&args[0], scope)?.to_text();
+            let width: usize = eval(&args[1], scope)?
+                .to_text()
+                .parse()
+                .map_err(|_| anyhow!("pad expects an integer width"))?;
+            let fill = match arg(2)? {
+                Some(fill) => fill.to_text().chars().next().unwrap_or(' '),
+                None => ' ',
+            };
+            let left = match arg(3)? {
+                Some(left) => left.is_true(),
+                None => false,
+            };
+            let padding: String = std::iter::repeat(fill)
+                .take(width.saturating_sub(text.chars().count()))
+                .collect();
+            Ok(Evaluated::text(if left {
+                padding + &text
+            } else {
+                text + &padding
+            }))
+        }
+        "separate" => {
+            let sep = eval(&args[0], scope)?.into_segments();
+            let mut segments = Vec::new();
+            for arg in &args[1..] {
+                let value = eval(arg, scope)?;
+                if value.to_text().is_empty() {
+                    continue;
+                }
+                if !segments.is_empty() {
+                    segments.extend(sep.iter().cloned());
+                }
+                segments.extend(value.into_segments());
+            }
+            Ok(Evaluated::Text(segments))
+        }
+        "get" => {
+            let key = eval(&args[1], scope)?.to_text();
+            match eval(&args[0], scope)?.into_value() {
+                Value::Object(mut map) => {
+                    Ok(Evaluated::Value(map.remove(&key).unwrap_or(Value::Null)))
+                }
+                _ => bail!("get() expects a dict as first argument"),
+            }
+        }
+        "startswith" => {
+            let prefix = eval(&args[0], scope)?.to_text();
+            let text = eval(&args[1], scope)?.to_text();
+            Ok(Evaluated::text(if text.starts_with(&prefix) {
+                text
+            } else {
+                String::new()
+            }))
+        }
+        _ => bail!("unknown template function: {}", name),
+    }
+}
+
+/// Dates are either `[unixtime, offset]` pairs, like Python's `hg` uses, or
+/// a bare unixtime in UTC. The offset is in seconds west of UTC.
+fn parse_date(value: &Value) -> Result<(i64, i32)> {
+    let parsed = match value {
+        Value::Array(pair) if pair.len() == 2 => pair[0]
+            .as_f64()
+            .zip(pair[1].as_i64())
+            .map(|(t, o)| (t as i64, o as i32)),
+        Value::Number(n) => n.as_f64().map(|t| (t as i64, 0)),
+        _ => None,
+    };
+    parsed.ok_or_else(|| anyhow!("not a date: {}", stringify(value)))
+}
+
+fn format_date(value: &Value, format: &str) -> Result<String> {
+    let (unixtime, offset) = parse_date(value)?;
+    let tz = FixedOffset::west_opt(offset).ok_or_else(|| anyhow!("invalid offset {}", offset))?;
+    let date = tz
+        .timestamp_opt(unixtime, 0)
+        .single()
+        .ok_or_else(|| anyhow!("invalid timestamp {}", unixtime))?;
+    let mut out = String::new();
+    write!(out, "{}", date.format(format))
+        .map_err(|_| anyhow!("invalid date format {}", format))?;
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    struct Output(Vec<u8>);
+
+    impl std::io::Write for Output {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl StyleWrite for Output {
+        fn write_styled(&mut self, style: &str, text: &str) -> anyhow::Result<()> {
+            write!(self.0, "[{text}|{style}]")?;
+            Ok(())
+        }
+    }
+
+    fn render(template: &str, item: Value) -> String {
+        let mut out = Output(Vec::new());
+        Template::parse(template)
+            .unwrap()
+            .render(&item, &["missing"], &mut out)
+            .unwrap();
+        String::from_utf8(out.0).unwrap()
+    }
+
+    #[test]
+    fn test_keywords_and_filters() {
+        let item = json!({
+            "node": "1234567890abcdef1234",
+            "desc": "First line\nsecond line",
+            "files": ["a/b", "c"],
+            "date": [0, 3600],
+        });
+        assert_eq!(render("{node|short}\\n", item.clone()), "1234567890ab\n");
+        assert_eq!(render("{desc|firstline|upper}", item.clone()), "FIRST LINE");
+        assert_eq!(render("{files}", item.clone()), "a/b c");
+        assert_eq!(render("{files|count} {missing}", item.clone()), "2 ");
+        assert_eq!(render("{files|json}", item.clone()), r#"["a/b","c"]"#);
+        assert_eq!(
+            render("{date|date}", item.clone()),
+            "Wed Dec 31 23:00:00 1969 -0100"
+        );
+        assert_eq!(
+            render("{date|isodate}", item.clone()),
+            "1969-12-31 23:00 -0100"
+        );
+        assert_eq!(render("{date|shortdate}", item), "1969-12-31");
+    }
+
+    #[test]
+    fn test_functions() {
+        let item = json!({
+            "status": "M",
+            "path": "foo",
+            "copy": null,
+            "files": ["a", "b"],
+            "extra": {"branch": "default"},
+        });
+        assert_eq!(
+            render("{ifeq(status, 'M', 'modified', 'other')}", item.clone()),
+            "modified"
+        );
+        assert_eq!(render("{if(copy, 'copied', 'new')}", item.clone()), "new");
+        assert_eq!(render("{join(files, ', ')}", item.clone()), "a, b");
+        assert_eq!(render("{files % '<{file}>'}", item.clone()), "<a><b>");
+        assert_eq!(
+            render("{ifcontains('b', files, 'yes')}", item.clone()),
+            "yes"
+        );
+        assert_eq!(render("{get(extra, 'branch')}", item.clone()), "default");
+        assert_eq!(render("{pad(path, 5, '.')}|", item.clone()), "foo..|");
+        assert_eq!(
+            render("{separate(' ', status, copy, path)}", item.clone()),
+            "M foo"
+        );
+        assert_eq!(
+            render("{label('status.modified', '{status} {path}')}\\n", item),
+            "[M foo|status.modified]\n"
+        );
+    }
+
+    #[test]
+    fn test_unsupported() {
+        assert!(Template::parse("{node|nonexistent}").is_err());
+        assert!(Template::parse("{nonexistent(node)}").is_err());
+        assert!(Template::parse("{if(node)}").is_err());
+        assert!(Template::parse("{node").is_err());
+
+        let template = Template::parse("{node} {nonexistent}").unwrap();
+        let err = template
+            .render(&json!({"node": "abc"}), &[], &mut Output(Vec::new()))
+            .unwrap_err();
+        assert!(err.is::<UnknownKeyword>());
+    }
+
+    #[test]
+    fn test_all_symbols_resolved() {
+        let template = Template::parse("{if(copy, nonexistent, path)}").unwrap();
+        let mut out = Output(Vec::new());
+        template
+            .render(&json!({"copy": null, "path": "a"}), &[], &mut out)
+            .unwrap();
+        assert!(!template.all_symbols_resolved());
+        let err = template
+            .render(&json!({"copy": "b", "path": "a"}), &[], &mut out)
+            .unwrap_err();
+        assert!(err.is::<UnknownKeyword>());
+
+        let template = Template::parse("{files % '{file}'} {missing}").unwrap();
+        template
+            .render(&json!({"files": []}), &["missing"], &mut out)
+            .unwrap();
+        assert!(!template.all_symbols_resolved());
+        template
+            .render(&json!({"files": ["a"]}), &["missing"], &mut out)
+            .unwrap();
+        assert!(template.all_symbols_resolved());
+    }
+}
@@ -34,6 +34,14 @@ impl ReadFileContents for GitStore {
         });
         futures::stream::iter(iter).boxed()
     }
+
+    /// Git does not record copies.
+    async fn read_rename_metadata(
+        &self,
+        keys: Vec<Key>,
+    ) -> BoxStream<Result<(Option<Key>, Key), Self::Error>> {
+        futures::stream::iter(keys.into_iter().map(|k| Ok((None, k)))).boxed()
+    }
 }
 
 impl TreeStore for GitStore {
@@ -25,17 +25,29 @@ macro_rules! commands {
 }
 
 mod debug;
+mod track;
+mod walk;
 
 commands! {
+    mod add;
+    mod addremove;
+    mod annotate;
+    mod bookmark;
+    mod cat;
     mod clone;
     mod config;
+    mod files;
+    mod forget;
     mod goto;
+    mod remove;
     mod root;
     mod status;
     mod version;
     mod whereami;
 }
 
+use std::ops::Deref;
+
 pub use anyhow::Result;
 use clidispatch::command::CommandTable;
 use clidispatch::errors::FallbackToPython;
@@ -43,9 +55,16 @@ use clidispatch::global_flags::HgGlobalOpts;
 use clidispatch::io::Write;
 pub use clidispatch::io::IO;
 pub use cliparser::define_flags;
+use configmodel::ConfigExt;
 pub use configparser::config::ConfigSet;
 use formatter::formatter;
+use manifest_tree::ReadTreeManifest;
+use manifest_tree::TreeManifest;
 pub use repo::repo::Repo;
+use repo::trees::TreeManifestResolver;
+use revsets::utils::resolve_single;
+use types::HgId;
+use workingcopy::workingcopy::WorkingCopy;
 
 fn get_formatter(
     config: &dyn configmodel::Config,
@@ -72,6 +91,40 @@ fn get_formatter(
     .map_err(|_| FallbackToPython("template not supported in Rust".to_owned()))
 }
 
+/// Fall back to Python unless `<command>.use-rust` is set or `command` is
+/// listed in `commands.force-rust`.
+fn check_use_rust(config: &dyn configmodel::Config, command: &str) -> Result<()> {
+    let force_rust = config
+        .get_or_default::<Vec<String>>("commands", "force-rust")?
+        .iter()
+        .any(|name| name == command);
+    if !(force_rust || config.get_or_default(command, "use-rust")?) {
+        return Err(FallbackToPython(format!("{}.use-rust not set to True", command)).into());
+    }
+    Ok(())
+}
+
+/// Resolve a single revision, like "." or a commit hash.
+fn resolve_rev(repo: &mut Repo, wc: &WorkingCopy, rev: &str) -> Result<HgId> {
+    let id_map = repo.dag_commits()?.read().id_map_snapshot()?;
+    let metalog = repo.metalog()?;
+    let metalog = metalog.read();
+    let node = resolve_single(
+        rev,
+        id_map.as_ref(),
+        &metalog,
+        wc.treestate().lock().deref(),
+    )?;
+    Ok(node)
+}
+
+/// The root manifest of commit `node`.
+fn commit_manifest(repo: &mut Repo, node: &HgId) -> Result<TreeManifest> {
+    let resolver = TreeManifestResolver::new(repo.dag_commits()?, repo.tree_store()?);
+    let manifest = resolver.get(node)?.read().clone();
+    Ok(manifest)
+}
+
 #[allow(dead_code)]
 /// Return the main command table including all Rust commands.
 pub fn table() -> CommandTable {
@@ -0,0 +1,144 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+use std::collections::BTreeSet;
+
+use anyhow::Result;
+use clidispatch::ReqCtx;
+use cliparser::define_flags;
+use repo::repo::Repo;
+use treestate::filestate::StateFlags;
+use types::RepoPathBuf;
+use vfs::VFS;
+use workingcopy::track;
+use workingcopy::workingcopy::WorkingCopy;
+
+use super::track::check_enabled;
+use super::track::lock_working_copy;
+use super::walk::Walk;
+use crate::commands::WalkOpts;
+
+define_flags! {
+    pub struct AddOpts {
+        walk_opts: WalkOpts,
+
+        /// do not perform actions, just print output
+        #[short('n')]
+        dry_run: bool,
+
+        #[args]
+        args: Vec<String>,
+    }
+}
+
+pub fn run(ctx: ReqCtx<AddOpts>, repo: &mut Repo, wc: &mut WorkingCopy) -> Result<u8> {
+    check_enabled(repo.config(), "add", wc)?;
+
+    let opts = &ctx.opts;
+    let _wlock = lock_working_copy(repo, wc)?;
+    let walk = Walk::new(repo, &opts.args, &opts.walk_opts)?;
+    let status = walk.status(wc, repo.config())?;
+    let vfs = VFS::new(repo.path().to_path_buf())?;
+
+    let treestate = wc.treestate();
+    let mut treestate = treestate.lock();
+
+    let mut ret = 0;
+    let mut names: BTreeSet<RepoPathBuf> = status.unknown().cloned().collect();
+    for path in walk.explicit.iter().filter(|p| walk.is_exact(p)) {
+        match vfs.metadata(path) {
+            // Explicitly named files are added even if they are ignored.
+            Ok(meta) if !meta.is_dir() => {
+                names.insert(path.clone());
+            }
+            // Directories are walked by status.
+            Ok(_) => {}
+            Err(_) => {
+                ctx.io()
+                    .write_err(format!("{}: No such file or directory\n", walk.rel(path)))?;
+                ret = 1;
+            }
+        }
+    }
+
+    let mut added = Vec::with_capacity(names.len());
+    for path in names {
+        let tracked = treestate
+            .get(&path)?
+            .map_or(false, |state| state.state.contains(StateFlags::EXIST_NEXT));
+        if tracked {
+            ctx.io()
+                .write_err(format!("{} already tracked!\n", walk.rel(&path)))?;
+            continue;
+        }
+        if (ctx.global_opts().verbose || !walk.is_exact(&path)) && !ctx.global_opts().quiet {
+            ctx.io().write(format!("adding {}\n", walk.rel(&path)))?;
+        }
+        added.push(path);
+    }
+
+    if !opts.dry_run && !added.is_empty() {
+        for path in added.iter() {
+            track::add(&mut treestate, path)?;
+        }
+        track::flush(&mut treestate, repo.dot_hg_path())?;
+    }
+
+    Ok(ret)
+}
+
+pub fn aliases() -> &'static str {
+    "add"
+}
+
+pub fn doc() -> &'static str {
+    r#"start tracking the specified files
+
+    Specify files to be tracked by @Product@. The files will be added to
+    the repository at the next commit.
+
+    To undo an add before files have been committed, use :hg:`forget`.
+    To undo an add after files have been committed, use :hg:`rm`.
+
+    If no names are given, add all files to the repository (except
+    files matching ``.gitignore``).
+
+    .. container:: verbose
+
+       Examples:
+
+         - New (unknown) files are added
+           automatically by :hg:`add`::
+
+             $ ls
+             foo.c
+             $ @prog@ status
+             ? foo.c
+             $ @prog@ add
+             adding foo.c
+             $ @prog@ status
+             A foo.c
+
+         - Specific files to be added can be specified::
+
+             $ ls
+             bar.c  foo.c
+             $ @prog@ status
+             ? bar.c
+             ? foo.c
+             $ @prog@ add bar.c
+             $ @prog@ status
+             A bar.c
+             ? foo.c
+
+    Returns 0 if all files are successfully added.
+    "#
+}
+
+pub fn synopsis() -> Option<&'static str> {
+    Some("[OPTION]... [FILE]...")
+}
@@ -0,0 +1,373 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use anyhow::Result;
+use async_runtime::block_unless_interrupted as block_on;
+use clidispatch::errors;
+use clidispatch::ReqCtx;
+use cliparser::define_flags;
+use futures::TryStreamExt;
+use manifest::Manifest;
+use manifest_tree::ReadTreeManifest;
+use minibytes::Bytes;
+use repo::repo::Repo;
+use repo::trees::TreeManifestResolver;
+use treestate::filestate::StateFlags;
+use types::HgId;
+use types::Key;
+use types::RepoPathBuf;
+use vfs::VFS;
+use workingcopy::track;
+use workingcopy::workingcopy::WorkingCopy;
+
+use super::track::check_enabled;
+use super::track::lock_working_copy;
+use super::walk::Walk;
+use crate::commands::WalkOpts;
+
+define_flags! {
+    pub struct AddremoveOpts {
+        /// guess renamed files by similarity (0<=s<=100)
+        #[short('s')]
+        #[argtype("SIMILARITY")]
+        similarity: String,
+
+        walk_opts: WalkOpts,
+
+        /// do not perform actions, just print output
+        #[short('n')]
+        dry_run: bool,
+
+        #[args]
+        args: Vec<String>,
+    }
+}
+
+/// A removed file recorded as renamed to an added file.
+struct Rename {
+    old: RepoPathBuf,
+    new: RepoPathBuf,
+    score: f64,
+}
+
+pub fn run(ctx: ReqCtx<AddremoveOpts>, repo: &mut Repo, wc: &mut WorkingCopy) -> Result<u8> {
+    check_enabled(repo.config(), "addremove", wc)?;
+
+    let opts = &ctx.opts;
+    let similarity = if opts.similarity.is_empty() {
+        100.0
+    } else {
+        opts.similarity
+            .parse::<f64>()
+            .map_err(|_| errors::Abort("similarity must be a number".into()))?
+    };
+    if !(0.0..=100.0).contains(&similarity) {
+        return Err(errors::Abort("similarity must be between 0 and 100".into()).into());
+    }
+
+    let _wlock = lock_working_copy(repo, wc)?;
+    let walk = Walk::new(repo, &opts.args, &opts.walk_opts)?;
+    let status = walk.status(wc, repo.config())?;
+    let vfs = VFS::new(repo.path().to_path_buf())?;
+
+    let mut ret = 0;
+    let mut unknown: Vec<RepoPathBuf> = status.unknown().cloned().collect();
+    {
+        let treestate = wc.treestate();
+        let mut treestate = treestate.lock();
+        for path in walk.explicit.iter().filter(|p| walk.is_exact(p)) {
+            let tracked = treestate.get(path)?.map_or(false, |state| {
+                state.state.intersects(
+                    StateFlags::EXIST_P1 | StateFlags::EXIST_P2 | StateFlags::EXIST_NEXT,
+                )
+            });
+            if tracked || status.status(path).is_some() {
+                continue;
+            }
+            match vfs.metadata(path) {
+                // Explicitly named files are added even if they are ignored.
+                Ok(meta) if !meta.is_dir() => unknown.push(path.clone()),
+                Ok(_) => {}
+                Err(_) => {
+                    ctx.io()
+                        .write_err(format!("{}: No such file or directory\n", walk.rel(path)))?;
+                    ret = 1;
+                }
+            }
+        }
+    }
+    let deleted: Vec<RepoPathBuf> = status.deleted().cloned().collect();
+    // Removed files that exist again on disk are added back.
+    let (forgotten, removed): (Vec<RepoPathBuf>, Vec<RepoPathBuf>) = status
+        .removed()
+        .cloned()
+        .partition(|path| vfs.metadata(path).map_or(false, |meta| !meta.is_dir()));
+
+    if !ctx.global_opts().quiet {
+        let mut toprint: BTreeMap<&RepoPathBuf, &str> = BTreeMap::new();
+        toprint.extend(
+            unknown
+                .iter()
+                .chain(forgotten.iter())
+                .map(|p| (p, "adding")),
+        );
+        toprint.extend(deleted.iter().map(|p| (p, "removing")));
+        for (path, action) in toprint {
+            if ctx.global_opts().verbose || !walk.is_exact(path) {
+                ctx.io().write(format!("{} {}\n", action, walk.rel(path)))?;
+            }
+        }
+    }
+
+    let renames = if similarity > 0.0 {
+        let added: Vec<RepoPathBuf> = status.added().chain(unknown.iter()).cloned().collect();
+        let removed: Vec<RepoPathBuf> =
+            removed.into_iter().chain(deleted.iter().cloned()).collect();
+        find_renames(repo, wc, &vfs, added, removed, similarity / 100.0)?
+    } else {
+        Vec::new()
+    };
+    for rename in renames.iter() {
+        if (ctx.global_opts().verbose || !walk.is_exact(&rename.old) || !walk.is_exact(&rename.new))
+            && !ctx.global_opts().quiet
+        {
+            ctx.io().write(format!(
+                "recording removal of {} as rename to {} ({}% similar)\n",
+                walk.rel(&rename.old),
+                walk.rel(&rename.new),
+                (rename.score * 100.0) as u32,
+            ))?;
+        }
+    }
+
+    let changed =
+        !(deleted.is_empty() && unknown.is_empty() && forgotten.is_empty() && renames.is_empty());
+    if !opts.dry_run && changed {
+        let monitored = wc.file_system_type().is_monitored();
+        let treestate = wc.treestate();
+        let mut treestate = treestate.lock();
+        for path in deleted.iter() {
+            track::untrack(&mut treestate, path, monitored)?;
+        }
+        for path in unknown.iter().chain(forgotten.iter()) {
+            track::add(&mut treestate, path)?;
+        }
+        for rename in renames.iter() {
+            track::copy(&mut treestate, &rename.old, &rename.new)?;
+        }
+        track::flush(&mut treestate, repo.dot_hg_path())?;
+    }
+
+    Ok(ret)
+}
+
+/// Find removed files that were renamed to added files, like Python's
+/// `similar.findrenames`.
+///
+/// Identical files are matched first. With a `threshold` below 1, each
+/// remaining added file is matched with the removed file it shares the most
+/// lines with, if that is above `threshold`. Empty files are never matched.
+fn find_renames(
+    repo: &mut Repo,
+    wc: &WorkingCopy,
+    vfs: &VFS,
+    mut added: Vec<RepoPathBuf>,
+    mut removed: Vec<RepoPathBuf>,
+    threshold: f64,
+) -> Result<Vec<Rename>> {
+    added.sort();
+    removed.sort();
+
+    let mut added_data = Vec::with_capacity(added.len());
+    for path in added {
+        let data = vfs.read(&path)?;
+        if !data.is_empty() {
+            added_data.push((path, data));
+        }
+    }
+    if added_data.is_empty() {
+        return Ok(Vec::new());
+    }
+    let removed_data = read_parent_files(repo, wc, removed)?;
+
+    let mut renames = Vec::new();
+    let mut by_content: HashMap<&Bytes, &RepoPathBuf> = HashMap::new();
+    for (path, data) in removed_data.iter() {
+        by_content.entry(data).or_insert(path);
+    }
+    let mut matched = HashSet::new();
+    for (path, data) in added_data.iter() {
+        if let Some(old) = by_content.get(data) {
+            matched.insert(path);
+            renames.push(Rename {
+                old: (*old).clone(),
+                new: path.clone(),
+                score: 1.0,
+            });
+        }
+    }
+
+    if threshold < 1.0 {
+        for (path, data) in added_data.iter() {
+            if matched.contains(path) {
+                continue;
+            }
+            let mut best: Option<(&RepoPathBuf, f64)> = None;
+            for (old, old_data) in removed_data.iter() {
+                let candidate = score(old_data, data);
+                if candidate > best.map_or(threshold, |(_, best)| best) {
+                    best = Some((old, candidate));
+                }
+            }
+            if let Some((old, score)) = best {
+                renames.push(Rename {
+                    old: old.clone(),
+                    new: path.clone(),
+                    score,
+                });
+            }
+        }
+    }
+
+    Ok(renames)
+}
+
+/// Non-empty contents of `paths` in the first working parent.
+fn read_parent_files(
+    repo: &mut Repo,
+    wc: &WorkingCopy,
+    paths: Vec<RepoPathBuf>,
+) -> Result<Vec<(RepoPathBuf, Bytes)>> {
+    let p1 = match wc.treestate().lock().parents().next() {
+        Some(p1) => p1?,
+        None => HgId::null_id().clone(),
+    };
+    let resolver = TreeManifestResolver::new(repo.dag_commits()?, repo.tree_store()?);
+    let manifest = resolver.get(&p1)?;
+    let manifest = manifest.read();
+
+    let mut keys = Vec::with_capacity(paths.len());
+    for path in paths {
+        if let Some(meta) = manifest.get_file(&path)? {
+            keys.push(Key::new(path, meta.hgid));
+        }
+    }
+    if keys.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let file_store = repo.file_store()?;
+    let mut contents: Vec<(Bytes, Key)> = block_on(async {
+        file_store
+            .read_file_contents(keys)
+            .await
+            .try_collect()
+            .await
+    })??;
+    contents.sort_by(|a, b| a.1.path.cmp(&b.1.path));
+    Ok(contents
+        .into_iter()
+        .filter(|(data, _)| !data.is_empty())
+        .map(|(data, key)| (key.path, data))
+        .collect())
+}
+
+/// Similarity of two file contents, between 0 and 1.
+///
+/// This is the share of bytes in lines of `old` that are kept in `new`,
+/// like Python's `similar.score`.
+fn score(old: &[u8], new: &[u8]) -> f64 {
+    let lengths = old.len() + new.len();
+    if lengths == 0 {
+        return 1.0;
+    }
+    let line_lengths: Vec<usize> = old
+        .split_inclusive(|&b| b == b'\n')
+        .map(|l| l.len())
+        .collect();
+    let changed: usize = xdiff::diff_hunks(old, new)
+        .into_iter()
+        .map(|hunk| line_lengths[hunk.remove].iter().sum::<usize>())
+        .sum();
+    (old.len() - changed) as f64 * 2.0 / lengths as f64
+}
+
+pub fn aliases() -> &'static str {
+    "addremove|addr|addre|addrem|addremo|addremov"
+}
+
+pub fn doc() -> &'static str {
+    r#"add all new files, delete all missing files
+
+    Add all new files and remove all missing files from the
+    repository.
+
+    Unless names are given, new files are ignored if they match any of
+    the patterns in ``.gitignore``. As with add, these changes take
+    effect at the next commit.
+
+    Use the -s/--similarity option to detect renamed files. This
+    option takes a percentage between 0 (disabled) and 100 (files must
+    be identical) as its parameter. With a parameter greater than 0,
+    this compares every removed file with every added file and records
+    those similar enough as renames. Detecting renamed files this way
+    can be expensive. After using this option, :hg:`status -C` can be
+    used to check which files were identified as moved or renamed. If
+    not specified, -s/--similarity defaults to 100 and only renames of
+    identical files are detected.
+
+    .. container:: verbose
+
+       Examples:
+
+         - A number of files (bar.c and foo.c) are new,
+           while foobar.c has been removed (without using :hg:`remove`)
+           from the repository::
+
+             $ ls
+             bar.c foo.c
+             $ @prog@ status
+             ! foobar.c
+             ? bar.c
+             ? foo.c
+             $ @prog@ addremove
+             adding bar.c
+             adding foo.c
+             removing foobar.c
+             $ @prog@ status
+             A bar.c
+             A foo.c
+             R foobar.c
+
+         - A file foobar.c was moved to foo.c without using :hg:`rename`.
+           Afterwards, it was edited slightly::
+
+             $ ls
+             foo.c
+             $ @prog@ status
+             ! foobar.c
+             ? foo.c
+             $ @prog@ addremove --similarity 90
+             removing foobar.c
+             adding foo.c
+             recording removal of foobar.c as rename to foo.c (94% similar)
+             $ @prog@ status -C
+             A foo.c
+               foobar.c
+             R foobar.c
+
+    Returns 0 if all files are successfully added.
+    "#
+}
+
+pub fn synopsis() -> Option<&'static str> {
+    Some("[OPTION]... [FILE]...")
+}
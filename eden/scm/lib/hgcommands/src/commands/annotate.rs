@@ -0,0 +1,647 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+mod blame;
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::io::Write;
+use std::ops::Deref;
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_runtime::block_unless_interrupted as block_on;
+use blame::LineInfo;
+use chrono::FixedOffset;
+use chrono::TimeZone;
+use clidispatch::errors;
+use clidispatch::io::IsTty;
+use clidispatch::ReqCtx;
+use cliparser::define_flags;
+use dag::ops::IdConvert;
+use dag::DagAlgorithm;
+use dag::Set;
+use dag::Vertex;
+use futures::TryStreamExt;
+use hgcommits::ReadCommitText;
+use manifest::FsNodeMetadata;
+use manifest::Manifest;
+use manifest_tree::TreeManifest;
+use manifest_tree::TreeStore;
+use minibytes::Bytes;
+use pathhistory::PathHistory;
+use repo::repo::Repo;
+use revsets::utils::resolve_single;
+use storemodel::ReadFileContents;
+use storemodel::ReadRootTreeIds;
+use types::HgId;
+use types::Key;
+use types::RepoPathBuf;
+use workingcopy::workingcopy::WorkingCopy;
+
+use super::check_use_rust;
+use crate::commands::FormatterOpts;
+use crate::commands::WalkOpts;
+
+define_flags! {
+    pub struct AnnotateOpts {
+        /// annotate the specified revision
+        #[short('r')]
+        #[argtype("REV")]
+        rev: String,
+
+        /// don't follow copies and renames
+        no_follow: bool,
+
+        /// treat all files as text
+        #[short('a')]
+        text: bool,
+
+        /// list the author (long with -v)
+        #[short('u')]
+        user: bool,
+
+        /// list the filename
+        #[short('f')]
+        file: bool,
+
+        /// list the date (short with -q)
+        #[short('d')]
+        date: bool,
+
+        /// list the revision number (default)
+        #[short('n')]
+        number: bool,
+
+        /// list the changeset
+        #[short('c')]
+        changeset: bool,
+
+        /// show line number at the first appearance
+        #[short('l')]
+        line_number: bool,
+
+        /// revision to not display (EXPERIMENTAL)
+        #[argtype("REV")]
+        skip: Vec<String>,
+
+        /// ignore white space when comparing lines
+        #[short('w')]
+        ignore_all_space: bool,
+
+        /// ignore changes in the amount of white space
+        #[short('b')]
+        ignore_space_change: bool,
+
+        /// ignore changes whose lines are all blank
+        #[short('B')]
+        ignore_blank_lines: bool,
+
+        /// ignore changes in whitespace at EOL
+        #[short('Z')]
+        ignore_space_at_eol: bool,
+
+        walk_opts: WalkOpts,
+        formatter_opts: FormatterOpts,
+
+        #[args]
+        args: Vec<String>,
+    }
+}
+
+type ArcReadFileContents = Arc<dyn ReadFileContents<Error = anyhow::Error> + Send + Sync>;
+
+/// What's needed to look at the history of a file.
+struct Stores {
+    dag: Arc<dyn DagAlgorithm + Send + Sync>,
+    id_map: Arc<dyn IdConvert + Send + Sync>,
+    commit_reader: Arc<dyn ReadCommitText + Send + Sync>,
+    root_tree_reader: Arc<dyn ReadRootTreeIds + Send + Sync>,
+    tree_store: Arc<dyn TreeStore + Send + Sync>,
+    file_store: ArcReadFileContents,
+}
+
+/// A commit that introduced some of the annotated lines.
+struct Commit {
+    rev: u64,
+    node: HgId,
+    /// The name of the file in this commit, which differs from the annotated
+    /// path for lines from before a copy or rename.
+    path: RepoPathBuf,
+    user: String,
+    time: i64,
+    tz: i32,
+}
+
+pub fn run(ctx: ReqCtx<AnnotateOpts>, repo: &mut Repo, wc: &mut WorkingCopy) -> Result<u8> {
+    check_use_rust(repo.config(), "annotate")?;
+
+    let opts = &ctx.opts;
+    if !opts.formatter_opts.template.is_empty()
+        || !opts.walk_opts.include.is_empty()
+        || !opts.walk_opts.exclude.is_empty()
+        || opts.ignore_all_space
+        || opts.ignore_space_change
+        || opts.ignore_blank_lines
+        || opts.ignore_space_at_eol
+        || opts.args.iter().any(|arg| arg.contains(':'))
+    {
+        return Err(errors::FallbackToPython(
+            "one or more unsupported options in Rust annotate".to_owned(),
+        )
+        .into());
+    }
+
+    if opts.args.is_empty() {
+        return Err(errors::Abort("at least one filename or pattern is required".into()).into());
+    }
+
+    let number = opts.number || !(opts.user || opts.changeset || opts.date || opts.file);
+    if opts.line_number && !opts.changeset && !number {
+        return Err(errors::Abort("at least one of -n/-c is required for -l".into()).into());
+    }
+
+    let dag = repo.dag_commits()?;
+    let id_map = dag.read().id_map_snapshot()?;
+    let (node, skip) = {
+        let metalog = repo.metalog()?;
+        let metalog = metalog.read();
+        let treestate = wc.treestate();
+        let treestate = treestate.lock();
+        let resolve = |rev: &str| {
+            resolve_single(rev, id_map.as_ref(), &metalog, treestate.deref()).map_err(|_| {
+                errors::FallbackToPython(format!("revset {} not supported in Rust annotate", rev))
+            })
+        };
+
+        let node = resolve(if opts.rev.is_empty() { "." } else { &opts.rev })?;
+        let skip = opts
+            .skip
+            .iter()
+            .map(|rev| Ok(Vertex::copy_from(resolve(rev)?.as_ref())))
+            .collect::<Result<HashSet<_>, errors::FallbackToPython>>()?;
+        (node, skip)
+    };
+    let ancestors = {
+        let dag = dag.read();
+        block_on(dag.ancestors(Vertex::copy_from(node.as_ref()).into()))??
+    };
+
+    let stores = Stores {
+        dag: dag.read().dag_snapshot()?,
+        id_map,
+        commit_reader: dag.read().to_dyn_read_commit_text(),
+        root_tree_reader: dag.read().to_dyn_read_root_tree_ids(),
+        tree_store: repo.tree_store()?,
+        file_store: repo.file_store()?,
+    };
+    let tree_id = match block_on(stores.root_tree_reader.read_root_tree_ids(vec![node]))??
+        .into_iter()
+        .next()
+    {
+        Some((_, tree_id)) => tree_id,
+        None => HgId::null_id().clone(),
+    };
+    let manifest = TreeManifest::durable(stores.tree_store.clone(), tree_id);
+
+    let mut paths = Vec::with_capacity(opts.args.len());
+    for arg in opts.args.iter() {
+        let path = util::path::absolute(arg)?;
+        let path = match path.strip_prefix(repo.path()) {
+            Ok(path) => RepoPathBuf::try_from(path.to_path_buf())?,
+            Err(_) => {
+                return Err(errors::Abort(
+                    format!("{} not under root '{}'", arg, repo.path().display()).into(),
+                )
+                .into());
+            }
+        };
+        match manifest.get(&path)? {
+            Some(FsNodeMetadata::File(meta)) => paths.push((path, meta.hgid)),
+            Some(FsNodeMetadata::Directory(_)) => {
+                return Err(errors::FallbackToPython(
+                    "annotating directories is not supported in Rust".to_owned(),
+                )
+                .into());
+            }
+            None => {
+                return Err(errors::Abort(
+                    format!("{}: no such file in rev {}", path, &node.to_hex()[..12]).into(),
+                )
+                .into());
+            }
+        }
+    }
+
+    // Annotate everything before printing, so falling back to Python on
+    // merged history does not repeat output.
+    let mut annotated = Vec::with_capacity(paths.len());
+    for (path, file_id) in paths {
+        let content = block_on(read_file(&stores.file_store, &path, file_id))??;
+        let blame = if !opts.text && content.contains(&0) {
+            None
+        } else {
+            Some(block_on(annotate_file(
+                &stores,
+                ancestors.clone(),
+                &path,
+                &skip,
+                !opts.no_follow,
+            ))??)
+        };
+        annotated.push((path, content, blame));
+    }
+
+    if ctx.io().output().is_tty() {
+        ctx.io().start_pager(repo.config())?;
+    }
+
+    let global_opts = ctx.global_opts();
+    let mut out = ctx.io().output();
+    for (path, content, blame) in annotated {
+        let (commits, infos) = match blame {
+            Some(blame) => blame,
+            None => {
+                write!(out, "{}: binary file\n", arg_path(&path, repo)?)?;
+                continue;
+            }
+        };
+        let lines = blame::split_lines(&content);
+        if lines.is_empty() {
+            continue;
+        }
+
+        let mut columns: Vec<(&str, Vec<String>)> = Vec::new();
+        let commit = |info: &LineInfo| &commits[&info.version];
+        if opts.user {
+            columns.push((
+                " ",
+                infos
+                    .iter()
+                    .map(|info| match global_opts.verbose {
+                        true => commit(info).user.clone(),
+                        false => short_user(&commit(info).user).to_string(),
+                    })
+                    .collect(),
+            ));
+        }
+        if number {
+            columns.push((
+                " ",
+                infos
+                    .iter()
+                    .map(|info| commit(info).rev.to_string())
+                    .collect(),
+            ));
+        }
+        if opts.changeset {
+            columns.push((
+                " ",
+                infos
+                    .iter()
+                    .map(|info| match global_opts.debug {
+                        true => commit(info).node.to_hex(),
+                        false => commit(info).node.to_hex()[..12].to_string(),
+                    })
+                    .collect(),
+            ));
+        }
+        if opts.date {
+            let format = match global_opts.quiet {
+                true => "%Y-%m-%d",
+                false => "%a %b %d %H:%M:%S %Y %z",
+            };
+            columns.push((
+                " ",
+                infos
+                    .iter()
+                    .map(|info| format_date(commit(info).time, commit(info).tz, format))
+                    .collect(),
+            ));
+        }
+        if opts.file {
+            columns.push((
+                " ",
+                infos
+                    .iter()
+                    .map(|info| commit(info).path.to_string())
+                    .collect(),
+            ));
+        }
+        if opts.line_number {
+            columns.push((
+                ":",
+                infos
+                    .iter()
+                    .map(|info| (info.line + 1).to_string())
+                    .collect(),
+            ));
+        }
+
+        let widths: Vec<usize> = columns
+            .iter()
+            .map(|(_, values)| values.iter().map(|v| v.chars().count()).max().unwrap_or(0))
+            .collect();
+        for (i, (info, line)) in infos.iter().zip(lines.iter()).enumerate() {
+            for (col, ((sep, values), width)) in columns.iter().zip(widths.iter()).enumerate() {
+                let sep = if col == 0 { "" } else { sep };
+                write!(out, "{}{:>width$}", sep, values[i], width = width)?;
+            }
+            let sep = if info.skip { "* " } else { ": " };
+            write!(out, "{}{}", sep, String::from_utf8_lossy(line))?;
+        }
+        if !content.ends_with(b"\n") {
+            write!(out, "\n")?;
+        }
+    }
+
+    Ok(0)
+}
+
+/// Attribute each line of `path` at the head of `set` to a commit. Returns
+/// the commits that lines were attributed to, keyed by `LineInfo::version`,
+/// or `FallbackToPython` if the history of `path` is not linear.
+async fn annotate_file(
+    stores: &Stores,
+    set: Set,
+    path: &RepoPathBuf,
+    skip: &HashSet<Vertex>,
+    follow: bool,
+) -> Result<(HashMap<usize, Commit>, Vec<LineInfo>)> {
+    let history = file_history(stores, set, path, follow).await?;
+
+    // Versions are diffed one after another, which only matches Python if
+    // each one descends from the previous. Otherwise a merge combined two
+    // versions of the file and needs the merge-aware Python annotate.
+    for pair in history.windows(2) {
+        if !stores
+            .dag
+            .is_ancestor(pair[0].0.clone(), pair[1].0.clone())
+            .await?
+        {
+            return Err(errors::FallbackToPython(format!(
+                "annotating {} across a merge is not supported in Rust",
+                path
+            ))
+            .into());
+        }
+    }
+
+    let keys: HashSet<Key> = history.iter().filter_map(|(_, key)| key.clone()).collect();
+    let contents: HashMap<Key, Bytes> = stores
+        .file_store
+        .read_file_contents(keys.into_iter().collect())
+        .await
+        .map_ok(|(content, key)| (key, content))
+        .try_collect()
+        .await?;
+
+    let versions: Vec<(&[u8], bool)> = history
+        .iter()
+        .map(|(vertex, key)| {
+            let content = match key {
+                Some(key) => contents.get(key).map_or(&b""[..], |c| c.as_ref()),
+                None => b"",
+            };
+            (content, skip.contains(vertex))
+        })
+        .collect();
+    let infos = blame::annotate(&versions);
+
+    let mut commits = HashMap::new();
+    let used: Vec<usize> = infos
+        .iter()
+        .map(|info| info.version)
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    let used_vertexes: Vec<Vertex> = used.iter().map(|i| history[*i].0.clone()).collect();
+    let texts = stores
+        .commit_reader
+        .get_commit_raw_text_list(&used_vertexes)
+        .await?;
+    for ((version, vertex), text) in used.into_iter().zip(used_vertexes).zip(texts) {
+        let (user, time, tz) = parse_commit_text(&text);
+        let path = match &history[version].1 {
+            Some(key) => key.path.clone(),
+            None => path.clone(),
+        };
+        let commit = Commit {
+            rev: stores.id_map.vertex_id(vertex.clone()).await?.0,
+            node: HgId::from_slice(vertex.as_ref())?,
+            path,
+            user,
+            time,
+            tz,
+        };
+        commits.insert(version, commit);
+    }
+
+    Ok((commits, infos))
+}
+
+/// The commits in `set` that changed `path`, oldest first, with the file in
+/// each of them (`None` where it was deleted).
+///
+/// With `follow`, a version whose copy header names a source file continues
+/// with the history of the source before that commit, like Python's
+/// `filectx.parents()`. Falls back to Python if the source isn't the version
+/// last committed to its path.
+async fn file_history(
+    stores: &Stores,
+    set: Set,
+    path: &RepoPathBuf,
+    follow: bool,
+) -> Result<Vec<(Vertex, Option<Key>)>> {
+    let mut history = Vec::new();
+    let mut set = set;
+    let mut path = path.clone();
+    let mut expected: Option<HgId> = None;
+    loop {
+        let versions = path_history(stores, set, &path).await?;
+        if let Some(expected) = expected {
+            if versions.first().and_then(|(_, id)| *id) != Some(expected) {
+                return Err(errors::FallbackToPython(format!(
+                    "following the copy of {} is not supported in Rust",
+                    path
+                ))
+                .into());
+            }
+        }
+
+        let copies: HashMap<HgId, Key> = if follow {
+            let keys = versions
+                .iter()
+                .filter_map(|(_, id)| id.map(|id| Key::new(path.clone(), id)))
+                .collect();
+            stores
+                .file_store
+                .read_rename_metadata(keys)
+                .await
+                .try_filter_map(|(copy_from, key)| async move {
+                    Ok(copy_from.map(|copy_from| (key.hgid, copy_from)))
+                })
+                .try_collect()
+                .await?
+        } else {
+            HashMap::new()
+        };
+
+        let mut copied = None;
+        for (vertex, file_id) in versions {
+            history.push((vertex.clone(), file_id.map(|id| Key::new(path.clone(), id))));
+            if let Some(source) = file_id.and_then(|id| copies.get(&id)) {
+                copied = Some((vertex, source.clone()));
+                break;
+            }
+        }
+        match copied {
+            Some((vertex, source)) => {
+                let parents = stores.dag.parents(vertex.into()).await?;
+                set = stores.dag.ancestors(parents).await?;
+                path = source.path;
+                expected = Some(source.hgid);
+            }
+            None => break,
+        }
+    }
+    history.reverse();
+    Ok(history)
+}
+
+/// The commits in `set` that changed `path`, newest first, with the id of
+/// the file in each of them.
+async fn path_history(
+    stores: &Stores,
+    set: Set,
+    path: &RepoPathBuf,
+) -> Result<Vec<(Vertex, Option<HgId>)>> {
+    let mut history = PathHistory::new(
+        set,
+        vec![path.clone()],
+        stores.root_tree_reader.clone(),
+        stores.tree_store.clone(),
+    )
+    .await?;
+    let mut vertexes = Vec::new();
+    while let Some(vertex) = history.next().await? {
+        vertexes.push(vertex);
+    }
+
+    let nodes = vertexes
+        .iter()
+        .map(|v| Ok(HgId::from_slice(v.as_ref())?))
+        .collect::<Result<Vec<_>>>()?;
+    let trees: HashMap<HgId, HgId> = stores
+        .root_tree_reader
+        .read_root_tree_ids(nodes.clone())
+        .await?
+        .into_iter()
+        .collect();
+    let mut versions = Vec::with_capacity(nodes.len());
+    for (vertex, node) in vertexes.into_iter().zip(nodes) {
+        let file_id = match trees.get(&node) {
+            Some(tree_id) => TreeManifest::durable(stores.tree_store.clone(), *tree_id)
+                .get_file(path)?
+                .map(|meta| meta.hgid),
+            None => None,
+        };
+        versions.push((vertex, file_id));
+    }
+    Ok(versions)
+}
+
+async fn read_file(
+    file_store: &ArcReadFileContents,
+    path: &RepoPathBuf,
+    id: HgId,
+) -> Result<Bytes> {
+    let key = Key::new(path.clone(), id);
+    let mut contents: Vec<(Bytes, Key)> = file_store
+        .read_file_contents(vec![key])
+        .await
+        .try_collect()
+        .await?;
+    match contents.pop() {
+        Some((content, _)) => Ok(content),
+        None => Err(anyhow::anyhow!("cannot read {} at {}", path, id)),
+    }
+}
+
+/// The path as the user would refer to it from the current directory.
+fn arg_path(path: &RepoPathBuf, repo: &Repo) -> Result<String> {
+    let relativizer = types::path::RepoPathRelativizer::new(std::env::current_dir()?, repo.path());
+    Ok(relativizer.relativize(path))
+}
+
+/// Extract user, time and timezone from hg commit text.
+fn parse_commit_text(text: &[u8]) -> (String, i64, i32) {
+    let text = String::from_utf8_lossy(text);
+    let mut lines = text.lines().skip(1);
+    let user = lines.next().unwrap_or_default().to_string();
+    let mut date = lines.next().unwrap_or_default().split(' ');
+    let time = date
+        .next()
+        .and_then(|t| t.parse::<f64>().ok())
+        .unwrap_or_default() as i64;
+    let tz = date.next().and_then(|t| t.parse().ok()).unwrap_or_default();
+    (user, time, tz)
+}
+
+/// Like `ui.shortuser`: "Foo Bar <foo.bar@example.com>" becomes "foo".
+fn short_user(user: &str) -> &str {
+    let mut user = user;
+    if let Some(i) = user.find('@') {
+        user = &user[..i];
+    }
+    if let Some(i) = user.find('<') {
+        user = &user[i + 1..];
+    }
+    if let Some(i) = user.find(' ') {
+        user = &user[..i];
+    }
+    if let Some(i) = user.find('.') {
+        user = &user[..i];
+    }
+    user
+}
+
+/// Format a commit date. `tz` is in seconds west of UTC, like hg dates.
+fn format_date(time: i64, tz: i32, format: &str) -> String {
+    match FixedOffset::west_opt(tz).and_then(|offset| offset.timestamp_opt(time, 0).single()) {
+        Some(date) => date.format(format).to_string(),
+        None => time.to_string(),
+    }
+}
+
+pub fn aliases() -> &'static str {
+    "annotate|blame"
+}
+
+pub fn doc() -> &'static str {
+    r#"show changeset information by line for each file
+
+    List changes in files, showing the revision id responsible for
+    each line.
+
+    This command is useful for discovering when a change was made and
+    by whom.
+
+    If you include --file, --user, or --date, the revision number is
+    suppressed unless you also include --number.
+
+    Without the -a/--text option, annotate will avoid processing files
+    it detects as binary. With -a, annotate will annotate the file
+    anyway, although the results will probably be neither useful
+    nor desirable.
+
+    Returns 0 on success."#
+}
+
+pub fn synopsis() -> Option<&'static str> {
+    Some("[-r REV] [-f] [-a] [-u] [-d] [-n] [-c] [-l] FILE...")
+}
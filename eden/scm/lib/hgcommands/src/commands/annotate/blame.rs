@@ -0,0 +1,134 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+//! Line attribution over a linear list of file versions.
+
+/// Where a line of the last version came from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LineInfo {
+    /// Index of the version that introduced the line.
+    pub version: usize,
+    /// 0-based line number in that version.
+    pub line: usize,
+    /// Whether the line was attributed past a skipped version.
+    pub skip: bool,
+}
+
+/// Split `text` into lines, keeping line endings.
+pub fn split_lines(text: &[u8]) -> Vec<&[u8]> {
+    text.split_inclusive(|b| *b == b'\n').collect()
+}
+
+/// Attribute each line of the last version in `versions` to the version that
+/// introduced it. `versions` is oldest first; each entry is the file content
+/// and whether that version should be skipped.
+///
+/// Lines changed by a skipped version are attributed to the line at the same
+/// offset of the hunk they replaced, like Python's `annotate --skip`.
+pub fn annotate(versions: &[(&[u8], bool)]) -> Vec<LineInfo> {
+    let mut attrs: Vec<LineInfo> = Vec::new();
+    let mut prev: &[u8] = b"";
+    for (version, &(text, skip)) in versions.iter().enumerate() {
+        let line_count = split_lines(text).len();
+        let mut next = Vec::with_capacity(line_count);
+        let mut old_pos = 0;
+        for hunk in xdiff::diff_hunks(prev, text) {
+            // Unchanged lines before the hunk keep their attribution.
+            next.extend_from_slice(&attrs[old_pos..hunk.remove.start]);
+            for (offset, line) in hunk.add.clone().enumerate() {
+                let inherited = if !skip {
+                    None
+                } else if !hunk.remove.is_empty() {
+                    Some((hunk.remove.start + offset).min(hunk.remove.end - 1))
+                } else {
+                    hunk.remove.start.checked_sub(1)
+                };
+                next.push(match inherited {
+                    Some(old) => LineInfo {
+                        skip: true,
+                        ..attrs[old]
+                    },
+                    None => LineInfo {
+                        version,
+                        line,
+                        skip: false,
+                    },
+                });
+            }
+            old_pos = hunk.remove.end;
+        }
+        next.extend_from_slice(&attrs[old_pos..]);
+        debug_assert_eq!(next.len(), line_count);
+        attrs = next;
+        prev = text;
+    }
+    attrs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(versions: &[(&str, bool)]) -> Vec<(usize, usize, bool)> {
+        let versions: Vec<(&[u8], bool)> = versions
+            .iter()
+            .map(|(text, skip)| (text.as_bytes(), *skip))
+            .collect();
+        annotate(&versions)
+            .into_iter()
+            .map(|info| (info.version, info.line, info.skip))
+            .collect()
+    }
+
+    #[test]
+    fn test_split_lines() {
+        assert!(split_lines(b"").is_empty());
+        assert_eq!(split_lines(b"a\nb"), vec![&b"a\n"[..], &b"b"[..]]);
+    }
+
+    #[test]
+    fn test_annotate() {
+        assert!(render(&[]).is_empty());
+        assert_eq!(
+            render(&[("a\nb\n", false)]),
+            vec![(0, 0, false), (0, 1, false)]
+        );
+        assert_eq!(
+            render(&[
+                ("a\nb\n", false),
+                ("a\nx\nb\ny\n", false),
+                ("x\nb\ny\n", false)
+            ]),
+            vec![(1, 1, false), (0, 1, false), (1, 3, false)]
+        );
+        // Removed and re-added files start over.
+        assert_eq!(
+            render(&[("a\n", false), ("", false), ("a\n", false)]),
+            vec![(2, 0, false)]
+        );
+    }
+
+    #[test]
+    fn test_annotate_skip() {
+        // Replaced lines are attributed to the lines they replaced.
+        assert_eq!(
+            render(&[("a\nb\nc\n", false), ("a\nB\nC\nD\n", true)]),
+            vec![(0, 0, false), (0, 1, true), (0, 2, true), (0, 2, true)]
+        );
+        // Inserted lines are attributed to the line before them.
+        assert_eq!(
+            render(&[
+                ("a\nb\n", false),
+                ("a\nx\nb\n", true),
+                ("a\nx\nb\nc\n", false)
+            ]),
+            vec![(0, 0, false), (0, 0, true), (0, 1, false), (2, 3, false)]
+        );
+        // Nothing to inherit from at the start of the file.
+        assert_eq!(render(&[("a\n", true)]), vec![(0, 0, false)]);
+    }
+}
@@ -0,0 +1,565 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::Path;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use anyhow::Result;
+use async_runtime::block_unless_interrupted as block_on;
+use clidispatch::errors;
+use clidispatch::ReqCtx;
+use cliparser::define_flags;
+use dag::ops::IdConvert;
+use dag::DagAlgorithm;
+use dag::Vertex;
+use formatter::formatter::FormatOptions;
+use formatter::formatter::Formattable;
+use formatter::StyleWrite;
+use metalog::CommitOptions;
+use metalog::MetaLog;
+use repo::repo::Repo;
+use serde::Serialize;
+use types::HgId;
+use workingcopy::workingcopy::WorkingCopy;
+
+use super::check_use_rust;
+use super::get_formatter;
+use super::resolve_rev;
+use crate::commands::FormatterOpts;
+
+define_flags! {
+    pub struct BookmarkOpts {
+        /// force
+        #[short('f')]
+        force: bool,
+
+        /// revision for bookmark action
+        #[short('r')]
+        #[argtype("REV")]
+        rev: String,
+
+        /// delete a given bookmark
+        #[short('d')]
+        delete: bool,
+
+        /// like --delete, but also strip changesets
+        #[short('D')]
+        strip: bool,
+
+        /// rename a given bookmark
+        #[short('m')]
+        #[argtype("OLD")]
+        rename: String,
+
+        /// mark a bookmark inactive
+        #[short('i')]
+        inactive: bool,
+
+        /// list remote bookmarks with the given names
+        list_remote: bool,
+
+        /// name of the remote path to list the bookmarks
+        remote_path: String,
+
+        /// show both remote and local bookmarks
+        #[short('a')]
+        all: bool,
+
+        /// show only remote bookmarks (DEPRECATED)
+        remote: bool,
+
+        /// show only remote bookmarks that are available locally
+        list_subscriptions: bool,
+
+        /// track this bookmark or remote name
+        #[short('t')]
+        #[argtype("BOOKMARK")]
+        track: String,
+
+        /// remove tracking for this bookmark
+        #[short('u')]
+        untrack: bool,
+
+        formatter_opts: FormatterOpts,
+
+        #[args]
+        args: Vec<String>,
+    }
+}
+
+const ACTIVE_LABEL: &str = "bookmarks.active bookmarks.current";
+
+pub fn run(ctx: ReqCtx<BookmarkOpts>, repo: &mut Repo, wc: &mut WorkingCopy) -> Result<u8> {
+    let opts = &ctx.opts;
+    check_use_rust(repo.config(), "bookmark")?;
+
+    // Options handled by extensions or by other commands.
+    if opts.strip
+        || opts.all
+        || opts.remote
+        || opts.list_subscriptions
+        || !opts.track.is_empty()
+        || opts.untrack
+        || !opts.remote_path.is_empty()
+        || (opts.list_remote && opts.args.iter().any(|name| name.ends_with('*')))
+    {
+        return Err(errors::FallbackToPython(
+            "one or more unsupported options in Rust bookmark".to_owned(),
+        )
+        .into());
+    }
+
+    if opts.list_remote {
+        return list_remote(&ctx, repo);
+    }
+
+    if opts.delete && !opts.rename.is_empty() {
+        return Err(errors::Abort("--delete and --rename are incompatible".into()).into());
+    }
+    if opts.delete && !opts.rev.is_empty() {
+        return Err(errors::Abort("--rev is incompatible with --delete".into()).into());
+    }
+    if !opts.rename.is_empty() && !opts.rev.is_empty() {
+        return Err(errors::Abort("--rev is incompatible with --rename".into()).into());
+    }
+    if opts.args.is_empty() && (opts.delete || !opts.rev.is_empty()) {
+        return Err(errors::Abort("bookmark name required".into()).into());
+    }
+
+    if opts.delete || !opts.rename.is_empty() || !opts.args.is_empty() || opts.inactive {
+        let _wlock = repolock::lock_working_copy(repo.config(), repo.dot_hg_path())?;
+        // Like Python, commit the metalog under the store lock.
+        let _lock = repolock::lock_store(repo.config(), repo.store_path())?;
+        // Pick up changes made by others before the locks were taken.
+        repo.invalidate_metalog();
+        let mut change = Change::new(repo)?;
+        if opts.delete {
+            change.delete(&opts.args)?;
+        } else if !opts.rename.is_empty() {
+            change.rename(&ctx, repo, wc)?;
+        } else if !opts.args.is_empty() {
+            change.add(&ctx, repo, wc)?;
+        } else if change.bookmarks.is_empty() {
+            ctx.io().write("no bookmarks set\n")?;
+        } else if change.active.is_none() {
+            ctx.io().write("no active bookmark\n")?;
+        } else {
+            change.active = None;
+        }
+        change.commit(repo)?;
+        Ok(0)
+    } else {
+        show_bookmarks(&ctx, repo)
+    }
+}
+
+/// Pending changes to local bookmarks and the active bookmark.
+struct Change {
+    orig_bookmarks: BTreeMap<String, HgId>,
+    bookmarks: BTreeMap<String, HgId>,
+    orig_active: Option<String>,
+    active: Option<String>,
+}
+
+impl Change {
+    fn new(repo: &mut Repo) -> Result<Self> {
+        let bookmarks = read_bookmarks(&repo.metalog()?.read())?;
+        let active = read_active(repo.dot_hg_path(), &bookmarks)?;
+        Ok(Self {
+            orig_bookmarks: bookmarks.clone(),
+            bookmarks,
+            orig_active: active.clone(),
+            active,
+        })
+    }
+
+    fn delete(&mut self, names: &[String]) -> Result<()> {
+        for name in names {
+            if self.bookmarks.remove(name).is_none() {
+                // Might be a remote scratch bookmark.
+                return Err(errors::FallbackToPython(format!(
+                    "bookmark {} is not a local bookmark",
+                    name
+                ))
+                .into());
+            }
+            if self.active.as_ref() == Some(name) {
+                self.active = None;
+            }
+        }
+        Ok(())
+    }
+
+    fn rename(
+        &mut self,
+        ctx: &ReqCtx<BookmarkOpts>,
+        repo: &mut Repo,
+        wc: &WorkingCopy,
+    ) -> Result<()> {
+        let opts = &ctx.opts;
+        let new = match &opts.args[..] {
+            [] => return Err(errors::Abort("new bookmark name required".into()).into()),
+            [new] => check_name(new)?,
+            _ => return Err(errors::Abort("only one new bookmark name allowed".into()).into()),
+        };
+        let old = &opts.rename;
+        let node = match self.bookmarks.get(old) {
+            Some(node) => *node,
+            None => {
+                return Err(
+                    errors::Abort(format!("bookmark '{}' does not exist", old).into()).into(),
+                );
+            }
+        };
+        self.check_conflict(ctx, repo, wc, &new, None)?;
+        self.bookmarks.remove(old);
+        self.bookmarks.insert(new.clone(), node);
+        if self.active.as_ref() == Some(old) {
+            self.active = if opts.inactive { None } else { Some(new) };
+        }
+        Ok(())
+    }
+
+    fn add(&mut self, ctx: &ReqCtx<BookmarkOpts>, repo: &mut Repo, wc: &WorkingCopy) -> Result<()> {
+        let opts = &ctx.opts;
+        let current = resolve(repo, wc, ".")?;
+        let target = if opts.rev.is_empty() {
+            current
+        } else {
+            resolve(repo, wc, &opts.rev)?
+        };
+
+        let mut first = None;
+        for name in opts.args.iter() {
+            let name = check_name(name)?;
+            if opts.inactive && self.active.as_ref() == Some(&name) {
+                self.active = None;
+                return Ok(());
+            }
+            self.check_conflict(ctx, repo, wc, &name, Some(target))?;
+            self.bookmarks.insert(name.clone(), target);
+            first.get_or_insert(name);
+        }
+
+        if !opts.inactive && opts.rev.is_empty() && target == current {
+            self.active = first;
+        } else if target != current && first.is_some() && first == self.active {
+            self.active = None;
+        }
+        Ok(())
+    }
+
+    /// Check whether `name` can be set to `target`. With a `target`, an
+    /// existing bookmark can be moved forward without --force.
+    fn check_conflict(
+        &self,
+        ctx: &ReqCtx<BookmarkOpts>,
+        repo: &mut Repo,
+        wc: &WorkingCopy,
+        name: &str,
+        target: Option<HgId>,
+    ) -> Result<()> {
+        let old = match self.bookmarks.get(name) {
+            Some(old) if !ctx.opts.force => *old,
+            _ => return Ok(()),
+        };
+        if let Some(target) = target {
+            if old == target && target == resolve(repo, wc, ".")? {
+                // Re-activating a bookmark.
+                return Ok(());
+            }
+            let dag = repo.dag_commits()?;
+            let forward = block_on(dag.read().is_ancestor(
+                Vertex::copy_from(old.as_ref()),
+                Vertex::copy_from(target.as_ref()),
+            ))??;
+            if forward && old != target {
+                ctx.io().write(format!(
+                    "moving bookmark '{}' forward from {}\n",
+                    name,
+                    &old.to_hex()[..12]
+                ))?;
+                return Ok(());
+            }
+        }
+        Err(
+            errors::Abort(format!("bookmark '{}' already exists (use -f to force)", name).into())
+                .into(),
+        )
+    }
+
+    /// Write changed bookmarks to the metalog and the active bookmark to disk.
+    fn commit(self, repo: &mut Repo) -> Result<()> {
+        if self.bookmarks != self.orig_bookmarks {
+            let metalog = repo.metalog()?;
+            let mut metalog = metalog.write();
+            metalog.set("bookmarks", &refencode::encode_bookmarks(&self.bookmarks))?;
+            let message = format!(
+                "{}\nParent: {}\nTransaction: bookmark",
+                std::env::args().skip(1).collect::<Vec<_>>().join(" "),
+                metalog.root_id().to_hex(),
+            );
+            let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+            metalog.commit(CommitOptions {
+                message: &message,
+                timestamp,
+                ..Default::default()
+            })?;
+        }
+        if self.active != self.orig_active {
+            write_active(repo.dot_hg_path(), self.active.as_deref())?;
+        }
+        Ok(())
+    }
+}
+
+fn read_bookmarks(metalog: &MetaLog) -> Result<BTreeMap<String, HgId>> {
+    Ok(match metalog.get("bookmarks")? {
+        Some(encoded) => refencode::decode_bookmarks(&encoded)?,
+        None => Default::default(),
+    })
+}
+
+/// The active bookmark, as stored in `.hg/bookmarks.current`.
+fn read_active(dot_hg: &Path, bookmarks: &BTreeMap<String, HgId>) -> Result<Option<String>> {
+    match util::file::read_to_string(dot_hg.join("bookmarks.current")) {
+        Ok(name) if bookmarks.contains_key(&name) => Ok(Some(name)),
+        Ok(_) => Ok(None),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+fn write_active(dot_hg: &Path, name: Option<&str>) -> Result<()> {
+    let path = dot_hg.join("bookmarks.current");
+    match name {
+        Some(name) => {
+            util::file::atomic_write(&path, |f| f.write_all(name.as_bytes()))?;
+        }
+        None => {
+            if let Err(err) = std::fs::remove_file(&path) {
+                if err.kind() != std::io::ErrorKind::NotFound {
+                    return Err(err.into());
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Validate a new bookmark name, like Python's `bookmarks.checkformat`.
+fn check_name(name: &str) -> Result<String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(
+            errors::Abort("bookmark names cannot consist entirely of whitespace".into()).into(),
+        );
+    }
+    if ["tip", ".", "null"].contains(&name) {
+        return Err(errors::Abort(format!("the name '{}' is reserved", name).into()).into());
+    }
+    for c in [':', '\0', '\n', '\r'] {
+        if name.contains(c) {
+            return Err(errors::Abort(format!("{:?} cannot be used in a name", c).into()).into());
+        }
+    }
+    if name.parse::<i64>().is_ok() {
+        return Err(errors::Abort("cannot use an integer as a name".into()).into());
+    }
+    Ok(name.to_string())
+}
+
+fn resolve(repo: &mut Repo, wc: &WorkingCopy, rev: &str) -> Result<HgId> {
+    resolve_rev(repo, wc, rev).map_err(|_| {
+        errors::FallbackToPython(format!("revset {} not supported in Rust bookmark", rev)).into()
+    })
+}
+
+#[derive(Serialize)]
+struct BookmarkItem {
+    bookmark: String,
+    node: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rev: Option<u64>,
+    active: bool,
+    #[serde(skip_serializing)]
+    remote: bool,
+}
+
+impl Formattable for BookmarkItem {
+    fn format_plain(
+        &self,
+        options: &FormatOptions,
+        writer: &mut dyn StyleWrite,
+    ) -> std::result::Result<(), anyhow::Error> {
+        let label = if self.active { ACTIVE_LABEL } else { "" };
+        if options.quiet {
+            writer.write_styled(label, &self.bookmark)?;
+        } else {
+            let prefix = match (self.remote, self.active) {
+                (true, _) => "   ",
+                (false, true) => " * ",
+                (false, false) => "   ",
+            };
+            let node = if options.debug || self.remote {
+                self.node.clone()
+            } else {
+                self.node[..12].to_string()
+            };
+            let node = match self.rev {
+                Some(rev) => format!("{}:{}", rev, node),
+                None => node,
+            };
+            let pad = 25usize.saturating_sub(self.bookmark.chars().count());
+            writer.write_styled(label, prefix)?;
+            writer.write_styled(
+                label,
+                &format!("{}{} {}", self.bookmark, " ".repeat(pad), node),
+            )?;
+        }
+        writer.write_all(b"\n")?;
+        Ok(())
+    }
+
+    fn optional_keywords(&self) -> &'static [&'static str] {
+        &["rev"]
+    }
+}
+
+fn show_bookmarks(ctx: &ReqCtx<BookmarkOpts>, repo: &mut Repo) -> Result<u8> {
+    let bookmarks = read_bookmarks(&repo.metalog()?.read())?;
+    let active = read_active(repo.dot_hg_path(), &bookmarks)?;
+
+    if bookmarks.is_empty()
+        && ctx.opts.formatter_opts.template.is_empty()
+        && !ctx.global_opts().quiet
+    {
+        ctx.io().write("no bookmarks set\n")?;
+        return Ok(0);
+    }
+
+    let mut formatter = get_formatter(
+        repo.config(),
+        "bookmarks",
+        &ctx.opts.formatter_opts.template,
+        ctx.global_opts(),
+        Box::new(ctx.io().output()),
+    )?;
+
+    // Python shows revision numbers with HGPLAIN.
+    let id_map = if hgplain::is_plain(None) {
+        Some(repo.dag_commits()?.read().id_map_snapshot()?)
+    } else {
+        None
+    };
+
+    formatter.begin_list()?;
+    for (name, node) in bookmarks {
+        let rev = match &id_map {
+            Some(id_map) => Some(block_on(id_map.vertex_id(Vertex::copy_from(node.as_ref())))??.0),
+            None => None,
+        };
+        formatter.format_item(&BookmarkItem {
+            active: active.as_ref() == Some(&name),
+            bookmark: name,
+            node: node.to_hex(),
+            rev,
+            remote: false,
+        })?;
+    }
+    formatter.end_list()?;
+
+    Ok(0)
+}
+
+fn list_remote(ctx: &ReqCtx<BookmarkOpts>, repo: &mut Repo) -> Result<u8> {
+    if ctx.opts.args.is_empty() {
+        return Err(errors::Abort("--list-remote requires a bookmark pattern".into()).into());
+    }
+
+    let eden_api = repo.eden_api()?;
+    let entries = block_on(eden_api.bookmarks(ctx.opts.args.clone()))??;
+
+    let mut formatter = get_formatter(
+        repo.config(),
+        "bookmarks",
+        &ctx.opts.formatter_opts.template,
+        ctx.global_opts(),
+        Box::new(ctx.io().output()),
+    )?;
+    formatter.begin_list()?;
+    let mut entries: Vec<_> = entries
+        .into_iter()
+        .filter_map(|entry| Some((entry.bookmark, entry.hgid?)))
+        .collect();
+    entries.sort();
+    for (name, node) in entries {
+        formatter.format_item(&BookmarkItem {
+            bookmark: name,
+            node: node.to_hex(),
+            rev: None,
+            active: false,
+            remote: true,
+        })?;
+    }
+    formatter.end_list()?;
+
+    Ok(0)
+}
+
+pub fn aliases() -> &'static str {
+    "bookmarks|bookmark|bo|boo|book|bookm|bookma|bookmar"
+}
+
+pub fn doc() -> &'static str {
+    r#"create a new bookmark or list existing bookmarks
+
+    Bookmarks are labels on changesets to help track lines of development.
+    Bookmarks are unversioned and can be moved, renamed and deleted.
+    Deleting or moving a bookmark has no effect on the associated changesets.
+
+    Creating or updating to a bookmark causes it to be marked as 'active'.
+    The active bookmark is indicated with a '*'.
+    When a commit is made, the active bookmark will advance to the new commit.
+    A plain :hg:`update` will also advance an active bookmark, if possible.
+    Updating away from a bookmark will cause it to be deactivated.
+
+    With --list-remote, list the bookmarks with the given names on the
+    server.
+
+    .. container:: verbose
+
+      Examples:
+
+      - create an active bookmark for a new line of development::
+
+          @prog@ book new-feature
+
+      - create an inactive bookmark as a place marker::
+
+          @prog@ book -i reviewed
+
+      - create an inactive bookmark on another changeset::
+
+          @prog@ book -r .^ tested
+
+      - rename bookmark turkey to dinner::
+
+          @prog@ book -m turkey dinner
+
+      - move the '@' bookmark from another branch::
+
+          @prog@ book -f @
+    "#
+}
+
+pub fn synopsis() -> Option<&'static str> {
+    Some("[OPTIONS]... [NAME]...")
+}
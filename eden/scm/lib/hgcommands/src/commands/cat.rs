@@ -0,0 +1,325 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+use std::io::Write;
+use std::path::Path;
+
+use anyhow::Result;
+use async_runtime::block_unless_interrupted as block_on;
+use clidispatch::errors;
+use clidispatch::io::IsTty;
+use clidispatch::ReqCtx;
+use cliparser::define_flags;
+use configmodel::Config;
+use dag::ops::IdConvert;
+use dag::Vertex;
+use formatter::formatter::FormatOptions;
+use formatter::formatter::Formattable;
+use formatter::StyleWrite;
+use futures::TryStreamExt;
+use manifest::FsNodeMetadata;
+use manifest::Manifest;
+use minibytes::Bytes;
+use repo::repo::Repo;
+use serde::Serialize;
+use types::HgId;
+use types::Key;
+use types::RepoPath;
+use types::RepoPathBuf;
+use workingcopy::workingcopy::WorkingCopy;
+
+use super::check_use_rust;
+use super::commit_manifest;
+use super::get_formatter;
+use super::resolve_rev;
+use super::walk::Walk;
+use crate::commands::FormatterOpts;
+use crate::commands::WalkOpts;
+
+/// Number of files fetched from scmstore at once.
+const BATCH_SIZE: usize = 1000;
+
+define_flags! {
+    pub struct CatOpts {
+        /// print output to file with formatted name
+        #[short('o')]
+        #[argtype("FORMAT")]
+        output: String,
+
+        /// print the given revision
+        #[short('r')]
+        #[argtype("REV")]
+        rev: String,
+
+        /// apply any matching decode filter
+        decode: bool,
+
+        walk_opts: WalkOpts,
+        formatter_opts: FormatterOpts,
+
+        #[args]
+        args: Vec<String>,
+    }
+}
+
+#[derive(Serialize)]
+struct CatItem {
+    data: String,
+    abspath: String,
+    path: String,
+}
+
+impl Formattable for CatItem {
+    fn format_plain(
+        &self,
+        _options: &FormatOptions,
+        writer: &mut dyn StyleWrite,
+    ) -> std::result::Result<(), anyhow::Error> {
+        writer.write_all(self.data.as_bytes())?;
+        Ok(())
+    }
+}
+
+pub fn run(ctx: ReqCtx<CatOpts>, repo: &mut Repo, wc: &mut WorkingCopy) -> Result<u8> {
+    check_use_rust(repo.config(), "cat")?;
+
+    let opts = &ctx.opts;
+    if opts.args.is_empty() {
+        return Err(errors::FallbackToPython("cat requires a file name".to_owned()).into());
+    }
+    if opts.decode && has_decode_filters(repo.config()) {
+        return Err(errors::FallbackToPython(
+            "decode filters are not supported in Rust".to_owned(),
+        )
+        .into());
+    }
+    // Like Python, "-" means stdout.
+    let output = if opts.output == "-" { "" } else { &opts.output };
+    if !output.is_empty() && !opts.formatter_opts.template.is_empty() {
+        return Err(errors::FallbackToPython(
+            "--output with --template is not supported in Rust".to_owned(),
+        )
+        .into());
+    }
+
+    let rev = if opts.rev.is_empty() { "." } else { &opts.rev };
+    let node = resolve_rev(repo, wc, rev).map_err(|_| {
+        errors::FallbackToPython(format!("revset {} not supported in Rust cat", rev))
+    })?;
+    let manifest = commit_manifest(repo, &node)?;
+    let walk = Walk::new(repo, &opts.args, &opts.walk_opts)?;
+
+    // Look up files named on the command line directly, so printing one file
+    // does not walk the whole tree.
+    let mut files: Vec<(RepoPathBuf, HgId)> = Vec::new();
+    let mut walk_tree = walk.explicit.len() != opts.args.len();
+    for path in walk.explicit.iter() {
+        match manifest.get(path)? {
+            Some(FsNodeMetadata::File(meta)) => {
+                if walk.matcher.matches_file(path)? {
+                    files.push((path.clone(), meta.hgid));
+                }
+            }
+            Some(FsNodeMetadata::Directory(_)) => walk_tree = true,
+            None => {
+                ctx.io().write_err(format!(
+                    "{}: no such file in rev {}\n",
+                    walk.rel(path),
+                    &node.to_hex()[..12]
+                ))?;
+            }
+        }
+    }
+    if walk_tree {
+        files.clear();
+        for file in manifest.files(walk.matcher.clone()) {
+            let file = file?;
+            files.push((file.path, file.meta.hgid));
+        }
+    }
+    files.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+    files.dedup();
+
+    let namer = if output.is_empty() {
+        None
+    } else {
+        Some(OutputName::new(repo, output, &node)?)
+    };
+    let mut formatter = if namer.is_none() {
+        if ctx.io().output().is_tty() {
+            ctx.io().start_pager(repo.config())?;
+        }
+        if opts.formatter_opts.template.is_empty() {
+            None
+        } else {
+            let mut formatter = get_formatter(
+                repo.config(),
+                "cat",
+                &opts.formatter_opts.template,
+                ctx.global_opts(),
+                Box::new(ctx.io().output()),
+            )?;
+            formatter.begin_list()?;
+            Some(formatter)
+        }
+    } else {
+        None
+    };
+
+    let file_store = repo.file_store()?;
+    for chunk in files.chunks(BATCH_SIZE) {
+        let keys: Vec<Key> = chunk
+            .iter()
+            .map(|(path, hgid)| Key::new(path.clone(), *hgid))
+            .collect();
+        let mut contents: Vec<(Bytes, Key)> = block_on(async {
+            file_store
+                .read_file_contents(keys)
+                .await
+                .try_collect()
+                .await
+        })??;
+        contents.sort_by(|a, b| a.1.path.cmp(&b.1.path));
+
+        for (data, key) in contents {
+            if let Some(namer) = &namer {
+                let filename = namer.expand(&key.path)?;
+                if let Some(parent) = Path::new(&filename).parent() {
+                    // Like Python, try to create the directory and let
+                    // writing the file report any error.
+                    let _ = std::fs::create_dir_all(parent);
+                }
+                std::fs::write(&filename, &data)?;
+            } else if let Some(formatter) = formatter.as_mut() {
+                formatter.format_item(&CatItem {
+                    data: String::from_utf8_lossy(&data).into_owned(),
+                    abspath: key.path.to_string(),
+                    path: walk.rel(&key.path),
+                })?;
+            } else {
+                ctx.io().output().write_all(&data)?;
+            }
+        }
+    }
+
+    if let Some(mut formatter) = formatter {
+        formatter.end_list()?;
+    }
+
+    Ok(if files.is_empty() { 1 } else { 0 })
+}
+
+/// Whether `--decode` would change file contents.
+fn has_decode_filters(config: &dyn Config) -> bool {
+    let eol = config
+        .get("extensions", "eol")
+        .map_or(false, |value| !value.starts_with('!'));
+    eol || !config.keys("decode").is_empty()
+}
+
+/// File names for `--output`, like Python's `cmdutil.makefilename`.
+struct OutputName {
+    template: String,
+    node: HgId,
+    rev: Option<u64>,
+    root_name: String,
+}
+
+impl OutputName {
+    fn new(repo: &mut Repo, template: &str, node: &HgId) -> Result<Self> {
+        if template.contains("%m") {
+            return Err(errors::FallbackToPython(
+                "%m in --output is not supported in Rust".to_owned(),
+            )
+            .into());
+        }
+        let rev = if template.contains("%R") || template.contains("%r") {
+            let id_map = repo.dag_commits()?.read().id_map_snapshot()?;
+            Some(block_on(id_map.vertex_id(Vertex::copy_from(node.as_ref())))??.0)
+        } else {
+            None
+        };
+        let root_name = repo
+            .path()
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        Ok(Self {
+            template: template.to_string(),
+            node: node.clone(),
+            rev,
+            root_name,
+        })
+    }
+
+    fn expand(&self, path: &RepoPath) -> Result<String> {
+        let mut name = String::with_capacity(self.template.len());
+        let mut chars = self.template.chars();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                name.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('%') => name.push('%'),
+                Some('s') => name.push_str(path.last_component().map_or("", |c| c.as_str())),
+                Some('d') => match path.parent() {
+                    Some(dir) if !dir.is_empty() => name.push_str(dir.as_str()),
+                    _ => name.push('.'),
+                },
+                Some('p') => name.push_str(path.as_str()),
+                Some('H') => name.push_str(&self.node.to_hex()),
+                Some('h') => name.push_str(&self.node.to_hex()[..12]),
+                Some('R') | Some('r') => name.push_str(&self.rev.unwrap_or_default().to_string()),
+                Some('b') => name.push_str(&self.root_name),
+                Some(c) => {
+                    return Err(errors::Abort(
+                        format!("invalid format spec '%{}' in output filename", c).into(),
+                    )
+                    .into());
+                }
+                None => {
+                    return Err(
+                        errors::Abort("incomplete format spec in output filename".into()).into(),
+                    );
+                }
+            }
+        }
+        Ok(name)
+    }
+}
+
+pub fn aliases() -> &'static str {
+    "cat"
+}
+
+pub fn doc() -> &'static str {
+    r#"output the current or given revision of files
+
+    Print the specified files as they were at the given revision. If
+    no revision is given, the parent of the working directory is used.
+
+    Output may be to a file, in which case the name of the file is
+    given using a format string. The formatting rules as follows:
+
+    :``%%``: literal "%" character
+    :``%s``: basename of file being printed
+    :``%d``: dirname of file being printed, or '.' if in repository root
+    :``%p``: root-relative path name of file being printed
+    :``%H``: changeset hash (40 hexadecimal digits)
+    :``%R``: changeset revision number
+    :``%h``: short-form changeset hash (12 hexadecimal digits)
+    :``%r``: zero-padded changeset revision number
+    :``%b``: basename of the exporting repository
+
+    Returns 0 on success.
+    "#
+}
+
+pub fn synopsis() -> Option<&'static str> {
+    Some("[OPTION]... FILE...")
+}
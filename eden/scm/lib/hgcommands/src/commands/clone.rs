@@ -18,7 +18,11 @@ use clidispatch::output::new_logger;
 use clidispatch::output::TermLogger;
 use clidispatch::ReqCtx;
 use cliparser::define_flags;
+use clone::CloneCheckout;
+use clone::ClonePhase;
+use clone::CloneState;
 use configmodel::ConfigExt;
+use dag::DagAlgorithm;
 use migration::feature::deprecate;
 use repo::repo::Repo;
 use tracing::instrument;
@@ -74,6 +78,9 @@ define_flags! {
         /// location of the backing repo to be used or created (EXPERIMENTAL)
         eden_backing_repo: String,
 
+        /// resume an interrupted clone (EXPERIMENTAL)
+        resume: bool,
+
         #[arg]
         source: String,
 
@@ -123,6 +130,16 @@ pub fn run(mut ctx: ReqCtx<CloneOpts>, config: &mut ConfigSet) -> Result<u8> {
         "--noupdate is not compatible with --eden",
     );
 
+    abort_if!(
+        ctx.opts.eden && ctx.opts.resume,
+        "--resume is not compatible with --eden",
+    );
+
+    abort_if!(
+        ctx.opts.noupdate && !ctx.opts.updaterev.is_empty(),
+        "cannot specify both --noupdate and --updaterev",
+    );
+
     let force_rust = config
         .get_or_default::<Vec<String>>("commands", "force-rust")?
         .contains(&"clone".to_owned());
@@ -142,7 +159,9 @@ pub fn run(mut ctx: ReqCtx<CloneOpts>, config: &mut ConfigSet) -> Result<u8> {
         Ok(url) => url.scheme() != "file" && url.scheme() != "ssh",
     };
 
-    if !ctx.opts.updaterev.is_empty()
+    // Only a full commit hash given to -u can be checked out without
+    // resolving it like Python does.
+    if (!ctx.opts.updaterev.is_empty() && HgId::from_hex(ctx.opts.updaterev.as_bytes()).is_err())
         || !ctx.opts.rev.is_empty()
         || ctx.opts.pull
         || ctx.opts.stream
@@ -223,6 +242,10 @@ pub fn run(mut ctx: ReqCtx<CloneOpts>, config: &mut ConfigSet) -> Result<u8> {
         tracing::debug!(target: "clone_info", cloned_sparse_profiles=ctx.opts.enable_profile.join(" "));
     }
 
+    if ctx.opts.resume {
+        return resume_clone(&ctx, &mut logger, &destination);
+    }
+
     if let Some(ident) = identity::sniff_dir(&destination)? {
         abort!(
             "{} directory already exists at clone destination {}",
@@ -257,7 +280,8 @@ pub fn run(mut ctx: ReqCtx<CloneOpts>, config: &mut ConfigSet) -> Result<u8> {
             )?
         };
         let target_rev =
-            get_update_target(&mut logger, &mut backing_repo, &ctx.opts)?.map(|(rev, _)| rev);
+            get_update_target(&mut logger, &mut backing_repo, &clone_checkout(&ctx.opts))?
+                .map(|(rev, _)| rev);
         logger.verbose(|| {
             format!(
                 "Performing EdenFS clone {}@{} from {} to {}",
@@ -268,33 +292,100 @@ pub fn run(mut ctx: ReqCtx<CloneOpts>, config: &mut ConfigSet) -> Result<u8> {
             )
         });
         clone::eden_clone(&backing_repo, &destination, target_rev)?;
+        if let Some(state) = CloneState::load(backing_repo.dot_hg_path())? {
+            state.finish()?;
+        }
     } else {
         let mut repo = try_clone_metadata(&ctx, &mut logger, config, &reponame, &destination)?;
+        let state = CloneState::load(repo.dot_hg_path())?;
+        init_working_copy(&mut logger, &mut repo, state, clone_checkout(&ctx.opts))?;
+    }
 
-        let target_rev = get_update_target(&mut logger, &mut repo, &ctx.opts)?;
-        if let Some((target_rev, bm)) = &target_rev {
-            logger.info(format!("Checking out '{}'", bm));
-            logger.verbose(|| {
-                format!(
-                    "Initializing non-EdenFS working copy to commit {}",
-                    target_rev.to_hex(),
-                )
-            });
-        } else {
-            logger.verbose("Initializing empty non-EdenFS working copy");
-        }
+    Ok(0)
+}
 
-        clone::init_working_copy(
-            &mut logger,
-            &mut repo,
-            target_rev.map(|(rev, _)| rev),
-            ctx.opts.enable_profile.clone(),
-        )?;
-    }
+/// Continue a clone interrupted after `clone.resumable` kept its progress.
+fn resume_clone(
+    ctx: &ReqCtx<CloneOpts>,
+    logger: &mut TermLogger,
+    destination: &Path,
+) -> Result<u8> {
+    let state = match identity::sniff_dir(destination)? {
+        Some(ident) => CloneState::load(&destination.join(ident.dot_dir()))?,
+        None => None,
+    };
+    let mut state = match state {
+        Some(state) => state,
+        None => abort!("no interrupted clone at {}", destination.display()),
+    };
+    let mut repo = Repo::load(
+        destination,
+        &ctx.global_opts().config,
+        &ctx.global_opts().configfile,
+    )?;
 
+    logger.verbose(|| format!("Resuming clone after phase {:?}", state.phase()));
+    if state.phase() < ClonePhase::Commits {
+        pull_commits(logger, &mut repo, &mut state)?;
+    }
+    // Check out what the interrupted clone was asked to, rather than what
+    // the options of the resuming command say.
+    let checkout = state.checkout().clone();
+    init_working_copy(logger, &mut repo, Some(state), checkout)?;
     Ok(0)
 }
 
+/// What `opts` ask to check out at the end of the clone.
+fn clone_checkout(opts: &CloneOpts) -> CloneCheckout {
+    CloneCheckout {
+        noupdate: opts.noupdate,
+        updaterev: HgId::from_hex(opts.updaterev.as_bytes()).ok(),
+        sparse_profiles: opts.enable_profile.clone(),
+    }
+}
+
+fn init_working_copy(
+    logger: &mut TermLogger,
+    repo: &mut Repo,
+    mut state: Option<CloneState>,
+    checkout: CloneCheckout,
+) -> Result<()> {
+    let target_rev = get_update_target(logger, repo, &checkout)?;
+    if let Some((target_rev, bm)) = &target_rev {
+        logger.info(format!("Checking out '{}'", bm));
+        logger.verbose(|| {
+            format!(
+                "Initializing non-EdenFS working copy to commit {}",
+                target_rev.to_hex(),
+            )
+        });
+    } else {
+        logger.verbose("Initializing empty non-EdenFS working copy");
+    }
+
+    if let Some(state) = state.as_mut() {
+        state.advance(ClonePhase::Checkout)?;
+        // Keep track of the files written so that resuming an interrupted
+        // checkout skips them.
+        repo.config_mut().set(
+            "checkout",
+            "resumable",
+            Some("true"),
+            &"clone.resumable".into(),
+        );
+    }
+    clone::init_working_copy(
+        logger,
+        repo,
+        target_rev.map(|(rev, _)| rev),
+        checkout.sparse_profiles,
+    )?;
+    if let Some(state) = state {
+        state.finish()?;
+    }
+    Ok(())
+}
+
 fn try_clone_metadata(
     ctx: &ReqCtx<CloneOpts>,
     logger: &mut TermLogger,
@@ -305,6 +396,16 @@ fn try_clone_metadata(
     let dest_preexists = destination.exists();
     match clone_metadata(ctx, logger, config, reponame, destination) {
         Err(e) => {
+            if config.get_or_default::<bool>("clone", "resumable")? && has_clone_state(destination)?
+            {
+                logger.warn(format!(
+                    "Clone interrupted. Resume with '{} clone --resume {} {}'",
+                    logger.cli_name(),
+                    ctx.opts.source,
+                    destination.display(),
+                ));
+                return Err(e);
+            }
             let removal_dir = if dest_preexists {
                 let ident = identity::sniff_dir(destination)?.unwrap_or_else(identity::sniff_env);
                 destination.join(ident.dot_dir())
@@ -318,6 +419,18 @@ fn try_clone_metadata(
     }
 }
 
+fn has_clone_state(destination: &Path) -> Result<bool> {
+    Ok(match identity::sniff_dir(destination)? {
+        Some(ident) => util::file::exists(
+            destination
+                .join(ident.dot_dir())
+                .join(clone::CLONE_STATE_FILE),
+        )?
+        .is_some(),
+        None => false,
+    })
+}
+
 #[instrument(skip_all, fields(repo=reponame), err)]
 fn clone_metadata(
     ctx: &ReqCtx<CloneOpts>,
@@ -364,17 +477,9 @@ fn clone_metadata(
     if segmented_changelog {
         repo.add_store_requirement("lazychangelog")?;
 
-        let bookmark_names: Vec<String> = get_selective_bookmarks(&repo)?;
-        let metalog = repo.metalog()?;
-        let commits = repo.dag_commits()?;
+        let mut state = CloneState::init(repo.dot_hg_path(), clone_checkout(&ctx.opts))?;
         tracing::trace!("fetching lazy commit data and bookmarks");
-        let bookmark_ids = exchange::clone(
-            edenapi,
-            &mut metalog.write(),
-            &mut commits.write(),
-            bookmark_names,
-        )?;
-        logger.verbose(|| format!("Pulled bookmarks {:?}", bookmark_ids));
+        pull_commits(logger, &mut repo, &mut state)?;
     } else {
         revlog_clone(repo.config(), logger, ctx, destination)?;
         // reload the repo to pick up any changes written out by the revlog clone
@@ -392,6 +497,36 @@ fn clone_metadata(
     Ok(repo)
 }
 
+/// Fetch bookmarks and the segmented changelog, skipping the phases that
+/// `state` records as completed.
+fn pull_commits(logger: &mut TermLogger, repo: &mut Repo, state: &mut CloneState) -> Result<()> {
+    let edenapi = repo.eden_api()?;
+    if state.phase() < ClonePhase::Bookmarks {
+        let bookmark_names: Vec<String> = get_selective_bookmarks(repo)?;
+        let bookmark_ids = exchange::fetch_bookmarks(&edenapi, bookmark_names)?;
+        state.set_bookmarks(bookmark_ids)?;
+    }
+    if state.phase() < ClonePhase::Commits {
+        let commits = repo.dag_commits()?;
+        // The import is flushed at once, so a non-empty graph means it
+        // completed before the clone was interrupted.
+        let all = block_on(commits.read().all())??;
+        if block_on(all.count())?? == 0 {
+            let heads = state.bookmarks().values().cloned().collect();
+            exchange::import_clone_data(&edenapi, &mut commits.write(), heads)?;
+        }
+        let metalog = repo.metalog()?;
+        exchange::record_bookmarks(
+            &mut metalog.write(),
+            &mut commits.write(),
+            state.bookmarks(),
+        )?;
+        state.advance(ClonePhase::Commits)?;
+    }
+    logger.verbose(|| format!("Pulled bookmarks {:?}", state.bookmarks()));
+    Ok(())
+}
+
 pub fn revlog_clone(
     config: &ConfigSet,
     logger: &mut TermLogger,
@@ -447,11 +582,14 @@ fn get_selective_bookmarks(repo: &Repo) -> Result<Vec<String>> {
 fn get_update_target(
     logger: &mut TermLogger,
     repo: &mut Repo,
-    clone_opts: &CloneOpts,
+    checkout: &CloneCheckout,
 ) -> Result<Option<(HgId, String)>> {
-    if clone_opts.noupdate {
+    if checkout.noupdate {
         return Ok(None);
     }
+    if let Some(rev) = checkout.updaterev {
+        return Ok(Some((rev, rev.to_hex())));
+    }
     let selective_bookmarks = get_selective_bookmarks(repo)?;
     let main_bookmark = selective_bookmarks
         .first()
@@ -550,6 +688,10 @@ pub fn doc() -> &'static str {
       repository may be rolled back to a partial clone. This behavior may
       change in future releases. See :hg:`help -e clonebundles` for more.
 
+      With ``clone.resumable`` set, an interrupted clone keeps what it
+      fetched so far. Use --resume with the same source and destination
+      to continue from the last completed step.
+
       Examples:
 
       - clone a remote repository to a new directory named hg/::
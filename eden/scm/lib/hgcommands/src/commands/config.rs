@@ -15,7 +15,9 @@ use clidispatch::io::IsTty;
 use clidispatch::OptionalRepo;
 use clidispatch::ReqCtx;
 use cliparser::define_flags;
+use configmodel::schema::Schema;
 use configmodel::ConfigExt;
+use configparser::validate::validate;
 use configparser::Config;
 use formatter::formatter::FormatOptions;
 use formatter::formatter::Formattable;
@@ -48,6 +50,9 @@ define_flags! {
         #[short('g')]
         global: bool,
 
+        /// check config items in sections with a registered schema
+        validate: bool,
+
         formatter_opts: FormatterOpts,
 
         #[args]
@@ -62,6 +67,11 @@ pub fn run(ctx: ReqCtx<ConfigOpts>, repo: &mut OptionalRepo) -> Result<u8> {
         .contains(&"config".to_owned());
     let use_rust = force_rust || config.get_or_default("config", "use-rust")?;
 
+    // Python does not know about the schema.
+    if ctx.opts.validate {
+        return validate_configs(&ctx, config);
+    }
+
     if !use_rust {
         bail!(errors::FallbackToPython(
             "config.use-rust not set to True".to_owned()
@@ -197,6 +207,14 @@ fn get_config_item<'a>(
     })
 }
 
+fn validate_configs(ctx: &ReqCtx<ConfigOpts>, config: &ConfigSet) -> Result<u8> {
+    let issues = validate(config, Schema::global());
+    for issue in issues.iter() {
+        ctx.io().write(format!("{}\n", issue))?;
+    }
+    Ok(if issues.is_empty() { 0 } else { 1 })
+}
+
 fn show_configs(
     requested_configs: Vec<String>,
     config: &ConfigSet,
@@ -290,6 +308,12 @@ pub fn doc() -> &'static str {
     With --debug, the source (filename and line number) is printed
     for each config item.
 
+    With --validate, check config items in sections that have a
+    registered config schema (currently ``progress``). Unknown names,
+    deprecated names and values of the wrong type in those sections are
+    reported with the file and line that set them. Items in other
+    sections are not checked. Returns 1 if any problem is found.
+
     See :hg:`help config` for more information about config files.
 
     Returns 0 on success, 1 if NAME does not exist.
@@ -298,7 +322,7 @@ pub fn doc() -> &'static str {
 }
 
 pub fn synopsis() -> Option<&'static str> {
-    Some("[-u] [NAME]...")
+    Some("[-u] [--validate] [NAME]...")
 }
 
 fn short_name() -> &'static str {
@@ -21,6 +21,7 @@ commands! {
     mod fsync;
     mod http;
     mod networkdoctor;
+    mod notifyd;
     mod python;
     mod racyoutput;
     mod revsets;
@@ -30,6 +31,8 @@ commands! {
     mod segmentclone;
     mod segmentgraph;
     mod segmentpull;
+    mod sparsediff;
+    mod sparseexplain;
     mod store;
     mod top;
 }
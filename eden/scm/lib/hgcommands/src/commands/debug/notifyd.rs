@@ -0,0 +1,54 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+use std::time::Duration;
+
+use clidispatch::ReqCtx;
+use configmodel::ConfigExt;
+
+use super::NoOpts;
+use super::Repo;
+use super::Result;
+
+pub fn run(_ctx: ReqCtx<NoOpts>, repo: &mut Repo) -> Result<u8> {
+    let idle_timeout: u64 = repo
+        .config()
+        .get_or("fsmonitor", "inotify-idle-timeout", || 3600)?;
+
+    #[cfg(target_os = "linux")]
+    {
+        let dot_dir = repo
+            .dot_hg_path()
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| ".hg".to_string());
+        workingcopy::notifyfs::run_daemon(
+            repo.path(),
+            &dot_dir,
+            Duration::from_secs(idle_timeout),
+        )?;
+        Ok(0)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = Duration::from_secs(idle_timeout);
+        anyhow::bail!("debugnotifyd requires Linux")
+    }
+}
+
+pub fn aliases() -> &'static str {
+    "debugnotifyd"
+}
+
+pub fn doc() -> &'static str {
+    "watch the working copy for changes using inotify (started automatically when fsmonitor.backend=inotify)"
+}
+
+pub fn synopsis() -> Option<&'static str> {
+    None
+}
@@ -0,0 +1,137 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+use std::collections::HashMap;
+use std::io::Write;
+use std::sync::Arc;
+
+use async_runtime::block_unless_interrupted as block_on;
+use clidispatch::errors;
+use clidispatch::ReqCtx;
+use futures::TryStreamExt;
+use manifest::Manifest;
+use manifest_tree::TreeManifest;
+use pathmatcher::Matcher;
+use pathmatcher::UnionMatcher;
+use types::Key;
+use types::RepoPath;
+use workingcopy::workingcopy::WorkingCopy;
+
+use super::define_flags;
+use super::Repo;
+use super::Result;
+use crate::commands::commit_manifest;
+use crate::commands::resolve_rev;
+
+define_flags! {
+    pub struct DebugSparseDiffOpts {
+        /// revision to compare the profiles at
+        #[short('r')]
+        #[argtype("REV")]
+        rev: String = ".",
+
+        #[arg]
+        old: String,
+
+        #[arg]
+        new: String,
+    }
+}
+
+pub fn run(ctx: ReqCtx<DebugSparseDiffOpts>, repo: &mut Repo, wc: &mut WorkingCopy) -> Result<u8> {
+    let manifest = resolve_manifest(repo, wc, &ctx.opts.rev)?;
+    let old = Arc::new(load_profile(repo, &manifest, &ctx.opts.old)?);
+    let new = Arc::new(load_profile(repo, &manifest, &ctx.opts.new)?);
+
+    // Only files either profile can include matter.
+    let matchers: Vec<Arc<dyn Matcher + Send + Sync>> = vec![old.clone(), new.clone()];
+    let file_ids: HashMap<_, _> = manifest
+        .files(UnionMatcher::new(matchers))
+        .map(|file| file.map(|file| (file.path, file.meta.hgid)))
+        .collect::<Result<_>>()?;
+
+    let mut paths: Vec<_> = file_ids.keys().cloned().collect();
+    paths.sort();
+    let diff = sparse::diff(&old, &new, paths)?;
+
+    let file_store = repo.file_store()?;
+    let total_size = |paths: &[types::RepoPathBuf]| -> Result<u64> {
+        let keys = paths
+            .iter()
+            .map(|path| Key::new(path.clone(), file_ids[path]))
+            .collect();
+        let sizes: Vec<(u64, Key)> =
+            block_on(async { file_store.read_file_sizes(keys).await.try_collect().await })??;
+        Ok(sizes.into_iter().map(|(size, _)| size).sum())
+    };
+    let added_size = total_size(&diff.added)?;
+    let removed_size = total_size(&diff.removed)?;
+
+    let mut out = ctx.io().output();
+    for path in diff.removed.iter() {
+        write!(out, "- {}\n", path)?;
+    }
+    for path in diff.added.iter() {
+        write!(out, "+ {}\n", path)?;
+    }
+    write!(
+        out,
+        "{} files added ({} bytes), {} files removed ({} bytes), size delta: {:+} bytes\n",
+        diff.added.len(),
+        added_size,
+        diff.removed.len(),
+        removed_size,
+        added_size as i64 - removed_size as i64,
+    )?;
+
+    Ok(0)
+}
+
+/// The manifest of `rev`.
+pub(super) fn resolve_manifest(
+    repo: &mut Repo,
+    wc: &WorkingCopy,
+    rev: &str,
+) -> Result<TreeManifest> {
+    let node = resolve_rev(repo, wc, rev)?;
+    commit_manifest(repo, &node)
+}
+
+/// A matcher for the sparse profile at `profile` in `manifest`, with the
+/// sparse profile overrides from config applied.
+pub(super) fn load_profile(
+    repo: &mut Repo,
+    manifest: &TreeManifest,
+    profile: &str,
+) -> Result<sparse::Matcher> {
+    if manifest.get_file(RepoPath::from_str(profile)?)?.is_none() {
+        return Err(errors::Abort(format!("no such profile {}", profile).into()).into());
+    }
+    workingcopy::sparse::profile_matcher(
+        profile,
+        manifest.clone(),
+        repo.file_store()?,
+        workingcopy::sparse::config_overrides(repo.config()),
+    )
+}
+
+pub fn aliases() -> &'static str {
+    "debugsparsediff"
+}
+
+pub fn doc() -> &'static str {
+    r#"show how switching between two sparse profiles changes the working copy
+
+Lists the files included by OLD but not NEW with "-", and those included by
+NEW but not OLD with "+", followed by a summary of the total size change.
+Profiles are read from the given revision (default: '.').
+"#
+}
+
+pub fn synopsis() -> Option<&'static str> {
+    Some("[-r REV] OLD NEW")
+}
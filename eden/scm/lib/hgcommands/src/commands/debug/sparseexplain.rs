@@ -0,0 +1,129 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+use std::io::Write;
+
+use clidispatch::errors;
+use clidispatch::ReqCtx;
+use manifest::Manifest;
+use pathmatcher::AlwaysMatcher;
+use types::RepoPathBuf;
+use workingcopy::workingcopy::WorkingCopy;
+
+use super::define_flags;
+use super::sparsediff::load_profile;
+use super::sparsediff::resolve_manifest;
+use super::Repo;
+use super::Result;
+
+define_flags! {
+    pub struct DebugSparseExplainOpts {
+        /// sparse profile to explain
+        #[short('s')]
+        #[argtype("PROFILE")]
+        sparse_profile: String,
+
+        /// revision to read the profile at
+        #[short('r')]
+        #[argtype("REV")]
+        rev: String = ".",
+
+        #[args]
+        args: Vec<String>,
+    }
+}
+
+pub fn run(
+    ctx: ReqCtx<DebugSparseExplainOpts>,
+    repo: &mut Repo,
+    wc: &mut WorkingCopy,
+) -> Result<u8> {
+    if ctx.opts.sparse_profile.is_empty() {
+        return Err(errors::Abort("--sparse-profile is required".into()).into());
+    }
+
+    let manifest = resolve_manifest(repo, wc, &ctx.opts.rev)?;
+    let matcher = load_profile(repo, &manifest, &ctx.opts.sparse_profile)?;
+
+    let mut out = ctx.io().output();
+
+    if !ctx.opts.args.is_empty() {
+        for file in ctx.opts.args.iter() {
+            let path = RepoPathBuf::from_string(file.clone())?;
+            match matcher.explain_rule(&path) {
+                Some(rule) => write!(
+                    out,
+                    "{}: {} by rule {} ({})\n",
+                    path,
+                    if rule.included {
+                        "included"
+                    } else {
+                        "excluded"
+                    },
+                    rule.pattern,
+                    rule.origin,
+                )?,
+                None => write!(out, "{}: excluded by default\n", path)?,
+            }
+        }
+        return Ok(0);
+    }
+
+    // Without files, report rules that don't affect the profile at `rev`.
+    let files = manifest
+        .files(AlwaysMatcher::new())
+        .map(|file| file.map(|file| file.path))
+        .collect::<Result<Vec<_>>>()?;
+    let verbose = ctx.global_opts().verbose;
+    for usage in matcher.rule_usage(files)? {
+        if usage.origin == "(builtin)" {
+            continue;
+        }
+        let kind = if usage.include { "include" } else { "exclude" };
+        if usage.is_unused() {
+            write!(
+                out,
+                "unused {} rule: {} ({})\n",
+                kind, usage.pattern, usage.origin
+            )?;
+        } else if usage.is_shadowed() {
+            write!(
+                out,
+                "shadowed {} rule: {} ({})\n",
+                kind, usage.pattern, usage.origin
+            )?;
+        } else if verbose {
+            write!(
+                out,
+                "{} rule: {} ({}) matched {} files, decided {} files\n",
+                kind, usage.pattern, usage.origin, usage.matched, usage.decided,
+            )?;
+        }
+    }
+
+    Ok(0)
+}
+
+pub fn aliases() -> &'static str {
+    "debugsparseexplain"
+}
+
+pub fn doc() -> &'static str {
+    r#"explain which sparse profile rules include or exclude files
+
+With FILEs, print the rule that decides whether each file is included by
+the profile given with --sparse-profile.
+
+Without FILEs, list rules of the profile that match no files (unused) or
+whose matches are all overridden by other rules (shadowed). With --verbose,
+print how many files every rule matches and decides.
+"#
+}
+
+pub fn synopsis() -> Option<&'static str> {
+    Some("-s PROFILE [-r REV] [FILE]...")
+}
@@ -0,0 +1,252 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+use std::collections::HashMap;
+use std::io::Write;
+
+use anyhow::Result;
+use async_runtime::block_unless_interrupted as block_on;
+use clidispatch::errors;
+use clidispatch::io::IsTty;
+use clidispatch::ReqCtx;
+use cliparser::define_flags;
+use formatter::formatter::FormatOptions;
+use formatter::formatter::Formattable;
+use formatter::StyleWrite;
+use futures::TryStreamExt;
+use manifest::FileType;
+use manifest::Manifest;
+use repo::repo::Repo;
+use serde::Serialize;
+use types::Key;
+use types::RepoPathBuf;
+use vfs::VFS;
+use workingcopy::filesystem::FileSystemType;
+use workingcopy::workingcopy::WorkingCopy;
+
+use super::check_use_rust;
+use super::commit_manifest;
+use super::get_formatter;
+use super::resolve_rev;
+use super::walk::Walk;
+use crate::commands::FormatterOpts;
+use crate::commands::WalkOpts;
+
+/// Number of file sizes fetched from scmstore at once.
+const BATCH_SIZE: usize = 1000;
+
+define_flags! {
+    pub struct FilesOpts {
+        /// search the repository as it is in REV
+        #[short('r')]
+        #[argtype("REV")]
+        rev: String,
+
+        /// end filenames with NUL, for use with xargs
+        #[short('0')]
+        print0: bool,
+
+        walk_opts: WalkOpts,
+        formatter_opts: FormatterOpts,
+
+        #[args]
+        args: Vec<String>,
+    }
+}
+
+#[derive(Serialize)]
+struct FilesItem {
+    abspath: String,
+    path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    size: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    flags: Option<&'static str>,
+    #[serde(skip_serializing)]
+    end: &'static str,
+}
+
+impl Formattable for FilesItem {
+    fn format_plain(
+        &self,
+        _options: &FormatOptions,
+        writer: &mut dyn StyleWrite,
+    ) -> std::result::Result<(), anyhow::Error> {
+        if let (Some(size), Some(flags)) = (self.size, self.flags) {
+            write!(writer, "{:>10} {:>1} ", size, flags)?;
+        }
+        write!(writer, "{}{}", self.path, self.end)?;
+        Ok(())
+    }
+
+    fn optional_keywords(&self) -> &'static [&'static str] {
+        &["size", "flags"]
+    }
+}
+
+pub fn run(ctx: ReqCtx<FilesOpts>, repo: &mut Repo, wc: &mut WorkingCopy) -> Result<u8> {
+    check_use_rust(repo.config(), "files")?;
+
+    let opts = &ctx.opts;
+    let verbose = ctx.global_opts().verbose;
+    let walk = Walk::new(repo, &opts.args, &opts.walk_opts)?;
+
+    // Paths with their sizes and flags, the latter only with --verbose.
+    let mut files: Vec<(RepoPathBuf, Option<(u64, &'static str)>)> = if opts.rev.is_empty() {
+        // The EdenFS treestate does not list every tracked file.
+        if wc.file_system_type() == FileSystemType::Eden {
+            return Err(errors::FallbackToPython(
+                "files without --rev is not supported in Rust on EdenFS".to_owned(),
+            )
+            .into());
+        }
+        let vfs = VFS::new(repo.path().to_path_buf())?;
+        walk.tracked(wc)?
+            .into_iter()
+            .map(|path| {
+                let info = verbose.then(|| working_copy_info(&vfs, &path));
+                (path, info)
+            })
+            .collect()
+    } else {
+        let node = resolve_rev(repo, wc, &opts.rev).map_err(|_| {
+            errors::FallbackToPython(format!("revset {} not supported in Rust files", opts.rev))
+        })?;
+        let manifest = commit_manifest(repo, &node)?;
+        let mut files = Vec::new();
+        for file in manifest.files(walk.matcher.clone()) {
+            let file = file?;
+            let flags = match file.meta.file_type {
+                FileType::Executable => "x",
+                FileType::Symlink => "l",
+                _ => "",
+            };
+            files.push((file.path, file.meta.hgid, flags));
+        }
+
+        let mut sizes = HashMap::new();
+        if verbose {
+            let file_store = repo.file_store()?;
+            for chunk in files.chunks(BATCH_SIZE) {
+                let keys: Vec<Key> = chunk
+                    .iter()
+                    .map(|(path, hgid, _)| Key::new(path.clone(), *hgid))
+                    .collect();
+                let chunk_sizes: Vec<(u64, Key)> = block_on(async {
+                    file_store.read_file_sizes(keys).await.try_collect().await
+                })??;
+                sizes.extend(chunk_sizes.into_iter().map(|(size, key)| (key.path, size)));
+            }
+        }
+
+        files
+            .into_iter()
+            .map(|(path, _, flags)| {
+                let info = verbose.then(|| (sizes.get(&path).copied().unwrap_or(0), flags));
+                (path, info)
+            })
+            .collect()
+    };
+    files.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+
+    if ctx.io().output().is_tty() {
+        ctx.io().start_pager(repo.config())?;
+    }
+
+    let mut formatter = get_formatter(
+        repo.config(),
+        "files",
+        &opts.formatter_opts.template,
+        ctx.global_opts(),
+        Box::new(ctx.io().output()),
+    )?;
+    let end = if opts.print0 { "\0" } else { "\n" };
+
+    formatter.begin_list()?;
+    for (path, info) in files.iter() {
+        formatter.format_item(&FilesItem {
+            abspath: path.to_string(),
+            path: walk.rel(path),
+            size: info.map(|(size, _)| size),
+            flags: info.map(|(_, flags)| flags),
+            end,
+        })?;
+    }
+    formatter.end_list()?;
+
+    Ok(if files.is_empty() { 1 } else { 0 })
+}
+
+/// Size and flags of a tracked file on disk. Missing files have size 0.
+fn working_copy_info(vfs: &VFS, path: &RepoPathBuf) -> (u64, &'static str) {
+    match vfs.metadata(path) {
+        Ok(meta) => {
+            let flags = if vfs.supports_symlinks() && vfs::is_symlink(&meta) {
+                "l"
+            } else if vfs.supports_executables() && vfs::is_executable(&meta) {
+                "x"
+            } else {
+                ""
+            };
+            (meta.len(), flags)
+        }
+        Err(_) => (0, ""),
+    }
+}
+
+pub fn aliases() -> &'static str {
+    "files|fi|fil|file"
+}
+
+pub fn doc() -> &'static str {
+    r#"list tracked files
+
+    Print files under source control in the working directory or
+    specified revision whose names match the given patterns (excluding
+    removed files).
+
+    If no patterns are given to match, this command prints the names
+    of all files under source control in the current working directory.
+
+    .. container:: verbose
+
+      Examples:
+
+      - list all files under the current directory::
+
+          @prog@ files .
+
+      - shows sizes and flags for current revision::
+
+          @prog@ files -vr .
+
+      - list all files named README::
+
+          @prog@ files -I "**/README"
+
+      - list all binary files::
+
+          @prog@ files "set:binary()"
+
+      - find files containing a regular expression::
+
+          @prog@ files "set:grep('bob')"
+
+      - search tracked file contents with xargs and grep::
+
+          @prog@ files -0 | xargs -0 grep foo
+
+    See :hg:`help patterns` and :hg:`help filesets` for more information
+    on specifying file patterns.
+
+    Returns 0 if a match is found, 1 otherwise.
+    "#
+}
+
+pub fn synopsis() -> Option<&'static str> {
+    Some("[OPTION]... [FILE]...")
+}
@@ -0,0 +1,119 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+use std::collections::HashSet;
+
+use anyhow::Result;
+use clidispatch::errors;
+use clidispatch::ReqCtx;
+use cliparser::define_flags;
+use repo::repo::Repo;
+use types::RepoPathBuf;
+use vfs::VFS;
+use workingcopy::track;
+use workingcopy::workingcopy::WorkingCopy;
+
+use super::track::check_enabled;
+use super::track::lock_working_copy;
+use super::walk::Walk;
+use crate::commands::WalkOpts;
+
+define_flags! {
+    pub struct ForgetOpts {
+        walk_opts: WalkOpts,
+
+        #[args]
+        args: Vec<String>,
+    }
+}
+
+pub fn run(ctx: ReqCtx<ForgetOpts>, repo: &mut Repo, wc: &mut WorkingCopy) -> Result<u8> {
+    check_enabled(repo.config(), "forget", wc)?;
+
+    let opts = &ctx.opts;
+    if opts.args.is_empty() {
+        return Err(errors::Abort("no files specified".into()).into());
+    }
+
+    let _wlock = lock_working_copy(repo, wc)?;
+    let walk = Walk::new(repo, &opts.args, &opts.walk_opts)?;
+    let status = walk.status(wc, repo.config())?;
+    let vfs = VFS::new(repo.path().to_path_buf())?;
+
+    // Modified, added, deleted and clean files.
+    let mut forget = walk.tracked(wc)?;
+    forget.sort();
+    let removed: HashSet<&RepoPathBuf> = status.removed().collect();
+
+    let mut ret = 0;
+    let forget_set: HashSet<&RepoPathBuf> = forget.iter().collect();
+    for path in walk.explicit.iter().filter(|p| walk.is_exact(p)) {
+        if forget_set.contains(path) || removed.contains(path) {
+            continue;
+        }
+        match vfs.metadata(path) {
+            Ok(meta) if meta.is_dir() => continue,
+            Ok(_) => {
+                ctx.io().write_err(format!(
+                    "not removing {}: file is already untracked\n",
+                    walk.rel(path)
+                ))?;
+            }
+            Err(_) => {
+                ctx.io()
+                    .write_err(format!("{}: No such file or directory\n", walk.rel(path)))?;
+            }
+        }
+        ret = 1;
+    }
+
+    if !ctx.global_opts().quiet {
+        for path in forget.iter() {
+            if ctx.global_opts().verbose || !walk.is_exact(path) {
+                ctx.io().write(format!("removing {}\n", walk.rel(path)))?;
+            }
+        }
+    }
+
+    if !forget.is_empty() {
+        let monitored = wc.file_system_type().is_monitored();
+        let treestate = wc.treestate();
+        let mut treestate = treestate.lock();
+        for path in forget.iter() {
+            track::untrack(&mut treestate, path, monitored)?;
+        }
+        track::flush(&mut treestate, repo.dot_hg_path())?;
+    }
+
+    Ok(ret)
+}
+
+pub fn aliases() -> &'static str {
+    "forget|for|forg|forge"
+}
+
+pub fn doc() -> &'static str {
+    r#"stop tracking the specified files
+
+    Mark the specified files so they will no longer be tracked
+    after the next commit.
+
+    This only removes files from the current branch, not from the
+    entire project history, and it does not delete them from the
+    working directory.
+
+    To delete the file from the working directory, see :hg:`remove`.
+
+    To undo a forget before the next commit, see :hg:`add`.
+
+    Returns 0 on success.
+    "#
+}
+
+pub fn synopsis() -> Option<&'static str> {
+    Some("[OPTION]... FILE...")
+}
@@ -0,0 +1,225 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+use std::collections::HashSet;
+
+use anyhow::Result;
+use clidispatch::errors;
+use clidispatch::ReqCtx;
+use cliparser::define_flags;
+use repo::repo::Repo;
+use types::RepoPathBuf;
+use vfs::VFS;
+use workingcopy::track;
+use workingcopy::workingcopy::WorkingCopy;
+
+use super::track::check_enabled;
+use super::track::lock_working_copy;
+use super::walk::Walk;
+use crate::commands::WalkOpts;
+
+define_flags! {
+    pub struct RemoveOpts {
+        /// record delete for missing files
+        #[short('A')]
+        after: bool,
+
+        /// forget added files, delete modified files
+        #[short('f')]
+        force: bool,
+
+        walk_opts: WalkOpts,
+
+        #[args]
+        args: Vec<String>,
+    }
+}
+
+pub fn run(ctx: ReqCtx<RemoveOpts>, repo: &mut Repo, wc: &mut WorkingCopy) -> Result<u8> {
+    check_enabled(repo.config(), "remove", wc)?;
+
+    let opts = &ctx.opts;
+    if opts.args.is_empty() && !opts.after {
+        return Err(errors::Abort("no files specified".into()).into());
+    }
+
+    let _wlock = lock_working_copy(repo, wc)?;
+    let walk = Walk::new(repo, &opts.args, &opts.walk_opts)?;
+    let status = walk.status(wc, repo.config())?;
+    let vfs = VFS::new(repo.path().to_path_buf())?;
+
+    let modified: Vec<&RepoPathBuf> = status.modified().collect();
+    let added: HashSet<&RepoPathBuf> = status.added().collect();
+    let deleted: Vec<&RepoPathBuf> = status.deleted().collect();
+    let tracked = walk.tracked(wc)?;
+    let clean: Vec<&RepoPathBuf> = tracked
+        .iter()
+        .filter(|path| status.status(path).is_none())
+        .collect();
+
+    let mut ret = 0;
+    let mut warnings = Vec::new();
+
+    // Warn about explicit files that cannot be removed.
+    let tracked_set: HashSet<&RepoPathBuf> = tracked.iter().collect();
+    for path in walk.explicit.iter().filter(|p| walk.is_exact(p)) {
+        if tracked_set.contains(path) || status.status(path).is_some() || path.is_empty() {
+            continue;
+        }
+        if let Ok(meta) = vfs.metadata(path) {
+            if meta.is_dir() {
+                if tracked
+                    .iter()
+                    .any(|t| t.parents().any(|p| p == path.as_repo_path()))
+                {
+                    continue;
+                }
+                warnings.push(format!(
+                    "not removing {}: no tracked files\n",
+                    walk.rel(path)
+                ));
+            } else {
+                warnings.push(format!(
+                    "not removing {}: file is untracked\n",
+                    walk.rel(path)
+                ));
+            }
+        }
+        ret = 1;
+    }
+
+    let mut list: Vec<&RepoPathBuf> = if opts.force {
+        let mut list = modified;
+        list.extend(deleted);
+        list.extend(clean);
+        list.extend(added.iter().copied());
+        list
+    } else if opts.after {
+        let removed: HashSet<&RepoPathBuf> = status.removed().collect();
+        let deleted_set: HashSet<&RepoPathBuf> = deleted.iter().copied().collect();
+        let mut remaining: Vec<&RepoPathBuf> = walk
+            .explicit
+            .iter()
+            .filter(|p| walk.is_exact(p) && !deleted_set.contains(p) && !removed.contains(p))
+            .collect();
+        remaining.sort();
+        for path in remaining {
+            if vfs.metadata(path).map_or(false, |meta| !meta.is_dir()) {
+                warnings.push(format!(
+                    "not removing {}: file still exists\n",
+                    walk.rel(path)
+                ));
+                ret = 1;
+            }
+        }
+        deleted
+    } else {
+        for path in modified {
+            warnings.push(format!(
+                "not removing {}: file is modified (use -f to force removal)\n",
+                walk.rel(path)
+            ));
+            ret = 1;
+        }
+        let mut added: Vec<&RepoPathBuf> = added.iter().copied().collect();
+        added.sort();
+        for path in added {
+            warnings.push(format!(
+                "not removing {}: file has been marked for add (use '{} forget' to undo add)\n",
+                walk.rel(path),
+                identity::cli_name(),
+            ));
+            ret = 1;
+        }
+        let mut list = deleted;
+        list.extend(clean);
+        list
+    };
+    list.sort();
+
+    if !ctx.global_opts().quiet {
+        for path in list.iter() {
+            if ctx.global_opts().verbose || !walk.is_exact(path) {
+                ctx.io().write(format!("removing {}\n", walk.rel(path)))?;
+            }
+        }
+    }
+
+    if !list.is_empty() {
+        if !opts.after {
+            for path in list.iter() {
+                // Added files are never deleted from disk.
+                if !added.contains(path) {
+                    vfs.remove(path)?;
+                }
+            }
+        }
+
+        let monitored = wc.file_system_type().is_monitored();
+        let treestate = wc.treestate();
+        let mut treestate = treestate.lock();
+        for path in list {
+            track::untrack(&mut treestate, path, monitored)?;
+        }
+        track::flush(&mut treestate, repo.dot_hg_path())?;
+    }
+
+    for warning in warnings {
+        ctx.io().write_err(warning)?;
+    }
+
+    Ok(ret)
+}
+
+pub fn aliases() -> &'static str {
+    "remove|rm|rem|remo|remov"
+}
+
+pub fn doc() -> &'static str {
+    r#"delete the specified tracked files
+
+    Remove the specified tracked files from the repository and delete
+    them. The files will be deleted from the repository at the next
+    commit.
+
+    To undo a remove before files have been committed, use :hg:`revert`.
+    To stop tracking files without deleting them, use :hg:`forget`.
+
+    .. container:: verbose
+
+      -A/--after can be used to remove only files that have already
+      been deleted, -f/--force can be used to force deletion, and -Af
+      can be used to remove files from the next revision without
+      deleting them from the working directory.
+
+      The following table details the behavior of remove for different
+      file states (columns) and option combinations (rows). The file
+      states are Added [A], Clean [C], Modified [M] and Missing [!]
+      (as reported by :hg:`status`). The actions are Warn, Remove
+      (from branch) and Delete (from disk):
+
+      ========= == == == ==
+      opt/state A  C  M  !
+      ========= == == == ==
+      none      W  RD W  R
+      -f        R  RD RD R
+      -A        W  W  W  R
+      -Af       R  R  R  R
+      ========= == == == ==
+
+      .. note::
+
+         :hg:`remove` never deletes files in Added [A] state from the
+         working directory, not even if ``--force`` is specified.
+
+    Returns 0 on success, 1 if any warnings encountered.
+    "#
+}
+
+pub fn synopsis() -> Option<&'static str> {
+    Some("[OPTION]... FILE...")
+}
@@ -132,6 +132,10 @@ impl<'a> Formattable for StatusEntry<'a> {
         }
         Ok(())
     }
+
+    fn optional_keywords(&self) -> &'static [&'static str] {
+        &["copy"]
+    }
 }
 
 pub fn print_status(
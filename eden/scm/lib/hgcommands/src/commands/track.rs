@@ -0,0 +1,42 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+//! Code shared by `add`, `addremove`, `forget` and `remove`.
+
+use anyhow::Result;
+use clidispatch::errors;
+use configmodel::Config;
+use repo::repo::Repo;
+use repolock::LockHandle;
+use workingcopy::filesystem::FileSystemType;
+use workingcopy::track;
+use workingcopy::workingcopy::WorkingCopy;
+
+use super::check_use_rust;
+
+/// Fall back to Python unless the Rust version of `command` is enabled and
+/// can update the working copy.
+pub(crate) fn check_enabled(config: &dyn Config, command: &str, wc: &WorkingCopy) -> Result<()> {
+    check_use_rust(config, command)?;
+    if wc.file_system_type() == FileSystemType::Eden {
+        return Err(errors::FallbackToPython(format!(
+            "{} is not supported in Rust on EdenFS",
+            command
+        ))
+        .into());
+    }
+    Ok(())
+}
+
+/// Take the working copy lock and reload the treestate, so the command sees
+/// and keeps changes made by other processes. Hold the lock until the
+/// treestate is flushed.
+pub(crate) fn lock_working_copy(repo: &Repo, wc: &WorkingCopy) -> Result<LockHandle> {
+    let lock = repolock::lock_working_copy(repo.config(), repo.dot_hg_path())?;
+    track::reload(&mut wc.treestate().lock(), repo.dot_hg_path())?;
+    Ok(lock)
+}
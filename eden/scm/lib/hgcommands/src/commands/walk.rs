@@ -0,0 +1,176 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+//! Command line patterns.
+
+use std::collections::HashSet;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use anyhow::Result;
+use clidispatch::errors;
+use configmodel::Config;
+use pathmatcher::normalize_glob;
+use pathmatcher::plain_to_glob;
+use pathmatcher::split_pattern;
+use pathmatcher::AlwaysMatcher;
+use pathmatcher::DifferenceMatcher;
+use pathmatcher::IntersectMatcher;
+use pathmatcher::Matcher;
+use pathmatcher::PatternKind;
+use pathmatcher::TreeMatcher;
+use repo::repo::Repo;
+use status::Status;
+use treestate::filestate::StateFlags;
+use types::path::RepoPathRelativizer;
+use types::RepoPath;
+use types::RepoPathBuf;
+use workingcopy::workingcopy::WorkingCopy;
+
+use crate::commands::WalkOpts;
+
+type ArcMatcher = Arc<dyn Matcher + Send + Sync + 'static>;
+
+/// Files selected by the command line patterns and -I/-X.
+pub(crate) struct Walk {
+    pub(crate) matcher: ArcMatcher,
+    /// Paths named on the command line, as opposed to matched by globs.
+    pub(crate) explicit: Vec<RepoPathBuf>,
+    exact: HashSet<RepoPathBuf>,
+    relativizer: RepoPathRelativizer,
+}
+
+impl Walk {
+    pub(crate) fn new(repo: &Repo, pats: &[String], opts: &WalkOpts) -> Result<Self> {
+        let cwd = std::env::current_dir()?;
+        let root = repo.path();
+        let mut explicit = Vec::new();
+
+        let mut matcher: ArcMatcher = if pats.is_empty() {
+            Arc::new(AlwaysMatcher::new())
+        } else {
+            let rules = pattern_rules(pats, PatternKind::RelPath, root, &cwd, &mut explicit)?;
+            Arc::new(TreeMatcher::from_rules(rules.iter())?)
+        };
+        if !opts.include.is_empty() {
+            let rules = pattern_rules(&opts.include, PatternKind::Glob, root, &cwd, &mut vec![])?;
+            let include = Arc::new(TreeMatcher::from_rules(rules.iter())?);
+            matcher = Arc::new(IntersectMatcher::new(vec![matcher, include]));
+        }
+        if !opts.exclude.is_empty() {
+            let rules = pattern_rules(&opts.exclude, PatternKind::Glob, root, &cwd, &mut vec![])?;
+            let exclude = TreeMatcher::from_rules(rules.iter())?;
+            matcher = Arc::new(DifferenceMatcher::new(matcher, exclude));
+        }
+
+        let mut exact = HashSet::with_capacity(explicit.len());
+        for path in explicit.iter() {
+            if matcher.matches_file(path)? {
+                exact.insert(path.clone());
+            }
+        }
+
+        Ok(Self {
+            matcher,
+            explicit,
+            exact,
+            relativizer: RepoPathRelativizer::new(cwd, root),
+        })
+    }
+
+    /// Whether `path` was named on the command line.
+    pub(crate) fn is_exact(&self, path: &RepoPath) -> bool {
+        self.exact.contains(path)
+    }
+
+    /// `path` as the user would refer to it from the current directory.
+    pub(crate) fn rel(&self, path: &RepoPath) -> String {
+        self.relativizer.relativize(path)
+    }
+
+    /// Working copy status of the matched files.
+    pub(crate) fn status(&self, wc: &WorkingCopy, config: &dyn Config) -> Result<Status> {
+        wc.status(self.matcher.clone(), SystemTime::UNIX_EPOCH, config)
+    }
+
+    /// Matched files that are tracked in the working copy.
+    pub(crate) fn tracked(&self, wc: &WorkingCopy) -> Result<Vec<RepoPathBuf>> {
+        let treestate = wc.treestate();
+        let entries = treestate.lock().visit_by_state(StateFlags::EXIST_NEXT)?;
+        let mut tracked = Vec::new();
+        for (path, _) in entries {
+            let path = RepoPathBuf::from_utf8(path)?;
+            if self.matcher.matches_file(&path)? {
+                tracked.push(path);
+            }
+        }
+        Ok(tracked)
+    }
+}
+
+/// Translate command line patterns to `TreeMatcher` rules.
+///
+/// Path patterns are added to `explicit`. Pattern kinds that cannot be
+/// expressed as globs fall back to Python.
+fn pattern_rules(
+    pats: &[String],
+    default_kind: PatternKind,
+    root: &Path,
+    cwd: &Path,
+    explicit: &mut Vec<RepoPathBuf>,
+) -> Result<Vec<String>> {
+    let mut rules = Vec::with_capacity(pats.len() * 2);
+    for pat in pats {
+        let (kind, pat) = split_pattern(pat, default_kind);
+        let glob = match kind {
+            PatternKind::RelPath | PatternKind::Path => {
+                let base = if kind == PatternKind::Path { root } else { cwd };
+                let path = repo_path(root, &base.join(pat), pat)?;
+                let glob = plain_to_glob(path.as_str());
+                explicit.push(path);
+                glob
+            }
+            PatternKind::Glob => {
+                let prefix = repo_path(root, cwd, pat)?;
+                if prefix.is_empty() {
+                    normalize_glob(pat)
+                } else {
+                    normalize_glob(&format!("{}/{}", plain_to_glob(prefix.as_str()), pat))
+                }
+            }
+            PatternKind::RelGlob => format!("**/{}", normalize_glob(pat)),
+            _ => {
+                return Err(errors::FallbackToPython(format!(
+                    "{} patterns are not supported in Rust",
+                    kind.name()
+                ))
+                .into());
+            }
+        };
+        // Like Python, patterns naming a directory match everything in it.
+        if glob.is_empty() {
+            rules.push("**".to_string());
+        } else {
+            rules.push(format!("{}/**", glob));
+            rules.push(glob);
+        }
+    }
+    Ok(rules)
+}
+
+/// Convert an absolute path to a path relative to `root`.
+fn repo_path(root: &Path, path: &Path, pat: &str) -> Result<RepoPathBuf> {
+    let path: PathBuf = util::path::normalize(path);
+    match path.strip_prefix(root) {
+        Ok(path) => Ok(RepoPathBuf::try_from(path.to_path_buf())?),
+        Err(_) => {
+            Err(errors::Abort(format!("{} not under root '{}'", pat, root.display()).into()).into())
+        }
+    }
+}
@@ -0,0 +1,55 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+//! Config items registered with `configmodel::schema::Schema`.
+//!
+//! Sections listed here are validated by `config --validate`, so they
+//! need to list all items of the section, including the ones only read by
+//! Python.
+
+use configmodel::schema::ConfigItem;
+use configmodel::schema::ConfigType;
+
+pub(crate) static CONFIG_ITEMS: &[ConfigItem] = &[
+    ConfigItem::new("progress", "assume-tty", ConfigType::Bool)
+        .default("false")
+        .doc("render progress bars even if stderr is not a terminal"),
+    ConfigItem::new("progress", "changedelay", ConfigType::Float)
+        .default("1")
+        .doc("seconds before a progress bar shows a new topic"),
+    ConfigItem::new("progress", "clear-complete", ConfigType::Bool)
+        .default("true")
+        .doc("clear progress bars when they complete"),
+    ConfigItem::new("progress", "debug", ConfigType::Bool).default("false"),
+    ConfigItem::new("progress", "delay", ConfigType::Float)
+        .default("3")
+        .doc("seconds before progress bars are shown"),
+    ConfigItem::new("progress", "disable", ConfigType::Bool)
+        .default("false")
+        .doc("disable progress bars"),
+    ConfigItem::new("progress", "estimateinterval", ConfigType::Float)
+        .default("10")
+        .doc("seconds of history used to estimate the remaining time"),
+    ConfigItem::new("progress", "fakedpid", ConfigType::Int),
+    ConfigItem::new("progress", "format", ConfigType::List)
+        .default("topic bar number estimate")
+        .doc("components of the classic progress bar"),
+    ConfigItem::new("progress", "lockstep", ConfigType::Bool)
+        .default("false")
+        .doc("render progress bars in lockstep with the command, for tests"),
+    ConfigItem::new("progress", "refresh", ConfigType::Float)
+        .default("0.1")
+        .doc("seconds between progress bar updates"),
+    ConfigItem::new("progress", "renderer", ConfigType::String)
+        .default("rust:simple")
+        .doc("progress bar renderer, or 'none'"),
+    ConfigItem::new("progress", "statefile", ConfigType::Path)
+        .doc("file to write progress state to"),
+    ConfigItem::new("progress", "statefileappend", ConfigType::Bool).default("false"),
+    ConfigItem::new("progress", "verbose", ConfigType::Bool).default("false"),
+    ConfigItem::new("progress", "width", ConfigType::Int).doc("width of progress bars"),
+];
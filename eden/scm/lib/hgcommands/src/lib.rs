@@ -6,6 +6,7 @@
  */
 
 pub mod commands;
+mod configitems;
 mod hgpython;
 mod python;
 mod run;
@@ -35,6 +35,7 @@ use configmodel::Config;
 use configmodel::ConfigExt;
 use configparser::config::ConfigSet;
 use fail::FailScenario;
+use formatter::errors::FormattingError;
 use once_cell::sync::Lazy;
 use once_cell::sync::OnceCell;
 use parking_lot::Mutex;
@@ -52,6 +53,7 @@ use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::Layer;
 
 use crate::commands;
+use crate::configitems::CONFIG_ITEMS;
 use crate::HgPython;
 
 /// Run a Rust or Python command.
@@ -100,6 +102,7 @@ pub fn run_command(args: Vec<String>, io: &IO) -> i32 {
 
     let scenario = setup_fail_points();
     setup_eager_repo();
+    setup_config_schema();
 
     // This is intended to be "process start". "exec/hgmain" seems to be
     // a better place for it. However, chg makes it tricky. Because if hgmain
@@ -207,6 +210,7 @@ fn dispatch_command(
 
     let mut fell_back = false;
     let exit_code = match dispatch_res
+        .map_err(fallback_on_unknown_keyword)
         .map_err(|err| errors::triage_error(config, err, command.map(|c| c.main_alias())))
     {
         Ok(exit_code) => exit_code as i32,
@@ -282,6 +286,18 @@ fn dispatch_command(
     exit_code
 }
 
+/// Leave templates using keywords that the Rust items do not have to Python.
+/// The template formatter holds its output back until no keyword can fail, so
+/// nothing has been written when this error is returned.
+fn fallback_on_unknown_keyword(err: anyhow::Error) -> anyhow::Error {
+    match err.downcast_ref::<FormattingError>() {
+        Some(FormattingError::UnknownKeyword(unknown)) => {
+            errors::FallbackToPython(unknown.to_string()).into()
+        }
+        _ => err,
+    }
+}
+
 /// Similar to `std::env::current_dir`. But does some extra things:
 /// - Attempt to autofix issues when running under a typical shell (which
 ///   sets $PWD), and a directory is deleted and then recreated.
@@ -393,11 +409,11 @@ fn spawn_progress_thread(
     // See 'hg help config.progress' for the config options.
     let mut disable_rendering = false;
 
-    if config.get_or("progress", "disable", || false)? {
+    if config.get_schema("progress", "disable")? {
         disable_rendering = true;
     }
 
-    let assume_tty = config.get_or("progress", "assume-tty", || false)?;
+    let assume_tty: bool = config.get_schema("progress", "assume-tty")?;
     if !assume_tty && !io.error().is_tty() {
         disable_rendering = true;
     }
@@ -407,16 +423,16 @@ fn spawn_progress_thread(
     }
 
     let render_function = progress_render::simple::render;
-    let renderer_name = config.get_or("progress", "renderer", || "rust:simple".to_string())?;
+    let renderer_name: String = config.get_schema("progress", "renderer")?;
     if renderer_name == "none" {
         disable_rendering = true;
     }
 
-    let interval = Duration::from_secs_f64(config.get_or("progress", "refresh", || 0.1)?)
+    let interval = Duration::from_secs_f64(config.get_schema("progress", "refresh")?)
         .max(Duration::from_millis(50));
 
     // lockstep is used by tests to control progress rendering run loop.
-    let lockstep = config.get_or("progress", "lockstep", || false)?;
+    let lockstep: bool = config.get_schema("progress", "lockstep")?;
 
     // Limit how often we write runlog. This config knob is primarily for tests to lower.
     let runlog_interval =
@@ -425,7 +441,7 @@ fn spawn_progress_thread(
     let progress = io.progress();
 
     let mut config = progress_render::RenderingConfig {
-        delay: Duration::from_secs_f64(config.get_or("progress", "delay", || 3.0)?),
+        delay: Duration::from_secs_f64(config.get_schema("progress", "delay")?),
         term_width: progress.term_size().0,
         ..Default::default()
     };
@@ -772,6 +788,13 @@ fn setup_eager_repo() {
     *REGISTERED
 }
 
+fn setup_config_schema() {
+    static REGISTERED: Lazy<()> =
+        Lazy::new(|| configmodel::schema::Schema::global().register(CONFIG_ITEMS));
+
+    *REGISTERED
+}
+
 static FAIL_SETUP: AtomicBool = AtomicBool::new(false);
 
 fn setup_fail_points<'a>() -> Option<FailScenario<'a>> {
@@ -9,7 +9,7 @@ use std::str::FromStr;
 
 use crate::error::Error;
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum PatternKind {
     /// a regular expression relative to repository root, check [RegexMatcher]
     /// for supported RE syntax
@@ -397,10 +397,13 @@ impl Repo {
         } else {
             fsmonitor_mode.is_none() || fsmonitor_mode == Some("on".into())
         };
-        let filesystem = match (is_eden, is_watchman) {
-            (true, _) => FileSystemType::Eden,
-            (false, true) => FileSystemType::Watchman,
-            (false, false) => FileSystemType::Normal,
+        let is_inotify =
+            self.config.get_nonempty("fsmonitor", "backend") == Some("inotify".into());
+        let filesystem = match (is_eden, is_inotify, is_watchman) {
+            (true, _, _) => FileSystemType::Eden,
+            (false, true, _) => FileSystemType::Notify,
+            (false, false, true) => FileSystemType::Watchman,
+            (false, false, false) => FileSystemType::Normal,
         };
 
         let dirstate_path = path.join(self.ident.dot_dir()).join("dirstate");
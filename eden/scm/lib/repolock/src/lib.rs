@@ -5,6 +5,8 @@
  * GNU General Public License version 2.
  */
 
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::error;
 use std::fmt;
 use std::fs::File;
@@ -16,6 +18,9 @@ use std::ops::Add;
 use std::os::unix::fs::PermissionsExt;
 use std::path::Path;
 use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::Weak;
 use std::thread::sleep;
 use std::time::Duration;
 use std::time::SystemTime;
@@ -29,21 +34,56 @@ use util::errors::IOResult;
 use util::lock::PathLock;
 
 const WORKING_COPY_NAME: &str = "wlock";
+const STORE_NAME: &str = "lock";
 
+thread_local! {
+    /// Working copy locks held by this thread, by lock file path.
+    static WORKING_COPY_LOCKS: RefCell<HashMap<PathBuf, Weak<LockInner>>> = RefCell::new(HashMap::new());
+}
+
+/// Lock the working copy at `dot_hg`.
+///
+/// Like Python's wlock, the lock is re-entrant: a thread already holding it
+/// (e.g. a command updating the dirstate, while status persists file system
+/// monitor state) gets another handle. The lock is released once all handles
+/// are dropped.
 pub fn lock_working_copy(
     config: &dyn Config,
     dot_hg: &Path,
 ) -> anyhow::Result<LockHandle, LockError> {
-    lock(
+    let path = dot_hg
+        .join(sanitize_lock_name(WORKING_COPY_NAME))
+        .with_extension("lock");
+    let held = WORKING_COPY_LOCKS.with(|locks| locks.borrow().get(&path).and_then(Weak::upgrade));
+    if let Some(inner) = held {
+        return Ok(LockHandle { inner });
+    }
+
+    let handle = lock(
         config,
         dot_hg,
         WORKING_COPY_NAME,
         format!("{}:{}", util::sys::hostname()?, std::process::id()).as_bytes(),
-    )
+    )?;
+    WORKING_COPY_LOCKS.with(|locks| {
+        locks
+            .borrow_mut()
+            .insert(path, Arc::downgrade(&handle.inner))
+    });
+    Ok(handle)
 }
 
-pub fn lock_store(_config: &dyn Config, _dot_hg: &Path) -> anyhow::Result<LockHandle, LockError> {
-    todo!("be sure to enforce wlock -> lock acquisition order to avoid deadlocks")
+/// Lock the store at `store_path` (e.g. ".hg/store").
+///
+/// Callers that also need the working copy lock must take it first, like
+/// Python does, to avoid deadlocks.
+pub fn lock_store(config: &dyn Config, store_path: &Path) -> anyhow::Result<LockHandle, LockError> {
+    lock(
+        config,
+        store_path,
+        STORE_NAME,
+        format!("{}:{}", util::sys::hostname()?, std::process::id()).as_bytes(),
+    )
 }
 
 /// lock loops until it can acquire the specified lock, subject to
@@ -179,43 +219,59 @@ pub fn try_lock(dir: &Path, name: &str, contents: &[u8]) -> anyhow::Result<LockH
         .path_context("error write lock contents", &path)?;
 
     Ok(LockHandle {
-        path: lock_file_path,
-        lock: lock_file,
-        legacy_path: if !legacy_already_locked {
-            Some(legacy_path)
-        } else {
-            None
-        },
-        legacy_lock,
+        inner: Arc::new(LockInner {
+            path: lock_file_path,
+            lock: lock_file,
+            legacy: Mutex::new(if !legacy_already_locked {
+                Some((legacy_path, legacy_lock))
+            } else {
+                None
+            }),
+        }),
     })
 }
 
 pub struct LockHandle {
+    inner: Arc<LockInner>,
+}
+
+/// The lock itself, shared by all handles of a re-entered lock. It is
+/// released when the last of them is dropped or unlocked.
+struct LockInner {
     path: PathBuf,
     lock: File,
-    legacy_path: Option<PathBuf>,
-    legacy_lock: Option<File>,
+    /// The legacy lock file we created, and the open file keeping it locked.
+    legacy: Mutex<Option<(PathBuf, Option<File>)>>,
 }
 
 impl LockHandle {
     pub fn unlock(&mut self) -> IOResult<()> {
-        self.unlink_legacy();
-        self.lock
+        // Other handles of a re-entered lock still need it.
+        if Arc::strong_count(&self.inner) > 1 {
+            return Ok(());
+        }
+        WORKING_COPY_LOCKS.with(|locks| locks.borrow_mut().remove(&self.inner.path));
+        self.inner.unlink_legacy();
+        self.inner
+            .lock
             .unlock()
-            .path_context("error unlocking lock file", &self.path)
+            .path_context("error unlocking lock file", &self.inner.path)
     }
+}
 
-    fn unlink_legacy(&mut self) {
-        if let Some(path) = self.legacy_path.take() {
+impl LockInner {
+    fn unlink_legacy(&self) {
+        let legacy = self.legacy.lock().unwrap().take();
+        if let Some((path, legacy_lock)) = legacy {
             // Close legacy_lock file, if present.
-            self.legacy_lock.take();
+            drop(legacy_lock);
 
             let _ = util::path::remove_file(&path);
         }
     }
 }
 
-impl Drop for LockHandle {
+impl Drop for LockInner {
     fn drop(&mut self) {
         self.unlink_legacy();
     }
@@ -404,6 +460,73 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_working_copy_reentrant() -> Result<()> {
+        let tmp = tempfile::tempdir()?;
+
+        let cfg = BTreeMap::from([("ui.timeout", "0.001"), ("devel.lock_backoff", "0.001")]);
+
+        let wlock = lock_working_copy(&cfg, tmp.path())?;
+        {
+            let _nested = lock_working_copy(&cfg, tmp.path())?;
+        }
+
+        // Dropping the nested handle keeps the lock, which other threads
+        // still cannot take.
+        let path = tmp.path().to_path_buf();
+        let other =
+            spawn(move || matches!(lock_working_copy(&cfg, &path), Err(LockError::Contended(_))));
+        assert!(other.join().unwrap());
+
+        drop(wlock);
+        assert!(try_lock(tmp.path(), WORKING_COPY_NAME, "foo".as_bytes()).is_ok());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_working_copy_reentrant_outer_dropped_first() -> Result<()> {
+        let tmp = tempfile::tempdir()?;
+
+        let cfg = BTreeMap::from([("ui.timeout", "0.001"), ("devel.lock_backoff", "0.001")]);
+
+        let mut wlock = lock_working_copy(&cfg, tmp.path())?;
+        let nested = lock_working_copy(&cfg, tmp.path())?;
+        wlock.unlock()?;
+        drop(wlock);
+
+        // The nested handle still holds the lock and the legacy lock file.
+        assert!(tmp.path().join(WORKING_COPY_NAME).exists());
+        let path = tmp.path().to_path_buf();
+        let other =
+            spawn(move || matches!(lock_working_copy(&cfg, &path), Err(LockError::Contended(_))));
+        assert!(other.join().unwrap());
+
+        drop(nested);
+        assert!(!tmp.path().join(WORKING_COPY_NAME).exists());
+        assert!(try_lock(tmp.path(), WORKING_COPY_NAME, "foo".as_bytes()).is_ok());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_store() -> Result<()> {
+        let tmp = tempfile::tempdir()?;
+
+        let cfg = BTreeMap::<&str, &str>::new();
+
+        let _wlock = lock_working_copy(&cfg, tmp.path())?;
+        let _lock = lock_store(&cfg, tmp.path())?;
+
+        // The store lock is independent of the working copy lock.
+        match try_lock(tmp.path(), STORE_NAME, "foo".as_bytes()) {
+            Err(LockError::Contended(_)) => {}
+            _ => panic!("lock should be contended"),
+        };
+
+        Ok(())
+    }
+
     #[test]
     fn test_lock_legacy_compat() -> Result<()> {
         let tmp = tempfile::tempdir()?;
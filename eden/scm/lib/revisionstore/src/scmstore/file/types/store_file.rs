@@ -10,6 +10,9 @@ use std::ops::BitOr;
 use anyhow::anyhow;
 use anyhow::Result;
 use minibytes::Bytes;
+use types::Key;
+
+use crate::datastore::strip_metadata;
 
 use crate::scmstore::file::LazyFile;
 use crate::scmstore::value::StoreValue;
@@ -65,6 +68,16 @@ impl StoreFile {
             .ok_or_else(|| anyhow!("no content available"))?
             .file_content()
     }
+
+    /// The file this one was copied from, according to its copy header.
+    pub fn copy_info(&mut self) -> Result<Option<Key>> {
+        let content = self
+            .content
+            .as_mut()
+            .ok_or_else(|| anyhow!("no content available"))?
+            .hg_content()?;
+        Ok(strip_metadata(&content)?.1)
+    }
 }
 
 impl BitOr for StoreFile {
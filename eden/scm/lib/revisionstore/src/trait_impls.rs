@@ -26,6 +26,7 @@ use types::Key;
 use crate::datastore::strip_metadata;
 use crate::scmstore::FileAttributes;
 use crate::scmstore::FileStore;
+use crate::scmstore::StoreFile;
 use crate::RemoteDataStore;
 use crate::StoreKey;
 use crate::StoreResult;
@@ -44,7 +45,15 @@ where
     type Error = anyhow::Error;
 
     async fn read_file_contents(&self, keys: Vec<Key>) -> BoxStream<Result<(Bytes, Key)>> {
-        stream_data_from_remote_data_store(self.0.clone(), keys).boxed()
+        stream_data_from_remote_data_store(self.0.clone(), keys)
+            .map(|result| result.map(|((data, _), key)| (data, key)))
+            .boxed()
+    }
+
+    async fn read_rename_metadata(&self, keys: Vec<Key>) -> BoxStream<Result<(Option<Key>, Key)>> {
+        stream_data_from_remote_data_store(self.0.clone(), keys)
+            .map(|result| result.map(|((_, copy_from), key)| (copy_from, key)))
+            .boxed()
     }
 }
 
@@ -53,7 +62,24 @@ impl ReadFileContents for ArcFileStore {
     type Error = anyhow::Error;
 
     async fn read_file_contents(&self, keys: Vec<Key>) -> BoxStream<Result<(Bytes, Key)>> {
-        stream_data_from_scmstore(self.0.clone(), keys).boxed()
+        stream_from_scmstore(self.0.clone(), keys, FileAttributes::CONTENT, |file| {
+            file.file_content()
+        })
+        .boxed()
+    }
+
+    async fn read_file_sizes(&self, keys: Vec<Key>) -> BoxStream<Result<(u64, Key)>> {
+        stream_from_scmstore(self.0.clone(), keys, FileAttributes::AUX, |file| {
+            Ok(file.aux_data()?.total_size)
+        })
+        .boxed()
+    }
+
+    async fn read_rename_metadata(&self, keys: Vec<Key>) -> BoxStream<Result<(Option<Key>, Key)>> {
+        stream_from_scmstore(self.0.clone(), keys, FileAttributes::CONTENT, |file| {
+            file.copy_info()
+        })
+        .boxed()
     }
 }
 
@@ -69,7 +95,7 @@ const FETCH_PARALLELISM: usize = 20;
 fn stream_data_from_remote_data_store<DS: RemoteDataStore + Clone + 'static>(
     store: DS,
     keys: Vec<Key>,
-) -> impl Stream<Item = Result<(Bytes, Key)>> {
+) -> impl Stream<Item = Result<((Bytes, Option<Key>), Key)>> {
     stream::iter(keys.into_iter().map(StoreKey::HgId))
         .chunks(PREFETCH_CHUNK_SIZE)
         .map(move |chunk| {
@@ -90,7 +116,7 @@ fn stream_data_from_remote_data_store<DS: RemoteDataStore + Clone + 'static>(
                             let result = match store_result {
                                 Err(err) => Err(err),
                                 Ok(StoreResult::Found(data)) => {
-                                    strip_metadata(&data.into()).map(|(d, _)| (d, key.clone()))
+                                    strip_metadata(&data.into()).map(|d| (d, key.clone()))
                                 }
                                 Ok(StoreResult::NotFound(k)) => {
                                     Err(format_err!("{:?} not found in store", k))
@@ -116,20 +142,22 @@ fn stream_data_from_remote_data_store<DS: RemoteDataStore + Clone + 'static>(
         .flatten()
 }
 
-fn stream_data_from_scmstore(
+fn stream_from_scmstore<T: Send + 'static>(
     store: Arc<FileStore>,
     keys: Vec<Key>,
-) -> impl Stream<Item = Result<(Bytes, Key)>> {
+    attrs: FileAttributes,
+    extract: fn(&mut StoreFile) -> Result<T>,
+) -> impl Stream<Item = Result<(T, Key)>> {
     stream::iter(keys.into_iter())
         .chunks(PREFETCH_CHUNK_SIZE)
         .map(move |chunk| {
             let store = store.clone();
             Handle::current().spawn_blocking(move || {
                 let mut data = vec![];
-                for result in store.fetch(chunk.iter().cloned(), FileAttributes::CONTENT) {
+                for result in store.fetch(chunk.iter().cloned(), attrs) {
                     let result = match result {
                         Err(err) => Err(err.into()),
-                        Ok((key, mut file)) => file.file_content().map(|content| (content, key)),
+                        Ok((key, mut file)) => extract(&mut file).map(|value| (value, key)),
                     };
                     let is_err = result.is_err();
                     data.push(result);
@@ -0,0 +1,212 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+//! Compare sparse profiles and find rules that don't matter.
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use types::RepoPath;
+use types::RepoPathBuf;
+
+use crate::Matcher;
+use crate::Pattern;
+
+/// Files whose inclusion differs between two sparse profiles.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ProfileDiff {
+    /// Files included by the new profile but not the old one.
+    pub added: Vec<RepoPathBuf>,
+    /// Files included by the old profile but not the new one.
+    pub removed: Vec<RepoPathBuf>,
+}
+
+/// Compare which of `files` are included by `old` and `new`.
+///
+/// To avoid walking a whole commit, `files` only needs to contain the files
+/// matched by either profile.
+pub fn diff(
+    old: &Matcher,
+    new: &Matcher,
+    files: impl IntoIterator<Item = RepoPathBuf>,
+) -> anyhow::Result<ProfileDiff> {
+    let mut diff = ProfileDiff::default();
+    for path in files {
+        match (old.matches(&path)?, new.matches(&path)?) {
+            (false, true) => diff.added.push(path),
+            (true, false) => diff.removed.push(path),
+            _ => {}
+        }
+    }
+    Ok(diff)
+}
+
+/// How much a rule of a profile is used by a set of files.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RuleUsage {
+    pub include: bool,
+    /// The rule as written in the profile, e.g. "path:foo".
+    pub pattern: String,
+    pub origin: String,
+    /// Number of files matched by the rule.
+    pub matched: usize,
+    /// Number of files for which the rule is the one deciding whether they
+    /// are included (see `Matcher::explain_rule`).
+    pub decided: usize,
+}
+
+impl RuleUsage {
+    /// The rule doesn't match any file.
+    pub fn is_unused(&self) -> bool {
+        self.matched == 0
+    }
+
+    /// The rule matches files, but later rules always override it.
+    pub fn is_shadowed(&self) -> bool {
+        self.matched > 0 && self.decided == 0
+    }
+}
+
+impl Matcher {
+    /// Count how many of `files` each rule matches and decides. Rules are
+    /// returned in evaluation order. An empty profile has no rules.
+    pub fn rule_usage(
+        &self,
+        files: impl IntoIterator<Item = impl AsRef<RepoPath>>,
+    ) -> anyhow::Result<Vec<RuleUsage>> {
+        // A profile rule can expand to several matcher rules. Map each matcher
+        // rule to the usage entry of the rule it came from.
+        let mut usage: Vec<RuleUsage> = Vec::new();
+        let mut slots: Vec<Vec<usize>> = Vec::with_capacity(self.matchers.len());
+        for (patterns, origins) in self.rule_patterns.iter().zip(self.rule_origins.iter()) {
+            let mut seen: HashMap<(&Pattern, &str), usize> = HashMap::new();
+            let mut matcher_slots = Vec::with_capacity(patterns.len());
+            for (pattern, origin) in patterns.iter().zip(origins.iter()) {
+                let slot = *seen.entry((pattern, origin.as_str())).or_insert_with(|| {
+                    usage.push(RuleUsage {
+                        include: matches!(pattern, Pattern::Include(_)),
+                        pattern: pattern.as_str().to_string(),
+                        origin: origin.clone(),
+                        matched: 0,
+                        decided: 0,
+                    });
+                    usage.len() - 1
+                });
+                matcher_slots.push(slot);
+            }
+            slots.push(matcher_slots);
+        }
+
+        for path in files {
+            let path = path.as_ref();
+            let mut matched = HashSet::new();
+            for (i, m) in self.matchers.iter().enumerate() {
+                for idx in m.matching_rule_indexes(path.as_str()) {
+                    matched.insert(slots[i][idx]);
+                }
+            }
+            for slot in matched {
+                usage[slot].matched += 1;
+            }
+            if let Some((i, idx)) = self.deciding_rule(path) {
+                usage[slots[i][idx]].decided += 1;
+            }
+        }
+
+        Ok(usage)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Root;
+
+    async fn matcher(profile: &'static [u8]) -> Matcher {
+        Root::from_bytes(profile, "test".to_string())
+            .unwrap()
+            .matcher(|_| async { Ok(Some(vec![])) })
+            .await
+            .unwrap()
+    }
+
+    fn paths(paths: &[&str]) -> Vec<RepoPathBuf> {
+        paths
+            .iter()
+            .map(|p| RepoPathBuf::from_string(p.to_string()).unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_diff() -> anyhow::Result<()> {
+        let old = matcher(b"a\nb").await;
+        let new = matcher(b"b\nc\n[exclude]\nb/x").await;
+
+        let diff = diff(&old, &new, paths(&["a/1", "b/1", "b/x/1", "c/1", "d/1"]))?;
+        assert_eq!(diff.added, paths(&["c/1"]));
+        assert_eq!(diff.removed, paths(&["a/1", "b/x/1"]));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rule_usage() -> anyhow::Result<()> {
+        let matcher = matcher(b"a\nglob:{b,c}\na/b\nunused\n[exclude]\na/b/x").await;
+
+        let usage = matcher.rule_usage(paths(&["a/1", "a/b/1", "a/b/x/1", "b/1", "d/1"]))?;
+        let usage: Vec<(&str, usize, usize)> = usage
+            .iter()
+            .filter(|u| u.origin != "(builtin)")
+            .map(|u| (u.pattern.as_str(), u.matched, u.decided))
+            .collect();
+        assert_eq!(
+            usage,
+            vec![
+                ("unused", 0, 0),
+                ("a/b", 2, 0),
+                ("glob:{b,c}", 1, 1),
+                ("a", 3, 2),
+                ("a/b/x", 1, 1),
+            ]
+        );
+
+        assert!(matcher
+            .rule_usage(paths(&[]))?
+            .iter()
+            .all(|u| u.is_unused()));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_shadowed() -> anyhow::Result<()> {
+        // Includes are evaluated in reverse, so "a" overrides "a/b".
+        let matcher = matcher(b"a\na/b").await;
+
+        let usage = matcher.rule_usage(paths(&["a/b/1", "a/c"]))?;
+        let shadowed: Vec<&str> = usage
+            .iter()
+            .filter(|u| u.is_shadowed())
+            .map(|u| u.pattern.as_str())
+            .collect();
+        assert_eq!(shadowed, vec!["a/b"]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_explain_rule() {
+        let matcher = matcher(b"a\n[exclude]\na/b").await;
+
+        let explained = matcher.explain_rule("a/b/c".try_into().unwrap()).unwrap();
+        assert!(!explained.included);
+        assert_eq!(explained.pattern, "a/b");
+        assert_eq!(explained.origin, "test");
+
+        assert!(matcher.explain_rule("z".try_into().unwrap()).is_none());
+    }
+}
@@ -20,6 +20,12 @@ use pathmatcher::PatternKind;
 use regex::Regex;
 use types::RepoPath;
 
+mod analysis;
+
+pub use crate::analysis::diff;
+pub use crate::analysis::ProfileDiff;
+pub use crate::analysis::RuleUsage;
+
 #[derive(Default, Debug)]
 pub struct Profile {
     // Where this profile came from (typically a file path).
@@ -39,7 +45,7 @@ pub struct Profile {
 #[derive(Debug)]
 pub struct Root(Profile);
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 enum Pattern {
     Include(String),
     Exclude(String),
@@ -112,8 +118,9 @@ impl Root {
 
         let mut matchers: Vec<pathmatcher::TreeMatcher> = Vec::new();
 
-        // List of rule origins per-matcher.
+        // List of rule origins and patterns per-matcher.
         let mut rule_origins: Vec<Vec<String>> = Vec::new();
+        let mut rule_patterns: Vec<Vec<Pattern>> = Vec::new();
 
         let mut rules: VecDeque<(Pattern, String)> = VecDeque::new();
 
@@ -123,27 +130,29 @@ impl Root {
             Pattern::Include(_) => rules.push_front((pat, src)),
         };
 
-        let prepare_rules =
-            |rules: VecDeque<(Pattern, String)>| -> Result<(Vec<String>, Vec<String>), Error> {
-                let mut matcher_rules = Vec::new();
-                let mut origins = Vec::new();
+        type PreparedRules = (Vec<String>, Vec<String>, Vec<Pattern>);
+        let prepare_rules = |rules: VecDeque<(Pattern, String)>| -> Result<PreparedRules, Error> {
+            let mut matcher_rules = Vec::new();
+            let mut origins = Vec::new();
+            let mut patterns = Vec::new();
 
-                for (pat, src) in rules {
-                    match sparse_pat_to_matcher_rule(&pat) {
-                        Err(err) => {
-                            tracing::error!(%err, ?pat, %src, "ignoring unsupported sparse pattern");
-                        }
-                        Ok(rules) => {
-                            for expanded_rule in rules {
-                                matcher_rules.push(expanded_rule);
-                                origins.push(src.clone());
-                            }
+            for (pat, src) in rules {
+                match sparse_pat_to_matcher_rule(&pat) {
+                    Err(err) => {
+                        tracing::error!(%err, ?pat, %src, "ignoring unsupported sparse pattern");
+                    }
+                    Ok(rules) => {
+                        for expanded_rule in rules {
+                            matcher_rules.push(expanded_rule);
+                            origins.push(src.clone());
+                            patterns.push(pat.clone());
                         }
                     }
                 }
+            }
 
-                Ok((matcher_rules, origins))
-            };
+            Ok((matcher_rules, origins, patterns))
+        };
 
         let mut only_v1 = true;
         for entry in self.0.entries.iter() {
@@ -168,9 +177,10 @@ impl Root {
                     if child.is_v2() {
                         only_v1 = false;
 
-                        let (matcher_rules, origins) = prepare_rules(child_rules)?;
+                        let (matcher_rules, origins, patterns) = prepare_rules(child_rules)?;
                         matchers.push(pathmatcher::TreeMatcher::from_rules(matcher_rules.iter())?);
                         rule_origins.push(origins);
+                        rule_patterns.push(patterns);
                     } else {
                         for rule in child_rules {
                             push_rule(rule);
@@ -192,11 +202,12 @@ impl Root {
             "(builtin)".to_string(),
         ));
 
-        let (matcher_rules, origins) = prepare_rules(rules)?;
+        let (matcher_rules, origins, patterns) = prepare_rules(rules)?;
         matchers.push(pathmatcher::TreeMatcher::from_rules(matcher_rules.iter())?);
         rule_origins.push(origins);
+        rule_patterns.push(patterns);
 
-        Ok(Matcher::new(matchers, rule_origins))
+        Ok(Matcher::new(matchers, rule_origins, rule_patterns))
     }
 }
 
@@ -378,6 +389,17 @@ pub struct Matcher {
     matchers: Vec<pathmatcher::TreeMatcher>,
     // List of rule origins per-matcher.
     rule_origins: Vec<Vec<String>>,
+    // List of the profile patterns each matcher rule was expanded from.
+    rule_patterns: Vec<Vec<Pattern>>,
+}
+
+/// The sparse rule deciding whether a path is included.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RuleMatch {
+    pub included: bool,
+    /// The rule as written in the profile, e.g. "path:foo".
+    pub pattern: String,
+    pub origin: String,
 }
 
 impl Matcher {
@@ -396,18 +418,45 @@ impl Matcher {
             return Ok((true, "implicit match due to empty profile".to_string()));
         }
 
-        for (i, m) in self.matchers.iter().enumerate() {
-            if let Some(idx) = m.matching_rule_indexes(path.as_str()).last() {
-                let rule_origin = self
-                    .rule_origins
-                    .get(i)
-                    .and_then(|o| o.get(*idx))
-                    .map_or("(unknown)".to_string(), |o| o.clone());
-                return Ok((m.matches(path.as_str()), rule_origin));
-            }
+        match self.deciding_rule(path) {
+            Some((i, idx)) => Ok((self.matchers[i].matches(path.as_str()), self.origin(i, idx))),
+            None => Ok((false, "no rules matched".to_string())),
         }
+    }
+
+    /// Like `explain`, but also report the pattern of the deciding rule.
+    /// Returns None if no rule matched, or the profile is empty.
+    pub fn explain_rule(&self, path: &RepoPath) -> Option<RuleMatch> {
+        let (i, idx) = self.deciding_rule(path)?;
+        Some(RuleMatch {
+            included: self.matchers[i].matches(path.as_str()),
+            pattern: self.pattern(i, idx),
+            origin: self.origin(i, idx),
+        })
+    }
+
+    /// Find the rule deciding whether `path` is included, as (matcher index,
+    /// rule index): the last matching rule of the first matcher with one.
+    fn deciding_rule(&self, path: &RepoPath) -> Option<(usize, usize)> {
+        self.matchers.iter().enumerate().find_map(|(i, m)| {
+            m.matching_rule_indexes(path.as_str())
+                .last()
+                .map(|idx| (i, *idx))
+        })
+    }
+
+    fn origin(&self, matcher: usize, idx: usize) -> String {
+        self.rule_origins
+            .get(matcher)
+            .and_then(|o| o.get(idx))
+            .map_or("(unknown)".to_string(), |o| o.clone())
+    }
 
-        Ok((false, "no rules matched".to_string()))
+    fn pattern(&self, matcher: usize, idx: usize) -> String {
+        self.rule_patterns
+            .get(matcher)
+            .and_then(|p| p.get(idx))
+            .map_or("(unknown)".to_string(), |p| p.as_str().to_string())
     }
 }
 
@@ -428,17 +477,23 @@ impl pathmatcher::Matcher for Matcher {
 }
 
 impl Matcher {
-    fn new(matchers: Vec<pathmatcher::TreeMatcher>, rule_origins: Vec<Vec<String>>) -> Self {
+    fn new(
+        matchers: Vec<pathmatcher::TreeMatcher>,
+        rule_origins: Vec<Vec<String>>,
+        rule_patterns: Vec<Vec<Pattern>>,
+    ) -> Self {
         Self {
             always: false,
             matchers,
             rule_origins,
+            rule_patterns,
         }
     }
     fn always() -> Self {
         Self {
             always: true,
             rule_origins: Vec::new(),
+            rule_patterns: Vec::new(),
             matchers: Vec::new(),
         }
     }
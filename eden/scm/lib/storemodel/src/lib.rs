@@ -23,6 +23,7 @@
 use async_trait::async_trait;
 pub use futures;
 use futures::stream::BoxStream;
+use futures::StreamExt;
 pub use minibytes;
 pub use types;
 use types::HgId;
@@ -46,6 +47,24 @@ pub trait ReadFileContents {
         &self,
         keys: Vec<Key>,
     ) -> BoxStream<Result<(minibytes::Bytes, Key), Self::Error>>;
+
+    /// Read the sizes of specified files, as returned by `read_file_contents`.
+    ///
+    /// The default implementation reads the file contents. Stores that keep
+    /// file aux data should answer from it instead.
+    async fn read_file_sizes(&self, keys: Vec<Key>) -> BoxStream<Result<(u64, Key), Self::Error>> {
+        self.read_file_contents(keys)
+            .await
+            .map(|result| result.map(|(content, key)| (content.len() as u64, key)))
+            .boxed()
+    }
+
+    /// Read the "copy from" header of specified files, which names the file
+    /// that each one was copied or renamed from, if any.
+    async fn read_rename_metadata(
+        &self,
+        keys: Vec<Key>,
+    ) -> BoxStream<Result<(Option<Key>, Key), Self::Error>>;
 }
 
 pub trait RefreshableReadFileContents: ReadFileContents {
@@ -10,6 +10,7 @@
 pub use anyhow;
 pub use edenfs;
 pub use edenfs_config;
+pub use edenfs_overlay;
 pub use fb303_core;
 pub use fbthrift;
 pub use futures;
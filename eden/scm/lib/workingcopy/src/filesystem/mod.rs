@@ -11,9 +11,18 @@ pub use pendingchanges::ChangeType;
 pub use pendingchanges::PendingChangeResult;
 pub use pendingchanges::PendingChanges;
 
-#[derive(PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum FileSystemType {
     Normal,
     Watchman,
     Eden,
+    Notify,
+}
+
+impl FileSystemType {
+    /// Whether a file system monitor tracks changed files, so untracked
+    /// files of interest are kept in the treestate.
+    pub fn is_monitored(self) -> bool {
+        matches!(self, FileSystemType::Watchman | FileSystemType::Notify)
+    }
 }
@@ -10,9 +10,12 @@ pub mod edenfs;
 
 mod filechangedetector;
 pub mod filesystem;
+#[cfg(target_os = "linux")]
+pub mod notifyfs;
 pub mod physicalfs;
 pub mod sparse;
 pub mod status;
+pub mod track;
 pub mod walker;
 pub mod watchmanfs;
 pub mod workingcopy;
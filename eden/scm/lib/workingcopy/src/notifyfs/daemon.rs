@@ -0,0 +1,308 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+//! The per working copy watcher daemon.
+//!
+//! The daemon recursively watches the working copy with inotify and appends
+//! every path it sees change to the journal. It also watches its own state
+//! directory for cookie files, which clients create to make sure all events
+//! that happened before a query have made it into the journal.
+
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::Duration;
+use std::time::SystemTime;
+
+use anyhow::bail;
+use anyhow::Result;
+use inotify::EventMask;
+use inotify::Inotify;
+use inotify::WatchDescriptor;
+use inotify::WatchMask;
+use repolock::LockError;
+
+use super::journal::JournalWriter;
+use super::journal::JOURNAL_FILE;
+use super::journal::NOTIFY_DIR;
+use super::notifyfs::last_used;
+use super::notifyfs::COOKIE_PREFIX;
+use super::notifyfs::DAEMON_LOCK;
+
+/// Start a new journal instance once the current one grows past this size so
+/// clients don't have to scan an ever growing file.
+const MAX_JOURNAL_SIZE: u64 = 64 << 20;
+
+/// How often to wake up and check whether the daemon is still wanted.
+const IDLE_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+fn watch_mask() -> WatchMask {
+    WatchMask::CREATE
+        | WatchMask::DELETE
+        | WatchMask::MODIFY
+        | WatchMask::ATTRIB
+        | WatchMask::CLOSE_WRITE
+        | WatchMask::MOVED_FROM
+        | WatchMask::MOVED_TO
+        | WatchMask::DELETE_SELF
+        | WatchMask::ONLYDIR
+        | WatchMask::DONT_FOLLOW
+}
+
+/// Watch the working copy at `root` until it is removed or nobody has asked
+/// for its status in `idle_timeout`. Returns immediately if another daemon is
+/// already watching it.
+pub fn run_daemon(root: &Path, dot_dir: &str, idle_timeout: Duration) -> Result<()> {
+    let notify_dir = root.join(dot_dir).join(NOTIFY_DIR);
+    std::fs::create_dir_all(&notify_dir)?;
+
+    let _lock = match repolock::try_lock(
+        &notify_dir,
+        DAEMON_LOCK,
+        std::process::id().to_string().as_bytes(),
+    ) {
+        Ok(lock) => lock,
+        Err(LockError::Contended(_)) => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut watcher = Watcher::start(root, dot_dir, notify_dir)?;
+    loop {
+        if !wait_readable(&watcher.inotify, IDLE_CHECK_INTERVAL)? {
+            if !root.exists() {
+                break;
+            }
+            let idle = match last_used(&watcher.notify_dir) {
+                Some(time) => SystemTime::now().duration_since(time).unwrap_or_default(),
+                None => Duration::MAX,
+            };
+            if idle > idle_timeout {
+                tracing::info!(?idle, "notifyfs daemon exiting after idle timeout");
+                break;
+            }
+            continue;
+        }
+        if !watcher.process_events()? {
+            break;
+        }
+    }
+
+    // Don't leave behind a journal that nobody is appending to anymore.
+    let _ = std::fs::remove_file(watcher.notify_dir.join(JOURNAL_FILE));
+    Ok(())
+}
+
+struct Watcher {
+    inotify: Inotify,
+    root: PathBuf,
+    dot_dir: PathBuf,
+    notify_dir: PathBuf,
+    notify_wd: WatchDescriptor,
+    /// Watched directories, relative to the root.
+    watches: HashMap<WatchDescriptor, PathBuf>,
+    journal: JournalWriter,
+}
+
+impl Watcher {
+    fn start(root: &Path, dot_dir: &str, notify_dir: PathBuf) -> Result<Self> {
+        let mut inotify = Inotify::init()?;
+        let notify_wd = inotify.add_watch(&notify_dir, WatchMask::CREATE)?;
+        let mut watches = HashMap::new();
+        add_watches(
+            &mut inotify,
+            &mut watches,
+            root,
+            Path::new(dot_dir),
+            PathBuf::new(),
+            &mut |_| Ok(()),
+        )?;
+
+        // Only publish the journal once everything is watched: its existence
+        // tells clients that the daemon is ready.
+        let journal = JournalWriter::create(&notify_dir)?;
+
+        Ok(Watcher {
+            inotify,
+            root: root.to_path_buf(),
+            dot_dir: PathBuf::from(dot_dir),
+            notify_dir,
+            notify_wd,
+            watches,
+            journal,
+        })
+    }
+
+    /// Drop all watches and start over with a new journal instance. Clients
+    /// holding a clock from the previous instance will fall back to a full
+    /// crawl.
+    fn restart(&mut self) -> Result<()> {
+        tracing::info!("restarting notifyfs journal");
+        let dot_dir = self.dot_dir.to_string_lossy().into_owned();
+        *self = Watcher::start(&self.root, &dot_dir, self.notify_dir.clone())?;
+        Ok(())
+    }
+
+    /// Drain pending inotify events into the journal. Returns false if the
+    /// working copy has gone away.
+    fn process_events(&mut self) -> Result<bool> {
+        let mut buffer = [0; 64 * 1024];
+        loop {
+            let events: Vec<(WatchDescriptor, EventMask, Option<OsString>)> =
+                match self.inotify.read_events(&mut buffer) {
+                    Ok(events) => events
+                        .map(|e| (e.wd, e.mask, e.name.map(|n| n.to_os_string())))
+                        .collect(),
+                    Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                    Err(e) => return Err(e.into()),
+                };
+            if events.is_empty() {
+                break;
+            }
+
+            for (wd, mask, name) in events {
+                if mask.contains(EventMask::Q_OVERFLOW) {
+                    // Events were dropped; the journal can't be trusted anymore.
+                    self.restart()?;
+                    return Ok(true);
+                }
+
+                if wd == self.notify_wd {
+                    if let Some(name) = name {
+                        if name.as_bytes().starts_with(COOKIE_PREFIX.as_bytes()) {
+                            // Everything before the cookie is in the journal.
+                            let _ = std::fs::remove_file(self.notify_dir.join(name));
+                        }
+                    }
+                    continue;
+                }
+
+                if mask.contains(EventMask::IGNORED) {
+                    self.watches.remove(&wd);
+                    continue;
+                }
+
+                let dir = match self.watches.get(&wd) {
+                    Some(dir) => dir.clone(),
+                    None => continue,
+                };
+
+                if mask.contains(EventMask::DELETE_SELF) {
+                    if dir.as_os_str().is_empty() {
+                        return Ok(false);
+                    }
+                    continue;
+                }
+
+                let path = match name {
+                    Some(name) => dir.join(name),
+                    None => continue,
+                };
+                if path == self.dot_dir {
+                    continue;
+                }
+
+                self.journal.record(path.as_os_str().as_bytes())?;
+
+                if mask.contains(EventMask::ISDIR)
+                    && mask.intersects(EventMask::CREATE | EventMask::MOVED_TO)
+                {
+                    // Files may have been created in the new directory before
+                    // we got to watch it, so record everything found in it.
+                    let journal = &mut self.journal;
+                    add_watches(
+                        &mut self.inotify,
+                        &mut self.watches,
+                        &self.root,
+                        &self.dot_dir,
+                        path,
+                        &mut |path| journal.record(path.as_os_str().as_bytes()),
+                    )?;
+                }
+            }
+
+            if self.journal.len() > MAX_JOURNAL_SIZE {
+                self.restart()?;
+                return Ok(true);
+            }
+        }
+        Ok(true)
+    }
+}
+
+/// Recursively watch `dir` (relative to `root`) and its subdirectories,
+/// calling `found` for every file found along the way.
+fn add_watches(
+    inotify: &mut Inotify,
+    watches: &mut HashMap<WatchDescriptor, PathBuf>,
+    root: &Path,
+    dot_dir: &Path,
+    dir: PathBuf,
+    found: &mut dyn FnMut(&Path) -> Result<()>,
+) -> Result<()> {
+    let mut pending = vec![dir];
+    while let Some(dir) = pending.pop() {
+        match inotify.add_watch(root.join(&dir), watch_mask()) {
+            Ok(wd) => {
+                watches.insert(wd, dir.clone());
+            }
+            // The directory went away before we got to it.
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) if e.raw_os_error() == Some(libc::ENOTDIR) => continue,
+            Err(e) if e.raw_os_error() == Some(libc::ENOSPC) => bail!(
+                "inotify watch limit reached while watching {}; consider raising fs.inotify.max_user_watches",
+                root.display()
+            ),
+            Err(e) => return Err(e.into()),
+        }
+
+        let entries = match std::fs::read_dir(root.join(&dir)) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e.into()),
+        };
+        for entry in entries {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e.into()),
+            };
+            let path = dir.join(entry.file_name());
+            if path == dot_dir {
+                continue;
+            }
+            if entry.file_type()?.is_dir() {
+                pending.push(path);
+            } else {
+                found(&path)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Wait up to `timeout` for events. Returns false on timeout.
+fn wait_readable(inotify: &Inotify, timeout: Duration) -> Result<bool> {
+    let mut fds = [libc::pollfd {
+        fd: inotify.as_raw_fd(),
+        events: libc::POLLIN,
+        revents: 0,
+    }];
+    loop {
+        let ret = unsafe { libc::poll(fds.as_mut_ptr(), 1, timeout.as_millis() as libc::c_int) };
+        if ret >= 0 {
+            return Ok(ret > 0);
+        }
+        let err = io::Error::last_os_error();
+        if err.kind() != io::ErrorKind::Interrupted {
+            return Err(err.into());
+        }
+    }
+}
@@ -0,0 +1,254 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+//! The change journal shared by the watcher daemon and `NotifyFileSystem`.
+//!
+//! The journal is a file in the working copy's dot dir. Its first line names
+//! the daemon instance that wrote it, and it is followed by NUL terminated
+//! paths, relative to the working copy root, in the order the daemon saw them
+//! change. A position in the journal is a `Clock`. Whenever the daemon can no
+//! longer vouch for the journal (it restarted, the kernel dropped events, or
+//! the journal grew too big) it atomically replaces it with a new instance,
+//! which invalidates all clocks from the old one.
+
+use std::fmt;
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::ErrorKind;
+use std::io::Read;
+use std::io::Seek;
+use std::io::SeekFrom;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use anyhow::anyhow;
+use anyhow::Result;
+use types::RepoPathBuf;
+
+/// Directory in the dot dir holding the journal and the daemon's files.
+pub const NOTIFY_DIR: &str = "notifyfs";
+pub const JOURNAL_FILE: &str = "journal";
+
+/// A position in a journal written by one daemon instance.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Clock {
+    instance: String,
+    offset: u64,
+}
+
+impl fmt::Display for Clock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.instance, self.offset)
+    }
+}
+
+impl FromStr for Clock {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (instance, offset) = s
+            .rsplit_once(':')
+            .ok_or_else(|| anyhow!("invalid notifyfs clock: {}", s))?;
+        Ok(Clock {
+            instance: instance.to_string(),
+            offset: offset.parse()?,
+        })
+    }
+}
+
+pub struct JournalWriter {
+    file: File,
+    len: u64,
+}
+
+impl JournalWriter {
+    /// Start a new journal instance in `dir`, replacing any previous one.
+    pub fn create(dir: &Path) -> Result<Self> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?;
+        let instance = format!("{}-{}", std::process::id(), now.as_nanos());
+        let header = format!("{}\n", instance);
+
+        let tmp_path = dir.join(format!("{}.{}", JOURNAL_FILE, instance));
+        let mut file = OpenOptions::new()
+            .create_new(true)
+            .append(true)
+            .open(&tmp_path)?;
+        file.write_all(header.as_bytes())?;
+        std::fs::rename(&tmp_path, journal_path(dir))?;
+
+        Ok(JournalWriter {
+            file,
+            len: header.len() as u64,
+        })
+    }
+
+    /// Record that `path`, relative to the working copy root, has changed.
+    pub fn record(&mut self, path: &[u8]) -> Result<()> {
+        let mut record = Vec::with_capacity(path.len() + 1);
+        record.extend_from_slice(path);
+        record.push(0);
+        self.file.write_all(&record)?;
+        self.len += record.len() as u64;
+        Ok(())
+    }
+
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+}
+
+/// The result of reading the journal.
+pub struct JournalRead {
+    /// The end of the journal.
+    pub clock: Clock,
+    /// Paths that changed since the requested clock, or None if that can't be
+    /// known because the clock is from a different daemon instance.
+    pub changed: Option<Vec<RepoPathBuf>>,
+}
+
+fn journal_path(dir: &Path) -> PathBuf {
+    dir.join(JOURNAL_FILE)
+}
+
+/// Read the changes since `since` from the journal in `dir`. Returns None if
+/// there is no journal, meaning the daemon isn't running or isn't ready yet.
+pub fn read(dir: &Path, since: Option<&Clock>) -> Result<Option<JournalRead>> {
+    let file = match File::open(journal_path(dir)) {
+        Ok(file) => file,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let mut reader = BufReader::new(file);
+
+    let mut header = String::new();
+    reader.read_line(&mut header)?;
+    let instance = header
+        .strip_suffix('\n')
+        .ok_or_else(|| anyhow!("truncated notifyfs journal header"))?
+        .to_string();
+    let header_len = header.len() as u64;
+
+    let start = match since {
+        Some(since) if since.instance == instance && since.offset >= header_len => since.offset,
+        _ => {
+            // Skip to the end: everything has to be checked anyway.
+            let end = reader.seek(SeekFrom::End(0))?;
+            let offset = last_record_end(&mut reader, header_len, end)?;
+            return Ok(Some(JournalRead {
+                clock: Clock { instance, offset },
+                changed: None,
+            }));
+        }
+    };
+
+    reader.seek(SeekFrom::Start(start))?;
+    let mut data = Vec::new();
+    reader.read_to_end(&mut data)?;
+
+    // The daemon may be in the middle of appending a record; stop at the last
+    // complete one and pick the rest up next time.
+    let complete = data.iter().rposition(|b| *b == 0).map_or(0, |pos| pos + 1);
+    let changed = data[..complete]
+        .split(|b| *b == 0)
+        .filter(|path| !path.is_empty())
+        .filter_map(|path| RepoPathBuf::from_utf8(path.to_vec()).ok())
+        .collect();
+
+    Ok(Some(JournalRead {
+        clock: Clock {
+            instance,
+            offset: start + complete as u64,
+        },
+        changed: Some(changed),
+    }))
+}
+
+/// Find the end of the last complete record at or before `end`.
+fn last_record_end(reader: &mut BufReader<File>, header_len: u64, end: u64) -> Result<u64> {
+    const CHUNK: u64 = 4096;
+    let mut pos = end;
+    while pos > header_len {
+        let chunk_start = pos.saturating_sub(CHUNK).max(header_len);
+        let mut buf = vec![0; (pos - chunk_start) as usize];
+        reader.seek(SeekFrom::Start(chunk_start))?;
+        reader.read_exact(&mut buf)?;
+        if let Some(i) = buf.iter().rposition(|b| *b == 0) {
+            return Ok(chunk_start + i as u64 + 1);
+        }
+        pos = chunk_start;
+    }
+    Ok(header_len)
+}
+
+#[cfg(test)]
+mod tests {
+    use tempdir::TempDir;
+
+    use super::*;
+
+    fn changed(dir: &Path, since: &Clock) -> (Vec<String>, Clock) {
+        let read = read(dir, Some(since)).unwrap().unwrap();
+        let changed = read
+            .changed
+            .unwrap()
+            .into_iter()
+            .map(|p| p.into_string())
+            .collect();
+        (changed, read.clock)
+    }
+
+    #[test]
+    fn test_journal() {
+        let dir = TempDir::new("journal").unwrap();
+        let dir = dir.path();
+        assert!(read(dir, None).unwrap().is_none());
+
+        let mut writer = JournalWriter::create(dir).unwrap();
+        let start = read(dir, None).unwrap().unwrap();
+        assert!(start.changed.is_none());
+
+        writer.record(b"a/b").unwrap();
+        writer.record(b"c").unwrap();
+        let (paths, clock) = changed(dir, &start.clock);
+        assert_eq!(paths, vec!["a/b", "c"]);
+
+        let (paths, clock) = changed(dir, &clock);
+        assert!(paths.is_empty());
+
+        writer.record(b"d").unwrap();
+        let (paths, _) = changed(dir, &clock);
+        assert_eq!(paths, vec!["d"]);
+
+        // A partially written record isn't reported until it is complete.
+        writer.file.write_all(b"e").unwrap();
+        let (paths, clock) = changed(dir, &clock);
+        assert_eq!(paths, vec!["d"]);
+        writer.file.write_all(b"\0").unwrap();
+        let (paths, _) = changed(dir, &clock);
+        assert_eq!(paths, vec!["e"]);
+
+        // Clocks from a previous instance are not usable.
+        JournalWriter::create(dir).unwrap();
+        assert!(read(dir, Some(&clock)).unwrap().unwrap().changed.is_none());
+    }
+
+    #[test]
+    fn test_clock_roundtrip() {
+        let clock = Clock {
+            instance: "123-456".to_string(),
+            offset: 789,
+        };
+        assert_eq!(clock.to_string().parse::<Clock>().unwrap(), clock);
+        assert!("nonsense".parse::<Clock>().is_err());
+    }
+}
@@ -0,0 +1,13 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+mod daemon;
+mod journal;
+mod notifyfs;
+
+pub use daemon::run_daemon;
+pub use notifyfs::NotifyFileSystem;
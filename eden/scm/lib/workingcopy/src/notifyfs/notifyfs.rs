@@ -0,0 +1,337 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use anyhow::anyhow;
+use anyhow::Result;
+use configmodel::Config;
+use configmodel::ConfigExt;
+use manifest_tree::ReadTreeManifest;
+use parking_lot::Mutex;
+use pathmatcher::AlwaysMatcher;
+use pathmatcher::DifferenceMatcher;
+use pathmatcher::GitignoreMatcher;
+use pathmatcher::Matcher;
+use repolock::LockError;
+use spawn_ext::CommandExt;
+use treestate::filestate::StateFlags;
+use treestate::metadata::Metadata;
+use treestate::serialization::Serializable;
+use treestate::treestate::TreeState;
+use types::RepoPathBuf;
+use vfs::VFS;
+
+use super::journal;
+use super::journal::Clock;
+use super::journal::JournalRead;
+use super::journal::NOTIFY_DIR;
+use crate::filechangedetector::ArcReadFileContents;
+use crate::filechangedetector::FileChangeDetector;
+use crate::filechangedetector::FileChangeDetectorTrait;
+use crate::filechangedetector::FileChangeResult;
+use crate::filechangedetector::ResolvedFileChangeResult;
+use crate::filesystem::PendingChangeResult;
+use crate::filesystem::PendingChanges;
+use crate::physicalfs::PhysicalFileSystem;
+use crate::watchmanfs::treestate::WatchmanTreeState;
+use crate::watchmanfs::treestate::WatchmanTreeStateWrite;
+use crate::workingcopy::WorkingCopy;
+
+type ArcReadTreeManifest = Arc<dyn ReadTreeManifest + Send + Sync>;
+
+pub(crate) const DAEMON_LOCK: &str = "daemon";
+pub(crate) const COOKIE_PREFIX: &str = "cookie.";
+const LAST_USED_FILE: &str = "lastused";
+const CLOCK_KEY: &str = "notifyclock";
+
+/// A `FileSystem` backed by a per working copy inotify daemon (see
+/// `debugnotifyd`), for machines where watchman isn't available.
+pub struct NotifyFileSystem {
+    vfs: VFS,
+    treestate: Arc<Mutex<TreeState>>,
+    tree_resolver: ArcReadTreeManifest,
+    store: ArcReadFileContents,
+    ignore_matcher: Arc<GitignoreMatcher>,
+}
+
+impl NotifyFileSystem {
+    pub fn new(
+        root: PathBuf,
+        treestate: Arc<Mutex<TreeState>>,
+        tree_resolver: ArcReadTreeManifest,
+        store: ArcReadFileContents,
+        ignore_matcher: Arc<GitignoreMatcher>,
+    ) -> Result<Self> {
+        Ok(NotifyFileSystem {
+            vfs: VFS::new(root)?,
+            treestate,
+            tree_resolver,
+            store,
+            ignore_matcher,
+        })
+    }
+
+    fn notify_dir(&self) -> Result<PathBuf> {
+        let ident = identity::must_sniff_dir(self.vfs.root())?;
+        Ok(self.vfs.root().join(ident.dot_dir()).join(NOTIFY_DIR))
+    }
+
+    /// Make sure a daemon is watching the working copy. Returns false if one
+    /// had to be started, in which case it can't answer queries yet.
+    fn ensure_daemon(&self, notify_dir: &Path) -> Result<bool> {
+        std::fs::create_dir_all(notify_dir)?;
+        touch_last_used(notify_dir)?;
+
+        match repolock::try_lock(notify_dir, DAEMON_LOCK, b"") {
+            Err(LockError::Contended(_)) => return Ok(true),
+            Err(e) => return Err(e.into()),
+            Ok(_lock) => {}
+        }
+
+        tracing::info!("starting notifyfs daemon");
+        Command::new(std::env::current_exe()?)
+            .arg("debugnotifyd")
+            .current_dir(self.vfs.root())
+            .spawn_detached()?;
+        Ok(false)
+    }
+
+    fn read_clock(&self) -> Result<Option<Clock>> {
+        self.treestate
+            .lock()
+            .get_metadata_by_key(CLOCK_KEY)?
+            .map(|clock| clock.parse())
+            .transpose()
+    }
+
+    fn needs_check(&self) -> Result<HashSet<RepoPathBuf>> {
+        self.treestate
+            .lock()
+            .visit_by_state(StateFlags::NEED_CHECK)?
+            .into_iter()
+            .map(|(path, _state)| RepoPathBuf::from_utf8(path).map_err(|e| anyhow!(e)))
+            .collect()
+    }
+
+    /// Paths that could have changed given the journal entries in `changed`.
+    /// A journal entry for a directory that was removed or renamed stands for
+    /// everything the treestate knows about under it.
+    fn candidates(&self, changed: Vec<RepoPathBuf>) -> Result<HashSet<RepoPathBuf>> {
+        let ident = identity::must_sniff_dir(self.vfs.root())?;
+        let dot_dir = ident.dot_dir();
+
+        let mut treestate = self.treestate.lock();
+        let mut candidates = HashSet::new();
+        for path in changed {
+            if path.as_str() == dot_dir || path.as_str().starts_with(&format!("{}/", dot_dir)) {
+                continue;
+            }
+            if treestate.has_dir(path.as_byte_slice())? {
+                let prefix = format!("{}/", path);
+                treestate.path_complete(prefix.as_bytes(), true, &|_| true, &mut |components| {
+                    candidates.insert(RepoPathBuf::from_utf8(components.concat())?);
+                    Ok(())
+                })?;
+            }
+            let tracked = treestate.get(&path)?.is_some();
+            if tracked || !self.ignore_matcher.matches_file(&path)? {
+                candidates.insert(path);
+            }
+        }
+        Ok(candidates)
+    }
+}
+
+impl PendingChanges for NotifyFileSystem {
+    fn pending_changes(
+        &self,
+        _matcher: Arc<dyn Matcher + Send + Sync + 'static>,
+        last_write: SystemTime,
+        config: &dyn Config,
+    ) -> Result<Box<dyn Iterator<Item = Result<PendingChangeResult>>>> {
+        let notify_dir = self.notify_dir()?;
+        let sync_timeout =
+            Duration::from_millis(config.get_or("fsmonitor", "inotify-sync-timeout", || 5000)?);
+
+        let journal = if self.ensure_daemon(&notify_dir)? && sync(&notify_dir, sync_timeout)? {
+            journal::read(&notify_dir, self.read_clock()?.as_ref())?
+        } else {
+            None
+        };
+
+        let needs_check = self.needs_check()?;
+        let (changes, clock) = match journal {
+            Some(JournalRead {
+                clock,
+                changed: Some(changed),
+            }) => {
+                let mut candidates = self.candidates(changed)?;
+                candidates.extend(needs_check.iter().cloned());
+                (self.check(candidates, last_write)?, Some(clock))
+            }
+            // Either the daemon isn't ready yet, or we don't have a usable
+            // clock. Fall back to a full crawl. If the daemon is ready, the
+            // clock was taken before the crawl so nothing that changes during
+            // it gets lost.
+            Some(JournalRead {
+                clock,
+                changed: None,
+            }) => (self.crawl(last_write, config)?, Some(clock)),
+            None => (self.crawl(last_write, config)?, None),
+        };
+
+        let mut pending_changes = Vec::with_capacity(changes.len());
+        let mut marked = HashSet::new();
+        for change in changes {
+            if let Ok(PendingChangeResult::File(change)) = &change {
+                marked.insert(change.get_path().clone());
+            }
+            pending_changes.push(change);
+        }
+
+        let mut treestate = WatchmanTreeState {
+            treestate: self.treestate.clone(),
+            root: self.vfs.root(),
+        };
+        for path in needs_check.difference(&marked) {
+            if let Err(e) = treestate.clear_needs_check(path) {
+                pending_changes.push(Err(e));
+            }
+        }
+        for path in marked.difference(&needs_check) {
+            treestate.mark_needs_check(path)?;
+        }
+        if let Some(clock) = clock {
+            set_clock(&mut self.treestate.lock(), &clock)?;
+        }
+        treestate.flush(config)?;
+
+        Ok(Box::new(pending_changes.into_iter()))
+    }
+}
+
+impl NotifyFileSystem {
+    fn check(
+        &self,
+        candidates: HashSet<RepoPathBuf>,
+        last_write: SystemTime,
+    ) -> Result<Vec<Result<PendingChangeResult>>> {
+        let manifests =
+            WorkingCopy::current_manifests(&self.treestate.lock(), &self.tree_resolver)?;
+        let mut file_change_detector = FileChangeDetector::new(
+            self.treestate.clone(),
+            self.vfs.clone(),
+            last_write.try_into()?,
+            manifests[0].clone(),
+            self.store.clone(),
+        );
+
+        let mut pending_changes = candidates
+            .into_iter()
+            .filter_map(|path| match file_change_detector.has_changed(&path) {
+                Ok(FileChangeResult::Yes(change)) => Some(Ok(PendingChangeResult::File(change))),
+                Ok(FileChangeResult::No) | Ok(FileChangeResult::Maybe) => None,
+                Err(e) => Some(Err(e)),
+            })
+            .collect::<Vec<_>>();
+
+        for result in file_change_detector.resolve_maybes() {
+            match result {
+                Ok(ResolvedFileChangeResult::Yes(change)) => {
+                    pending_changes.push(Ok(PendingChangeResult::File(change)))
+                }
+                Ok(ResolvedFileChangeResult::No(_)) => {}
+                Err(e) => pending_changes.push(Err(e)),
+            }
+        }
+        Ok(pending_changes)
+    }
+
+    /// Crawl everything that isn't ignored, not just what the caller asked
+    /// about, so the result is complete enough to continue incrementally from.
+    fn crawl(
+        &self,
+        last_write: SystemTime,
+        config: &dyn Config,
+    ) -> Result<Vec<Result<PendingChangeResult>>> {
+        let matcher = Arc::new(DifferenceMatcher::new(
+            AlwaysMatcher::new(),
+            self.ignore_matcher.clone(),
+        ));
+        let physical = PhysicalFileSystem::new(
+            self.vfs.root().to_path_buf(),
+            self.tree_resolver.clone(),
+            self.store.clone(),
+            self.treestate.clone(),
+            false,
+            8,
+        )?;
+        Ok(physical
+            .pending_changes(matcher, last_write, config)?
+            .filter(|change| !matches!(change, Ok(PendingChangeResult::SeenDirectory(_))))
+            .collect())
+    }
+}
+
+fn set_clock(treestate: &mut TreeState, clock: &Clock) -> Result<()> {
+    let mut metadata_buf = treestate.get_metadata();
+    let mut metadata = Metadata::deserialize(&mut metadata_buf)?;
+    metadata.0.insert(CLOCK_KEY.to_string(), clock.to_string());
+    let mut metadata_buf = vec![];
+    metadata.serialize(&mut metadata_buf)?;
+    treestate.set_metadata(&metadata_buf);
+    Ok(())
+}
+
+/// Wait for the daemon to catch up with every event that happened before this
+/// call. Returns false if it didn't within `timeout`.
+fn sync(notify_dir: &Path, timeout: Duration) -> Result<bool> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?;
+    let cookie = notify_dir.join(format!(
+        "{}{}-{}",
+        COOKIE_PREFIX,
+        std::process::id(),
+        now.as_nanos()
+    ));
+    std::fs::write(&cookie, b"")?;
+
+    let start = Instant::now();
+    while cookie.exists() {
+        if start.elapsed() > timeout {
+            tracing::warn!("timed out waiting for notifyfs daemon");
+            let _ = std::fs::remove_file(&cookie);
+            return Ok(false);
+        }
+        std::thread::sleep(Duration::from_millis(1));
+    }
+    Ok(true)
+}
+
+fn touch_last_used(notify_dir: &Path) -> Result<()> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?;
+    util::file::atomic_write(&notify_dir.join(LAST_USED_FILE), |file| {
+        file.write_all(now.as_secs().to_string().as_bytes())
+    })
+    .map_err(|e| anyhow!(e))
+    .map(|_| ())
+}
+
+/// When a client last asked the daemon for changes.
+pub(crate) fn last_used(notify_dir: &Path) -> Option<SystemTime> {
+    let secs = std::fs::read_to_string(notify_dir.join(LAST_USED_FILE)).ok()?;
+    Some(UNIX_EPOCH + Duration::from_secs(secs.trim().parse().ok()?))
+}
@@ -67,52 +67,9 @@ fn build_matcher(
 ) -> anyhow::Result<Arc<dyn Matcher + Send + Sync + 'static>> {
     let manifest = Arc::new(manifest);
 
-    let matcher = try_block_unless_interrupted(prof.matcher(|path| async {
-        let path = path;
-
-        let file_id = {
-            let manifest = manifest.clone();
-            let repo_path = RepoPathBuf::from_string(path.clone())?;
-
-            // Work around nested block_on() calls by spawning a new thread.
-            // Once the Manifest is async this can go away.
-            tokio::task::spawn_blocking(move || match manifest.get(&repo_path)? {
-                None => {
-                    tracing::warn!(?repo_path, "non-existent sparse profile include");
-                    Ok::<_, Error>(None)
-                }
-                Some(fs_node) => match fs_node {
-                    FsNodeMetadata::File(FileMetadata { hgid, .. }) => Ok(Some(hgid)),
-                    FsNodeMetadata::Directory(_) => {
-                        tracing::warn!(?repo_path, "sparse profile include is a directory");
-                        Ok(None)
-                    }
-                },
-            })
-            .await??
-        };
-
-        let file_id = match file_id {
-            Some(id) => id,
-            None => return Ok(None),
-        };
-
-        let repo_path = RepoPathBuf::from_string(path.clone())?;
-        let mut stream = store
-            .read_file_contents(vec![Key::new(repo_path.clone(), file_id.clone())])
-            .await;
-        match stream.next().await {
-            Some(Ok((bytes, _key))) => {
-                let mut bytes = bytes.into_vec();
-                if let Some(extra) = overrides.get(&path) {
-                    bytes.append(&mut extra.to_string().into_bytes());
-                }
-                Ok(Some(bytes))
-            }
-            Some(Err(err)) => Err(err),
-            None => Err(anyhow!("no contents for {}", repo_path)),
-        }
-    }))?;
+    let matcher = try_block_unless_interrupted(
+        prof.matcher(|path| read_profile(path, manifest.clone(), &store, &overrides)),
+    )?;
 
     let mut matcher: Arc<dyn Matcher + Send + Sync + 'static> = Arc::new(matcher);
 
@@ -133,6 +90,73 @@ fn build_matcher(
     Ok(matcher)
 }
 
+/// Build a matcher for the sparse profile at `profile` in `manifest`, as if it
+/// was the only profile enabled. Unlike `repo_matcher`, this ignores the
+/// working copy's sparse config, which makes it useful to inspect profiles.
+pub fn profile_matcher(
+    profile: &str,
+    manifest: impl Manifest + Send + Sync + 'static,
+    store: impl ReadFileContents<Error = anyhow::Error> + Send + Sync,
+    overrides: HashMap<String, String>,
+) -> anyhow::Result<sparse::Matcher> {
+    let prof = sparse::Root::from_bytes(format!("%include {}", profile), "<cli>".to_string())?;
+    let manifest = Arc::new(manifest);
+    Ok(try_block_unless_interrupted(prof.matcher(|path| {
+        read_profile(path, manifest.clone(), &store, &overrides)
+    }))?)
+}
+
+/// Read the sparse profile at `path` in `manifest`, with config `overrides`
+/// applied. Returns None if there is no such profile.
+async fn read_profile(
+    path: String,
+    manifest: Arc<impl Manifest + Send + Sync + 'static>,
+    store: &(impl ReadFileContents<Error = anyhow::Error> + Send + Sync),
+    overrides: &HashMap<String, String>,
+) -> anyhow::Result<Option<Vec<u8>>> {
+    let file_id = {
+        let repo_path = RepoPathBuf::from_string(path.clone())?;
+
+        // Work around nested block_on() calls by spawning a new thread.
+        // Once the Manifest is async this can go away.
+        tokio::task::spawn_blocking(move || match manifest.get(&repo_path)? {
+            None => {
+                tracing::warn!(?repo_path, "non-existent sparse profile include");
+                Ok::<_, Error>(None)
+            }
+            Some(fs_node) => match fs_node {
+                FsNodeMetadata::File(FileMetadata { hgid, .. }) => Ok(Some(hgid)),
+                FsNodeMetadata::Directory(_) => {
+                    tracing::warn!(?repo_path, "sparse profile include is a directory");
+                    Ok(None)
+                }
+            },
+        })
+        .await??
+    };
+
+    let file_id = match file_id {
+        Some(id) => id,
+        None => return Ok(None),
+    };
+
+    let repo_path = RepoPathBuf::from_string(path.clone())?;
+    let mut stream = store
+        .read_file_contents(vec![Key::new(repo_path.clone(), file_id.clone())])
+        .await;
+    match stream.next().await {
+        Some(Ok((bytes, _key))) => {
+            let mut bytes = bytes.into_vec();
+            if let Some(extra) = overrides.get(&path) {
+                bytes.append(&mut extra.to_string().into_bytes());
+            }
+            Ok(Some(bytes))
+        }
+        Some(Err(err)) => Err(err),
+        None => Err(anyhow!("no contents for {}", repo_path)),
+    }
+}
+
 pub fn config_overrides(config: impl Config) -> HashMap<String, String> {
     let mut overrides: HashMap<String, String> = HashMap::new();
     for key in config.keys("sparseprofile") {
@@ -415,5 +439,12 @@ exc",
             }))
             .boxed()
         }
+
+        async fn read_rename_metadata(
+            &self,
+            keys: Vec<Key>,
+        ) -> BoxStream<Result<(Option<Key>, Key), Self::Error>> {
+            stream::iter(keys.into_iter().map(|k| Ok((None, k)))).boxed()
+        }
     }
 }
@@ -0,0 +1,276 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+//! Changes to the set of tracked files, used by `add`, `remove`, `forget`
+//! and `addremove`.
+//!
+//! The treestate updates match what the Python dirstate does for the same
+//! operations, so both implementations can be used on the same working copy.
+
+use std::io::Write;
+use std::path::Path;
+
+use anyhow::anyhow;
+use anyhow::Result;
+use treestate::dirstate::Dirstate;
+use treestate::filestate::FileStateV2;
+use treestate::filestate::StateFlags;
+use treestate::serialization::Serializable;
+use treestate::treestate::TreeState;
+use types::RepoPath;
+
+/// Start tracking `path`. Return false if it is already tracked.
+///
+/// Re-adding a removed file restores it to the tracked state of the parent.
+pub fn add(treestate: &mut TreeState, path: &RepoPath) -> Result<bool> {
+    let state = match treestate.get(path)? {
+        Some(state) if state.state.contains(StateFlags::EXIST_NEXT) => return Ok(false),
+        Some(state) => state.state & (StateFlags::EXIST_P1 | StateFlags::EXIST_P2),
+        None => StateFlags::empty(),
+    };
+    treestate.insert(
+        path,
+        &FileStateV2 {
+            mode: 0,
+            size: -1,
+            mtime: -1,
+            state: state | StateFlags::EXIST_NEXT | StateFlags::NEED_CHECK,
+            copied: None,
+        },
+    )?;
+    Ok(true)
+}
+
+/// Mark `path` as removed in the next commit.
+///
+/// The entry is kept, together with its copy information, so `status` can
+/// report it as removed.
+pub fn remove(treestate: &mut TreeState, path: &RepoPath) -> Result<()> {
+    let state = match treestate.get(path)? {
+        Some(state) => {
+            let mut state = state.clone();
+            state.state -= StateFlags::EXIST_NEXT;
+            state.state |= StateFlags::NEED_CHECK;
+            state.mode = 0;
+            state.mtime = -1;
+            if state.size > 0 {
+                state.size = 0;
+            }
+            state
+        }
+        None => FileStateV2 {
+            mode: 0,
+            size: 0,
+            mtime: -1,
+            state: StateFlags::NEED_CHECK,
+            copied: None,
+        },
+    };
+    treestate.insert(path, &state)
+}
+
+/// Stop tracking `path` without recording a removal.
+///
+/// With a file system monitor the entry is kept with `NEED_CHECK` so the
+/// next status looks at the file, even if the monitor does not report it.
+pub fn forget(treestate: &mut TreeState, path: &RepoPath, monitored: bool) -> Result<()> {
+    if !monitored {
+        treestate.remove(path)?;
+        return Ok(());
+    }
+    let state = match treestate.get(path)? {
+        Some(state) => {
+            let mut state = state.clone();
+            state.state -= StateFlags::EXIST_P1
+                | StateFlags::EXIST_P2
+                | StateFlags::EXIST_NEXT
+                | StateFlags::COPIED;
+            state.state |= StateFlags::NEED_CHECK;
+            state.copied = None;
+            state
+        }
+        None => return Ok(()),
+    };
+    treestate.insert(path, &state)
+}
+
+/// Stop tracking `path` in the next commit. Files in a working parent are
+/// marked as removed, added files are forgotten.
+pub fn untrack(treestate: &mut TreeState, path: &RepoPath, monitored: bool) -> Result<()> {
+    let in_parent = match treestate.get(path)? {
+        Some(state) => state
+            .state
+            .intersects(StateFlags::EXIST_P1 | StateFlags::EXIST_P2),
+        None => return Ok(()),
+    };
+    if in_parent {
+        remove(treestate, path)
+    } else {
+        forget(treestate, path, monitored)
+    }
+}
+
+/// Record `dest` as copied from `source`. `dest` must be tracked.
+pub fn copy(treestate: &mut TreeState, source: &RepoPath, dest: &RepoPath) -> Result<()> {
+    let mut state = match treestate.get(dest)? {
+        Some(state) => state.clone(),
+        None => return Err(anyhow!("cannot record copy to untracked file {}", dest)),
+    };
+    state.state |= StateFlags::COPIED;
+    state.copied = Some(source.as_byte_slice().to_vec().into_boxed_slice());
+    treestate.insert(dest, &state)
+}
+
+/// Reopen `treestate` at the root `.hg/dirstate` points to, to pick up
+/// changes written since it was loaded.
+///
+/// Call with the working copy lock held, before reading the treestate to
+/// decide what to change.
+pub fn reload(treestate: &mut TreeState, dot_dir: &Path) -> Result<()> {
+    let dirstate_input = util::file::read(dot_dir.join("dirstate")).map_err(|e| anyhow!(e))?;
+    let dirstate = Dirstate::deserialize(&mut dirstate_input.as_slice())?;
+    let fields = dirstate
+        .tree_state
+        .ok_or_else(|| anyhow!("missing treestate fields on dirstate"))?;
+    *treestate = TreeState::open(
+        dot_dir.join("treestate").join(fields.tree_filename),
+        fields.tree_root_id,
+    )?;
+    Ok(())
+}
+
+/// Write `treestate` and point `.hg/dirstate` at the new root.
+///
+/// The working copy lock must be held since the treestate was reloaded, see
+/// [`reload`].
+pub fn flush(treestate: &mut TreeState, dot_dir: &Path) -> Result<()> {
+    let dirstate_path = dot_dir.join("dirstate");
+
+    let dirstate_input = util::file::read(&dirstate_path).map_err(|e| anyhow!(e))?;
+    let mut dirstate = Dirstate::deserialize(&mut dirstate_input.as_slice())?;
+    let treestate_fields = dirstate.tree_state.as_mut().ok_or_else(|| {
+        anyhow!("Unable to flush treestate because dirstate is missing required treestate fields")
+    })?;
+    treestate_fields.tree_root_id = treestate.flush()?;
+
+    let mut dirstate_output: Vec<u8> = Vec::new();
+    dirstate.serialize(&mut dirstate_output)?;
+    util::file::atomic_write(&dirstate_path, |file| file.write_all(&dirstate_output))
+        .map_err(|e| anyhow!(e))
+        .map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use tempdir::TempDir;
+    use treestate::dirstate::TreeStateFields;
+    use types::HgId;
+    use types::RepoPathBuf;
+
+    use super::*;
+
+    fn path(path: &str) -> RepoPathBuf {
+        RepoPathBuf::from_string(path.to_string()).unwrap()
+    }
+
+    fn flags(treestate: &mut TreeState, path: &RepoPath) -> Option<StateFlags> {
+        treestate.get(path).unwrap().map(|state| state.state)
+    }
+
+    #[test]
+    fn test_add_remove_forget() -> Result<()> {
+        let dir = TempDir::new("track")?;
+        let (mut treestate, _) = TreeState::new(dir.path())?;
+        let clean = FileStateV2 {
+            mode: 0o644,
+            size: 3,
+            mtime: 1,
+            state: StateFlags::EXIST_P1 | StateFlags::EXIST_NEXT,
+            copied: None,
+        };
+        let a = path("a");
+        let b = path("b");
+        treestate.insert(&a, &clean)?;
+
+        assert!(!add(&mut treestate, &a)?);
+        assert!(add(&mut treestate, &b)?);
+        assert_eq!(
+            flags(&mut treestate, &b),
+            Some(StateFlags::EXIST_NEXT | StateFlags::NEED_CHECK)
+        );
+
+        copy(&mut treestate, &a, &b)?;
+        assert_eq!(
+            treestate.get(&b)?.unwrap().copied.as_deref(),
+            Some(&b"a"[..])
+        );
+
+        remove(&mut treestate, &a)?;
+        let state = treestate.get(&a)?.unwrap();
+        assert_eq!(state.state, StateFlags::EXIST_P1 | StateFlags::NEED_CHECK);
+        assert_eq!((state.size, state.mtime), (0, -1));
+
+        // Adding a removed file brings it back.
+        assert!(add(&mut treestate, &a)?);
+        assert!(flags(&mut treestate, &a)
+            .unwrap()
+            .contains(StateFlags::EXIST_P1));
+
+        forget(&mut treestate, &b, false)?;
+        assert_eq!(flags(&mut treestate, &b), None);
+
+        add(&mut treestate, &b)?;
+        untrack(&mut treestate, &b, false)?;
+        assert_eq!(flags(&mut treestate, &b), None);
+        untrack(&mut treestate, &a, false)?;
+        assert_eq!(
+            flags(&mut treestate, &a),
+            Some(StateFlags::EXIST_P1 | StateFlags::NEED_CHECK)
+        );
+
+        add(&mut treestate, &b)?;
+        forget(&mut treestate, &b, true)?;
+        assert_eq!(flags(&mut treestate, &b), Some(StateFlags::NEED_CHECK));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_reload_keeps_concurrent_changes() -> Result<()> {
+        let dir = TempDir::new("track")?;
+        let dot_dir = dir.path();
+        let (mut treestate, root_id) = TreeState::new(&dot_dir.join("treestate"))?;
+        let dirstate = Dirstate {
+            p0: *HgId::null_id(),
+            p1: *HgId::null_id(),
+            tree_state: Some(TreeStateFields {
+                tree_filename: treestate.file_name()?,
+                tree_root_id: root_id,
+                repack_threshold: None,
+            }),
+        };
+        let mut buf = Vec::new();
+        dirstate.serialize(&mut buf)?;
+        std::fs::write(dot_dir.join("dirstate"), buf)?;
+
+        // Another process adds "a" after the treestate was loaded.
+        let (mut other, _) = TreeState::new(&dot_dir.join("other"))?;
+        reload(&mut other, dot_dir)?;
+        add(&mut other, &path("a"))?;
+        flush(&mut other, dot_dir)?;
+
+        reload(&mut treestate, dot_dir)?;
+        add(&mut treestate, &path("b"))?;
+        flush(&mut treestate, dot_dir)?;
+
+        reload(&mut other, dot_dir)?;
+        assert!(flags(&mut other, &path("a")).is_some());
+        assert!(flags(&mut other, &path("b")).is_some());
+
+        Ok(())
+    }
+}
@@ -6,7 +6,7 @@
  */
 
 mod state;
-mod treestate;
+pub(crate) mod treestate;
 mod watchmanfs;
 
 pub use watchmanfs::WatchmanFileSystem;
@@ -38,6 +38,8 @@ use crate::edenfs::EdenFileSystem;
 use crate::filesystem::FileSystemType;
 use crate::filesystem::PendingChangeResult;
 use crate::filesystem::PendingChanges;
+#[cfg(target_os = "linux")]
+use crate::notifyfs::NotifyFileSystem;
 use crate::physicalfs::PhysicalFileSystem;
 use crate::status::compute_status;
 use crate::watchmanfs::WatchmanFileSystem;
@@ -91,6 +93,7 @@ impl WorkingCopy {
             treestate.clone(),
             tree_resolver.clone(),
             filestore,
+            ignore_matcher.clone(),
         )?);
 
         Ok(WorkingCopy {
@@ -105,6 +108,10 @@ impl WorkingCopy {
         self.treestate.clone()
     }
 
+    pub fn file_system_type(&self) -> FileSystemType {
+        self.filesystem.lock().file_system_type
+    }
+
     pub(crate) fn current_manifests(
         treestate: &TreeState,
         tree_resolver: &ArcReadTreeManifest,
@@ -141,6 +148,7 @@ impl WorkingCopy {
         treestate: Arc<Mutex<TreeState>>,
         tree_resolver: ArcReadTreeManifest,
         store: ArcReadFileContents,
+        ignore_matcher: Arc<GitignoreMatcher>,
     ) -> Result<FileSystem> {
         let inner: Box<dyn PendingChanges + Send> = match file_system_type {
             FileSystemType::Normal => Box::new(PhysicalFileSystem::new(
@@ -163,6 +171,21 @@ impl WorkingCopy {
                 #[cfg(feature = "eden")]
                 Box::new(EdenFileSystem::new(root.clone())?)
             }
+            FileSystemType::Notify => {
+                #[cfg(not(target_os = "linux"))]
+                {
+                    let _ = ignore_matcher;
+                    return Err(anyhow!("inotify filesystem monitor requires Linux"));
+                }
+                #[cfg(target_os = "linux")]
+                Box::new(NotifyFileSystem::new(
+                    root.clone(),
+                    treestate.clone(),
+                    tree_resolver,
+                    store.clone(),
+                    ignore_matcher,
+                )?)
+            }
         };
         Ok(FileSystem {
             root,
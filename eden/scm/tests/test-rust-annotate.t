@@ -0,0 +1,54 @@
+#chg-compatible
+
+test rust annotate
+
+  $ configure modern
+  $ setconfig annotate.use-rust=True
+
+  $ newrepo
+  $ echo 1 > a
+  $ hg commit -Aqm A
+  $ echo 2 >> a
+  $ hg commit -m B
+
+Linear history with --no-follow is annotated in Rust:
+
+  $ hg annotate --no-follow --config commands.force-rust=annotate a
+  0: 1
+  1: 2
+
+Renames are followed in Rust unless --no-follow is given:
+
+  $ hg mv a b
+  $ echo 3 >> b
+  $ hg commit -m R
+  $ hg annotate -f --config commands.force-rust=annotate b
+  0 a: 1
+  1 a: 2
+  2 b: 3
+  $ hg annotate -f b
+  0 a: 1
+  1 a: 2
+  2 b: 3
+  $ hg annotate -f --no-follow --config commands.force-rust=annotate b
+  2 b: 1
+  2 b: 2
+  2 b: 3
+
+History with a merge falls back to Python, even with --no-follow:
+
+  $ hg up -q 'desc(A)'
+  $ printf '0\n1\n' > a
+  $ hg commit -m C
+  $ hg merge -q 'desc(B)'
+  $ hg commit -m M
+  $ cat a
+  0
+  1
+  2
+  $ hg annotate --no-follow --config commands.force-rust=annotate a
+  [197]
+  $ hg annotate --no-follow a
+  3: 0
+  0: 1
+  1: 2
@@ -174,3 +174,69 @@ Test various --eden errors:
   $ hg clone -q test:e1 --eden --config clone.use-rust=0
   abort: --eden requires --config clone.use-rust=True
   [255]
+
+Test resuming an interrupted clone
+  $ FAILPOINTS=run::clone=return hg clone -Uq test:e1 $TESTTMP/resume-clone --config clone.resumable=true
+  TRACE hgcommands::commands::clone: performing rust clone
+   INFO clone_metadata{repo="test-repo"}: hgcommands::commands::clone: enter
+  TRACE clone_metadata{repo="test-repo"}: hgcommands::commands::clone: fetching lazy commit data and bookmarks
+  ERROR clone_metadata{repo="test-repo"}: hgcommands::commands::clone: error=Injected clone failure
+   INFO clone_metadata{repo="test-repo"}: hgcommands::commands::clone: exit
+  Clone interrupted. Resume with 'hg clone --resume test:e1 $TESTTMP/resume-clone'
+  abort: Injected clone failure
+  [255]
+  $ cat $TESTTMP/resume-clone/.hg/clonestate
+  phase commits
+  bookmark 9bc730a19041f9ec7cb33c626e811aa233efb18c master
+  noupdate
+  $ hg clone -q --resume test:e1 $TESTTMP/resume-clone
+  TRACE hgcommands::commands::clone: performing rust clone
+   INFO get_update_target: hgcommands::commands::clone: enter
+   INFO get_update_target: hgcommands::commands::clone: return=None
+   INFO get_update_target: hgcommands::commands::clone: exit
+  $ [ -f $TESTTMP/resume-clone/.hg/clonestate ]
+  [1]
+  $ hg -R $TESTTMP/resume-clone log -r tip -T "{desc}\n"
+  E
+  $ ls $TESTTMP/resume-clone
+  $ hg clone -Uq --resume test:e1 $TESTTMP/resume-clone
+  TRACE hgcommands::commands::clone: performing rust clone
+  abort: no interrupted clone at $TESTTMP/resume-clone
+  [255]
+
+Test resuming a clone interrupted before checkout keeps the sparse profiles
+  $ unset LOG
+  $ newrepo e2
+  $ drawdag << 'EOS'
+  > B  # bookmark master = B
+  > |  # B/profile = [include]\nA\nprofile\n
+  > A
+  > EOS
+  $ cd $TESTTMP
+  $ FAILPOINTS=run::clone=return hg clone -q test:e2 $TESTTMP/resume-sparse --enable-profile profile --config clone.resumable=true
+  Clone interrupted. Resume with 'hg clone --resume test:e2 $TESTTMP/resume-sparse'
+  abort: Injected clone failure
+  [255]
+  $ cat $TESTTMP/resume-sparse/.hg/clonestate
+  phase commits
+  bookmark * master (glob)
+  profile profile
+  $ hg clone -q --resume test:e2 $TESTTMP/resume-sparse
+  $ [ -f $TESTTMP/resume-sparse/.hg/clonestate ]
+  [1]
+  $ cat $TESTTMP/resume-sparse/.hg/sparse
+  %include profile
+  $ ls $TESTTMP/resume-sparse
+  A
+  profile
+
+Test resuming a clone keeps the commit given to -u
+  $ FAILPOINTS=run::clone=return hg clone -q -u $A test:e2 $TESTTMP/resume-updaterev --config clone.resumable=true
+  Clone interrupted. Resume with 'hg clone --resume test:e2 $TESTTMP/resume-updaterev'
+  abort: Injected clone failure
+  [255]
+  $ grep updaterev $TESTTMP/resume-updaterev/.hg/clonestate | sed "s/$A/A/"
+  updaterev A
+  $ hg clone -q --resume test:e2 $TESTTMP/resume-updaterev
+  $ hg -R $TESTTMP/resume-updaterev log -r . -T "{desc}\n"
+  A